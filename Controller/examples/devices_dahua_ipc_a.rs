@@ -96,7 +96,6 @@ async fn main() -> Result<(), Error> {
                     Configuration {
                         device_id: command_configure.device_id,
                         device_name: command_configure.device_name,
-                        shared_user_password: command_configure.shared_user_password,
                         video_upside_down: command_configure.video_upside_down,
                         channel_title: Some(command_configure.channel_title),
                         privacy_mask: None,
@@ -114,6 +113,7 @@ async fn main() -> Result<(), Error> {
                             sensitivity: Percentage::new(50).unwrap(),
                         }),
                     },
+                    command_configure.shared_user_password,
                 )
                 .await
                 .context("configure")?;
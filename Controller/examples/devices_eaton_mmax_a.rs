@@ -88,6 +88,7 @@ async fn main() -> Result<(), Error> {
         ftdi_descriptor.clone(),
         arguments.baud_rate,
         arguments.parity.0,
+        None,
     );
 
     let device = mmax_a::hardware::Device::new(&modbus_bus, arguments.device_address);
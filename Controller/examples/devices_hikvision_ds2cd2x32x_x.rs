@@ -89,27 +89,29 @@ async fn main() -> Result<(), Error> {
             log::info!("capabilities: {:?}", configurator.capabilities());
             log::info!("starting configuration");
             configurator
-                .configure(Configuration {
-                    device_name: command_configure.device_name,
-                    device_id: command_configure.device_id,
-                    shared_user_password: command_configure.shared_user_password,
-                    video_upside_down: command_configure.video_upside_down,
-                    overlay_text: command_configure.overlay_text,
-                    privacy_mask: None,
-                    motion_detection: Some(
-                        MotionDetection::new(
-                            vec![MotionDetectionRegion {
-                                region: RegionSquare::full(),
-                                sensitivity: Percentage::new(50).unwrap(),
-                                object_size: Percentage::new(0).unwrap(),
-                            }]
-                            .into_boxed_slice(),
-                        )
-                        .unwrap(),
-                    ),
-                    field_detection: None,
-                    line_detection: None,
-                })
+                .configure(
+                    Configuration {
+                        device_name: command_configure.device_name,
+                        device_id: command_configure.device_id,
+                        video_upside_down: command_configure.video_upside_down,
+                        overlay_text: command_configure.overlay_text,
+                        privacy_mask: None,
+                        motion_detection: Some(
+                            MotionDetection::new(
+                                vec![MotionDetectionRegion {
+                                    region: RegionSquare::full(),
+                                    sensitivity: Percentage::new(50).unwrap(),
+                                    object_size: Percentage::new(0).unwrap(),
+                                }]
+                                .into_boxed_slice(),
+                            )
+                            .unwrap(),
+                        ),
+                        field_detection: None,
+                        line_detection: None,
+                    },
+                    command_configure.shared_user_password,
+                )
                 .await
                 .context("configure")?;
             log::info!("configuration completed");
@@ -32,7 +32,7 @@ async fn run_inner<S: Specification>(
     master: &Master,
     address_serial: AddressSerial,
 ) {
-    let device = Device::<S>::new();
+    let device = Device::<S>::new(None);
     let runner = Runner::new(master, address_serial, device);
 
     let PropertiesRemote {
@@ -42,6 +42,7 @@ async fn run_inner(
         leds,
         buzzer,
         ds18x20,
+        reset_cause,
     } = runner.device().properties_remote();
 
     let exit_flag_sender = Sender::new();
@@ -107,12 +108,19 @@ async fn run_inner(
         }
     };
 
+    let reset_cause_changed = || {
+        if let Some(Some(reset_cause)) = reset_cause.take_pending() {
+            log::info!("reset_cause: {:?}", reset_cause);
+        }
+    };
+
     let ins_changed_waker_remote_runner = async {
         futures::stream::once(async {})
             .chain(ins_changed_waker_remote.stream())
             .for_each(async |()| {
                 keys_changed();
                 ds18x20_changed();
+                reset_cause_changed();
             })
             .await;
     };
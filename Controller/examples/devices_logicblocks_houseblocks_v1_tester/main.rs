@@ -132,7 +132,7 @@ fn submenu_select_master(global: &mut Global) -> Result<Option<Master>, Error> {
     };
 
     let descriptor = &descriptors[index];
-    let master = Master::new(descriptor.clone());
+    let master = Master::new(descriptor.clone(), None);
 
     Ok(Some(master))
 }
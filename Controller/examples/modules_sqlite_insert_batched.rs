@@ -0,0 +1,130 @@
+// Compares row-by-row and batched inserts, meant to be run on target hardware
+// usage: modules_sqlite_insert_batched db.sqlite 100000
+
+use anyhow::{ensure, Context, Error};
+use clap::Parser;
+use logicblocks_controller::{
+    modules::sqlite::{insert_batched, InsertBatchLimits},
+    util::logging,
+};
+use rusqlite::{types::Value, Connection};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Parser)]
+#[clap(name = "modules.sqlite.insert_batched")]
+struct Arguments {
+    database_path: PathBuf,
+    rows: usize,
+    #[clap(long, default_value_t = InsertBatchLimits::default().rows)]
+    batch_rows: usize,
+    #[clap(long, default_value_t = InsertBatchLimits::default().bytes)]
+    batch_bytes: usize,
+}
+
+fn connection_open(database_path: &PathBuf) -> Result<Connection, Error> {
+    let connection = Connection::open(database_path).context("open")?;
+    connection
+        .pragma_update(None, "journal_mode", "WAL")
+        .context("journal_mode")?;
+    connection
+        .pragma_update(None, "synchronous", "NORMAL")
+        .context("synchronous")?;
+    connection
+        .execute_batch(
+            "
+            DROP TABLE IF EXISTS `buffer_real`;
+            CREATE TABLE `buffer_real` (`sink_id` INTEGER, `timestamp` INTEGER, `value` REAL);
+            ",
+        )
+        .context("execute_batch")?;
+
+    Ok(connection)
+}
+
+fn rows(count: usize) -> impl Iterator<Item = [Value; 3]> {
+    (0..count).map(|index| {
+        [
+            Value::Integer((index % 64) as i64),
+            Value::Integer(1_600_000_000 + index as i64),
+            Value::Real(index as f64 / 10.0),
+        ]
+    })
+}
+
+fn run_single(
+    connection: &mut Connection,
+    count: usize,
+) -> Result<Duration, Error> {
+    let start = Instant::now();
+
+    let transaction = connection.transaction().context("transaction")?;
+    {
+        let mut statement = transaction
+            .prepare_cached(
+                "INSERT INTO `buffer_real` (`sink_id`, `timestamp`, `value`) VALUES (?, ?, ?)",
+            )
+            .context("prepare_cached")?;
+        for row in rows(count) {
+            statement
+                .execute(rusqlite::params_from_iter(row.iter()))
+                .context("execute")?;
+        }
+    }
+    transaction.commit().context("commit")?;
+
+    Ok(start.elapsed())
+}
+
+fn run_batched(
+    connection: &mut Connection,
+    count: usize,
+    limits: InsertBatchLimits,
+) -> Result<Duration, Error> {
+    let start = Instant::now();
+
+    let transaction = connection.transaction().context("transaction")?;
+    let inserted = insert_batched(
+        &transaction,
+        "INSERT INTO `buffer_real` (`sink_id`, `timestamp`, `value`)",
+        rows(count),
+        limits,
+    )
+    .context("insert_batched")?;
+    ensure!(inserted == count, "inserted {} of {} rows", inserted, count);
+    transaction.commit().context("commit")?;
+
+    Ok(start.elapsed())
+}
+
+fn main() -> Result<(), Error> {
+    logging::configure(module_path!(), true);
+
+    let arguments = Arguments::parse();
+    let limits = InsertBatchLimits {
+        rows: arguments.batch_rows,
+        bytes: arguments.batch_bytes,
+    };
+
+    let mut connection = connection_open(&arguments.database_path).context("connection_open")?;
+    let single = run_single(&mut connection, arguments.rows).context("run_single")?;
+    log::info!(
+        "single: {:?}, {:.0} rows/s",
+        single,
+        arguments.rows as f64 / single.as_secs_f64()
+    );
+    drop(connection);
+
+    let mut connection = connection_open(&arguments.database_path).context("connection_open")?;
+    let batched = run_batched(&mut connection, arguments.rows, limits).context("run_batched")?;
+    log::info!(
+        "batched ({:?}): {:?}, {:.0} rows/s",
+        limits,
+        batched,
+        arguments.rows as f64 / batched.as_secs_f64()
+    );
+
+    Ok(())
+}
@@ -1,58 +1,311 @@
 use super::{
     devices::{
+        configuration_history::{self, ConfigurationHistory},
+        definition::Definition,
         helpers::{Devices, Signals},
+        maintenance::Maintenance,
         runner::Runner,
+        scenes::Scenes,
     },
     web::{
+        mdns,
         root_service::RootService,
         server,
         uri_cursor::{map_router::MapRouter, Handler},
     },
 };
-use crate::gui::dashboards;
+#[cfg(unix)]
+use crate::util::systemd;
+use crate::{
+    gui::dashboards,
+    modules::{
+        api_tokens::ApiTokens,
+        emergency_stop, events, i18n, metrics,
+        module_path::ModulePath,
+        restart::{self, Restart},
+        secrets::Secrets,
+    },
+    util::{
+        async_flag, logging,
+        runnable::{Runnable, Supervisor, SupervisorConfiguration},
+        runtime::{Runtime, RuntimeScopeRunnable},
+    },
+};
 use anyhow::{Context, Error};
+use futures::{future::FutureExt, join, select};
 use maplit::hashmap;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use once_cell::sync::Lazy;
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    thread::available_parallelism,
+};
 use tokio::signal::ctrl_c;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+
+const PORT_DEFAULT: u16 = 8080;
+
+#[derive(Default, Debug)]
+pub struct Configuration {
+    // listen addresses, defaults to 0.0.0.0:8080
+    pub binds: Option<Box<[server::Bind]>>,
+    pub server_limits: server::Limits,
+    // advertises api over mDNS as _logicblocks._tcp with given instance name
+    pub mdns_instance: Option<String>,
+    // worker threads of runtime executing devices, defaults to number of cpus
+    pub devices_worker_threads: Option<usize>,
+}
 
-pub async fn run(
-    devices: Devices<'_>,
+// runs the application on its own multi threaded runtime
+#[allow(clippy::too_many_arguments)]
+pub fn run_blocking<'d>(
+    devices: Devices<'d>,
     signals: Signals,
+    scenes: Option<&'d Scenes<'d>>,
+    maintenance: Option<&'d Maintenance>,
+    secrets: Option<&'d Secrets<'d>>,
+    api_tokens: &'d ApiTokens<'d>,
+    configuration_history: Option<&'d ConfigurationHistory<'d>>,
     dashboards: dashboards::Dashboard,
-    bind_custom: Option<SocketAddrV4>,
+    modules_runnables: Box<[(String, &'d dyn Runnable)]>,
+    configuration: Configuration,
 ) -> Result<(), Error> {
+    // app itself only waits for signals, heavy work is done in dedicated runtimes /
+    // threads
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("app")
+        .worker_threads(1)
+        .build()
+        .context("build")?;
+
+    runtime.block_on(run(
+        devices,
+        signals,
+        scenes,
+        maintenance,
+        secrets,
+        api_tokens,
+        configuration_history,
+        dashboards,
+        modules_runnables,
+        configuration,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run<'d>(
+    devices: Devices<'d>,
+    signals: Signals,
+    scenes: Option<&'d Scenes<'d>>,
+    maintenance: Option<&'d Maintenance>,
+    secrets: Option<&'d Secrets<'d>>,
+    api_tokens: &'d ApiTokens<'d>,
+    configuration_history: Option<&'d ConfigurationHistory<'d>>,
+    dashboards: dashboards::Dashboard,
+    modules_runnables: Box<[(String, &'d dyn Runnable)]>,
+    configuration: Configuration,
+) -> Result<(), Error> {
+    api_tokens.load().await.context("load")?;
+
+    let restart = Restart::new();
+
     let device_wrappers_by_id = devices.into_device_wrappers_by_id();
-    let connections_requested = signals.into_connections_requested();
+    let mut connections_requested = signals.into_connections_requested();
+
+    let mut snapshot =
+        configuration_history::Snapshot::new(&device_wrappers_by_id, &connections_requested);
+    let mut rolled_back = false;
+    if let Some(configuration_history) = configuration_history {
+        match configuration_history
+            .startup(&snapshot, &device_wrappers_by_id)
+            .await
+        {
+            Ok(Some(connections_requested_rollback)) => {
+                connections_requested = connections_requested_rollback;
+                rolled_back = true;
+                snapshot = configuration_history::Snapshot::new(
+                    &device_wrappers_by_id,
+                    &connections_requested,
+                );
+            }
+            Ok(None) => {}
+            Err(error) => log::error!("configuration_history: startup: {error:?}"),
+        }
+    }
+
+    let definition = Definition::new(
+        snapshot,
+        &device_wrappers_by_id,
+        &dashboards,
+        configuration_history,
+    );
 
     // devices runner
-    let device_runner =
-        Runner::new(device_wrappers_by_id, &connections_requested).context("new")?;
+    let devices_worker_threads = configuration.devices_worker_threads.unwrap_or_else(|| {
+        available_parallelism()
+            .map(|available_parallelism| available_parallelism.get())
+            .unwrap_or(4)
+    });
+    let device_runner = match Runner::new(
+        device_wrappers_by_id,
+        &connections_requested,
+        scenes,
+        maintenance,
+        devices_worker_threads,
+    ) {
+        Ok(device_runner) => device_runner,
+        Err(error) => {
+            // rolled back connections that cannot be made would fail every
+            // start, so next one goes with connections from code
+            if rolled_back {
+                if let Some(configuration_history) = configuration_history {
+                    log::error!("configuration rollback failed, cancelling");
+                    if let Err(error) = configuration_history.rollback_cancel().await {
+                        log::error!("configuration_history: rollback_cancel: {error:?}");
+                    }
+                }
+            }
+            return Err(error.context("new"));
+        }
+    };
+
+    // module background tasks (eg. backup, storage monitor), restarted on
+    // failure independently from each other
+    static MODULES_MODULE_PATH: Lazy<ModulePath> = Lazy::new(|| ModulePath::new(&["modules"]));
+    let modules_runtime = Runtime::new(&*MODULES_MODULE_PATH, 1, 1);
+    let modules_supervisor = Supervisor::new(SupervisorConfiguration::default(), modules_runnables);
+    let modules_supervisor_runtime_scope_runnable =
+        RuntimeScopeRunnable::new(&modules_runtime, &modules_supervisor);
 
     // web service
     let gui_router = MapRouter::new(hashmap! {
         "dashboards".to_owned() => &dashboards as &(dyn Handler + Sync),
     });
-    let root_router = MapRouter::new(hashmap! {
+    let mut root_routes = hashmap! {
+        ApiTokens::ROUTE.to_owned() => api_tokens as &(dyn Handler + Sync),
+        Definition::ROUTE.to_owned() => &definition as &(dyn Handler + Sync),
         "devices-runner".to_owned() => &device_runner as &(dyn Handler + Sync),
+        "emergency-stop".to_owned() => emergency_stop::emergency_stop() as &(dyn Handler + Sync),
+        "events".to_owned() => events::reporter() as &(dyn Handler + Sync),
         "gui".to_owned() => &gui_router as &(dyn Handler + Sync),
+        "i18n".to_owned() => i18n::catalog() as &(dyn Handler + Sync),
+        "logging".to_owned() => &logging::Handler as &(dyn Handler + Sync),
+        "metrics".to_owned() => metrics::registry() as &(dyn Handler + Sync),
+        "modules-supervisor".to_owned() => &modules_supervisor as &(dyn Handler + Sync),
+        Restart::ROUTE.to_owned() => &restart as &(dyn Handler + Sync),
+    };
+    if let Some(secrets) = secrets {
+        root_routes.insert("secrets".to_owned(), secrets as &(dyn Handler + Sync));
+    }
+    if let Some(configuration_history) = configuration_history {
+        root_routes.insert(
+            ConfigurationHistory::ROUTE.to_owned(),
+            configuration_history as &(dyn Handler + Sync),
+        );
+    }
+    let root_router = MapRouter::new(root_routes);
+    // routes managing credentials or whole system are never served without
+    // admin token
+    let root_service = RootService::new(&root_router, api_tokens);
+    let binds = configuration.binds.unwrap_or_else(|| {
+        Box::new([server::Bind {
+            address: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT_DEFAULT)),
+            interface: None,
+        }])
     });
-    let root_service = RootService::new(&root_router);
+    let advertisement = configuration
+        .mdns_instance
+        .map(|mdns_instance| mdns::Advertisement {
+            instance: mdns_instance,
+            port: binds
+                .first()
+                .map_or(PORT_DEFAULT, |bind| bind.address.port()),
+            binds: binds.clone(),
+        });
     let server_runner = server::RunnerOwned::new(
-        SocketAddr::V4(
-            bind_custom.unwrap_or_else(|| SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 8080)),
-        ),
+        server::Configuration {
+            binds,
+            limits: configuration.server_limits,
+            advertisement,
+        },
         &root_service,
     );
 
     // wait for exit signal
     log::info!("application started, awaiting exit signal");
-    ctrl_c().await.context("ctrlc")?;
-    log::info!("received exit signal, closing application");
 
-    // teardown
+    let (watchdog_exit_flag_sender, watchdog_exit_flag_receiver) = async_flag::pair();
+    let mut ready_exit_flag_receiver = watchdog_exit_flag_sender.receiver();
+    // service is ready once devices loaded their state and connected to buses
+    let ready_runner = async {
+        select! {
+            () = device_runner.initialized().fuse() => {},
+            () = ready_exit_flag_receiver => return,
+        }
+        log::info!("devices initialized");
+        #[cfg(unix)]
+        systemd::ready();
+    };
+    let watchdog_runner = async {
+        #[cfg(unix)]
+        systemd::watchdog_run(
+            |timeout| device_runner.health_check(timeout),
+            watchdog_exit_flag_receiver,
+        )
+        .await;
+        #[cfg(not(unix))]
+        watchdog_exit_flag_receiver.await;
+    };
+    let exit_signal_runner = async {
+        let result = select! {
+            result = exit_signal().fuse() => result,
+            () = restart.receiver() => Ok(()),
+        };
+        watchdog_exit_flag_sender.signal();
+        result
+    };
+    let ((), (), result) = join!(ready_runner, watchdog_runner, exit_signal_runner);
+    result.context("exit_signal")?;
+
+    let restart_requested = restart.requested();
+    if restart_requested {
+        log::info!("received restart request, restarting application");
+        #[cfg(unix)]
+        systemd::reloading();
+    } else {
+        log::info!("received exit signal, closing application");
+        #[cfg(unix)]
+        systemd::stopping();
+    }
+
+    // teardown, devices persist their state here
     server_runner.finalize().await;
     device_runner.finalize().await;
+    // devices may use modules, so these are stopped last
+    modules_supervisor_runtime_scope_runnable.finalize().await;
+
+    if restart_requested {
+        // returns only on failure
+        return Err(restart::exec()).context("exec");
+    }
 
     // bye bye
     Ok(())
 }
+
+// ctrl-c, or SIGTERM sent by service manager
+async fn exit_signal() -> Result<(), Error> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal(SignalKind::terminate()).context("signal")?;
+        select! {
+            result = ctrl_c().fuse() => result.context("ctrl_c")?,
+            _ = terminate.recv().fuse() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    ctrl_c().await.context("ctrl_c")?;
+
+    Ok(())
+}
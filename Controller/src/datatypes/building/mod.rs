@@ -1 +1,2 @@
+pub mod house_mode;
 pub mod window;
@@ -0,0 +1,2 @@
+pub mod spot_prices;
+pub mod tariff;
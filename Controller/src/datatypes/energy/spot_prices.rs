@@ -0,0 +1,102 @@
+use crate::datatypes::real::Real;
+use anyhow::{ensure, Context, Error};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// energy prices for consecutive, equal length slots (ex. hourly day-ahead
+// market prices), starting at given instant
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "SpotPricesSerde")]
+#[serde(into = "SpotPricesSerde")]
+pub struct SpotPrices {
+    start: DateTime<Utc>,
+    slot: Duration,
+    prices: Box<[Real]>,
+}
+impl SpotPrices {
+    pub fn new(
+        start: DateTime<Utc>,
+        slot: Duration,
+        prices: Box<[Real]>,
+    ) -> Result<Self, Error> {
+        ensure!(slot > Duration::ZERO, "slot must be positive");
+        chrono::Duration::from_std(slot * prices.len() as u32).context("from_std")?;
+        Ok(Self {
+            start,
+            slot,
+            prices,
+        })
+    }
+
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+    pub fn end(&self) -> DateTime<Utc> {
+        self.slot_start(self.prices.len())
+    }
+    pub fn slot(&self) -> Duration {
+        self.slot
+    }
+    pub fn prices(&self) -> &[Real] {
+        &self.prices
+    }
+
+    pub fn slot_start(
+        &self,
+        index: usize,
+    ) -> DateTime<Utc> {
+        self.start + chrono::Duration::from_std(self.slot * index as u32).unwrap()
+    }
+
+    // price integrated over given period, in price * hours
+    // None if period is not fully covered
+    pub fn cost(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Option<f64> {
+        if from < self.start() || to > self.end() {
+            return None;
+        }
+
+        let cost = self
+            .prices
+            .iter()
+            .enumerate()
+            .map(|(index, price)| {
+                let slot_from = self.slot_start(index).max(from);
+                let slot_to = self.slot_start(index + 1).min(to);
+                if slot_from >= slot_to {
+                    return 0.0;
+                }
+                let hours = (slot_to - slot_from).to_std().unwrap().as_secs_f64() / 3600.0;
+                price.to_f64() * hours
+            })
+            .sum();
+
+        Some(cost)
+    }
+}
+impl TryFrom<SpotPricesSerde> for SpotPrices {
+    type Error = Error;
+
+    fn try_from(value: SpotPricesSerde) -> Result<Self, Self::Error> {
+        Self::new(value.start, value.slot, value.prices)
+    }
+}
+impl Into<SpotPricesSerde> for SpotPrices {
+    fn into(self) -> SpotPricesSerde {
+        SpotPricesSerde {
+            start: self.start,
+            slot: self.slot,
+            prices: self.prices,
+        }
+    }
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct SpotPricesSerde {
+    start: DateTime<Utc>,
+    slot: Duration,
+    prices: Box<[Real]>,
+}
@@ -0,0 +1,128 @@
+use crate::datatypes::real::Real;
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+// part of the week with its own price
+// time range is in local time and wraps over midnight when time_to is
+// before time_from (eg. 22:00 - 06:00 night zone), equal bounds mean whole day
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TariffZone {
+    // empty means every day, for wrapping zones this is the day zone starts
+    pub weekdays: Box<[Weekday]>,
+    pub time_from: NaiveTime,
+    pub time_to: NaiveTime,
+    pub price: Real,
+}
+impl TariffZone {
+    fn matches(
+        &self,
+        local: NaiveDateTime,
+    ) -> bool {
+        let weekday_matches = |weekday: Weekday| -> bool {
+            self.weekdays.is_empty() || self.weekdays.contains(&weekday)
+        };
+
+        let time = local.time();
+        if self.time_from == self.time_to {
+            weekday_matches(local.weekday())
+        } else if self.time_from < self.time_to {
+            weekday_matches(local.weekday()) && time >= self.time_from && time < self.time_to
+        } else {
+            (weekday_matches(local.weekday()) && time >= self.time_from)
+                || (weekday_matches(local.weekday().pred()) && time < self.time_to)
+        }
+    }
+}
+
+// time of use tariff, price per unit of consumption (kWh, m3)
+// first matching zone wins, price_default is used outside of all zones
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Tariff {
+    zones: Box<[TariffZone]>,
+    price_default: Real,
+}
+impl Tariff {
+    pub fn new(
+        zones: Box<[TariffZone]>,
+        price_default: Real,
+    ) -> Self {
+        Self {
+            zones,
+            price_default,
+        }
+    }
+    pub fn flat(price: Real) -> Self {
+        Self {
+            zones: Box::new([]),
+            price_default: price,
+        }
+    }
+
+    pub fn zones(&self) -> &[TariffZone] {
+        &self.zones
+    }
+    pub fn price_default(&self) -> Real {
+        self.price_default
+    }
+
+    pub fn price(
+        &self,
+        local: NaiveDateTime,
+    ) -> Real {
+        self.zones
+            .iter()
+            .find(|zone| zone.matches(local))
+            .map(|zone| zone.price)
+            .unwrap_or(self.price_default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tariff, TariffZone};
+    use crate::datatypes::real::Real;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+
+    #[test]
+    fn price() {
+        let tariff = Tariff::new(
+            Box::new([
+                TariffZone {
+                    weekdays: Box::new([Weekday::Sat, Weekday::Sun]),
+                    time_from: NaiveTime::MIN,
+                    time_to: NaiveTime::MIN,
+                    price: Real::from_f64(0.5).unwrap(),
+                },
+                TariffZone {
+                    weekdays: Box::new([Weekday::Fri]),
+                    time_from: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                    time_to: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+                    price: Real::from_f64(0.6).unwrap(),
+                },
+            ]),
+            Real::from_f64(1.0).unwrap(),
+        );
+
+        // 2024-01-05 is friday
+        let at = |day: u32, hour: u32| {
+            NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+        let price = |day: u32, hour: u32| tariff.price(at(day, hour)).to_f64();
+        assert_eq!(price(5, 12), 1.0);
+        assert_eq!(price(5, 23), 0.6);
+        // weekend zone is listed first, so it wins over friday night zone
+        assert_eq!(price(6, 3), 0.5);
+        assert_eq!(price(6, 12), 0.5);
+        assert_eq!(price(4, 3), 1.0);
+
+        assert_eq!(
+            Tariff::flat(Real::from_f64(2.0).unwrap())
+                .price(at(5, 0))
+                .to_f64(),
+            2.0
+        );
+    }
+}
@@ -1,13 +1,17 @@
 pub mod angle;
 pub mod building;
 pub mod color_rgb_boolean;
+pub mod energy;
 pub mod geography;
 pub mod ipc_rtsp_url;
+pub mod mode;
 pub mod multiplier;
 pub mod pressure;
+pub mod quality;
 pub mod range;
 pub mod ratio;
 pub mod real;
 pub mod resistance;
 pub mod temperature;
+pub mod timestamped;
 pub mod voltage;
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// one of named states, eg. "comfort", "eco", "off"
+// used instead of several boolean signals for multi-state modes
+// allowed values are declared by devices in signal metadata
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Mode(String);
+impl Mode {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for Mode {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{ensure, Error};
 use derive_more::{Add, AddAssign, Sub, SubAssign, Sum};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -60,6 +61,15 @@ impl Into<MultiplierSerde> for Multiplier {
         MultiplierSerde(self.to_f64())
     }
 }
+// serialized as plain number, see MultiplierSerde
+impl JsonSchema for Multiplier {
+    fn schema_name() -> String {
+        "Multiplier".to_owned()
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        f64::json_schema(generator)
+    }
+}
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(transparent)]
 struct MultiplierSerde(f64);
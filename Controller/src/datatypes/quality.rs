@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+// trust level of value read from hardware, eg. based on bus communication
+// status, so consumers and analysis can exclude unreliable data
+// ordered from worst to best, so worst of several can be taken with min()
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Quality {
+    // communication failed, values are not available
+    Bad,
+    // communication is being established, values may be missing or outdated
+    Uncertain,
+    // values were read successfully
+    Good,
+}
+impl Quality {
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Quality::Bad => 0.0,
+            Quality::Uncertain => 0.5,
+            Quality::Good => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quality;
+
+    #[test]
+    fn worst() {
+        assert_eq!(
+            [Quality::Good, Quality::Uncertain, Quality::Good]
+                .into_iter()
+                .min(),
+            Some(Quality::Uncertain)
+        );
+        assert_eq!(Quality::Bad.min(Quality::Good), Quality::Bad);
+    }
+}
@@ -3,6 +3,7 @@ use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -55,6 +56,15 @@ impl Into<RatioSerde> for Ratio {
         RatioSerde(self.to_f64())
     }
 }
+// serialized as plain number, see RatioSerde
+impl JsonSchema for Ratio {
+    fn schema_name() -> String {
+        "Ratio".to_owned()
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        f64::json_schema(generator)
+    }
+}
 impl Distribution<Ratio> for Standard {
     fn sample<R: Rng + ?Sized>(
         &self,
@@ -1,4 +1,5 @@
 use anyhow::{ensure, Error};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, fmt};
 
@@ -59,6 +60,15 @@ impl Into<TemperatureSerde> for Temperature {
         TemperatureSerde(self.to_kelvins())
     }
 }
+// serialized as plain number of kelvins, see TemperatureSerde
+impl JsonSchema for Temperature {
+    fn schema_name() -> String {
+        "Temperature".to_owned()
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        f64::json_schema(generator)
+    }
+}
 impl Eq for Temperature {}
 #[allow(clippy::derive_ord_xor_partial_ord)]
 impl Ord for Temperature {
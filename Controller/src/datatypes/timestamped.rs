@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// value together with time it was read at the source
+// lets consumers tell fresh value from the last known one
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Timestamped<T> {
+    pub value: T,
+    pub timestamp: DateTime<Utc>,
+}
+impl<T> Timestamped<T> {
+    pub fn new(
+        value: T,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self { value, timestamp }
+    }
+
+    // timestamps from the future are considered fresh
+    pub fn age(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Duration {
+        (now - self.timestamp).to_std().unwrap_or_default()
+    }
+    pub fn stale(
+        &self,
+        now: DateTime<Utc>,
+        age_max: Duration,
+    ) -> bool {
+        self.age(now) > age_max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamped;
+    use chrono::{TimeDelta, TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn age_and_stale() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let value = Timestamped::new(0.0, timestamp);
+        let age_max = Duration::from_secs(60);
+
+        let now = timestamp + TimeDelta::seconds(60);
+        assert_eq!(value.age(now), age_max);
+        assert!(!value.stale(now, age_max));
+
+        let now = timestamp + TimeDelta::hours(2);
+        assert!(value.stale(now, age_max));
+
+        // clock went back
+        let now = timestamp - TimeDelta::seconds(1);
+        assert_eq!(value.age(now), Duration::ZERO);
+    }
+}
@@ -0,0 +1,18 @@
+pub mod motion_light_a;
+
+use super::helpers::{Devices, Signals};
+
+// parameterized set of soft devices and connections for a common automation
+// pattern, expanded while building the device tree, so it doesn't have to be
+// wired by hand each time
+// handles to created devices are returned, so they can be further connected,
+// put in shadow mode or shown on dashboards
+pub trait Blueprint<'d> {
+    type Instance;
+
+    fn instantiate(
+        self,
+        devices: &mut Devices<'d>,
+        signals: &mut Signals,
+    ) -> Self::Instance;
+}
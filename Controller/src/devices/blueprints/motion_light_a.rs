@@ -0,0 +1,226 @@
+use super::Blueprint as BlueprintTrait;
+use crate::{
+    devices::{
+        helpers::{DeviceHandle, DeviceSignalHandleErased, Devices, Signals},
+        soft::{
+            logic::{
+                boolean::gate::and_a,
+                compare::binary_ord_a::{self, Operation},
+            },
+            time::boolean_change_delay_a,
+            value::constant_a,
+        },
+    },
+    signals::types::state::Value,
+};
+use std::time::Duration;
+
+// light is turned on when motion is detected and turned off after timeout
+// since the last motion
+// if light level is given, light is turned on only when it's below the
+// threshold, the sensor should not be lit by the controlled light
+
+#[derive(Debug)]
+pub struct Configuration<V>
+where
+    V: Value + PartialOrd + Clone,
+{
+    pub timeout: Duration,
+    pub light_level_threshold: V,
+}
+
+#[derive(Debug)]
+pub struct Inputs<'d> {
+    pub motion: DeviceSignalHandleErased<'d>, // state source, bool
+    pub light_level: Option<DeviceSignalHandleErased<'d>>, // state source, V
+}
+
+#[derive(Debug)]
+pub struct Outputs<'d> {
+    pub light: DeviceSignalHandleErased<'d>, // state target, bool
+}
+
+#[derive(Debug)]
+pub struct Blueprint<'d, V>
+where
+    V: Value + PartialOrd + Clone,
+{
+    pub name: String,
+    pub configuration: Configuration<V>,
+    pub inputs: Inputs<'d>,
+    pub outputs: Outputs<'d>,
+}
+
+#[derive(Debug)]
+pub struct InstanceLightLevel<'d, V>
+where
+    V: Value + PartialOrd + Clone,
+{
+    pub threshold: DeviceHandle<'d, constant_a::Device<V>>,
+    pub compare: DeviceHandle<'d, binary_ord_a::Device<V>>,
+    pub and: DeviceHandle<'d, and_a::Device>,
+}
+
+#[derive(Debug)]
+pub struct Instance<'d, V>
+where
+    V: Value + PartialOrd + Clone,
+{
+    pub timeout: DeviceHandle<'d, boolean_change_delay_a::Device>,
+    pub light_level: Option<InstanceLightLevel<'d, V>>,
+}
+
+impl<'d, V> BlueprintTrait<'d> for Blueprint<'d, V>
+where
+    V: Value + PartialOrd + Clone,
+{
+    type Instance = Instance<'d, V>;
+
+    fn instantiate(
+        self,
+        devices: &mut Devices<'d>,
+        signals: &mut Signals,
+    ) -> Self::Instance {
+        let timeout = devices.add(
+            format!("{} - timeout", self.name),
+            boolean_change_delay_a::Device::new(boolean_change_delay_a::Configuration {
+                delay_raising: Duration::ZERO,
+                delay_falling: self.configuration.timeout,
+            }),
+        );
+        signals.dse2d(
+            self.inputs.motion,
+            timeout,
+            boolean_change_delay_a::SignalIdentifier::Input,
+        );
+
+        let light_level = match self.inputs.light_level {
+            Some(light_level) => {
+                let threshold = devices.add(
+                    format!("{} - light level threshold", self.name),
+                    constant_a::Device::new(constant_a::Configuration {
+                        value: self.configuration.light_level_threshold,
+                    }),
+                );
+                let compare = devices.add(
+                    format!("{} - light level compare", self.name),
+                    binary_ord_a::Device::<V>::new(binary_ord_a::Configuration {
+                        operation: Operation::Less,
+                    }),
+                );
+                let and = devices.add(
+                    format!("{} - and", self.name),
+                    and_a::Device::new(and_a::Configuration { inputs_count: 2 }),
+                );
+
+                signals.dse2d(light_level, compare, binary_ord_a::SignalIdentifier::A);
+                signals.d2d(
+                    threshold,
+                    constant_a::SignalIdentifier::Output,
+                    compare,
+                    binary_ord_a::SignalIdentifier::B,
+                );
+                signals.d2d(
+                    timeout,
+                    boolean_change_delay_a::SignalIdentifier::Output,
+                    and,
+                    and_a::SignalIdentifier::Input(0),
+                );
+                signals.d2d(
+                    compare,
+                    binary_ord_a::SignalIdentifier::Output,
+                    and,
+                    and_a::SignalIdentifier::Input(1),
+                );
+                signals.d2dse(and, and_a::SignalIdentifier::Output, self.outputs.light);
+
+                Some(InstanceLightLevel {
+                    threshold,
+                    compare,
+                    and,
+                })
+            }
+            None => {
+                signals.d2dse(
+                    timeout,
+                    boolean_change_delay_a::SignalIdentifier::Output,
+                    self.outputs.light,
+                );
+
+                None
+            }
+        };
+
+        Instance {
+            timeout,
+            light_level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Blueprint, Configuration, Inputs, Outputs};
+    use crate::{
+        datatypes::ratio::Ratio,
+        devices::{
+            blueprints::Blueprint as _,
+            helpers::{Devices, Signals},
+            soft::{time::boolean_change_delay_a, value::constant_a},
+        },
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn instantiate_with_light_level() {
+        let mut devices = Devices::new();
+        let mut signals = Signals::new();
+
+        let motion = devices.add(
+            "motion",
+            constant_a::Device::new(constant_a::Configuration { value: false }),
+        );
+        let light_level = devices.add(
+            "light level",
+            constant_a::Device::new(constant_a::Configuration {
+                value: Ratio::from_f64(0.0).unwrap(),
+            }),
+        );
+        let light = devices.add(
+            "light",
+            boolean_change_delay_a::Device::new(boolean_change_delay_a::Configuration {
+                delay_raising: Duration::ZERO,
+                delay_falling: Duration::ZERO,
+            }),
+        );
+
+        let instance = Blueprint {
+            name: "hall".to_owned(),
+            configuration: Configuration {
+                timeout: Duration::from_secs(60),
+                light_level_threshold: Ratio::from_f64(0.2).unwrap(),
+            },
+            inputs: Inputs {
+                motion: motion
+                    .with_signal(constant_a::SignalIdentifier::Output)
+                    .into_erased(),
+                light_level: Some(
+                    light_level
+                        .with_signal(constant_a::SignalIdentifier::Output)
+                        .into_erased(),
+                ),
+            },
+            outputs: Outputs {
+                light: light
+                    .with_signal(boolean_change_delay_a::SignalIdentifier::Input)
+                    .into_erased(),
+            },
+        }
+        .instantiate(&mut devices, &mut signals);
+
+        assert!(instance.light_level.is_some());
+        // 3 given + timeout, threshold, compare, and
+        assert_eq!(devices.into_device_wrappers_by_id().len(), 7);
+        assert_eq!(signals.as_connections_requested().len(), 6);
+    }
+}
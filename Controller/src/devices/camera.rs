@@ -0,0 +1,95 @@
+use crate::{
+    datatypes::ipc_rtsp_url::IpcRtspUrl, devices::soft::surveillance::snapshot::attachment,
+    modules::events::Attachment, util::async_waker::mpmc,
+};
+use anyhow::{Context, Error};
+use futures::future::BoxFuture;
+use image::DynamicImage;
+use serde::Serialize;
+
+// vendor independent view of ip camera drivers, consumers (surveillance, gui,
+// notifications) should use this instead of driver specific types
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum Health {
+    Initializing,
+    Running,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RtspUrl {
+    pub name: &'static str,
+    pub url: IpcRtspUrl,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct Event {
+    pub name: &'static str,
+    pub active: bool,
+}
+
+// woken when health, rtsp urls or events change
+#[derive(Debug)]
+pub struct Waker {
+    inner: mpmc::Signal,
+}
+impl Waker {
+    pub fn new() -> Self {
+        let inner = mpmc::Signal::new();
+
+        Self { inner }
+    }
+
+    pub fn wake(&self) {
+        self.inner.wake();
+    }
+
+    pub fn receiver(&self) -> mpmc::Receiver {
+        self.inner.receiver()
+    }
+}
+
+pub trait Camera: Send + Sync {
+    fn waker(&self) -> &Waker;
+
+    fn health(&self) -> Health;
+    // streams ordered from the highest quality, empty unless running
+    fn rtsp_urls(&self) -> Box<[RtspUrl]>;
+    // empty unless running
+    fn events(&self) -> Box<[Event]>;
+
+    // fresh image, fetched from the camera on each call
+    fn snapshot(&self) -> BoxFuture<'_, Result<DynamicImage, Error>>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    health: Health,
+    rtsp_urls: Box<[RtspUrl]>,
+    events: Box<[Event]>,
+}
+impl Summary {
+    pub fn from_camera(camera: &dyn Camera) -> Self {
+        Self {
+            health: camera.health(),
+            rtsp_urls: camera.rtsp_urls(),
+            events: camera.events(),
+        }
+    }
+}
+
+pub async fn snapshot_attachment(
+    camera: &dyn Camera,
+    configuration: &attachment::Configuration,
+) -> Result<Attachment, Error> {
+    let image = camera.snapshot().await.context("snapshot")?;
+
+    let configuration = configuration.clone();
+    let attachment = tokio::task::spawn_blocking(move || attachment::build(&image, &configuration))
+        .await
+        .context("spawn_blocking")?
+        .context("build")?;
+
+    Ok(attachment)
+}
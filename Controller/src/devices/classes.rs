@@ -0,0 +1,627 @@
+use super::{
+    soft::{
+        building::{
+            window_open_state_open_closed_from_parts_a,
+            window_open_state_open_tilted_closed_from_parts_a,
+        },
+        climate::{frost_guard_a, humidistat_a, pump_control_a, window_detect_a},
+        converter::multiplayer_to_ratio_clamp_a,
+        diagnostics::flap_detect_a,
+        energy::surplus_a,
+        logic::{
+            boolean::{
+                flip_flop::{override_a, rst_a},
+                gate::{and_a, not_a, or_a},
+                group_a,
+                value::slope_a,
+            },
+            button_decoder_a,
+            encoders_decoders::{boolean_to_ratio_a, rotary_a},
+        },
+        mode::{house_mode_a, selector_a},
+        net::connectivity_a,
+        simulation::{blind_a, light_level_a, thermal_room_a},
+        surveillance::motion_zones_a,
+        system::emergency_stop_a,
+        time::{
+            boolean_change_delay_a, boolean_level_duration_a, countdown_a, pulse_a, pwm_slow_a,
+            sequence_parallel_a,
+        },
+        web::{button_event_a, button_event_boolean_a, button_state_monostable_a, ratio_slider_a},
+    },
+    Configuration, Device,
+};
+use anyhow::{ensure, Context, Error};
+use once_cell::sync::Lazy;
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// upgrades configuration stored in version n to version n + 1
+pub type Migration = fn(serde_json::Value) -> Result<serde_json::Value, Error>;
+
+type Constructor = Box<dyn Fn(serde_json::Value) -> Result<Box<dyn Device>, Error> + Send + Sync>;
+
+// configuration as persisted, tagged with version of Configuration struct it
+// was written with
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigurationVersioned {
+    pub version: usize,
+    pub configuration: serde_json::Value,
+}
+
+// describes device class, so gui can build configuration forms and devices
+// can be created from stored configuration
+#[derive(Serialize)]
+pub struct Class {
+    pub class: &'static str,
+    pub configuration_version: usize,
+    pub configuration_schema: RootSchema,
+
+    #[serde(skip)]
+    migrations: &'static [Migration],
+    #[serde(skip)]
+    constructor: Constructor,
+}
+impl Class {
+    // migrations[n] upgrades configuration from version n to n + 1
+    // when Configuration struct changes shape, append migration instead of
+    // modifying existing ones
+    pub fn new<C: Configuration + JsonSchema + 'static>(
+        class: &'static str,
+        migrations: &'static [Migration],
+        constructor: fn(C) -> Box<dyn Device>,
+    ) -> Self {
+        let constructor = Box::new(move |configuration: serde_json::Value| {
+            let configuration = serde_json::from_value::<C>(configuration).context("from_value")?;
+            Ok(constructor(configuration))
+        });
+
+        Self {
+            class,
+            configuration_version: migrations.len(),
+            configuration_schema: schema_for!(C),
+
+            migrations,
+            constructor,
+        }
+    }
+
+    pub fn configuration_migrate(
+        &self,
+        configuration_versioned: ConfigurationVersioned,
+    ) -> Result<serde_json::Value, Error> {
+        let ConfigurationVersioned {
+            version,
+            mut configuration,
+        } = configuration_versioned;
+
+        ensure!(
+            version <= self.configuration_version,
+            "configuration version {} is newer than supported {}",
+            version,
+            self.configuration_version
+        );
+
+        for (migration_version, migration) in self.migrations.iter().enumerate().skip(version) {
+            configuration = migration(configuration)
+                .with_context(|| format!("migration from version {}", migration_version))?;
+        }
+
+        Ok(configuration)
+    }
+
+    pub fn configuration_current(
+        &self,
+        configuration: serde_json::Value,
+    ) -> ConfigurationVersioned {
+        ConfigurationVersioned {
+            version: self.configuration_version,
+            configuration,
+        }
+    }
+
+    pub fn device_new(
+        &self,
+        configuration_versioned: ConfigurationVersioned,
+    ) -> Result<Box<dyn Device>, Error> {
+        let configuration = self
+            .configuration_migrate(configuration_versioned)
+            .context("configuration_migrate")?;
+        let device = (self.constructor)(configuration).context("constructor")?;
+        Ok(device)
+    }
+}
+impl fmt::Debug for Class {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_struct("Class")
+            .field("class", &self.class)
+            .field("configuration_version", &self.configuration_version)
+            .finish_non_exhaustive()
+    }
+}
+
+// devices with serializable configuration, () for devices without one
+// generic devices and devices borrowing other objects (clock, fs, ...) can't
+// be created from configuration alone and are not listed
+// class names must match Device::class()
+static CLASSES: Lazy<Box<[Class]>> = Lazy::new(|| {
+    [
+        Class::new(
+            "soft/building/window_open_state_open_closed_from_parts_a",
+            &[],
+            |(): ()| Box::new(window_open_state_open_closed_from_parts_a::Device::new()),
+        ),
+        Class::new(
+            "soft/building/window_open_state_open_tilted_closed_from_parts_a",
+            &[],
+            |configuration: window_open_state_open_tilted_closed_from_parts_a::Configuration| {
+                Box::new(
+                    window_open_state_open_tilted_closed_from_parts_a::Device::new(configuration),
+                )
+            },
+        ),
+        Class::new(
+            "soft/climate/frost_guard_a",
+            &[],
+            |configuration: frost_guard_a::Configuration| {
+                Box::new(frost_guard_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/climate/humidistat_a",
+            &[],
+            |configuration: humidistat_a::Configuration| {
+                Box::new(humidistat_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/climate/pump_control_a",
+            &[],
+            |configuration: pump_control_a::Configuration| {
+                Box::new(pump_control_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/climate/window_detect_a",
+            &[],
+            |configuration: window_detect_a::Configuration| {
+                Box::new(window_detect_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/converter/multiplayer_to_ratio_clamp_a",
+            &[],
+            |(): ()| Box::new(multiplayer_to_ratio_clamp_a::Device::new()),
+        ),
+        Class::new(
+            "soft/diagnostics/flap_detect_a",
+            &[],
+            |configuration: flap_detect_a::Configuration| {
+                Box::new(flap_detect_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/energy/surplus_a",
+            &[],
+            |configuration: surplus_a::Configuration| {
+                Box::new(surplus_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/logic/boolean/flip_flop/override_a",
+            &[],
+            |configuration: override_a::Configuration| {
+                Box::new(override_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/logic/boolean/flip_flop/rst_a",
+            &[],
+            |configuration: rst_a::Configuration| Box::new(rst_a::Device::new(configuration)),
+        ),
+        Class::new(
+            "soft/logic/boolean/gate/and_a",
+            &[],
+            |configuration: and_a::Configuration| Box::new(and_a::Device::new(configuration)),
+        ),
+        Class::new("soft/logic/boolean/gate/not_a", &[], |(): ()| {
+            Box::new(not_a::Device::new())
+        }),
+        Class::new(
+            "soft/logic/boolean/gate/or_a",
+            &[],
+            |configuration: or_a::Configuration| Box::new(or_a::Device::new(configuration)),
+        ),
+        Class::new(
+            "soft/logic/boolean/group_a",
+            &[],
+            |configuration: group_a::Configuration| Box::new(group_a::Device::new(configuration)),
+        ),
+        Class::new("soft/logic/boolean/value/slope_a", &[], |(): ()| {
+            Box::new(slope_a::Device::new())
+        }),
+        Class::new(
+            "soft/logic/button_decoder_a",
+            &[],
+            |configuration: button_decoder_a::Configuration| {
+                Box::new(button_decoder_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/logic/encoders_decoders/boolean_to_ratio_a",
+            &[],
+            |configuration: boolean_to_ratio_a::Configuration| {
+                Box::new(boolean_to_ratio_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/logic/encoders_decoders/rotary_a",
+            &[],
+            |configuration: rotary_a::Configuration| Box::new(rotary_a::Device::new(configuration)),
+        ),
+        Class::new(
+            "soft/mode/house_mode_a",
+            &[],
+            |configuration: house_mode_a::Configuration| {
+                Box::new(house_mode_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/mode/selector_a",
+            &[],
+            |configuration: selector_a::Configuration| {
+                Box::new(selector_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/net/connectivity_a",
+            &[],
+            |configuration: connectivity_a::Configuration| {
+                Box::new(connectivity_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/simulation/blind_a",
+            &[],
+            |configuration: blind_a::Configuration| Box::new(blind_a::Device::new(configuration)),
+        ),
+        Class::new(
+            "soft/simulation/light_level_a",
+            &[],
+            |configuration: light_level_a::Configuration| {
+                Box::new(light_level_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/simulation/thermal_room_a",
+            &[],
+            |configuration: thermal_room_a::Configuration| {
+                Box::new(thermal_room_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/surveillance/motion_zones_a",
+            &[],
+            |configuration: motion_zones_a::Configuration| {
+                Box::new(motion_zones_a::Device::new(configuration))
+            },
+        ),
+        Class::new("soft/system/emergency_stop_a", &[], |(): ()| {
+            Box::new(emergency_stop_a::Device::new())
+        }),
+        Class::new(
+            "soft/time/boolean_change_delay_a",
+            &[],
+            |configuration: boolean_change_delay_a::Configuration| {
+                Box::new(boolean_change_delay_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/time/boolean_level_duration_a",
+            &[],
+            |configuration: boolean_level_duration_a::Configuration| {
+                Box::new(boolean_level_duration_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/time/countdown_a",
+            &[],
+            |configuration: countdown_a::Configuration| {
+                Box::new(countdown_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/time/pulse_a",
+            &[],
+            |configuration: pulse_a::Configuration| Box::new(pulse_a::Device::new(configuration)),
+        ),
+        Class::new(
+            "soft/time/pwm_slow_a",
+            &[],
+            |configuration: pwm_slow_a::Configuration| {
+                Box::new(pwm_slow_a::Device::new(configuration))
+            },
+        ),
+        Class::new(
+            "soft/time/sequence_parallel_a",
+            &[],
+            |configuration: sequence_parallel_a::Configuration| {
+                Box::new(sequence_parallel_a::Device::new(configuration))
+            },
+        ),
+        Class::new("soft/web/button_event_a", &[], |(): ()| {
+            Box::new(button_event_a::Device::new())
+        }),
+        Class::new("soft/web/button_event_boolean_a", &[], |(): ()| {
+            Box::new(button_event_boolean_a::Device::new())
+        }),
+        Class::new("soft/web/button_state_monostable_a", &[], |(): ()| {
+            Box::new(button_state_monostable_a::Device::new())
+        }),
+        Class::new(
+            "soft/web/ratio_slider_a",
+            &[],
+            |configuration: ratio_slider_a::Configuration| {
+                Box::new(ratio_slider_a::Device::new(configuration))
+            },
+        ),
+    ]
+    .into()
+});
+
+pub fn classes() -> &'static [Class] {
+    &CLASSES
+}
+
+pub fn class_by_name(class: &str) -> Option<&'static Class> {
+    classes().iter().find(|class_| class_.class == class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classes, Class, ConfigurationVersioned};
+    use crate::devices::soft::time::pulse_a;
+    use anyhow::anyhow;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    #[test]
+    fn classes_unique() {
+        let classes = classes();
+        let names = classes
+            .iter()
+            .map(|class| class.class)
+            .collect::<HashSet<_>>();
+        assert_eq!(names.len(), classes.len());
+    }
+
+    #[test]
+    fn classes_match_devices() {
+        let duration = |secs: u64| json!({ "secs": secs, "nanos": 0 });
+
+        // example configuration for every registered class
+        let configurations = hashmap! {
+            "soft/building/window_open_state_open_closed_from_parts_a" => json!(null),
+            "soft/building/window_open_state_open_tilted_closed_from_parts_a" => json!({
+                "open_on_opened_not_tilted": true,
+            }),
+            "soft/climate/frost_guard_a" => json!({
+                "threshold": 278.15,
+                "hysteresis": 1.0,
+            }),
+            "soft/climate/humidistat_a" => json!({
+                "baseline": { "Absolute": 0.7 },
+                "hysteresis": 0.05,
+                "run_min": duration(60),
+                "run_max": duration(3600),
+            }),
+            "soft/climate/pump_control_a" => json!({
+                "demands_count": 2,
+                "overrun": duration(60),
+                "exercise_interval": duration(7 * 24 * 3600),
+                "exercise_duration": duration(60),
+            }),
+            "soft/climate/window_detect_a" => json!({
+                "detection_window": duration(300),
+                "detection_drop": 1.0,
+                "recovery_rise": 0.5,
+                "recovery_timeout": duration(1800),
+            }),
+            "soft/converter/multiplayer_to_ratio_clamp_a" => json!(null),
+            "soft/diagnostics/flap_detect_a" => json!({
+                "changes_max": 10,
+                "window": duration(60),
+            }),
+            "soft/energy/surplus_a" => json!({
+                "loads": [{ "priority": 0, "power_max": 2000.0, "power_min": 0.0 }],
+                "grid_target": 50.0,
+                "gain": 0.5,
+                "interval": duration(10),
+            }),
+            "soft/logic/boolean/flip_flop/override_a" => json!({
+                "initial_mode": "PassThrough",
+            }),
+            "soft/logic/boolean/flip_flop/rst_a" => json!({
+                "initial_value": false,
+            }),
+            "soft/logic/boolean/gate/and_a" => json!({
+                "inputs_count": 2,
+            }),
+            "soft/logic/boolean/gate/not_a" => json!(null),
+            "soft/logic/boolean/gate/or_a" => json!({
+                "inputs_count": 2,
+            }),
+            "soft/logic/boolean/group_a" => json!({
+                "outputs": [{ "inverted": false, "delay": duration(0) }],
+            }),
+            "soft/logic/boolean/value/slope_a" => json!(null),
+            "soft/logic/button_decoder_a" => json!({
+                "click_duration_max": duration(1),
+                "clicks_gap_max": duration(1),
+            }),
+            "soft/logic/encoders_decoders/boolean_to_ratio_a" => json!({
+                "inputs_count": 2,
+            }),
+            "soft/logic/encoders_decoders/rotary_a" => json!({
+                "mode": "UpDown",
+                "step": 0.1,
+                "minimum": 0.0,
+                "maximum": 1.0,
+                "acceleration": null,
+            }),
+            "soft/mode/house_mode_a" => json!({
+                "initial": "Home",
+            }),
+            "soft/mode/selector_a" => json!({
+                "modes": ["day", "night"],
+                "initial": null,
+            }),
+            "soft/net/connectivity_a" => json!({
+                "probes": [{ "Dns": { "name": "example.com" } }],
+                "interval": duration(60),
+                "timeout": duration(5),
+                "down_rounds": 3,
+                "loss_rounds": 10,
+            }),
+            "soft/simulation/blind_a" => json!({
+                "position_initial": 0.0,
+                "travel_time": duration(30),
+                "step": duration(1),
+            }),
+            "soft/simulation/light_level_a" => json!({
+                "daylight_max": 1000.0,
+                "blind_closed_transmittance": 0.1,
+                "light_max": 500.0,
+            }),
+            "soft/simulation/thermal_room_a" => json!({
+                "temperature_initial": 293.15,
+                "temperature_outdoor_default": 273.15,
+                "heat_loss": 100.0,
+                "heat_capacity": 1000000.0,
+                "heater_power": 2000.0,
+                "step": duration(60),
+            }),
+            "soft/surveillance/motion_zones_a" => json!({
+                "inputs_count": 1,
+                "zones": [{
+                    "name": "hall",
+                    "inputs": [0],
+                    "debounce": duration(0),
+                    "hold": duration(10),
+                }],
+            }),
+            "soft/system/emergency_stop_a" => json!(null),
+            "soft/time/boolean_change_delay_a" => json!({
+                "delay_raising": duration(1),
+                "delay_falling": duration(1),
+            }),
+            "soft/time/boolean_level_duration_a" => json!({
+                "breakpoints": [{ "expires": duration(1) }],
+            }),
+            "soft/time/countdown_a" => json!({
+                "duration": duration(60),
+            }),
+            "soft/time/pulse_a" => json!({
+                "duration": duration(1),
+            }),
+            "soft/time/pwm_slow_a" => json!({
+                "cycle_duration": duration(60),
+                "cycle_phase_shift": null,
+            }),
+            "soft/time/sequence_parallel_a" => json!({
+                "power_max": 1.0,
+                "channels": [{
+                    "name": "pump",
+                    "base_time": duration(60),
+                    "power_required": 1.0,
+                    "round_min": duration(60),
+                    "round_max": duration(600),
+                }],
+            }),
+            "soft/web/button_event_a" => json!(null),
+            "soft/web/button_event_boolean_a" => json!(null),
+            "soft/web/button_state_monostable_a" => json!(null),
+            "soft/web/ratio_slider_a" => json!({
+                "initial": null,
+            }),
+        };
+        assert_eq!(configurations.len(), classes().len());
+
+        for class in classes() {
+            let configuration = configurations
+                .get(class.class)
+                .unwrap_or_else(|| panic!("missing configuration for {}", class.class));
+            let device = class
+                .device_new(class.configuration_current(configuration.clone()))
+                .unwrap_or_else(|error| panic!("{}: {:?}", class.class, error));
+            assert_eq!(device.class(), class.class);
+        }
+    }
+
+    #[test]
+    fn migrations_applied() {
+        // version 0 stored duration as milliseconds
+        // version 1 renamed field
+        let class = Class::new(
+            "tests/migrations_applied",
+            &[
+                |mut configuration| {
+                    let duration_ms = configuration["duration_ms"]
+                        .take()
+                        .as_u64()
+                        .ok_or_else(|| anyhow!("duration_ms"))?;
+                    Ok(json!({
+                        "length": { "secs": duration_ms / 1000, "nanos": 0 },
+                    }))
+                },
+                |mut configuration| {
+                    Ok(json!({
+                        "duration": configuration["length"].take(),
+                    }))
+                },
+            ],
+            |configuration: pulse_a::Configuration| Box::new(pulse_a::Device::new(configuration)),
+        );
+        assert_eq!(class.configuration_version, 2);
+
+        let configuration = class
+            .configuration_migrate(ConfigurationVersioned {
+                version: 0,
+                configuration: json!({ "duration_ms": 3000 }),
+            })
+            .unwrap();
+        assert_eq!(
+            configuration,
+            json!({ "duration": { "secs": 3, "nanos": 0 } })
+        );
+
+        let configuration = class
+            .configuration_migrate(ConfigurationVersioned {
+                version: 1,
+                configuration: json!({ "length": { "secs": 3, "nanos": 0 } }),
+            })
+            .unwrap();
+        assert_eq!(
+            configuration,
+            json!({ "duration": { "secs": 3, "nanos": 0 } })
+        );
+
+        // configuration from future release is rejected
+        assert!(class
+            .configuration_migrate(ConfigurationVersioned {
+                version: 3,
+                configuration: json!({}),
+            })
+            .is_err());
+
+        // invalid configuration is rejected
+        assert!(class
+            .device_new(class.configuration_current(json!({ "length": 3 })))
+            .is_err());
+    }
+}
@@ -0,0 +1,932 @@
+use super::{DeviceWrapper, Id as DeviceId};
+use crate::{
+    modules::{
+        fs::Fs,
+        sqlite::SQLite,
+        sqlite_migrations::{
+            self,
+            cli::Database,
+            graph::{Graph, GraphResolver},
+        },
+    },
+    signals::exchanger::{ConnectionRequested, DeviceIdSignalIdentifierBaseWrapper},
+    web::{self, uri_cursor},
+};
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, Future, FutureExt};
+use phf::phf_map;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+static MIGRATIONS: Graph = phf_map! {
+    // rollback keeps its own copy of snapshot, so imported definitions can be
+    // rolled back to, pending rollback is dropped
+    3u32 => phf_map! {
+        2u32 => Some("
+            DROP TABLE `rollback`;
+            CREATE TABLE `rollback` (
+                `id` INTEGER PRIMARY KEY NOT NULL CHECK (`id` = 0), -- single row
+                `version_id` INTEGER REFERENCES `versions` (`id`), -- null if imported
+                `base_version_id` INTEGER NOT NULL REFERENCES `versions` (`id`), -- version running when requested
+                `snapshot` TEXT NOT NULL -- json
+            ) STRICT;
+        "),
+    },
+    2u32 => phf_map! {
+        1u32 => Some("
+            CREATE TABLE `rollback` (
+                `id` INTEGER PRIMARY KEY NOT NULL CHECK (`id` = 0), -- single row
+                `version_id` INTEGER NOT NULL REFERENCES `versions` (`id`),
+                `base_version_id` INTEGER NOT NULL REFERENCES `versions` (`id`) -- version running when requested
+            ) STRICT;
+        "),
+    },
+    1u32 => phf_map! {
+        0u32 => Some("
+            CREATE TABLE `versions` (
+                `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                `created` INTEGER NOT NULL, -- unix seconds
+                `snapshot` TEXT NOT NULL -- json
+            ) STRICT;
+        "),
+    },
+};
+static MIGRATIONS_RESOLVER: GraphResolver<'static> = GraphResolver(&MIGRATIONS);
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SnapshotDevice {
+    pub name: String,
+    pub class: String,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct SnapshotSignal {
+    pub device_id: DeviceId,
+    pub signal: String,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct SnapshotConnection {
+    pub source: SnapshotSignal,
+    pub target: SnapshotSignal,
+}
+
+// devices and connections, as given to runner at startup
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub devices: BTreeMap<DeviceId, SnapshotDevice>,
+    pub connections: BTreeSet<SnapshotConnection>,
+}
+impl Snapshot {
+    pub fn new(
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+        connections_requested: &[ConnectionRequested],
+    ) -> Self {
+        let devices = device_wrappers_by_id
+            .iter()
+            .map(|(device_id, device_wrapper)| {
+                let snapshot_device = SnapshotDevice {
+                    name: device_wrapper.name().clone(),
+                    class: device_wrapper.device().class().into_owned(),
+                };
+                (*device_id, snapshot_device)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let connections = connections_requested
+            .iter()
+            .map(|(source, target)| SnapshotConnection {
+                source: SnapshotSignal {
+                    device_id: source.device_id(),
+                    signal: source.signal_identifier_base_wrapper().name(),
+                },
+                target: SnapshotSignal {
+                    device_id: target.device_id(),
+                    signal: target.signal_identifier_base_wrapper().name(),
+                },
+            })
+            .collect::<BTreeSet<_>>();
+
+        Self {
+            devices,
+            connections,
+        }
+    }
+
+    // resolves connections back to signals of given devices
+    // devices are built in code and cannot be restored from snapshot, so
+    // connections of devices which no longer exist or changed class are skipped
+    pub fn connections_requested(
+        &self,
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+    ) -> Box<[ConnectionRequested]> {
+        let resolve = |snapshot_signal: &SnapshotSignal| {
+            let device_wrapper = device_wrappers_by_id.get(&snapshot_signal.device_id)?;
+            let snapshot_device = self.devices.get(&snapshot_signal.device_id)?;
+            if device_wrapper.device().class() != snapshot_device.class {
+                return None;
+            }
+
+            let signal_identifier_base_wrapper = device_wrapper
+                .device()
+                .as_signals_device_base()
+                .by_identifier()
+                .into_keys()
+                .find(|signal_identifier_base_wrapper| {
+                    signal_identifier_base_wrapper.name() == snapshot_signal.signal
+                })?;
+
+            Some(DeviceIdSignalIdentifierBaseWrapper::new(
+                snapshot_signal.device_id,
+                signal_identifier_base_wrapper,
+            ))
+        };
+
+        self.connections
+            .iter()
+            .filter_map(|connection| {
+                match (resolve(&connection.source), resolve(&connection.target)) {
+                    (Some(source), Some(target)) => Some((source, target)),
+                    _ => {
+                        log::warn!(
+                            "configuration_history: connection {:?} cannot be restored, skipping",
+                            connection
+                        );
+                        None
+                    }
+                }
+            })
+            .collect::<Box<[_]>>()
+    }
+
+    // changes needed to get from self to other
+    pub fn diff(
+        &self,
+        other: &Self,
+    ) -> Diff {
+        let devices_added = other
+            .devices
+            .iter()
+            .filter(|(device_id, _)| !self.devices.contains_key(device_id))
+            .map(|(device_id, device)| (*device_id, device.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let devices_removed = self
+            .devices
+            .iter()
+            .filter(|(device_id, _)| !other.devices.contains_key(device_id))
+            .map(|(device_id, device)| (*device_id, device.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let devices_changed = self
+            .devices
+            .iter()
+            .filter_map(|(device_id, device)| {
+                let device_other = other.devices.get(device_id)?;
+                if device == device_other {
+                    return None;
+                }
+                Some((*device_id, (device.clone(), device_other.clone())))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let connections_added = other
+            .connections
+            .difference(&self.connections)
+            .cloned()
+            .collect::<Box<[_]>>();
+        let connections_removed = self
+            .connections
+            .difference(&other.connections)
+            .cloned()
+            .collect::<Box<[_]>>();
+
+        Diff {
+            devices_added,
+            devices_removed,
+            devices_changed,
+            connections_added,
+            connections_removed,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Serialize)]
+pub struct Diff {
+    pub devices_added: BTreeMap<DeviceId, SnapshotDevice>,
+    pub devices_removed: BTreeMap<DeviceId, SnapshotDevice>,
+    pub devices_changed: BTreeMap<DeviceId, (SnapshotDevice, SnapshotDevice)>,
+    pub connections_added: Box<[SnapshotConnection]>,
+    pub connections_removed: Box<[SnapshotConnection]>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Version {
+    pub id: i64,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Rollback {
+    // none if imported
+    pub version_id: Option<i64>,
+    pub base_version_id: i64,
+}
+
+// keeps every distinct configuration the application was started with
+// devices are defined in code, so rollback restores connections of selected
+// version (or imported definition, see definition), applied on next start (see
+// restart) as long as the build is still the one rollback was requested on,
+// deploying another build cancels it
+#[derive(Debug)]
+pub struct ConfigurationHistory<'f> {
+    sqlite: SQLite<'f>,
+}
+impl<'f> ConfigurationHistory<'f> {
+    const SQLITE_NAME: &'static str = "configuration_history";
+    pub const ROUTE: &'static str = "configuration-history";
+
+    pub fn new(fs: &'f Fs) -> Self {
+        let sqlite = SQLite::new(Self::SQLITE_NAME.to_owned(), fs);
+
+        Self { sqlite }
+    }
+
+    // for running migrations before startup, see sqlite_migrations::cli
+    pub fn migrations_database() -> Database<'static> {
+        Database {
+            name: Self::SQLITE_NAME.to_owned(),
+            resolver: &MIGRATIONS_RESOLVER,
+        }
+    }
+
+    // stores snapshot as new version, unless it equals the latest one
+    // resolves to id of the version matching snapshot
+    pub fn record(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<impl Future<Output = Result<i64, Error>> + Send + 'static, Error> {
+        let snapshot_json = serde_json::to_string(snapshot).context("to_string")?;
+
+        let recorded = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+
+                let latest = transaction
+                    .query_row(
+                        "SELECT `id`, `snapshot` FROM `versions` ORDER BY `id` DESC LIMIT 1",
+                        [],
+                        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+                    )
+                    .optional()
+                    .context("query_row")?;
+                if let Some((id, snapshot_json_latest)) = latest {
+                    if snapshot_json_latest == snapshot_json {
+                        return Ok(id);
+                    }
+                }
+
+                let id = transaction
+                    .query_row(
+                        "INSERT INTO `versions` (`created`, `snapshot`) VALUES (?, ?) RETURNING `id`",
+                        rusqlite::params![Utc::now().timestamp(), snapshot_json],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .context("query_row")?;
+                Ok(id)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result));
+        Ok(recorded)
+    }
+
+    pub fn list(&self) -> impl Future<Output = Result<Box<[Version]>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let versions = transaction
+                    .prepare("SELECT `id`, `created` FROM `versions` ORDER BY `id`")
+                    .context("prepare")?
+                    .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+                    .context("query_map")?
+                    .map(|row| -> Result<Version, Error> {
+                        let (id, created) = row.context("row")?;
+                        let created =
+                            DateTime::<Utc>::from_timestamp(created, 0).context("created")?;
+                        Ok(Version { id, created })
+                    })
+                    .collect::<Result<Box<[_]>, _>>()
+                    .context("collect")?;
+                Ok(versions)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    pub fn get(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<Option<Snapshot>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let snapshot = transaction
+                    .query_row(
+                        "SELECT `snapshot` FROM `versions` WHERE `id` = ?",
+                        [id],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()
+                    .context("query_row")?;
+                let snapshot = match snapshot {
+                    Some(snapshot) => Some(serde_json::from_str(&snapshot).context("from_str")?),
+                    None => None,
+                };
+                Ok(snapshot)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // records snapshot of configuration being started, resolves to connections
+    // of requested rollback, if one is pending for this build
+    pub async fn startup(
+        &self,
+        snapshot: &Snapshot,
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+    ) -> Result<Option<Box<[ConnectionRequested]>>, Error> {
+        let version_id = self
+            .record(snapshot)
+            .context("record")?
+            .await
+            .context("record")?;
+        log::info!("configuration version: {version_id}");
+
+        let (rollback_version_id, snapshot_rollback) = match self
+            .rollback_target(version_id)
+            .await
+            .context("rollback_target")?
+        {
+            Some(rollback_target) => rollback_target,
+            None => return Ok(None),
+        };
+        match rollback_version_id {
+            Some(rollback_version_id) => {
+                log::warn!(
+                    "configuration version {version_id} rolled back to {rollback_version_id}"
+                )
+            }
+            None => log::warn!("configuration version {version_id} replaced by imported one"),
+        }
+
+        let connections_requested = snapshot_rollback.connections_requested(device_wrappers_by_id);
+        Ok(Some(connections_requested))
+    }
+
+    // requests rollback to given version on next start, resolves to false if
+    // version does not exist
+    pub fn rollback_request(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<bool, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+
+                // latest version is the one currently running
+                let base_version_id = match transaction
+                    .query_row("SELECT MAX(`id`) FROM `versions`", [], |row| {
+                        row.get::<_, Option<i64>>(0)
+                    })
+                    .context("query_row")?
+                {
+                    Some(base_version_id) => base_version_id,
+                    None => return Ok(false),
+                };
+
+                let inserted = transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO `rollback` (`id`, `version_id`, `base_version_id`, `snapshot`) SELECT 0, `id`, ?, `snapshot` FROM `versions` WHERE `id` = ?",
+                        [base_version_id, id],
+                    )
+                    .context("execute")?;
+                Ok(inserted > 0)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+    // requests connections of given snapshot on next start, as for rollback
+    pub fn rollback_import(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<impl Future<Output = Result<(), Error>> + Send + 'static, Error> {
+        let snapshot_json = serde_json::to_string(snapshot).context("to_string")?;
+
+        let imported = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+
+                let base_version_id = transaction
+                    .query_row("SELECT MAX(`id`) FROM `versions`", [], |row| {
+                        row.get::<_, Option<i64>>(0)
+                    })
+                    .context("query_row")?
+                    .context("no version recorded")?;
+
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO `rollback` (`id`, `version_id`, `base_version_id`, `snapshot`) VALUES (0, NULL, ?, ?)",
+                        rusqlite::params![base_version_id, snapshot_json],
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .map(|result| result.context("transaction").and_then(|result| result));
+        Ok(imported)
+    }
+    pub fn rollback_cancel(&self) -> impl Future<Output = Result<(), Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                transaction
+                    .execute("DELETE FROM `rollback`", [])
+                    .context("execute")?;
+                Ok(())
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+    pub fn rollback_get(
+        &self
+    ) -> impl Future<Output = Result<Option<Rollback>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let rollback = transaction
+                    .query_row(
+                        "SELECT `version_id`, `base_version_id` FROM `rollback`",
+                        [],
+                        |row| {
+                            Ok(Rollback {
+                                version_id: row.get(0)?,
+                                base_version_id: row.get(1)?,
+                            })
+                        },
+                    )
+                    .optional()
+                    .context("query_row")?;
+                Ok(rollback)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // snapshot to roll back to when starting given version
+    // rollback requested on other version is outdated and dropped
+    fn rollback_target(
+        &self,
+        version_id: i64,
+    ) -> impl Future<Output = Result<Option<(Option<i64>, Snapshot)>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+
+                let rollback = transaction
+                    .query_row(
+                        "SELECT `version_id`, `base_version_id`, `snapshot` FROM `rollback`",
+                        [],
+                        |row| {
+                            Ok((
+                                row.get::<_, Option<i64>>(0)?,
+                                row.get::<_, i64>(1)?,
+                                row.get::<_, String>(2)?,
+                            ))
+                        },
+                    )
+                    .optional()
+                    .context("query_row")?;
+                let (rollback_version_id, base_version_id, snapshot) = match rollback {
+                    Some(rollback) => rollback,
+                    None => return Ok(None),
+                };
+
+                if base_version_id != version_id {
+                    log::warn!(
+                        "configuration_history: rollback to {rollback_version_id:?} requested on version {base_version_id}, dropping"
+                    );
+                    transaction
+                        .execute("DELETE FROM `rollback`", [])
+                        .context("execute")?;
+                    return Ok(None);
+                }
+
+                let snapshot = serde_json::from_str(&snapshot).context("from_str")?;
+                Ok(Some((rollback_version_id, snapshot)))
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+        // creates or migrates the tables
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, transaction).context("execute")?;
+
+        Ok(())
+    }
+}
+
+impl<'f> uri_cursor::Handler for ConfigurationHistory<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let versions = self.list();
+                    async {
+                        match versions.await {
+                            Ok(versions) => web::Response::ok_json(versions),
+                            Err(error) => {
+                                log::error!("configuration_history: list: {:?}", error);
+                                web::Response::error_500()
+                            }
+                        }
+                    }
+                    .boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("rollback", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let rollback = self.rollback_get();
+                        async {
+                            match rollback.await {
+                                Ok(rollback) => web::Response::ok_json(rollback),
+                                Err(error) => {
+                                    log::error!("configuration_history: rollback_get: {:?}", error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    http::Method::DELETE => {
+                        let rollback_cancel = self.rollback_cancel();
+                        async {
+                            match rollback_cancel.await {
+                                Ok(()) => web::Response::ok_empty(),
+                                Err(error) => {
+                                    log::error!(
+                                        "configuration_history: rollback_cancel: {:?}",
+                                        error
+                                    );
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("diff", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Next(from_id_str, uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Next(to_id_str, uri_cursor) => match uri_cursor.as_ref()
+                    {
+                        uri_cursor::UriCursor::Terminal => match *request.method() {
+                            http::Method::GET => {
+                                let (from_id, to_id) = match (
+                                    from_id_str.parse::<i64>().context("from_id"),
+                                    to_id_str.parse::<i64>().context("to_id"),
+                                ) {
+                                    (Ok(from_id), Ok(to_id)) => (from_id, to_id),
+                                    (Err(error), _) | (_, Err(error)) => {
+                                        return async {
+                                            web::Response::error_400_from_error(error)
+                                        }
+                                        .boxed();
+                                    }
+                                };
+
+                                let from = self.get(from_id);
+                                let to = self.get(to_id);
+                                async move {
+                                    match (from.await, to.await) {
+                                        (Ok(Some(from)), Ok(Some(to))) => {
+                                            web::Response::ok_json(from.diff(&to))
+                                        }
+                                        (Ok(_), Ok(_)) => web::Response::error_404(),
+                                        (Err(error), _) | (_, Err(error)) => {
+                                            log::error!("configuration_history: diff: {:?}", error);
+                                            web::Response::error_500()
+                                        }
+                                    }
+                                }
+                                .boxed()
+                            }
+                            _ => async { web::Response::error_405() }.boxed(),
+                        },
+                        _ => async { web::Response::error_404() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next(id_str, uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let id = match id_str.parse::<i64>().context("id") {
+                            Ok(id) => id,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+
+                        let snapshot = self.get(id);
+                        async move {
+                            match snapshot.await {
+                                Ok(Some(snapshot)) => web::Response::ok_json(snapshot),
+                                Ok(None) => web::Response::error_404(),
+                                Err(error) => {
+                                    log::error!("configuration_history: get: {:?}", error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("rollback", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::POST => {
+                            let id = match id_str.parse::<i64>().context("id") {
+                                Ok(id) => id,
+                                Err(error) => {
+                                    return async { web::Response::error_400_from_error(error) }
+                                        .boxed();
+                                }
+                            };
+
+                            let rollback_request = self.rollback_request(id);
+                            async move {
+                                match rollback_request.await {
+                                    Ok(true) => web::Response::ok_empty(),
+                                    Ok(false) => web::Response::error_404(),
+                                    Err(error) => {
+                                        log::error!(
+                                            "configuration_history: rollback_request: {:?}",
+                                            error
+                                        );
+                                        web::Response::error_500()
+                                    }
+                                }
+                            }
+                            .boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ConfigurationHistory, Rollback, Snapshot, SnapshotConnection, SnapshotDevice,
+        SnapshotSignal,
+    };
+    use crate::{
+        devices::{
+            helpers::{Devices, Signals},
+            soft::{time::boolean_change_delay_a, value::constant_a},
+        },
+        modules::{fs::Fs, sqlite::sqlite_file},
+    };
+    use maplit::{btreemap, btreeset};
+    use std::{collections::HashMap, fs, time::Duration};
+
+    fn device(name: &str) -> SnapshotDevice {
+        SnapshotDevice {
+            name: name.to_owned(),
+            class: "soft/time/pulse_a".to_owned(),
+        }
+    }
+    fn connection(
+        source_device_id: u32,
+        target_device_id: u32,
+    ) -> SnapshotConnection {
+        SnapshotConnection {
+            source: SnapshotSignal {
+                device_id: source_device_id,
+                signal: "output".to_owned(),
+            },
+            target: SnapshotSignal {
+                device_id: target_device_id,
+                signal: "input".to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let from = Snapshot {
+            devices: btreemap! {
+                1 => device("a"),
+                2 => device("b"),
+                3 => device("c"),
+            },
+            connections: btreeset! {
+                connection(1, 2),
+                connection(2, 3),
+            },
+        };
+        let to = Snapshot {
+            devices: btreemap! {
+                1 => device("a"),
+                2 => device("b renamed"),
+                4 => device("d"),
+            },
+            connections: btreeset! {
+                connection(1, 2),
+                connection(2, 4),
+            },
+        };
+
+        let diff = from.diff(&to);
+        assert_eq!(diff.devices_added, btreemap! { 4 => device("d") });
+        assert_eq!(diff.devices_removed, btreemap! { 3 => device("c") });
+        assert_eq!(
+            diff.devices_changed,
+            btreemap! { 2 => (device("b"), device("b renamed")) }
+        );
+        assert_eq!(&*diff.connections_added, &[connection(2, 4)]);
+        assert_eq!(&*diff.connections_removed, &[connection(2, 3)]);
+
+        assert_eq!(to.diff(&to).devices_changed, btreemap! {});
+    }
+
+    #[test]
+    fn connections_requested() {
+        let mut devices = Devices::new();
+        let mut signals = Signals::new();
+
+        let constant = devices.add(
+            "constant",
+            constant_a::Device::new(constant_a::Configuration { value: true }),
+        );
+        let delay_configuration = || boolean_change_delay_a::Configuration {
+            delay_raising: Duration::ZERO,
+            delay_falling: Duration::ZERO,
+        };
+        let light_a = devices.add(
+            "light a",
+            boolean_change_delay_a::Device::new(delay_configuration()),
+        );
+        let light_b = devices.add(
+            "light b",
+            boolean_change_delay_a::Device::new(delay_configuration()),
+        );
+        signals.d2d(
+            constant,
+            constant_a::SignalIdentifier::Output,
+            light_a,
+            boolean_change_delay_a::SignalIdentifier::Input,
+        );
+        let device_wrappers_by_id = devices.into_device_wrappers_by_id();
+        let connections_requested = signals.into_connections_requested();
+        let snapshot_previous = Snapshot::new(&device_wrappers_by_id, &connections_requested);
+
+        let constant_id = constant.into_erased().device_id();
+        let light_a_id = light_a.into_erased().device_id();
+        let light_b_id = light_b.into_erased().device_id();
+        let connection = |source_device_id, target_device_id| SnapshotConnection {
+            source: SnapshotSignal {
+                device_id: source_device_id,
+                signal: "Output".to_owned(),
+            },
+            target: SnapshotSignal {
+                device_id: target_device_id,
+                signal: "Input".to_owned(),
+            },
+        };
+        assert_eq!(
+            snapshot_previous.connections,
+            btreeset! { connection(constant_id, light_a_id) }
+        );
+
+        // light b had different class back then, device 99 is gone
+        let mut snapshot_rollback = snapshot_previous.clone();
+        snapshot_rollback
+            .connections
+            .insert(connection(constant_id, light_b_id));
+        snapshot_rollback
+            .connections
+            .insert(connection(constant_id, 99));
+        snapshot_rollback
+            .devices
+            .insert(light_b_id, device("light b"));
+
+        let connections_requested_rollback =
+            snapshot_rollback.connections_requested(&device_wrappers_by_id);
+        assert_eq!(&*connections_requested_rollback, &*connections_requested);
+    }
+
+    #[tokio::test]
+    async fn rollback() {
+        let fs = Fs::new(Default::default());
+        let path = sqlite_file(&fs, ConfigurationHistory::SQLITE_NAME);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(path.with_extension(format!("sqlite{}", suffix)));
+        }
+        let configuration_history = ConfigurationHistory::new(&fs);
+        let device_wrappers_by_id = HashMap::new();
+
+        let snapshot_good = Snapshot {
+            devices: btreemap! {},
+            connections: btreeset! { connection(1, 2) },
+        };
+        let snapshot_bad = Snapshot {
+            devices: btreemap! {},
+            connections: btreeset! { connection(1, 3) },
+        };
+        let snapshot_fixed = Snapshot {
+            devices: btreemap! {},
+            connections: btreeset! { connection(1, 4) },
+        };
+
+        // good version, replaced by bad one
+        assert!(configuration_history
+            .startup(&snapshot_good, &device_wrappers_by_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(configuration_history
+            .startup(&snapshot_bad, &device_wrappers_by_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(!configuration_history.rollback_request(3).await.unwrap());
+        assert!(configuration_history.rollback_request(1).await.unwrap());
+        assert_eq!(
+            configuration_history.rollback_get().await.unwrap(),
+            Some(Rollback {
+                version_id: Some(1),
+                base_version_id: 2,
+            })
+        );
+
+        // restarted with the same build, rollback is applied on each start
+        // the rolled back configuration itself is not recorded
+        for _ in 0..2 {
+            assert!(configuration_history
+                .startup(&snapshot_bad, &device_wrappers_by_id)
+                .await
+                .unwrap()
+                .is_some());
+        }
+        assert_eq!(configuration_history.list().await.unwrap().len(), 2);
+
+        // new build deployed, rollback is dropped
+        assert!(configuration_history
+            .startup(&snapshot_fixed, &device_wrappers_by_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(configuration_history.rollback_get().await.unwrap(), None);
+
+        // cancelled before restart
+        assert!(configuration_history.rollback_request(2).await.unwrap());
+        configuration_history.rollback_cancel().await.unwrap();
+        assert!(configuration_history
+            .startup(&snapshot_fixed, &device_wrappers_by_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        // imported definition, not a version itself
+        configuration_history
+            .rollback_import(&snapshot_good)
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(
+            configuration_history.rollback_get().await.unwrap(),
+            Some(Rollback {
+                version_id: None,
+                base_version_id: 3,
+            })
+        );
+        assert!(configuration_history
+            .startup(&snapshot_fixed, &device_wrappers_by_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(configuration_history.list().await.unwrap().len(), 3);
+    }
+}
@@ -1,4 +1,5 @@
 use super::boundary_stream;
+use crate::devices::helpers::http as http_helpers;
 use anyhow::{anyhow, bail, ensure, Context, Error};
 use bytes::Bytes;
 use digest_auth::{AuthContext, WwwAuthenticateHeader};
@@ -95,7 +96,7 @@ impl Api {
         host: Authority,
         admin_password: String,
     ) -> Self {
-        let reqwest_client = reqwest::ClientBuilder::new().build().unwrap();
+        let reqwest_client = http_helpers::client();
 
         let rpc2_request_id_next = 0;
         let rpc2_request_id_next = AtomicU64::new(rpc2_request_id_next);
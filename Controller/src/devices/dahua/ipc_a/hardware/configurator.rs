@@ -450,7 +450,6 @@ pub struct AudioMutationDetection {
 pub struct Configuration {
     pub device_id: u8,
     pub device_name: String,
-    pub shared_user_password: String,
     pub video_upside_down: bool,
     pub channel_title: Option<String>,
     pub privacy_mask: Option<PrivacyMask>,
@@ -2215,6 +2214,7 @@ impl<'a> Configurator<'a> {
         &mut self,
         factory_reset: bool,
         configuration: Configuration,
+        shared_user_password: String,
     ) -> Result<(), Error> {
         if factory_reset {
             log::trace!("system_factory_reset");
@@ -2233,7 +2233,7 @@ impl<'a> Configurator<'a> {
         //     .context("system_firmware_upgrade")?;
 
         log::trace!("system_shared_user");
-        self.system_shared_user(configuration.shared_user_password)
+        self.system_shared_user(shared_user_password)
             .await
             .context("system_shared_user")?;
 
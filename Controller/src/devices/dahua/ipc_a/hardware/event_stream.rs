@@ -18,6 +18,22 @@ use std::{
 };
 use tokio::sync::watch;
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ObjectType {
+    Human,
+    Vehicle,
+    Other,
+}
+impl ObjectType {
+    fn parse(object_type: &str) -> Self {
+        match object_type {
+            "Human" => Self::Human,
+            "Vehicle" | "MotorVehicle" | "NonMotor" => Self::Vehicle,
+            _ => Self::Other,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Event {
     VideoBlind,
@@ -26,32 +42,71 @@ pub enum Event {
     AudioMutation,
     SmartMotionHuman,
     SmartMotionVehicle,
+
+    // region indices, as configured in camera
+    VideoMotionRegion(usize),
+    SmartMotionHumanRegion(usize),
+    SmartMotionVehicleRegion(usize),
+
+    // ivs rule (tripwire, intrusion) by its name, as configured in camera
+    Ivs {
+        rule: String,
+        object_type: ObjectType,
+    },
+}
+impl Event {
+    // region events are cleared together with their parent, as regions in
+    // stop events do not match ones from start events
+    fn parent(&self) -> Option<Event> {
+        match self {
+            Event::VideoMotionRegion(_) => Some(Event::VideoMotion),
+            Event::SmartMotionHumanRegion(_) => Some(Event::SmartMotionHuman),
+            Event::SmartMotionVehicleRegion(_) => Some(Event::SmartMotionVehicle),
+            _ => None,
+        }
+    }
 }
 
 pub type Events = HashSet<Event>;
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Start,
+    Stop,
+    // momentary events (eg. tripwire crossing), active for
+    // Manager::EVENT_PULSE_DURATION
+    Pulse,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct EventStateUpdate {
-    event: Event,
-    active: bool,
+    events: Box<[Event]>,
+    action: Action,
+}
+
+#[derive(Debug)]
+struct EventActive {
+    started: Instant,
+    expires: Option<Instant>,
 }
 
 #[derive(Debug)]
 pub struct Manager<'a> {
     api: &'a Api,
 
-    events_active: AtomicRefCell<HashMap<Event, Instant>>, // {event: started}
+    events_active: AtomicRefCell<HashMap<Event, EventActive>>,
 
     events_sender: watch::Sender<Events>,
     events_receiver: watch::Receiver<Events>,
 }
 impl<'a> Manager<'a> {
     const EVENT_DURATION_THRESHOLD: Duration = Duration::from_secs(60 * 60);
-    const EVENT_FIXER_INTERVAL: Duration = Duration::from_secs(60);
+    const EVENT_PULSE_DURATION: Duration = Duration::from_secs(5);
+    const EVENT_FIXER_INTERVAL: Duration = Duration::from_secs(1);
     const ERROR_RESTART_DELAY: Duration = Duration::from_secs(1);
 
     pub fn new(api: &'a Api) -> Self {
-        let events_active = HashMap::<Event, Instant>::new();
+        let events_active = HashMap::<Event, EventActive>::new();
         let events_active = AtomicRefCell::new(events_active);
 
         let (events_sender, events_receiver) = watch::channel(Events::new());
@@ -70,17 +125,76 @@ impl<'a> Manager<'a> {
         self.events_receiver.clone()
     }
 
+    fn regions_parse(
+        data: Option<&serde_json::Value>,
+        key: &str,
+    ) -> Result<Box<[usize]>, Error> {
+        let regions = match data.and_then(|data| data.get(key)) {
+            Some(regions) => regions,
+            None => return Ok(Box::new([])),
+        };
+
+        let regions = serde_json::from_value::<Box<[usize]>>(regions.clone())
+            .with_context(|| format!("from_value {}", key))?;
+
+        Ok(regions)
+    }
+    fn ivs_parse(data: Option<&serde_json::Value>) -> Result<Event, Error> {
+        let data = data.ok_or_else(|| anyhow!("missing ivs data"))?;
+
+        let rule = data
+            .get("Name")
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| anyhow!("missing ivs rule name"))?
+            .to_owned();
+
+        let object_type = data
+            .get("Object")
+            .and_then(|object| object.get("ObjectType"))
+            .and_then(|object_type| object_type.as_str())
+            .map_or(ObjectType::Other, ObjectType::parse);
+
+        Ok(Event::Ivs { rule, object_type })
+    }
+
     fn event_parse(
         code: &str,
-        _data: Option<serde_json::Value>,
-    ) -> Result<Option<Event>, Error> {
+        data: Option<serde_json::Value>,
+    ) -> Result<Option<Box<[Event]>>, Error> {
+        let data = data.as_ref();
+
+        // parent event goes first, followed by its regions
+        let with_regions = |event: Event,
+                            key: &str,
+                            region_event: fn(usize) -> Event|
+         -> Result<Option<Box<[Event]>>, Error> {
+            let regions = Self::regions_parse(data, key).context("regions_parse")?;
+            let events = [event]
+                .into_iter()
+                .chain(regions.iter().copied().map(region_event))
+                .collect::<Box<[_]>>();
+            Ok(Some(events))
+        };
+
         match code {
-            "VideoBlind" => Ok(Some(Event::VideoBlind)),
-            "AudioMutation" => Ok(Some(Event::AudioMutation)),
-            "SceneChange" => Ok(Some(Event::SceneChange)),
-            "VideoMotion" => Ok(Some(Event::VideoMotion)),
-            "SmartMotionHuman" => Ok(Some(Event::SmartMotionHuman)),
-            "SmartMotionVehicle" => Ok(Some(Event::SmartMotionVehicle)),
+            "VideoBlind" => Ok(Some(Box::new([Event::VideoBlind]))),
+            "AudioMutation" => Ok(Some(Box::new([Event::AudioMutation]))),
+            "SceneChange" => Ok(Some(Box::new([Event::SceneChange]))),
+            "VideoMotion" => with_regions(Event::VideoMotion, "Id", Event::VideoMotionRegion),
+            "SmartMotionHuman" => with_regions(
+                Event::SmartMotionHuman,
+                "WindowId",
+                Event::SmartMotionHumanRegion,
+            ),
+            "SmartMotionVehicle" => with_regions(
+                Event::SmartMotionVehicle,
+                "WindowId",
+                Event::SmartMotionVehicleRegion,
+            ),
+            "CrossLineDetection" | "CrossRegionDetection" => {
+                let event = Self::ivs_parse(data).context("ivs_parse")?;
+                Ok(Some(Box::new([event])))
+            }
             code => {
                 log::debug!("unrecognized event: {}", code);
                 Ok(None)
@@ -110,18 +224,19 @@ impl<'a> Manager<'a> {
             None => None,
         };
 
-        let event = match Self::event_parse(code, data).context("event_parse")? {
-            Some(event) => event,
+        let events = match Self::event_parse(code, data).context("event_parse")? {
+            Some(events) => events,
             None => return Ok(None),
         };
 
-        let active = match captures.get(2).unwrap().as_str() {
-            "Start" => true,
-            "Stop" => false,
+        let action = match captures.get(2).unwrap().as_str() {
+            "Start" => Action::Start,
+            "Stop" => Action::Stop,
+            "Pulse" => Action::Pulse,
             other => bail!("unrecognized action: {}", other),
         };
 
-        Ok(Some(EventStateUpdate { event, active }))
+        Ok(Some(EventStateUpdate { events, action }))
     }
 
     fn event_state_update_handle(
@@ -133,30 +248,67 @@ impl<'a> Manager<'a> {
 
         let mut changed = false;
 
-        if event_state_update.active {
-            match events_active.insert(event_state_update.event, event_time) {
-                None => {
-                    changed = true;
-                }
-                Some(previous) => {
-                    log::warn!(
-                        "adding already added event: {:?} ({:?})",
-                        previous,
-                        events_active
-                    );
+        match event_state_update.action {
+            Action::Start => {
+                for event in event_state_update.events.into_vec() {
+                    let event_active = EventActive {
+                        started: event_time,
+                        expires: None,
+                    };
+                    match events_active.insert(event, event_active) {
+                        None => {
+                            changed = true;
+                        }
+                        Some(previous) => {
+                            log::warn!(
+                                "adding already added event: {:?} ({:?})",
+                                previous,
+                                events_active
+                            );
+                        }
+                    }
                 }
             }
-        } else {
-            match events_active.remove(&event_state_update.event) {
-                Some(_) => {
-                    changed = true;
+            Action::Stop => {
+                for event in event_state_update.events.iter() {
+                    // regions of stop events are not reliable, see top of file
+                    if event.parent().is_some() {
+                        continue;
+                    }
+
+                    match events_active.remove(event) {
+                        Some(_) => {
+                            changed = true;
+                        }
+                        None => {
+                            log::warn!(
+                                "removing not added element: {:?} ({:?})",
+                                event,
+                                events_active
+                            );
+                        }
+                    }
+
+                    events_active.retain(|event_active, _| {
+                        if event_active.parent().as_ref() == Some(event) {
+                            changed = true;
+                            false
+                        } else {
+                            true
+                        }
+                    });
                 }
-                None => {
-                    log::warn!(
-                        "removing not added element: {:?} ({:?})",
-                        event_state_update.event,
-                        events_active
-                    );
+            }
+            Action::Pulse => {
+                for event in event_state_update.events.into_vec() {
+                    let event_active = EventActive {
+                        started: event_time,
+                        expires: Some(event_time + Self::EVENT_PULSE_DURATION),
+                    };
+                    // repeated pulse only extends the event
+                    if events_active.insert(event, event_active).is_none() {
+                        changed = true;
+                    }
                 }
             }
         }
@@ -171,12 +323,14 @@ impl<'a> Manager<'a> {
 
         self.events_active
             .borrow_mut()
-            .extract_if(|event, started| {
-                if *started < fix_before {
+            .extract_if(|event, event_active| {
+                if event_active.started < fix_before {
                     log::warn!("removing outdated events: {:?}", event);
-                    true
-                } else {
-                    false
+                    return true;
+                }
+                match event_active.expires {
+                    Some(expires) => expires <= now,
+                    None => false,
                 }
             })
             .count()
@@ -251,8 +405,10 @@ impl<'a> Manager<'a> {
 }
 #[cfg(test)]
 mod tests_manager {
-    use super::{Event, EventStateUpdate, Manager};
+    use super::{Action, Event, EventStateUpdate, Manager, ObjectType};
+    use crate::devices::dahua::ipc_a::hardware::api::Api;
     use indoc::indoc;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn unsupported() {
@@ -277,8 +433,8 @@ mod tests_manager {
             Manager::event_state_update_parse("Code=AudioMutation;action=Stop;index=0").unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::AudioMutation,
-            active: false,
+            events: Box::new([Event::AudioMutation]),
+            action: Action::Stop,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
@@ -299,8 +455,8 @@ mod tests_manager {
         let event_state_update = Manager::event_state_update_parse(event).unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::VideoMotion,
-            active: true,
+            events: Box::new([Event::VideoMotion, Event::VideoMotionRegion(1)]),
+            action: Action::Start,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
@@ -319,8 +475,8 @@ mod tests_manager {
         let event_state_update = Manager::event_state_update_parse(event).unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::VideoMotion,
-            active: true,
+            events: Box::new([Event::VideoMotion]),
+            action: Action::Start,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
@@ -346,8 +502,8 @@ mod tests_manager {
         let event_state_update = Manager::event_state_update_parse(event).unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::SmartMotionHuman,
-            active: true,
+            events: Box::new([Event::SmartMotionHuman, Event::SmartMotionHumanRegion(1)]),
+            action: Action::Start,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
@@ -373,8 +529,11 @@ mod tests_manager {
         let event_state_update = Manager::event_state_update_parse(event).unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::SmartMotionVehicle,
-            active: false,
+            events: Box::new([
+                Event::SmartMotionVehicle,
+                Event::SmartMotionVehicleRegion(0),
+            ]),
+            action: Action::Stop,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
@@ -395,10 +554,80 @@ mod tests_manager {
         let event_state_update = Manager::event_state_update_parse(event).unwrap();
 
         let event_state_update_expected = EventStateUpdate {
-            event: Event::VideoMotion,
-            active: false,
+            events: Box::new([
+                Event::VideoMotion,
+                Event::VideoMotionRegion(0),
+                Event::VideoMotionRegion(1),
+            ]),
+            action: Action::Stop,
+        };
+
+        assert_eq!(event_state_update, Some(event_state_update_expected));
+    }
+
+    #[test]
+    fn cross_line_detection() {
+        let event = indoc!(
+            r#"
+            Code=CrossLineDetection;action=Pulse;index=0;data={
+                "Class" : "Normal",
+                "Direction" : "LeftToRight",
+                "Name" : "Gate",
+                "Object" : {
+                    "ObjectID" : 372,
+                    "ObjectType" : "Human"
+                },
+                "RuleId" : 1
+            }
+            "#
+        );
+
+        let event_state_update = Manager::event_state_update_parse(event).unwrap();
+
+        let event_state_update_expected = EventStateUpdate {
+            events: Box::new([Event::Ivs {
+                rule: "Gate".to_owned(),
+                object_type: ObjectType::Human,
+            }]),
+            action: Action::Pulse,
         };
 
         assert_eq!(event_state_update, Some(event_state_update_expected));
     }
+
+    #[test]
+    fn regions_cleared_with_parent() {
+        let api = Api::new("127.0.0.1".parse().unwrap(), String::new());
+        let manager = Manager::new(&api);
+        let now = Instant::now();
+
+        assert!(manager.event_state_update_handle(
+            now,
+            EventStateUpdate {
+                events: Box::new([Event::VideoMotion, Event::VideoMotionRegion(0)]),
+                action: Action::Start,
+            },
+        ));
+        // camera reports different region on stop
+        assert!(manager.event_state_update_handle(
+            now,
+            EventStateUpdate {
+                events: Box::new([Event::VideoMotion, Event::VideoMotionRegion(1)]),
+                action: Action::Stop,
+            },
+        ));
+        assert!(manager.events_active.borrow().is_empty());
+
+        // pulses expire on their own
+        assert!(manager.event_state_update_handle(
+            now,
+            EventStateUpdate {
+                events: Box::new([Event::SceneChange]),
+                action: Action::Pulse,
+            },
+        ));
+        assert!(!manager.events_fixer_handle(now + Duration::from_secs(1)));
+        assert!(manager.events_fixer_handle(now + Manager::EVENT_PULSE_DURATION));
+        assert!(manager.events_active.borrow().is_empty());
+    }
 }
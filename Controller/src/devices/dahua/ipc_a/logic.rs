@@ -2,14 +2,20 @@ use super::hardware::{api, configurator, event_stream};
 use crate::{
     datatypes::ipc_rtsp_url::IpcRtspUrl,
     devices::{
-        self,
-        soft::surveillance::snapshot::logic_device_inner::{
-            Manager as SnapshotManager, Runner as SnapshotRunner,
+        self, camera,
+        soft::surveillance::snapshot::{
+            attachment,
+            logic_device_inner::{Manager as SnapshotManager, Runner as SnapshotRunner},
         },
     },
+    modules::{
+        events,
+        secrets::{SecretReference, Secrets},
+    },
     signals::{self, signal},
     util::{
         async_flag,
+        circuit_breaker::{self, CircuitBreaker},
         runnable::{Exited, Runnable},
     },
     web::{self, uri_cursor},
@@ -23,10 +29,11 @@ use futures::{
     stream::StreamExt,
 };
 use http::uri::Authority;
+use image::DynamicImage;
 use maplit::hashmap;
 use parking_lot::RwLock;
 use serde::Serialize;
-use std::{borrow::Cow, time::Duration};
+use std::{borrow::Cow, iter, time::Duration};
 
 // TODO: get actual event stream count from the camera
 
@@ -34,17 +41,32 @@ use std::{borrow::Cow, time::Duration};
 pub enum ConfigurationHardware {
     Full {
         hardware_configuration: configurator::Configuration,
+        shared_user_password: SecretReference,
     },
     Skip {
         shared_user_login: String,
-        shared_user_password: String,
+        shared_user_password: SecretReference,
     },
 }
+// reported to events when alarm signal is triggered
+#[derive(Debug)]
+pub struct Notification {
+    pub severity: events::Severity,
+    pub message: String,
+    // fresh snapshot is attached, if camera is reachable
+    pub attachment: attachment::Configuration,
+}
+
 #[derive(Debug)]
 pub struct Configuration {
     pub host: Authority,
-    pub admin_password: String,
+    pub admin_password: SecretReference,
     pub hardware: ConfigurationHardware,
+    pub notification: Option<Notification>,
+    // number of motion detection regions, signals are created for each
+    pub regions_count: usize,
+    // names of ivs rules, as configured in camera
+    pub ivs_rules: Box<[String]>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -55,6 +77,18 @@ pub struct RtspUrls {
 }
 
 #[derive(Clone, Copy, Default, Debug, Serialize)]
+pub struct EventsIvsRule {
+    human: bool,
+    vehicle: bool,
+    other: bool,
+}
+impl EventsIvsRule {
+    pub fn any(&self) -> bool {
+        self.human || self.vehicle || self.other
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Events {
     video_blind: bool,
     scene_change: bool,
@@ -62,9 +96,18 @@ pub struct Events {
     audio_mutation: bool,
     smart_motion_human: bool,
     smart_motion_vehicle: bool,
+
+    video_motion_regions: Box<[bool]>,
+    smart_motion_human_regions: Box<[bool]>,
+    smart_motion_vehicle_regions: Box<[bool]>,
+    ivs_rules: Box<[EventsIvsRule]>,
 }
 impl Events {
-    pub fn from_event_stream_events(hardware_events: &event_stream::Events) -> Self {
+    pub fn from_event_stream_events(
+        hardware_events: &event_stream::Events,
+        regions_count: usize,
+        ivs_rules: &[String],
+    ) -> Self {
         let mut video_blind: bool = false;
         let mut scene_change: bool = false;
         let mut video_motion: bool = false;
@@ -72,6 +115,19 @@ impl Events {
         let mut smart_motion_human: bool = false;
         let mut smart_motion_vehicle: bool = false;
 
+        let mut video_motion_regions = vec![false; regions_count].into_boxed_slice();
+        let mut smart_motion_human_regions = vec![false; regions_count].into_boxed_slice();
+        let mut smart_motion_vehicle_regions = vec![false; regions_count].into_boxed_slice();
+        let mut ivs_rules_events =
+            vec![EventsIvsRule::default(); ivs_rules.len()].into_boxed_slice();
+
+        // events for regions and rules not in configuration are ignored
+        let region_set = |regions: &mut [bool], region: usize| {
+            if let Some(value) = regions.get_mut(region) {
+                *value = true;
+            }
+        };
+
         hardware_events.iter().for_each(|event| match event {
             event_stream::Event::VideoBlind => {
                 video_blind = true;
@@ -91,6 +147,26 @@ impl Events {
             event_stream::Event::SmartMotionVehicle => {
                 smart_motion_vehicle = true;
             }
+            event_stream::Event::VideoMotionRegion(region) => {
+                region_set(&mut video_motion_regions, *region);
+            }
+            event_stream::Event::SmartMotionHumanRegion(region) => {
+                region_set(&mut smart_motion_human_regions, *region);
+            }
+            event_stream::Event::SmartMotionVehicleRegion(region) => {
+                region_set(&mut smart_motion_vehicle_regions, *region);
+            }
+            event_stream::Event::Ivs { rule, object_type } => {
+                let ivs_rule_events = match ivs_rules.iter().position(|ivs_rule| ivs_rule == rule) {
+                    Some(ivs_rule_index) => &mut ivs_rules_events[ivs_rule_index],
+                    None => return,
+                };
+                match object_type {
+                    event_stream::ObjectType::Human => ivs_rule_events.human = true,
+                    event_stream::ObjectType::Vehicle => ivs_rule_events.vehicle = true,
+                    event_stream::ObjectType::Other => ivs_rule_events.other = true,
+                }
+            }
         });
 
         Self {
@@ -100,6 +176,11 @@ impl Events {
             audio_mutation,
             smart_motion_human,
             smart_motion_vehicle,
+
+            video_motion_regions,
+            smart_motion_human_regions,
+            smart_motion_vehicle_regions,
+            ivs_rules: ivs_rules_events,
         }
     }
 }
@@ -117,13 +198,17 @@ pub enum DeviceState {
 }
 
 #[derive(Debug)]
-pub struct Device {
+pub struct Device<'s> {
     configuration: Configuration,
+    secrets: &'s Secrets<'s>,
 
     device_state: RwLock<DeviceState>,
+    circuit_breaker: CircuitBreaker,
     snapshot_manager: SnapshotManager,
 
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
     signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_alarm: signal::event_target_queued::Signal<()>,
     signal_rtsp_url_main: signal::state_source::Signal<IpcRtspUrl>,
     signal_rtsp_url_sub1: signal::state_source::Signal<IpcRtspUrl>,
     signal_rtsp_url_sub2: signal::state_source::Signal<IpcRtspUrl>,
@@ -133,18 +218,49 @@ pub struct Device {
     signal_event_audio_mutation: signal::state_source::Signal<bool>,
     signal_event_smart_motion_human: signal::state_source::Signal<bool>,
     signal_event_smart_motion_vehicle: signal::state_source::Signal<bool>,
+    signal_event_video_motion_regions: Box<[signal::state_source::Signal<bool>]>,
+    signal_event_smart_motion_human_regions: Box<[signal::state_source::Signal<bool>]>,
+    signal_event_smart_motion_vehicle_regions: Box<[signal::state_source::Signal<bool>]>,
+    signal_event_ivs_rules: Box<[signal::state_source::Signal<bool>]>,
+    signal_event_ivs_rules_human: Box<[signal::state_source::Signal<bool>]>,
+    signal_event_ivs_rules_vehicle: Box<[signal::state_source::Signal<bool>]>,
 
     gui_summary_waker: devices::gui_summary::Waker,
+    camera_waker: camera::Waker,
 }
-impl Device {
-    pub fn new(configuration: Configuration) -> Self {
+impl<'s> Device<'s> {
+    pub fn new(
+        configuration: Configuration,
+        secrets: &'s Secrets<'s>,
+    ) -> Self {
+        let circuit_breaker = CircuitBreaker::new(
+            format!("dahua/ipc_a/{}", configuration.host),
+            circuit_breaker::Configuration::default(),
+        );
+
+        let signals_bool = |count: usize| {
+            (0..count)
+                .map(|_| signal::state_source::Signal::<bool>::new(None))
+                .collect::<Box<[_]>>()
+        };
+        let signal_event_video_motion_regions = signals_bool(configuration.regions_count);
+        let signal_event_smart_motion_human_regions = signals_bool(configuration.regions_count);
+        let signal_event_smart_motion_vehicle_regions = signals_bool(configuration.regions_count);
+        let signal_event_ivs_rules = signals_bool(configuration.ivs_rules.len());
+        let signal_event_ivs_rules_human = signals_bool(configuration.ivs_rules.len());
+        let signal_event_ivs_rules_vehicle = signals_bool(configuration.ivs_rules.len());
+
         Self {
             configuration,
+            secrets,
 
             device_state: RwLock::new(DeviceState::Initializing),
+            circuit_breaker,
             snapshot_manager: SnapshotManager::new(),
 
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
             signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_alarm: signal::event_target_queued::Signal::<()>::new(),
             signal_rtsp_url_main: signal::state_source::Signal::<IpcRtspUrl>::new(None),
             signal_rtsp_url_sub1: signal::state_source::Signal::<IpcRtspUrl>::new(None),
             signal_rtsp_url_sub2: signal::state_source::Signal::<IpcRtspUrl>::new(None),
@@ -154,8 +270,15 @@ impl Device {
             signal_event_audio_mutation: signal::state_source::Signal::<bool>::new(None),
             signal_event_smart_motion_human: signal::state_source::Signal::<bool>::new(None),
             signal_event_smart_motion_vehicle: signal::state_source::Signal::<bool>::new(None),
+            signal_event_video_motion_regions,
+            signal_event_smart_motion_human_regions,
+            signal_event_smart_motion_vehicle_regions,
+            signal_event_ivs_rules,
+            signal_event_ivs_rules_human,
+            signal_event_ivs_rules_vehicle,
 
             gui_summary_waker: devices::gui_summary::Waker::new(),
+            camera_waker: camera::Waker::new(),
         }
     }
 
@@ -169,6 +292,7 @@ impl Device {
             _ => panic!("snapshot_updated_handle can be called only when device is running"),
         }
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
     }
     fn events_handle(
         &self,
@@ -178,10 +302,11 @@ impl Device {
             DeviceState::Running {
                 events: state_events,
                 ..
-            } => *state_events = events,
+            } => *state_events = events.clone(),
             _ => panic!("events_handle can be called only when device is running"),
         }
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         let mut signals_changed = false;
         signals_changed |= self
@@ -202,14 +327,65 @@ impl Device {
         signals_changed |= self
             .signal_event_smart_motion_vehicle
             .set_one(Some(events.smart_motion_vehicle));
+        for (signals, values) in [
+            (
+                &self.signal_event_video_motion_regions,
+                &events.video_motion_regions,
+            ),
+            (
+                &self.signal_event_smart_motion_human_regions,
+                &events.smart_motion_human_regions,
+            ),
+            (
+                &self.signal_event_smart_motion_vehicle_regions,
+                &events.smart_motion_vehicle_regions,
+            ),
+        ] {
+            for (signal, value) in signals.iter().zip(values.iter()) {
+                signals_changed |= signal.set_one(Some(*value));
+            }
+        }
+        for (ivs_rule_index, ivs_rule) in events.ivs_rules.iter().enumerate() {
+            signals_changed |=
+                self.signal_event_ivs_rules[ivs_rule_index].set_one(Some(ivs_rule.any()));
+            signals_changed |=
+                self.signal_event_ivs_rules_human[ivs_rule_index].set_one(Some(ivs_rule.human));
+            signals_changed |=
+                self.signal_event_ivs_rules_vehicle[ivs_rule_index].set_one(Some(ivs_rule.vehicle));
+        }
         if signals_changed {
             self.signals_sources_changed_waker.wake();
         }
     }
 
+    fn signals_event_indexed(&self) -> impl Iterator<Item = &signal::state_source::Signal<bool>> {
+        iter::empty()
+            .chain(self.signal_event_video_motion_regions.iter())
+            .chain(self.signal_event_smart_motion_human_regions.iter())
+            .chain(self.signal_event_smart_motion_vehicle_regions.iter())
+            .chain(self.signal_event_ivs_rules.iter())
+            .chain(self.signal_event_ivs_rules_human.iter())
+            .chain(self.signal_event_ivs_rules_vehicle.iter())
+    }
+
+    // password is fetched from secrets store for every connection and snapshot,
+    // so changes are picked up without restart
+    async fn api_build(&self) -> Result<api::Api, Error> {
+        let admin_password = self
+            .secrets
+            .get(&self.configuration.admin_password)
+            .await
+            .context("get")?;
+        Ok(api::Api::new(
+            self.configuration.host.clone(),
+            admin_password.expose().to_owned(),
+        ))
+    }
+
     fn failed(&self) {
         *self.device_state.write() = DeviceState::Error;
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         self.snapshot_manager.image_unset();
 
@@ -222,6 +398,9 @@ impl Device {
         let _ = self.signal_event_audio_mutation.set_one(None);
         let _ = self.signal_event_smart_motion_human.set_one(None);
         let _ = self.signal_event_smart_motion_vehicle.set_one(None);
+        self.signals_event_indexed().for_each(|signal| {
+            let _ = signal.set_one(None);
+        });
         self.signals_sources_changed_waker.wake();
     }
 
@@ -229,29 +408,38 @@ impl Device {
     async fn run_once(&self) -> Result<!, Error> {
         *self.device_state.write() = DeviceState::Initializing;
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         // api
-        let api = api::Api::new(
-            self.configuration.host.clone(),
-            self.configuration.admin_password.clone(),
-        );
+        let api = self.api_build().await.context("api_build")?;
 
         // configuration & watcher credentials
         let (shared_user_login, shared_user_password) = match &self.configuration.hardware {
             ConfigurationHardware::Full {
                 hardware_configuration,
+                shared_user_password,
             } => {
+                let shared_user_password = self
+                    .secrets
+                    .get(shared_user_password)
+                    .await
+                    .context("shared_user_password get")?;
+
                 let mut configurator = configurator::Configurator::connect(&api)
                     .await
                     .context("connect")?;
                 configurator
-                    .configure(true, hardware_configuration.clone())
+                    .configure(
+                        true,
+                        hardware_configuration.clone(),
+                        shared_user_password.expose().to_owned(),
+                    )
                     .await
                     .context("configure")?;
 
                 (
                     configurator::Configurator::SHARED_USER_LOGIN,
-                    &hardware_configuration.shared_user_password,
+                    shared_user_password,
                 )
             }
             ConfigurationHardware::Skip {
@@ -263,6 +451,12 @@ impl Device {
                     .validate_basic_device_info()
                     .await
                     .context("validate_basic_device_info")?;
+                let shared_user_password = self
+                    .secrets
+                    .get(shared_user_password)
+                    .await
+                    .context("shared_user_password get")?;
+
                 (shared_user_login.as_str(), shared_user_password)
             }
         };
@@ -270,17 +464,17 @@ impl Device {
         let rtsp_urls = RtspUrls {
             main: IpcRtspUrl(api.rtsp_url_build(
                 shared_user_login,
-                shared_user_password,
+                shared_user_password.expose(),
                 api::VideoStream::Main,
             )),
             sub1: IpcRtspUrl(api.rtsp_url_build(
                 shared_user_login,
-                shared_user_password,
+                shared_user_password.expose(),
                 api::VideoStream::Sub1,
             )),
             sub2: IpcRtspUrl(api.rtsp_url_build(
                 shared_user_login,
-                shared_user_password,
+                shared_user_password.expose(),
                 api::VideoStream::Sub2,
             )),
         };
@@ -292,7 +486,11 @@ impl Device {
             events_stream_manager.receiver(),
         )
         .for_each(async |hardware_events| {
-            let events = Events::from_event_stream_events(&hardware_events);
+            let events = Events::from_event_stream_events(
+                &hardware_events,
+                self.configuration.regions_count,
+                &self.configuration.ivs_rules,
+            );
             self.events_handle(events);
         });
         pin_mut!(events_stream_manager_receiver_runner);
@@ -314,13 +512,22 @@ impl Device {
         pin_mut!(snapshot_runner_runner);
         let mut snapshot_runner_runner = snapshot_runner_runner.fuse();
 
+        if self.circuit_breaker.success() {
+            log::info!("device {} recovered", self.configuration.host);
+        }
+
         // device is ready
         *self.device_state.write() = DeviceState::Running {
             snapshot_updated: None,
             rtsp_urls: rtsp_urls.clone(),
-            events: Events::default(),
+            events: Events::from_event_stream_events(
+                &event_stream::Events::new(),
+                self.configuration.regions_count,
+                &self.configuration.ivs_rules,
+            ),
         };
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         // signal values
         let _ = self.signal_rtsp_url_main.set_one(Some(rtsp_urls.main));
@@ -339,16 +546,71 @@ impl Device {
     const ERROR_RESTART_INTERVAL: Duration = Duration::from_secs(10);
     async fn run(&self) -> ! {
         loop {
+            self.circuit_breaker.ready().await;
+
             let error = self.run_once().await.context("run_once");
             self.failed();
 
-            log::error!("device {} failed: {:?}", self.configuration.host, error);
+            // repeated failures of unreachable device are not reported
+            if self.circuit_breaker.failure() {
+                log::error!("device {} failed: {:?}", self.configuration.host, error);
+                events::reporter().report(
+                    events::Severity::Error,
+                    format!("dahua/ipc_a/{}", self.configuration.host),
+                    format!("device failed: {:#}", error.as_ref().unwrap_err()),
+                );
+            }
             tokio::time::sleep(Self::ERROR_RESTART_INTERVAL).await;
         }
     }
 }
 
-impl devices::Device for Device {
+impl<'s> Device<'s> {
+    async fn alarm_report(
+        &self,
+        notification: &Notification,
+    ) {
+        let attachment = camera::snapshot_attachment(self, &notification.attachment).await;
+        let attachment = match attachment {
+            Ok(attachment) => Some(attachment),
+            Err(error) => {
+                log::warn!(
+                    "device {} alarm snapshot failed: {:?}",
+                    self.configuration.host,
+                    error
+                );
+                None
+            }
+        };
+
+        events::reporter().report_with_attachment(
+            notification.severity,
+            format!("dahua/ipc_a/{}", self.configuration.host),
+            notification.message.clone(),
+            attachment,
+        );
+    }
+
+    // independent of device state, so alarms are reported with or without
+    // snapshot
+    async fn alarms_run(&self) -> ! {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+        loop {
+            signals_targets_changed_stream.select_next_some().await;
+
+            if self.signal_alarm.take_pending().is_empty() {
+                continue;
+            }
+            let notification = match &self.configuration.notification {
+                Some(notification) => notification,
+                None => continue,
+            };
+            self.alarm_report(notification).await;
+        }
+    }
+}
+
+impl<'s> devices::Device for Device<'s> {
     fn class(&self) -> Cow<'static, str> {
         Cow::from("dahua/ipc_a")
     }
@@ -365,10 +627,13 @@ impl devices::Device for Device {
     fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
         Some(self)
     }
+    fn as_camera(&self) -> Option<&dyn camera::Camera> {
+        Some(self)
+    }
 }
 
 #[async_trait]
-impl Runnable for Device {
+impl<'s> Runnable for Device<'s> {
     async fn run(
         &self,
         mut exit_flag: async_flag::Receiver,
@@ -377,8 +642,13 @@ impl Runnable for Device {
         pin_mut!(runner);
         let mut runner = runner.fuse();
 
+        let alarms_runner = self.alarms_run();
+        pin_mut!(alarms_runner);
+        let mut alarms_runner = alarms_runner.fuse();
+
         select! {
             _ = runner => panic!("runner yielded"),
+            _ = alarms_runner => panic!("alarms_runner yielded"),
             () = exit_flag => {},
         }
 
@@ -398,11 +668,32 @@ pub enum SignalIdentifier {
     EventAudioMutation,
     EventSmartMotionHuman,
     EventSmartMotionVehicle,
+
+    EventVideoMotionRegion(usize),        // region index in camera
+    EventSmartMotionHumanRegion(usize),   // region index in camera
+    EventSmartMotionVehicleRegion(usize), // region index in camera
+
+    EventIvsRule(usize),        // index in configuration.ivs_rules
+    EventIvsRuleHuman(usize),   // index in configuration.ivs_rules
+    EventIvsRuleVehicle(usize), // index in configuration.ivs_rules
+
+    Alarm,
 }
 impl signals::Identifier for SignalIdentifier {}
-impl signals::Device for Device {
+impl<'s> Device<'s> {
+    fn signals_indexed(
+        signals: &[signal::state_source::Signal<bool>],
+        identifier: fn(usize) -> SignalIdentifier,
+    ) -> impl Iterator<Item = (SignalIdentifier, &dyn signal::Base)> {
+        signals
+            .iter()
+            .enumerate()
+            .map(move |(index, signal)| (identifier(index), signal as &dyn signal::Base))
+    }
+}
+impl<'s> signals::Device for Device<'s> {
     fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
-        None
+        Some(&self.signals_targets_changed_waker)
     }
     fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
         Some(&self.signals_sources_changed_waker)
@@ -410,7 +701,7 @@ impl signals::Device for Device {
 
     type Identifier = SignalIdentifier;
     fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
-        hashmap! {
+        let by_identifier = hashmap! {
             SignalIdentifier::RtspUrlMain => &self.signal_rtsp_url_main as &dyn signal::Base,
             SignalIdentifier::RtspUrlSub1 => &self.signal_rtsp_url_sub1 as &dyn signal::Base,
             SignalIdentifier::RtspUrlSub2 => &self.signal_rtsp_url_sub2 as &dyn signal::Base,
@@ -421,11 +712,41 @@ impl signals::Device for Device {
             SignalIdentifier::EventAudioMutation => &self.signal_event_audio_mutation as &dyn signal::Base,
             SignalIdentifier::EventSmartMotionHuman => &self.signal_event_smart_motion_human as &dyn signal::Base,
             SignalIdentifier::EventSmartMotionVehicle => &self.signal_event_smart_motion_vehicle as &dyn signal::Base,
-        }
+
+            SignalIdentifier::Alarm => &self.signal_alarm as &dyn signal::Base,
+        };
+
+        by_identifier
+            .into_iter()
+            .chain(Self::signals_indexed(
+                &self.signal_event_video_motion_regions,
+                SignalIdentifier::EventVideoMotionRegion,
+            ))
+            .chain(Self::signals_indexed(
+                &self.signal_event_smart_motion_human_regions,
+                SignalIdentifier::EventSmartMotionHumanRegion,
+            ))
+            .chain(Self::signals_indexed(
+                &self.signal_event_smart_motion_vehicle_regions,
+                SignalIdentifier::EventSmartMotionVehicleRegion,
+            ))
+            .chain(Self::signals_indexed(
+                &self.signal_event_ivs_rules,
+                SignalIdentifier::EventIvsRule,
+            ))
+            .chain(Self::signals_indexed(
+                &self.signal_event_ivs_rules_human,
+                SignalIdentifier::EventIvsRuleHuman,
+            ))
+            .chain(Self::signals_indexed(
+                &self.signal_event_ivs_rules_vehicle,
+                SignalIdentifier::EventIvsRuleVehicle,
+            ))
+            .collect::<signals::ByIdentifier<_>>()
     }
 }
 
-impl devices::gui_summary::Device for Device {
+impl<'s> devices::gui_summary::Device for Device<'s> {
     fn waker(&self) -> &devices::gui_summary::Waker {
         &self.gui_summary_waker
     }
@@ -436,7 +757,79 @@ impl devices::gui_summary::Device for Device {
     }
 }
 
-impl uri_cursor::Handler for Device {
+impl<'s> camera::Camera for Device<'s> {
+    fn waker(&self) -> &camera::Waker {
+        &self.camera_waker
+    }
+
+    fn health(&self) -> camera::Health {
+        match &*self.device_state.read() {
+            DeviceState::Initializing => camera::Health::Initializing,
+            DeviceState::Running { .. } => camera::Health::Running,
+            DeviceState::Error => camera::Health::Error,
+        }
+    }
+    fn rtsp_urls(&self) -> Box<[camera::RtspUrl]> {
+        match &*self.device_state.read() {
+            DeviceState::Running { rtsp_urls, .. } => Box::new([
+                camera::RtspUrl {
+                    name: "main",
+                    url: rtsp_urls.main.clone(),
+                },
+                camera::RtspUrl {
+                    name: "sub1",
+                    url: rtsp_urls.sub1.clone(),
+                },
+                camera::RtspUrl {
+                    name: "sub2",
+                    url: rtsp_urls.sub2.clone(),
+                },
+            ]),
+            _ => Box::new([]),
+        }
+    }
+    fn events(&self) -> Box<[camera::Event]> {
+        match &*self.device_state.read() {
+            DeviceState::Running { events, .. } => Box::new([
+                camera::Event {
+                    name: "video_blind",
+                    active: events.video_blind,
+                },
+                camera::Event {
+                    name: "scene_change",
+                    active: events.scene_change,
+                },
+                camera::Event {
+                    name: "video_motion",
+                    active: events.video_motion,
+                },
+                camera::Event {
+                    name: "audio_mutation",
+                    active: events.audio_mutation,
+                },
+                camera::Event {
+                    name: "smart_motion_human",
+                    active: events.smart_motion_human,
+                },
+                camera::Event {
+                    name: "smart_motion_vehicle",
+                    active: events.smart_motion_vehicle,
+                },
+            ]),
+            _ => Box::new([]),
+        }
+    }
+
+    fn snapshot(&self) -> BoxFuture<'_, Result<DynamicImage, Error>> {
+        async {
+            let api = self.api_build().await.context("api_build")?;
+            api.snapshot().await.context("snapshot")
+        }
+        .boxed()
+    }
+}
+
+impl<'s> uri_cursor::Handler for Device<'s> {
     fn handle(
         &self,
         request: web::Request,
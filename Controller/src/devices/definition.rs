@@ -0,0 +1,451 @@
+use super::{
+    configuration_history::{ConfigurationHistory, Snapshot, SnapshotConnection, SnapshotDevice},
+    DeviceWrapper,
+};
+use crate::{
+    devices::Id as DeviceId,
+    gui::dashboards::Dashboard,
+    signals::signal::RemoteBaseVariant,
+    web::{self, uri_cursor},
+};
+use anyhow::{bail, ensure, Context, Error};
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SignalKind {
+    StateSource,
+    StateTarget,
+    EventSource,
+    EventTarget,
+}
+
+// what is needed to tell whether imported connection can be made
+#[derive(Clone, Debug)]
+struct SignalDefinition {
+    kind: SignalKind,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+// declarative state of the controller as single document, for comparing
+// deployments (eg. staging vs production) and keeping it next to the sources
+// devices and dashboards are built in code, so import must match them exactly,
+// imported connections replace ones from code on next start (see
+// configuration_history)
+#[derive(Debug)]
+pub struct Definition<'a> {
+    snapshot: Snapshot,
+    signals: HashMap<DeviceId, HashMap<String, SignalDefinition>>,
+    dashboards: &'a Dashboard,
+    configuration_history: Option<&'a ConfigurationHistory<'a>>,
+}
+impl<'a> Definition<'a> {
+    pub const ROUTE: &'static str = "definition";
+    const FORMAT_VERSION: u32 = 1;
+
+    pub fn new(
+        snapshot: Snapshot,
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+        dashboards: &'a Dashboard,
+        configuration_history: Option<&'a ConfigurationHistory<'a>>,
+    ) -> Self {
+        let signals = device_wrappers_by_id
+            .iter()
+            .map(|(device_id, device_wrapper)| {
+                let signals = device_wrapper
+                    .device()
+                    .as_signals_device_base()
+                    .by_identifier()
+                    .into_iter()
+                    .map(|(signal_identifier_base_wrapper, signal)| {
+                        let remote_base = signal.as_remote_base();
+                        let kind = match remote_base.as_remote_base_variant() {
+                            RemoteBaseVariant::StateSource(_) => SignalKind::StateSource,
+                            RemoteBaseVariant::StateTarget(_) => SignalKind::StateTarget,
+                            RemoteBaseVariant::EventSource(_) => SignalKind::EventSource,
+                            RemoteBaseVariant::EventTarget(_) => SignalKind::EventTarget,
+                        };
+                        let signal_definition = SignalDefinition {
+                            kind,
+                            type_id: remote_base.type_id(),
+                            type_name: remote_base.type_name(),
+                        };
+                        (signal_identifier_base_wrapper.name(), signal_definition)
+                    })
+                    .collect::<HashMap<_, _>>();
+                (*device_id, signals)
+            })
+            .collect::<HashMap<_, _>>();
+
+        Self {
+            snapshot,
+            signals,
+            dashboards,
+            configuration_history,
+        }
+    }
+
+    fn export(&self) -> DefinitionSerialize<'_> {
+        DefinitionSerialize {
+            format_version: Self::FORMAT_VERSION,
+            devices: &self.snapshot.devices,
+            connections: &self.snapshot.connections,
+            dashboards: self.dashboards,
+        }
+    }
+
+    // checks imported document against devices of this build, resolves to
+    // snapshot with connections to be used
+    fn import_validate(
+        &self,
+        definition: DefinitionDeserialize,
+    ) -> Result<Snapshot, Error> {
+        ensure!(
+            definition.format_version == Self::FORMAT_VERSION,
+            "format version {} not supported, expected {}",
+            definition.format_version,
+            Self::FORMAT_VERSION
+        );
+
+        for (device_id, device) in self.snapshot.devices.iter() {
+            let device_imported = match definition.devices.get(device_id) {
+                Some(device_imported) => device_imported,
+                None => bail!("device {} ({}) missing", device_id, device.name),
+            };
+            ensure!(
+                device_imported.class == device.class,
+                "device {} ({}) class mismatch: {} imported, {} expected",
+                device_id,
+                device.name,
+                device_imported.class,
+                device.class
+            );
+        }
+        for (device_id, device_imported) in definition.devices.iter() {
+            ensure!(
+                self.snapshot.devices.contains_key(device_id),
+                "device {} ({}) not found",
+                device_id,
+                device_imported.name
+            );
+        }
+
+        let dashboards = serde_json::to_value(self.dashboards).context("to_value")?;
+        ensure!(definition.dashboards == dashboards, "dashboards mismatch");
+
+        let mut targets = BTreeSet::new();
+        for connection in definition.connections.iter() {
+            let signal = |device_id: &DeviceId, signal: &str| {
+                self.signals
+                    .get(device_id)
+                    .and_then(|signals| signals.get(signal))
+                    .with_context(|| format!("signal {} not found on device {}", signal, device_id))
+            };
+            let source = signal(&connection.source.device_id, &connection.source.signal)?;
+            let target = signal(&connection.target.device_id, &connection.target.signal)?;
+
+            ensure!(
+                matches!(
+                    (source.kind, target.kind),
+                    (SignalKind::StateSource, SignalKind::StateTarget)
+                        | (SignalKind::EventSource, SignalKind::EventTarget)
+                ),
+                "connection {:?} cannot connect {:?} to {:?}",
+                connection,
+                source.kind,
+                target.kind
+            );
+            ensure!(
+                source.type_id == target.type_id,
+                "connection {:?} type mismatch: {} -> {}",
+                connection,
+                source.type_name,
+                target.type_name
+            );
+            ensure!(
+                targets.insert(&connection.target),
+                "target of connection {:?} already connected",
+                connection
+            );
+        }
+
+        Ok(Snapshot {
+            devices: self.snapshot.devices.clone(),
+            connections: definition.connections,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct DefinitionSerialize<'a> {
+    format_version: u32,
+    devices: &'a BTreeMap<DeviceId, SnapshotDevice>,
+    connections: &'a BTreeSet<SnapshotConnection>,
+    dashboards: &'a Dashboard,
+}
+
+#[derive(Deserialize)]
+struct DefinitionDeserialize {
+    format_version: u32,
+    devices: BTreeMap<DeviceId, SnapshotDevice>,
+    connections: BTreeSet<SnapshotConnection>,
+    dashboards: serde_json::Value,
+}
+
+impl<'a> uri_cursor::Handler for Definition<'a> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let response = web::Response::ok_json(self.export());
+                    async { response }.boxed()
+                }
+                http::Method::PUT => {
+                    let configuration_history = match self.configuration_history {
+                        Some(configuration_history) => configuration_history,
+                        None => return async { web::Response::error_405() }.boxed(),
+                    };
+
+                    let definition = match request.body_parse_json::<DefinitionDeserialize>() {
+                        Ok(definition) => definition,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed();
+                        }
+                    };
+                    let snapshot = match self.import_validate(definition) {
+                        Ok(snapshot) => snapshot,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed();
+                        }
+                    };
+
+                    let imported = configuration_history.rollback_import(&snapshot);
+                    async move {
+                        let result: Result<(), Error> = try { imported?.await? };
+                        match result {
+                            Ok(()) => web::Response::ok_empty(),
+                            Err(error) => {
+                                log::error!("definition: import: {:?}", error);
+                                web::Response::error_500()
+                            }
+                        }
+                    }
+                    .boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Definition, DefinitionDeserialize};
+    use crate::{
+        datatypes::ratio::Ratio,
+        devices::{
+            configuration_history::{Snapshot, SnapshotConnection, SnapshotSignal},
+            helpers::{Devices, Signals},
+            soft::{time::boolean_change_delay_a, value::constant_a},
+        },
+        gui::{
+            dashboards::builder::{DeviceListBuilder, IntoContent},
+            fontawesome::{Icon, IconPrefix},
+        },
+    };
+    use std::time::Duration;
+
+    fn document(definition: &Definition) -> serde_json::Value {
+        serde_json::to_value(definition.export()).unwrap()
+    }
+    fn import_validate(
+        definition: &Definition,
+        document: serde_json::Value,
+    ) -> Result<Snapshot, String> {
+        let definition_deserialize =
+            serde_json::from_value::<DefinitionDeserialize>(document).unwrap();
+        definition
+            .import_validate(definition_deserialize)
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn export_import() {
+        let mut devices = Devices::new();
+        let mut signals = Signals::new();
+
+        let switch = devices.add(
+            "switch",
+            constant_a::Device::new(constant_a::Configuration { value: true }),
+        );
+        let level = devices.add(
+            "level",
+            constant_a::Device::new(constant_a::Configuration {
+                value: Ratio::from_f64(1.0).unwrap(),
+            }),
+        );
+        let delay_configuration = || boolean_change_delay_a::Configuration {
+            delay_raising: Duration::ZERO,
+            delay_falling: Duration::ZERO,
+        };
+        let light_a = devices.add(
+            "light a",
+            boolean_change_delay_a::Device::new(delay_configuration()),
+        );
+        let light_b = devices.add(
+            "light b",
+            boolean_change_delay_a::Device::new(delay_configuration()),
+        );
+        signals.d2d(
+            switch,
+            constant_a::SignalIdentifier::Output,
+            light_a,
+            boolean_change_delay_a::SignalIdentifier::Input,
+        );
+        let dashboards = DeviceListBuilder::new()
+            .add_with(light_a.into_erased())
+            .add_with(light_b.into_erased())
+            .into_dashboard(
+                "lights".to_owned(),
+                Icon {
+                    prefix: IconPrefix::Solid,
+                    name: "lightbulb".to_owned(),
+                },
+            );
+
+        let device_wrappers_by_id = devices.into_device_wrappers_by_id();
+        let connections_requested = signals.into_connections_requested();
+        let snapshot = Snapshot::new(&device_wrappers_by_id, &connections_requested);
+        let definition =
+            Definition::new(snapshot.clone(), &device_wrappers_by_id, &dashboards, None);
+
+        // exported document is accepted as is
+        let document_exported = document(&definition);
+        assert_eq!(
+            import_validate(&definition, document_exported.clone()),
+            Ok(snapshot.clone())
+        );
+
+        // rewired, goes through and back as edited
+        let signal = |device_id, signal: &str| SnapshotSignal {
+            device_id,
+            signal: signal.to_owned(),
+        };
+        let switch_id = switch.into_erased().device_id();
+        let level_id = level.into_erased().device_id();
+        let light_a_id = light_a.into_erased().device_id();
+        let light_b_id = light_b.into_erased().device_id();
+        let connection = |source, target| SnapshotConnection { source, target };
+        let connections_with = |connections: &[SnapshotConnection]| {
+            let mut document = document_exported.clone();
+            document["connections"] = serde_json::to_value(connections).unwrap();
+            document
+        };
+
+        let connections_rewired = [
+            connection(signal(switch_id, "Output"), signal(light_a_id, "Input")),
+            connection(signal(switch_id, "Output"), signal(light_b_id, "Input")),
+        ];
+        let snapshot_rewired =
+            import_validate(&definition, connections_with(&connections_rewired)).unwrap();
+        assert_eq!(
+            snapshot_rewired
+                .connections
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>(),
+            connections_rewired
+        );
+        let definition_rewired = Definition::new(
+            snapshot_rewired.clone(),
+            &device_wrappers_by_id,
+            &dashboards,
+            None,
+        );
+        assert_eq!(
+            import_validate(&definition, document(&definition_rewired)),
+            Ok(snapshot_rewired)
+        );
+
+        // invalid connections
+        assert_eq!(
+            import_validate(
+                &definition,
+                connections_with(&[connection(
+                    signal(switch_id, "Output"),
+                    signal(light_a_id, "Missing")
+                )])
+            ),
+            Err(format!("signal Missing not found on device {}", light_a_id))
+        );
+        assert!(import_validate(
+            &definition,
+            connections_with(&[connection(
+                signal(light_a_id, "Input"),
+                signal(light_b_id, "Input")
+            )])
+        )
+        .unwrap_err()
+        .contains("cannot connect StateTarget to StateTarget"));
+        assert!(import_validate(
+            &definition,
+            connections_with(&[connection(
+                signal(level_id, "Output"),
+                signal(light_a_id, "Input")
+            )])
+        )
+        .unwrap_err()
+        .contains("type mismatch"));
+        assert!(import_validate(
+            &definition,
+            connections_with(&[
+                connection(signal(switch_id, "Output"), signal(light_a_id, "Input")),
+                connection(signal(light_b_id, "Output"), signal(light_a_id, "Input")),
+            ])
+        )
+        .unwrap_err()
+        .contains("already connected"));
+
+        // document of other build
+        let mut document_other = document_exported.clone();
+        document_other["format_version"] = 2.into();
+        assert_eq!(
+            import_validate(&definition, document_other),
+            Err("format version 2 not supported, expected 1".to_owned())
+        );
+        let mut document_other = document_exported.clone();
+        document_other["devices"][light_b_id.to_string()]["class"] = "soft/other".into();
+        assert_eq!(
+            import_validate(&definition, document_other),
+            Err(format!(
+                "device {} (light b) class mismatch: soft/other imported, soft/time/boolean_change_delay_a expected",
+                light_b_id
+            ))
+        );
+        let mut document_other = document_exported.clone();
+        document_other["devices"]
+            .as_object_mut()
+            .unwrap()
+            .remove(&light_b_id.to_string());
+        assert_eq!(
+            import_validate(&definition, document_other),
+            Err(format!("device {} (light b) missing", light_b_id))
+        );
+        let mut document_other = document_exported.clone();
+        document_other["dashboards"]["name"] = "other".into();
+        assert_eq!(
+            import_validate(&definition, document_other),
+            Err("dashboards mismatch".to_owned())
+        );
+    }
+}
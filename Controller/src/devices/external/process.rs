@@ -0,0 +1,538 @@
+use crate::{
+    datatypes::{ratio::Ratio, real::Real, temperature::Temperature},
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        circuit_breaker::{self, CircuitBreaker},
+        runnable::{Exited, Runnable},
+    },
+};
+use anyhow::{anyhow, bail, ensure, Context, Error};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select, stream::StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, convert::Infallible, iter, path::PathBuf, process::Stdio, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{ChildStdin, Command},
+};
+
+// protocol
+//
+// plugin is a program talking json-rpc 2.0 notifications (without `id`) over
+// stdin / stdout, one message per line. stderr is passed to controller log.
+//
+// host -> plugin
+// - `input`, params: `{"name": "...", "value": ...}`, sent for every declared
+//   input on plugin start and then after every change. `null` value means
+//   unknown.
+//
+// plugin -> host
+// - `output`, params: `{"name": "...", "value": ...}`, sets declared output.
+//   outputs are cleared when plugin exits.
+// - `log`, params: `{"level": "error|warn|info|debug", "message": "..."}`
+//
+// plugin is restarted if it exits or violates the protocol.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+impl From<LogLevel> for log::Level {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Notification {
+    Input {
+        name: String,
+        value: serde_json::Value,
+    },
+    Output {
+        name: String,
+        value: serde_json::Value,
+    },
+    Log {
+        level: LogLevel,
+        message: String,
+    },
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+struct Message {
+    jsonrpc: Cow<'static, str>,
+    #[serde(flatten)]
+    notification: Notification,
+}
+impl Message {
+    const VERSION: &'static str = "2.0";
+
+    pub fn serialize(notification: Notification) -> String {
+        let message = Self {
+            jsonrpc: Cow::from(Self::VERSION),
+            notification,
+        };
+        let mut line = serde_json::to_string(&message).unwrap();
+        line.push('\n');
+        line
+    }
+    pub fn parse(line: &str) -> Result<Notification, Error> {
+        let message = serde_json::from_str::<Self>(line).context("from_str")?;
+        ensure!(
+            message.jsonrpc == Self::VERSION,
+            "unsupported jsonrpc version: {}",
+            message.jsonrpc
+        );
+        Ok(message.notification)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValueType {
+    Boolean,
+    Ratio,
+    Real,
+    Temperature,
+}
+
+#[derive(Debug)]
+pub struct SignalDeclaration {
+    pub name: String,
+    pub value_type: ValueType,
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub program: PathBuf,
+    pub arguments: Box<[String]>,
+    // by input / output index, names must be unique within direction
+    pub inputs: Box<[SignalDeclaration]>,
+    pub outputs: Box<[SignalDeclaration]>,
+}
+
+#[derive(Debug)]
+enum SignalInput {
+    Boolean(signal::state_target_last::Signal<bool>),
+    Ratio(signal::state_target_last::Signal<Ratio>),
+    Real(signal::state_target_last::Signal<Real>),
+    Temperature(signal::state_target_last::Signal<Temperature>),
+}
+impl SignalInput {
+    fn new(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::Boolean => Self::Boolean(signal::state_target_last::Signal::<bool>::new()),
+            ValueType::Ratio => Self::Ratio(signal::state_target_last::Signal::<Ratio>::new()),
+            ValueType::Real => Self::Real(signal::state_target_last::Signal::<Real>::new()),
+            ValueType::Temperature => {
+                Self::Temperature(signal::state_target_last::Signal::<Temperature>::new())
+            }
+        }
+    }
+
+    fn as_signal_base(&self) -> &dyn signal::Base {
+        match self {
+            Self::Boolean(signal) => signal,
+            Self::Ratio(signal) => signal,
+            Self::Real(signal) => signal,
+            Self::Temperature(signal) => signal,
+        }
+    }
+
+    // returns value if changed since last call, or last value if forced
+    fn take_json(
+        &self,
+        force: bool,
+    ) -> Option<serde_json::Value> {
+        fn take<V: Serialize + Clone + signals::types::state::Value>(
+            signal: &signal::state_target_last::Signal<V>,
+            force: bool,
+        ) -> Option<serde_json::Value> {
+            let value = match signal.take_pending() {
+                Some(value) => value,
+                None if force => signal.peek_last(),
+                None => return None,
+            };
+            Some(serde_json::to_value(value).unwrap())
+        }
+
+        match self {
+            Self::Boolean(signal) => take(signal, force),
+            Self::Ratio(signal) => take(signal, force),
+            Self::Real(signal) => take(signal, force),
+            Self::Temperature(signal) => take(signal, force),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SignalOutput {
+    Boolean(signal::state_source::Signal<bool>),
+    Ratio(signal::state_source::Signal<Ratio>),
+    Real(signal::state_source::Signal<Real>),
+    Temperature(signal::state_source::Signal<Temperature>),
+}
+impl SignalOutput {
+    fn new(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::Boolean => Self::Boolean(signal::state_source::Signal::<bool>::new(None)),
+            ValueType::Ratio => Self::Ratio(signal::state_source::Signal::<Ratio>::new(None)),
+            ValueType::Real => Self::Real(signal::state_source::Signal::<Real>::new(None)),
+            ValueType::Temperature => {
+                Self::Temperature(signal::state_source::Signal::<Temperature>::new(None))
+            }
+        }
+    }
+
+    fn as_signal_base(&self) -> &dyn signal::Base {
+        match self {
+            Self::Boolean(signal) => signal,
+            Self::Ratio(signal) => signal,
+            Self::Real(signal) => signal,
+            Self::Temperature(signal) => signal,
+        }
+    }
+
+    // returns true if value changed
+    fn set_json(
+        &self,
+        value: serde_json::Value,
+    ) -> Result<bool, Error> {
+        let changed = match self {
+            Self::Boolean(signal) => signal.set_one(serde_json::from_value(value)?),
+            Self::Ratio(signal) => signal.set_one(serde_json::from_value(value)?),
+            Self::Real(signal) => signal.set_one(serde_json::from_value(value)?),
+            Self::Temperature(signal) => signal.set_one(serde_json::from_value(value)?),
+        };
+        Ok(changed)
+    }
+    fn clear(&self) -> bool {
+        match self {
+            Self::Boolean(signal) => signal.set_one(None),
+            Self::Ratio(signal) => signal.set_one(None),
+            Self::Real(signal) => signal.set_one(None),
+            Self::Temperature(signal) => signal.set_one(None),
+        }
+    }
+}
+
+// hosts device implemented as external program, see protocol above
+// integrations can be written in any language and crash of the plugin does not
+// affect the controller
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    circuit_breaker: CircuitBreaker,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_inputs: Box<[SignalInput]>,
+    signal_outputs: Box<[SignalOutput]>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let circuit_breaker = CircuitBreaker::new(
+            format!("external/process/{}", configuration.program.display()),
+            circuit_breaker::Configuration::default(),
+        );
+
+        let signal_inputs = configuration
+            .inputs
+            .iter()
+            .map(|input| SignalInput::new(input.value_type))
+            .collect::<Box<[_]>>();
+        let signal_outputs = configuration
+            .outputs
+            .iter()
+            .map(|output| SignalOutput::new(output.value_type))
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+
+            circuit_breaker,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_inputs,
+            signal_outputs,
+        }
+    }
+
+    fn inputs_serialize(
+        &self,
+        force: bool,
+    ) -> String {
+        self.configuration
+            .inputs
+            .iter()
+            .zip(self.signal_inputs.iter())
+            .filter_map(|(input, signal_input)| {
+                let value = signal_input.take_json(force)?;
+                Some(Message::serialize(Notification::Input {
+                    name: input.name.clone(),
+                    value,
+                }))
+            })
+            .collect::<String>()
+    }
+
+    fn notification_handle(
+        &self,
+        notification: Notification,
+    ) -> Result<(), Error> {
+        match notification {
+            Notification::Output { name, value } => {
+                let output_index = self
+                    .configuration
+                    .outputs
+                    .iter()
+                    .position(|output| output.name == name)
+                    .ok_or_else(|| anyhow!("output {} not declared", name))?;
+
+                if self.signal_outputs[output_index]
+                    .set_json(value)
+                    .with_context(|| format!("output {}", name))?
+                {
+                    self.signals_sources_changed_waker.wake();
+                }
+            }
+            Notification::Log { level, message } => {
+                log::log!(
+                    level.into(),
+                    "{}: {}",
+                    self.configuration.program.display(),
+                    message
+                );
+            }
+            Notification::Input { .. } => bail!("input notification sent by plugin"),
+        }
+
+        Ok(())
+    }
+
+    fn outputs_clear(&self) {
+        let mut signals_sources_changed = false;
+        for signal_output in self.signal_outputs.iter() {
+            signals_sources_changed |= signal_output.clear();
+        }
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn inputs_send(
+        &self,
+        stdin: &mut ChildStdin,
+    ) -> Result<Infallible, Error> {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        // plugin gets complete state on start
+        let mut force = true;
+        loop {
+            let payload = self.inputs_serialize(force);
+            force = false;
+
+            if !payload.is_empty() {
+                stdin
+                    .write_all(payload.as_bytes())
+                    .await
+                    .context("write_all")?;
+                stdin.flush().await.context("flush")?;
+            }
+
+            signals_targets_changed_stream.select_next_some().await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<Infallible, Error> {
+        let mut child = Command::new(&self.configuration.program)
+            .args(self.configuration.arguments.iter())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .context("spawn")?;
+        let mut stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let inputs_sender = self.inputs_send(&mut stdin);
+
+        let outputs_receiver = async {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Some(line) = lines.next_line().await.context("next_line")? {
+                let notification = Message::parse(&line).context("parse")?;
+                self.notification_handle(notification)
+                    .context("notification_handle")?;
+
+                if self.circuit_breaker.success() {
+                    log::info!("plugin {} recovered", self.configuration.program.display());
+                }
+            }
+            Err::<Infallible, _>(anyhow!("stdout closed"))
+        };
+
+        select! {
+            result = inputs_sender.fuse() => result.context("inputs_sender"),
+            result = outputs_receiver.fuse() => result.context("outputs_receiver"),
+            result = child.wait().fuse() => match result {
+                Ok(status) => Err(anyhow!("plugin exited: {}", status)),
+                Err(error) => Err(error).context("wait"),
+            },
+        }
+    }
+
+    const ERROR_RESTART_INTERVAL: Duration = Duration::from_secs(5);
+    async fn run_loop(&self) -> ! {
+        loop {
+            self.circuit_breaker.ready().await;
+
+            let error = self.run_once().await.context("run_once");
+            self.outputs_clear();
+
+            // crash looping plugin is not reported every time
+            if self.circuit_breaker.failure() {
+                log::error!(
+                    "plugin {} failed: {:?}",
+                    self.configuration.program.display(),
+                    error.unwrap_err()
+                );
+            }
+            tokio::time::sleep(Self::ERROR_RESTART_INTERVAL).await;
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        // child is killed when runner is dropped
+        select! {
+            _ = self.run_loop().fuse() => panic!("run_loop yielded"),
+            () = exit_flag.fuse() => {},
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("external/process")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+    Output(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input_index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(input_index),
+                            signal_input.as_signal_base(),
+                        )
+                    }),
+            )
+            .chain(
+                self.signal_outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(output_index, signal_output)| {
+                        (
+                            SignalIdentifier::Output(output_index),
+                            signal_output.as_signal_base(),
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogLevel, Message, Notification};
+    use serde_json::json;
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            Message::serialize(Notification::Input {
+                name: "temperature".to_owned(),
+                value: json!(21.5),
+            }),
+            "{\"jsonrpc\":\"2.0\",\"method\":\"input\",\"params\":{\"name\":\"temperature\",\"value\":21.5}}\n"
+        );
+
+        assert_eq!(
+            Message::parse(
+                r#"{"jsonrpc": "2.0", "method": "output", "params": {"name": "valve", "value": null}}"#
+            )
+            .unwrap(),
+            Notification::Output {
+                name: "valve".to_owned(),
+                value: json!(null),
+            }
+        );
+        assert_eq!(
+            Message::parse(
+                r#"{"jsonrpc": "2.0", "method": "log", "params": {"level": "warn", "message": "x"}}"#
+            )
+            .unwrap(),
+            Notification::Log {
+                level: LogLevel::Warn,
+                message: "x".to_owned(),
+            }
+        );
+
+        assert!(Message::parse(r#"{"jsonrpc": "1.0", "method": "log", "params": {}}"#).is_err());
+        assert!(Message::parse(r#"{"jsonrpc": "2.0", "method": "unknown"}"#).is_err());
+    }
+}
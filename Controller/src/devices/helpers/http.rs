@@ -0,0 +1,91 @@
+use anyhow::{Context, Error};
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    pub connect_timeout: Duration,
+    // for finite requests, see request_timeout()
+    pub request_timeout: Duration,
+    pub tcp_keepalive: Option<Duration>,
+    pub pool_idle_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: usize,
+}
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(60),
+            tcp_keepalive: Some(Duration::from_secs(30)),
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: 4,
+        }
+    }
+}
+
+fn client_build(configuration: &Configuration) -> Result<reqwest::Client, Error> {
+    let client = reqwest::ClientBuilder::new()
+        .connect_timeout(configuration.connect_timeout)
+        .tcp_keepalive(configuration.tcp_keepalive)
+        .pool_idle_timeout(configuration.pool_idle_timeout)
+        .pool_max_idle_per_host(configuration.pool_max_idle_per_host)
+        .build()
+        .context("build")?;
+
+    Ok(client)
+}
+
+#[derive(Debug)]
+struct Shared {
+    client: reqwest::Client,
+    request_timeout: Duration,
+}
+impl Shared {
+    fn new(configuration: &Configuration) -> Result<Self, Error> {
+        let client = client_build(configuration).context("client_build")?;
+        let request_timeout = configuration.request_timeout;
+
+        Ok(Self {
+            client,
+            request_timeout,
+        })
+    }
+}
+
+static SHARED: OnceCell<Shared> = OnceCell::new();
+fn shared() -> &'static Shared {
+    SHARED.get_or_init(|| Shared::new(&Configuration::default()).unwrap())
+}
+
+// must be called before first client() call, otherwise defaults are used
+pub fn configure(configuration: Configuration) -> Result<(), Error> {
+    let shared = Shared::new(&configuration).context("new")?;
+    SHARED
+        .set(shared)
+        .ok()
+        .context("http client already initialized")?;
+
+    Ok(())
+}
+
+// shared by all api drivers, so connections to the same host are reused
+// reqwest::Client is a handle to the pool, cloning it is cheap
+// client has no total timeout, as it is also used for endless streams (eg.
+// camera event streams), finite requests should set their own with
+// RequestBuilder::timeout, eg. request_timeout()
+pub fn client() -> reqwest::Client {
+    shared().client.clone()
+}
+pub fn request_timeout() -> Duration {
+    shared().request_timeout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_build, Configuration};
+
+    #[test]
+    fn default_builds() {
+        client_build(&Configuration::default()).unwrap();
+    }
+}
@@ -0,0 +1,503 @@
+pub mod http;
+
+use super::{
+    classes::{class_by_name, Class, ConfigurationVersioned},
+    Device, DeviceWrapper, Id as DeviceId,
+};
+use crate::{
+    modules::i18n,
+    signals::{
+        exchanger::{ConnectionRequested, DeviceIdSignalIdentifierBaseWrapper},
+        Device as SignalsDevice, IdentifierBaseWrapper as SignalIdentifierBaseWrapper,
+    },
+};
+use anyhow::{anyhow, Context, Error};
+use std::{collections::HashMap, marker::PhantomData};
+
+#[derive(Debug)]
+pub struct Devices<'d> {
+    device_wrappers: Vec<DeviceWrapper<'d>>,
+
+    // class and current configuration of devices created through class registry
+    configurations: HashMap<DeviceId, (&'static Class, serde_json::Value)>,
+}
+impl<'d> Devices<'d> {
+    pub fn new() -> Self {
+        Self {
+            device_wrappers: Vec::<DeviceWrapper<'d>>::new(),
+
+            configurations: HashMap::<DeviceId, (&'static Class, serde_json::Value)>::new(),
+        }
+    }
+
+    pub fn add<N: ToString, D: Device + SignalsDevice + 'd>(
+        &mut self,
+        name: N,
+        device: D,
+    ) -> DeviceHandle<'d, D> {
+        let device_handle = self.add_erased(name.to_string(), Box::new(device));
+
+        DeviceHandle::<D>::new(device_handle.device_id())
+    }
+
+    fn add_erased(
+        &mut self,
+        name: String,
+        device: Box<dyn Device + 'd>,
+    ) -> DeviceHandleErased<'d> {
+        let device_wrapper = DeviceWrapper::new(name, device);
+
+        let device_id = (self.device_wrappers.len() + 1) as DeviceId; // starts from 1
+        self.device_wrappers.push(device_wrapper);
+
+        DeviceHandleErased::new(device_id)
+    }
+
+    // creates device from stored configuration, using class registry
+    pub fn add_class<N: ToString>(
+        &mut self,
+        name: N,
+        class: &str,
+        configuration_versioned: ConfigurationVersioned,
+    ) -> Result<DeviceHandleErased<'d>, Error> {
+        let class = class_by_name(class).ok_or_else(|| anyhow!("unknown class: {}", class))?;
+
+        let configuration = class
+            .configuration_migrate(configuration_versioned)
+            .context("configuration_migrate")?;
+
+        self.add_class_configuration(name.to_string(), class, configuration)
+    }
+
+    // creates new device with same class and configuration as given one
+    // device must be created with add_class()
+    pub fn duplicate<N: ToString>(
+        &mut self,
+        device: DeviceHandleErased,
+        name: N,
+    ) -> Result<DeviceHandleErased<'d>, Error> {
+        let (class, configuration) = self
+            .configurations
+            .get(&device.device_id())
+            .cloned()
+            .ok_or_else(|| anyhow!("device not created from class registry"))?;
+
+        self.add_class_configuration(name.to_string(), class, configuration)
+    }
+    fn add_class_configuration(
+        &mut self,
+        name: String,
+        class: &'static Class,
+        configuration: serde_json::Value,
+    ) -> Result<DeviceHandleErased<'d>, Error> {
+        let device = class
+            .device_new(class.configuration_current(configuration.clone()))
+            .context("device_new")?;
+
+        let device_handle = self.add_erased(name, device);
+        self.configurations
+            .insert(device_handle.device_id(), (class, configuration));
+
+        Ok(device_handle)
+    }
+
+    // device will be started after and finalized before its dependency
+    pub fn dependency_add<D: Device + SignalsDevice, DD: Device + SignalsDevice>(
+        &mut self,
+        device: DeviceHandle<D>,
+        dependency: DeviceHandle<DD>,
+    ) {
+        self.dependency_add_erased(device.into_erased(), dependency.into_erased());
+    }
+    pub fn dependency_add_erased(
+        &mut self,
+        device: DeviceHandleErased,
+        dependency: DeviceHandleErased,
+    ) {
+        let device_wrapper = &mut self.device_wrappers[(device.device_id() - 1) as usize];
+        device_wrapper.dependency_add(dependency.device_id());
+    }
+
+    // new automation can be validated against live inputs before going active
+    // see DeviceWrapper::shadow_set()
+    pub fn shadow_set<D: Device + SignalsDevice>(
+        &mut self,
+        device: DeviceHandle<D>,
+    ) {
+        self.shadow_set_erased(device.into_erased());
+    }
+    pub fn shadow_set_erased(
+        &mut self,
+        device: DeviceHandleErased,
+    ) {
+        let device_wrapper = &mut self.device_wrappers[(device.device_id() - 1) as usize];
+        device_wrapper.shadow_set();
+    }
+
+    // device keeps receiving values while maintenance mode is active
+    // see DeviceWrapper::maintenance_exempt_set()
+    pub fn maintenance_exempt_set<D: Device + SignalsDevice>(
+        &mut self,
+        device: DeviceHandle<D>,
+    ) {
+        self.maintenance_exempt_set_erased(device.into_erased());
+    }
+    pub fn maintenance_exempt_set_erased(
+        &mut self,
+        device: DeviceHandleErased,
+    ) {
+        let device_wrapper = &mut self.device_wrappers[(device.device_id() - 1) as usize];
+        device_wrapper.maintenance_exempt_set();
+    }
+
+    // device is shown in gui under label translated to user locale
+    // translations are registered in i18n catalog under given key
+    pub fn label_set<'a, D: Device + SignalsDevice>(
+        &mut self,
+        device: DeviceHandle<D>,
+        label_key: &str,
+        translations: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) {
+        self.label_set_erased(device.into_erased(), label_key, translations);
+    }
+    pub fn label_set_erased<'a>(
+        &mut self,
+        device: DeviceHandleErased,
+        label_key: &str,
+        translations: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) {
+        i18n::catalog().register(label_key, translations);
+
+        let device_wrapper = &mut self.device_wrappers[(device.device_id() - 1) as usize];
+        device_wrapper.label_key_set(label_key.to_owned());
+    }
+
+    // web writes of the signal need to be confirmed by user
+    // see DeviceWrapper::confirmation_required_set()
+    pub fn confirmation_required_set<D: Device + SignalsDevice>(
+        &mut self,
+        device_signal: DeviceSignalHandle<'d, D>,
+    ) {
+        self.confirmation_required_set_erased(device_signal.into_erased());
+    }
+    pub fn confirmation_required_set_erased(
+        &mut self,
+        device_signal: DeviceSignalHandleErased,
+    ) {
+        let device_wrapper = &mut self.device_wrappers
+            [(device_signal.device_handle_erased.device_id() - 1) as usize];
+        device_wrapper
+            .confirmation_required_set(device_signal.signal_identifier_base_wrapper.name());
+    }
+
+    pub fn into_device_wrappers_by_id(self) -> HashMap<DeviceId, DeviceWrapper<'d>> {
+        self.device_wrappers
+            .into_iter()
+            .enumerate()
+            .map(|(device_id, device)| ((device_id + 1) as DeviceId, device))
+            .collect::<HashMap<_, _>>()
+    }
+}
+
+#[derive(Debug)]
+pub struct Signals {
+    connections_requested: Vec<ConnectionRequested>,
+}
+impl Signals {
+    pub fn new() -> Self {
+        let connections_requested = Vec::<ConnectionRequested>::new();
+
+        Self {
+            connections_requested,
+        }
+    }
+
+    // device to device
+    pub fn d2d<SD: Device + SignalsDevice, TD: Device + SignalsDevice>(
+        &mut self,
+        source_device: DeviceHandle<SD>,
+        source_signal_identifier: SD::Identifier,
+        target_device: DeviceHandle<TD>,
+        target_signal_identifier: TD::Identifier,
+    ) {
+        self.ds2ds(
+            source_device.with_signal(source_signal_identifier),
+            target_device.with_signal(target_signal_identifier),
+        )
+    }
+    pub fn ds2ds<SD: Device + SignalsDevice, TD: Device + SignalsDevice>(
+        &mut self,
+        source: DeviceSignalHandle<SD>,
+        target: DeviceSignalHandle<TD>,
+    ) {
+        self.dse2dse(source.into_erased(), target.into_erased());
+    }
+    pub fn dse2dse(
+        &mut self,
+        source: DeviceSignalHandleErased,
+        target: DeviceSignalHandleErased,
+    ) {
+        self.connections_requested.push((
+            source.into_device_id_signal_identifier_base_wrapper(),
+            target.into_device_id_signal_identifier_base_wrapper(),
+        ));
+    }
+
+    pub fn d2dse<SD: Device + SignalsDevice>(
+        &mut self,
+        source_device: DeviceHandle<SD>,
+        source_signal_identifier: SD::Identifier,
+        target: DeviceSignalHandleErased,
+    ) {
+        self.dse2dse(
+            source_device
+                .with_signal(source_signal_identifier)
+                .into_erased(),
+            target,
+        );
+    }
+    pub fn dse2d<TD: Device + SignalsDevice>(
+        &mut self,
+        source: DeviceSignalHandleErased,
+        target_device: DeviceHandle<TD>,
+        target_signal_identifier: TD::Identifier,
+    ) {
+        self.dse2dse(
+            source,
+            target_device
+                .with_signal(target_signal_identifier)
+                .into_erased(),
+        );
+    }
+
+    // copies every connection targeting any of substituted devices, with device
+    // ids replaced according to substitutions
+    // eg. [(blind_1, blind_5), (button_1, button_5)] connects button_5 to
+    // blind_5 the same way button_1 is connected to blind_1, while sources
+    // shared with other devices are connected to both
+    // connections targeting devices outside substitutions are skipped, as
+    // each target may have only one source
+    pub fn connections_duplicate(
+        &mut self,
+        substitutions: &[(DeviceHandleErased, DeviceHandleErased)],
+    ) {
+        let substitutions = substitutions
+            .iter()
+            .map(|(device, device_duplicate)| (device.device_id(), device_duplicate.device_id()))
+            .collect::<HashMap<_, _>>();
+
+        let substitute =
+            |device_id_signal_identifier_base_wrapper: &DeviceIdSignalIdentifierBaseWrapper| {
+                substitutions
+                    .get(&device_id_signal_identifier_base_wrapper.device_id())
+                    .map(|device_id| {
+                        device_id_signal_identifier_base_wrapper.with_device_id(*device_id)
+                    })
+            };
+
+        let connections_requested_duplicated = self
+            .connections_requested
+            .iter()
+            .filter_map(|(source, target)| {
+                let target_duplicated = substitute(target)?;
+                let source_duplicated = substitute(source).unwrap_or_else(|| source.clone());
+                Some((source_duplicated, target_duplicated))
+            })
+            .collect::<Vec<_>>();
+
+        self.connections_requested
+            .extend(connections_requested_duplicated);
+    }
+
+    pub fn as_connections_requested(&self) -> &[ConnectionRequested] {
+        &self.connections_requested
+    }
+    pub fn into_connections_requested(self) -> Box<[ConnectionRequested]> {
+        self.connections_requested.into_boxed_slice()
+    }
+}
+
+#[derive(Debug)]
+pub struct DeviceHandle<'d, D: Device + SignalsDevice + 'd> {
+    device_id: DeviceId,
+
+    phantom_data: PhantomData<&'d D>,
+}
+impl<'d, D: Device + SignalsDevice + 'd> DeviceHandle<'d, D> {
+    pub(crate) fn new(device_id: DeviceId) -> Self {
+        Self {
+            device_id,
+
+            phantom_data: PhantomData,
+        }
+    }
+
+    pub fn with_signal(
+        self,
+        signal_identifier: <D as SignalsDevice>::Identifier,
+    ) -> DeviceSignalHandle<'d, D> {
+        DeviceSignalHandle::new(self, signal_identifier)
+    }
+
+    pub fn into_erased(self) -> DeviceHandleErased<'d> {
+        DeviceHandleErased::new(self.device_id)
+    }
+}
+impl<'d, D: Device + SignalsDevice + 'd> Clone for DeviceHandle<'d, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'d, D: Device + SignalsDevice + 'd> Copy for DeviceHandle<'d, D> {}
+
+#[derive(Clone, Debug)]
+pub struct DeviceSignalHandle<'d, D: Device + SignalsDevice + 'd> {
+    device_handle_erased: DeviceHandle<'d, D>,
+    signal_identifier: <D as SignalsDevice>::Identifier,
+}
+impl<'d, D: Device + SignalsDevice + 'd> DeviceSignalHandle<'d, D> {
+    pub fn new(
+        device_handle_erased: DeviceHandle<'d, D>,
+        signal_identifier: <D as SignalsDevice>::Identifier,
+    ) -> Self {
+        Self {
+            device_handle_erased,
+            signal_identifier,
+        }
+    }
+
+    pub fn into_erased(self) -> DeviceSignalHandleErased<'d> {
+        DeviceSignalHandleErased::new(
+            self.device_handle_erased.into_erased(),
+            SignalIdentifierBaseWrapper::new(self.signal_identifier.clone()),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct DeviceHandleErased<'d> {
+    device_id: DeviceId,
+
+    phantom_data: PhantomData<&'d ()>,
+}
+impl<'d> DeviceHandleErased<'d> {
+    fn new(device_id: DeviceId) -> Self {
+        Self {
+            device_id,
+            phantom_data: PhantomData,
+        }
+    }
+
+    pub fn device_id(&self) -> DeviceId {
+        self.device_id
+    }
+}
+impl<'d> Clone for DeviceHandleErased<'d> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'d> Copy for DeviceHandleErased<'d> {}
+
+#[derive(Clone, Debug)]
+pub struct DeviceSignalHandleErased<'d> {
+    device_handle_erased: DeviceHandleErased<'d>,
+    signal_identifier_base_wrapper: SignalIdentifierBaseWrapper,
+}
+impl<'d> DeviceSignalHandleErased<'d> {
+    pub fn new(
+        device_handle_erased: DeviceHandleErased<'d>,
+        signal_identifier_base_wrapper: SignalIdentifierBaseWrapper,
+    ) -> Self {
+        Self {
+            device_handle_erased,
+            signal_identifier_base_wrapper,
+        }
+    }
+
+    fn into_device_id_signal_identifier_base_wrapper(self) -> DeviceIdSignalIdentifierBaseWrapper {
+        DeviceIdSignalIdentifierBaseWrapper::new(
+            self.device_handle_erased.device_id(),
+            self.signal_identifier_base_wrapper,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceSignalHandleErased, Devices, Signals};
+    use crate::{
+        devices::{classes::ConfigurationVersioned, soft::time::pulse_a::SignalIdentifier},
+        signals::{exchanger::DeviceIdSignalIdentifierBaseWrapper, IdentifierBaseWrapper},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn duplicate_with_connections() {
+        let configuration_versioned = || ConfigurationVersioned {
+            version: 0,
+            configuration: json!({ "duration": { "secs": 1, "nanos": 0 } }),
+        };
+
+        let mut devices = Devices::new();
+        let source = devices
+            .add_class("source", "soft/time/pulse_a", configuration_versioned())
+            .unwrap();
+        let pulse = devices
+            .add_class("pulse", "soft/time/pulse_a", configuration_versioned())
+            .unwrap();
+        let target = devices
+            .add_class("target", "soft/time/pulse_a", configuration_versioned())
+            .unwrap();
+
+        let pulse_duplicate = devices.duplicate(pulse, "pulse duplicate").unwrap();
+        let target_duplicate = devices.duplicate(target, "target duplicate").unwrap();
+        assert_eq!(pulse_duplicate.device_id(), 4);
+        assert_eq!(target_duplicate.device_id(), 5);
+
+        let mut signals = Signals::new();
+        signals.dse2dse(
+            DeviceSignalHandleErased::new(
+                source,
+                IdentifierBaseWrapper::new(SignalIdentifier::Output),
+            ),
+            DeviceSignalHandleErased::new(
+                pulse,
+                IdentifierBaseWrapper::new(SignalIdentifier::Input),
+            ),
+        );
+        signals.dse2dse(
+            DeviceSignalHandleErased::new(
+                pulse,
+                IdentifierBaseWrapper::new(SignalIdentifier::Output),
+            ),
+            DeviceSignalHandleErased::new(
+                target,
+                IdentifierBaseWrapper::new(SignalIdentifier::Input),
+            ),
+        );
+        signals.connections_duplicate(&[(pulse, pulse_duplicate), (target, target_duplicate)]);
+
+        let connection = |source, target| {
+            (
+                DeviceIdSignalIdentifierBaseWrapper::new(
+                    source,
+                    IdentifierBaseWrapper::new(SignalIdentifier::Output),
+                ),
+                DeviceIdSignalIdentifierBaseWrapper::new(
+                    target,
+                    IdentifierBaseWrapper::new(SignalIdentifier::Input),
+                ),
+            )
+        };
+        assert_eq!(
+            signals.as_connections_requested(),
+            &[
+                connection(1, 2),
+                connection(2, 3),
+                connection(1, 4),
+                connection(4, 5),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::boundary_stream;
+use crate::devices::helpers::http as http_helpers;
 use anyhow::{anyhow, bail, ensure, Context, Error};
 use bytes::Bytes;
 use futures::stream::{BoxStream, Stream, StreamExt};
@@ -57,7 +58,7 @@ impl Api {
         host: Authority,
         admin_password: String,
     ) -> Self {
-        let reqwest_client = reqwest::ClientBuilder::new().build().unwrap();
+        let reqwest_client = http_helpers::client();
 
         Self {
             host,
@@ -298,7 +298,6 @@ pub struct LineDetection {
 pub struct Configuration {
     pub device_name: String,
     pub device_id: u8,
-    pub shared_user_password: String,
     pub video_upside_down: bool,
     pub overlay_text: Option<String>,
     pub privacy_mask: Option<PrivacyMask>,
@@ -1149,6 +1148,7 @@ impl<'a> Configurator<'a> {
     pub async fn configure(
         &mut self,
         configuration: Configuration,
+        shared_user_password: String,
     ) -> Result<(), Error> {
         // TODO: Progress callback
 
@@ -1168,7 +1168,7 @@ impl<'a> Configurator<'a> {
             .await
             .context("system_time_ntp")?;
 
-        self.system_shared_user(configuration.shared_user_password)
+        self.system_shared_user(shared_user_password)
             .await
             .context("system_shared_user")?;
 
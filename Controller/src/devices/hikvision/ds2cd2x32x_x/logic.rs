@@ -2,14 +2,20 @@ use super::hardware::{api, configurator, event_stream};
 use crate::{
     datatypes::ipc_rtsp_url::IpcRtspUrl,
     devices::{
-        self,
-        soft::surveillance::snapshot::logic_device_inner::{
-            Manager as SnapshotManager, Runner as SnapshotRunner,
+        self, camera,
+        soft::surveillance::snapshot::{
+            attachment,
+            logic_device_inner::{Manager as SnapshotManager, Runner as SnapshotRunner},
         },
     },
+    modules::{
+        events,
+        secrets::{SecretReference, Secrets},
+    },
     signals::{self, signal},
     util::{
         async_flag,
+        circuit_breaker::{self, CircuitBreaker},
         runnable::{Exited, Runnable},
     },
     web::{self, uri_cursor},
@@ -23,6 +29,7 @@ use futures::{
     stream::StreamExt,
 };
 use http::uri::Authority;
+use image::DynamicImage;
 use maplit::hashmap;
 use parking_lot::RwLock;
 use serde::Serialize;
@@ -32,17 +39,28 @@ use std::{borrow::Cow, time::Duration};
 pub enum ConfigurationHardware {
     Full {
         hardware_configuration: configurator::Configuration,
+        shared_user_password: SecretReference,
     },
     Skip {
         shared_user_login: String,
-        shared_user_password: String,
+        shared_user_password: SecretReference,
     },
 }
+// reported to events when alarm signal is triggered
+#[derive(Debug)]
+pub struct Notification {
+    pub severity: events::Severity,
+    pub message: String,
+    // fresh snapshot is attached, if camera is reachable
+    pub attachment: attachment::Configuration,
+}
+
 #[derive(Debug)]
 pub struct Configuration {
     pub host: Authority,
-    pub admin_password: String,
+    pub admin_password: SecretReference,
     pub hardware: ConfigurationHardware,
+    pub notification: Option<Notification>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -86,13 +104,17 @@ pub enum DeviceState {
 }
 
 #[derive(Debug)]
-pub struct Device {
+pub struct Device<'s> {
     configuration: Configuration,
+    secrets: &'s Secrets<'s>,
 
     device_state: RwLock<DeviceState>,
+    circuit_breaker: CircuitBreaker,
     snapshot_manager: SnapshotManager,
 
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
     signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_alarm: signal::event_target_queued::Signal<()>,
     signal_rtsp_url_main: signal::state_source::Signal<IpcRtspUrl>,
     signal_rtsp_url_sub: signal::state_source::Signal<IpcRtspUrl>,
     signal_event_camera_failure: signal::state_source::Signal<bool>,
@@ -103,16 +125,29 @@ pub struct Device {
     signal_event_field_detection: signal::state_source::Signal<bool>,
 
     gui_summary_waker: devices::gui_summary::Waker,
+    camera_waker: camera::Waker,
 }
-impl Device {
-    pub fn new(configuration: Configuration) -> Self {
+impl<'s> Device<'s> {
+    pub fn new(
+        configuration: Configuration,
+        secrets: &'s Secrets<'s>,
+    ) -> Self {
+        let circuit_breaker = CircuitBreaker::new(
+            format!("hikvision/ds2cd2x32x_x/{}", configuration.host),
+            circuit_breaker::Configuration::default(),
+        );
+
         Self {
             configuration,
+            secrets,
 
             device_state: RwLock::new(DeviceState::Initializing),
+            circuit_breaker,
             snapshot_manager: SnapshotManager::new(),
 
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
             signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_alarm: signal::event_target_queued::Signal::<()>::new(),
             signal_rtsp_url_main: signal::state_source::Signal::<IpcRtspUrl>::new(None),
             signal_rtsp_url_sub: signal::state_source::Signal::<IpcRtspUrl>::new(None),
             signal_event_camera_failure: signal::state_source::Signal::<bool>::new(None),
@@ -123,6 +158,7 @@ impl Device {
             signal_event_field_detection: signal::state_source::Signal::<bool>::new(None),
 
             gui_summary_waker: devices::gui_summary::Waker::new(),
+            camera_waker: camera::Waker::new(),
         }
     }
 
@@ -136,6 +172,7 @@ impl Device {
             _ => panic!("snapshot_updated_handle can be called only when device is running"),
         }
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
     }
     fn events_handle(
         &self,
@@ -149,6 +186,7 @@ impl Device {
             _ => panic!("events_handle can be called only when device is running"),
         }
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         let mut signals_sources_changed = false;
         if self
@@ -192,9 +230,24 @@ impl Device {
         }
     }
 
+    // password is fetched from secrets store for every connection and snapshot,
+    // so changes are picked up without restart
+    async fn api_build(&self) -> Result<api::Api, Error> {
+        let admin_password = self
+            .secrets
+            .get(&self.configuration.admin_password)
+            .await
+            .context("get")?;
+        Ok(api::Api::new(
+            self.configuration.host.clone(),
+            admin_password.expose().to_owned(),
+        ))
+    }
+
     fn failed(&self) {
         *self.device_state.write() = DeviceState::Error;
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         self.snapshot_manager.image_unset();
 
@@ -213,30 +266,38 @@ impl Device {
     async fn run_once(&self) -> Result<!, Error> {
         *self.device_state.write() = DeviceState::Initializing;
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         // Build client
-        let api = api::Api::new(
-            self.configuration.host.clone(),
-            self.configuration.admin_password.clone(),
-        );
+        let api = self.api_build().await.context("api_build")?;
 
         // Set device configuration
         // Get rtsp data based on configuration type
         let (shared_user_login, shared_user_password) = match &self.configuration.hardware {
             ConfigurationHardware::Full {
                 hardware_configuration,
+                shared_user_password,
             } => {
+                let shared_user_password = self
+                    .secrets
+                    .get(shared_user_password)
+                    .await
+                    .context("shared_user_password get")?;
+
                 let mut configurator = configurator::Configurator::connect(&api)
                     .await
                     .context("connect")?;
                 configurator
-                    .configure(hardware_configuration.clone())
+                    .configure(
+                        hardware_configuration.clone(),
+                        shared_user_password.expose().to_owned(),
+                    )
                     .await
                     .context("configure")?;
 
                 (
                     configurator::Configurator::SHARED_USER_LOGIN,
-                    &hardware_configuration.shared_user_password,
+                    shared_user_password,
                 )
             }
             ConfigurationHardware::Skip {
@@ -249,6 +310,12 @@ impl Device {
                     .await
                     .context("validate_basic_device_info")?;
 
+                let shared_user_password = self
+                    .secrets
+                    .get(shared_user_password)
+                    .await
+                    .context("shared_user_password get")?;
+
                 (shared_user_login.as_str(), shared_user_password)
             }
         };
@@ -257,12 +324,12 @@ impl Device {
         let rtsp_urls = RtspUrls {
             main: IpcRtspUrl(api.rtsp_url_build(
                 shared_user_login,
-                shared_user_password,
+                shared_user_password.expose(),
                 api::VideoStream::Main,
             )),
             sub: IpcRtspUrl(api.rtsp_url_build(
                 shared_user_login,
-                shared_user_password,
+                shared_user_password.expose(),
                 api::VideoStream::Sub,
             )),
         };
@@ -296,6 +363,10 @@ impl Device {
         pin_mut!(snapshot_runner_runner);
         let mut snapshot_runner_runner = snapshot_runner_runner.fuse();
 
+        if self.circuit_breaker.success() {
+            log::info!("device {} recovered", self.configuration.host);
+        }
+
         // Mark device as ready
         *self.device_state.write() = DeviceState::Running {
             snapshot_updated: None,
@@ -303,6 +374,7 @@ impl Device {
             events: Events::default(),
         };
         self.gui_summary_waker.wake();
+        self.camera_waker.wake();
 
         // Set initial signal values
         let _ = self.signal_rtsp_url_main.set_one(Some(rtsp_urls.main));
@@ -319,16 +391,71 @@ impl Device {
     const ERROR_RESTART_INTERVAL: Duration = Duration::from_secs(10);
     async fn run(&self) -> ! {
         loop {
+            self.circuit_breaker.ready().await;
+
             let error = self.run_once().await.context("run_once");
             self.failed();
 
-            log::error!("device {} failed: {:?}", self.configuration.host, error);
+            // repeated failures of unreachable device are not reported
+            if self.circuit_breaker.failure() {
+                log::error!("device {} failed: {:?}", self.configuration.host, error);
+                events::reporter().report(
+                    events::Severity::Error,
+                    format!("hikvision/ds2cd2x32x_x/{}", self.configuration.host),
+                    format!("device failed: {:#}", error.as_ref().unwrap_err()),
+                );
+            }
             tokio::time::sleep(Self::ERROR_RESTART_INTERVAL).await;
         }
     }
 }
 
-impl devices::Device for Device {
+impl<'s> Device<'s> {
+    async fn alarm_report(
+        &self,
+        notification: &Notification,
+    ) {
+        let attachment = camera::snapshot_attachment(self, &notification.attachment).await;
+        let attachment = match attachment {
+            Ok(attachment) => Some(attachment),
+            Err(error) => {
+                log::warn!(
+                    "device {} alarm snapshot failed: {:?}",
+                    self.configuration.host,
+                    error
+                );
+                None
+            }
+        };
+
+        events::reporter().report_with_attachment(
+            notification.severity,
+            format!("hikvision/ds2cd2x32x_x/{}", self.configuration.host),
+            notification.message.clone(),
+            attachment,
+        );
+    }
+
+    // independent of device state, so alarms are reported with or without
+    // snapshot
+    async fn alarms_run(&self) -> ! {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+        loop {
+            signals_targets_changed_stream.select_next_some().await;
+
+            if self.signal_alarm.take_pending().is_empty() {
+                continue;
+            }
+            let notification = match &self.configuration.notification {
+                Some(notification) => notification,
+                None => continue,
+            };
+            self.alarm_report(notification).await;
+        }
+    }
+}
+
+impl<'s> devices::Device for Device<'s> {
     fn class(&self) -> Cow<'static, str> {
         Cow::from("hikvision/ds2cd2x32x_x")
     }
@@ -345,10 +472,13 @@ impl devices::Device for Device {
     fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
         Some(self)
     }
+    fn as_camera(&self) -> Option<&dyn camera::Camera> {
+        Some(self)
+    }
 }
 
 #[async_trait]
-impl Runnable for Device {
+impl<'s> Runnable for Device<'s> {
     async fn run(
         &self,
         mut exit_flag: async_flag::Receiver,
@@ -357,8 +487,13 @@ impl Runnable for Device {
         pin_mut!(runner);
         let mut runner = runner.fuse();
 
+        let alarms_runner = self.alarms_run();
+        pin_mut!(alarms_runner);
+        let mut alarms_runner = alarms_runner.fuse();
+
         select! {
             _ = runner => panic!("runner yielded"),
+            _ = alarms_runner => panic!("alarms_runner yielded"),
             () = exit_flag => {},
         }
 
@@ -377,11 +512,13 @@ pub enum SignalIdentifier {
     EventMotionDetection,
     EventLineDetection,
     EventFieldDetection,
+
+    Alarm,
 }
 impl signals::Identifier for SignalIdentifier {}
-impl signals::Device for Device {
+impl<'s> signals::Device for Device<'s> {
     fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
-        None
+        Some(&self.signals_targets_changed_waker)
     }
     fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
         Some(&self.signals_sources_changed_waker)
@@ -398,11 +535,13 @@ impl signals::Device for Device {
             SignalIdentifier::EventMotionDetection => &self.signal_event_motion_detection as &dyn signal::Base,
             SignalIdentifier::EventLineDetection => &self.signal_event_line_detection as &dyn signal::Base,
             SignalIdentifier::EventFieldDetection => &self.signal_event_field_detection as &dyn signal::Base,
+
+            SignalIdentifier::Alarm => &self.signal_alarm as &dyn signal::Base,
         }
     }
 }
 
-impl devices::gui_summary::Device for Device {
+impl<'s> devices::gui_summary::Device for Device<'s> {
     fn waker(&self) -> &devices::gui_summary::Waker {
         &self.gui_summary_waker
     }
@@ -413,7 +552,75 @@ impl devices::gui_summary::Device for Device {
     }
 }
 
-impl uri_cursor::Handler for Device {
+impl<'s> camera::Camera for Device<'s> {
+    fn waker(&self) -> &camera::Waker {
+        &self.camera_waker
+    }
+
+    fn health(&self) -> camera::Health {
+        match &*self.device_state.read() {
+            DeviceState::Initializing => camera::Health::Initializing,
+            DeviceState::Running { .. } => camera::Health::Running,
+            DeviceState::Error => camera::Health::Error,
+        }
+    }
+    fn rtsp_urls(&self) -> Box<[camera::RtspUrl]> {
+        match &*self.device_state.read() {
+            DeviceState::Running { rtsp_urls, .. } => Box::new([
+                camera::RtspUrl {
+                    name: "main",
+                    url: rtsp_urls.main.clone(),
+                },
+                camera::RtspUrl {
+                    name: "sub",
+                    url: rtsp_urls.sub.clone(),
+                },
+            ]),
+            _ => Box::new([]),
+        }
+    }
+    fn events(&self) -> Box<[camera::Event]> {
+        match &*self.device_state.read() {
+            DeviceState::Running { events, .. } => Box::new([
+                camera::Event {
+                    name: "camera_failure",
+                    active: events.camera_failure,
+                },
+                camera::Event {
+                    name: "video_loss",
+                    active: events.video_loss,
+                },
+                camera::Event {
+                    name: "tampering_detection",
+                    active: events.tampering_detection,
+                },
+                camera::Event {
+                    name: "motion_detection",
+                    active: events.motion_detection,
+                },
+                camera::Event {
+                    name: "line_detection",
+                    active: events.line_detection,
+                },
+                camera::Event {
+                    name: "field_detection",
+                    active: events.field_detection,
+                },
+            ]),
+            _ => Box::new([]),
+        }
+    }
+
+    fn snapshot(&self) -> BoxFuture<'_, Result<DynamicImage, Error>> {
+        async {
+            let api = self.api_build().await.context("api_build")?;
+            api.snapshot().await.context("snapshot")
+        }
+        .boxed()
+    }
+}
+
+impl<'s> uri_cursor::Handler for Device<'s> {
     fn handle(
         &self,
         request: web::Request,
@@ -196,7 +196,12 @@ pub mod hardware {
     use arrayvec::ArrayVec;
     use async_trait::async_trait;
     use futures::{future::FutureExt, join, stream::StreamExt};
-    use std::{fmt, iter, marker::PhantomData, time::Duration};
+    use std::{
+        fmt, iter,
+        marker::PhantomData,
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
 
     pub const OUTPUT_COUNT: usize = 14;
     pub type OutputValues = [bool; OUTPUT_COUNT];
@@ -245,16 +250,24 @@ pub mod hardware {
 
     #[derive(Debug)]
     pub struct Device<S: Specification> {
+        // outputs forced while emergency stop is latched, None if relays
+        // are not affected by emergency stop
+        emergency_stop_outputs: Option<OutputValues>,
+
         properties: Properties,
+        emergency_stop_latched: AtomicBool,
 
         poll_waker: async_waker::mpsc::Signal,
 
         _phantom: PhantomData<S>,
     }
     impl<S: Specification> Device<S> {
-        pub fn new() -> Self {
+        pub fn new(emergency_stop_outputs: Option<OutputValues>) -> Self {
             Self {
+                emergency_stop_outputs,
+
                 properties: Properties::new(),
+                emergency_stop_latched: AtomicBool::new(false),
 
                 poll_waker: async_waker::mpsc::Signal::new(),
 
@@ -322,10 +335,14 @@ pub mod hardware {
         ) -> Result<(), Error> {
             let outputs_pending = self.properties.outputs.device_pending();
 
+            let emergency_stop_outputs = self
+                .emergency_stop_outputs
+                .filter(|_| self.emergency_stop_latched.load(Ordering::Relaxed));
+
             let request = BusRequest {
-                outputs: outputs_pending
-                    .as_ref()
-                    .map(|outputs| BusRequestOutputs { values: **outputs }),
+                outputs: outputs_pending.as_ref().map(|outputs| BusRequestOutputs {
+                    values: emergency_stop_outputs.unwrap_or(**outputs),
+                }),
             };
             // we make a request no matter if it's required or not to confirm device is up
             let request_payload = request.to_payload();
@@ -347,14 +364,42 @@ pub mod hardware {
 
         async fn deinitialize(
             &self,
-            _driver: &ApplicationDriver<'_>,
+            driver: &ApplicationDriver<'_>,
         ) -> Result<(), Error> {
+            // leave all relays open, regardless of what logic requested
+            let request = BusRequest {
+                outputs: Some(BusRequestOutputs {
+                    values: [false; OUTPUT_COUNT],
+                }),
+            };
+            let request_payload = request.to_payload();
+            let response_payload = driver
+                .transaction_out_in(request_payload, None)
+                .await
+                .context("transaction")?;
+            let response = BusResponse::from_payload(&response_payload).context("response")?;
+
+            ensure!(response == BusResponse {});
+
             Ok(())
         }
 
         fn reset(&self) {
             self.properties.device_reset();
         }
+
+        fn emergency_stop_set(
+            &self,
+            latched: bool,
+        ) {
+            if self.emergency_stop_outputs.is_none() {
+                return;
+            }
+            if self.emergency_stop_latched.swap(latched, Ordering::Relaxed) != latched {
+                // outputs are sent again, either safe or requested by logic
+                self.properties.device_reset();
+            }
+        }
     }
 
     #[async_trait]
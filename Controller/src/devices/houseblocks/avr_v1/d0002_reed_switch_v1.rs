@@ -1,7 +1,7 @@
 pub mod logic {
     use super::{super::logic::runner, hardware};
     use crate::{
-        datatypes::resistance::Resistance,
+        datatypes::{quality::Quality, resistance::Resistance},
         devices,
         signals::{self, signal},
         util::{
@@ -16,6 +16,7 @@ pub mod logic {
     use itertools::Itertools;
     use serde::Serialize;
     use serde_big_array::BigArray;
+    use std::iter;
 
     #[derive(Debug)]
     pub struct DeviceFactory;
@@ -33,6 +34,7 @@ pub mod logic {
 
         signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
         signal_inputs: [signal::state_source::Signal<Resistance>; hardware::INPUTS_COUNT],
+        signal_quality: signal::state_source::Signal<Quality>,
 
         gui_summary_waker: devices::gui_summary::Waker,
     }
@@ -46,6 +48,7 @@ pub mod logic {
                 signal_inputs: array_init(|_input_index| {
                     signal::state_source::Signal::<Resistance>::new(None)
                 }),
+                signal_quality: signal::state_source::Signal::<Quality>::new(None),
 
                 gui_summary_waker: devices::gui_summary::Waker::new(),
             }
@@ -73,6 +76,13 @@ pub mod logic {
                     });
                 }
 
+                if self
+                    .signal_quality
+                    .set_one(Some(self.properties_remote.inputs.peek_quality()))
+                {
+                    signals_sources_changed = true;
+                }
+
                 gui_summary_changed = true;
             }
 
@@ -119,6 +129,7 @@ pub mod logic {
     #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
     pub enum SignalIdentifier {
         Input(usize),
+        Quality,
     }
     impl signals::Identifier for SignalIdentifier {}
     impl<'h> signals::Device for Device<'h> {
@@ -131,15 +142,22 @@ pub mod logic {
 
         type Identifier = SignalIdentifier;
         fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
-            self.signal_inputs
-                .iter()
-                .enumerate()
-                .map(|(input_index, signal_input)| {
-                    (
-                        SignalIdentifier::Input(input_index),
-                        signal_input as &dyn signal::Base,
-                    )
-                })
+            iter::empty()
+                .chain(
+                    self.signal_inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(input_index, signal_input)| {
+                            (
+                                SignalIdentifier::Input(input_index),
+                                signal_input as &dyn signal::Base,
+                            )
+                        }),
+                )
+                .chain([(
+                    SignalIdentifier::Quality,
+                    &self.signal_quality as &dyn signal::Base,
+                )])
                 .collect::<signals::ByIdentifier<_>>()
         }
     }
@@ -189,7 +207,7 @@ pub mod hardware {
         properties,
     };
     use crate::{
-        datatypes::resistance::Resistance,
+        datatypes::{quality::Quality, resistance::Resistance},
         util::{
             async_flag, async_waker,
             runnable::{Exited, Runnable},
@@ -230,6 +248,12 @@ pub mod hardware {
         pub fn device_reset(&self) -> bool {
             self.inputs.device_reset()
         }
+        pub fn device_quality_set(
+            &self,
+            quality: Quality,
+        ) -> bool {
+            self.inputs.device_quality_set(quality)
+        }
 
         pub fn remote(&self) -> PropertiesRemote {
             PropertiesRemote {
@@ -342,6 +366,14 @@ pub mod hardware {
                 self.properties.ins_changed_waker.wake();
             }
         }
+        fn quality_set(
+            &self,
+            quality: Quality,
+        ) {
+            if self.properties.device_quality_set(quality) {
+                self.properties.ins_changed_waker.wake();
+            }
+        }
     }
 
     #[async_trait]
@@ -1,7 +1,7 @@
 pub mod logic {
     use super::{super::logic::runner, hardware};
     use crate::{
-        datatypes::temperature::Temperature,
+        datatypes::{quality::Quality, temperature::Temperature},
         devices,
         signals::{self, signal},
         util::{
@@ -37,6 +37,8 @@ pub mod logic {
         signal_leds: [signal::state_target_last::Signal<bool>; hardware::LED_COUNT],
         signal_buzzer: signal::event_target_last::Signal<Duration>,
         signal_temperature: signal::state_source::Signal<Temperature>,
+        signal_quality: signal::state_source::Signal<Quality>,
+        signal_brown_out: signal::event_source::Signal<()>,
 
         gui_summary_waker: devices::gui_summary::Waker,
     }
@@ -56,6 +58,8 @@ pub mod logic {
                 }),
                 signal_buzzer: signal::event_target_last::Signal::<Duration>::new(),
                 signal_temperature: signal::state_source::Signal::<Temperature>::new(None),
+                signal_quality: signal::state_source::Signal::<Quality>::new(None),
+                signal_brown_out: signal::event_source::Signal::<()>::new(),
 
                 gui_summary_waker: devices::gui_summary::Waker::new(),
             }
@@ -147,10 +151,23 @@ pub mod logic {
                 if self.signal_temperature.set_one(temperature) {
                     signals_sources_changed = true;
                 }
+                if self
+                    .signal_quality
+                    .set_one(Some(self.properties_remote.ds18x20.peek_quality()))
+                {
+                    signals_sources_changed = true;
+                }
 
                 gui_summary_changed = true;
             }
 
+            // reported once for each device preparation
+            if let Some(Some(reset_cause)) = self.properties_remote.reset_cause.take_pending() {
+                if reset_cause.bod && self.signal_brown_out.push_one(()) {
+                    signals_sources_changed = true;
+                }
+            }
+
             if signals_sources_changed {
                 self.signals_sources_changed_waker.wake();
             }
@@ -214,6 +231,8 @@ pub mod logic {
         Led(usize),
         Buzzer,
         Temperature,
+        Quality,
+        BrownOut,
     }
     impl signals::Identifier for SignalIdentifier {}
     impl<'h> signals::Device for Device<'h> {
@@ -258,6 +277,14 @@ pub mod logic {
                         SignalIdentifier::Temperature,
                         &self.signal_temperature as &dyn signal::Base,
                     ),
+                    (
+                        SignalIdentifier::Quality,
+                        &self.signal_quality as &dyn signal::Base,
+                    ),
+                    (
+                        SignalIdentifier::BrownOut,
+                        &self.signal_brown_out as &dyn signal::Base,
+                    ),
                 ])
                 .collect::<signals::ByIdentifier<_>>()
         }
@@ -300,15 +327,21 @@ pub mod hardware {
         super::houseblocks_v1::common::{AddressDeviceType, Payload},
         datatypes::ds18x20::State as Ds18x20State,
         hardware::{
-            datatypes::ds18x20::SensorState as Ds18x20SensorState, driver::ApplicationDriver,
-            parser::Parser, runner, serializer::Serializer,
+            datatypes::ds18x20::SensorState as Ds18x20SensorState,
+            driver::{ApplicationDriver, PowerFlags},
+            parser::Parser,
+            runner,
+            serializer::Serializer,
         },
         properties,
     };
-    use crate::util::{
-        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
-        async_flag, async_waker,
-        runnable::{Exited, Runnable},
+    use crate::{
+        datatypes::quality::Quality,
+        util::{
+            async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+            async_flag, async_waker,
+            runnable::{Exited, Runnable},
+        },
     };
     use anyhow::{bail, ensure, Context, Error};
     use arrayvec::ArrayVec;
@@ -336,6 +369,7 @@ pub mod hardware {
         pub leds: properties::state_out::Remote<'p, LedValues>,
         pub buzzer: properties::event_out_last::Remote<'p, Duration>,
         pub ds18x20: properties::state_in::Remote<'p, Ds18x20State>,
+        pub reset_cause: properties::state_in::Remote<'p, PowerFlags>,
     }
 
     #[derive(Debug)]
@@ -347,6 +381,7 @@ pub mod hardware {
         leds: properties::state_out::Property<LedValues>,
         buzzer: properties::event_out_last::Property<Duration>,
         ds18x20: properties::state_in::Property<Ds18x20State>,
+        reset_cause: properties::state_in::Property<PowerFlags>,
     }
     impl Properties {
         pub fn new() -> Self {
@@ -358,6 +393,7 @@ pub mod hardware {
                 leds: properties::state_out::Property::<LedValues>::new([false; LED_COUNT]),
                 buzzer: properties::event_out_last::Property::<Duration>::new(),
                 ds18x20: properties::state_in::Property::<Ds18x20State>::new(),
+                reset_cause: properties::state_in::Property::<PowerFlags>::new(),
             }
         }
 
@@ -367,6 +403,13 @@ pub mod hardware {
             false // break
                 || self.keys.device_reset()
                 || self.ds18x20.device_reset()
+                || self.reset_cause.device_reset()
+        }
+        pub fn device_quality_set(
+            &self,
+            quality: Quality,
+        ) -> bool {
+            self.ds18x20.device_quality_set(quality)
         }
 
         pub fn remote(&self) -> PropertiesRemote {
@@ -378,6 +421,7 @@ pub mod hardware {
                 leds: self.leds.user_remote(),
                 buzzer: self.buzzer.user_remote(),
                 ds18x20: self.ds18x20.user_remote(),
+                reset_cause: self.reset_cause.user_remote(),
             }
         }
     }
@@ -583,6 +627,22 @@ pub mod hardware {
                 self.properties.ins_changed_waker.wake();
             }
         }
+        fn quality_set(
+            &self,
+            quality: Quality,
+        ) {
+            if self.properties.device_quality_set(quality) {
+                self.properties.ins_changed_waker.wake();
+            }
+        }
+        fn reset_cause_set(
+            &self,
+            power_flags: PowerFlags,
+        ) {
+            if self.properties.reset_cause.device_set(power_flags) {
+                self.properties.ins_changed_waker.wake();
+            }
+        }
     }
 
     #[async_trait]
@@ -1,7 +1,7 @@
 pub mod logic {
     use super::{super::logic::runner, hardware};
     use crate::{
-        datatypes::color_rgb_boolean::ColorRgbBoolean,
+        datatypes::{color_rgb_boolean::ColorRgbBoolean, quality::Quality},
         devices,
         signals::{self, signal},
         util::{
@@ -43,6 +43,7 @@ pub mod logic {
             hardware::DIGITAL_OUT_COUNT],
         signal_ds18x20s:
             [Option<signal::state_source::Signal<hardware::Ds18x20Value>>; hardware::DS18X20_COUNT],
+        signal_quality: signal::state_source::Signal<Quality>,
 
         gui_summary_waker: devices::gui_summary::Waker,
     }
@@ -117,6 +118,7 @@ pub mod logic {
                     .collect::<ArrayVec<_, { hardware::DS18X20_COUNT }>>()
                     .into_inner()
                     .unwrap(),
+                signal_quality: signal::state_source::Signal::<Quality>::new(None),
 
                 gui_summary_waker: devices::gui_summary::Waker::new(),
             }
@@ -179,6 +181,31 @@ pub mod logic {
                 self.gui_summary_waker.wake();
             }
         }
+        // worst of all inputs, blocks not enabled are never read so they are
+        // skipped, None if there is nothing to read
+        fn quality(&self) -> Option<Quality> {
+            iter::empty()
+                .chain(
+                    self.signal_analog_ins
+                        .iter()
+                        .any(|signal_analog_in| signal_analog_in.is_some())
+                        .then(|| self.properties_remote.analog_ins.peek_quality()),
+                )
+                .chain(
+                    self.signal_digital_ins
+                        .iter()
+                        .any(|signal_digital_in| signal_digital_in.is_some())
+                        .then(|| self.properties_remote.digital_ins.peek_quality()),
+                )
+                .chain(
+                    self.signal_ds18x20s
+                        .iter()
+                        .any(|signal_ds18x20| signal_ds18x20.is_some())
+                        .then(|| self.properties_remote.ds18x20s.peek_quality()),
+                )
+                .min()
+        }
+
         fn properties_ins_changed(&self) {
             let mut signals_sources_changed = false;
             let mut gui_summary_changed = false;
@@ -270,6 +297,10 @@ pub mod logic {
                 gui_summary_changed = true;
             }
 
+            if self.signal_quality.set_one(self.quality()) {
+                signals_sources_changed = true;
+            }
+
             if signals_sources_changed {
                 self.signals_sources_changed_waker.wake();
             }
@@ -334,6 +365,7 @@ pub mod logic {
         DigitalIn(usize),
         DigitalOut(usize),
         Ds18x20(usize),
+        Quality,
     }
     impl signals::Identifier for SignalIdentifier {}
     impl<'h> signals::Device for Device<'h> {
@@ -391,6 +423,10 @@ pub mod logic {
                         })
                     },
                 ))
+                .chain([(
+                    SignalIdentifier::Quality,
+                    &self.signal_quality as &dyn signal::Base,
+                )])
                 .collect::<signals::ByIdentifier<_>>()
         }
     }
@@ -548,7 +584,7 @@ pub mod hardware {
         properties,
     };
     use crate::{
-        datatypes::{color_rgb_boolean::ColorRgbBoolean, voltage::Voltage},
+        datatypes::{color_rgb_boolean::ColorRgbBoolean, quality::Quality, voltage::Voltage},
         util::{
             async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
             async_flag, async_waker,
@@ -783,6 +819,16 @@ pub mod hardware {
                 || self.digital_ins.device_reset()
                 || self.ds18x20s.device_reset()
         }
+        pub fn device_quality_set(
+            &self,
+            quality: Quality,
+        ) -> bool {
+            // all properties must be updated, so no short circuit here
+            false
+                | self.analog_ins.device_quality_set(quality)
+                | self.digital_ins.device_quality_set(quality)
+                | self.ds18x20s.device_quality_set(quality)
+        }
 
         pub fn remote(&self) -> PropertiesRemote {
             PropertiesRemote {
@@ -1115,6 +1161,14 @@ pub mod hardware {
                 self.properties.ins_changed_waker.wake();
             }
         }
+        fn quality_set(
+            &self,
+            quality: Quality,
+        ) {
+            if self.properties.device_quality_set(quality) {
+                self.properties.ins_changed_waker.wake();
+            }
+        }
     }
 
     #[async_trait]
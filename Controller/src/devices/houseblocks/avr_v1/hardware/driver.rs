@@ -6,14 +6,20 @@ use super::{
     parser::Parser,
 };
 use anyhow::{ensure, Context, Error};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
 use std::time::Duration;
 
-#[derive(Debug)]
+// cause of last device reset, multiple flags may be set
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
 pub struct PowerFlags {
-    wdt: bool,
-    bod: bool,
-    ext_reset: bool,
-    pon: bool,
+    // watchdog, also used by reboot command
+    pub wdt: bool,
+    // brown-out, supply voltage dropped below threshold
+    pub bod: bool,
+    pub ext_reset: bool,
+    pub pon: bool,
 }
 
 #[derive(Debug)]
@@ -26,6 +32,9 @@ pub struct Version {
 pub struct Driver<'m> {
     master: &'m Master,
     address: Address,
+
+    // last transaction with valid response from the device
+    transaction_last: Mutex<Option<DateTime<Utc>>>,
 }
 impl<'m> Driver<'m> {
     const TIMEOUT_DEFAULT: Duration = Duration::from_millis(250);
@@ -34,13 +43,22 @@ impl<'m> Driver<'m> {
         master: &'m Master,
         address: Address,
     ) -> Self {
-        Self { master, address }
+        Self {
+            master,
+            address,
+
+            transaction_last: Mutex::new(None),
+        }
     }
 
     pub fn address(&self) -> &Address {
         &self.address
     }
 
+    pub fn transaction_last(&self) -> Option<DateTime<Utc>> {
+        *self.transaction_last.lock()
+    }
+
     // Transactions
     async fn transaction_out(
         &self,
@@ -72,6 +90,8 @@ impl<'m> Driver<'m> {
             .await
             .context("transaction_out_in")?;
 
+        *self.transaction_last.lock() = Some(Utc::now());
+
         Ok(result)
     }
 
@@ -172,18 +192,40 @@ impl<'m> Driver<'m> {
     }
 
     // Procedures
-    pub async fn prepare(&self) -> Result<(), Error> {
+    // returns cause of last reset not triggered by this procedure
+    pub async fn prepare(&self) -> Result<PowerFlags, Error> {
         // Driver may be already initialized, check it.
         let healthcheck_result = self.healthcheck(false).await;
-        if healthcheck_result.is_ok() {
+        let power_flags = if healthcheck_result.is_ok() {
+            let power_flags = self
+                .read_clear_power_flags(false)
+                .await
+                .context("read power flags")?;
+
             // Is initialized, perform reboot
             self.reboot(false).await.context("deinitialize reboot")?;
-        }
 
-        // We should be in service mode
-        self.healthcheck(true)
-            .await
-            .context("service mode healthcheck")?;
+            // We should be in service mode
+            self.healthcheck(true)
+                .await
+                .context("service mode healthcheck")?;
+
+            // Clear flags set by reboot above
+            self.read_clear_power_flags(true)
+                .await
+                .context("service mode clear power flags")?;
+
+            power_flags
+        } else {
+            // We should be in service mode
+            self.healthcheck(true)
+                .await
+                .context("service mode healthcheck")?;
+
+            self.read_clear_power_flags(true)
+                .await
+                .context("service mode read power flags")?
+        };
 
         // Check application up to date
         let application_checksum = self
@@ -204,7 +246,7 @@ impl<'m> Driver<'m> {
             .await
             .context("application mode healthcheck")?;
 
-        Ok(())
+        Ok(power_flags)
     }
 }
 
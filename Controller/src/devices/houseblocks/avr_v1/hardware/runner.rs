@@ -3,10 +3,12 @@ use super::{
         common::{Address, AddressDeviceType, AddressSerial},
         master::Master,
     },
-    driver::{ApplicationDriver, Driver},
+    driver::{ApplicationDriver, Driver, PowerFlags},
 };
 use crate::{
+    datatypes::quality::Quality,
     devices,
+    modules::{emergency_stop, events},
     util::{
         async_ext::optional::StreamOrPending,
         async_flag, async_waker,
@@ -15,6 +17,7 @@ use crate::{
 };
 use anyhow::{Context, Error};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use futures::{
     future::{Either, FutureExt},
     join, select,
@@ -44,6 +47,29 @@ pub trait BusDevice {
 
     // the device was internally reset, so need to clear internal state
     fn reset(&self) {}
+
+    // bus communication status changed, values read from the device should
+    // be marked accordingly
+    fn quality_set(
+        &self,
+        _quality: Quality,
+    ) {
+    }
+
+    // cause of last device reset, read during preparation, before initialize
+    fn reset_cause_set(
+        &self,
+        _power_flags: PowerFlags,
+    ) {
+    }
+
+    // controller-wide emergency stop, called before each poll. while latched
+    // outputs should be driven to their safe states, bypassing logic
+    fn emergency_stop_set(
+        &self,
+        _latched: bool,
+    ) {
+    }
 }
 
 pub trait Device: BusDevice + Sync + Send + Sized + fmt::Debug {
@@ -62,18 +88,40 @@ pub enum DeviceState {
     Running,
 }
 
+#[derive(Debug)]
+struct Diagnostics {
+    errors_consecutive: usize,
+    // delay before next initialization attempt, while in error state
+    retry_delay: Option<Duration>,
+    // device is rebooted during preparation, so this is the start of its
+    // uptime (avr_v1 has no command to read it)
+    running_since: Option<DateTime<Utc>>,
+    reset_cause: Option<PowerFlags>,
+}
+
+// doubled for each consecutive error
+const ERROR_RESTART_DELAY: Duration = Duration::from_secs(10);
+const ERROR_RESTART_DELAY_MAX: Duration = Duration::from_secs(300);
+fn error_restart_delay(errors_consecutive: usize) -> Duration {
+    let exponent = errors_consecutive.saturating_sub(1).min(16) as u32;
+    min(
+        ERROR_RESTART_DELAY.saturating_mul(1 << exponent),
+        ERROR_RESTART_DELAY_MAX,
+    )
+}
+
 #[derive(Debug)]
 pub struct Runner<'m, D: Device> {
     driver: Driver<'m>,
     device: D,
 
     device_state: Mutex<DeviceState>,
+    diagnostics: Mutex<Diagnostics>,
 
     gui_summary_waker: devices::gui_summary::Waker,
 }
 impl<'m, D: Device> Runner<'m, D> {
     const POLL_DELAY_MAX: Duration = Duration::from_secs(5);
-    const ERROR_RESTART_DELAY: Duration = Duration::from_secs(10);
 
     pub fn new(
         master: &'m Master,
@@ -88,12 +136,19 @@ impl<'m, D: Device> Runner<'m, D> {
             },
         );
         let device_state = Mutex::new(DeviceState::Initializing);
+        let diagnostics = Mutex::new(Diagnostics {
+            errors_consecutive: 0,
+            retry_delay: None,
+            running_since: None,
+            reset_cause: None,
+        });
 
         Self {
             driver,
             device,
 
             device_state,
+            diagnostics,
 
             gui_summary_waker: devices::gui_summary::Waker::new(),
         }
@@ -108,12 +163,19 @@ impl<'m, D: Device> Runner<'m, D> {
         mut exit_flag: async_flag::Receiver,
     ) -> Result<Exited, Error> {
         *self.device_state.lock() = DeviceState::Initializing;
+        self.diagnostics.lock().retry_delay = None;
         self.gui_summary_waker.wake();
 
         self.device.reset();
+        self.device.quality_set(Quality::Uncertain);
 
         // Hardware initializing & avr_v1
-        self.driver.prepare().await.context("initial prepare")?;
+        let power_flags = self.driver.prepare().await.context("initial prepare")?;
+        if power_flags.bod {
+            log::warn!("device {} was reset by brown-out", self.driver.address());
+        }
+        self.diagnostics.lock().reset_cause = Some(power_flags);
+        self.device.reset_cause_set(power_flags);
 
         // Device is prepared in application mode, we can start application driver
         let application_driver = ApplicationDriver::new(&self.driver);
@@ -126,6 +188,11 @@ impl<'m, D: Device> Runner<'m, D> {
 
         // Device is fully initialized
         *self.device_state.lock() = DeviceState::Running;
+        {
+            let mut diagnostics = self.diagnostics.lock();
+            diagnostics.errors_consecutive = 0;
+            diagnostics.running_since = Some(Utc::now());
+        }
         self.gui_summary_waker.wake();
 
         // Main loop
@@ -136,7 +203,12 @@ impl<'m, D: Device> Runner<'m, D> {
         );
         let mut device_poll_waker = device_poll_waker.fuse();
 
+        let emergency_stop = emergency_stop::emergency_stop();
+        let mut emergency_stop_receiver = emergency_stop.receiver();
+
         loop {
+            self.device.emergency_stop_set(emergency_stop.latched());
+
             // Poll
             self.device
                 .poll(&application_driver)
@@ -152,6 +224,7 @@ impl<'m, D: Device> Runner<'m, D> {
             select! {
                 () = tokio::time::sleep(poll_delay).fuse() => {},
                 () = device_poll_waker.select_next_some() => {},
+                () = emergency_stop_receiver.select_next_some() => {},
                 () = exit_flag => break,
             };
         }
@@ -163,8 +236,10 @@ impl<'m, D: Device> Runner<'m, D> {
             .context("deinitialize")?;
 
         self.device.reset();
+        self.device.quality_set(Quality::Uncertain);
 
         *self.device_state.lock() = DeviceState::Initializing;
+        self.diagnostics.lock().running_since = None;
         self.gui_summary_waker.wake();
 
         Ok(Exited)
@@ -183,14 +258,28 @@ impl<'m, D: Device> Runner<'m, D> {
                 Err(error) => error,
             };
             log::error!("device {} failed: {:?}", self.driver.address(), error);
+            events::reporter().report(
+                events::Severity::Error,
+                format!("houseblocks/avr_v1/{}", self.driver.address()),
+                format!("device failed: {:#}", error),
+            );
 
             self.device.reset();
+            self.device.quality_set(Quality::Bad);
 
             *self.device_state.lock() = DeviceState::Error;
+            let retry_delay = {
+                let mut diagnostics = self.diagnostics.lock();
+                diagnostics.errors_consecutive += 1;
+                diagnostics.running_since = None;
+                let retry_delay = error_restart_delay(diagnostics.errors_consecutive);
+                diagnostics.retry_delay = Some(retry_delay);
+                retry_delay
+            };
             self.gui_summary_waker.wake();
 
             select! {
-                () = tokio::time::sleep(Self::ERROR_RESTART_DELAY).fuse() => {},
+                () = tokio::time::sleep(retry_delay).fuse() => {},
                 () = exit_flag => break,
             }
         }
@@ -234,6 +323,11 @@ impl<'m, D: Device> Runnable for Runner<'m, D> {
 #[derive(Debug, Serialize)]
 pub struct GuiSummary {
     device_state: DeviceState,
+    transaction_last: Option<DateTime<Utc>>,
+    errors_consecutive: usize,
+    retry_delay: Option<Duration>,
+    running_since: Option<DateTime<Utc>>,
+    reset_cause: Option<PowerFlags>,
 }
 impl<'m, D: Device> devices::gui_summary::Device for Runner<'m, D> {
     fn waker(&self) -> &devices::gui_summary::Waker {
@@ -243,7 +337,235 @@ impl<'m, D: Device> devices::gui_summary::Device for Runner<'m, D> {
     type Value = GuiSummary;
     fn value(&self) -> Self::Value {
         let device_state = *self.device_state.lock();
+        let diagnostics = self.diagnostics.lock();
+
+        Self::Value {
+            device_state,
+            transaction_last: self.driver.transaction_last(),
+            errors_consecutive: diagnostics.errors_consecutive,
+            retry_delay: diagnostics.retry_delay,
+            running_since: diagnostics.running_since,
+            reset_cause: diagnostics.reset_cause,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            super::super::houseblocks_v1::{
+                common::{Address, AddressDeviceType, AddressSerial, Frame, Payload},
+                fault_injection::{
+                    Configuration as FaultInjectionConfiguration, FaultInjectedBus, Injector,
+                },
+                master::{Bus, Master},
+            },
+            driver::ApplicationDriver,
+        },
+        error_restart_delay, BusDevice, Device, DeviceState, Runner,
+    };
+    use crate::{
+        datatypes::quality::Quality,
+        util::{async_flag, async_waker, runnable::Runnable},
+    };
+    use anyhow::{bail, ensure, Context, Error};
+    use async_trait::async_trait;
+    use futures::{join, stream::StreamExt};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    // avr_v1 device side of the bus, handles service / application mode
+    // switching and routines used by driver prepare, application mode accepts
+    // healthcheck only
+    #[derive(Debug)]
+    struct EmulatedBus {
+        address: Address,
+        application_mode: bool,
+        response: Option<Box<[u8]>>,
+    }
+    impl EmulatedBus {
+        fn new(address: Address) -> Self {
+            Self {
+                address,
+                application_mode: true,
+                response: None,
+            }
+        }
+    }
+    impl Bus for EmulatedBus {
+        fn purge(&mut self) -> Result<(), Error> {
+            self.response = None;
+            Ok(())
+        }
+        fn write(
+            &mut self,
+            data: &[u8],
+        ) -> Result<(), Error> {
+            let service_mode = match data[1] {
+                b'}' => true,
+                b'>' => false,
+                _ => bail!("invalid direction"),
+            };
+            // begin, direction, device type, serial, crc16 ... end
+            let payload = &data[1 + 1 + 4 + 8 + 4..data.len() - 1];
+
+            // device in the other mode does not respond
+            if service_mode == self.application_mode {
+                return Ok(());
+            }
+
+            let response: Option<&[u8]> = match (service_mode, payload) {
+                (_, b"") => Some(b""),
+                (_, b"@") => Some(b"0001"),
+                (_, b"!") => {
+                    self.application_mode = false;
+                    None
+                }
+                (true, b"C") => Some(b"1234"),
+                (true, b"R") => {
+                    self.application_mode = true;
+                    None
+                }
+                _ => bail!("unexpected payload"),
+            };
+            self.response = response.map(|response| {
+                Frame::in_build(
+                    service_mode,
+                    &self.address,
+                    &Payload::new(Box::from(response)).unwrap(),
+                )
+            });
+            Ok(())
+        }
+        fn read(&mut self) -> Result<Box<[u8]>, Error> {
+            Ok(self.response.take().unwrap_or_default())
+        }
+    }
+
+    #[derive(Debug)]
+    struct HealthcheckDevice {
+        polls: AtomicUsize,
+        failures: AtomicUsize,
+        poll_signal: async_waker::mpsc::Signal,
+    }
+    impl HealthcheckDevice {
+        fn new() -> Self {
+            Self {
+                polls: AtomicUsize::new(0),
+                failures: AtomicUsize::new(0),
+                poll_signal: async_waker::mpsc::Signal::new(),
+            }
+        }
+    }
+    #[async_trait]
+    impl BusDevice for HealthcheckDevice {
+        async fn initialize(
+            &self,
+            _driver: &ApplicationDriver<'_>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn poll_delay(&self) -> Option<Duration> {
+            Some(Duration::from_millis(100))
+        }
+        async fn poll(
+            &self,
+            driver: &ApplicationDriver<'_>,
+        ) -> Result<(), Error> {
+            let response = driver
+                .transaction_out_in(Payload::new(Box::from(*b"")).unwrap(), None)
+                .await
+                .context("transaction_out_in")?;
+            ensure!(response.as_bytes().is_empty(), "invalid response");
+
+            self.polls.fetch_add(1, Ordering::SeqCst);
+            self.poll_signal.wake();
+            Ok(())
+        }
+
+        async fn deinitialize(
+            &self,
+            _driver: &ApplicationDriver<'_>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn quality_set(
+            &self,
+            quality: Quality,
+        ) {
+            if quality == Quality::Bad {
+                self.failures.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+    impl Device for HealthcheckDevice {
+        fn device_type_name() -> &'static str {
+            "Healthcheck"
+        }
+        fn address_device_type() -> AddressDeviceType {
+            AddressDeviceType::new(*b"0001").unwrap()
+        }
+
+        fn poll_waker(&self) -> Option<&async_waker::mpsc::Signal> {
+            None
+        }
+
+        fn as_runnable(&self) -> Option<&dyn Runnable> {
+            None
+        }
+    }
+
+    #[test]
+    fn error_restart_delay_backoff() {
+        assert_eq!(error_restart_delay(1), Duration::from_secs(10));
+        assert_eq!(error_restart_delay(2), Duration::from_secs(20));
+        assert_eq!(error_restart_delay(3), Duration::from_secs(40));
+        assert_eq!(error_restart_delay(5), Duration::from_secs(160));
+        assert_eq!(error_restart_delay(6), Duration::from_secs(300));
+        assert_eq!(error_restart_delay(usize::MAX), Duration::from_secs(300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recovery_under_bus_faults() {
+        const POLLS: usize = 200;
+
+        let address_serial = AddressSerial::new(*b"98765432").unwrap();
+        let bus = EmulatedBus::new(Address {
+            device_type: HealthcheckDevice::address_device_type(),
+            serial: address_serial,
+        });
+        let bus = FaultInjectedBus::new(
+            bus,
+            Injector::new(FaultInjectionConfiguration {
+                drop_rate: 0.05,
+                corrupt_rate: 0.05,
+                seed: Some(0),
+            }),
+        );
+        let master = Master::new_with_bus(bus);
+        let runner = Runner::new(&master, address_serial, HealthcheckDevice::new());
+
+        let (exit_flag_sender, exit_flag_receiver) = async_flag::pair();
+        let runner_runner = runner.run(exit_flag_receiver);
+        // waits for polls instead of time, as virtual clock advances while the
+        // runner waits for master thread
+        let polls_runner = async {
+            let mut poll_receiver = runner.device().poll_signal.receiver();
+            while runner.device().polls.load(Ordering::SeqCst) < POLLS {
+                poll_receiver.next().await;
+            }
+            assert_eq!(*runner.device_state.lock(), DeviceState::Running);
+            exit_flag_sender.signal();
+        };
+        join!(runner_runner, polls_runner);
 
-        Self::Value { device_state }
+        // every failure was followed by successful preparation and polling
+        assert!(runner.device().failures.load(Ordering::SeqCst) > 0);
+        assert_eq!(runner.diagnostics.lock().errors_consecutive, 0);
     }
 }
@@ -1,3 +1,4 @@
+use crate::datatypes::quality::Quality;
 use parking_lot::Mutex;
 
 #[derive(Debug)]
@@ -6,6 +7,7 @@ where
     T: Eq + Clone + Send + Sync + 'static,
 {
     value: Option<T>,
+    quality: Quality,
     user_pending: bool,
 }
 impl<T> State<T>
@@ -15,6 +17,7 @@ where
     pub fn new() -> Self {
         Self {
             value: None,
+            quality: Quality::Uncertain,
             user_pending: false,
         }
     }
@@ -60,11 +63,12 @@ where
     ) -> bool {
         let mut state = self.state.lock();
 
-        if state.value.as_ref() == Some(&value) {
+        if state.value.as_ref() == Some(&value) && state.quality == Quality::Good {
             return false;
         }
 
         state.value.replace(value);
+        state.quality = Quality::Good;
         state.user_pending = true;
 
         drop(state);
@@ -84,6 +88,26 @@ where
 
         drop(state);
 
+        true
+    }
+    // value is kept, eg. so last known value is still available when
+    // communication is uncertain, successful read marks it good again
+    #[must_use = "use this value to wake properties changed waker"]
+    pub fn device_quality_set(
+        &self,
+        quality: Quality,
+    ) -> bool {
+        let mut state = self.state.lock();
+
+        if state.quality == quality {
+            return false;
+        }
+
+        state.quality = quality;
+        state.user_pending = true;
+
+        drop(state);
+
         true
     }
 }
@@ -127,6 +151,16 @@ where
 
         value
     }
+
+    pub fn peek_quality(&self) -> Quality {
+        let state = self.property.state.lock();
+
+        let quality = state.quality;
+
+        drop(state);
+
+        quality
+    }
 }
 
 #[cfg(test)]
@@ -156,4 +190,27 @@ mod tests {
         assert!(stream.take_pending().unwrap().is_none());
         assert!(stream.take_pending().is_none());
     }
+
+    #[test]
+    fn test_quality() {
+        let property = Property::<usize>::new();
+        let stream = property.user_remote();
+
+        assert_eq!(stream.peek_quality(), Quality::Uncertain);
+
+        assert!(property.device_set(1));
+        assert_eq!(stream.peek_quality(), Quality::Good);
+        assert_eq!(stream.take_pending().unwrap().unwrap(), 1);
+
+        // value is kept, only quality changes
+        assert!(property.device_quality_set(Quality::Bad));
+        assert!(!property.device_quality_set(Quality::Bad));
+        assert_eq!(stream.take_pending().unwrap().unwrap(), 1);
+        assert_eq!(stream.peek_quality(), Quality::Bad);
+
+        // same value read again restores quality
+        assert!(property.device_set(1));
+        assert_eq!(stream.peek_quality(), Quality::Good);
+        assert_eq!(stream.take_pending().unwrap().unwrap(), 1);
+    }
 }
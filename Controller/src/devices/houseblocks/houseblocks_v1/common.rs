@@ -371,6 +371,40 @@ impl Frame {
 
         Ok(payload)
     }
+
+    // device side of in_parse, for bus emulation in tests
+    #[cfg(test)]
+    pub fn in_build(
+        service_mode: bool,
+        address: &Address,
+        payload: &Payload,
+    ) -> Box<[u8]> {
+        let char_direction = if service_mode {
+            &Self::CHAR_DIRECTION_SERVICE_IN
+        } else {
+            &Self::CHAR_DIRECTION_NORMAL_IN
+        };
+
+        let mut crc16 = Self::CRC_HASHER.digest();
+        crc16.update(slice::from_ref(char_direction));
+        crc16.update(address.device_type.as_bytes());
+        crc16.update(address.serial.as_bytes());
+        crc16.update(payload.as_bytes());
+        let crc16 = crc16.finalize();
+        let crc16 = hex::encode_upper(crc16.to_be_bytes());
+        let crc16 = crc16.as_bytes();
+
+        let frame = [
+            slice::from_ref(&Self::CHAR_BEGIN),
+            slice::from_ref(char_direction),
+            crc16,
+            payload.as_bytes(),
+            slice::from_ref(&Self::CHAR_END),
+        ]
+        .concat();
+
+        Box::from(frame)
+    }
 }
 #[cfg(test)]
 mod tests_frame {
@@ -0,0 +1,139 @@
+// simulates unreliable bus for robustness tests
+// outgoing frames are dropped (device does not respond, so transaction times
+// out), incoming frames are corrupted by single bit flip (so crc check fails)
+use super::master::Bus;
+use anyhow::Error;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Configuration {
+    // probability of outgoing frame not being sent, 0.0 - 1.0
+    pub drop_rate: f64,
+    // probability of incoming frame being corrupted, 0.0 - 1.0
+    pub corrupt_rate: f64,
+
+    // for reproducible runs, random if None
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct Injector {
+    configuration: Configuration,
+    rng: StdRng,
+}
+impl Injector {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!((0.0..=1.0).contains(&configuration.drop_rate));
+        assert!((0.0..=1.0).contains(&configuration.corrupt_rate));
+
+        let rng = match configuration.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self { configuration, rng }
+    }
+
+    pub fn drop_next(&mut self) -> bool {
+        self.rng.gen_bool(self.configuration.drop_rate)
+    }
+
+    pub fn corrupt_next(&mut self) -> bool {
+        self.rng.gen_bool(self.configuration.corrupt_rate)
+    }
+    pub fn corrupt(
+        &mut self,
+        frame: &mut [u8],
+    ) {
+        if frame.is_empty() {
+            return;
+        }
+        let bit = self.rng.gen_range(0..frame.len() * 8);
+        frame_bit_flip(frame, bit);
+    }
+}
+
+// wraps the real bus, so regular master code path is not affected
+#[derive(Debug)]
+pub struct FaultInjectedBus<B: Bus> {
+    bus: B,
+    injector: Injector,
+}
+impl<B: Bus> FaultInjectedBus<B> {
+    pub fn new(
+        bus: B,
+        injector: Injector,
+    ) -> Self {
+        Self { bus, injector }
+    }
+}
+impl<B: Bus> Bus for FaultInjectedBus<B> {
+    fn purge(&mut self) -> Result<(), Error> {
+        self.bus.purge()
+    }
+    fn write(
+        &mut self,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if self.injector.drop_next() {
+            log::trace!("fault injection: dropping outgoing data");
+            return Ok(());
+        }
+        self.bus.write(data)
+    }
+    fn read(&mut self) -> Result<Box<[u8]>, Error> {
+        let mut data = self.bus.read()?;
+        if !data.is_empty() && self.injector.corrupt_next() {
+            log::trace!("fault injection: corrupting incoming data");
+            self.injector.corrupt(&mut data);
+        }
+        Ok(data)
+    }
+}
+
+fn frame_bit_flip(
+    frame: &mut [u8],
+    bit: usize,
+) {
+    frame[bit / 8] ^= 1 << (bit % 8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::common::{Address, AddressDeviceType, AddressSerial, Frame},
+        frame_bit_flip, Configuration, Injector,
+    };
+
+    #[test]
+    fn rates() {
+        let mut injector = Injector::new(Configuration {
+            drop_rate: 0.25,
+            corrupt_rate: 0.0,
+            seed: Some(0),
+        });
+
+        let drops = (0..10_000).filter(|_| injector.drop_next()).count();
+        assert!((2_000..3_000).contains(&drops));
+
+        assert!(!(0..10_000).any(|_| injector.corrupt_next()));
+    }
+
+    #[test]
+    fn corrupted_frame_rejected() {
+        let address = Address {
+            device_type: AddressDeviceType::new(*b"0001").unwrap(),
+            serial: AddressSerial::new(*b"98765432").unwrap(),
+        };
+        let frame = b"\n<A721ChujDupaKamieniKupa\r";
+
+        assert!(Frame::in_parse(frame, false, &address).is_ok());
+
+        // crc16 detects all single bit errors
+        for bit in 0..frame.len() * 8 {
+            let mut frame = *frame;
+            frame_bit_flip(&mut frame, bit);
+            assert!(Frame::in_parse(&frame, false, &address).is_err());
+        }
+    }
+}
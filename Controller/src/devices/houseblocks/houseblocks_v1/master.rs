@@ -5,7 +5,7 @@ use crate::{
             Descriptor as FtdiDescriptor, DeviceConfiguration as FtdiDeviceConfiguration,
             DeviceFailSafe as FtdiDeviceFailSafe,
         },
-        Bits, Configuration as SerialConfiguration, Parity, StopBits,
+        Bits, Configuration as SerialConfiguration, Parity, Rs485DirectionRts, StopBits,
     },
     modules::module_path::{ModulePath, ModulePathName},
 };
@@ -35,11 +35,36 @@ enum Transaction {
     },
 }
 
+// byte stream the frames are exchanged over, ftdi device in production
+pub trait Bus {
+    fn purge(&mut self) -> Result<(), Error>;
+    fn write(
+        &mut self,
+        data: &[u8],
+    ) -> Result<(), Error>;
+    // returns empty buffer if nothing was received within latency timer
+    fn read(&mut self) -> Result<Box<[u8]>, Error>;
+}
+impl Bus for FtdiDeviceFailSafe {
+    fn purge(&mut self) -> Result<(), Error> {
+        FtdiDeviceFailSafe::purge(self)
+    }
+    fn write(
+        &mut self,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        FtdiDeviceFailSafe::write(self, data)
+    }
+    fn read(&mut self) -> Result<Box<[u8]>, Error> {
+        FtdiDeviceFailSafe::read(self)
+    }
+}
+
 #[derive(Debug)]
-struct Driver {
-    ftdi_device: FtdiDeviceFailSafe,
+struct Driver<B: Bus> {
+    bus: B,
 }
-impl Driver {
+impl<B: Bus> Driver<B> {
     const SERIAL_CONFIGURATION: SerialConfiguration = SerialConfiguration {
         baud_rate: 115_200,
         bits: Bits::Bits7,
@@ -48,18 +73,11 @@ impl Driver {
     };
     const FTDI_DEVICE_CONFIGURATION: FtdiDeviceConfiguration = FtdiDeviceConfiguration {
         latency_timer_ms: 10,
+        rs485_direction_rts: None,
     };
 
-    pub fn new(ftdi_descriptor: FtdiDescriptor) -> Self {
-        Self {
-            ftdi_device: FtdiDeviceFailSafe::new(
-                ftdi_descriptor,
-                Self::SERIAL_CONFIGURATION,
-                Self::FTDI_DEVICE_CONFIGURATION,
-                3,
-                Duration::from_secs(1),
-            ),
-        }
+    pub fn new(bus: B) -> Self {
+        Self { bus }
     }
 
     fn phase_frame_out(
@@ -69,7 +87,7 @@ impl Driver {
         payload: &Payload,
     ) -> Result<(), Error> {
         let frame = Frame::out_build(service_mode, address, payload);
-        self.ftdi_device.write(&frame).context("write")?;
+        self.bus.write(&frame).context("write")?;
         Ok(())
     }
     fn phase_frame_in(
@@ -83,7 +101,7 @@ impl Driver {
 
         let mut timeout_left = *timeout;
         loop {
-            let frame = self.ftdi_device.read().context("read")?;
+            let frame = self.bus.read().context("read")?;
             if frame.is_empty() {
                 match timeout_left.checked_sub(Duration::from_millis(
                     Self::FTDI_DEVICE_CONFIGURATION.latency_timer_ms as u64,
@@ -125,19 +143,15 @@ impl Driver {
                 log::warn!("Frame::CHAR_END not on end of message. Noise?");
             }
 
-            let payload = Frame::in_parse(
-                &frame_buffer[char_begin_position..char_end_position + 1],
-                service_mode,
-                address,
-            )
-            .context("payload")?;
+            let frame = &frame_buffer[char_begin_position..char_end_position + 1];
+            let payload = Frame::in_parse(frame, service_mode, address).context("payload")?;
 
             return Ok(payload);
         }
     }
 
     fn phase_device_discovery_out(&mut self) -> Result<(), Error> {
-        self.ftdi_device.write(b"\x07").context("write")?;
+        self.bus.write(b"\x07").context("write")?;
         Ok(())
     }
     fn phase_device_discovery_in(
@@ -149,7 +163,7 @@ impl Driver {
 
         let mut timeout_left = *timeout;
         loop {
-            let frame = self.ftdi_device.read().context("read")?;
+            let frame = self.bus.read().context("read")?;
             if frame.is_empty() {
                 match timeout_left.checked_sub(Duration::from_millis(
                     Self::FTDI_DEVICE_CONFIGURATION.latency_timer_ms as u64,
@@ -218,7 +232,7 @@ impl Driver {
         &mut self,
         in_timeout: &Duration,
     ) -> Result<Address, Error> {
-        self.ftdi_device.purge().context("purge")?;
+        self.bus.purge().context("purge")?;
         self.phase_device_discovery_out()
             .context("phase_device_discovery_out")?;
         let address = self
@@ -228,6 +242,29 @@ impl Driver {
     }
 }
 
+impl Driver<FtdiDeviceFailSafe> {
+    fn ftdi_bus(
+        ftdi_descriptor: FtdiDescriptor,
+        rs485_direction_rts: Option<Rs485DirectionRts>,
+    ) -> FtdiDeviceFailSafe {
+        FtdiDeviceFailSafe::new(
+            ftdi_descriptor,
+            Self::SERIAL_CONFIGURATION,
+            FtdiDeviceConfiguration {
+                rs485_direction_rts,
+                ..Self::FTDI_DEVICE_CONFIGURATION
+            },
+            3,
+            Duration::from_secs(1),
+        )
+    }
+}
+
+// single bus (ftdi device), transactions are executed one by one by its own
+// worker thread, as the bus is half duplex
+// each master has separate thread and ftdi context, so multiple buses run
+// their transactions concurrently and don't need common scheduling
+// the thread is blocked on usb io most of the time, not on cpu
 #[derive(Debug)]
 pub struct Master {
     ftdi_descriptor: FtdiDescriptor,
@@ -242,7 +279,58 @@ impl Master {
         &MODULE_PATH
     }
 
-    pub fn new(ftdi_descriptor: FtdiDescriptor) -> Self {
+    // rs485_direction_rts is required for adapters without automatic direction
+    // control
+    pub fn new(
+        ftdi_descriptor: FtdiDescriptor,
+        rs485_direction_rts: Option<Rs485DirectionRts>,
+    ) -> Self {
+        let worker_ftdi_descriptor = ftdi_descriptor.clone();
+        Self::new_with_driver_factory(ftdi_descriptor, move || {
+            Driver::new(Driver::ftdi_bus(
+                worker_ftdi_descriptor,
+                rs485_direction_rts,
+            ))
+        })
+    }
+
+    // bus with frames dropped or corrupted on purpose, for robustness tests
+    // only, never use it for real installation
+    #[cfg(feature = "houseblocks-fault-injection")]
+    pub fn new_fault_injected(
+        ftdi_descriptor: FtdiDescriptor,
+        rs485_direction_rts: Option<Rs485DirectionRts>,
+        fault_injection_configuration: super::fault_injection::Configuration,
+    ) -> Self {
+        use super::fault_injection::{FaultInjectedBus, Injector};
+
+        log::warn!("fault injection enabled for {:?}", ftdi_descriptor);
+        let worker_ftdi_descriptor = ftdi_descriptor.clone();
+        Self::new_with_driver_factory(ftdi_descriptor, move || {
+            Driver::new(FaultInjectedBus::new(
+                Driver::ftdi_bus(worker_ftdi_descriptor, rs485_direction_rts),
+                Injector::new(fault_injection_configuration),
+            ))
+        })
+    }
+
+    // bus emulated in memory, without hardware
+    #[cfg(test)]
+    pub fn new_with_bus<B: Bus + Send + 'static>(bus: B) -> Self {
+        let ftdi_descriptor = FtdiDescriptor {
+            vid: 0,
+            pid: 0,
+            serial_number: c"test".to_owned(),
+        };
+        Self::new_with_driver_factory(ftdi_descriptor, move || Driver::new(bus))
+    }
+
+    // driver is created in worker thread, as ftdi device can't be moved
+    // between threads
+    fn new_with_driver_factory<B: Bus>(
+        ftdi_descriptor: FtdiDescriptor,
+        driver_factory: impl FnOnce() -> Driver<B> + Send + 'static,
+    ) -> Self {
         let (transaction_sender, transaction_receiver) = channel::unbounded::<Transaction>();
 
         let module_path_name = ModulePathName::new(
@@ -250,11 +338,10 @@ impl Master {
             ftdi_descriptor.serial_number.to_str().unwrap().to_owned(),
         );
 
-        let worker_ftdi_descriptor = ftdi_descriptor.clone();
         let worker_thread = thread::Builder::new()
             .name(module_path_name.thread_name())
-            .spawn(|| {
-                Self::thread_main(worker_ftdi_descriptor, transaction_receiver);
+            .spawn(move || {
+                Self::thread_main(driver_factory(), transaction_receiver);
             })
             .unwrap();
 
@@ -319,12 +406,10 @@ impl Master {
         result_receiver.await.context("result_receiver")?
     }
 
-    fn thread_main(
-        ftdi_descriptor: FtdiDescriptor,
+    fn thread_main<B: Bus>(
+        mut driver: Driver<B>,
         transaction_receiver: channel::Receiver<Transaction>,
     ) {
-        let mut driver = Driver::new(ftdi_descriptor);
-
         for transaction in transaction_receiver.iter() {
             let _ = match transaction {
                 Transaction::FrameOut {
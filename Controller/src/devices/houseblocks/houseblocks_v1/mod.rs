@@ -1,2 +1,4 @@
 pub mod common;
+#[cfg(any(test, feature = "houseblocks-fault-injection"))]
+pub mod fault_injection;
 pub mod master;
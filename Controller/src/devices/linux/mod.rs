@@ -0,0 +1,2 @@
+pub mod ntp_status;
+pub mod system_monitor;
@@ -0,0 +1,353 @@
+use crate::{
+    datatypes::real::Real,
+    devices,
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use anyhow::{anyhow, ensure, Context, Error};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{borrow::Cow, time::Duration};
+use tokio::process::Command;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Source {
+    // chronyc tracking, provides offset
+    Chrony,
+    // timedatectl (systemd-timesyncd), synchronization flag only
+    Timedatectl,
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub source: Source,
+    pub interval: Duration,
+    // clock is considered synchronized only if absolute offset is below this
+    // value (if source provides offset)
+    pub offset_max: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Status {
+    synchronized: bool,
+    // seconds, positive if system clock is ahead
+    offset: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct State {
+    status: Option<Status>,
+    time_valid: bool,
+}
+
+// reports system clock synchronization
+// time valid is latched after first synchronization, so schedule driven
+// devices can wait for it after cold boot (boards without rtc start with
+// invalid time), without being affected by later temporary sync losses
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    state: RwLock<State>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_synchronized: signal::state_source::Signal<bool>,
+    signal_offset: signal::state_source::Signal<Real>,
+    signal_time_valid: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        Self {
+            configuration,
+
+            state: RwLock::new(State {
+                status: None,
+                time_valid: false,
+            }),
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_synchronized: signal::state_source::Signal::<bool>::new(None),
+            signal_offset: signal::state_source::Signal::<Real>::new(None),
+            signal_time_valid: signal::state_source::Signal::<bool>::new(Some(false)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // parses `chronyc tracking` output, ex.
+    // System time     : 0.000012345 seconds fast of NTP time
+    // Leap status     : Normal
+    fn chrony_parse(output: &str) -> Result<Status, Error> {
+        let field = |name: &str| -> Result<&str, Error> {
+            let value = output
+                .lines()
+                .find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    (key.trim() == name).then_some(value.trim())
+                })
+                .ok_or_else(|| anyhow!("{} missing", name))?;
+            Ok(value)
+        };
+
+        let mut system_time = field("System time")?.split_whitespace();
+        let offset = system_time
+            .next()
+            .ok_or_else(|| anyhow!("offset missing"))?
+            .parse::<f64>()
+            .context("offset parse")?;
+        let offset = match system_time.nth(1) {
+            Some("fast") => offset,
+            Some("slow") => -offset,
+            direction => return Err(anyhow!("unknown direction: {:?}", direction)),
+        };
+
+        let synchronized = field("Leap status")? != "Not synchronised";
+
+        Ok(Status {
+            synchronized,
+            offset: Some(offset),
+        })
+    }
+    // parses `timedatectl show -p NTPSynchronized --value` output
+    fn timedatectl_parse(output: &str) -> Result<Status, Error> {
+        let synchronized = match output.trim() {
+            "yes" => true,
+            "no" => false,
+            output => return Err(anyhow!("unknown value: {}", output)),
+        };
+
+        Ok(Status {
+            synchronized,
+            offset: None,
+        })
+    }
+
+    async fn status_get(&self) -> Result<Status, Error> {
+        let mut command = match self.configuration.source {
+            Source::Chrony => {
+                let mut command = Command::new("chronyc");
+                command.arg("tracking");
+                command
+            }
+            Source::Timedatectl => {
+                let mut command = Command::new("timedatectl");
+                command
+                    .arg("show")
+                    .arg("-p")
+                    .arg("NTPSynchronized")
+                    .arg("--value");
+                command
+            }
+        };
+        let output = command
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("output")?;
+        ensure!(output.status.success(), "command failed: {}", output.status);
+        let output = String::from_utf8(output.stdout).context("from_utf8")?;
+
+        let mut status = match self.configuration.source {
+            Source::Chrony => Self::chrony_parse(&output).context("chrony_parse")?,
+            Source::Timedatectl => Self::timedatectl_parse(&output).context("timedatectl_parse")?,
+        };
+        if let Some(offset) = status.offset {
+            if offset.abs() > self.configuration.offset_max.as_secs_f64() {
+                status.synchronized = false;
+            }
+        }
+
+        Ok(status)
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        loop {
+            let status = match self.status_get().await.context("status_get") {
+                Ok(status) => Some(status),
+                Err(error) => {
+                    log::warn!("ntp_status: {:?}", error);
+                    None
+                }
+            };
+
+            let (time_valid, time_valid_changed) = {
+                let mut state = self.state.write();
+                state.status = status;
+                let time_valid_changed =
+                    !state.time_valid && status.is_some_and(|status| status.synchronized);
+                if time_valid_changed {
+                    state.time_valid = true;
+                }
+                (state.time_valid, time_valid_changed)
+            };
+
+            if time_valid_changed {
+                log::info!("ntp_status: system time valid");
+            }
+
+            let mut signals_sources_changed = false;
+            signals_sources_changed |= self
+                .signal_synchronized
+                .set_one(status.map(|status| status.synchronized));
+            signals_sources_changed |= self.signal_offset.set_one(
+                status
+                    .and_then(|status| status.offset)
+                    .map(|offset| Real::from_f64(offset).unwrap()),
+            );
+            signals_sources_changed |= self.signal_time_valid.set_one(Some(time_valid));
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+            }
+            self.gui_summary_waker.wake();
+
+            select! {
+                () = tokio::time::sleep(self.configuration.interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("linux/ntp_status")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Synchronized,
+    Offset,
+    TimeValid,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Synchronized => &self.signal_synchronized as &dyn signal::Base,
+            SignalIdentifier::Offset => &self.signal_offset as &dyn signal::Base,
+            SignalIdentifier::TimeValid => &self.signal_time_valid as &dyn signal::Base,
+        }
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Synchronized => Metadata {
+                name: Some(Cow::from("Clock synchronized")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Offset => Metadata {
+                name: Some(Cow::from("Clock offset")),
+                unit: Some(Cow::from("s")),
+                precision: Some(6),
+                ..Metadata::default()
+            },
+            SignalIdentifier::TimeValid => Metadata {
+                name: Some(Cow::from("Time valid")),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    status: Option<Status>,
+    time_valid: bool,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        Self::Value {
+            status: state.status,
+            time_valid: state.time_valid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Device;
+    use indoc::indoc;
+
+    #[test]
+    fn chrony_parse() {
+        let status = Device::chrony_parse(indoc!(
+            "
+            Reference ID    : C0A80101 (router.lan)
+            Stratum         : 3
+            Ref time (UTC)  : Sat Jun 01 12:00:00 2024
+            System time     : 0.000250000 seconds slow of NTP time
+            Last offset     : -0.000012345 seconds
+            Leap status     : Normal
+        "
+        ))
+        .unwrap();
+        assert!(status.synchronized);
+        assert_eq!(status.offset, Some(-0.00025));
+
+        let status = Device::chrony_parse(indoc!(
+            "
+            Reference ID    : 00000000 ()
+            System time     : 0.000000000 seconds fast of NTP time
+            Leap status     : Not synchronised
+        "
+        ))
+        .unwrap();
+        assert!(!status.synchronized);
+    }
+
+    #[test]
+    fn timedatectl_parse() {
+        assert!(Device::timedatectl_parse("yes\n").unwrap().synchronized);
+        assert!(!Device::timedatectl_parse("no\n").unwrap().synchronized);
+        assert!(Device::timedatectl_parse("").is_err());
+    }
+}
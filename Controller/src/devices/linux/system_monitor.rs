@@ -0,0 +1,391 @@
+use crate::{
+    datatypes::{
+        ratio::Ratio,
+        real::Real,
+        temperature::{Temperature, Unit},
+    },
+    devices,
+    modules::{fs::Fs, metrics},
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_flag,
+        fs::disk_space,
+        runnable::{Exited, Runnable},
+    },
+};
+use anyhow::{anyhow, Context, Error};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select};
+use maplit::{btreemap, hashmap};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{borrow::Cow, path::PathBuf, time::Duration};
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub interval: Duration,
+    // ex. /sys/class/thermal/thermal_zone0/temp on raspberry pi
+    pub cpu_temperature_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Status {
+    load: Option<Real>,
+    memory_used: Option<Ratio>,
+    disk_used: Option<Ratio>,
+    cpu_temperature: Option<Temperature>,
+    uptime: Option<Duration>,
+}
+
+// controller host health, so automations can alert before it runs out of
+// resources (ex. disk space for the logger)
+// disk space metrics are already exported by fs monitor
+#[derive(Debug)]
+pub struct Device<'f> {
+    configuration: Configuration,
+    fs: &'f Fs,
+
+    status: RwLock<Option<Status>>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_load: signal::state_source::Signal<Real>,
+    signal_memory_used: signal::state_source::Signal<Ratio>,
+    signal_disk_used: signal::state_source::Signal<Ratio>,
+    signal_cpu_temperature: signal::state_source::Signal<Temperature>,
+    signal_uptime: signal::state_source::Signal<Duration>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'f> Device<'f> {
+    pub fn new(
+        configuration: Configuration,
+        fs: &'f Fs,
+    ) -> Self {
+        Self {
+            configuration,
+            fs,
+
+            status: RwLock::new(None),
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_load: signal::state_source::Signal::<Real>::new(None),
+            signal_memory_used: signal::state_source::Signal::<Ratio>::new(None),
+            signal_disk_used: signal::state_source::Signal::<Ratio>::new(None),
+            signal_cpu_temperature: signal::state_source::Signal::<Temperature>::new(None),
+            signal_uptime: signal::state_source::Signal::<Duration>::new(None),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // 1 minute load average
+    fn load_parse(loadavg: &str) -> Result<Real, Error> {
+        let load = loadavg
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| anyhow!("empty"))?
+            .parse::<f64>()
+            .context("parse")?;
+        let load = Real::from_f64(load).context("from_f64")?;
+        Ok(load)
+    }
+    fn memory_used_parse(meminfo: &str) -> Result<Ratio, Error> {
+        let field = |name: &str| -> Result<f64, Error> {
+            let value = meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .ok_or_else(|| anyhow!("{} missing", name))?
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<f64>()
+                .with_context(|| format!("{} parse", name))?;
+            Ok(value)
+        };
+
+        let total = field("MemTotal")?;
+        let available = field("MemAvailable")?;
+
+        let memory_used =
+            Ratio::from_f64(((total - available) / total).clamp(0.0, 1.0)).context("from_f64")?;
+        Ok(memory_used)
+    }
+    fn cpu_temperature_parse(temp: &str) -> Result<Temperature, Error> {
+        let millicelsius = temp.trim().parse::<f64>().context("parse")?;
+        let cpu_temperature =
+            Temperature::from_unit(Unit::Celsius, millicelsius / 1000.0).context("from_unit")?;
+        Ok(cpu_temperature)
+    }
+    fn uptime_parse(uptime: &str) -> Result<Duration, Error> {
+        let uptime = uptime
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| anyhow!("empty"))?
+            .parse::<f64>()
+            .context("parse")?;
+        // published with second resolution, to avoid changing signal on every
+        // read for no reason
+        let uptime = Duration::from_secs(uptime as u64);
+        Ok(uptime)
+    }
+
+    async fn read(
+        &self,
+        path: &str,
+    ) -> Result<String, Error> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("read {}", path))?;
+        Ok(content)
+    }
+
+    fn result_log<T>(result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                log::warn!("system_monitor: {:?}", error);
+                None
+            }
+        }
+    }
+    async fn status_build(&self) -> Status {
+        let load = async { Self::load_parse(&self.read("/proc/loadavg").await?) }
+            .await
+            .context("load");
+        let memory_used = async { Self::memory_used_parse(&self.read("/proc/meminfo").await?) }
+            .await
+            .context("memory_used");
+        let disk_used = disk_space(self.fs.persistent_storage_directory())
+            .and_then(|disk_space| {
+                let disk_used = 1.0 - disk_space.available as f64 / disk_space.total as f64;
+                Ratio::from_f64(disk_used.clamp(0.0, 1.0))
+            })
+            .context("disk_used");
+        let cpu_temperature = match &self.configuration.cpu_temperature_path {
+            Some(cpu_temperature_path) => Some(
+                async {
+                    let temp = tokio::fs::read_to_string(cpu_temperature_path)
+                        .await
+                        .context("read")?;
+                    Self::cpu_temperature_parse(&temp)
+                }
+                .await
+                .context("cpu_temperature"),
+            ),
+            None => None,
+        };
+        let uptime = async { Self::uptime_parse(&self.read("/proc/uptime").await?) }
+            .await
+            .context("uptime");
+
+        Status {
+            load: Self::result_log(load),
+            memory_used: Self::result_log(memory_used),
+            disk_used: Self::result_log(disk_used),
+            cpu_temperature: cpu_temperature.and_then(Self::result_log),
+            uptime: Self::result_log(uptime),
+        }
+    }
+
+    fn metrics_update(status: &Status) {
+        let registry = metrics::registry();
+
+        if let Some(load) = status.load {
+            registry
+                .gauge("system_load1", "Host 1 minute load average", btreemap! {})
+                .set(load.to_f64());
+        }
+        if let Some(memory_used) = status.memory_used {
+            registry
+                .gauge(
+                    "system_memory_used_ratio",
+                    "Fraction of host memory in use (excluding reclaimable caches)",
+                    btreemap! {},
+                )
+                .set(memory_used.to_f64());
+        }
+        if let Some(cpu_temperature) = status.cpu_temperature {
+            registry
+                .gauge(
+                    "system_cpu_temperature_celsius",
+                    "Host cpu temperature",
+                    btreemap! {},
+                )
+                .set(cpu_temperature.to_unit(Unit::Celsius));
+        }
+        if let Some(uptime) = status.uptime {
+            registry
+                .gauge("system_uptime_seconds", "Host uptime", btreemap! {})
+                .set(uptime.as_secs_f64());
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        loop {
+            let status = self.status_build().await;
+            Self::metrics_update(&status);
+
+            let mut signals_sources_changed = false;
+            signals_sources_changed |= self.signal_load.set_one(status.load);
+            signals_sources_changed |= self.signal_memory_used.set_one(status.memory_used);
+            signals_sources_changed |= self.signal_disk_used.set_one(status.disk_used);
+            signals_sources_changed |= self.signal_cpu_temperature.set_one(status.cpu_temperature);
+            signals_sources_changed |= self.signal_uptime.set_one(status.uptime);
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+            }
+
+            *self.status.write() = Some(status);
+            self.gui_summary_waker.wake();
+
+            select! {
+                () = tokio::time::sleep(self.configuration.interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'f> devices::Device for Device<'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("linux/system_monitor")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'f> Runnable for Device<'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Load,
+    MemoryUsed,
+    DiskUsed,
+    CpuTemperature,
+    Uptime,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f> signals::Device for Device<'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Load => &self.signal_load as &dyn signal::Base,
+            SignalIdentifier::MemoryUsed => &self.signal_memory_used as &dyn signal::Base,
+            SignalIdentifier::DiskUsed => &self.signal_disk_used as &dyn signal::Base,
+            SignalIdentifier::CpuTemperature => &self.signal_cpu_temperature as &dyn signal::Base,
+            SignalIdentifier::Uptime => &self.signal_uptime as &dyn signal::Base,
+        }
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Load => Metadata {
+                name: Some(Cow::from("Load average (1 min)")),
+                min: Some(0.0),
+                precision: Some(2),
+                icon: Some(Cow::from("cpu")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::MemoryUsed => Metadata {
+                name: Some(Cow::from("Memory used")),
+                icon: Some(Cow::from("memory")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::DiskUsed => Metadata {
+                name: Some(Cow::from("Disk used")),
+                icon: Some(Cow::from("disk")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::CpuTemperature => Metadata {
+                name: Some(Cow::from("CPU temperature")),
+                icon: Some(Cow::from("thermometer")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Uptime => Metadata {
+                name: Some(Cow::from("Uptime")),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct GuiSummary {
+    status: Option<Status>,
+}
+impl<'f> devices::gui_summary::Device for Device<'f> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let status = *self.status.read();
+        Self::Value { status }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Device;
+    use crate::datatypes::temperature::Unit;
+    use approx::assert_relative_eq;
+    use indoc::indoc;
+    use std::time::Duration;
+
+    #[test]
+    fn parse() {
+        let load = Device::load_parse("0.52 0.58 0.59 1/345 12345\n").unwrap();
+        assert_relative_eq!(load.to_f64(), 0.52);
+
+        let memory_used = Device::memory_used_parse(indoc!(
+            "
+            MemTotal:        4000000 kB
+            MemFree:          500000 kB
+            MemAvailable:    3000000 kB
+            Buffers:          100000 kB
+        "
+        ))
+        .unwrap();
+        assert_relative_eq!(memory_used.to_f64(), 0.25);
+
+        let cpu_temperature = Device::cpu_temperature_parse("48312\n").unwrap();
+        assert_relative_eq!(
+            cpu_temperature.to_unit(Unit::Celsius),
+            48.312,
+            epsilon = 1e-9
+        );
+
+        let uptime = Device::uptime_parse("3600.75 13000.12\n").unwrap();
+        assert_eq!(uptime, Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::web::{self, uri_cursor};
+use futures::future::{BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::watch;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Requests {
+    web: bool,
+    signal: bool,
+}
+
+// controller wide maintenance mode, for safe electrical work
+// while active, state targets of hardware devices are frozen by exchanger, so
+// actuators keep their current state, while sources (monitoring) keep working
+// mode is active if requested by web or by signal (see
+// soft::system::maintenance_a), devices can be exempted with
+// Devices::maintenance_exempt_set()
+#[derive(Debug)]
+pub struct Maintenance {
+    requests: Mutex<Requests>,
+    active_sender: watch::Sender<bool>,
+}
+impl Maintenance {
+    pub fn new() -> Self {
+        Self {
+            requests: Mutex::new(Requests {
+                web: false,
+                signal: false,
+            }),
+            active_sender: watch::channel(false).0,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        *self.active_sender.borrow()
+    }
+    pub fn active_receiver(&self) -> watch::Receiver<bool> {
+        self.active_sender.subscribe()
+    }
+
+    fn requests_update(
+        &self,
+        update: impl FnOnce(&mut Requests),
+    ) {
+        let mut requests = self.requests.lock();
+        update(&mut requests);
+        let active = requests.web || requests.signal;
+        drop(requests);
+
+        self.active_sender.send_if_modified(|active_current| {
+            if *active_current == active {
+                return false;
+            }
+            *active_current = active;
+            true
+        });
+    }
+    pub fn web_set(
+        &self,
+        active: bool,
+    ) {
+        self.requests_update(|requests| requests.web = active);
+    }
+    pub fn signal_set(
+        &self,
+        active: bool,
+    ) {
+        self.requests_update(|requests| requests.signal = active);
+    }
+}
+impl uri_cursor::Handler for Maintenance {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    #[derive(Debug, Serialize)]
+                    struct Status {
+                        active: bool,
+                        requests: Requests,
+                    }
+
+                    let status = Status {
+                        active: self.active(),
+                        requests: *self.requests.lock(),
+                    };
+                    async { web::Response::ok_json(status) }.boxed()
+                }
+                http::Method::PUT => {
+                    let active = match request.body_parse_json::<bool>() {
+                        Ok(active) => active,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed();
+                        }
+                    };
+
+                    if active {
+                        log::warn!("maintenance mode requested by web");
+                    } else {
+                        log::info!("maintenance mode released by web");
+                    }
+                    self.web_set(active);
+
+                    async { web::Response::ok_empty() }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Maintenance;
+    use crate::simulation::{mock, Simulation};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn frozen_until_released() {
+        let maintenance = Maintenance::new();
+        let source = mock::StateSource::<bool>::new(Some(false));
+        let frozen = mock::StateRecorder::<bool>::new();
+        let exempt = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let source_handle = simulation.device_add(&source);
+        let frozen_handle = simulation.device_add(&frozen);
+        let exempt_handle = simulation.device_add(&exempt);
+        simulation.maintenance_set(&maintenance, &[frozen_handle.into_erased()]);
+        simulation.signals().d2d(
+            source_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            frozen_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            source_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            exempt_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                maintenance.web_set(true);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                source.set(Some(true));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                maintenance.signal_set(true);
+                maintenance.web_set(false);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                maintenance.signal_set(false);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        // released only when both requests are gone
+        frozen.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(4), Some(true)),
+        ]);
+        exempt.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(2), Some(true)),
+        ]);
+    }
+}
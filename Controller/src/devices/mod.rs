@@ -1,13 +1,23 @@
+pub mod blueprints;
+pub mod camera;
+pub mod classes;
+pub mod configuration_history;
 pub mod dahua;
+pub mod definition;
 pub mod eaton;
+pub mod external;
 pub mod gui_summary;
 pub mod helpers;
 pub mod hikvision;
 pub mod houseblocks;
+pub mod linux;
+pub mod maintenance;
 pub mod runner;
+pub mod scenes;
 pub mod soft;
 
 use crate::{
+    modules::{confirmation, i18n},
     signals,
     util::{
         async_flag,
@@ -18,7 +28,7 @@ use crate::{
 use async_trait::async_trait;
 use futures::future::{BoxFuture, FutureExt};
 use serde::{de::DeserializeOwned, Serialize};
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, collections::HashSet, fmt};
 
 pub type Id = u32;
 
@@ -36,19 +46,44 @@ pub trait Device: Send + Sync + fmt::Debug {
     fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
         None
     }
+    fn as_camera(&self) -> Option<&dyn camera::Camera> {
+        None
+    }
+
+    // devices initializing in run (eg. loading persisted state, connecting to
+    // bus) signal returned flag once done, next start stage waits for it
+    // devices without it are initialized as soon as they are started
+    fn initialized(&self) -> Option<&async_flag::LocalSender> {
+        None
+    }
 }
 
 #[derive(Debug)]
 pub struct DeviceWrapper<'d> {
     name: String,
     device: Box<dyn Device + 'd>,
+    dependencies: Vec<Id>,
+    label_key: Option<String>,
+    confirmation_required: HashSet<String>,
+    shadow: bool,
+    maintenance_exempt: bool,
+    started: async_flag::LocalSender,
 }
 impl<'d> DeviceWrapper<'d> {
     pub fn new(
         name: String,
         device: Box<dyn Device + 'd>,
     ) -> Self {
-        Self { name, device }
+        Self {
+            name,
+            device,
+            dependencies: Vec::<Id>::new(),
+            label_key: None,
+            confirmation_required: HashSet::<String>::new(),
+            shadow: false,
+            maintenance_exempt: false,
+            started: async_flag::LocalSender::new(),
+        }
     }
 
     pub fn name(&self) -> &String {
@@ -58,10 +93,82 @@ impl<'d> DeviceWrapper<'d> {
         &*self.device as &dyn Device
     }
 
+    // devices started before and finalized after this one
+    pub fn dependency_add(
+        &mut self,
+        device_id: Id,
+    ) {
+        if !self.dependencies.contains(&device_id) {
+            self.dependencies.push(device_id);
+        }
+    }
+    pub fn dependencies(&self) -> &[Id] {
+        &self.dependencies
+    }
+
+    // display name shown in gui, translated through i18n catalog
+    // name stays as internal identifier
+    pub fn label_key_set(
+        &mut self,
+        label_key: String,
+    ) {
+        self.label_key = Some(label_key);
+    }
+    pub fn label_key(&self) -> Option<&str> {
+        self.label_key.as_deref()
+    }
+
+    // writing signal (given by identifier name) from web requires two-step
+    // confirmation, see modules::confirmation
+    // as device web handlers may actuate any of device signals, all their
+    // modifying requests require confirmation once any signal is marked
+    pub fn confirmation_required_set(
+        &mut self,
+        signal_name: String,
+    ) {
+        self.confirmation_required.insert(signal_name);
+    }
+    pub fn confirmation_required(
+        &self,
+        signal_name: &str,
+    ) -> bool {
+        self.confirmation_required.contains(signal_name)
+    }
+    pub fn confirmation_required_any(&self) -> bool {
+        !self.confirmation_required.is_empty()
+    }
+
+    // device runs normally, but values of its sources are only recorded by
+    // exchanger instead of being forwarded to connected targets
+    pub fn shadow_set(&mut self) {
+        self.shadow = true;
+    }
+    pub fn shadow(&self) -> bool {
+        self.shadow
+    }
+
+    // hardware devices (all except soft/*) have their state targets frozen
+    // while maintenance mode is active, unless exempted
+    pub fn maintenance_exempt_set(&mut self) {
+        self.maintenance_exempt = true;
+    }
+    pub fn maintenance_frozen(&self) -> bool {
+        !self.maintenance_exempt && !self.device.class().starts_with("soft/")
+    }
+
+    // completes once device is started and reported its initialization
+    pub async fn initialized(&self) {
+        self.started.receiver().await;
+        if let Some(initialized) = self.device.initialized() {
+            initialized.receiver().await;
+        }
+    }
+
     async fn run(
         &self,
         exit_flag: async_flag::Receiver,
     ) -> Exited {
+        self.started.signal();
         self.device.as_runnable().run(exit_flag).await
     }
 
@@ -90,15 +197,27 @@ impl<'d> uri_cursor::Handler for DeviceWrapper<'d> {
                     #[derive(Debug, Serialize)]
                     struct DeviceData {
                         name: String,
+                        label: Option<String>,
                         class: Cow<'static, str>,
+                        shadow: bool,
                     }
 
                     let name = self.name().clone();
+                    let label = self.label_key().and_then(|label_key| {
+                        i18n::catalog().translate(label_key, &i18n::request_locales(&request))
+                    });
                     let class = self.device().class();
+                    let shadow = self.shadow();
 
-                    let device_data = DeviceData { name, class };
+                    let device_data = DeviceData {
+                        name,
+                        label,
+                        class,
+                        shadow,
+                    };
 
-                    async { web::Response::ok_json(device_data) }.boxed()
+                    let response = web::Response::ok_json_etag(&request, device_data);
+                    async { response }.boxed()
                 }
                 _ => async { web::Response::error_405() }.boxed(),
             },
@@ -108,7 +227,8 @@ impl<'d> uri_cursor::Handler for DeviceWrapper<'d> {
                         uri_cursor::UriCursor::Terminal => match *request.method() {
                             http::Method::GET => {
                                 let value = gui_summary_device_base.value();
-                                async { web::Response::ok_json(value) }.boxed()
+                                let response = web::Response::ok_json_etag(&request, value);
+                                async { response }.boxed()
                             }
                             _ => async { web::Response::error_405() }.boxed(),
                         },
@@ -117,9 +237,33 @@ impl<'d> uri_cursor::Handler for DeviceWrapper<'d> {
                     None => async { web::Response::error_404() }.boxed(),
                 }
             }
+            uri_cursor::UriCursor::Next("camera", uri_cursor) => match self.device().as_camera() {
+                Some(camera) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            let summary = camera::Summary::from_camera(camera);
+                            async { web::Response::ok_json(summary) }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                None => async { web::Response::error_404() }.boxed(),
+            },
             uri_cursor::UriCursor::Next("device", uri_cursor) => {
                 match self.device().as_web_handler() {
-                    Some(handler) => handler.handle(request, uri_cursor),
+                    Some(handler) => {
+                        if self.confirmation_required_any()
+                            && !matches!(*request.method(), http::Method::GET | http::Method::HEAD)
+                        {
+                            if let Some(response) =
+                                confirmation::confirmations().check(&request, self.name())
+                            {
+                                return async { response }.boxed();
+                            }
+                        }
+                        handler.handle(request, uri_cursor)
+                    }
                     None => async { web::Response::error_404() }.boxed(),
                 }
             }
@@ -1,24 +1,158 @@
 #![allow(clippy::drop_non_drop)] // TODO: something in self_referencing
 #![allow(clippy::too_many_arguments)] // TODO: something in self_referencing
 
-use super::{DeviceWrapper, Id as DeviceId};
+use super::{
+    classes::classes,
+    maintenance::Maintenance,
+    scenes::{self, Scenes},
+    DeviceWrapper, Id as DeviceId,
+};
 use crate::{
-    modules::module_path::ModulePath,
+    modules::{confirmation, metrics, module_path::ModulePath},
     signals::{
-        exchanger::{ConnectionRequested, Exchanger},
-        DeviceBaseRef as SignalsDeviceBaseRef,
+        exchanger::{ConnectionRequested, Exchanger, TargetWrite},
+        latency, DeviceBaseRef as SignalsDeviceBaseRef,
     },
     util::{
+        async_ext::poll_budget::{PollBudget, PollBudgetedExt},
+        async_flag,
         drop_guard::DropGuard,
+        runnable::{Exited, Runnable, Supervisor, SupervisorConfiguration},
         runtime::{Runtime, RuntimeScopeRunnable},
     },
     web::{self, sse_topic, uri_cursor},
 };
-use anyhow::{Context, Error};
-use futures::future::{BoxFuture, FutureExt, JoinAll};
+use anyhow::{bail, ensure, Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{BoxFuture, FutureExt, JoinAll},
+    select,
+};
+use maplit::btreemap;
 use once_cell::sync::Lazy;
 use ouroboros::self_referencing;
-use std::{collections::HashMap, mem::ManuallyDrop};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::ManuallyDrop,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tokio::time::{timeout_at, Instant};
+
+// measures time spent in device runner, to find devices consuming most cpu
+// also delays device start until devices of previous start stage initialized
+#[derive(Debug)]
+struct DeviceWrapperBudgeted<'w, 'd> {
+    device_wrapper: &'w DeviceWrapper<'d>,
+    stage_previous: Box<[&'w DeviceWrapper<'d>]>,
+    stage_previous_waited: AtomicBool,
+    poll_budget: PollBudget,
+}
+impl<'w, 'd> DeviceWrapperBudgeted<'w, 'd> {
+    // stuck device delays next stage only up to this
+    const STAGE_PREVIOUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn new(
+        device_id: DeviceId,
+        device_wrapper: &'w DeviceWrapper<'d>,
+        stage_previous: Box<[&'w DeviceWrapper<'d>]>,
+    ) -> Self {
+        let registry = metrics::registry();
+        let labels = btreemap! {
+            "device_id".to_owned() => device_id.to_string(),
+            "class".to_owned() => device_wrapper.device().class().into_owned(),
+        };
+        let poll_budget = PollBudget::new(
+            registry.counter(
+                "devices_polls_total",
+                "Number of times device runner was woken up",
+                labels.clone(),
+            ),
+            registry.counter(
+                "devices_poll_time_microseconds_total",
+                "Time spent polling device runner",
+                labels,
+            ),
+        );
+
+        Self {
+            device_wrapper,
+            stage_previous,
+            stage_previous_waited: AtomicBool::new(false),
+            poll_budget,
+        }
+    }
+
+    // returns false if exit was requested while waiting
+    async fn stage_previous_wait(
+        &self,
+        exit_flag: &mut async_flag::Receiver,
+    ) -> bool {
+        let stage_previous_initialized = initialized_wait(
+            self.stage_previous.iter().copied(),
+            Instant::now() + Self::STAGE_PREVIOUS_TIMEOUT,
+        );
+        let names_not_initialized = select! {
+            names_not_initialized = stage_previous_initialized.fuse() => names_not_initialized,
+            () = &mut *exit_flag => return false,
+        };
+        if !names_not_initialized.is_empty() {
+            log::warn!(
+                "{}: starting before devices initialized: {}",
+                self.device_wrapper.name(),
+                names_not_initialized.join(", ")
+            );
+        }
+
+        true
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        // restarts by supervisor are not delayed
+        if !self.stage_previous_waited.swap(true, Ordering::Relaxed)
+            && !self.stage_previous_wait(&mut exit_flag).await
+        {
+            return Exited;
+        }
+
+        latency::Scoped::new(self.device_wrapper.run(exit_flag))
+            .poll_budgeted(&self.poll_budget)
+            .await
+    }
+}
+#[async_trait]
+impl<'w, 'd> Runnable for DeviceWrapperBudgeted<'w, 'd> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+// returns names of devices not initialized before deadline
+async fn initialized_wait<'w, 'd: 'w>(
+    device_wrappers: impl Iterator<Item = &'w DeviceWrapper<'d>>,
+    deadline: Instant,
+) -> Box<[&'w str]> {
+    device_wrappers
+        .map(|device_wrapper| async move {
+            match timeout_at(deadline, device_wrapper.initialized()).await {
+                Ok(()) => None,
+                Err(_) => Some(device_wrapper.name().as_str()),
+            }
+        })
+        .collect::<JoinAll<_>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect::<Box<[_]>>()
+}
 
 #[self_referencing]
 #[derive(Debug)]
@@ -26,10 +160,20 @@ struct RunnerInner<'d> {
     runtime: Runtime,
     device_wrappers_by_id: HashMap<DeviceId, DeviceWrapper<'d>>,
 
-    #[borrows(runtime, device_wrappers_by_id)]
+    #[borrows(device_wrappers_by_id)]
+    #[covariant]
+    device_wrappers_budgeted_by_id: HashMap<DeviceId, DeviceWrapperBudgeted<'this, 'd>>,
+
+    // each device runs under its own supervisor, so panic in one device restarts
+    // only this device
+    #[borrows(device_wrappers_budgeted_by_id)]
+    #[covariant]
+    device_supervisors_by_id: HashMap<DeviceId, Supervisor<'this>>,
+
+    #[borrows(runtime, device_supervisors_by_id)]
     #[not_covariant]
     devices_wrapper_runtime_scope_runnable:
-        ManuallyDrop<Box<[RuntimeScopeRunnable<'this, 'this, DeviceWrapper<'d>>]>>,
+        ManuallyDrop<HashMap<DeviceId, RuntimeScopeRunnable<'this, 'this, Supervisor<'this>>>>,
 
     #[borrows(device_wrappers_by_id)]
     #[covariant]
@@ -40,6 +184,15 @@ struct RunnerInner<'d> {
     exchanger_runtime_scope_runnable:
         ManuallyDrop<RuntimeScopeRunnable<'this, 'this, Exchanger<'this>>>,
 
+    #[borrows(device_wrappers_by_id, exchanger)]
+    #[covariant]
+    scenes_runner: Option<scenes::Runner<'this, 'd>>,
+
+    #[borrows(runtime, scenes_runner)]
+    #[not_covariant]
+    scenes_runner_runtime_scope_runnable:
+        ManuallyDrop<Option<RuntimeScopeRunnable<'this, 'this, scenes::Runner<'this, 'd>>>>,
+
     #[borrows(device_wrappers_by_id)]
     #[covariant]
     devices_gui_summary_sse_root_node: sse_topic::Node<'this>,
@@ -57,10 +210,15 @@ struct RunnerInner<'d> {
 #[derive(Debug)]
 pub struct Runner<'d> {
     inner: RunnerInner<'d>,
+    maintenance: Option<&'d Maintenance>,
+    finalize_stages: Stages,
 
     drop_guard: DropGuard,
 }
 impl<'d> Runner<'d> {
+    // devices not initialized by then are reported and skipped
+    const INITIALIZATION_TIMEOUT: Duration = Duration::from_secs(120);
+
     fn module_path() -> &'static ModulePath {
         static MODULE_PATH: Lazy<ModulePath> =
             Lazy::new(|| ModulePath::new(&["devices", "runner"]));
@@ -70,17 +228,84 @@ impl<'d> Runner<'d> {
     pub fn new(
         device_wrappers_by_id: HashMap<DeviceId, DeviceWrapper<'d>>,
         connections_requested: &[ConnectionRequested],
+        scenes: Option<&'d Scenes<'d>>,
+        maintenance: Option<&'d Maintenance>,
+        worker_threads: usize,
     ) -> Result<Self, Error> {
-        let runtime = Runtime::new(Self::module_path(), 4, 4);
+        let runtime = Runtime::new(Self::module_path(), worker_threads, worker_threads);
+
+        let start_stages = start_stages(&device_wrappers_by_id).context("start_stages")?;
+        let stage_previous_by_device_id = start_stages
+            .windows(2)
+            .flat_map(|stages| {
+                let [stage_previous, stage] = stages else {
+                    unreachable!()
+                };
+                stage
+                    .iter()
+                    .map(move |device_id| (*device_id, stage_previous))
+            })
+            .collect::<HashMap<_, _>>();
+        let finalize_stages = finalize_stages(
+            device_wrappers_by_id.keys().copied(),
+            dependencies(&device_wrappers_by_id),
+            connections_requested,
+        );
 
         let inner = RunnerInner::try_new(
             runtime,
             device_wrappers_by_id,
-            |runtime, device_wrappers_by_id| -> Result<_, Error> {
-                let devices_wrapper_runtime_scope_runnable = device_wrappers_by_id
-                    .values()
-                    .map(|device_wrapper| RuntimeScopeRunnable::new(runtime, device_wrapper))
-                    .collect::<Box<[_]>>();
+            |device_wrappers_by_id| -> Result<_, Error> {
+                let device_wrappers_budgeted_by_id = device_wrappers_by_id
+                    .iter()
+                    .map(|(device_id, device_wrapper)| {
+                        let stage_previous = stage_previous_by_device_id
+                            .get(device_id)
+                            .map(|stage_previous| {
+                                stage_previous
+                                    .iter()
+                                    .map(|device_id| &device_wrappers_by_id[device_id])
+                                    .collect::<Box<[_]>>()
+                            })
+                            .unwrap_or_default();
+                        let device_wrapper_budgeted =
+                            DeviceWrapperBudgeted::new(*device_id, device_wrapper, stage_previous);
+                        (*device_id, device_wrapper_budgeted)
+                    })
+                    .collect::<HashMap<_, _>>();
+                Ok(device_wrappers_budgeted_by_id)
+            },
+            |device_wrappers_budgeted_by_id| -> Result<_, Error> {
+                let device_supervisors_by_id = device_wrappers_budgeted_by_id
+                    .iter()
+                    .map(|(device_id, device_wrapper_budgeted)| {
+                        let name = format!(
+                            "{} ({})",
+                            device_id,
+                            device_wrapper_budgeted.device_wrapper.name()
+                        );
+                        let device_supervisor = Supervisor::new(
+                            SupervisorConfiguration::default(),
+                            Box::new([(name, device_wrapper_budgeted as &dyn Runnable)]),
+                        );
+                        (*device_id, device_supervisor)
+                    })
+                    .collect::<HashMap<_, _>>();
+                Ok(device_supervisors_by_id)
+            },
+            |runtime, device_supervisors_by_id| -> Result<_, Error> {
+                // devices wait for previous stage to initialize before starting, so
+                // dependencies are ready before devices using them
+                let devices_wrapper_runtime_scope_runnable = start_stages
+                    .iter()
+                    .flat_map(|start_stage| start_stage.iter())
+                    .map(|device_id| {
+                        let device_supervisor = &device_supervisors_by_id[device_id];
+                        let runtime_scope_runnable =
+                            RuntimeScopeRunnable::new(runtime, device_supervisor);
+                        (*device_id, runtime_scope_runnable)
+                    })
+                    .collect::<HashMap<_, _>>();
                 let devices_wrapper_runtime_scope_runnable =
                     ManuallyDrop::new(devices_wrapper_runtime_scope_runnable);
                 Ok(devices_wrapper_runtime_scope_runnable)
@@ -98,8 +323,23 @@ impl<'d> Runner<'d> {
                         (device_id, signals_device_base)
                     })
                     .collect::<HashMap<_, _>>();
-                let exchanger =
-                    Exchanger::new(&exchanger_devices, connections_requested).context("new")?;
+                let shadow_device_ids = device_wrappers_by_id
+                    .iter()
+                    .filter(|(_, device_wrapper)| device_wrapper.shadow())
+                    .map(|(device_id, _)| *device_id)
+                    .collect::<HashSet<_>>();
+                let maintenance_device_ids = device_wrappers_by_id
+                    .iter()
+                    .filter(|(_, device_wrapper)| device_wrapper.maintenance_frozen())
+                    .map(|(device_id, _)| *device_id)
+                    .collect::<HashSet<_>>();
+                let exchanger = Exchanger::new(
+                    &exchanger_devices,
+                    connections_requested,
+                    &shadow_device_ids,
+                    maintenance.map(|maintenance| (maintenance, &maintenance_device_ids)),
+                )
+                .context("new")?;
                 Ok(exchanger)
             },
             |runtime, exchanger| -> Result<_, Error> {
@@ -109,6 +349,20 @@ impl<'d> Runner<'d> {
                     ManuallyDrop::new(exchanger_runtime_scope_runnable);
                 Ok(exchanger_runtime_scope_runnable)
             },
+            |device_wrappers_by_id, exchanger| -> Result<_, Error> {
+                let scenes_runner = scenes.map(|scenes| {
+                    scenes::Runner::new(scenes, device_wrappers_by_id, exchanger)
+                });
+                Ok(scenes_runner)
+            },
+            |runtime, scenes_runner| -> Result<_, Error> {
+                let scenes_runner_runtime_scope_runnable = scenes_runner
+                    .as_ref()
+                    .map(|scenes_runner| RuntimeScopeRunnable::new(runtime, scenes_runner));
+                let scenes_runner_runtime_scope_runnable =
+                    ManuallyDrop::new(scenes_runner_runtime_scope_runnable);
+                Ok(scenes_runner_runtime_scope_runnable)
+            },
             |device_wrappers_by_id| -> Result<_, Error> {
                 let devices_gui_summary_sse_root_node = sse_topic::Node::new(
                     None,
@@ -147,8 +401,39 @@ impl<'d> Runner<'d> {
 
         let drop_guard = DropGuard::new();
 
-        Ok(Self { inner, drop_guard })
+        Ok(Self {
+            inner,
+            maintenance,
+            finalize_stages,
+            drop_guard,
+        })
     }
+    // completes once all devices are started and initialized, eg. to report
+    // readiness of the application
+    pub async fn initialized(&self) {
+        let names_not_initialized = initialized_wait(
+            self.inner.borrow_device_wrappers_by_id().values(),
+            Instant::now() + Self::INITIALIZATION_TIMEOUT,
+        )
+        .await;
+        if !names_not_initialized.is_empty() {
+            log::warn!(
+                "devices not initialized within timeout: {}",
+                names_not_initialized.join(", ")
+            );
+        }
+    }
+
+    // returns true if devices runtime responded within timeout
+    pub async fn health_check(
+        &self,
+        timeout: Duration,
+    ) -> bool {
+        tokio::time::timeout(timeout, self.inner.borrow_runtime().probe())
+            .await
+            .is_ok()
+    }
+
     pub async fn finalize(mut self) -> HashMap<DeviceId, DeviceWrapper<'d>> {
         let devices_gui_summary_sse_responder_runtime_scope_runnable = self
             .inner
@@ -161,27 +446,44 @@ impl<'d> Runner<'d> {
             .finalize()
             .await;
 
-        let exchanger_runtime_scope_runnable = self
-            .inner
-            .with_exchanger_runtime_scope_runnable_mut(|exchanger_runtime_scope_runnable| unsafe {
-                ManuallyDrop::take(exchanger_runtime_scope_runnable)
-            });
-        exchanger_runtime_scope_runnable.finalize().await;
+        let scenes_runner_runtime_scope_runnable =
+            self.inner.with_scenes_runner_runtime_scope_runnable_mut(
+                |scenes_runner_runtime_scope_runnable| unsafe {
+                    ManuallyDrop::take(scenes_runner_runtime_scope_runnable)
+                },
+            );
+        if let Some(scenes_runner_runtime_scope_runnable) = scenes_runner_runtime_scope_runnable {
+            scenes_runner_runtime_scope_runnable.finalize().await;
+        }
 
-        let devices_wrapper_runtime_scope_runnable =
+        // exchanger is kept running, so values set by devices while exiting still
+        // reach devices finalized in later stages
+        let mut devices_wrapper_runtime_scope_runnable =
             self.inner.with_devices_wrapper_runtime_scope_runnable_mut(
                 |devices_wrapper_runtime_scope_runnable| unsafe {
                     ManuallyDrop::take(devices_wrapper_runtime_scope_runnable)
                 },
             );
-        devices_wrapper_runtime_scope_runnable
-            .into_vec()
-            .into_iter()
-            .map(|device_wrapper_runtime_scope_runnable| {
-                device_wrapper_runtime_scope_runnable.finalize()
-            })
-            .collect::<JoinAll<_>>()
-            .await;
+        for finalize_stage in self.finalize_stages.iter() {
+            finalize_stage
+                .iter()
+                .map(|device_id| {
+                    devices_wrapper_runtime_scope_runnable
+                        .remove(device_id)
+                        .unwrap()
+                        .finalize()
+                })
+                .collect::<JoinAll<_>>()
+                .await;
+        }
+        assert!(devices_wrapper_runtime_scope_runnable.is_empty());
+
+        let exchanger_runtime_scope_runnable = self
+            .inner
+            .with_exchanger_runtime_scope_runnable_mut(|exchanger_runtime_scope_runnable| unsafe {
+                ManuallyDrop::take(exchanger_runtime_scope_runnable)
+            });
+        exchanger_runtime_scope_runnable.finalize().await;
 
         self.drop_guard.set();
 
@@ -189,6 +491,228 @@ impl<'d> Runner<'d> {
         inner_heads.device_wrappers_by_id
     }
 }
+
+type Stages = Box<[Box<[DeviceId]>]>;
+
+// strongly connected components of graph given as edges (device -> devices
+// after it), using tarjan algorithm
+// each component is sorted, components are in reverse topological order
+fn components(
+    after_device_ids_by_device_id: &HashMap<DeviceId, HashSet<DeviceId>>
+) -> Vec<Box<[DeviceId]>> {
+    struct State<'a> {
+        after_device_ids_by_device_id: &'a HashMap<DeviceId, HashSet<DeviceId>>,
+        index_next: usize,
+        // (index, lowest index reachable)
+        indices_by_device_id: HashMap<DeviceId, (usize, usize)>,
+        stack: Vec<DeviceId>,
+        stack_device_ids: HashSet<DeviceId>,
+        components: Vec<Box<[DeviceId]>>,
+    }
+    fn visit(
+        state: &mut State<'_>,
+        device_id: DeviceId,
+    ) {
+        let index = state.index_next;
+        state.index_next += 1;
+        state.indices_by_device_id.insert(device_id, (index, index));
+        state.stack.push(device_id);
+        state.stack_device_ids.insert(device_id);
+
+        let after_device_ids_by_device_id = state.after_device_ids_by_device_id;
+        for after_device_id in after_device_ids_by_device_id[&device_id].iter() {
+            let lowlink = match state.indices_by_device_id.get(after_device_id) {
+                None => {
+                    visit(state, *after_device_id);
+                    state.indices_by_device_id[after_device_id].1
+                }
+                Some((after_index, _)) if state.stack_device_ids.contains(after_device_id) => {
+                    *after_index
+                }
+                Some(_) => continue,
+            };
+            let (_, device_lowlink) = state.indices_by_device_id.get_mut(&device_id).unwrap();
+            *device_lowlink = (*device_lowlink).min(lowlink);
+        }
+
+        let (index, lowlink) = state.indices_by_device_id[&device_id];
+        if index == lowlink {
+            let mut component = Vec::<DeviceId>::new();
+            loop {
+                let component_device_id = state.stack.pop().unwrap();
+                state.stack_device_ids.remove(&component_device_id);
+                component.push(component_device_id);
+                if component_device_id == device_id {
+                    break;
+                }
+            }
+            component.sort();
+            state.components.push(component.into_boxed_slice());
+        }
+    }
+
+    let mut state = State {
+        after_device_ids_by_device_id,
+        index_next: 0,
+        indices_by_device_id: HashMap::new(),
+        stack: Vec::new(),
+        stack_device_ids: HashSet::new(),
+        components: Vec::new(),
+    };
+
+    // sorted, so result does not depend on hash map order
+    let mut device_ids = after_device_ids_by_device_id
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    device_ids.sort();
+    for device_id in device_ids {
+        if !state.indices_by_device_id.contains_key(&device_id) {
+            visit(&mut state, device_id);
+        }
+    }
+
+    state.components
+}
+
+// groups devices into stages, so each device comes after all devices it has
+// edge from (before, after)
+// devices in cycle are put together in single stage, devices after the cycle
+// follow in next stages, cycles are returned to be reported separately
+fn stages(
+    device_ids: impl Iterator<Item = DeviceId>,
+    edges: impl Iterator<Item = (DeviceId, DeviceId)>,
+) -> (Stages, Box<[Box<[DeviceId]>]>) {
+    let mut after_device_ids_by_device_id = device_ids
+        .map(|device_id| (device_id, HashSet::<DeviceId>::new()))
+        .collect::<HashMap<_, _>>();
+    for (before, after) in edges {
+        if before == after || !after_device_ids_by_device_id.contains_key(&after) {
+            continue;
+        }
+        if let Some(after_device_ids) = after_device_ids_by_device_id.get_mut(&before) {
+            after_device_ids.insert(after);
+        }
+    }
+
+    let components = components(&after_device_ids_by_device_id);
+    let component_index_by_device_id = components
+        .iter()
+        .enumerate()
+        .flat_map(|(component_index, component)| {
+            component
+                .iter()
+                .map(move |device_id| (*device_id, component_index))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // edges between components
+    let mut after_component_indices = vec![HashSet::<usize>::new(); components.len()];
+    let mut before_counts = vec![0usize; components.len()];
+    for (device_id, after_device_ids) in after_device_ids_by_device_id.iter() {
+        let component_index = component_index_by_device_id[device_id];
+        for after_device_id in after_device_ids.iter() {
+            let after_component_index = component_index_by_device_id[after_device_id];
+            if after_component_index != component_index
+                && after_component_indices[component_index].insert(after_component_index)
+            {
+                before_counts[after_component_index] += 1;
+            }
+        }
+    }
+
+    let mut stages = Vec::<Box<[DeviceId]>>::new();
+    let mut component_indices = (0..components.len())
+        .filter(|component_index| before_counts[*component_index] == 0)
+        .collect::<Vec<_>>();
+    while !component_indices.is_empty() {
+        let mut stage = component_indices
+            .iter()
+            .flat_map(|component_index| components[*component_index].iter().copied())
+            .collect::<Vec<_>>();
+        stage.sort();
+        stages.push(stage.into_boxed_slice());
+
+        let mut component_indices_next = Vec::<usize>::new();
+        for component_index in component_indices {
+            for after_component_index in after_component_indices[component_index].iter() {
+                before_counts[*after_component_index] -= 1;
+                if before_counts[*after_component_index] == 0 {
+                    component_indices_next.push(*after_component_index);
+                }
+            }
+        }
+        component_indices = component_indices_next;
+    }
+
+    let mut cycles = components
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .collect::<Vec<_>>();
+    cycles.sort();
+
+    (stages.into_boxed_slice(), cycles.into_boxed_slice())
+}
+
+// dependencies are started first
+fn start_stages(
+    device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>
+) -> Result<Stages, Error> {
+    for (device_id, device_wrapper) in device_wrappers_by_id {
+        for dependency in device_wrapper.dependencies() {
+            ensure!(
+                device_wrappers_by_id.contains_key(dependency),
+                "device {} ({}) depends on missing device {}",
+                device_id,
+                device_wrapper.name(),
+                dependency
+            );
+        }
+    }
+
+    let (start_stages, cycles) = stages(
+        device_wrappers_by_id.keys().copied(),
+        dependencies(device_wrappers_by_id).map(|(device_id, dependency)| (dependency, device_id)),
+    );
+    if let Some(cycle) = cycles.first() {
+        bail!("dependency cycle between devices {:?}", cycle);
+    }
+
+    Ok(start_stages)
+}
+
+// devices are finalized in stages, each one after all devices feeding its
+// inputs and all devices depending on it, so safe states applied by outputs on
+// exit (eg. relays opened) are not overwritten by upstream logic still running
+// devices in feedback loop are finalized together, devices downstream of it
+// after them
+fn finalize_stages(
+    device_ids: impl Iterator<Item = DeviceId>,
+    dependencies: impl Iterator<Item = (DeviceId, DeviceId)>,
+    connections_requested: &[ConnectionRequested],
+) -> Stages {
+    let connections = connections_requested
+        .iter()
+        .map(|(source, target)| (source.device_id(), target.device_id()));
+
+    let (finalize_stages, _) = stages(device_ids, dependencies.chain(connections));
+    finalize_stages
+}
+
+// (device, dependency)
+fn dependencies<'a>(
+    device_wrappers_by_id: &'a HashMap<DeviceId, DeviceWrapper<'_>>
+) -> impl Iterator<Item = (DeviceId, DeviceId)> + 'a {
+    device_wrappers_by_id
+        .iter()
+        .flat_map(|(device_id, device_wrapper)| {
+            device_wrapper
+                .dependencies()
+                .iter()
+                .map(|dependency| (*device_id, *dependency))
+        })
+}
+
 impl<'d> uri_cursor::Handler for Runner<'d> {
     fn handle(
         &self,
@@ -196,6 +720,178 @@ impl<'d> uri_cursor::Handler for Runner<'d> {
         uri_cursor: &uri_cursor::UriCursor,
     ) -> BoxFuture<'static, web::Response> {
         match uri_cursor {
+            uri_cursor::UriCursor::Next("classes", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let response = web::Response::ok_json_etag(&request, classes());
+                        async { response }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("maintenance", uri_cursor) => match self.maintenance {
+                Some(maintenance) => maintenance.handle(request, uri_cursor),
+                None => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("scenes", uri_cursor) => {
+                match self.inner.borrow_scenes_runner() {
+                    Some(scenes_runner) => scenes_runner.handle(request, uri_cursor),
+                    None => async { web::Response::error_404() }.boxed(),
+                }
+            }
+            uri_cursor::UriCursor::Next("supervisor", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        // device id -> status
+                        let statuses = self
+                            .inner
+                            .borrow_device_supervisors_by_id()
+                            .iter()
+                            .map(|(device_id, device_supervisor)| {
+                                let status = device_supervisor.statuses().into_vec().pop().unwrap();
+                                (*device_id, status)
+                            })
+                            .collect::<BTreeMap<_, _>>();
+                        async { web::Response::ok_json(statuses) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("signals", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Next("meta", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            // device id -> signal name -> metadata
+                            let metadata = self
+                                .inner
+                                .borrow_device_wrappers_by_id()
+                                .iter()
+                                .filter_map(|(device_id, device_wrapper)| {
+                                    let metadata = device_wrapper
+                                        .device()
+                                        .as_signals_device_base()
+                                        .metadata()
+                                        .into_iter()
+                                        .map(|(signal_identifier, metadata)| {
+                                            (signal_identifier.name(), metadata)
+                                        })
+                                        .collect::<BTreeMap<_, _>>();
+                                    if metadata.is_empty() {
+                                        return None;
+                                    }
+                                    Some((*device_id, metadata))
+                                })
+                                .collect::<BTreeMap<_, _>>();
+
+                            let response = web::Response::ok_json_etag(&request, metadata);
+                            async { response }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("shadow", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            let shadow_comparisons = self.inner.borrow_exchanger().shadow_compare();
+                            async { web::Response::ok_json(shadow_comparisons) }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("latency", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            #[derive(Debug, Serialize)]
+                            struct Status {
+                                enabled: bool,
+                                routes: Box<[latency::RouteLatency]>,
+                            }
+
+                            let status = Status {
+                                enabled: latency::enabled(),
+                                routes: self.inner.borrow_exchanger().latency(),
+                            };
+                            async { web::Response::ok_json(status) }.boxed()
+                        }
+                        http::Method::PUT => {
+                            let enabled = match request.body_parse_json::<bool>() {
+                                Ok(enabled) => enabled,
+                                Err(error) => {
+                                    return async { web::Response::error_400_from_error(error) }
+                                        .boxed();
+                                }
+                            };
+
+                            log::info!("signals latency tracing enabled: {}", enabled);
+                            latency::enabled_set(enabled);
+
+                            async { web::Response::ok_empty() }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("targets-write", uri_cursor) => {
+                    match uri_cursor.as_ref() {
+                        uri_cursor::UriCursor::Terminal => match *request.method() {
+                            http::Method::POST => {
+                                let target_writes =
+                                    match request.body_parse_json::<Vec<TargetWrite>>() {
+                                        Ok(target_writes) => target_writes,
+                                        Err(error) => {
+                                            return async {
+                                                web::Response::error_400_from_error(error)
+                                            }
+                                            .boxed();
+                                        }
+                                    };
+
+                                // writes are not applied until all marked signals are confirmed
+                                let device_wrappers_by_id =
+                                    self.inner.borrow_device_wrappers_by_id();
+                                let confirmation_required = target_writes
+                                    .iter()
+                                    .filter_map(|target_write| {
+                                        let target_address = &target_write.target_address;
+                                        let device_wrapper =
+                                            device_wrappers_by_id.get(&target_address.device_id)?;
+                                        device_wrapper
+                                            .confirmation_required(&target_address.signal)
+                                            .then(|| {
+                                                format!(
+                                                    "{} {}",
+                                                    device_wrapper.name(),
+                                                    target_address.signal
+                                                )
+                                            })
+                                    })
+                                    .collect::<Box<[_]>>();
+                                if !confirmation_required.is_empty() {
+                                    if let Some(response) = confirmation::confirmations()
+                                        .check(&request, &confirmation_required.join(", "))
+                                    {
+                                        return async { response }.boxed();
+                                    }
+                                }
+
+                                match self.inner.borrow_exchanger().targets_write(target_writes) {
+                                    Ok(()) => async { web::Response::ok_empty() }.boxed(),
+                                    Err(error) => {
+                                        async { web::Response::error_400_from_error(error) }.boxed()
+                                    }
+                                }
+                            }
+                            _ => async { web::Response::error_405() }.boxed(),
+                        },
+                        _ => async { web::Response::error_404() }.boxed(),
+                    }
+                }
+                _ => async { web::Response::error_404() }.boxed(),
+            },
             uri_cursor::UriCursor::Next("devices", uri_cursor) => match uri_cursor.as_ref() {
                 uri_cursor::UriCursor::Next("list", uri_cursor) => match uri_cursor.as_ref() {
                     uri_cursor::UriCursor::Terminal => match *request.method() {
@@ -206,7 +902,81 @@ impl<'d> uri_cursor::Handler for Runner<'d> {
                                 .keys()
                                 .copied()
                                 .collect::<Box<[_]>>();
-                            async { web::Response::ok_json(device_ids) }.boxed()
+                            let response = web::Response::ok_json_etag(&request, device_ids);
+                            async { response }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("budget", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            #[derive(Debug, Serialize)]
+                            struct DeviceBudget {
+                                device_id: DeviceId,
+                                name: String,
+                                class: Cow<'static, str>,
+                                polls: u64,
+                                poll_time_seconds: f64,
+                            }
+
+                            let mut device_budgets = self
+                                .inner
+                                .borrow_device_wrappers_budgeted_by_id()
+                                .iter()
+                                .map(|(device_id, device_wrapper_budgeted)| {
+                                    let device_wrapper = device_wrapper_budgeted.device_wrapper;
+                                    let poll_budget = &device_wrapper_budgeted.poll_budget;
+                                    DeviceBudget {
+                                        device_id: *device_id,
+                                        name: device_wrapper.name().clone(),
+                                        class: device_wrapper.device().class(),
+                                        polls: poll_budget.polls(),
+                                        poll_time_seconds: poll_budget.poll_time().as_secs_f64(),
+                                    }
+                                })
+                                .collect::<Vec<_>>();
+                            // most expensive first
+                            device_budgets.sort_by(|a, b| {
+                                b.poll_time_seconds.total_cmp(&a.poll_time_seconds)
+                            });
+
+                            async { web::Response::ok_json(device_budgets) }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("gui-summaries", uri_cursor) => match uri_cursor
+                    .as_ref()
+                {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            // ?class=<prefix> may be repeated, no filter means all devices
+                            let class_filters = form_urlencoded::parse(
+                                request.uri().query().unwrap_or("").as_bytes(),
+                            )
+                            .filter(|(key, _)| key == "class")
+                            .map(|(_, value)| value.into_owned())
+                            .collect::<Box<[_]>>();
+
+                            let gui_summaries = self
+                                .inner
+                                .borrow_device_wrappers_by_id()
+                                .iter()
+                                .filter(|(_, device_wrapper)| {
+                                    class_matches(&device_wrapper.device().class(), &class_filters)
+                                })
+                                .filter_map(|(device_id, device_wrapper)| {
+                                    let gui_summary_device_base =
+                                        device_wrapper.device().as_gui_summary_device_base()?;
+                                    Some((*device_id, gui_summary_device_base.value()))
+                                })
+                                .collect::<BTreeMap<_, _>>();
+
+                            let response = web::Response::ok_json_etag(&request, gui_summaries);
+                            async { response }.boxed()
                         }
                         _ => async { web::Response::error_405() }.boxed(),
                     },
@@ -236,3 +1006,186 @@ impl<'d> uri_cursor::Handler for Runner<'d> {
         }
     }
 }
+
+// empty filters match every class
+fn class_matches(
+    class: &str,
+    class_filters: &[String],
+) -> bool {
+    class_filters.is_empty()
+        || class_filters
+            .iter()
+            .any(|class_filter| class.starts_with(class_filter.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{class_matches, finalize_stages, stages, Runner};
+    use crate::{
+        devices::{self, soft::time::pulse_a::SignalIdentifier, DeviceWrapper},
+        signals::{self, exchanger::DeviceIdSignalIdentifierBaseWrapper, IdentifierBaseWrapper},
+        util::{
+            async_flag,
+            runnable::{Exited, Runnable},
+        },
+    };
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use std::{borrow::Cow, collections::HashMap, sync::Arc, time::Duration};
+    use tokio::time::{sleep, Instant};
+
+    // (started, initialized)
+    type InitializingDeviceTimes = Arc<Mutex<(Option<Instant>, Option<Instant>)>>;
+
+    // reports initialization given time after start
+    #[derive(Debug)]
+    struct InitializingDevice {
+        delay: Duration,
+        times: InitializingDeviceTimes,
+        initialized: async_flag::LocalSender,
+    }
+    impl InitializingDevice {
+        fn new(delay: Duration) -> (Self, InitializingDeviceTimes) {
+            let times = InitializingDeviceTimes::default();
+            let device = Self {
+                delay,
+                times: times.clone(),
+                initialized: async_flag::LocalSender::new(),
+            };
+            (device, times)
+        }
+    }
+    impl devices::Device for InitializingDevice {
+        fn class(&self) -> Cow<'static, str> {
+            Cow::from("test/initializing")
+        }
+
+        fn as_runnable(&self) -> &dyn Runnable {
+            self
+        }
+        fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+            self
+        }
+        fn initialized(&self) -> Option<&async_flag::LocalSender> {
+            Some(&self.initialized)
+        }
+    }
+    #[async_trait]
+    impl Runnable for InitializingDevice {
+        async fn run(
+            &self,
+            exit_flag: async_flag::Receiver,
+        ) -> Exited {
+            self.times.lock().0 = Some(Instant::now());
+            sleep(self.delay).await;
+            self.times.lock().1 = Some(Instant::now());
+            self.initialized.signal();
+
+            exit_flag.await;
+            Exited
+        }
+    }
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    enum InitializingDeviceSignalIdentifier {}
+    impl signals::Identifier for InitializingDeviceSignalIdentifier {}
+    impl signals::Device for InitializingDevice {
+        fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+            None
+        }
+        fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+            None
+        }
+
+        type Identifier = InitializingDeviceSignalIdentifier;
+        fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+            signals::ByIdentifier::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn start_waits_for_slow_dependency() {
+        let (dependency, dependency_times) = InitializingDevice::new(Duration::from_millis(200));
+        let (dependent, dependent_times) = InitializingDevice::new(Duration::ZERO);
+
+        let dependency = DeviceWrapper::new("dependency".to_owned(), Box::new(dependency));
+        let mut dependent = DeviceWrapper::new("dependent".to_owned(), Box::new(dependent));
+        dependent.dependency_add(1);
+
+        let runner = Runner::new(
+            HashMap::from([(1, dependency), (2, dependent)]),
+            &[],
+            None,
+            None,
+            1,
+        )
+        .unwrap();
+
+        runner.initialized().await;
+        runner.finalize().await;
+
+        let (_, dependency_initialized) = *dependency_times.lock();
+        let (dependent_started, dependent_initialized) = *dependent_times.lock();
+        assert!(dependent_started.unwrap() >= dependency_initialized.unwrap());
+        assert!(dependent_initialized.is_some());
+    }
+
+    #[test]
+    fn finalize_stages_follow_signals() {
+        let connection = |source, target| {
+            (
+                DeviceIdSignalIdentifierBaseWrapper::new(
+                    source,
+                    IdentifierBaseWrapper::new(SignalIdentifier::Output),
+                ),
+                DeviceIdSignalIdentifierBaseWrapper::new(
+                    target,
+                    IdentifierBaseWrapper::new(SignalIdentifier::Input),
+                ),
+            )
+        };
+
+        // 1 -> 2 -> 3, 4 <-> 5 -> 6, 6 -> 7 <-> 8
+        let connections_requested = [
+            connection(1, 2),
+            connection(2, 3),
+            connection(4, 5),
+            connection(5, 4),
+            connection(5, 6),
+            connection(6, 7),
+            connection(7, 8),
+            connection(8, 7),
+        ];
+
+        let stages = finalize_stages(1..=8, [].into_iter(), &connections_requested);
+        let stages = stages.iter().map(|stage| &**stage).collect::<Vec<_>>();
+        assert_eq!(stages, [&[1, 4, 5][..], &[2, 6], &[3, 7, 8]]);
+    }
+
+    #[test]
+    fn stages_cycles() {
+        // 1 -> 2 <-> 3 -> 4, 5 -> 5
+        let (stages, cycles) = stages(1..=5, [(1, 2), (2, 3), (3, 2), (3, 4), (5, 5)].into_iter());
+        let stages = stages.iter().map(|stage| &**stage).collect::<Vec<_>>();
+        assert_eq!(stages, [&[1, 5][..], &[2, 3], &[4]]);
+        assert_eq!(&*cycles, [Box::from([2, 3])]);
+    }
+
+    #[test]
+    fn finalize_stages_follow_dependencies() {
+        // 2 depends on 1, 3 depends on 2
+        let stages = finalize_stages(1..=3, [(2, 1), (3, 2)].into_iter(), &[]);
+        let stages = stages.iter().map(|stage| &**stage).collect::<Vec<_>>();
+        assert_eq!(stages, [&[3][..], &[2], &[1]]);
+    }
+
+    #[test]
+    fn class_matches_prefix() {
+        assert!(class_matches("dahua/ipc_a", &[]));
+        assert!(class_matches("dahua/ipc_a", &["dahua/".to_owned()]));
+        assert!(class_matches(
+            "dahua/ipc_a",
+            &["hikvision/".to_owned(), "dahua/ipc_a".to_owned()]
+        ));
+        assert!(!class_matches("soft/time/pulse_a", &["dahua/".to_owned()]));
+    }
+}
@@ -0,0 +1,504 @@
+use super::{DeviceWrapper, Id as DeviceId};
+use crate::{
+    modules::{fs::Fs, sqlite::SQLite},
+    signals::exchanger::{Exchanger, TargetAddress, TargetWrite},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::{anyhow, bail, Context, Error};
+use async_trait::async_trait;
+use atomic_refcell::AtomicRefCell;
+use futures::{
+    channel::mpsc,
+    future::{BoxFuture, Future, FutureExt},
+    stream::StreamExt,
+};
+use indoc::indoc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// captured value of single state target
+// device is referenced by name, as device ids are positional and change when
+// devices are added or removed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SceneTarget {
+    device_name: String,
+    device_class: String,
+    signal: String,
+    value: serde_json::Value,
+}
+
+// resolves scene targets against running devices
+// targets of devices that no longer exist (or are ambiguous), changed class or
+// lost the signal are skipped with a warning, so remaining part of the scene
+// can still be restored
+// signals requiring confirmation are skipped too, as scene restore would write
+// them without it
+fn scene_targets_resolve(
+    scene_targets: &[SceneTarget],
+    device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+    target_exists: impl Fn(&TargetAddress) -> bool,
+) -> Vec<TargetWrite> {
+    // None if multiple devices share the name
+    let mut device_ids_by_name = HashMap::<&str, Option<DeviceId>>::new();
+    for (device_id, device_wrapper) in device_wrappers_by_id {
+        device_ids_by_name
+            .entry(device_wrapper.name())
+            .and_modify(|device_id| *device_id = None)
+            .or_insert(Some(*device_id));
+    }
+
+    scene_targets
+        .iter()
+        .filter_map(|scene_target| {
+            let device_id = match device_ids_by_name.get(scene_target.device_name.as_str()) {
+                Some(Some(device_id)) => *device_id,
+                Some(None) => {
+                    log::warn!(
+                        "scenes: device {} is ambiguous, skipping",
+                        scene_target.device_name
+                    );
+                    return None;
+                }
+                None => {
+                    log::warn!(
+                        "scenes: device {} not found, skipping",
+                        scene_target.device_name
+                    );
+                    return None;
+                }
+            };
+
+            let device_wrapper = &device_wrappers_by_id[&device_id];
+            let device_class = device_wrapper.device().class();
+            if device_class != scene_target.device_class {
+                log::warn!(
+                    "scenes: device {} class changed from {} to {}, skipping",
+                    scene_target.device_name,
+                    scene_target.device_class,
+                    device_class
+                );
+                return None;
+            }
+
+            if device_wrapper.confirmation_required(&scene_target.signal) {
+                log::warn!(
+                    "scenes: signal {} on device {} requires confirmation, skipping",
+                    scene_target.signal,
+                    scene_target.device_name
+                );
+                return None;
+            }
+
+            let target_address = TargetAddress {
+                device_id,
+                signal: scene_target.signal.clone(),
+            };
+            if !target_exists(&target_address) {
+                log::warn!(
+                    "scenes: signal {} not found on device {}, skipping",
+                    scene_target.signal,
+                    scene_target.device_name
+                );
+                return None;
+            }
+
+            Some(TargetWrite {
+                target_address,
+                value: scene_target.value.clone(),
+            })
+        })
+        .collect::<Vec<_>>()
+}
+
+// named snapshots of state targets values across devices
+// snapshots are kept in memory and persisted in sqlite, so they can be restored
+// synchronously (all values are written in single exchanger batch)
+#[derive(Debug)]
+pub struct Scenes<'f> {
+    sqlite: SQLite<'f>,
+
+    scenes: RwLock<HashMap<String, Box<[SceneTarget]>>>,
+
+    restore_requests_sender: mpsc::UnboundedSender<String>,
+    restore_requests_receiver: AtomicRefCell<mpsc::UnboundedReceiver<String>>,
+}
+impl<'f> Scenes<'f> {
+    pub fn new(fs: &'f Fs) -> Self {
+        let sqlite = SQLite::new("devices.scenes".to_owned(), fs);
+
+        let (restore_requests_sender, restore_requests_receiver) = mpsc::unbounded::<String>();
+        let restore_requests_receiver = AtomicRefCell::new(restore_requests_receiver);
+
+        Self {
+            sqlite,
+
+            scenes: RwLock::new(HashMap::<String, Box<[SceneTarget]>>::new()),
+
+            restore_requests_sender,
+            restore_requests_receiver,
+        }
+    }
+
+    pub fn names(&self) -> Box<[String]> {
+        let mut names = self.scenes.read().keys().cloned().collect::<Box<[_]>>();
+        names.sort();
+        names
+    }
+
+    // scene is restored by runner as soon as possible
+    pub fn restore_request(
+        &self,
+        name: String,
+    ) {
+        self.restore_requests_sender.unbounded_send(name).unwrap();
+    }
+
+    fn capture(
+        &self,
+        exchanger: &Exchanger,
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+        name: String,
+        target_addresses: &[TargetAddress],
+    ) -> Result<impl Future<Output = Result<(), Error>> + Send + 'static, Error> {
+        // restoring them would bypass confirmation
+        if let Some(target_address) = target_addresses.iter().find(|target_address| {
+            device_wrappers_by_id
+                .get(&target_address.device_id)
+                .is_some_and(|device_wrapper| {
+                    device_wrapper.confirmation_required(&target_address.signal)
+                })
+        }) {
+            bail!(
+                "signal {} on device {} requires confirmation",
+                target_address.signal,
+                target_address.device_id
+            );
+        }
+
+        let scene_targets = exchanger
+            .targets_read(target_addresses)
+            .context("targets_read")?
+            .into_vec()
+            .into_iter()
+            .map(|target_write| {
+                // exchanger already checked that device exists
+                let device_wrapper = &device_wrappers_by_id[&target_write.target_address.device_id];
+                SceneTarget {
+                    device_name: device_wrapper.name().clone(),
+                    device_class: device_wrapper.device().class().into_owned(),
+                    signal: target_write.target_address.signal,
+                    value: target_write.value,
+                }
+            })
+            .collect::<Box<[_]>>();
+        let scene_targets_json = serde_json::to_string(&scene_targets).context("to_string")?;
+
+        self.scenes.write().insert(name.clone(), scene_targets);
+
+        let persisted = self
+            .sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                transaction
+                    .execute(
+                        indoc!(
+                            "
+                        INSERT OR REPLACE INTO
+                            `scenes` (`name`, `targets`)
+                        VALUES
+                            (?, ?)
+                    "
+                        ),
+                        (&name, &scene_targets_json),
+                    )
+                    .context("execute")?;
+                Ok(())
+            });
+
+        Ok(persisted.map(|result| result.and_then(|result| result)))
+    }
+
+    fn remove(
+        &self,
+        name: String,
+    ) -> Result<impl Future<Output = Result<(), Error>> + Send + 'static, Error> {
+        self.scenes
+            .write()
+            .remove(&name)
+            .ok_or_else(|| anyhow!("scene {} not found", name))?;
+
+        let persisted = self
+            .sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                transaction
+                    .execute("DELETE FROM `scenes` WHERE `name` = ?", (&name,))
+                    .context("execute")?;
+                Ok(())
+            });
+
+        Ok(persisted.map(|result| result.and_then(|result| result)))
+    }
+
+    fn restore(
+        &self,
+        exchanger: &Exchanger,
+        device_wrappers_by_id: &HashMap<DeviceId, DeviceWrapper<'_>>,
+        name: &str,
+    ) -> Result<(), Error> {
+        let target_writes = scene_targets_resolve(
+            self.scenes
+                .read()
+                .get(name)
+                .ok_or_else(|| anyhow!("scene {} not found", name))?,
+            device_wrappers_by_id,
+            |target_address| exchanger.target_exists(target_address),
+        );
+
+        exchanger
+            .targets_write(target_writes)
+            .context("targets_write")?;
+
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<(), Error> {
+        let scenes = self
+            .sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                transaction
+                    .execute_batch(indoc!(
+                        "
+                        CREATE TABLE IF NOT EXISTS `scenes` (
+                            `name` TEXT PRIMARY KEY NOT NULL,
+                            `targets` TEXT NOT NULL -- json
+                        ) STRICT;
+                    "
+                    ))
+                    .context("initialize")?;
+
+                let scenes = transaction
+                    .prepare("SELECT `name`, `targets` FROM `scenes`")
+                    .context("prepare")?
+                    .query_map([], |row| -> rusqlite::Result<(String, String)> {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .context("query_map")?
+                    .collect::<Result<Box<[_]>, _>>()
+                    .context("collect")?;
+
+                Ok(scenes)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        let mut scenes_lock = self.scenes.write();
+        for (name, scene_targets_json) in scenes.into_vec() {
+            let scene_targets = serde_json::from_str::<Box<[SceneTarget]>>(&scene_targets_json)
+                .with_context(|| format!("scene {}", name))?;
+
+            // scenes captured before loading completed are newer
+            scenes_lock.entry(name).or_insert(scene_targets);
+        }
+        drop(scenes_lock);
+
+        Ok(())
+    }
+}
+
+// binds scenes to exchanger of running devices
+#[derive(Debug)]
+pub struct Runner<'e, 's> {
+    scenes: &'s Scenes<'s>,
+    device_wrappers_by_id: &'e HashMap<DeviceId, DeviceWrapper<'e>>,
+    exchanger: &'e Exchanger<'e>,
+}
+impl<'e, 's> Runner<'e, 's> {
+    pub fn new(
+        scenes: &'s Scenes<'s>,
+        device_wrappers_by_id: &'e HashMap<DeviceId, DeviceWrapper<'e>>,
+        exchanger: &'e Exchanger<'e>,
+    ) -> Self {
+        Self {
+            scenes,
+            device_wrappers_by_id,
+            exchanger,
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        if let Err(error) = self.scenes.load().await.context("load") {
+            log::error!("scenes: {:?}", error);
+        }
+
+        let mut restore_requests_receiver = self.scenes.restore_requests_receiver.borrow_mut();
+        restore_requests_receiver
+            .by_ref()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |name| {
+                if let Err(error) =
+                    self.scenes
+                        .restore(self.exchanger, self.device_wrappers_by_id, &name)
+                {
+                    log::error!("scenes: restore {}: {:?}", name, error);
+                }
+            })
+            .await;
+
+        Exited
+    }
+}
+#[async_trait]
+impl<'e, 's> Runnable for Runner<'e, 's> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+impl<'e, 's> uri_cursor::Handler for Runner<'e, 's> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let names = self.scenes.names();
+                    async { web::Response::ok_json(names) }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next(name, uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::PUT => {
+                        let target_addresses =
+                            match request.body_parse_json::<Box<[TargetAddress]>>() {
+                                Ok(target_addresses) => target_addresses,
+                                Err(error) => {
+                                    return async { web::Response::error_400_from_error(error) }
+                                        .boxed();
+                                }
+                            };
+
+                        match self.scenes.capture(
+                            self.exchanger,
+                            self.device_wrappers_by_id,
+                            (*name).to_owned(),
+                            &target_addresses,
+                        ) {
+                            Ok(persisted) => async {
+                                match persisted.await {
+                                    Ok(()) => web::Response::ok_empty(),
+                                    Err(error) => {
+                                        log::error!("scenes: capture: {:?}", error);
+                                        web::Response::error_500()
+                                    }
+                                }
+                            }
+                            .boxed(),
+                            Err(error) => {
+                                async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        }
+                    }
+                    http::Method::DELETE => match self.scenes.remove((*name).to_owned()) {
+                        Ok(persisted) => async {
+                            match persisted.await {
+                                Ok(()) => web::Response::ok_empty(),
+                                Err(error) => {
+                                    log::error!("scenes: remove: {:?}", error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed(),
+                        Err(error) => async { web::Response::error_400_from_error(error) }.boxed(),
+                    },
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next("restore", uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::POST => match self.scenes.restore(
+                            self.exchanger,
+                            self.device_wrappers_by_id,
+                            name,
+                        ) {
+                            Ok(()) => async { web::Response::ok_empty() }.boxed(),
+                            Err(error) => {
+                                async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        },
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scene_targets_resolve, SceneTarget};
+    use crate::devices::{soft::time::pulse_a, DeviceWrapper};
+    use serde_json::json;
+    use std::{collections::HashMap, time::Duration};
+
+    #[test]
+    fn scene_targets_resolve_by_name() {
+        let device_wrapper = |name: &str| {
+            let mut device_wrapper = DeviceWrapper::new(
+                name.to_owned(),
+                Box::new(pulse_a::Device::new(pulse_a::Configuration {
+                    duration: Duration::from_secs(1),
+                })),
+            );
+            if name == "gate" {
+                device_wrapper.confirmation_required_set("input".to_owned());
+            }
+            device_wrapper
+        };
+        // ids differ from the ones scene was captured with
+        let device_wrappers_by_id = HashMap::from([
+            (5, device_wrapper("hall")),
+            (6, device_wrapper("kitchen")),
+            (7, device_wrapper("kitchen")),
+            (8, device_wrapper("gate")),
+        ]);
+
+        let scene_target = |device_name: &str, device_class: &str, signal: &str| SceneTarget {
+            device_name: device_name.to_owned(),
+            device_class: device_class.to_owned(),
+            signal: signal.to_owned(),
+            value: json!(true),
+        };
+        let scene_targets = [
+            scene_target("hall", "soft/time/pulse_a", "input"),
+            scene_target("hall", "soft/time/pulse_a", "removed"),
+            scene_target("hall", "soft/time/countdown_a", "input"),
+            scene_target("kitchen", "soft/time/pulse_a", "input"),
+            scene_target("garage", "soft/time/pulse_a", "input"),
+            scene_target("gate", "soft/time/pulse_a", "input"),
+        ];
+
+        let target_writes =
+            scene_targets_resolve(&scene_targets, &device_wrappers_by_id, |target_address| {
+                target_address.signal != "removed"
+            });
+        assert_eq!(target_writes.len(), 1);
+        assert_eq!(target_writes[0].target_address.device_id, 5);
+        assert_eq!(target_writes[0].target_address.signal, "input");
+    }
+}
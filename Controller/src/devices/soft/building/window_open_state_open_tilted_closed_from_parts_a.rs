@@ -11,9 +11,11 @@ use crate::{
 use async_trait::async_trait;
 use futures::stream::StreamExt;
 use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     // whether to treat opened = true, tilted = false as "Open" (true) or
     // "Unknown" (false). This could be useful not to go to Unknown state if
@@ -5,6 +5,7 @@ use crate::{
         real::Real,
     },
     devices,
+    modules::clock::Clock,
     signals::{self, signal},
     util::{
         async_flag,
@@ -12,7 +13,6 @@ use crate::{
     },
 };
 use async_trait::async_trait;
-use chrono::Utc;
 use futures::{future::FutureExt, select};
 use maplit::hashmap;
 use parking_lot::RwLock;
@@ -26,8 +26,9 @@ pub struct Configuration {
 }
 
 #[derive(Debug)]
-pub struct Device {
+pub struct Device<'c> {
     configuration: Configuration,
+    clock: &'c Clock<'c>,
 
     spa: RwLock<Option<spa::SPA2>>,
 
@@ -37,10 +38,14 @@ pub struct Device {
 
     gui_summary_waker: devices::gui_summary::Waker,
 }
-impl Device {
-    pub fn new(configuration: Configuration) -> Self {
+impl<'c> Device<'c> {
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+    ) -> Self {
         Self {
             configuration,
+            clock,
 
             spa: RwLock::new(None),
 
@@ -55,7 +60,7 @@ impl Device {
     }
 
     fn calculate(&self) {
-        let datetime = Utc::now();
+        let datetime = self.clock.now();
 
         let spa0 = spa::SPA0::calculate();
         let spa1 = spa::SPA1::calculate(spa0, datetime, spa::DELTA_T_DEFAULT);
@@ -109,7 +114,7 @@ impl Device {
     }
 }
 
-impl devices::Device for Device {
+impl<'c> devices::Device for Device<'c> {
     fn class(&self) -> Cow<'static, str> {
         Cow::from("soft/calendar/solar_position_a")
     }
@@ -126,7 +131,7 @@ impl devices::Device for Device {
 }
 
 #[async_trait]
-impl Runnable for Device {
+impl<'c> Runnable for Device<'c> {
     async fn run(
         &self,
         exit_flag: async_flag::Receiver,
@@ -141,7 +146,7 @@ pub enum SignalIdentifier {
     Azimuth,
 }
 impl signals::Identifier for SignalIdentifier {}
-impl signals::Device for Device {
+impl<'c> signals::Device for Device<'c> {
     fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
         None
     }
@@ -169,7 +174,7 @@ struct GuiSummaryInner {
 pub struct GuiSummary {
     inner: Option<GuiSummaryInner>,
 }
-impl devices::gui_summary::Device for Device {
+impl<'c> devices::gui_summary::Device for Device<'c> {
     fn waker(&self) -> &devices::gui_summary::Waker {
         &self.gui_summary_waker
     }
@@ -0,0 +1,367 @@
+use crate::{
+    datatypes::temperature::Temperature,
+    devices,
+    modules::clock::Clock,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Days, NaiveTime, Utc, Weekday};
+use futures::{future::FutureExt, select, stream::StreamExt};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{borrow::Cow, time::Duration};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SchedulePeriod {
+    // local time, period may span midnight
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+impl SchedulePeriod {
+    fn contains(
+        &self,
+        time: NaiveTime,
+    ) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            self.from <= time || time < self.to
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Boost {
+    // weekly anti-legionella cycle, started at given local weekday and time
+    pub weekday: Weekday,
+    pub time: NaiveTime,
+
+    pub temperature: Temperature,
+    // how long temperature must be held after being reached
+    pub hold: Duration,
+    // cycle is abandoned if temperature is not reached in this time
+    pub timeout: Duration,
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    // comfort setpoint is used within schedule periods, eco outside of them
+    pub setpoint_comfort: Temperature,
+    pub setpoint_eco: Temperature,
+    pub schedule: Box<[SchedulePeriod]>,
+
+    // heating starts when tank temperature drops hysteresis kelvins below
+    // setpoint and stops when setpoint is reached
+    pub hysteresis: f64,
+
+    pub boost: Boost,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BoostRunning {
+    started: DateTime<Utc>,
+    reached: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct State {
+    heating: bool,
+    boost_running: Option<BoostRunning>,
+    boost_next: DateTime<Utc>,
+}
+
+// domestic hot water tank controller
+// regular heating is done through Valve (ex. boiler coil), boost cycle uses
+// Heater (ex. electric element), as boiler usually can't reach boost
+// temperature. missing tank temperature disables heating.
+#[derive(Debug)]
+pub struct Device<'c> {
+    configuration: Configuration,
+    clock: &'c Clock<'c>,
+
+    state: RwLock<State>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_temperature: signal::state_target_last::Signal<Temperature>,
+    signal_valve: signal::state_source::Signal<bool>,
+    signal_heater: signal::state_source::Signal<bool>,
+    signal_boost: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'c> Device<'c> {
+    const INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+    ) -> Self {
+        assert!(configuration.hysteresis >= 0.0);
+
+        let boost_next = Self::boost_next(&configuration.boost, clock);
+
+        Self {
+            configuration,
+            clock,
+
+            state: RwLock::new(State {
+                heating: false,
+                boost_running: None,
+                boost_next,
+            }),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_temperature: signal::state_target_last::Signal::<Temperature>::new(),
+            signal_valve: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_heater: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_boost: signal::state_source::Signal::<bool>::new(Some(false)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // first boost start strictly after now
+    fn boost_next(
+        boost: &Boost,
+        clock: &Clock,
+    ) -> DateTime<Utc> {
+        let now = clock.now();
+        let today = clock.now_local().date_naive();
+
+        (0..=7)
+            .map(|days| today.checked_add_days(Days::new(days)).unwrap())
+            .filter(|date| date.weekday() == boost.weekday)
+            .map(|date| clock.local(date, boost.time).with_timezone(&Utc))
+            .find(|datetime| *datetime > now)
+            .unwrap()
+    }
+
+    fn calculate(&self) {
+        let now = self.clock.now();
+        let time = self.clock.now_local().time();
+        let temperature = self.signal_temperature.take_last().value;
+
+        let mut state = self.state.write();
+
+        // boost cycle
+        if now >= state.boost_next {
+            state.boost_running = Some(BoostRunning {
+                started: now,
+                reached: None,
+            });
+            state.boost_next = Self::boost_next(&self.configuration.boost, self.clock);
+            log::info!("dhw boost started");
+        }
+        if let Some(boost_running) = &mut state.boost_running {
+            if boost_running.reached.is_none()
+                && temperature
+                    .is_some_and(|temperature| temperature >= self.configuration.boost.temperature)
+            {
+                boost_running.reached = Some(now);
+            }
+
+            let finished = match boost_running.reached {
+                Some(reached) => {
+                    (now - reached).to_std().unwrap_or_default() >= self.configuration.boost.hold
+                }
+                None => {
+                    let timed_out = (now - boost_running.started).to_std().unwrap_or_default()
+                        >= self.configuration.boost.timeout;
+                    if timed_out {
+                        log::warn!("dhw boost temperature not reached, abandoning");
+                    }
+                    timed_out
+                }
+            };
+            if finished {
+                state.boost_running = None;
+            }
+        }
+        let boost = state.boost_running.is_some();
+
+        // setpoint
+        let setpoint = if boost {
+            self.configuration.boost.temperature
+        } else if self
+            .configuration
+            .schedule
+            .iter()
+            .any(|schedule_period| schedule_period.contains(time))
+        {
+            self.configuration.setpoint_comfort
+        } else {
+            self.configuration.setpoint_eco
+        };
+
+        state.heating = match temperature {
+            Some(temperature) => {
+                let temperature = temperature.to_kelvins();
+                let setpoint = setpoint.to_kelvins();
+                if state.heating {
+                    temperature < setpoint
+                } else {
+                    temperature < setpoint - self.configuration.hysteresis
+                }
+            }
+            None => false,
+        };
+        let heating = state.heating;
+        drop(state);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_valve.set_one(Some(heating && !boost));
+        signals_sources_changed |= self.signal_heater.set_one(Some(heating && boost));
+        signals_sources_changed |= self.signal_boost.set_one(Some(boost));
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+
+        self.gui_summary_waker.wake();
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            self.calculate();
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = timer_wheel::sleep(Self::INTERVAL).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'c> devices::Device for Device<'c> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/climate/dhw_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'c> Runnable for Device<'c> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Temperature,
+    Valve,
+    Heater,
+    Boost,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c> signals::Device for Device<'c> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Temperature => &self.signal_temperature as &dyn signal::Base,
+            SignalIdentifier::Valve => &self.signal_valve as &dyn signal::Base,
+            SignalIdentifier::Heater => &self.signal_heater as &dyn signal::Base,
+            SignalIdentifier::Boost => &self.signal_boost as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    temperature: Option<Temperature>,
+    heating: bool,
+    boost: bool,
+    boost_next: DateTime<Utc>,
+}
+impl<'c> devices::gui_summary::Device for Device<'c> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let temperature = self.signal_temperature.peek_last();
+        let state = self.state.read();
+
+        Self::Value {
+            temperature,
+            heating: state.heating,
+            boost: state.boost_running.is_some(),
+            boost_next: state.boost_next,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Boost, Device};
+    use crate::{
+        datatypes::temperature::{Temperature, Unit},
+        modules::clock::{Clock, Configuration, SourceSimulated},
+    };
+    use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+    use chrono_tz::Europe::Warsaw;
+    use std::time::Duration;
+
+    #[test]
+    fn boost_next() {
+        let boost = Boost {
+            weekday: Weekday::Sun,
+            time: NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            temperature: Temperature::from_unit(Unit::Celsius, 60.0).unwrap(),
+            hold: Duration::from_secs(30 * 60),
+            timeout: Duration::from_secs(3 * 60 * 60),
+        };
+
+        // saturday, 2024-03-30 12:00 local
+        let source = SourceSimulated::new(Utc.with_ymd_and_hms(2024, 3, 30, 11, 0, 0).unwrap());
+        let clock = Clock::new(Configuration { timezone: Warsaw }, &source);
+
+        // 02:30 does not exist on DST change day, first existing instant is used
+        assert_eq!(
+            Device::boost_next(&boost, &clock),
+            Warsaw.with_ymd_and_hms(2024, 3, 31, 3, 0, 0).unwrap()
+        );
+
+        // right after boost start, next one is week later
+        source.set(Utc.with_ymd_and_hms(2024, 4, 7, 0, 30, 0).unwrap());
+        assert_eq!(
+            Device::boost_next(&boost, &clock),
+            Warsaw.with_ymd_and_hms(2024, 4, 14, 2, 30, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    datatypes::temperature::Temperature,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // protection is activated below threshold and released when temperature
+    // rises above threshold + hysteresis (in kelvins)
+    pub threshold: Temperature,
+    pub hysteresis: f64,
+}
+
+// safety layer under regular heating logic
+// heating demand is passed through, but forced on when temperature drops below
+// threshold. missing temperature is treated as freezing, so broken sensor
+// keeps heating on instead of letting installation freeze
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    active: RwLock<bool>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_temperature: signal::state_target_last::Signal<Temperature>,
+    signal_demand: signal::state_target_last::Signal<bool>,
+    signal_output: signal::state_source::Signal<bool>,
+    signal_active: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.hysteresis >= 0.0);
+
+        Self {
+            configuration,
+
+            // no temperature yet
+            active: RwLock::new(true),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_temperature: signal::state_target_last::Signal::<Temperature>::new(),
+            signal_demand: signal::state_target_last::Signal::<bool>::new(),
+            signal_output: signal::state_source::Signal::<bool>::new(Some(true)),
+            signal_active: signal::state_source::Signal::<bool>::new(Some(true)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn active_calculate(
+        &self,
+        active: bool,
+        temperature: Option<Temperature>,
+    ) -> bool {
+        let temperature = match temperature {
+            Some(temperature) => temperature.to_kelvins(),
+            None => return true,
+        };
+        let threshold = self.configuration.threshold.to_kelvins();
+
+        if active {
+            temperature <= threshold + self.configuration.hysteresis
+        } else {
+            temperature < threshold
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        let temperature = self.signal_temperature.take_last().value;
+        let demand = self.signal_demand.take_last().value;
+
+        let mut active_lock = self.active.write();
+        let active = self.active_calculate(*active_lock, temperature);
+        let active_changed = *active_lock != active;
+        *active_lock = active;
+        drop(active_lock);
+
+        let output = active || demand.unwrap_or(false);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_output.set_one(Some(output));
+        signals_sources_changed |= self.signal_active.set_one(Some(active));
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+
+        if active_changed {
+            if active {
+                log::warn!("frost protection activated, temperature: {:?}", temperature);
+            } else {
+                log::info!("frost protection released");
+            }
+            self.gui_summary_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/climate/frost_guard_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Temperature,
+    Demand,
+    Output,
+    Active,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Temperature => &self.signal_temperature as &dyn signal::Base,
+            SignalIdentifier::Demand => &self.signal_demand as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+            SignalIdentifier::Active => &self.signal_active as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    threshold: Temperature,
+    active: bool,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let threshold = self.configuration.threshold;
+        let active = *self.active.read();
+
+        Self::Value { threshold, active }
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{
+    datatypes::ratio::Ratio,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Baseline {
+    // fan is started above fixed humidity
+    Absolute(Ratio),
+    // fan is started above reference room humidity + offset
+    Reference { offset: f64 },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub baseline: Baseline,
+    // fan is stopped when humidity drops hysteresis below baseline
+    pub hysteresis: f64,
+
+    pub run_min: Duration,
+    // fan is stopped after run_max even if humidity is still high, and won't
+    // be started again until humidity drops
+    pub run_max: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    humidity: Option<Ratio>,
+    threshold: Option<f64>,
+    demand: bool,
+    running_since: Option<Instant>,
+    lockout: bool,
+}
+
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    state: RwLock<State>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_humidity: signal::state_target_last::Signal<Ratio>,
+    signal_reference: signal::state_target_last::Signal<Ratio>,
+    signal_output: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.hysteresis >= 0.0);
+        assert!(configuration.run_min <= configuration.run_max);
+
+        Self {
+            configuration,
+
+            state: RwLock::new(State {
+                humidity: None,
+                threshold: None,
+                demand: false,
+                running_since: None,
+                lockout: false,
+            }),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_humidity: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_reference: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_output: signal::state_source::Signal::<bool>::new(Some(false)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // returns time at which state should be recalculated
+    fn calculate(
+        &self,
+        now: Instant,
+    ) -> Option<Instant> {
+        let humidity = self.signal_humidity.take_last().value;
+        let reference = self.signal_reference.take_last().value;
+
+        let threshold = match self.configuration.baseline {
+            Baseline::Absolute(threshold) => Some(threshold.to_f64()),
+            Baseline::Reference { offset } => {
+                reference.map(|reference| reference.to_f64() + offset)
+            }
+        };
+
+        let mut state = self.state.write();
+
+        state.humidity = humidity;
+        state.threshold = threshold;
+        state.demand = match (humidity, threshold) {
+            (Some(humidity), Some(threshold)) => {
+                if state.demand {
+                    humidity.to_f64() > threshold - self.configuration.hysteresis
+                } else {
+                    humidity.to_f64() > threshold
+                }
+            }
+            _ => false,
+        };
+        if !state.demand {
+            state.lockout = false;
+        }
+
+        let mut wake_at = None;
+        match state.running_since {
+            None => {
+                if state.demand && !state.lockout {
+                    state.running_since = Some(now);
+                    wake_at = Some(now + self.configuration.run_max);
+                }
+            }
+            Some(running_since) => {
+                let running = now.duration_since(running_since);
+                if running >= self.configuration.run_max {
+                    log::warn!("humidity still high after maximum fan run time");
+                    state.running_since = None;
+                    state.lockout = true;
+                } else if !state.demand && running >= self.configuration.run_min {
+                    state.running_since = None;
+                } else if !state.demand {
+                    wake_at = Some(running_since + self.configuration.run_min);
+                } else {
+                    wake_at = Some(running_since + self.configuration.run_max);
+                }
+            }
+        }
+        let output = state.running_since.is_some();
+        drop(state);
+
+        if self.signal_output.set_one(Some(output)) {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+
+        wake_at
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let now = Instant::now();
+            let wake_at = self.calculate(now);
+
+            let wake = match wake_at {
+                Some(wake_at) => {
+                    timer_wheel::sleep(wake_at.saturating_duration_since(now)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/climate/humidistat_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Humidity,
+    Reference,
+    Output,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Humidity => &self.signal_humidity as &dyn signal::Base,
+            SignalIdentifier::Reference => &self.signal_reference as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    humidity: Option<Ratio>,
+    threshold: Option<f64>,
+    running: bool,
+    lockout: bool,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        Self::Value {
+            humidity: state.humidity,
+            threshold: state.threshold,
+            running: state.running_since.is_some(),
+            lockout: state.lockout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Baseline, Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::ratio::Ratio,
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn run_timers() {
+        let ratio = |value: f64| Some(Ratio::from_f64(value).unwrap());
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+
+        let humidity = mock::StateSource::<Ratio>::new(ratio(0.5));
+        let humidistat = Device::new(Configuration {
+            baseline: Baseline::Absolute(Ratio::from_f64(0.7).unwrap()),
+            hysteresis: 0.05,
+            run_min: minutes(5),
+            run_max: minutes(30),
+        });
+        let output = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let humidity_handle = simulation.device_add(&humidity);
+        let humidistat_handle = simulation.device_add(&humidistat);
+        let output_handle = simulation.device_add(&output);
+        simulation.signals().d2d(
+            humidity_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            humidistat_handle,
+            SignalIdentifier::Humidity,
+        );
+        simulation.signals().d2d(
+            humidistat_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                // short peak, kept running for run_min
+                tokio::time::sleep(minutes(1)).await;
+                humidity.set(ratio(0.8));
+                tokio::time::sleep(minutes(1)).await;
+                humidity.set(ratio(0.6));
+
+                // stopped after run_max, locked until humidity drops
+                tokio::time::sleep(minutes(8)).await;
+                humidity.set(ratio(0.9));
+                tokio::time::sleep(minutes(35)).await;
+                humidity.set(ratio(0.6));
+                tokio::time::sleep(minutes(5)).await;
+                humidity.set(ratio(0.9));
+
+                tokio::time::sleep(minutes(2)).await;
+            })
+            .await
+            .unwrap();
+
+        output.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (minutes(1), Some(true)),
+            (minutes(6), Some(false)),
+            (minutes(10), Some(true)),
+            (minutes(40), Some(false)),
+            (minutes(50), Some(true)),
+        ]);
+    }
+}
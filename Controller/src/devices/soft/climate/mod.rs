@@ -0,0 +1,5 @@
+pub mod dhw_a;
+pub mod frost_guard_a;
+pub mod humidistat_a;
+pub mod pump_control_a;
+pub mod window_detect_a;
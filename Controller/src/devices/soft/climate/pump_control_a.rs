@@ -0,0 +1,409 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, iter, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub demands_count: usize,
+
+    // pump keeps running after last demand is gone, to move heat out of the
+    // boiler / pipes
+    pub overrun: Duration,
+
+    // if pump was not running for exercise_interval, it is started for
+    // exercise_duration to prevent seizing
+    pub exercise_interval: Duration,
+    pub exercise_duration: Duration,
+}
+
+#[derive(Debug)]
+struct RunTime {
+    total: Duration,
+    since: Option<Instant>,
+}
+impl RunTime {
+    fn current(
+        &self,
+        now: Instant,
+    ) -> Duration {
+        self.total
+            + self
+                .since
+                .map_or(Duration::ZERO, |since| now.duration_since(since))
+    }
+}
+
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    run_time: RwLock<RunTime>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_demands: Box<[signal::state_target_last::Signal<bool>]>,
+    signal_output: signal::state_source::Signal<bool>,
+    signal_run_time: signal::state_source::Signal<Duration>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.exercise_interval > Duration::ZERO);
+
+        Self {
+            configuration,
+
+            run_time: RwLock::new(RunTime {
+                total: Duration::ZERO,
+                since: None,
+            }),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_demands: (0..configuration.demands_count)
+                .map(|_demand_index| signal::state_target_last::Signal::<bool>::new())
+                .collect::<Box<[_]>>(),
+            signal_output: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_run_time: signal::state_source::Signal::<Duration>::new(Some(Duration::ZERO)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn demand(&self) -> bool {
+        let mut demand = false;
+
+        // take_last for all inputs, so none of them stays pending
+        for signal_demand in self.signal_demands.iter() {
+            if signal_demand.take_last().value == Some(true) {
+                demand = true;
+            }
+        }
+
+        demand
+    }
+
+    fn output_set(
+        &self,
+        now: Instant,
+        output: bool,
+    ) {
+        let mut signals_sources_changed = false;
+
+        if self.signal_output.set_one(Some(output)) {
+            signals_sources_changed = true;
+
+            let mut run_time = self.run_time.write();
+            if output {
+                run_time.since = Some(now);
+            } else {
+                run_time.total = run_time.current(now);
+                run_time.since = None;
+
+                // published after each run, gui summary shows live value
+                signals_sources_changed |= self.signal_run_time.set_one(Some(run_time.total));
+            }
+            drop(run_time);
+
+            self.gui_summary_waker.wake();
+        }
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    fn run_time_reset(&self) {
+        let now = Instant::now();
+
+        let mut run_time = self.run_time.write();
+        run_time.total = Duration::ZERO;
+        if run_time.since.is_some() {
+            run_time.since = Some(now);
+        }
+        drop(run_time);
+
+        if self.signal_run_time.set_one(Some(Duration::ZERO)) {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut demand_last = false;
+        let mut overrun_until = None::<Instant>;
+        let mut exercise_until = None::<Instant>;
+        let mut output_last = false;
+        let mut running_last = Instant::now();
+
+        loop {
+            let now = Instant::now();
+
+            let demand = self.demand();
+            if demand {
+                overrun_until = None;
+                exercise_until = None;
+            } else if demand_last {
+                overrun_until = Some(now + self.configuration.overrun);
+            }
+            demand_last = demand;
+
+            if overrun_until.is_some_and(|overrun_until| overrun_until <= now) {
+                overrun_until = None;
+            }
+            if exercise_until.is_some_and(|exercise_until| exercise_until <= now) {
+                exercise_until = None;
+            }
+
+            let exercise_at = running_last + self.configuration.exercise_interval;
+            if !demand && overrun_until.is_none() && exercise_until.is_none() && exercise_at <= now
+            {
+                log::info!("pump exercise run");
+                exercise_until = Some(now + self.configuration.exercise_duration);
+            }
+
+            let output = demand || overrun_until.is_some() || exercise_until.is_some();
+            // exercise interval is counted from the moment pump stops
+            if output || output_last {
+                running_last = now;
+            }
+            output_last = output;
+            self.output_set(now, output);
+
+            // nothing to wait for while demand is active
+            let wake_at = if demand {
+                None
+            } else {
+                overrun_until
+                    .or(exercise_until)
+                    .or(Some(running_last + self.configuration.exercise_interval))
+            };
+            let wake = match wake_at {
+                Some(wake_at) => {
+                    timer_wheel::sleep(wake_at.saturating_duration_since(now)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/climate/pump_control_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Demand(usize),
+    Output,
+    RunTime,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_demands
+                    .iter()
+                    .enumerate()
+                    .map(|(demand_index, signal_demand)| {
+                        (
+                            SignalIdentifier::Demand(demand_index),
+                            signal_demand as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain([
+                (
+                    SignalIdentifier::Output,
+                    &self.signal_output as &dyn signal::Base,
+                ),
+                (
+                    SignalIdentifier::RunTime,
+                    &self.signal_run_time as &dyn signal::Base,
+                ),
+            ])
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    running: bool,
+    run_time: Duration,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let run_time = self.run_time.read();
+        let running = run_time.since.is_some();
+        let run_time = run_time.current(Instant::now());
+
+        Self::Value { running, run_time }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("run-time-reset", uri_cursor) => {
+                match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::POST => {
+                            self.run_time_reset();
+                            async { web::Response::ok_empty() }.boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                }
+            }
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::simulation::{mock, Simulation};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn overrun_and_exercise() {
+        let demand = mock::StateSource::<bool>::new(None);
+        let pump_control = Device::new(Configuration {
+            demands_count: 2,
+            overrun: Duration::from_secs(60),
+            exercise_interval: Duration::from_secs(60 * 60),
+            exercise_duration: Duration::from_secs(60),
+        });
+        let output = mock::StateRecorder::<bool>::new();
+        let run_time = mock::StateRecorder::<Duration>::new();
+
+        let mut simulation = Simulation::new();
+        let demand_handle = simulation.device_add(&demand);
+        let pump_control_handle = simulation.device_add(&pump_control);
+        let output_handle = simulation.device_add(&output);
+        let run_time_handle = simulation.device_add(&run_time);
+        simulation.signals().d2d(
+            demand_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            pump_control_handle,
+            SignalIdentifier::Demand(1),
+        );
+        simulation.signals().d2d(
+            pump_control_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            pump_control_handle,
+            SignalIdentifier::RunTime,
+            run_time_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+
+        simulation
+            .run(async {
+                tokio::time::sleep(minutes(10)).await;
+                demand.set(Some(true));
+                tokio::time::sleep(minutes(10)).await;
+                demand.set(Some(false));
+
+                // overrun, then exercise hour after last run
+                tokio::time::sleep(minutes(70)).await;
+            })
+            .await
+            .unwrap();
+
+        output.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (minutes(10), Some(true)),
+            (minutes(21), Some(false)),
+            (minutes(81), Some(true)),
+            (minutes(82), Some(false)),
+        ]);
+        run_time.trace().assert_entries(&[
+            (Duration::ZERO, Some(Duration::ZERO)),
+            (minutes(21), Some(minutes(11))),
+            (minutes(82), Some(minutes(12))),
+        ]);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{
+    datatypes::temperature::Temperature,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // window is considered open if temperature drops by at least
+    // detection_drop kelvins within detection_window
+    pub detection_window: Duration,
+    pub detection_drop: f64,
+
+    // window is considered closed if temperature rises by recovery_rise kelvins
+    // from the lowest value seen while open or after recovery_timeout, whatever
+    // comes first
+    pub recovery_rise: f64,
+    pub recovery_timeout: Duration,
+}
+
+#[derive(Debug)]
+struct Detected {
+    since: Instant,
+    temperature_min: Temperature,
+}
+
+#[derive(Debug)]
+struct State {
+    // samples within detection window, oldest first
+    temperatures: VecDeque<(Instant, Temperature)>,
+    detected: Option<Detected>,
+}
+
+// outputs true if window is open, either detected from temperature drop or
+// reported by contact, so thermostats can be paused
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_temperature: signal::state_target_last::Signal<Temperature>,
+    signal_contact: signal::state_target_last::Signal<bool>,
+    signal_output: signal::state_source::Signal<bool>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.detection_drop > 0.0);
+        assert!(configuration.recovery_rise > 0.0);
+
+        Self {
+            configuration,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_temperature: signal::state_target_last::Signal::<Temperature>::new(),
+            signal_contact: signal::state_target_last::Signal::<bool>::new(),
+            signal_output: signal::state_source::Signal::<bool>::new(Some(false)),
+        }
+    }
+
+    fn temperature_process(
+        &self,
+        state: &mut State,
+        now: Instant,
+        temperature: Temperature,
+    ) {
+        if let Some(detected) = &mut state.detected {
+            if detected.temperature_min > temperature {
+                detected.temperature_min = temperature;
+            }
+
+            if temperature.to_kelvins() - detected.temperature_min.to_kelvins()
+                >= self.configuration.recovery_rise
+            {
+                state.detected = None;
+                state.temperatures.clear();
+            }
+        }
+
+        state.temperatures.push_back((now, temperature));
+        while let Some((time, _)) = state.temperatures.front() {
+            if now.duration_since(*time) <= self.configuration.detection_window {
+                break;
+            }
+            state.temperatures.pop_front();
+        }
+
+        if state.detected.is_none() {
+            let temperature_max = state
+                .temperatures
+                .iter()
+                .map(|(_, temperature)| *temperature)
+                .max()
+                .unwrap();
+
+            if temperature_max.to_kelvins() - temperature.to_kelvins()
+                >= self.configuration.detection_drop
+            {
+                state.detected = Some(Detected {
+                    since: now,
+                    temperature_min: temperature,
+                });
+            }
+        }
+    }
+
+    fn signals_targets_changed(
+        &self,
+        state: &mut State,
+        now: Instant,
+    ) {
+        match self.signal_temperature.take_pending() {
+            Some(Some(temperature)) => self.temperature_process(state, now, temperature),
+            // history is not reliable across missing values
+            Some(None) => state.temperatures.clear(),
+            None => {}
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut state = State {
+            temperatures: VecDeque::new(),
+            detected: None,
+        };
+
+        loop {
+            let now = Instant::now();
+
+            // recovery timeout
+            if state.detected.as_ref().is_some_and(|detected| {
+                now.duration_since(detected.since) >= self.configuration.recovery_timeout
+            }) {
+                state.detected = None;
+                state.temperatures.clear();
+            }
+
+            let contact = self.signal_contact.peek_last().unwrap_or(false);
+            let output = contact || state.detected.is_some();
+            if self.signal_output.set_one(Some(output)) {
+                self.signals_sources_changed_waker.wake();
+            }
+
+            let recovery_timeout = match &state.detected {
+                Some(detected) => timer_wheel::sleep(
+                    (detected.since + self.configuration.recovery_timeout)
+                        .saturating_duration_since(now),
+                )
+                .left_future(),
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {
+                    self.signals_targets_changed(&mut state, Instant::now());
+                },
+                () = recovery_timeout.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/climate/window_detect_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Temperature,
+    Contact,
+    Output,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Temperature => &self.signal_temperature as &dyn signal::Base,
+            SignalIdentifier::Contact => &self.signal_contact as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::temperature::{Temperature, Unit},
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    fn celsius(value: f64) -> Option<Temperature> {
+        Some(Temperature::from_unit(Unit::Celsius, value).unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn detects_and_recovers() {
+        let temperature = mock::StateSource::<Temperature>::new(celsius(21.0));
+        let contact = mock::StateSource::<bool>::new(None);
+        let window_detect = Device::new(Configuration {
+            detection_window: Duration::from_secs(5 * 60),
+            detection_drop: 0.5,
+            recovery_rise: 0.5,
+            recovery_timeout: Duration::from_secs(10 * 60),
+        });
+        let output = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let temperature_handle = simulation.device_add(&temperature);
+        let contact_handle = simulation.device_add(&contact);
+        let window_detect_handle = simulation.device_add(&window_detect);
+        let output_handle = simulation.device_add(&output);
+        simulation.signals().d2d(
+            temperature_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            window_detect_handle,
+            SignalIdentifier::Temperature,
+        );
+        simulation.signals().d2d(
+            contact_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            window_detect_handle,
+            SignalIdentifier::Contact,
+        );
+        simulation.signals().d2d(
+            window_detect_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+
+        simulation
+            .run(async {
+                // slow drop is ignored, fast drop is detected
+                tokio::time::sleep(minutes(1)).await;
+                temperature.set(celsius(20.8));
+                tokio::time::sleep(minutes(1)).await;
+                temperature.set(celsius(20.0));
+
+                // recovered by temperature rise
+                tokio::time::sleep(minutes(3)).await;
+                temperature.set(celsius(20.2));
+                tokio::time::sleep(minutes(2)).await;
+                temperature.set(celsius(20.6));
+
+                // contact
+                tokio::time::sleep(minutes(3)).await;
+                contact.set(Some(true));
+                tokio::time::sleep(minutes(1)).await;
+                contact.set(Some(false));
+
+                // recovered by timeout
+                tokio::time::sleep(minutes(1)).await;
+                temperature.set(celsius(19.5));
+                tokio::time::sleep(minutes(15)).await;
+            })
+            .await
+            .unwrap();
+
+        output.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (minutes(2), Some(true)),
+            (minutes(7), Some(false)),
+            (minutes(10), Some(true)),
+            (minutes(11), Some(false)),
+            (minutes(12), Some(true)),
+            (minutes(22), Some(false)),
+        ]);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::{
+    datatypes::{ratio::Ratio, timestamped::Timestamped},
+    devices,
+    modules::clock::Clock,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use std::{borrow::Cow, iter, time::Duration};
+
+#[derive(Debug)]
+pub struct InputConfiguration {
+    // battery powered sensors report periodically, missing this many reports
+    // in a row makes sensor stale
+    pub report_interval_expected: Duration,
+    pub reports_missed_max: u32,
+}
+impl InputConfiguration {
+    pub fn age_max(&self) -> Duration {
+        self.report_interval_expected * self.reports_missed_max
+    }
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    // one per sensor, in order of inputs
+    pub inputs: Box<[InputConfiguration]>,
+    pub level_low: Ratio,
+}
+
+#[derive(PartialEq, Debug)]
+struct Summary {
+    low: bool,
+    stale: bool,
+    level_lowest: Option<Ratio>,
+
+    // time left until first fresh input gets stale
+    fresh_for: Option<Duration>,
+}
+
+// aggregates battery levels of wireless sensors (zigbee, ble, etc.), to get
+// single "replace batteries" and "sensor lost" indication
+// sensor readings should go through soft/value/staleness_a with the same age
+// max, so stale readings never silently drive logic
+#[derive(Debug)]
+pub struct Device<'c> {
+    configuration: Configuration,
+    clock: &'c Clock<'c>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_inputs: Box<[signal::state_target_last::Signal<Timestamped<Ratio>>]>,
+    signal_low: signal::state_source::Signal<bool>,
+    signal_stale: signal::state_source::Signal<bool>,
+    signal_level_lowest: signal::state_source::Signal<Ratio>,
+}
+impl<'c> Device<'c> {
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+    ) -> Self {
+        let inputs_count = configuration.inputs.len();
+
+        Self {
+            configuration,
+            clock,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_inputs: (0..inputs_count)
+                .map(|_input_index| signal::state_target_last::Signal::<Timestamped<Ratio>>::new())
+                .collect::<Box<[_]>>(),
+            signal_low: signal::state_source::Signal::<bool>::new(None),
+            signal_stale: signal::state_source::Signal::<bool>::new(None),
+            signal_level_lowest: signal::state_source::Signal::<Ratio>::new(None),
+        }
+    }
+
+    // missing inputs are treated as stale, as sensor never reported since
+    // startup
+    fn summarize(
+        configuration: &Configuration,
+        inputs: &[Option<Timestamped<Ratio>>],
+        now: DateTime<Utc>,
+    ) -> Summary {
+        let mut summary = Summary {
+            low: false,
+            stale: false,
+            level_lowest: None,
+            fresh_for: None,
+        };
+
+        for (input_configuration, input) in configuration.inputs.iter().zip(inputs) {
+            let input = match input {
+                Some(input) => input,
+                None => {
+                    summary.stale = true;
+                    continue;
+                }
+            };
+
+            let age_max = input_configuration.age_max();
+            let age = input.age(now);
+            if age > age_max {
+                summary.stale = true;
+                continue;
+            }
+
+            let fresh_for = age_max - age;
+            summary.fresh_for = Some(match summary.fresh_for {
+                Some(fresh_for_summary) => fresh_for_summary.min(fresh_for),
+                None => fresh_for,
+            });
+
+            summary.low |= input.value < configuration.level_low;
+            summary.level_lowest = Some(match summary.level_lowest {
+                Some(level_lowest) => level_lowest.min(input.value),
+                None => input.value,
+            });
+        }
+
+        summary
+    }
+
+    fn update(&self) -> Option<Duration> {
+        let inputs = self
+            .signal_inputs
+            .iter()
+            .map(|signal_input| signal_input.take_last().value)
+            .collect::<Box<[_]>>();
+
+        let summary = Self::summarize(&self.configuration, &inputs, self.clock.now());
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_low.set_one(Some(summary.low));
+        signals_sources_changed |= self.signal_stale.set_one(Some(summary.stale));
+        signals_sources_changed |= self.signal_level_lowest.set_one(summary.level_lowest);
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+
+        summary.fresh_for
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let wake = match self.update() {
+                // value equal to age max is still fresh
+                Some(fresh_for) => {
+                    timer_wheel::sleep(fresh_for + Duration::from_millis(1)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'c> devices::Device for Device<'c> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/diagnostics/battery_status_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'c> Runnable for Device<'c> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+    Low,
+    Stale,
+    LevelLowest,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c> signals::Device for Device<'c> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input_index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(input_index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain([
+                (SignalIdentifier::Low, &self.signal_low as &dyn signal::Base),
+                (
+                    SignalIdentifier::Stale,
+                    &self.signal_stale as &dyn signal::Base,
+                ),
+                (
+                    SignalIdentifier::LevelLowest,
+                    &self.signal_level_lowest as &dyn signal::Base,
+                ),
+            ])
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, InputConfiguration};
+    use crate::datatypes::{ratio::Ratio, timestamped::Timestamped};
+    use chrono::{TimeDelta, TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn summarize() {
+        let configuration = Configuration {
+            inputs: [Duration::from_secs(60), Duration::from_secs(3600)]
+                .into_iter()
+                .map(|report_interval_expected| InputConfiguration {
+                    report_interval_expected,
+                    reports_missed_max: 2,
+                })
+                .collect(),
+            level_low: Ratio::from_f64(0.2).unwrap(),
+        };
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let level = |value: f64, age: i64| {
+            Some(Timestamped::new(
+                Ratio::from_f64(value).unwrap(),
+                now - TimeDelta::seconds(age),
+            ))
+        };
+
+        // never reported
+        let summary = Device::summarize(&configuration, &[level(0.5, 0), None], now);
+        assert!(summary.stale);
+        assert!(!summary.low);
+        assert_eq!(summary.level_lowest, Some(Ratio::from_f64(0.5).unwrap()));
+        assert_eq!(summary.fresh_for, Some(Duration::from_secs(120)));
+
+        // all fresh, second one low
+        let summary = Device::summarize(&configuration, &[level(0.5, 30), level(0.1, 600)], now);
+        assert!(!summary.stale);
+        assert!(summary.low);
+        assert_eq!(summary.level_lowest, Some(Ratio::from_f64(0.1).unwrap()));
+        assert_eq!(summary.fresh_for, Some(Duration::from_secs(90)));
+
+        // first one missed its reports, its level is not considered
+        let summary = Device::summarize(&configuration, &[level(0.1, 121), level(0.5, 600)], now);
+        assert!(summary.stale);
+        assert!(!summary.low);
+        assert_eq!(summary.level_lowest, Some(Ratio::from_f64(0.5).unwrap()));
+        assert_eq!(summary.fresh_for, Some(Duration::from_secs(6600)));
+    }
+}
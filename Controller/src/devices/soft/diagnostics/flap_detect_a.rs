@@ -0,0 +1,324 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use async_trait::async_trait;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // alert is raised when input changes more than changes_max times within
+    // window
+    pub changes_max: usize,
+    pub window: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    value_last: Option<bool>,
+    // times of recent changes, oldest first
+    changes: VecDeque<Instant>,
+    alert: bool,
+}
+
+// detects sticking relays and failing sensors
+// alert is latched until acknowledged
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    state: RwLock<State>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<bool>,
+    signal_acknowledge: signal::event_target_last::Signal<()>,
+    signal_alert: signal::state_source::Signal<bool>,
+    signal_alerted: signal::event_source::Signal<()>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        Self {
+            configuration,
+
+            state: RwLock::new(State {
+                value_last: None,
+                changes: VecDeque::new(),
+                alert: false,
+            }),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<bool>::new(),
+            signal_acknowledge: signal::event_target_last::Signal::<()>::new(),
+            signal_alert: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_alerted: signal::event_source::Signal::<()>::new(),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn acknowledge(&self) {
+        let mut state = self.state.write();
+        state.alert = false;
+        state.changes.clear();
+        drop(state);
+
+        if self.signal_alert.set_one(Some(false)) {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    fn signals_targets_changed(&self) {
+        if self.signal_acknowledge.take_pending().is_some() {
+            self.acknowledge();
+        }
+
+        // missing values are not counted as changes
+        let value = match self.signal_input.take_pending() {
+            Some(Some(value)) => value,
+            _ => return,
+        };
+
+        let now = Instant::now();
+        let mut state = self.state.write();
+
+        let changed = state
+            .value_last
+            .is_some_and(|value_last| value_last != value);
+        state.value_last = Some(value);
+        if !changed {
+            return;
+        }
+
+        state.changes.push_back(now);
+        while let Some(change) = state.changes.front() {
+            if now.duration_since(*change) <= self.configuration.window {
+                break;
+            }
+            state.changes.pop_front();
+        }
+
+        let mut alerted = false;
+        if !state.alert && state.changes.len() > self.configuration.changes_max {
+            state.alert = true;
+            alerted = true;
+        }
+        drop(state);
+
+        if alerted {
+            log::warn!("input flapping detected");
+
+            let mut signals_sources_changed = false;
+            signals_sources_changed |= self.signal_alert.set_one(Some(true));
+            signals_sources_changed |= self.signal_alerted.push_one(());
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+            }
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/diagnostics/flap_detect_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Acknowledge,
+    Alert,
+    Alerted,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Acknowledge => &self.signal_acknowledge as &dyn signal::Base,
+            SignalIdentifier::Alert => &self.signal_alert as &dyn signal::Base,
+            SignalIdentifier::Alerted => &self.signal_alerted as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    alert: bool,
+    changes: usize,
+    changes_max: usize,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        Self::Value {
+            alert: state.alert,
+            changes: state.changes.len(),
+            changes_max: self.configuration.changes_max,
+        }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("acknowledge", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        self.acknowledge();
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::simulation::{mock, Simulation};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn latched_until_acknowledged() {
+        let input = mock::StateSource::<bool>::new(Some(false));
+        let acknowledge = mock::EventSource::<()>::new();
+        let flap_detect = Device::new(Configuration {
+            changes_max: 3,
+            window: Duration::from_secs(10),
+        });
+        let alert = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let acknowledge_handle = simulation.device_add(&acknowledge);
+        let flap_detect_handle = simulation.device_add(&flap_detect);
+        let alert_handle = simulation.device_add(&alert);
+        simulation.signals().d2d(
+            input_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            flap_detect_handle,
+            SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            acknowledge_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            flap_detect_handle,
+            SignalIdentifier::Acknowledge,
+        );
+        simulation.signals().d2d(
+            flap_detect_handle,
+            SignalIdentifier::Alert,
+            alert_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                // slow changes are fine
+                for value in [true, false, true, false] {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    input.set(Some(value));
+                }
+
+                // fast changes raise alert, kept after input settles
+                for value in [true, false, true, false] {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    input.set(Some(value));
+                }
+
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                acknowledge.push(());
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        alert.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(22), Some(true)),
+            (Duration::from_secs(34), Some(false)),
+        ]);
+    }
+}
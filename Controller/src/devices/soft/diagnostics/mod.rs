@@ -0,0 +1,3 @@
+pub mod battery_status_a;
+pub mod flap_detect_a;
+pub mod runtime_counter_a;
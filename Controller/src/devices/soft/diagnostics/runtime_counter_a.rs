@@ -0,0 +1,399 @@
+use crate::{
+    devices,
+    modules::{fs::Fs, sqlite::SQLite},
+    signals::{self, signal},
+    util::{
+        async_flag, async_waker,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use indoc::indoc;
+use maplit::hashmap;
+use parking_lot::RwLock;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::{borrow::Cow, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across counters
+    pub name: String,
+    // maintenance due event is raised once service time reaches this value
+    pub maintenance_interval: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    // lifetime on-time, never reset
+    total: Duration,
+    // on-time since last maintenance
+    service: Duration,
+    due: bool,
+
+    // last accumulation point, if input is on
+    running_since: Option<Instant>,
+}
+
+// accumulates on-time of a boolean input (pump, fan, compressor)
+// totals are persisted, so they survive restarts
+#[derive(Debug)]
+pub struct Device<'f> {
+    configuration: Configuration,
+
+    sqlite: SQLite<'f>,
+
+    state: RwLock<State>,
+    state_changed_waker: async_waker::mpsc::Signal,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<bool>,
+    signal_reset: signal::event_target_last::Signal<()>,
+    signal_total: signal::state_source::Signal<Duration>,
+    signal_service: signal::state_source::Signal<Duration>,
+    signal_due: signal::state_source::Signal<bool>,
+    signal_maintenance_due: signal::event_source::Signal<()>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'f> Device<'f> {
+    // while running, totals are published and persisted at this pace
+    const INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        configuration: Configuration,
+        fs: &'f Fs,
+    ) -> Self {
+        let sqlite = SQLite::new(format!("runtime_counter.{}", configuration.name), fs);
+
+        Self {
+            configuration,
+
+            sqlite,
+
+            state: RwLock::new(State {
+                total: Duration::ZERO,
+                service: Duration::ZERO,
+                due: false,
+                running_since: None,
+            }),
+            state_changed_waker: async_waker::mpsc::Signal::new(),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<bool>::new(),
+            signal_reset: signal::event_target_last::Signal::<()>::new(),
+            signal_total: signal::state_source::Signal::<Duration>::new(None),
+            signal_service: signal::state_source::Signal::<Duration>::new(None),
+            signal_due: signal::state_source::Signal::<bool>::new(None),
+            signal_maintenance_due: signal::event_source::Signal::<()>::new(),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn accumulate(
+        state: &mut State,
+        now: Instant,
+    ) {
+        if let Some(running_since) = state.running_since {
+            let duration = now.duration_since(running_since);
+            state.total += duration;
+            state.service += duration;
+            state.running_since = Some(now);
+        }
+    }
+
+    fn reset(&self) {
+        let mut state = self.state.write();
+        Self::accumulate(&mut state, Instant::now());
+        state.service = Duration::ZERO;
+        state.due = false;
+        drop(state);
+
+        log::info!("{}: service counter reset", self.configuration.name);
+        self.state_changed_waker.wake();
+    }
+
+    async fn load(&self) -> Result<(), Error> {
+        let counters = self
+            .sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                transaction
+                    .execute_batch(indoc!(
+                        "
+                        CREATE TABLE IF NOT EXISTS `counters` (
+                            `id` INTEGER PRIMARY KEY NOT NULL,
+                            `total` REAL NOT NULL, -- seconds
+                            `service` REAL NOT NULL -- seconds
+                        ) STRICT;
+                    "
+                    ))
+                    .context("initialize")?;
+
+                let counters = transaction
+                    .query_row(
+                        "SELECT `total`, `service` FROM `counters` WHERE `id` = 0",
+                        [],
+                        |row| -> rusqlite::Result<(f64, f64)> { Ok((row.get(0)?, row.get(1)?)) },
+                    )
+                    .optional()
+                    .context("query_row")?;
+
+                Ok(counters)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        if let Some((total, service)) = counters {
+            let mut state = self.state.write();
+            state.total = Duration::from_secs_f64(total);
+            state.service = Duration::from_secs_f64(service);
+            // event was raised before restart
+            state.due = state.service >= self.configuration.maintenance_interval;
+        }
+
+        Ok(())
+    }
+    async fn persist(
+        &self,
+        total: Duration,
+        service: Duration,
+    ) -> Result<(), Error> {
+        self.sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                transaction
+                    .execute(
+                        indoc!(
+                            "
+                            INSERT OR REPLACE INTO
+                                `counters` (`id`, `total`, `service`)
+                            VALUES
+                                (0, ?, ?)
+                        "
+                        ),
+                        (total.as_secs_f64(), service.as_secs_f64()),
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(())
+    }
+
+    // returns whether counters should be persisted
+    fn update(&self) -> bool {
+        if self.signal_reset.take_pending().is_some() {
+            self.reset();
+        }
+        let input = self.signal_input.take_last().value.unwrap_or(false);
+
+        let now = Instant::now();
+        let mut state = self.state.write();
+        let running = state.running_since.is_some();
+        Self::accumulate(&mut state, now);
+        state.running_since = input.then_some(now);
+
+        let mut maintenance_due = false;
+        if !state.due && state.service >= self.configuration.maintenance_interval {
+            state.due = true;
+            maintenance_due = true;
+        }
+
+        let (total, service, due) = (state.total, state.service, state.due);
+        drop(state);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_total.set_one(Some(total));
+        signals_sources_changed |= self.signal_service.set_one(Some(service));
+        signals_sources_changed |= self.signal_due.set_one(Some(due));
+        if maintenance_due {
+            log::warn!("{}: maintenance due", self.configuration.name);
+            signals_sources_changed |= self.signal_maintenance_due.push_one(());
+        }
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+            self.gui_summary_waker.wake();
+        }
+
+        running
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        if let Err(error) = self.load().await.context("load") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+        let mut state_changed_receiver = self.state_changed_waker.receiver();
+
+        let mut persist = true;
+        loop {
+            persist |= self.update();
+
+            if persist {
+                let (total, service) = {
+                    let state = self.state.read();
+                    (state.total, state.service)
+                };
+                if let Err(error) = self.persist(total, service).await.context("persist") {
+                    log::error!("{}: {:?}", self.configuration.name, error);
+                }
+                persist = false;
+            }
+
+            let running = self.state.read().running_since.is_some();
+            let tick = if running {
+                timer_wheel::sleep(Self::INTERVAL).left_future()
+            } else {
+                future::pending().right_future()
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = state_changed_receiver.select_next_some() => persist = true,
+                () = tick.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        // store time accumulated since last tick
+        if self.update() {
+            let (total, service) = {
+                let state = self.state.read();
+                (state.total, state.service)
+            };
+            if let Err(error) = self.persist(total, service).await.context("persist") {
+                log::error!("{}: {:?}", self.configuration.name, error);
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'f> devices::Device for Device<'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/diagnostics/runtime_counter_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'f> Runnable for Device<'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Reset,
+    Total,
+    Service,
+    Due,
+    MaintenanceDue,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f> signals::Device for Device<'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Reset => &self.signal_reset as &dyn signal::Base,
+            SignalIdentifier::Total => &self.signal_total as &dyn signal::Base,
+            SignalIdentifier::Service => &self.signal_service as &dyn signal::Base,
+            SignalIdentifier::Due => &self.signal_due as &dyn signal::Base,
+            SignalIdentifier::MaintenanceDue => &self.signal_maintenance_due as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    running: bool,
+    total: Duration,
+    service: Duration,
+    maintenance_interval: Duration,
+    due: bool,
+}
+impl<'f> devices::gui_summary::Device for Device<'f> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        Self::Value {
+            running: state.running_since.is_some(),
+            total: state.total,
+            service: state.service,
+            maintenance_interval: self.configuration.maintenance_interval,
+            due: state.due,
+        }
+    }
+}
+
+impl<'f> uri_cursor::Handler for Device<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("reset", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        self.reset();
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{
+    datatypes::{energy::tariff::Tariff, real::Real},
+    devices,
+    modules::{clock::Clock, fs::Fs, sqlite::SQLite},
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate};
+use futures::{future::FutureExt, select, stream::StreamExt};
+use indoc::indoc;
+use maplit::hashmap;
+use parking_lot::RwLock;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::{borrow::Cow, time::Duration};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across devices
+    pub name: String,
+    // used when price signal has no value, cost is not accumulated if both
+    // are missing
+    pub tariff: Option<Tariff>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Accumulator {
+    // last seen meter reading
+    value_last: Option<f64>,
+    // local date costs are accumulated for
+    date: NaiveDate,
+    cost_daily: f64,
+    cost_monthly: f64,
+}
+impl Accumulator {
+    fn new(date: NaiveDate) -> Self {
+        Self {
+            value_last: None,
+            date,
+            cost_daily: 0.0,
+            cost_monthly: 0.0,
+        }
+    }
+
+    // starts new day (and month) if date changed
+    fn rollover(
+        &mut self,
+        date: NaiveDate,
+    ) {
+        if self.date == date {
+            return;
+        }
+        if (self.date.year(), self.date.month()) != (date.year(), date.month()) {
+            self.cost_monthly = 0.0;
+        }
+        self.cost_daily = 0.0;
+        self.date = date;
+    }
+
+    // decreasing readings (meter replacement, correction) are not consumption
+    fn consume(
+        &mut self,
+        value: f64,
+        price: Option<f64>,
+    ) {
+        if let (Some(value_last), Some(price)) = (self.value_last, price) {
+            let consumption = (value - value_last).max(0.0);
+            let cost = consumption * price;
+            self.cost_daily += cost;
+            self.cost_monthly += cost;
+        }
+        self.value_last = Some(value);
+    }
+}
+
+// accumulates cost of consumption measured by meter reading (energy, volume),
+// eg. total of pulse meter
+// price comes from signal (eg. spot price) or configured tariff
+// daily and monthly costs are reset at local day / month start and persisted
+#[derive(Debug)]
+pub struct Device<'c, 'f> {
+    configuration: Configuration,
+    clock: &'c Clock<'c>,
+
+    sqlite: SQLite<'f>,
+
+    accumulator: RwLock<Accumulator>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<Real>,
+    signal_price: signal::state_target_last::Signal<Real>,
+    signal_price_current: signal::state_source::Signal<Real>,
+    signal_cost_daily: signal::state_source::Signal<Real>,
+    signal_cost_monthly: signal::state_source::Signal<Real>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'c, 'f> Device<'c, 'f> {
+    // day boundaries are checked at this pace
+    const INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+        fs: &'f Fs,
+    ) -> Self {
+        let sqlite = SQLite::new(format!("cost.{}", configuration.name), fs);
+
+        let accumulator = Accumulator::new(clock.now_local().date_naive());
+
+        Self {
+            configuration,
+            clock,
+
+            sqlite,
+
+            accumulator: RwLock::new(accumulator),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<Real>::new(),
+            signal_price: signal::state_target_last::Signal::<Real>::new(),
+            signal_price_current: signal::state_source::Signal::<Real>::new(None),
+            signal_cost_daily: signal::state_source::Signal::<Real>::new(None),
+            signal_cost_monthly: signal::state_source::Signal::<Real>::new(None),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn price_current(&self) -> Option<f64> {
+        if let Some(price) = self.signal_price.peek_last() {
+            return Some(price.to_f64());
+        }
+        self.configuration
+            .tariff
+            .as_ref()
+            .map(|tariff| tariff.price(self.clock.now_local().naive_local()).to_f64())
+    }
+
+    async fn load(&self) -> Result<(), Error> {
+        let accumulator = self
+            .sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                transaction
+                    .execute_batch(indoc!(
+                        "
+                        CREATE TABLE IF NOT EXISTS `accumulator` (
+                            `id` INTEGER PRIMARY KEY NOT NULL,
+                            `value_last` REAL NULL,
+                            `date` TEXT NOT NULL, -- local, YYYY-MM-DD
+                            `cost_daily` REAL NOT NULL,
+                            `cost_monthly` REAL NOT NULL
+                        ) STRICT;
+                    "
+                    ))
+                    .context("initialize")?;
+
+                let accumulator = transaction
+                    .query_row(
+                        indoc!(
+                            "
+                            SELECT
+                                `value_last`, `date`, `cost_daily`, `cost_monthly`
+                            FROM
+                                `accumulator`
+                            WHERE
+                                `id` = 0
+                        "
+                        ),
+                        [],
+                        |row| -> rusqlite::Result<(Option<f64>, String, f64, f64)> {
+                            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                        },
+                    )
+                    .optional()
+                    .context("query_row")?;
+
+                Ok(accumulator)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        if let Some((value_last, date, cost_daily, cost_monthly)) = accumulator {
+            let date = date.parse::<NaiveDate>().context("date")?;
+            *self.accumulator.write() = Accumulator {
+                value_last,
+                date,
+                cost_daily,
+                cost_monthly,
+            };
+        }
+
+        Ok(())
+    }
+    async fn persist(&self) -> Result<(), Error> {
+        let accumulator = *self.accumulator.read();
+
+        self.sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                transaction
+                    .execute(
+                        indoc!(
+                            "
+                            INSERT OR REPLACE INTO
+                                `accumulator` (
+                                    `id`, `value_last`, `date`, `cost_daily`, `cost_monthly`
+                                )
+                            VALUES
+                                (0, ?, ?, ?, ?)
+                        "
+                        ),
+                        (
+                            accumulator.value_last,
+                            accumulator.date.to_string(),
+                            accumulator.cost_daily,
+                            accumulator.cost_monthly,
+                        ),
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(())
+    }
+
+    // returns whether accumulator changed and should be persisted
+    fn update(&self) -> bool {
+        let price = self.price_current();
+        let input = self.signal_input.take_pending().flatten();
+
+        let mut accumulator = self.accumulator.write();
+        let accumulator_previous = *accumulator;
+        accumulator.rollover(self.clock.now_local().date_naive());
+        if let Some(input) = input {
+            accumulator.consume(input.to_f64(), price);
+        }
+        let changed = *accumulator != accumulator_previous;
+        let (cost_daily, cost_monthly) = (accumulator.cost_daily, accumulator.cost_monthly);
+        drop(accumulator);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self
+            .signal_price_current
+            .set_one(price.map(|price| Real::from_f64(price).unwrap()));
+        signals_sources_changed |= self
+            .signal_cost_daily
+            .set_one(Some(Real::from_f64(cost_daily).unwrap()));
+        signals_sources_changed |= self
+            .signal_cost_monthly
+            .set_one(Some(Real::from_f64(cost_monthly).unwrap()));
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+            self.gui_summary_waker.wake();
+        }
+
+        changed
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        if let Err(error) = self.load().await.context("load") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            if self.update() {
+                if let Err(error) = self.persist().await.context("persist") {
+                    log::error!("{}: {:?}", self.configuration.name, error);
+                }
+            }
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = timer_wheel::sleep(Self::INTERVAL).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'c, 'f> devices::Device for Device<'c, 'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/energy/cost_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'c, 'f> Runnable for Device<'c, 'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Price,
+    PriceCurrent,
+    CostDaily,
+    CostMonthly,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c, 'f> signals::Device for Device<'c, 'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Price => &self.signal_price as &dyn signal::Base,
+            SignalIdentifier::PriceCurrent => &self.signal_price_current as &dyn signal::Base,
+            SignalIdentifier::CostDaily => &self.signal_cost_daily as &dyn signal::Base,
+            SignalIdentifier::CostMonthly => &self.signal_cost_monthly as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    date: NaiveDate,
+    cost_daily: f64,
+    cost_monthly: f64,
+}
+impl<'c, 'f> devices::gui_summary::Device for Device<'c, 'f> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let accumulator = self.accumulator.read();
+
+        Self::Value {
+            date: accumulator.date,
+            cost_daily: accumulator.cost_daily,
+            cost_monthly: accumulator.cost_monthly,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Accumulator;
+    use approx::assert_relative_eq;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn accumulate() {
+        let date = |month: u32, day: u32| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+        let mut accumulator = Accumulator::new(date(1, 30));
+
+        // first reading is the reference only
+        accumulator.consume(100.0, Some(0.5));
+        assert_eq!(accumulator.cost_daily, 0.0);
+
+        accumulator.consume(104.0, Some(0.5));
+        assert_relative_eq!(accumulator.cost_daily, 2.0);
+
+        // no price, consumption is lost
+        accumulator.consume(106.0, None);
+        assert_relative_eq!(accumulator.cost_daily, 2.0);
+
+        // meter replaced
+        accumulator.consume(1.0, Some(0.5));
+        assert_relative_eq!(accumulator.cost_daily, 2.0);
+
+        accumulator.rollover(date(1, 31));
+        accumulator.consume(3.0, Some(1.0));
+        assert_relative_eq!(accumulator.cost_daily, 2.0);
+        assert_relative_eq!(accumulator.cost_monthly, 4.0);
+
+        accumulator.rollover(date(2, 1));
+        assert_eq!(accumulator.cost_daily, 0.0);
+        assert_eq!(accumulator.cost_monthly, 0.0);
+    }
+}
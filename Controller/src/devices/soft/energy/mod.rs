@@ -0,0 +1,3 @@
+pub mod cost_a;
+pub mod smart_start_a;
+pub mod surplus_a;
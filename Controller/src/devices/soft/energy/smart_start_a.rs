@@ -0,0 +1,393 @@
+use crate::{
+    datatypes::energy::spot_prices::SpotPrices,
+    devices,
+    modules::clock::Clock,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{borrow::Cow, time::Duration};
+use tokio::sync::watch;
+
+#[derive(Debug)]
+pub struct Configuration {
+    // how long appliance program runs
+    pub runtime: Duration,
+    // used when armed without explicit deadline, counted from arming
+    pub deadline: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Plan {
+    deadline: DateTime<Utc>,
+    start: DateTime<Utc>,
+}
+
+// starts appliance (ex. dishwasher on smart plug) in cheapest window before
+// deadline, based on spot prices
+// if prices are not known for any window, appliance is started immediately
+#[derive(Debug)]
+pub struct Device<'c> {
+    configuration: Configuration,
+    clock: &'c Clock<'c>,
+
+    deadline_sender: watch::Sender<Option<DateTime<Utc>>>,
+    deadline_receiver: watch::Receiver<Option<DateTime<Utc>>>,
+
+    plan: RwLock<Option<Plan>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_prices: signal::state_target_last::Signal<SpotPrices>,
+    signal_arm: signal::event_target_last::Signal<()>,
+    signal_cancel: signal::event_target_last::Signal<()>,
+    signal_armed: signal::state_source::Signal<bool>,
+    signal_start: signal::event_source::Signal<()>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'c> Device<'c> {
+    // clock may be adjusted, so plan is rechecked periodically
+    const INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+    ) -> Self {
+        let (deadline_sender, deadline_receiver) = watch::channel(None);
+
+        Self {
+            configuration,
+            clock,
+
+            deadline_sender,
+            deadline_receiver,
+
+            plan: RwLock::new(None),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_prices: signal::state_target_last::Signal::<SpotPrices>::new(),
+            signal_arm: signal::event_target_last::Signal::<()>::new(),
+            signal_cancel: signal::event_target_last::Signal::<()>::new(),
+            signal_armed: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_start: signal::event_source::Signal::<()>::new(),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn arm(
+        &self,
+        deadline: Option<DateTime<Utc>>,
+    ) {
+        let deadline = deadline.unwrap_or_else(|| {
+            self.clock.now() + chrono::Duration::from_std(self.configuration.deadline).unwrap()
+        });
+        self.deadline_sender.send_replace(Some(deadline));
+    }
+    fn cancel(&self) {
+        self.deadline_sender.send_replace(None);
+    }
+
+    // start of cheapest window of given runtime, within now and deadline
+    // for piecewise constant prices optimal window starts or ends at slot
+    // boundary, so only these are checked
+    fn start_cheapest(
+        prices: Option<&SpotPrices>,
+        now: DateTime<Utc>,
+        deadline: DateTime<Utc>,
+        runtime: Duration,
+    ) -> DateTime<Utc> {
+        let runtime = chrono::Duration::from_std(runtime).unwrap();
+        let start_latest = deadline - runtime;
+
+        let prices = match prices {
+            Some(prices) => prices,
+            None => return now,
+        };
+        if start_latest <= now {
+            return now;
+        }
+
+        let mut best = None::<(DateTime<Utc>, f64)>;
+        let candidates = (0..=prices.prices().len())
+            .map(|index| prices.slot_start(index))
+            .flat_map(|boundary| [boundary, boundary - runtime]);
+        for start in [now].into_iter().chain(candidates) {
+            if start < now || start > start_latest {
+                continue;
+            }
+            let cost = match prices.cost(start, start + runtime) {
+                Some(cost) => cost,
+                None => continue,
+            };
+            // ties resolved to earlier start
+            let better = match best {
+                Some((best_start, best_cost)) => {
+                    cost < best_cost || (cost == best_cost && start < best_start)
+                }
+                None => true,
+            };
+            if better {
+                best = Some((start, cost));
+            }
+        }
+
+        best.map_or(now, |(start, _)| start)
+    }
+
+    fn signals_targets_changed(&self) {
+        if self.signal_cancel.take_pending().is_some() {
+            self.cancel();
+        }
+        if self.signal_arm.take_pending().is_some() {
+            self.arm(None);
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut deadline_receiver = self.deadline_receiver.clone();
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let deadline = *deadline_receiver.borrow_and_update();
+            let now = self.clock.now();
+
+            let plan = deadline.map(|deadline| {
+                let prices = self.signal_prices.peek_last();
+                let start = Self::start_cheapest(
+                    prices.as_ref(),
+                    now,
+                    deadline,
+                    self.configuration.runtime,
+                );
+                Plan { deadline, start }
+            });
+
+            // start time reached
+            if let Some(plan) = plan {
+                if plan.start <= now {
+                    self.deadline_sender.send_replace(None);
+                    if self.signal_start.push_one(()) {
+                        self.signals_sources_changed_waker.wake();
+                    }
+                    continue;
+                }
+            }
+
+            *self.plan.write() = plan;
+            if self.signal_armed.set_one(Some(plan.is_some())) {
+                self.signals_sources_changed_waker.wake();
+            }
+            self.gui_summary_waker.wake();
+
+            let wake = match plan {
+                Some(plan) => {
+                    let duration = (plan.start - now).to_std().unwrap_or_default();
+                    timer_wheel::sleep(duration.min(Self::INTERVAL)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {
+                    self.signals_targets_changed();
+                },
+                result = deadline_receiver.changed().fuse() => result.unwrap(),
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'c> devices::Device for Device<'c> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/energy/smart_start_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'c> Runnable for Device<'c> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Prices,
+    Arm,
+    Cancel,
+    Armed,
+    Start,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c> signals::Device for Device<'c> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Prices => &self.signal_prices as &dyn signal::Base,
+            SignalIdentifier::Arm => &self.signal_arm as &dyn signal::Base,
+            SignalIdentifier::Cancel => &self.signal_cancel as &dyn signal::Base,
+            SignalIdentifier::Armed => &self.signal_armed as &dyn signal::Base,
+            SignalIdentifier::Start => &self.signal_start as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    runtime: Duration,
+    plan: Option<Plan>,
+}
+impl<'c> devices::gui_summary::Device for Device<'c> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let runtime = self.configuration.runtime;
+        let plan = *self.plan.read();
+
+        Self::Value { runtime, plan }
+    }
+}
+
+impl<'c> uri_cursor::Handler for Device<'c> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("arm", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        // null for default deadline
+                        let deadline = match request.body_parse_json::<Option<DateTime<Utc>>>() {
+                            Ok(deadline) => deadline,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+                        self.arm(deadline);
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("cancel", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        self.cancel();
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Device;
+    use crate::datatypes::{energy::spot_prices::SpotPrices, real::Real};
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn start_cheapest() {
+        let hour = Duration::from_secs(60 * 60);
+        let prices = SpotPrices::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            hour,
+            [5.0, 4.0, 1.0, 1.0, 3.0, 0.5]
+                .into_iter()
+                .map(|price| Real::from_f64(price).unwrap())
+                .collect(),
+        )
+        .unwrap();
+        let at =
+            |hour: u32, minute: u32| Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, 0).unwrap();
+
+        // two cheap slots in a row
+        assert_eq!(
+            Device::start_cheapest(Some(&prices), at(0, 10), at(5, 0), hour * 2),
+            at(2, 0)
+        );
+        // cheapest slot is after deadline
+        assert_eq!(
+            Device::start_cheapest(Some(&prices), at(0, 10), at(5, 0), hour * 3),
+            at(2, 0)
+        );
+        // window ending at slot boundary
+        assert_eq!(
+            Device::start_cheapest(Some(&prices), at(0, 10), at(4, 30), hour * 3 / 2),
+            at(2, 0)
+        );
+        // no prices beyond last slot, cheapest covered window is used
+        assert_eq!(
+            Device::start_cheapest(Some(&prices), at(3, 30), at(12, 0), hour),
+            at(5, 0)
+        );
+        // too late or unknown prices, started immediately
+        assert_eq!(
+            Device::start_cheapest(Some(&prices), at(4, 0), at(4, 30), hour),
+            at(4, 0)
+        );
+        assert_eq!(
+            Device::start_cheapest(None, at(0, 0), at(12, 0), hour),
+            at(0, 0)
+        );
+    }
+}
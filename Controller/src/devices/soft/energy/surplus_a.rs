@@ -0,0 +1,310 @@
+use crate::{
+    datatypes::{ratio::Ratio, real::Real},
+    devices,
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, iter, time::Duration};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Load {
+    // lower value is served first
+    pub priority: usize,
+    // nominal power at ratio = 1.0, in watts
+    pub power_max: f64,
+    // load is not started below this power (ex. ev charger minimum current)
+    pub power_min: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub loads: Box<[Load]>,
+
+    // desired grid power, in watts, positive for import
+    // slightly positive value avoids exporting due to measurement lag
+    pub grid_target: f64,
+    // fraction of error corrected in each step, (0.0, 1.0]
+    pub gain: f64,
+    pub interval: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    // power assigned to each load, in watts, same order as configuration
+    loads_power: Box<[f64]>,
+}
+
+// diverts photovoltaic surplus into controllable loads, keeping grid export
+// near zero
+// grid power is expected in watts, positive for import, negative for export
+// when grid power is unknown all loads are turned off
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    state: RwLock<State>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_grid: signal::state_target_last::Signal<Real>,
+    signal_loads: Box<[signal::state_source::Signal<Ratio>]>,
+    signal_diverted: signal::state_source::Signal<Real>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.gain > 0.0 && configuration.gain <= 1.0);
+        for load in configuration.loads.iter() {
+            assert!(load.power_max > 0.0);
+            assert!(load.power_min >= 0.0 && load.power_min <= load.power_max);
+        }
+
+        let loads_count = configuration.loads.len();
+
+        Self {
+            configuration,
+
+            state: RwLock::new(State {
+                loads_power: vec![0.0; loads_count].into_boxed_slice(),
+            }),
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_grid: signal::state_target_last::Signal::<Real>::new(),
+            signal_loads: (0..loads_count)
+                .map(|_load_index| signal::state_source::Signal::<Ratio>::new(Some(Ratio::zero())))
+                .collect::<Box<[_]>>(),
+            signal_diverted: signal::state_source::Signal::<Real>::new(Some(
+                Real::from_f64(0.0).unwrap(),
+            )),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // splits power between loads in priority order
+    fn allocate(
+        loads: &[Load],
+        mut power: f64,
+    ) -> Box<[f64]> {
+        let mut loads_order = (0..loads.len()).collect::<Box<[_]>>();
+        loads_order.sort_by_key(|load_index| loads[*load_index].priority);
+
+        let mut loads_power = vec![0.0; loads.len()].into_boxed_slice();
+        for load_index in loads_order.iter().copied() {
+            let load = &loads[load_index];
+
+            let load_power = power.min(load.power_max);
+            if load_power <= 0.0 || load_power < load.power_min {
+                continue;
+            }
+
+            loads_power[load_index] = load_power;
+            power -= load_power;
+        }
+
+        loads_power
+    }
+
+    fn calculate(&self) {
+        let grid = self.signal_grid.peek_last();
+
+        let mut state = self.state.write();
+        state.loads_power = match grid {
+            Some(grid) => {
+                let diverted = state.loads_power.iter().sum::<f64>();
+                let error = self.configuration.grid_target - grid.to_f64();
+                let power = (diverted + error * self.configuration.gain).max(0.0);
+                Self::allocate(&self.configuration.loads, power)
+            }
+            None => vec![0.0; self.configuration.loads.len()].into_boxed_slice(),
+        };
+        let loads_power = state.loads_power.clone();
+        drop(state);
+
+        let mut signals_sources_changed = false;
+        for ((signal_load, load), load_power) in self
+            .signal_loads
+            .iter()
+            .zip(self.configuration.loads.iter())
+            .zip(loads_power.iter())
+        {
+            let ratio = Ratio::from_f64((load_power / load.power_max).clamp(0.0, 1.0)).unwrap();
+            signals_sources_changed |= signal_load.set_one(Some(ratio));
+        }
+        let diverted = Real::from_f64(loads_power.iter().sum::<f64>()).unwrap();
+        signals_sources_changed |= self.signal_diverted.set_one(Some(diverted));
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+            self.gui_summary_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        // loads react with delay, so regulation runs at fixed pace instead of
+        // on every grid measurement
+        loop {
+            self.calculate();
+
+            select! {
+                () = timer_wheel::sleep(self.configuration.interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/energy/surplus_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Grid,
+    Load(usize),
+    Diverted,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain([(
+                SignalIdentifier::Grid,
+                &self.signal_grid as &dyn signal::Base,
+            )])
+            .chain(
+                self.signal_loads
+                    .iter()
+                    .enumerate()
+                    .map(|(load_index, signal_load)| {
+                        (
+                            SignalIdentifier::Load(load_index),
+                            signal_load as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain([(
+                SignalIdentifier::Diverted,
+                &self.signal_diverted as &dyn signal::Base,
+            )])
+            .collect::<signals::ByIdentifier<_>>()
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Grid => Metadata {
+                name: Some(Cow::from("Grid power")),
+                unit: Some(Cow::from("W")),
+                precision: Some(0),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Diverted => Metadata {
+                name: Some(Cow::from("Diverted power")),
+                unit: Some(Cow::from("W")),
+                min: Some(0.0),
+                precision: Some(0),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    grid: Option<Real>,
+    loads_power: Box<[f64]>,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let grid = self.signal_grid.peek_last();
+        let loads_power = self.state.read().loads_power.clone();
+
+        Self::Value { grid, loads_power }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, Load};
+
+    #[test]
+    fn allocate() {
+        let loads = [
+            Load {
+                priority: 1,
+                power_max: 2000.0,
+                power_min: 0.0,
+            },
+            Load {
+                priority: 0,
+                power_max: 1000.0,
+                power_min: 0.0,
+            },
+            Load {
+                priority: 2,
+                power_max: 11000.0,
+                power_min: 4000.0,
+            },
+        ];
+
+        assert_eq!(&*Device::allocate(&loads, 0.0), &[0.0, 0.0, 0.0]);
+        assert_eq!(&*Device::allocate(&loads, 500.0), &[0.0, 500.0, 0.0]);
+        assert_eq!(&*Device::allocate(&loads, 2500.0), &[1500.0, 1000.0, 0.0]);
+        // below minimum power of last load
+        assert_eq!(&*Device::allocate(&loads, 6000.0), &[2000.0, 1000.0, 0.0]);
+        assert_eq!(
+            &*Device::allocate(&loads, 20000.0),
+            &[2000.0, 1000.0, 11000.0]
+        );
+    }
+}
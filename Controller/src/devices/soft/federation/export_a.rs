@@ -0,0 +1,231 @@
+use super::{authorized, Snapshot};
+use crate::{
+    devices,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, sse, uri_cursor},
+};
+use async_trait::async_trait;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    select,
+    stream::{self, StreamExt},
+};
+use serde::Serialize;
+use std::{any::type_name, borrow::Cow, iter, sync::Arc, time::Duration};
+use tokio::sync::watch;
+
+#[derive(Debug)]
+pub struct Configuration {
+    // names under which inputs are exported, by input index
+    pub names: Box<[String]>,
+    pub token: String,
+    // snapshot is resent at least this often, so importers can detect dead link
+    pub keepalive: Duration,
+}
+
+// exports inputs to importers on other controllers
+// every connected importer receives complete snapshot on connect and after
+// every change
+#[derive(Debug)]
+pub struct Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    configuration: Configuration,
+
+    snapshot_sender: watch::Sender<Arc<str>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_inputs: Box<[signal::state_target_last::Signal<V>]>,
+}
+impl<V> Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    pub fn new(configuration: Configuration) -> Self {
+        let signal_inputs = configuration
+            .names
+            .iter()
+            .map(|_name| signal::state_target_last::Signal::<V>::new())
+            .collect::<Box<[_]>>();
+
+        let snapshot = Self::snapshot_serialize(&configuration.names, &signal_inputs);
+        let (snapshot_sender, _) = watch::channel(snapshot);
+
+        Self {
+            configuration,
+
+            snapshot_sender,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_inputs,
+        }
+    }
+
+    fn snapshot_serialize(
+        names: &[String],
+        signal_inputs: &[signal::state_target_last::Signal<V>],
+    ) -> Arc<str> {
+        let snapshot = Snapshot {
+            values: names
+                .iter()
+                .zip(signal_inputs.iter())
+                .map(|(name, signal_input)| (name.clone(), signal_input.peek_last()))
+                .collect(),
+        };
+        Arc::from(serde_json::to_string(&snapshot).unwrap())
+    }
+
+    fn signals_targets_changed(&self) {
+        let mut changed = false;
+        for signal_input in self.signal_inputs.iter() {
+            if signal_input.take_pending().is_some() {
+                changed = true;
+            }
+        }
+        if !changed {
+            return;
+        }
+
+        let snapshot = Self::snapshot_serialize(&self.configuration.names, &self.signal_inputs);
+        self.snapshot_sender.send_replace(snapshot);
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<V> devices::Device for Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!("soft/federation/export_a<{}>", type_name::<V>()))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<V> Runnable for Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<V> signals::Device for Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input_index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(input_index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+impl<V> uri_cursor::Handler for Device<V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("stream", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        if !authorized(&request, &self.configuration.token) {
+                            return async { web::Response::error(http::StatusCode::UNAUTHORIZED) }
+                                .boxed();
+                        }
+
+                        // stream ends when device is dropped
+                        let keepalive = self.configuration.keepalive;
+                        let snapshot_receiver = self.snapshot_sender.subscribe();
+                        let sse_stream = stream::unfold(
+                            (snapshot_receiver, true),
+                            move |(mut snapshot_receiver, first)| async move {
+                                if !first {
+                                    select! {
+                                        result = snapshot_receiver.changed().fuse() => result.ok()?,
+                                        () = tokio::time::sleep(keepalive).fuse() => {},
+                                    }
+                                }
+
+                                let snapshot = snapshot_receiver.borrow_and_update().clone();
+                                let event = sse::Event {
+                                    id: None,
+                                    data: Cow::from(snapshot.to_string()),
+                                };
+
+                                Some((event, (snapshot_receiver, false)))
+                            },
+                        );
+
+                        async { web::Response::ok_sse_stream(sse_stream) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
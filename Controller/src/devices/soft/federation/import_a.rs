@@ -0,0 +1,335 @@
+use super::Snapshot;
+use crate::{
+    devices,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_flag,
+        circuit_breaker::{self, CircuitBreaker},
+        runnable::{Exited, Runnable},
+    },
+    web::sse,
+};
+use anyhow::{anyhow, Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{any::type_name, borrow::Cow, convert::Infallible, iter, time::Duration};
+use tokio::sync::watch;
+
+#[derive(Debug)]
+pub struct Configuration {
+    // stream of exporting device, ex.
+    // http://house:8080/devices-runner/devices/12/stream
+    pub url: String,
+    pub token: String,
+    // exported names to import, by output index
+    pub names: Box<[String]>,
+    // link is considered lost if nothing was received for this time, should be
+    // a few times larger than exporter keepalive
+    pub timeout: Duration,
+    // after link loss outputs keep last received values for this time (forever
+    // if None), then they are cleared
+    pub hold: Option<Duration>,
+}
+
+// imports values exported by export_a device on other controller
+#[derive(Debug)]
+pub struct Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    configuration: Configuration,
+
+    reqwest_client: reqwest::Client,
+    circuit_breaker: CircuitBreaker,
+    connected_sender: watch::Sender<bool>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_outputs: Box<[signal::state_source::Signal<V>]>,
+    signal_connected: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<V> Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    pub fn new(configuration: Configuration) -> Self {
+        // own client, so connect timeout follows configuration
+        let reqwest_client = reqwest::ClientBuilder::new()
+            .connect_timeout(configuration.timeout)
+            .build()
+            .unwrap();
+
+        let circuit_breaker = CircuitBreaker::new(
+            format!("soft/federation/import_a/{}", configuration.url),
+            circuit_breaker::Configuration::default(),
+        );
+
+        let signal_outputs = configuration
+            .names
+            .iter()
+            .map(|_name| signal::state_source::Signal::<V>::new(None))
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+
+            reqwest_client,
+            circuit_breaker,
+            connected_sender: watch::channel(false).0,
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_outputs,
+            signal_connected: signal::state_source::Signal::<bool>::new(Some(false)),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn snapshot_apply(
+        &self,
+        mut snapshot: Snapshot<V>,
+    ) {
+        let mut signals_sources_changed = false;
+        for (name, signal_output) in self
+            .configuration
+            .names
+            .iter()
+            .zip(self.signal_outputs.iter())
+        {
+            // names missing in snapshot (not exported) are kept as None
+            let value = snapshot.values.remove(name).flatten();
+            signals_sources_changed |= signal_output.set_one(value);
+        }
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+    fn outputs_clear(&self) {
+        let mut signals_sources_changed = false;
+        for signal_output in self.signal_outputs.iter() {
+            signals_sources_changed |= signal_output.set_one(None);
+        }
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+    fn connected_set(
+        &self,
+        connected: bool,
+    ) {
+        if !self.connected_sender.send_if_modified(|connected_current| {
+            let changed = *connected_current != connected;
+            *connected_current = connected;
+            changed
+        }) {
+            return;
+        }
+
+        if self.signal_connected.set_one(Some(connected)) {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    async fn link_run_once(&self) -> Result<Infallible, Error> {
+        let response = self
+            .reqwest_client
+            .get(&self.configuration.url)
+            .bearer_auth(&self.configuration.token)
+            .header(http::header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .context("send")?
+            .error_for_status()
+            .context("error_for_status")?;
+
+        let mut data_stream = response.bytes_stream();
+        let mut parser = sse::Parser::new();
+        loop {
+            let chunk = tokio::time::timeout(self.configuration.timeout, data_stream.next())
+                .await
+                .context("timeout")?
+                .ok_or_else(|| anyhow!("stream closed"))?
+                .context("chunk")?;
+
+            for event in parser.push(&chunk) {
+                let snapshot =
+                    serde_json::from_str::<Snapshot<V>>(&event.data).context("from_str")?;
+                self.snapshot_apply(snapshot);
+
+                if !*self.connected_sender.borrow() {
+                    if self.circuit_breaker.success() {
+                        log::info!("import {} recovered", self.configuration.url);
+                    }
+                    self.connected_set(true);
+                }
+            }
+        }
+    }
+
+    const ERROR_RESTART_INTERVAL: Duration = Duration::from_secs(5);
+    async fn link_run(&self) -> ! {
+        loop {
+            self.circuit_breaker.ready().await;
+
+            let error = self.link_run_once().await.context("link_run_once");
+            self.connected_set(false);
+
+            // repeated failures of unreachable controller are not reported
+            if self.circuit_breaker.failure() {
+                log::warn!(
+                    "import {} failed: {:?}",
+                    self.configuration.url,
+                    error.unwrap_err()
+                );
+            }
+            tokio::time::sleep(Self::ERROR_RESTART_INTERVAL).await;
+        }
+    }
+
+    // clears outputs if link stays down for longer than hold
+    async fn hold_run(&self) -> ! {
+        let mut connected_receiver = self.connected_sender.subscribe();
+        loop {
+            // wait for disconnection
+            connected_receiver
+                .wait_for(|connected| !*connected)
+                .await
+                .unwrap();
+
+            let hold_runner = match self.configuration.hold {
+                Some(hold) => tokio::time::sleep(hold).left_future(),
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = hold_runner.fuse() => {
+                    self.outputs_clear();
+                },
+                result = connected_receiver.wait_for(|connected| *connected).fuse() => {
+                    result.unwrap();
+                    continue;
+                },
+            }
+
+            // wait for reconnection, outputs will be set by link
+            connected_receiver
+                .wait_for(|connected| *connected)
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let link_runner = self.link_run();
+        let hold_runner = self.hold_run();
+
+        select! {
+            _ = link_runner.fuse() => panic!("link_runner yielded"),
+            _ = hold_runner.fuse() => panic!("hold_runner yielded"),
+            () = exit_flag.fuse() => {},
+        }
+
+        Exited
+    }
+}
+
+impl<V> devices::Device for Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!("soft/federation/import_a<{}>", type_name::<V>()))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<V> Runnable for Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Output(usize),
+    Connected,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<V> signals::Device for Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(output_index, signal_output)| {
+                        (
+                            SignalIdentifier::Output(output_index),
+                            signal_output as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain([(
+                SignalIdentifier::Connected,
+                &self.signal_connected as &dyn signal::Base,
+            )])
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    connected: bool,
+}
+impl<V> devices::gui_summary::Device for Device<V>
+where
+    V: Value + Clone + DeserializeOwned,
+{
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        Self::Value {
+            connected: *self.connected_sender.borrow(),
+        }
+    }
+}
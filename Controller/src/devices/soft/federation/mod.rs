@@ -0,0 +1,29 @@
+// sharing signals between controllers
+// export_a device streams its inputs over sse, import_a device on other
+// controller connects to it and republishes values as its outputs
+pub mod export_a;
+pub mod import_a;
+
+use crate::web;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// payload of every event sent by exporter, values by exported name
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = "V: Deserialize<'de>"))]
+pub struct Snapshot<V> {
+    pub values: HashMap<String, Option<V>>,
+}
+
+// importers authenticate with `Authorization: Bearer <token>` header
+pub fn authorized(
+    request: &web::Request,
+    token: &str,
+) -> bool {
+    request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        == Some(token)
+}
@@ -0,0 +1,546 @@
+use crate::{
+    devices,
+    modules::{clock::Clock, fs::Fs, sqlite::SQLite},
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{BoxFuture, Future, FutureExt},
+    stream::StreamExt,
+};
+use indoc::indoc;
+use parking_lot::Mutex;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::{any::type_name, borrow::Cow, iter};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across journals
+    pub name: String,
+    // names of journaled inputs, used to query entries
+    pub names: Box<[String]>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Entry {
+    pub id: i64,
+    pub name: String,
+    pub value: Option<serde_json::Value>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Filter {
+    pub name: Option<String>,
+    // json representation, null matches entries with no value
+    pub value: Option<serde_json::Value>,
+    // only entries with id lower than this, used for paging
+    pub before: Option<i64>,
+}
+impl Filter {
+    pub fn from_query(query: &str) -> Result<Self, Error> {
+        let mut filter = Self::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match &*key {
+                "name" => filter.name = Some(value.into_owned()),
+                "value" => {
+                    filter.value = Some(serde_json::from_str(&value).context("value")?);
+                }
+                "before" => filter.before = Some(value.parse().context("before")?),
+                _ => {}
+            }
+        }
+        Ok(filter)
+    }
+}
+
+fn sql_initialize(connection: &rusqlite::Connection) -> Result<(), Error> {
+    connection
+        .execute_batch(indoc!(
+            "
+            CREATE TABLE IF NOT EXISTS `entries` (
+                `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                `name` TEXT NOT NULL,
+                `value` TEXT NULL, -- json
+                `timestamp` INTEGER NOT NULL -- unix milliseconds
+            ) STRICT;
+            CREATE INDEX IF NOT EXISTS `entries_name_value` ON `entries` (`name`, `value`, `id`);
+        "
+        ))
+        .context("execute_batch")?;
+    Ok(())
+}
+fn sql_insert(
+    connection: &rusqlite::Connection,
+    name: &str,
+    value: Option<&str>,
+    timestamp: DateTime<Utc>,
+) -> Result<(), Error> {
+    connection
+        .execute(
+            indoc!(
+                "
+                INSERT INTO
+                    `entries` (`name`, `value`, `timestamp`)
+                VALUES
+                    (?, ?, ?)
+            "
+            ),
+            (name, value, timestamp.timestamp_millis()),
+        )
+        .context("execute")?;
+    Ok(())
+}
+// last journaled value of given name, as stored
+fn sql_value_last(
+    connection: &rusqlite::Connection,
+    name: &str,
+) -> Result<Option<Option<String>>, Error> {
+    let value = connection
+        .query_row(
+            "SELECT `value` FROM `entries` WHERE `name` = ? ORDER BY `id` DESC LIMIT 1",
+            (name,),
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .context("query_row")?;
+    Ok(value)
+}
+// matching entries, newest first
+fn sql_entries(
+    connection: &rusqlite::Connection,
+    filter: &Filter,
+    limit: usize,
+) -> Result<Box<[Entry]>, Error> {
+    let value = filter
+        .value
+        .as_ref()
+        .map(|value| match value {
+            serde_json::Value::Null => None,
+            value => Some(value.to_string()),
+        })
+        .map(|value| (value.is_none(), value));
+
+    let entries = connection
+        .prepare(indoc!(
+            "
+            SELECT
+                `id`, `name`, `value`, `timestamp`
+            FROM
+                `entries`
+            WHERE
+                (?1 IS NULL OR `name` = ?1)
+                AND (?2 IS NULL OR (?2 AND `value` IS NULL) OR `value` = ?3)
+                AND (?4 IS NULL OR `id` < ?4)
+            ORDER BY
+                `id` DESC
+            LIMIT
+                ?5
+        "
+        ))
+        .context("prepare")?
+        .query_map(
+            (
+                &filter.name,
+                value.as_ref().map(|(is_null, _)| *is_null),
+                value.as_ref().and_then(|(_, value)| value.as_ref()),
+                filter.before,
+                limit as i64,
+            ),
+            |row| -> rusqlite::Result<(i64, String, Option<String>, i64)> {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            },
+        )
+        .context("query_map")?
+        .map(|row| -> Result<Entry, Error> {
+            let (id, name, value, timestamp) = row.context("row")?;
+            let value = value
+                .map(|value| serde_json::from_str(&value))
+                .transpose()
+                .context("value")?;
+            let timestamp =
+                DateTime::<Utc>::from_timestamp_millis(timestamp).context("timestamp")?;
+            Ok(Entry {
+                id,
+                name,
+                value,
+                timestamp,
+            })
+        })
+        .collect::<Result<Box<[_]>, _>>()
+        .context("collect")?;
+
+    Ok(entries)
+}
+
+// append only journal of state transitions of discrete (boolean, enum)
+// inputs, answering questions like "when did the garage door last open"
+// unlike state logger, only changes are stored and entries are never merged
+#[derive(Debug)]
+pub struct Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    configuration: Configuration,
+    clock: &'f Clock<'f>,
+
+    sqlite: SQLite<'f>,
+
+    // last journaled value (as json) of each input, if known
+    values_last: Mutex<Box<[Option<Option<String>>]>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_inputs: Box<[signal::state_target_queued::Signal<V>]>,
+}
+impl<'f, V> Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    const LIMIT_DEFAULT: usize = 100;
+    const LIMIT_MAX: usize = 10000;
+
+    pub fn new(
+        configuration: Configuration,
+        clock: &'f Clock<'f>,
+        fs: &'f Fs,
+    ) -> Self {
+        let sqlite = SQLite::new(format!("journal.{}", configuration.name), fs);
+
+        let values_last = (0..configuration.names.len())
+            .map(|_| None)
+            .collect::<Box<[_]>>();
+        let signal_inputs = (0..configuration.names.len())
+            .map(|_| signal::state_target_queued::Signal::<V>::new())
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+            clock,
+
+            sqlite,
+
+            values_last: Mutex::new(values_last),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_inputs,
+        }
+    }
+
+    async fn load(&self) -> Result<(), Error> {
+        let names = self.configuration.names.clone();
+        let values_last = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                sql_initialize(transaction).context("sql_initialize")?;
+
+                let values_last = names
+                    .iter()
+                    .map(|name| sql_value_last(transaction, name))
+                    .collect::<Result<Box<[_]>, _>>()
+                    .context("sql_value_last")?;
+
+                Ok(values_last)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        *self.values_last.lock() = values_last;
+
+        Ok(())
+    }
+
+    // returns (index, value) of inputs which value differs from last journaled
+    fn transitions(&self) -> Result<Vec<(usize, Option<String>)>, Error> {
+        let mut values_last = self.values_last.lock();
+
+        let mut transitions = Vec::<(usize, Option<String>)>::new();
+        for (index, signal_input) in self.signal_inputs.iter().enumerate() {
+            for value in signal_input.take_pending().into_vec() {
+                let value = value
+                    .map(|value| serde_json::to_string(&value))
+                    .transpose()
+                    .context("to_string")?;
+
+                if values_last[index].as_ref() == Some(&value) {
+                    continue;
+                }
+                values_last[index] = Some(value.clone());
+                transitions.push((index, value));
+            }
+        }
+
+        Ok(transitions)
+    }
+    async fn signals_targets_changed(&self) -> Result<(), Error> {
+        let transitions = self.transitions().context("transitions")?;
+        if transitions.is_empty() {
+            return Ok(());
+        }
+
+        let timestamp = self.clock.now();
+        let transitions = transitions
+            .into_iter()
+            .map(|(index, value)| (self.configuration.names[index].clone(), value))
+            .collect::<Box<[_]>>();
+
+        self.sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                for (name, value) in transitions.iter() {
+                    sql_insert(transaction, name, value.as_deref(), timestamp)
+                        .context("sql_insert")?;
+                }
+                Ok(())
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(())
+    }
+
+    fn entries(
+        &self,
+        filter: Filter,
+        limit: usize,
+    ) -> impl Future<Output = Result<Box<[Entry]>, Error>> + Send + 'static {
+        let limit = limit.min(Self::LIMIT_MAX);
+        self.sqlite
+            .query(move |connection| sql_entries(connection, &filter, limit))
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        if let Err(error) = self.load().await.context("load") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                if let Err(error) = self
+                    .signals_targets_changed()
+                    .await
+                    .context("signals_targets_changed")
+                {
+                    log::error!("{}: {:?}", self.configuration.name, error);
+                }
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<'f, V> devices::Device for Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!("soft/logger/journal_a<{}>", type_name::<V>()))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'f, V> Runnable for Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f, V> signals::Device for Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect()
+    }
+}
+
+impl<'f, V> uri_cursor::Handler for Device<'f, V>
+where
+    V: Value + Clone + Serialize,
+{
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            // ?name=&value=&before=&limit=
+            uri_cursor::UriCursor::Next("entries", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let query = request.uri().query().unwrap_or("");
+                        let filter = match Filter::from_query(query) {
+                            Ok(filter) => filter,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+                        let limit = match form_urlencoded::parse(query.as_bytes())
+                            .find_map(|(key, value)| (key == "limit").then_some(value))
+                            .map(|value| value.parse::<usize>())
+                            .transpose()
+                        {
+                            Ok(limit) => limit.unwrap_or(Self::LIMIT_DEFAULT),
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+
+                        let entries = self.entries(filter, limit);
+                        let name = self.configuration.name.clone();
+                        async move {
+                            match entries.await {
+                                Ok(entries) => web::Response::ok_json(entries),
+                                Err(error) => {
+                                    log::error!("{}: entries: {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            // most recent matching entry, eg. ?name=garage_door&value="Open"
+            uri_cursor::UriCursor::Next("last", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let filter = match Filter::from_query(request.uri().query().unwrap_or("")) {
+                            Ok(filter) => filter,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+
+                        let entries = self.entries(filter, 1);
+                        let name = self.configuration.name.clone();
+                        async move {
+                            match entries.await {
+                                Ok(entries) => {
+                                    let entry = entries.into_vec().into_iter().next();
+                                    web::Response::ok_json(entry)
+                                }
+                                Err(error) => {
+                                    log::error!("{}: last: {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sql_entries, sql_initialize, sql_insert, sql_value_last, Filter};
+    use chrono::{TimeZone, Utc};
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    #[test]
+    fn query() {
+        let connection = Connection::open_in_memory().unwrap();
+        sql_initialize(&connection).unwrap();
+
+        let timestamp = |second| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap();
+        sql_insert(&connection, "door", Some("true"), timestamp(0)).unwrap();
+        sql_insert(&connection, "door", Some("false"), timestamp(1)).unwrap();
+        sql_insert(&connection, "gate", None, timestamp(2)).unwrap();
+        sql_insert(&connection, "door", Some("true"), timestamp(3)).unwrap();
+        sql_insert(&connection, "door", Some("false"), timestamp(4)).unwrap();
+
+        assert_eq!(
+            sql_value_last(&connection, "door").unwrap(),
+            Some(Some("false".to_owned()))
+        );
+        assert_eq!(sql_value_last(&connection, "gate").unwrap(), Some(None));
+        assert_eq!(sql_value_last(&connection, "window").unwrap(), None);
+
+        let filter = Filter::from_query("name=door&value=true").unwrap();
+        let entries = sql_entries(&connection, &filter, 1).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value, Some(json!(true)));
+        assert_eq!(entries[0].timestamp, timestamp(3));
+
+        let filter = Filter::from_query(&format!("name=door&before={}", entries[0].id)).unwrap();
+        let entries = sql_entries(&connection, &filter, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, timestamp(1));
+        assert_eq!(entries[1].timestamp, timestamp(0));
+
+        let filter = Filter::from_query("value=null").unwrap();
+        let entries = sql_entries(&connection, &filter, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "gate");
+
+        let entries = sql_entries(&connection, &Filter::default(), 10).unwrap();
+        assert_eq!(entries.len(), 5);
+
+        assert!(Filter::from_query("value=open").is_err());
+    }
+}
@@ -1 +1,2 @@
+pub mod journal_a;
 pub mod state;
@@ -0,0 +1,248 @@
+use super::SinkId;
+use anyhow::{ensure, Context, Error};
+use chrono::{DateTime, TimeDelta, Utc};
+use indoc::indoc;
+use serde::Serialize;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Check {
+    // any value outside of range during last analysis period
+    Range { min: Option<f64>, max: Option<f64> },
+    // value did not change at all for given time
+    Stuck { duration: TimeDelta },
+    // average of last hour differs from the same hour week ago by more than
+    // tolerance
+    WeekDeviation { tolerance: f64 },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rule {
+    // used to bind alarms to signals, must be unique
+    pub name: String,
+    // must be a real sink
+    pub sink_id: SinkId,
+    pub check: Check,
+}
+impl Rule {
+    pub fn validate(&self) -> Result<(), Error> {
+        match &self.check {
+            Check::Range { min, max } => {
+                ensure!(min.is_some() || max.is_some(), "range is unbounded");
+                if let (Some(min), Some(max)) = (min, max) {
+                    ensure!(min <= max, "min is greater than max");
+                }
+            }
+            Check::Stuck { duration } => {
+                ensure!(*duration > TimeDelta::zero(), "duration must be positive");
+            }
+            Check::WeekDeviation { tolerance } => {
+                ensure!(*tolerance >= 0.0, "tolerance must not be negative");
+            }
+        }
+        Ok(())
+    }
+}
+
+// reported when rule becomes active or inactive
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Anomaly {
+    pub rule: String,
+    pub active: bool,
+}
+
+// aggregated storage of a sink over a time range
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stats {
+    pub timestamp_first: i64,
+    pub min: f64,
+    pub max: f64,
+    pub average: f64,
+}
+
+// groups starting in [from, to)
+pub fn sql_stats(
+    connection: &rusqlite::Connection,
+    sink_id: SinkId,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Option<Stats>, Error> {
+    let stats = connection
+        .query_row(
+            indoc!(
+                "
+                SELECT
+                    MIN(`timestamp_group_start`), MIN(`min`), MAX(`max`), SUM(`sum`) / SUM(`weight`)
+                FROM
+                    `storage_real`
+                WHERE
+                    `sink_id` = ?
+                    AND `timestamp_group_start` >= ?
+                    AND `timestamp_group_start` < ?
+                    AND `weight` > 0
+            "
+            ),
+            (sink_id, from.timestamp(), to.timestamp()),
+            |row| -> rusqlite::Result<_> {
+                let timestamp_first = row.get::<_, Option<i64>>(0)?;
+                let min = row.get::<_, Option<f64>>(1)?;
+                let max = row.get::<_, Option<f64>>(2)?;
+                let average = row.get::<_, Option<f64>>(3)?;
+                Ok(match (timestamp_first, min, max, average) {
+                    (Some(timestamp_first), Some(min), Some(max), Some(average)) => Some(Stats {
+                        timestamp_first,
+                        min,
+                        max,
+                        average,
+                    }),
+                    _ => None,
+                })
+            },
+        )
+        .context("query_row")?;
+    Ok(stats)
+}
+
+// time ranges that need to be aggregated to evaluate the check
+pub fn ranges(
+    check: &Check,
+    now: DateTime<Utc>,
+    period: TimeDelta,
+) -> Box<[(DateTime<Utc>, DateTime<Utc>)]> {
+    match check {
+        Check::Range { .. } => Box::new([(now - period, now)]),
+        Check::Stuck { duration } => Box::new([(now - *duration, now)]),
+        Check::WeekDeviation { .. } => {
+            let hour = TimeDelta::hours(1);
+            let week = TimeDelta::weeks(1);
+            Box::new([(now - hour, now), (now - week - hour, now - week)])
+        }
+    }
+}
+
+// stats are given for ranges returned by ranges()
+// missing data never makes the rule active
+pub fn evaluate(
+    check: &Check,
+    ranges: &[(DateTime<Utc>, DateTime<Utc>)],
+    stats: &[Option<Stats>],
+    timestamp_divisor: f64,
+) -> bool {
+    match check {
+        Check::Range { min, max } => {
+            let stats = match stats[0] {
+                Some(stats) => stats,
+                None => return false,
+            };
+            min.is_some_and(|min| stats.min < min) || max.is_some_and(|max| stats.max > max)
+        }
+        Check::Stuck { .. } => {
+            let stats = match stats[0] {
+                Some(stats) => stats,
+                None => return false,
+            };
+            // data must cover whole range, otherwise sink may have just started
+            let (from, _) = ranges[0];
+            let covered = ((stats.timestamp_first - from.timestamp()) as f64) < timestamp_divisor;
+            covered && stats.min == stats.max
+        }
+        Check::WeekDeviation { tolerance } => {
+            let (current, previous) = match (stats[0], stats[1]) {
+                (Some(current), Some(previous)) => (current, previous),
+                _ => return false,
+            };
+            (current.average - previous.average).abs() > *tolerance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, ranges, Check, Stats};
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    #[test]
+    fn checks() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 8, 12, 0, 0).unwrap();
+        let period = TimeDelta::minutes(5);
+        let stats = |timestamp_first: i64, min: f64, max: f64, average: f64| Stats {
+            timestamp_first,
+            min,
+            max,
+            average,
+        };
+
+        let check = Check::Range {
+            min: Some(0.0),
+            max: Some(30.0),
+        };
+        let ranges_ = ranges(&check, now, period);
+        assert_eq!(&*ranges_, [(now - period, now)]);
+        let from = ranges_[0].0.timestamp();
+        assert!(!evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(from, 1.0, 29.0, 20.0))],
+            60.0
+        ));
+        assert!(evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(from, 1.0, 31.0, 20.0))],
+            60.0
+        ));
+        assert!(!evaluate(&check, &ranges_, &[None], 60.0));
+
+        let check = Check::Stuck {
+            duration: TimeDelta::hours(6),
+        };
+        let ranges_ = ranges(&check, now, period);
+        let from = ranges_[0].0.timestamp();
+        assert!(evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(from, 5.0, 5.0, 5.0))],
+            60.0
+        ));
+        // sink started recently
+        assert!(!evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(from + 3600, 5.0, 5.0, 5.0))],
+            60.0
+        ));
+        assert!(!evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(from, 5.0, 5.1, 5.0))],
+            60.0
+        ));
+
+        let check = Check::WeekDeviation { tolerance: 2.0 };
+        let ranges_ = ranges(&check, now, period);
+        assert_eq!(ranges_.len(), 2);
+        assert!(evaluate(
+            &check,
+            &ranges_,
+            &[
+                Some(stats(0, 0.0, 0.0, 25.0)),
+                Some(stats(0, 0.0, 0.0, 21.0))
+            ],
+            60.0
+        ));
+        assert!(!evaluate(
+            &check,
+            &ranges_,
+            &[
+                Some(stats(0, 0.0, 0.0, 22.0)),
+                Some(stats(0, 0.0, 0.0, 21.0))
+            ],
+            60.0
+        ));
+        assert!(!evaluate(
+            &check,
+            &ranges_,
+            &[Some(stats(0, 0.0, 0.0, 25.0)), None],
+            60.0
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use super::SinkId;
+use anyhow::{ensure, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// how value of derived sink is computed from its source sinks
+// derived value is missing when any of source values is missing
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Derivation {
+    Sum(Box<[SinkId]>),
+    Difference(SinkId, SinkId), // first minus second
+    Average(Box<[SinkId]>),
+}
+impl Derivation {
+    pub fn sources(&self) -> Box<[SinkId]> {
+        match self {
+            Derivation::Sum(sink_ids) => sink_ids.clone(),
+            Derivation::Difference(a, b) => Box::new([*a, *b]),
+            Derivation::Average(sink_ids) => sink_ids.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Derivation::Sum(sink_ids) | Derivation::Average(sink_ids) => {
+                ensure!(!sink_ids.is_empty(), "at least one source is required");
+            }
+            Derivation::Difference(a, b) => {
+                ensure!(a != b, "sources must be different");
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(
+        &self,
+        values: &HashMap<SinkId, f64>,
+    ) -> Option<f64> {
+        match self {
+            Derivation::Sum(sink_ids) => sink_ids
+                .iter()
+                .map(|sink_id| values.get(sink_id).copied())
+                .sum::<Option<f64>>(),
+            Derivation::Difference(a, b) => Some(*values.get(a)? - *values.get(b)?),
+            Derivation::Average(sink_ids) => {
+                let sum = sink_ids
+                    .iter()
+                    .map(|sink_id| values.get(sink_id).copied())
+                    .sum::<Option<f64>>()?;
+                Some(sum / sink_ids.len() as f64)
+            }
+        }
+    }
+}
+
+// last known source values and derived sinks depending on them
+#[derive(Debug)]
+pub struct Derived {
+    derivations: HashMap<SinkId, Derivation>,
+    dependents: HashMap<SinkId, Vec<SinkId>>,
+    values: HashMap<SinkId, f64>,
+}
+impl Derived {
+    pub fn new(
+        derivations: HashMap<SinkId, Derivation>,
+        values: HashMap<SinkId, f64>,
+    ) -> Self {
+        let mut dependents = HashMap::<SinkId, Vec<SinkId>>::new();
+        for (sink_id, derivation) in derivations.iter() {
+            for source in derivation.sources().into_vec() {
+                dependents.entry(source).or_default().push(*sink_id);
+            }
+        }
+
+        Self {
+            derivations,
+            dependents,
+            values,
+        }
+    }
+    pub fn empty() -> Self {
+        Self::new(HashMap::new(), HashMap::new())
+    }
+
+    // records new value of source sink, returns new values of derived sinks
+    // that depend on it
+    pub fn push(
+        &mut self,
+        sink_id: SinkId,
+        value: Option<f64>,
+    ) -> Vec<(SinkId, Option<f64>)> {
+        let dependents = match self.dependents.get(&sink_id) {
+            Some(dependents) => dependents,
+            None => return Vec::new(),
+        };
+
+        match value {
+            Some(value) => self.values.insert(sink_id, value),
+            None => self.values.remove(&sink_id),
+        };
+
+        dependents
+            .iter()
+            .map(|dependent| {
+                let value = self.derivations[dependent].evaluate(&self.values);
+                (*dependent, value)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Derivation, Derived};
+    use maplit::hashmap;
+
+    #[test]
+    fn push() {
+        let mut derived = Derived::new(
+            hashmap! {
+                10 => Derivation::Difference(1, 2),
+                11 => Derivation::Sum(Box::new([1, 2, 3])),
+                12 => Derivation::Average(Box::new([2, 3])),
+            },
+            hashmap! {
+                // restored from storage
+                3 => 4.0,
+            },
+        );
+
+        let mut values = derived.push(1, Some(20.0));
+        values.sort_by_key(|(sink_id, _)| *sink_id);
+        assert_eq!(values, [(10, None), (11, None)]);
+
+        let mut values = derived.push(2, Some(15.0));
+        values.sort_by_key(|(sink_id, _)| *sink_id);
+        assert_eq!(values, [(10, Some(5.0)), (11, Some(39.0)), (12, Some(9.5))]);
+
+        let mut values = derived.push(3, None);
+        values.sort_by_key(|(sink_id, _)| *sink_id);
+        assert_eq!(values, [(11, None), (12, None)]);
+
+        assert!(derived.push(4, Some(1.0)).is_empty());
+
+        assert!(Derivation::Difference(1, 1).validate().is_err());
+        assert!(Derivation::Sum(Box::new([])).validate().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::Instant;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClockAdjustment {
+    pub time: DateTime<Utc>,
+    pub offset: TimeDelta, // wall clock minus timeline, negative when wall clock went back
+}
+
+// maps wall clock readings onto non-decreasing timeline
+// timeline advances with monotonic clock, wall clock steps forward are followed
+// immediately, steps backward are absorbed by running the timeline slower
+// until wall clock catches up
+#[derive(Debug)]
+pub struct HybridClock {
+    last: Option<(Instant, DateTime<Utc>)>,
+    slewing: bool,
+}
+impl HybridClock {
+    // differences below this are regular jitter and are not reported
+    const TOLERANCE: TimeDelta = TimeDelta::seconds(2);
+    // while slewing, timeline moves by (1 - 1 / SLEW_DIVISOR) of real time
+    const SLEW_DIVISOR: i32 = 10;
+
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            slewing: false,
+        }
+    }
+
+    pub fn map(
+        &mut self,
+        wall: DateTime<Utc>,
+        instant: Instant,
+    ) -> (DateTime<Utc>, Option<ClockAdjustment>) {
+        let (instant_last, time_last) = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some((instant, wall));
+                return (wall, None);
+            }
+        };
+
+        // items from different sinks may come slightly out of order
+        let elapsed = TimeDelta::from_std(instant.saturating_duration_since(instant_last)).unwrap();
+        let expected = time_last + elapsed;
+        let offset = wall - expected;
+
+        let mut adjustment = None;
+        let time = if offset > Self::TOLERANCE {
+            // wall clock stepped forward
+            adjustment = Some(ClockAdjustment { time: wall, offset });
+            self.slewing = false;
+            wall
+        } else if offset >= -Self::TOLERANCE {
+            self.slewing = false;
+            wall.max(time_last)
+        } else {
+            // wall clock stepped back, reported only once
+            if !self.slewing {
+                adjustment = Some(ClockAdjustment {
+                    time: expected,
+                    offset,
+                });
+                self.slewing = true;
+            }
+            wall.max(expected - elapsed / Self::SLEW_DIVISOR)
+        };
+
+        self.last = Some((instant_last.max(instant), time));
+        (time, adjustment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HybridClock;
+    use chrono::{TimeDelta, TimeZone, Utc};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn steps() {
+        let mut hybrid_clock = HybridClock::new();
+
+        let instant = Instant::now();
+        let wall = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(hybrid_clock.map(wall, instant), (wall, None));
+
+        // regular progress
+        let (time, adjustment) = hybrid_clock.map(
+            wall + TimeDelta::seconds(10),
+            instant + Duration::from_secs(10),
+        );
+        assert_eq!(time, wall + TimeDelta::seconds(10));
+        assert_eq!(adjustment, None);
+
+        // one hour forward
+        let (time, adjustment) = hybrid_clock.map(
+            wall + TimeDelta::seconds(3620),
+            instant + Duration::from_secs(20),
+        );
+        assert_eq!(time, wall + TimeDelta::seconds(3620));
+        assert_eq!(adjustment.unwrap().offset, TimeDelta::seconds(3600));
+
+        // one hour back, time must not go back
+        let (time, adjustment) = hybrid_clock.map(
+            wall + TimeDelta::seconds(30),
+            instant + Duration::from_secs(30),
+        );
+        assert_eq!(time, wall + TimeDelta::seconds(3629));
+        assert_eq!(adjustment.unwrap().offset, TimeDelta::seconds(-3600));
+
+        // still slewing, adjustment not repeated
+        let (time, adjustment) = hybrid_clock.map(
+            wall + TimeDelta::seconds(130),
+            instant + Duration::from_secs(130),
+        );
+        assert_eq!(time, wall + TimeDelta::seconds(3719));
+        assert_eq!(adjustment, None);
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, bail, ensure, Context, Error};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+// history uploaded to be merged into storage of a single sink
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    // `timestamp,value` rows, optional header, timestamp as rfc3339 or unix
+    // seconds, empty value marks a gap
+    Csv,
+    // influxdb line protocol, first field of each line is used, timestamp in
+    // nanoseconds is required
+    LineProtocol,
+}
+impl Format {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next().unwrap().trim() {
+            "text/csv" => Some(Self::Csv),
+            "text/plain" => Some(Self::LineProtocol),
+            _ => None,
+        }
+    }
+}
+
+pub type Sample = (DateTime<Utc>, Option<f64>);
+
+fn value_parse(value: &str) -> Result<f64, Error> {
+    let value = match value {
+        "t" | "T" | "true" | "True" | "TRUE" => 1.0,
+        "f" | "F" | "false" | "False" | "FALSE" => 0.0,
+        value => {
+            // line protocol integers have `i` or `u` suffix
+            let value = value.trim_end_matches(['i', 'u']);
+            value.parse::<f64>().context("parse")?
+        }
+    };
+    ensure!(value.is_finite(), "value must be finite");
+    Ok(value)
+}
+
+fn csv_line_parse(line: &str) -> Result<Sample, Error> {
+    let (timestamp, value) = line
+        .split_once(',')
+        .ok_or_else(|| anyhow!("expected two columns"))?;
+    let (timestamp, value) = (timestamp.trim(), value.trim());
+
+    let timestamp = match timestamp.parse::<i64>() {
+        Ok(timestamp) => DateTime::<Utc>::from_timestamp(timestamp, 0)
+            .ok_or_else(|| anyhow!("timestamp out of range"))?,
+        Err(_) => DateTime::parse_from_rfc3339(timestamp)
+            .context("timestamp")?
+            .with_timezone(&Utc),
+    };
+    let value = match value {
+        "" => None,
+        value => Some(value_parse(value).context("value")?),
+    };
+
+    Ok((timestamp, value))
+}
+fn csv_parse(text: &str) -> Result<Vec<Sample>, Error> {
+    let mut samples = Vec::<Sample>::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match csv_line_parse(line) {
+            Ok(sample) => samples.push(sample),
+            // header row
+            Err(_) if index == 0 => {}
+            Err(error) => return Err(error.context(format!("line {}", index + 1))),
+        }
+    }
+    Ok(samples)
+}
+
+fn line_protocol_line_parse(line: &str) -> Result<Sample, Error> {
+    let mut parts = line.split_ascii_whitespace();
+    let _measurement = parts.next().unwrap();
+    let fields = parts.next().ok_or_else(|| anyhow!("missing fields"))?;
+    let timestamp = parts.next().ok_or_else(|| anyhow!("missing timestamp"))?;
+    ensure!(parts.next().is_none(), "unexpected trailing data");
+
+    let field = fields.split(',').next().unwrap();
+    let (_, value) = field
+        .split_once('=')
+        .ok_or_else(|| anyhow!("invalid field"))?;
+    let value = value_parse(value).context("value")?;
+
+    let timestamp = timestamp.parse::<i64>().context("timestamp")?;
+    let timestamp = DateTime::<Utc>::from_timestamp_nanos(timestamp);
+
+    Ok((timestamp, Some(value)))
+}
+fn line_protocol_parse(text: &str) -> Result<Vec<Sample>, Error> {
+    let mut samples = Vec::<Sample>::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let sample =
+            line_protocol_line_parse(line).with_context(|| format!("line {}", index + 1))?;
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+// returns samples ordered by timestamp
+// repeated timestamps are accepted only if they carry the same value
+pub fn parse(
+    format: Format,
+    text: &str,
+) -> Result<Box<[Sample]>, Error> {
+    let samples = match format {
+        Format::Csv => csv_parse(text).context("csv_parse")?,
+        Format::LineProtocol => line_protocol_parse(text).context("line_protocol_parse")?,
+    };
+
+    let mut samples_by_timestamp = BTreeMap::<DateTime<Utc>, Option<f64>>::new();
+    for (timestamp, value) in samples {
+        if let Some(value_previous) = samples_by_timestamp.insert(timestamp, value) {
+            if value_previous != value {
+                bail!("conflicting values for {}", timestamp);
+            }
+        }
+    }
+
+    Ok(samples_by_timestamp.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Format};
+    use chrono::{TimeZone, Utc};
+    use indoc::indoc;
+
+    #[test]
+    fn csv() {
+        let samples = parse(
+            Format::Csv,
+            indoc!(
+                "
+                timestamp,value
+                2024-01-01T00:01:00Z,21.5
+                1704067200,true
+
+                2024-01-01T00:02:00+01:00,
+                1704067200,1
+            "
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            &*samples,
+            [
+                (Utc.with_ymd_and_hms(2023, 12, 31, 23, 2, 0).unwrap(), None),
+                (
+                    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                    Some(1.0)
+                ),
+                (
+                    Utc.with_ymd_and_hms(2024, 1, 1, 0, 1, 0).unwrap(),
+                    Some(21.5)
+                ),
+            ]
+        );
+
+        assert!(parse(Format::Csv, "1704067200,1\n1704067200,0").is_err());
+        assert!(parse(Format::Csv, "1704067200,1\nyesterday,1").is_err());
+        assert!(parse(Format::Csv, "1704067200,1\n1704067201,NaN").is_err());
+    }
+
+    #[test]
+    fn line_protocol() {
+        let samples = parse(
+            Format::LineProtocol,
+            indoc!(
+                "
+                # exported
+                power,phase=L1 value=230.5,current=1.2 1704067260000000000
+                power,phase=L1 value=12i 1704067200000000000
+            "
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            &*samples,
+            [
+                (
+                    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                    Some(12.0)
+                ),
+                (
+                    Utc.with_ymd_and_hms(2024, 1, 1, 0, 1, 0).unwrap(),
+                    Some(230.5)
+                ),
+            ]
+        );
+
+        assert!(parse(Format::LineProtocol, "power value=1").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+WITH
+    `t1` AS (
+        SELECT
+            `timestamp` AS `timestamp_start`,
+            LEAD(`timestamp`) OVER(ORDER BY `timestamp`) AS `timestamp_end`,
+            CAST(`timestamp` / :timestamp_divisor AS INTEGER) * :timestamp_divisor AS `timestamp_group_start`,
+            `value`
+        FROM
+            `temp`.`import_boolean`
+    ),
+    `t2` AS (
+        SELECT
+            `timestamp_start`,
+            `timestamp_end`,
+            `timestamp_group_start`,
+            `value`,
+
+            LAST_VALUE(`timestamp_start`) OVER `w` AS `value_last_timestamp`,
+            LAST_VALUE(`value`) OVER `w` AS `value_last_value`
+        FROM
+            `t1`
+        WHERE
+            `timestamp_end` IS NOT NULL
+        WINDOW
+            `w` AS (PARTITION BY `timestamp_group_start` ORDER BY `timestamp_start` ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING)
+    ),
+    `t3` AS (
+        SELECT
+            `timestamp_group_start`,
+
+            MAX(`value_last_timestamp`) AS `value_last_timestamp`,
+            MAX(`value_last_value`) AS `value_last_value`,
+
+            SUM(IIF(`value` IS NOT NULL, `timestamp_end` - `timestamp_start`, 0)) AS `weight`,
+            SUM(IIF(`value` IS NOT NULL, (`timestamp_end` - `timestamp_start`) * `value`, 0)) AS `sum`
+        FROM
+            `t2`
+        GROUP BY
+            `timestamp_group_start`
+    )
+INSERT INTO
+    `storage_boolean`
+    (`sink_id`, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`)
+SELECT
+    :sink_id, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`
+FROM
+    `t3`
+WHERE
+    TRUE
+ON CONFLICT
+    (`sink_id`, `timestamp_group_start`)
+DO NOTHING
+;
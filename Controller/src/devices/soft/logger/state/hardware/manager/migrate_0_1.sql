@@ -0,0 +1,68 @@
+-- schema of databases created before versioning was introduced
+-- such databases are at version 0 too, so every statement must be a no-op for them
+CREATE TABLE IF NOT EXISTS `sinks` (
+    `sink_id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `name` TEXT NOT NULL,
+    `class` TEXT NOT NULL,
+    `timestamp_divisor` REAL NOT NULL,
+    `enabled` INTEGER NOT NULL
+) STRICT;
+CREATE TABLE IF NOT EXISTS `clock_adjustments` (
+    `timestamp` INTEGER NOT NULL, -- timeline position where wall clock jump was detected
+    `offset` REAL NOT NULL -- seconds, wall clock minus timeline
+) STRICT;
+CREATE TABLE IF NOT EXISTS `sinks_derived` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT UNIQUE,
+
+    `derivation` TEXT NOT NULL -- json
+) STRICT;
+CREATE TABLE IF NOT EXISTS `sinks_ext_boolean` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT UNIQUE,
+    
+    `value_last_timestamp` INTEGER NULL,
+    `value_last_value` INTEGER NULL
+) STRICT;
+CREATE TABLE IF NOT EXISTS `buffer_boolean` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    
+    `timestamp` INTEGER NOT NULL,
+    `value` INTEGER NULL
+) STRICT;
+CREATE TABLE IF NOT EXISTS `storage_boolean` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    `timestamp_group_start` INTEGER NOT NULL, -- FLOOR(timestamp / timestamp_divisor) * timestamp_divisor
+
+    `value_last_timestamp` INTEGER NOT NULL,
+    `value_last_value` INTEGER NULL,
+
+    `weight` REAL NOT NULL,
+    `sum` INTEGER NOT NULL,
+    
+    UNIQUE(`sink_id`, `timestamp_group_start`)
+) STRICT;
+CREATE TABLE IF NOT EXISTS `sinks_ext_real` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT UNIQUE,
+    
+    `value_last_timestamp` INTEGER NULL,
+    `value_last_value` REAL NULL
+) STRICT;
+CREATE TABLE IF NOT EXISTS `buffer_real` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    
+    `timestamp` INTEGER NOT NULL,
+    `value` REAL NULL
+) STRICT;
+CREATE TABLE IF NOT EXISTS `storage_real` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    `timestamp_group_start` INTEGER NOT NULL, -- FLOOR(timestamp / timestamp_divisor) * timestamp_divisor
+
+    `value_last_timestamp` INTEGER NOT NULL,
+    `value_last_value` REAL NULL,
+
+    `weight` REAL NOT NULL,
+    `sum` REAL NOT NULL,
+    `min` REAL NULL,
+    `max` REAL NULL,
+
+    UNIQUE(`sink_id`, `timestamp_group_start`)
+) STRICT;
@@ -0,0 +1,87 @@
+-- sink metadata
+ALTER TABLE `sinks` ADD COLUMN `unit` TEXT NULL;
+ALTER TABLE `sinks` ADD COLUMN `precision` INTEGER NULL;
+ALTER TABLE `sinks` ADD COLUMN `value_min` REAL NULL;
+ALTER TABLE `sinks` ADD COLUMN `value_max` REAL NULL;
+
+-- unversioned databases may have `sink_id` columns without type, tables are
+-- rebuilt with their data, as sqlite cannot alter column types
+CREATE TABLE `sinks_ext_boolean_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT UNIQUE,
+
+    `value_last_timestamp` INTEGER NULL,
+    `value_last_value` INTEGER NULL
+) STRICT;
+INSERT INTO `sinks_ext_boolean_new` (`sink_id`, `value_last_timestamp`, `value_last_value`)
+SELECT `sink_id`, `value_last_timestamp`, `value_last_value` FROM `sinks_ext_boolean`;
+DROP TABLE `sinks_ext_boolean`;
+ALTER TABLE `sinks_ext_boolean_new` RENAME TO `sinks_ext_boolean`;
+
+CREATE TABLE `buffer_boolean_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+
+    `timestamp` INTEGER NOT NULL,
+    `value` INTEGER NULL
+) STRICT;
+INSERT INTO `buffer_boolean_new` (`sink_id`, `timestamp`, `value`)
+SELECT `sink_id`, `timestamp`, `value` FROM `buffer_boolean`;
+DROP TABLE `buffer_boolean`;
+ALTER TABLE `buffer_boolean_new` RENAME TO `buffer_boolean`;
+
+CREATE TABLE `storage_boolean_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    `timestamp_group_start` INTEGER NOT NULL, -- FLOOR(timestamp / timestamp_divisor) * timestamp_divisor
+
+    `value_last_timestamp` INTEGER NOT NULL,
+    `value_last_value` INTEGER NULL,
+
+    `weight` REAL NOT NULL,
+    `sum` INTEGER NOT NULL,
+
+    UNIQUE(`sink_id`, `timestamp_group_start`)
+) STRICT;
+INSERT INTO `storage_boolean_new` (`sink_id`, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`)
+SELECT `sink_id`, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum` FROM `storage_boolean`;
+DROP TABLE `storage_boolean`;
+ALTER TABLE `storage_boolean_new` RENAME TO `storage_boolean`;
+
+CREATE TABLE `sinks_ext_real_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT UNIQUE,
+
+    `value_last_timestamp` INTEGER NULL,
+    `value_last_value` REAL NULL
+) STRICT;
+INSERT INTO `sinks_ext_real_new` (`sink_id`, `value_last_timestamp`, `value_last_value`)
+SELECT `sink_id`, `value_last_timestamp`, `value_last_value` FROM `sinks_ext_real`;
+DROP TABLE `sinks_ext_real`;
+ALTER TABLE `sinks_ext_real_new` RENAME TO `sinks_ext_real`;
+
+CREATE TABLE `buffer_real_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+
+    `timestamp` INTEGER NOT NULL,
+    `value` REAL NULL
+) STRICT;
+INSERT INTO `buffer_real_new` (`sink_id`, `timestamp`, `value`)
+SELECT `sink_id`, `timestamp`, `value` FROM `buffer_real`;
+DROP TABLE `buffer_real`;
+ALTER TABLE `buffer_real_new` RENAME TO `buffer_real`;
+
+CREATE TABLE `storage_real_new` (
+    `sink_id` INTEGER NOT NULL REFERENCES `sinks`(`sink_id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+    `timestamp_group_start` INTEGER NOT NULL, -- FLOOR(timestamp / timestamp_divisor) * timestamp_divisor
+
+    `value_last_timestamp` INTEGER NOT NULL,
+    `value_last_value` REAL NULL,
+
+    `weight` REAL NOT NULL,
+    `sum` REAL NOT NULL,
+    `min` REAL NULL,
+    `max` REAL NULL,
+
+    UNIQUE(`sink_id`, `timestamp_group_start`)
+) STRICT;
+INSERT INTO `storage_real_new` (`sink_id`, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`, `min`, `max`)
+SELECT `sink_id`, `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`, `min`, `max` FROM `storage_real`;
+DROP TABLE `storage_real`;
+ALTER TABLE `storage_real_new` RENAME TO `storage_real`;
@@ -0,0 +1,5 @@
+-- buffers are looked up by sink when sinks are removed (foreign key checks)
+-- and read ordered by sink and timestamp when moved to storage
+-- storage and sinks_ext are already covered by their unique constraints
+CREATE INDEX `buffer_boolean_sink_id_timestamp` ON `buffer_boolean` (`sink_id`, `timestamp`);
+CREATE INDEX `buffer_real_sink_id_timestamp` ON `buffer_real` (`sink_id`, `timestamp`);
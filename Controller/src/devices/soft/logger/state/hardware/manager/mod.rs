@@ -1,38 +1,105 @@
+pub mod anomaly;
+pub mod derived;
+mod hybrid_clock;
+pub mod import;
+pub mod report;
+
+use self::{
+    anomaly::{Anomaly, Rule},
+    derived::{Derivation, Derived},
+    hybrid_clock::{ClockAdjustment, HybridClock},
+    import::Sample,
+    report::{Period, Report, Row},
+};
 use super::types::{Class, TimeValue, Value};
 use crate::{
     datatypes::temperature,
-    modules::{fs::Fs, sqlite::SQLite},
+    modules::{
+        fs::Fs,
+        sqlite::{delete_in, insert_batched, InsertBatchLimits, SQLite},
+        sqlite_migrations::{
+            self,
+            cli::Database,
+            graph::{Graph, GraphResolver},
+        },
+    },
     util::{
         async_barrier::Barrier,
         async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
         async_flag,
         runnable::{Exited, Runnable},
     },
+    web::{self, uri_cursor},
 };
-use anyhow::{ensure, Context, Error};
+use anyhow::{bail, ensure, Context, Error};
 use async_trait::async_trait;
 use atomic_refcell::AtomicRefCell;
-use chrono::{DateTime, Utc};
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use crossbeam::channel;
 use futures::{
-    future::FutureExt,
+    channel::mpsc,
+    future::{BoxFuture, Future, FutureExt},
     select,
-    stream::{StreamExt, TryStreamExt},
+    stream::{Stream, StreamExt, TryStreamExt},
     try_join,
 };
 use indoc::indoc;
+use parking_lot::Mutex;
+use phf::phf_map;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
 use std::{
     collections::{HashMap, HashSet},
-    fmt,
-    rc::Rc,
-    time::Duration,
+    fmt, mem, str, thread,
+    time::{Duration, Instant},
+};
+
+// every schema change must be added here as a new version, initial schema is
+// never changed, so existing databases can be brought up to date
+static MIGRATIONS: Graph = phf_map! {
+    1u32 => phf_map! {
+        0u32 => Some(include_str!("migrate_0_1.sql")),
+    },
+    2u32 => phf_map! {
+        1u32 => Some(include_str!("migrate_1_2.sql")),
+    },
+    3u32 => phf_map! {
+        2u32 => Some(include_str!("migrate_2_3.sql")),
+    },
 };
+static MIGRATIONS_RESOLVER: GraphResolver<'static> = GraphResolver(&MIGRATIONS);
 
 pub type SinkId = usize;
-#[derive(Clone, PartialEq, Eq, Debug)]
+// describes what sink values mean, so clients don't need separate configuration
+// not used by the logger itself
+#[derive(Clone, PartialEq, Default, Debug, Serialize)]
+pub struct SinkMetadata {
+    pub unit: Option<String>,
+    pub precision: Option<u8>, // number of decimal places to display
+    pub value_min: Option<f64>,
+    pub value_max: Option<f64>,
+}
+impl SinkMetadata {
+    pub fn validate(&self) -> Result<(), Error> {
+        for value in [self.value_min, self.value_max].into_iter().flatten() {
+            ensure!(value.is_finite(), "bounds must be finite");
+        }
+        if let (Some(value_min), Some(value_max)) = (self.value_min, self.value_max) {
+            ensure!(
+                value_min <= value_max,
+                "value_min is greater than value_max"
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
 pub struct SinkDataDetails {
     pub name: String,
     pub class: Class,
+    pub metadata: SinkMetadata,
 }
 #[derive(Clone, PartialEq, Debug)]
 pub struct SinkData {
@@ -40,6 +107,9 @@ pub struct SinkData {
     pub class: Class,           // invariant
     pub timestamp_divisor: f64, // invariant
     pub enabled: bool,
+    pub metadata: SinkMetadata,
+    // derived sinks are not pushed to, their values are computed from sources
+    pub derivation: Option<Derivation>, // invariant
 }
 
 fn sink_data_compatible(
@@ -52,6 +122,9 @@ fn sink_data_compatible(
     if a.timestamp_divisor != b.timestamp_divisor {
         return false;
     }
+    if a.derivation != b.derivation {
+        return false;
+    }
 
     true
 }
@@ -62,6 +135,13 @@ pub struct SinkItem {
     pub time_value: TimeValue,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct ImportSummary {
+    pub samples: usize,
+    // storage groups created, groups already present in storage are skipped
+    pub groups: usize,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum DbClass {
     Boolean,
@@ -71,6 +151,9 @@ impl DbClass {
     pub fn from_class(class: Class) -> Self {
         match class {
             Class::Boolean => Self::Boolean,
+            // stored as number, so bad data windows can be found with range
+            // queries, see Quality::to_f64
+            Class::Quality => Self::Real,
             Class::Ratio => Self::Real,
             Class::Real => Self::Real,
             Class::Temperature => Self::Real,
@@ -88,6 +171,7 @@ impl DbValue {
     pub fn from_value(value: Value) -> Self {
         match value {
             Value::Boolean(value) => Self::Boolean(value),
+            Value::Quality(value) => Self::Real(value.map(|value| value.to_f64())),
             Value::Ratio(value) => Self::Real(value.map(|value| value.to_f64())),
             Value::Real(value) => Self::Real(value.map(|value| value.to_f64())),
             Value::Temperature(value) => {
@@ -108,6 +192,15 @@ pub struct Manager<'f> {
 
     sink_items_sender: channel::Sender<SinkItem>,
     sink_items_receiver: AtomicRefCell<channel::Receiver<SinkItem>>,
+
+    hybrid_clock: AtomicRefCell<HybridClock>,
+    derived: Mutex<Derived>,
+
+    anomaly_rules: Mutex<Box<[Rule]>>,
+    anomalies_active: AtomicRefCell<HashMap<String, bool>>,
+    anomalies_senders: Mutex<Vec<mpsc::UnboundedSender<Anomaly>>>,
+
+    reports: Mutex<HashMap<String, Report>>,
 }
 impl<'f> Manager<'f> {
     // general
@@ -115,13 +208,25 @@ impl<'f> Manager<'f> {
         name: String,
         fs: &'f Fs,
     ) -> Self {
-        let sqlite = SQLite::new(format!("logger.state.manager.{}", name), fs);
+        let sqlite = SQLite::new(Self::sqlite_name(&name), fs);
 
         let initialized = Barrier::new();
 
         let (sink_items_sender, sink_items_receiver) = channel::unbounded::<SinkItem>();
         let sink_items_receiver = AtomicRefCell::new(sink_items_receiver);
 
+        let hybrid_clock = HybridClock::new();
+        let hybrid_clock = AtomicRefCell::new(hybrid_clock);
+
+        let derived = Derived::empty();
+        let derived = Mutex::new(derived);
+
+        let anomaly_rules = Mutex::new(Box::<[Rule]>::default());
+        let anomalies_active = AtomicRefCell::new(HashMap::<String, bool>::new());
+        let anomalies_senders = Mutex::new(Vec::<mpsc::UnboundedSender<Anomaly>>::new());
+
+        let reports = Mutex::new(HashMap::<String, Report>::new());
+
         Self {
             name,
 
@@ -131,6 +236,26 @@ impl<'f> Manager<'f> {
 
             sink_items_sender,
             sink_items_receiver,
+
+            hybrid_clock,
+            derived,
+
+            anomaly_rules,
+            anomalies_active,
+            anomalies_senders,
+
+            reports,
+        }
+    }
+
+    fn sqlite_name(name: &str) -> String {
+        format!("logger.state.manager.{}", name)
+    }
+    // for running migrations before startup, see sqlite_migrations::cli
+    pub fn migrations_database(name: &str) -> Database<'static> {
+        Database {
+            name: Self::sqlite_name(name),
+            resolver: &MIGRATIONS_RESOLVER,
         }
     }
 
@@ -138,41 +263,15 @@ impl<'f> Manager<'f> {
     pub async fn sinks_data_details_get(&self) -> Result<HashMap<SinkId, SinkDataDetails>, Error> {
         self.initialized.waiter().await;
 
-        let rows = self
-            .sqlite
-            .query(
-                |connection| -> Result<_, Error> {
-                    let rows = connection
-                        .prepare(indoc!("
-                            -------------------------------------------------------------------------
-                            SELECT
-                                `sink_id`, `name`, `class`
-                            FROM
-                                `sinks`
-                            WHERE
-                                `enabled`
-                        "))?
-                        .query_map([], |row| -> rusqlite::Result<(SinkId, String, Class)> {
-                            let sink_id = row.get_ref_unwrap(0).as_i64()? as usize;
-                            let name = row.get_ref_unwrap(1).as_str()?.to_owned();
-                            let class = Class::from_string(row.get_ref_unwrap(2).as_str()?).unwrap();
-
-                            Ok((sink_id, name, class))
-                        })?.collect::<rusqlite::Result<Box<[_]>>>()?;
-
-                    Ok(rows)
-                },
-            )
-            .await
-            .context("query")?;
-
-        let sink_data = rows
-            .into_vec()
-            .into_iter()
-            .map(|(sink_id, name, class)| (sink_id, SinkDataDetails { name, class }))
-            .collect::<HashMap<_, _>>();
-
-        Ok(sink_data)
+        self.sinks_data_details_query().await
+    }
+    fn sinks_data_details_query(
+        &self
+    ) -> impl Future<Output = Result<HashMap<SinkId, SinkDataDetails>, Error>> + Send + 'static
+    {
+        self.sqlite.query(|connection| -> Result<_, Error> {
+            Self::sql_sinks_data_details_get(connection).context("sql_sinks_data_details_get")
+        })
     }
     pub async fn sinks_data_set(
         &self,
@@ -183,6 +282,47 @@ impl<'f> Manager<'f> {
 
         self.initialized.waiter().await;
 
+        for (sink_id, sink_data) in sinks_data.iter() {
+            sink_data
+                .metadata
+                .validate()
+                .with_context(|| format!("sink #{} - metadata", sink_id))?;
+        }
+
+        // derivations may refer only to real sinks being set
+        for (sink_id, sink_data) in sinks_data.iter() {
+            let derivation = match &sink_data.derivation {
+                Some(derivation) => derivation,
+                None => continue,
+            };
+
+            derivation
+                .validate()
+                .with_context(|| format!("sink #{} - derivation", sink_id))?;
+            ensure!(
+                DbClass::from_class(sink_data.class) == DbClass::Real,
+                "sink #{} - derived sink must be real",
+                sink_id
+            );
+            for source in derivation.sources().into_vec() {
+                let source_data = sinks_data
+                    .get(&source)
+                    .with_context(|| format!("sink #{} - source #{} not found", sink_id, source))?;
+                ensure!(
+                    DbClass::from_class(source_data.class) == DbClass::Real,
+                    "sink #{} - source #{} must be real",
+                    sink_id,
+                    source
+                );
+                ensure!(
+                    source_data.derivation.is_none(),
+                    "sink #{} - source #{} must not be derived",
+                    sink_id,
+                    source
+                );
+            }
+        }
+
         let sinks_data_current = self.db_sinks_data_get().await.context("sinks_data_set")?;
 
         // remove no longer existing items
@@ -223,6 +363,10 @@ impl<'f> Manager<'f> {
                 .context("db_sinks_upsert")?;
         }
 
+        self.db_derived_reload()
+            .await
+            .context("db_derived_reload")?;
+
         Ok(())
     }
 
@@ -230,6 +374,107 @@ impl<'f> Manager<'f> {
         self.sink_items_sender.clone()
     }
 
+    // anomalies
+    pub fn anomaly_rules_set(
+        &self,
+        rules: Box<[Rule]>,
+    ) -> Result<(), Error> {
+        let mut names = HashSet::<&str>::new();
+        for rule in rules.iter() {
+            rule.validate()
+                .with_context(|| format!("rule {}", rule.name))?;
+            ensure!(
+                names.insert(&rule.name),
+                "rule {} - duplicated name",
+                rule.name
+            );
+        }
+
+        *self.anomaly_rules.lock() = rules;
+
+        Ok(())
+    }
+    // receives rule activations and deactivations
+    pub fn anomalies_subscribe(&self) -> mpsc::UnboundedReceiver<Anomaly> {
+        let (sender, receiver) = mpsc::unbounded::<Anomaly>();
+        self.anomalies_senders.lock().push(sender);
+        receiver
+    }
+
+    // reports
+    pub fn reports_set(
+        &self,
+        reports: HashMap<String, Report>,
+    ) -> Result<(), Error> {
+        for (name, report) in reports.iter() {
+            ensure!(
+                report.scale.is_finite() && report.scale > 0.0,
+                "report {} - scale must be positive",
+                name
+            );
+        }
+
+        *self.reports.lock() = reports;
+
+        Ok(())
+    }
+    pub fn report(
+        &self,
+        name: &str,
+        period: Period,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Future<Output = Result<Box<[Row]>, Error>> + Send + 'static {
+        let report = self.reports.lock().get(name).cloned();
+        let name = name.to_owned();
+
+        self.sqlite.query(move |connection| -> Result<_, Error> {
+            let report = report.with_context(|| format!("report {} not found", name))?;
+            ensure!(from < to, "empty range");
+
+            let (from_utc, to_utc) = report::range(&report, from, to);
+            let groups = Self::sql_report_groups(connection, report.sink_id, from_utc, to_utc)
+                .context("sql_report_groups")?;
+            let rows =
+                report::aggregate(&report, period, from, to, &groups).context("aggregate")?;
+
+            Ok(rows)
+        })
+    }
+    fn report_parse(request: &web::Request) -> Result<(Period, NaiveDate, NaiveDate, bool), Error> {
+        let mut period = None::<Period>;
+        let mut from = None::<NaiveDate>;
+        let mut to = None::<NaiveDate>;
+        let mut csv = false;
+        for (key, value) in form_urlencoded::parse(request.uri().query().unwrap_or("").as_bytes()) {
+            match key.as_ref() {
+                "period" => {
+                    period = Some(Period::from_string(&value).context("period")?);
+                }
+                "from" => {
+                    from = Some(value.parse().context("from")?);
+                }
+                "to" => {
+                    to = Some(value.parse().context("to")?);
+                }
+                "format" => {
+                    csv = match value.as_ref() {
+                        "json" => false,
+                        "csv" => true,
+                        _ => bail!("unsupported format: {}", value),
+                    };
+                }
+                _ => bail!("unexpected parameter: {}", key),
+            }
+        }
+
+        let period = period.context("period missing")?;
+        let from = from.context("from missing")?;
+        let to = to.context("to missing")?;
+
+        Ok((period, from, to, csv))
+    }
+
     // lifecycle methods
     async fn run(
         &self,
@@ -284,6 +529,9 @@ impl<'f> Manager<'f> {
 
     async fn initialize_once(&self) -> Result<(), Error> {
         self.db_initialize().await.context("db_initialize")?;
+        self.db_derived_reload()
+            .await
+            .context("db_derived_reload")?;
 
         self.initialized.release();
 
@@ -294,13 +542,20 @@ impl<'f> Manager<'f> {
         exit_flag: async_flag::Receiver,
     ) -> Result<Exited, Error> {
         let sink_items_to_buffer_to_storage_runner =
-            self.sink_items_to_buffer_to_storage_run(exit_flag);
+            self.sink_items_to_buffer_to_storage_run(exit_flag.clone());
+        let anomalies_runner = self.anomalies_run(exit_flag);
 
-        let _: (Exited,) = try_join!(sink_items_to_buffer_to_storage_runner).context("try_join")?;
+        let _: (Exited, Exited) =
+            try_join!(sink_items_to_buffer_to_storage_runner, anomalies_runner)
+                .context("try_join")?;
 
         Ok(Exited)
     }
     async fn finalize_once(&self) -> Result<(), Error> {
+        // sinks are finalized before manager, so this takes their last values
+        self.db_sink_items_to_buffer_to_storage()
+            .await
+            .context("db_sink_items_to_buffer_to_storage")?;
         self.db_finalize().await.context("db_finalize")?;
 
         Ok(())
@@ -328,6 +583,72 @@ impl<'f> Manager<'f> {
 
         Ok(Exited)
     }
+    const ANOMALIES_INTERVAL: Duration = Duration::from_secs(300);
+    async fn anomalies_run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Result<Exited, Error> {
+        tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(
+            Self::ANOMALIES_INTERVAL,
+        ))
+        .stream_take_until_exhausted(exit_flag)
+        .for_each(async |_| {
+            // analysis failures must not stop data collection
+            if let Err(error) = self.anomalies_check().await.context("anomalies_check") {
+                log::error!("{}: {:?}", self, error);
+            }
+        })
+        .await;
+
+        Ok(Exited)
+    }
+    async fn anomalies_check(&self) -> Result<(), Error> {
+        let rules = self.anomaly_rules.lock().clone();
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let period = TimeDelta::from_std(Self::ANOMALIES_INTERVAL).unwrap();
+        let results = self
+            .sqlite
+            .query(move |connection| -> Result<_, Error> {
+                rules
+                    .iter()
+                    .map(|rule| -> Result<_, Error> {
+                        let active = Self::sql_anomaly_rule_evaluate(connection, rule, now, period)
+                            .with_context(|| format!("rule {}", rule.name))?;
+                        Ok((rule.name.clone(), active))
+                    })
+                    .collect::<Result<Box<[_]>, _>>()
+            })
+            .await
+            .context("query")?;
+
+        let mut anomalies_active = self.anomalies_active.borrow_mut();
+        let mut anomalies = Vec::<Anomaly>::new();
+        for (name, active) in results.into_vec() {
+            let active_previous = anomalies_active.insert(name.clone(), active);
+            if active_previous.unwrap_or(false) == active {
+                continue;
+            }
+            if active {
+                log::warn!("{}: anomaly detected: {}", self, name);
+            }
+            anomalies.push(Anomaly { rule: name, active });
+        }
+        drop(anomalies_active);
+
+        if !anomalies.is_empty() {
+            self.anomalies_senders.lock().retain(|sender| {
+                anomalies
+                    .iter()
+                    .all(|anomaly| sender.unbounded_send(anomaly.clone()).is_ok())
+            });
+        }
+
+        Ok(())
+    }
 
     // db methods
     async fn db_initialize(&self) -> Result<(), Error> {
@@ -344,6 +665,16 @@ impl<'f> Manager<'f> {
         Ok(())
     }
     async fn db_sinks_data_get(&self) -> Result<HashMap<SinkId, SinkData>, Error> {
+        type SinkDataRow = (
+            SinkId,
+            String,
+            Class,
+            f64,
+            bool,
+            SinkMetadata,
+            Option<String>,
+        );
+
         let sinks_data = self
             .sqlite
             .query(|connection| -> Result<_, Error> {
@@ -351,22 +682,28 @@ impl<'f> Manager<'f> {
                     .prepare(indoc!("
                         -----------------------------------------------------------------------------
                         SELECT
-                            `sink_id`, `name`, `class`, `timestamp_divisor`, `enabled`
+                            `sink_id`, `name`, `class`, `timestamp_divisor`, `enabled`,
+                            `unit`, `precision`, `value_min`, `value_max`,
+                            `derivation`
                         FROM
                             `sinks`
+                        LEFT JOIN
+                            `sinks_derived` USING(`sink_id`)
                     "
                     ))?
                     .query_map(
                         [],
-                        |row| -> rusqlite::Result<(SinkId, String, Class, f64, bool)> {
+                        |row| -> rusqlite::Result<SinkDataRow> {
                             let sink_id = row.get_ref_unwrap(0).as_i64()? as usize;
                             let name = row.get_ref_unwrap(1).as_str()?.to_owned();
                             let class =
                                 Class::from_string(row.get_ref_unwrap(2).as_str()?).unwrap();
                             let timestamp_divisor = row.get_ref_unwrap(3).as_f64()?;
                             let enabled = row.get_ref_unwrap(4).as_i64()? != 0;
+                            let metadata = Self::sql_sink_metadata_from_row(row, 5)?;
+                            let derivation = row.get_ref_unwrap(9).as_str_or_null()?.map(str::to_owned);
 
-                            Ok((sink_id, name, class, timestamp_divisor, enabled))
+                            Ok((sink_id, name, class, timestamp_divisor, enabled, metadata, derivation))
                         },
                     )?
                     .collect::<rusqlite::Result<Box<[_]>>>()?;
@@ -379,16 +716,25 @@ impl<'f> Manager<'f> {
         let sinks_data = sinks_data
             .into_vec()
             .into_iter()
-            .map(|(sink_id, name, class, timestamp_divisor, enabled)| {
-                let sink_data = SinkData {
-                    name,
-                    class,
-                    timestamp_divisor,
-                    enabled,
-                };
-                (sink_id, sink_data)
-            })
-            .collect::<HashMap<_, _>>();
+            .map(
+                |(sink_id, name, class, timestamp_divisor, enabled, metadata, derivation)| -> Result<_, Error> {
+                    let derivation = derivation
+                        .map(|derivation| serde_json::from_str::<Derivation>(&derivation))
+                        .transpose()
+                        .context("derivation")?;
+
+                    let sink_data = SinkData {
+                        name,
+                        class,
+                        timestamp_divisor,
+                        enabled,
+                        metadata,
+                        derivation,
+                    };
+                    Ok((sink_id, sink_data))
+                },
+            )
+            .collect::<Result<HashMap<_, _>, _>>()?;
 
         Ok(sinks_data)
     }
@@ -430,6 +776,19 @@ impl<'f> Manager<'f> {
 
         Ok(())
     }
+    async fn db_derived_reload(&self) -> Result<(), Error> {
+        let derived = self
+            .sqlite
+            .query(|connection| -> Result<_, Error> {
+                Self::sql_derived_get(connection).context("sql_derived_get")
+            })
+            .await
+            .context("query")?;
+
+        *self.derived.lock() = derived;
+
+        Ok(())
+    }
     async fn db_sink_items_to_buffer_to_storage(&self) -> Result<(), Error> {
         let sink_items_receiver = self.sink_items_receiver.borrow();
 
@@ -440,13 +799,26 @@ impl<'f> Manager<'f> {
         // split by storage type
         let mut items_boolean = Vec::<(SinkId, DateTime<Utc>, Option<bool>)>::new();
         let mut items_real = Vec::<(SinkId, DateTime<Utc>, Option<f64>)>::new();
+        let mut clock_adjustments = Vec::<ClockAdjustment>::new();
 
+        let mut hybrid_clock = self.hybrid_clock.borrow_mut();
         while let Ok(sink_item) = sink_items_receiver.try_recv() {
             let SinkItem {
                 sink_id,
-                time_value: TimeValue { time, value },
+                time_value:
+                    TimeValue {
+                        time,
+                        instant,
+                        value,
+                    },
             } = sink_item;
 
+            let (time, clock_adjustment) = hybrid_clock.map(time, instant);
+            if let Some(clock_adjustment) = clock_adjustment {
+                log::warn!("{}: wall clock jump detected: {:?}", self, clock_adjustment);
+                clock_adjustments.push(clock_adjustment);
+            }
+
             let value = DbValue::from_value(value);
 
             match value {
@@ -455,62 +827,89 @@ impl<'f> Manager<'f> {
             }
         }
 
+        drop(hybrid_clock);
+
+        // derived sinks are materialized together with their sources
+        let items_derived = {
+            let mut derived = self.derived.lock();
+            items_real
+                .iter()
+                .flat_map(|(sink_id, time, value)| {
+                    let time = *time;
+                    derived
+                        .push(*sink_id, *value)
+                        .into_iter()
+                        .map(move |(sink_id, value)| (sink_id, time, value))
+                })
+                .collect::<Vec<_>>()
+        };
+        items_real.extend(items_derived);
+
         let mut sink_any = false;
 
+        // clock adjustments
+        if !clock_adjustments.is_empty() {
+            self.sqlite
+                .transaction(move |transaction| -> Result<(), Error> {
+                    Self::sql_clock_adjustments_insert(transaction, &clock_adjustments)
+                        .context("sql_clock_adjustments_insert")?;
+
+                    Ok(())
+                })
+                .await
+                .context("transaction")??;
+        }
+
         // boolean
         if !items_boolean.is_empty() {
-            self.sqlite.transaction(|transaction| -> Result<(), Error> {
-                let mut statement = transaction
-                    .prepare(indoc!("
-                        ---------------------------------------------------------------------------------
-                        INSERT INTO
-                            `buffer_boolean` (`sink_id`, `timestamp`, `value`)
-                        VALUES
-                            (?, ?, ?)
-                    "))
-                    .context("prepare")?;
-
-                for (sink_id, time, value) in items_boolean {
-                    let params = rusqlite::params![
-                        sink_id,
-                        time.timestamp(),
-                        value,
-                    ];
-
-                    statement.insert(params).context("execute")?;
-                }
+            self.sqlite
+                .transaction(|transaction| -> Result<(), Error> {
+                    let rows = items_boolean.into_iter().map(|(sink_id, time, value)| {
+                        [
+                            rusqlite::types::Value::from(sink_id as i64),
+                            rusqlite::types::Value::from(time.timestamp()),
+                            rusqlite::types::Value::from(value),
+                        ]
+                    });
+                    insert_batched(
+                        transaction,
+                        "INSERT INTO `buffer_boolean` (`sink_id`, `timestamp`, `value`)",
+                        rows,
+                        InsertBatchLimits::default(),
+                    )
+                    .context("insert_batched")?;
 
-                Ok(())
-            }).await.context("transaction")??;
+                    Ok(())
+                })
+                .await
+                .context("transaction")??;
 
             sink_any = true;
         }
 
         // real
         if !items_real.is_empty() {
-            self.sqlite.transaction(|transaction| -> Result<(), Error> {
-                let mut statement = transaction
-                    .prepare(indoc!("
-                        ---------------------------------------------------------------------------------
-                        INSERT INTO
-                            `buffer_real` (`sink_id`, `timestamp`, `value`)
-                        VALUES
-                            (?, ?, ?)
-                    "))
-                    .context("prepare")?;
-
-                for (sink_id, time, value) in items_real {
-                    let params = rusqlite::params![
-                        sink_id,
-                        time.timestamp(),
-                        value,
-                    ];
-
-                    statement.insert(params).context("execute")?;
-                }
+            self.sqlite
+                .transaction(|transaction| -> Result<(), Error> {
+                    let rows = items_real.into_iter().map(|(sink_id, time, value)| {
+                        [
+                            rusqlite::types::Value::from(sink_id as i64),
+                            rusqlite::types::Value::from(time.timestamp()),
+                            rusqlite::types::Value::from(value),
+                        ]
+                    });
+                    insert_batched(
+                        transaction,
+                        "INSERT INTO `buffer_real` (`sink_id`, `timestamp`, `value`)",
+                        rows,
+                        InsertBatchLimits::default(),
+                    )
+                    .context("insert_batched")?;
 
-                Ok(())
-            }).await.context("transaction")??;
+                    Ok(())
+                })
+                .await
+                .context("transaction")??;
 
             sink_any = true;
         }
@@ -530,9 +929,14 @@ impl<'f> Manager<'f> {
         Ok(())
     }
     async fn db_finalize(&self) -> Result<(), Error> {
+        let (now, _) = self
+            .hybrid_clock
+            .borrow_mut()
+            .map(Utc::now(), Instant::now());
+
         self.sqlite
-            .transaction(|transaction| -> Result<(), Error> {
-                Self::sql_buffer_finalize_with_nulls(transaction)
+            .transaction(move |transaction| -> Result<(), Error> {
+                Self::sql_buffer_finalize_with_nulls(transaction, now)
                     .context("sql_buffer_finalize_with_nulls")?;
 
                 Self::sql_buffer_to_storage(transaction) // break
@@ -546,23 +950,119 @@ impl<'f> Manager<'f> {
         Ok(())
     }
 
-    // sql wrappers
-    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
-        // creates the tables
+    // export
+    const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+    const EXPORT_CHUNKS_BUFFERED: usize = 16;
+    // longest time database thread waits for the client to take a chunk
+    const EXPORT_SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+    // storage rows as csv, produced on database thread and streamed in chunks
+    // database thread is shared with buffer flushes, so it is never blocked on
+    // the client for long, export of client not keeping up is aborted (response
+    // is cut short) instead
+    pub fn sink_storage_csv_stream(
+        &self,
+        sink_id: SinkId,
+    ) -> impl Stream<Item = Bytes> + Send + Sync + 'static {
+        let (sender, receiver) = mpsc::channel::<Bytes>(Self::EXPORT_CHUNKS_BUFFERED);
+
+        // operation is queued immediately, result is not needed
+        let name = self.name.clone();
+        drop(self.sqlite.query(move |connection| {
+            if let Err(error) = Self::sql_sink_storage_csv(connection, sink_id, sender) {
+                log::error!("Manager({}): {:?}", name, error);
+            }
+        }));
 
-        transaction
-            .execute_batch(include_str!("initialize.sql"))
-            .context("initialize")?;
+        receiver
+    }
 
-        transaction
-            .execute_batch(include_str!("initialize_boolean.sql"))
-            .context("initialize_boolean")?;
-        transaction
-            .execute_batch(include_str!("initialize_real.sql"))
-            .context("initialize_real")?;
+    // import
+    // samples are aggregated directly into storage, bypassing the buffer, so
+    // they must precede values already received by the sink
+    fn import_parse(request: &web::Request) -> Result<Box<[Sample]>, Error> {
+        let content_type = request
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|header| header.to_str().ok())
+            .unwrap_or("");
+        let format = import::Format::from_content_type(content_type)
+            .with_context(|| format!("unsupported content type: {:?}", content_type))?;
+
+        let text = str::from_utf8(request.body_payload()).context("from_utf8")?;
+        let samples = import::parse(format, text).context("parse")?;
+
+        Ok(samples)
+    }
+    pub fn sink_import(
+        &self,
+        sink_id: SinkId,
+        samples: Box<[Sample]>,
+    ) -> impl Future<Output = Result<ImportSummary, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_sink_import(transaction, sink_id, &samples).context("sql_sink_import")
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // sql wrappers
+    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+        // creates or migrates the tables
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, transaction).context("execute")?;
 
         Ok(())
     }
+    fn sql_sinks_data_details_get(
+        connection: &rusqlite::Connection
+    ) -> Result<HashMap<SinkId, SinkDataDetails>, Error> {
+        let sinks_data_details = connection
+            .prepare(indoc!("
+                -------------------------------------------------------------------------------------
+                SELECT
+                    `sink_id`, `name`, `class`,
+                    `unit`, `precision`, `value_min`, `value_max`
+                FROM
+                    `sinks`
+                WHERE
+                    `enabled`
+                    AND `sink_id` NOT IN (SELECT `sink_id` FROM `sinks_derived`)
+            "))
+            .context("prepare")?
+            .query_map([], |row| -> rusqlite::Result<(SinkId, SinkDataDetails)> {
+                let sink_id = row.get_ref_unwrap(0).as_i64()? as usize;
+                let name = row.get_ref_unwrap(1).as_str()?.to_owned();
+                let class = Class::from_string(row.get_ref_unwrap(2).as_str()?).unwrap();
+                let metadata = Self::sql_sink_metadata_from_row(row, 3)?;
+
+                Ok((sink_id, SinkDataDetails { name, class, metadata }))
+            })
+            .context("query_map")?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .context("collect")?;
+
+        Ok(sinks_data_details)
+    }
+    // reads `unit`, `precision`, `value_min`, `value_max` starting at offset
+    fn sql_sink_metadata_from_row(
+        row: &rusqlite::Row,
+        offset: usize,
+    ) -> rusqlite::Result<SinkMetadata> {
+        let unit = row
+            .get_ref_unwrap(offset)
+            .as_str_or_null()?
+            .map(str::to_owned);
+        let precision = row.get::<_, Option<u8>>(offset + 1)?;
+        let value_min = row.get_ref_unwrap(offset + 2).as_f64_or_null()?;
+        let value_max = row.get_ref_unwrap(offset + 3).as_f64_or_null()?;
+
+        Ok(SinkMetadata {
+            unit,
+            precision,
+            value_min,
+            value_max,
+        })
+    }
     fn sql_sinks_remove(
         transaction: &rusqlite::Transaction,
         sink_ids: HashSet<SinkId>,
@@ -574,107 +1074,27 @@ impl<'f> Manager<'f> {
         let sink_ids = sink_ids
             .into_iter()
             .map(|sink_id| rusqlite::types::Value::Integer(sink_id as i64))
-            .collect::<Vec<_>>();
-        let sink_ids = Rc::new(sink_ids);
-
-        let params = rusqlite::named_params! {
-            ":sink_ids": sink_ids,
-        };
-
-        // storage
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `storage_boolean`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `storage_real`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        // buffer
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `buffer_boolean`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `buffer_real`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        // sink_ext
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `sinks_ext_boolean`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `sinks_ext_real`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
-
-        // sinks
-        transaction
-            .execute(
-                indoc!("
-                    ---------------------------------------------------------------------------------
-                    DELETE FROM
-                        `sinks`
-                    WHERE
-                        `sink_id` IN rarray(:sink_ids)
-                "),
-                params,
-            )
-            .context("execute")?;
+            .collect::<Box<[_]>>();
+
+        // dependent tables first, `sinks` last
+        for table in [
+            // storage
+            "storage_boolean",
+            "storage_real",
+            // buffer
+            "buffer_boolean",
+            "buffer_real",
+            // sink_ext
+            "sinks_ext_boolean",
+            "sinks_ext_real",
+            // sinks_derived
+            "sinks_derived",
+            // sinks
+            "sinks",
+        ] {
+            delete_in(transaction, table, "sink_id", &sink_ids)
+                .with_context(|| format!("delete_in {}", table))?;
+        }
 
         Ok(())
     }
@@ -692,14 +1112,24 @@ impl<'f> Manager<'f> {
                 -------------------------------------------------------------------------------------
                 INSERT INTO
                     `sinks`
-                    (`sink_id`, `name`, `class`, `timestamp_divisor`, `enabled`)
+                    (
+                        `sink_id`, `name`, `class`, `timestamp_divisor`, `enabled`,
+                        `unit`, `precision`, `value_min`, `value_max`
+                    )
                 VALUES
-                    (:sink_id, :name, :class, :timestamp_divisor, :enabled)
+                    (
+                        :sink_id, :name, :class, :timestamp_divisor, :enabled,
+                        :unit, :precision, :value_min, :value_max
+                    )
                 ON CONFLICT
                     (`sink_id`)
                 DO UPDATE SET
                     `name` = EXCLUDED.`name`,
-                    `enabled` = EXCLUDED.`enabled`
+                    `enabled` = EXCLUDED.`enabled`,
+                    `unit` = EXCLUDED.`unit`,
+                    `precision` = EXCLUDED.`precision`,
+                    `value_min` = EXCLUDED.`value_min`,
+                    `value_max` = EXCLUDED.`value_max`
             "))
             .context("prepare")?;
 
@@ -710,6 +1140,10 @@ impl<'f> Manager<'f> {
                 ":class": sink_data.class.to_string(),
                 ":timestamp_divisor": sink_data.timestamp_divisor,
                 ":enabled": sink_data.enabled,
+                ":unit": sink_data.metadata.unit,
+                ":precision": sink_data.metadata.precision,
+                ":value_min": sink_data.metadata.value_min,
+                ":value_max": sink_data.metadata.value_max,
             };
             query.execute(params).context("execute")?;
         }
@@ -781,13 +1215,51 @@ impl<'f> Manager<'f> {
             }
         }
 
+        // sinks_derived
+        let sinks_derived = sinks_data
+            .iter()
+            .filter_map(|(sink_id, sink_data)| {
+                sink_data
+                    .derivation
+                    .as_ref()
+                    .map(|derivation| (sink_id, derivation))
+            })
+            .collect::<Box<[_]>>();
+        if !sinks_derived.is_empty() {
+            let mut query = transaction
+                .prepare(indoc!("
+                    ---------------------------------------------------------------------------------
+                    INSERT INTO
+                        `sinks_derived`
+                        (`sink_id`, `derivation`)
+                    VALUES
+                        (:sink_id, :derivation)
+                    ON CONFLICT
+                        (`sink_id`)
+                    DO NOTHING
+                "))
+                .context("prepare")?;
+
+            for (sink_id, derivation) in sinks_derived.iter() {
+                let derivation = serde_json::to_string(derivation).context("to_string")?;
+                let params = rusqlite::named_params! {
+                    ":sink_id": **sink_id,
+                    ":derivation": derivation,
+                };
+                query.execute(params).context("execute")?;
+            }
+        }
+
         Ok(())
     }
-    fn sql_buffer_finalize_with_nulls(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+    fn sql_buffer_finalize_with_nulls(
+        transaction: &rusqlite::Transaction,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
         // appends "null" value to each buffer at current time point
 
         let params = rusqlite::named_params! {
-            ":now": Utc::now().timestamp(),
+            ":now": now.timestamp(),
         };
 
         transaction
@@ -806,6 +1278,348 @@ impl<'f> Manager<'f> {
 
         Ok(())
     }
+    fn sql_clock_adjustments_insert(
+        transaction: &rusqlite::Transaction,
+        clock_adjustments: &[ClockAdjustment],
+    ) -> Result<(), Error> {
+        let mut statement = transaction
+            .prepare_cached("INSERT INTO `clock_adjustments` (`timestamp`, `offset`) VALUES (?, ?)")
+            .context("prepare_cached")?;
+
+        for clock_adjustment in clock_adjustments {
+            statement
+                .execute(rusqlite::params![
+                    clock_adjustment.time.timestamp(),
+                    clock_adjustment.offset.num_milliseconds() as f64 / 1000.0,
+                ])
+                .context("execute")?;
+        }
+
+        Ok(())
+    }
+    fn sql_derived_get(connection: &rusqlite::Connection) -> Result<Derived, Error> {
+        // derivations of enabled sinks
+        let derivations = connection
+            .prepare(indoc!("
+                -------------------------------------------------------------------------------------
+                SELECT
+                    `sink_id`, `derivation`
+                FROM
+                    `sinks_derived`
+                JOIN
+                    `sinks` USING(`sink_id`)
+                WHERE
+                    `enabled`
+            "))
+            .context("prepare")?
+            .query_map([], |row| -> rusqlite::Result<(SinkId, String)> {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .context("query_map")?
+            .map(|row| -> Result<_, Error> {
+                let (sink_id, derivation) = row.context("row")?;
+                let derivation =
+                    serde_json::from_str::<Derivation>(&derivation).context("from_str")?;
+                Ok((sink_id, derivation))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("collect")?;
+
+        // last stored values of sources, so derived values are available
+        // before all sources report after restart
+        let values = connection
+            .prepare(indoc!("
+                -------------------------------------------------------------------------------------
+                SELECT
+                    `sink_id`, `value_last_value`
+                FROM
+                    `sinks_ext_real`
+                WHERE
+                    `value_last_value` IS NOT NULL
+            "))
+            .context("prepare")?
+            .query_map([], |row| -> rusqlite::Result<(SinkId, f64)> {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .context("query_map")?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .context("collect")?;
+
+        let derived = Derived::new(derivations, values);
+        Ok(derived)
+    }
+    fn sql_sink_import(
+        transaction: &rusqlite::Transaction,
+        sink_id: SinkId,
+        samples: &[Sample],
+    ) -> Result<ImportSummary, Error> {
+        let (class, timestamp_divisor, derived) = transaction
+            .query_row(
+                indoc!("
+                    SELECT
+                        `class`, `timestamp_divisor`, `sink_id` IN (SELECT `sink_id` FROM `sinks_derived`)
+                    FROM
+                        `sinks`
+                    WHERE
+                        `sink_id` = ?
+                "),
+                [sink_id],
+                |row| -> rusqlite::Result<(String, f64, bool)> {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                },
+            )
+            .optional()
+            .context("query_row")?
+            .with_context(|| format!("sink #{} not found", sink_id))?;
+        let class = Class::from_string(&class).context("from_string")?;
+        let db_class = DbClass::from_class(class);
+        ensure!(!derived, "sink #{} is derived", sink_id);
+
+        let (sinks_ext, import, import_to_storage) = match db_class {
+            DbClass::Boolean => {
+                for (timestamp, value) in samples {
+                    ensure!(
+                        matches!(value, None | Some(0.0) | Some(1.0)),
+                        "{}: boolean sink accepts only 0 and 1",
+                        timestamp
+                    );
+                }
+                (
+                    "sinks_ext_boolean",
+                    "import_boolean",
+                    include_str!("import_to_storage_boolean.sql"),
+                )
+            }
+            DbClass::Real => (
+                "sinks_ext_real",
+                "import_real",
+                include_str!("import_to_storage_real.sql"),
+            ),
+        };
+
+        let value_last_timestamp = transaction
+            .query_row(
+                &format!(
+                    "SELECT `value_last_timestamp` FROM `{}` WHERE `sink_id` = ?",
+                    sinks_ext
+                ),
+                [sink_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .context("query_row")?;
+        if let (Some(value_last_timestamp), Some((timestamp, _))) =
+            (value_last_timestamp, samples.last())
+        {
+            ensure!(
+                timestamp.timestamp() < value_last_timestamp,
+                "{}: samples must precede values already stored",
+                timestamp
+            );
+        }
+
+        transaction
+            .execute_batch(&format!(
+                indoc!(
+                    "
+                    CREATE TEMP TABLE IF NOT EXISTS `{0}` (
+                        `timestamp` INTEGER NOT NULL,
+                        `value` {1} NULL
+                    ) STRICT;
+                    DELETE FROM `temp`.`{0}`;
+                "
+                ),
+                import,
+                match db_class {
+                    DbClass::Boolean => "INTEGER",
+                    DbClass::Real => "REAL",
+                },
+            ))
+            .context("initialize")?;
+
+        let rows = samples.iter().map(|(timestamp, value)| {
+            let value = match db_class {
+                DbClass::Boolean => rusqlite::types::Value::from(value.map(|value| value as i64)),
+                DbClass::Real => rusqlite::types::Value::from(*value),
+            };
+            [rusqlite::types::Value::from(timestamp.timestamp()), value]
+        });
+        insert_batched(
+            transaction,
+            &format!("INSERT INTO `temp`.`{}` (`timestamp`, `value`)", import),
+            rows,
+            InsertBatchLimits::default(),
+        )
+        .context("insert_batched")?;
+
+        let params = rusqlite::named_params! {
+            ":sink_id": sink_id,
+            ":timestamp_divisor": timestamp_divisor,
+        };
+        let groups = transaction
+            .execute(import_to_storage, params)
+            .context("import_to_storage")?;
+
+        transaction
+            .execute(&format!("DELETE FROM `temp`.`{}`", import), [])
+            .context("execute")?;
+
+        Ok(ImportSummary {
+            samples: samples.len(),
+            groups,
+        })
+    }
+    fn sql_anomaly_rule_evaluate(
+        connection: &rusqlite::Connection,
+        rule: &Rule,
+        now: DateTime<Utc>,
+        period: TimeDelta,
+    ) -> Result<bool, Error> {
+        let (class, timestamp_divisor) = connection
+            .query_row(
+                "SELECT `class`, `timestamp_divisor` FROM `sinks` WHERE `sink_id` = ?",
+                [rule.sink_id],
+                |row| -> rusqlite::Result<(String, f64)> { Ok((row.get(0)?, row.get(1)?)) },
+            )
+            .optional()
+            .context("query_row")?
+            .with_context(|| format!("sink #{} not found", rule.sink_id))?;
+        let class = Class::from_string(&class).context("from_string")?;
+        ensure!(
+            DbClass::from_class(class) == DbClass::Real,
+            "sink #{} must be real",
+            rule.sink_id
+        );
+
+        let ranges = anomaly::ranges(&rule.check, now, period);
+        let stats = ranges
+            .iter()
+            .map(|(from, to)| anomaly::sql_stats(connection, rule.sink_id, *from, *to))
+            .collect::<Result<Box<[_]>, _>>()
+            .context("sql_stats")?;
+
+        let active = anomaly::evaluate(&rule.check, &ranges, &stats, timestamp_divisor);
+        Ok(active)
+    }
+    fn sql_report_groups(
+        connection: &rusqlite::Connection,
+        sink_id: SinkId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Box<[report::Group]>, Error> {
+        let class = connection
+            .query_row(
+                "SELECT `class` FROM `sinks` WHERE `sink_id` = ?",
+                [sink_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .context("query_row")?
+            .with_context(|| format!("sink #{} not found", sink_id))?;
+        let class = Class::from_string(&class).context("from_string")?;
+        ensure!(
+            DbClass::from_class(class) == DbClass::Real,
+            "sink #{} must be real",
+            sink_id
+        );
+
+        let groups = report::sql_groups(connection, sink_id, from, to).context("sql_groups")?;
+        Ok(groups)
+    }
+    // returns false if client disconnected
+    fn sql_sink_storage_csv_send(
+        sender: &mut mpsc::Sender<Bytes>,
+        mut chunk: Bytes,
+    ) -> Result<bool, Error> {
+        let deadline = Instant::now() + Self::EXPORT_SEND_TIMEOUT;
+        loop {
+            match sender.try_send(chunk) {
+                Ok(()) => return Ok(true),
+                Err(error) if error.is_disconnected() => return Ok(false),
+                Err(error) => {
+                    ensure!(
+                        Instant::now() < deadline,
+                        "client not receiving, export aborted"
+                    );
+                    chunk = error.into_inner();
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        }
+    }
+    fn sql_sink_storage_csv(
+        connection: &rusqlite::Connection,
+        sink_id: SinkId,
+        mut sender: mpsc::Sender<Bytes>,
+    ) -> Result<(), Error> {
+        let class = connection
+            .query_row(
+                "SELECT `class` FROM `sinks` WHERE `sink_id` = ?",
+                [sink_id],
+                |row| row.get::<_, String>(0),
+            )
+            .context("query_row")?;
+        let class = Class::from_string(&class).context("from_string")?;
+
+        let sql = match DbClass::from_class(class) {
+            DbClass::Boolean => indoc!("
+                SELECT
+                    `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`
+                FROM
+                    `storage_boolean`
+                WHERE
+                    `sink_id` = ?
+                ORDER BY
+                    `timestamp_group_start`
+            "),
+            DbClass::Real => indoc!("
+                SELECT
+                    `timestamp_group_start`, `value_last_timestamp`, `value_last_value`, `weight`, `sum`, `min`, `max`
+                FROM
+                    `storage_real`
+                WHERE
+                    `sink_id` = ?
+                ORDER BY
+                    `timestamp_group_start`
+            "),
+        };
+        let mut statement = connection.prepare(sql).context("prepare")?;
+
+        let mut chunk = statement.column_names().join(",");
+        chunk.push('\n');
+
+        let mut rows = statement.query([sink_id]).context("query")?;
+        while let Some(row) = rows.next().context("next")? {
+            let row = (0..row.as_ref().column_count())
+                .map(|index| -> Result<String, Error> {
+                    let value = match row.get_ref(index).context("get_ref")? {
+                        rusqlite::types::ValueRef::Null => String::new(),
+                        rusqlite::types::ValueRef::Integer(value) => value.to_string(),
+                        rusqlite::types::ValueRef::Real(value) => value.to_string(),
+                        value => bail!("unexpected value: {:?}", value),
+                    };
+                    Ok(value)
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(",");
+            chunk.push_str(&row);
+            chunk.push('\n');
+
+            if chunk.len() >= Self::EXPORT_CHUNK_SIZE {
+                let chunk = Bytes::from(mem::take(&mut chunk));
+                if !Self::sql_sink_storage_csv_send(&mut sender, chunk)
+                    .context("sql_sink_storage_csv_send")?
+                {
+                    return Ok(());
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            Self::sql_sink_storage_csv_send(&mut sender, Bytes::from(chunk))
+                .context("sql_sink_storage_csv_send")?;
+        }
+
+        Ok(())
+    }
     fn sql_buffer_to_storage(transaction: &rusqlite::Transaction) -> Result<(), Error> {
         transaction
             .execute_batch(include_str!("buffer_to_storage_boolean.sql"))
@@ -827,6 +1641,135 @@ impl<'f> Runnable for Manager<'f> {
         self.run(exit_flag).await
     }
 }
+impl<'f> uri_cursor::Handler for Manager<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("sinks", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let name = self.name.clone();
+                        let sinks_data_details = self.sinks_data_details_query();
+                        async move {
+                            match sinks_data_details.await {
+                                Ok(sinks_data_details) => {
+                                    web::Response::ok_json(sinks_data_details)
+                                }
+                                Err(error) => {
+                                    log::error!("Manager({}): {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next(sink_id, uri_cursor) => {
+                    let sink_id: SinkId = match sink_id.parse().context("sink_id") {
+                        Ok(sink_id) => sink_id,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed()
+                        }
+                    };
+                    match uri_cursor.as_ref() {
+                        uri_cursor::UriCursor::Next("storage.csv", uri_cursor) => {
+                            match uri_cursor.as_ref() {
+                                uri_cursor::UriCursor::Terminal => match *request.method() {
+                                    http::Method::GET => {
+                                        let body_stream = self.sink_storage_csv_stream(sink_id);
+                                        async {
+                                            web::Response::ok_content_type_stream(
+                                                "text/csv",
+                                                body_stream,
+                                            )
+                                        }
+                                        .boxed()
+                                    }
+                                    _ => async { web::Response::error_405() }.boxed(),
+                                },
+                                _ => async { web::Response::error_404() }.boxed(),
+                            }
+                        }
+                        // body format is selected by content type, see import::Format
+                        uri_cursor::UriCursor::Next("import", uri_cursor) => {
+                            match uri_cursor.as_ref() {
+                                uri_cursor::UriCursor::Terminal => match *request.method() {
+                                    http::Method::POST => {
+                                        let samples = match Self::import_parse(&request) {
+                                            Ok(samples) => samples,
+                                            Err(error) => {
+                                                return async {
+                                                    web::Response::error_400_from_error(error)
+                                                }
+                                                .boxed()
+                                            }
+                                        };
+
+                                        let summary = self.sink_import(sink_id, samples);
+                                        async {
+                                            match summary.await {
+                                                Ok(summary) => web::Response::ok_json(summary),
+                                                Err(error) => {
+                                                    web::Response::error_400_from_error(error)
+                                                }
+                                            }
+                                        }
+                                        .boxed()
+                                    }
+                                    _ => async { web::Response::error_405() }.boxed(),
+                                },
+                                _ => async { web::Response::error_404() }.boxed(),
+                            }
+                        }
+                        _ => async { web::Response::error_404() }.boxed(),
+                    }
+                }
+            },
+            // ?period=daily|weekly|monthly&from=YYYY-MM-DD&to=YYYY-MM-DD&format=json|csv
+            uri_cursor::UriCursor::Next("reports", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Next(name, uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            let (period, from, to, csv) = match Self::report_parse(&request) {
+                                Ok(parameters) => parameters,
+                                Err(error) => {
+                                    return async { web::Response::error_400_from_error(error) }
+                                        .boxed()
+                                }
+                            };
+
+                            let rows = self.report(name, period, from, to);
+                            async move {
+                                match rows.await {
+                                    Ok(rows) => {
+                                        if csv {
+                                            web::Response::ok_content_type_body(
+                                                "text/csv",
+                                                Bytes::from(report::rows_csv(&rows)),
+                                            )
+                                        } else {
+                                            web::Response::ok_json(rows)
+                                        }
+                                    }
+                                    Err(error) => web::Response::error_400_from_error(error),
+                                }
+                            }
+                            .boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
 impl<'f> fmt::Display for Manager<'f> {
     fn fmt(
         &self,
@@ -835,3 +1778,89 @@ impl<'f> fmt::Display for Manager<'f> {
         write!(f, "Manager({})", self.name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Manager, MIGRATIONS_RESOLVER};
+    use crate::modules::sqlite_migrations;
+    use bytes::Bytes;
+    use futures::channel::mpsc;
+
+    #[test]
+    fn sql_sink_storage_csv_send() {
+        let (mut sender, receiver) = mpsc::channel::<Bytes>(0);
+
+        assert!(Manager::sql_sink_storage_csv_send(&mut sender, Bytes::from("a")).unwrap());
+        // receiver not reading
+        assert!(Manager::sql_sink_storage_csv_send(&mut sender, Bytes::from("b")).is_err());
+
+        drop(receiver);
+        assert!(!Manager::sql_sink_storage_csv_send(&mut sender, Bytes::from("c")).unwrap());
+    }
+
+    #[test]
+    fn migrations() {
+        let mut connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .unwrap();
+
+        // database at version 1 with some data
+        let transaction = connection.transaction().unwrap();
+        transaction
+            .execute_batch(include_str!("migrate_0_1.sql"))
+            .unwrap();
+        transaction.pragma_update(None, "user_version", 1).unwrap();
+        transaction
+            .execute_batch(
+                "
+                INSERT INTO `sinks` VALUES (1, 'power', 'Real', 60.0, 1);
+                INSERT INTO `sinks_ext_real` VALUES (1, 120, 230.0);
+                INSERT INTO `storage_real` VALUES (1, 60, 120, 230.0, 60.0, 13800.0, 230.0, 230.0);
+                INSERT INTO `buffer_real` VALUES (1, 130, 231.0);
+            ",
+            )
+            .unwrap();
+        transaction.commit().unwrap();
+
+        let transaction = connection.transaction().unwrap();
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, &transaction).unwrap();
+        transaction.commit().unwrap();
+
+        let version = connection
+            .pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0))
+            .unwrap();
+        assert_eq!(version, 3);
+
+        let (unit, sum) = connection
+            .query_row(
+                "
+                SELECT `unit`, `sum`
+                FROM `sinks` JOIN `storage_real` USING(`sink_id`)
+            ",
+                [],
+                |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, f64>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(unit, None);
+        assert_eq!(sum, 13800.0);
+
+        let buffered = connection
+            .query_row("SELECT COUNT(*) FROM `buffer_real`", [], |row| {
+                row.get::<_, usize>(0)
+            })
+            .unwrap();
+        assert_eq!(buffered, 1);
+
+        // foreign keys still point to sinks
+        assert!(connection
+            .execute("INSERT INTO `buffer_real` VALUES (2, 140, 1.0)", [])
+            .is_err());
+
+        // fresh database
+        let mut connection = rusqlite::Connection::open_in_memory().unwrap();
+        let transaction = connection.transaction().unwrap();
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, &transaction).unwrap();
+        transaction.commit().unwrap();
+    }
+}
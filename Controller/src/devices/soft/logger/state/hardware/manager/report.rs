@@ -0,0 +1,362 @@
+use super::SinkId;
+use crate::{
+    datatypes::energy::tariff::Tariff,
+    modules::clock::{Clock, Configuration as ClockConfiguration, SourceSystem},
+};
+use anyhow::{bail, ensure, Context, Error};
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use indoc::indoc;
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    // sink holds rate (power, flow), consumption is its integral in
+    // value * hours
+    Rate,
+    // sink holds meter reading (energy, volume), consumption is its increase
+    // decreasing readings (meter replacement) are treated as no consumption
+    Counter,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    // must be a real sink
+    pub sink_id: SinkId,
+    pub mode: Mode,
+    // applied to consumption before cost, eg. 0.001 for W -> kWh
+    pub scale: f64,
+    // used for period boundaries and tariff zones
+    pub timezone: Tz,
+    pub tariff: Option<Tariff>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Period {
+    Daily,
+    Weekly, // weeks start on monday
+    Monthly,
+}
+impl Period {
+    pub fn from_string(input: &str) -> Option<Self> {
+        match input {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+
+    fn start(
+        &self,
+        date: NaiveDate,
+    ) -> NaiveDate {
+        match self {
+            Self::Daily => date,
+            Self::Weekly => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Self::Monthly => date.with_day(1).unwrap(),
+        }
+    }
+    fn next(
+        &self,
+        start: NaiveDate,
+    ) -> NaiveDate {
+        match self {
+            Self::Daily => start + Days::new(1),
+            Self::Weekly => start + Days::new(7),
+            Self::Monthly => start + Months::new(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Row {
+    pub start: NaiveDate,
+    pub consumption: f64,
+    pub cost: Option<f64>,
+}
+
+pub fn rows_csv(rows: &[Row]) -> String {
+    let mut csv = "start,consumption,cost\n".to_owned();
+    for row in rows {
+        let cost = row.cost.map(|cost| cost.to_string()).unwrap_or_default();
+        writeln!(csv, "{},{},{}", row.start, row.consumption, cost).unwrap();
+    }
+    csv
+}
+
+// single storage group of a real sink
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Group {
+    pub timestamp_group_start: i64,
+    pub value_last_value: Option<f64>,
+    pub sum: f64, // value * seconds
+}
+
+fn clock(timezone: Tz) -> Clock<'static> {
+    Clock::new(ClockConfiguration { timezone }, &SourceSystem)
+}
+
+// utc range covering local dates [from, to)
+pub fn range(
+    report: &Report,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let clock = clock(report.timezone);
+    (
+        clock.day_start(from).with_timezone(&Utc),
+        clock.day_start(to).with_timezone(&Utc),
+    )
+}
+
+// groups in [from, to), preceded by the last group before from, so counter
+// increase can be calculated for the first group
+pub fn sql_groups(
+    connection: &rusqlite::Connection,
+    sink_id: SinkId,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Box<[Group]>, Error> {
+    let groups = connection
+        .prepare(indoc!(
+            "
+            SELECT
+                `timestamp_group_start`, `value_last_value`, `sum`
+            FROM
+                `storage_real`
+            WHERE
+                `sink_id` = :sink_id
+                AND `timestamp_group_start` < :to
+                AND `timestamp_group_start` >= COALESCE(
+                    (
+                        SELECT
+                            MAX(`timestamp_group_start`)
+                        FROM
+                            `storage_real`
+                        WHERE
+                            `sink_id` = :sink_id
+                            AND `timestamp_group_start` < :from
+                    ),
+                    :from
+                )
+            ORDER BY
+                `timestamp_group_start`
+        "
+        ))
+        .context("prepare")?
+        .query_map(
+            rusqlite::named_params! {
+                ":sink_id": sink_id,
+                ":from": from.timestamp(),
+                ":to": to.timestamp(),
+            },
+            |row| -> rusqlite::Result<Group> {
+                Ok(Group {
+                    timestamp_group_start: row.get(0)?,
+                    value_last_value: row.get(1)?,
+                    sum: row.get(2)?,
+                })
+            },
+        )
+        .context("query_map")?
+        .collect::<rusqlite::Result<Box<[_]>>>()
+        .context("collect")?;
+    Ok(groups)
+}
+
+// one row for each period starting in [from, to), including empty ones
+pub fn aggregate(
+    report: &Report,
+    period: Period,
+    from: NaiveDate,
+    to: NaiveDate,
+    groups: &[Group],
+) -> Result<Box<[Row]>, Error> {
+    ensure!(from < to, "empty range");
+
+    let mut rows = Vec::<Row>::new();
+    let mut start = period.start(from);
+    while start < to {
+        rows.push(Row {
+            start,
+            consumption: 0.0,
+            cost: report.tariff.as_ref().map(|_| 0.0),
+        });
+        start = period.next(start);
+    }
+
+    let (from_utc, _) = range(report, rows[0].start, to);
+    let mut value_last_previous = None::<f64>;
+    for group in groups {
+        let consumption = match report.mode {
+            Mode::Rate => Some(group.sum / 3600.0),
+            Mode::Counter => match (value_last_previous, group.value_last_value) {
+                (Some(previous), Some(current)) => Some((current - previous).max(0.0)),
+                _ => None,
+            },
+        };
+        if group.value_last_value.is_some() {
+            value_last_previous = group.value_last_value;
+        }
+
+        // lookback group
+        if group.timestamp_group_start < from_utc.timestamp() {
+            continue;
+        }
+        let consumption = match consumption {
+            Some(consumption) => consumption * report.scale,
+            None => continue,
+        };
+
+        let time = match DateTime::<Utc>::from_timestamp(group.timestamp_group_start, 0) {
+            Some(time) => time.with_timezone(&report.timezone),
+            None => bail!("timestamp out of range"),
+        };
+        let start = period.start(time.date_naive());
+        let row = match rows.iter_mut().find(|row| row.start == start) {
+            Some(row) => row,
+            None => continue,
+        };
+
+        row.consumption += consumption;
+        if let (Some(cost), Some(tariff)) = (row.cost.as_mut(), report.tariff.as_ref()) {
+            *cost += consumption * tariff.price(time.naive_local()).to_f64();
+        }
+    }
+
+    Ok(rows.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aggregate, rows_csv, Group, Mode, Period, Report, Row};
+    use crate::datatypes::{
+        energy::tariff::{Tariff, TariffZone},
+        real::Real,
+    };
+    use chrono::{NaiveDate, NaiveTime, TimeZone};
+    use chrono_tz::Europe::Warsaw;
+
+    #[test]
+    fn counter_monthly() {
+        let report = Report {
+            sink_id: 1,
+            mode: Mode::Counter,
+            scale: 1.0,
+            timezone: Warsaw,
+            tariff: Some(Tariff::new(
+                Box::new([TariffZone {
+                    weekdays: Box::new([]),
+                    time_from: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                    time_to: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+                    price: Real::from_f64(0.5).unwrap(),
+                }]),
+                Real::from_f64(1.0).unwrap(),
+            )),
+        };
+        let group = |month: u32, day: u32, hour: u32, value: Option<f64>| Group {
+            timestamp_group_start: Warsaw
+                .with_ymd_and_hms(2024, month, day, hour, 0, 0)
+                .unwrap()
+                .timestamp(),
+            value_last_value: value,
+            sum: 0.0,
+        };
+
+        let groups = [
+            // lookback
+            group(12, 31, 12, Some(100.0)),
+            group(1, 1, 0, Some(110.0)),
+            group(1, 15, 12, Some(130.0)),
+            group(1, 31, 23, None),
+            group(2, 1, 12, Some(135.0)),
+            // meter replaced
+            group(2, 2, 12, Some(1.0)),
+            group(2, 3, 12, Some(3.0)),
+        ];
+        let groups = {
+            let mut groups = groups;
+            // lookback is in previous year
+            groups[0].timestamp_group_start = Warsaw
+                .with_ymd_and_hms(2023, 12, 31, 12, 0, 0)
+                .unwrap()
+                .timestamp();
+            groups
+        };
+
+        let rows = aggregate(
+            &report,
+            Period::Monthly,
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            &groups,
+        )
+        .unwrap();
+        assert_eq!(
+            &*rows,
+            [
+                Row {
+                    start: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                    consumption: 30.0,
+                    cost: Some(10.0 * 0.5 + 20.0),
+                },
+                Row {
+                    start: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+                    consumption: 7.0,
+                    cost: Some(7.0),
+                },
+            ]
+        );
+
+        assert_eq!(
+            rows_csv(&rows),
+            "start,consumption,cost\n2024-01-01,30,25\n2024-02-01,7,7\n"
+        );
+    }
+
+    #[test]
+    fn rate_weekly() {
+        let report = Report {
+            sink_id: 1,
+            mode: Mode::Rate,
+            scale: 0.001,
+            timezone: Warsaw,
+            tariff: None,
+        };
+        // 2024-01-03 is wednesday, 1 kW for one hour
+        let groups = [Group {
+            timestamp_group_start: Warsaw
+                .with_ymd_and_hms(2024, 1, 3, 12, 0, 0)
+                .unwrap()
+                .timestamp(),
+            value_last_value: Some(1000.0),
+            sum: 1000.0 * 3600.0,
+        }];
+
+        let rows = aggregate(
+            &report,
+            Period::Weekly,
+            NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            &groups,
+        )
+        .unwrap();
+        assert_eq!(
+            &*rows,
+            [
+                Row {
+                    start: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                    consumption: 1.0,
+                    cost: None,
+                },
+                Row {
+                    start: NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(),
+                    consumption: 0.0,
+                    cost: None,
+                },
+            ]
+        );
+    }
+}
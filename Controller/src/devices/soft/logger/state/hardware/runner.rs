@@ -1,5 +1,9 @@
 use super::{
-    manager::{Manager, SinkData, SinkId, SinkItem},
+    manager::{
+        anomaly::{Anomaly, Rule},
+        report::Report,
+        Manager, SinkData, SinkId, SinkItem,
+    },
     sink::SinkBase,
 };
 use crate::{
@@ -11,12 +15,14 @@ use crate::{
         runnable::{Exited, Runnable},
         runtime::{Runtime, RuntimeScope, RuntimeScopeRunnable},
     },
+    web::{self, uri_cursor},
 };
 use anyhow::{Context, Error};
 use async_trait::async_trait;
 use crossbeam::channel;
 use futures::{
-    future::{FutureExt, JoinAll},
+    channel::mpsc,
+    future::{BoxFuture, FutureExt, JoinAll},
     join,
     stream::StreamExt,
 };
@@ -365,12 +371,39 @@ impl<'f: 'r, 'r> Runner<'f, 'r> {
         Some(runner_sinks_lock)
     }
 
+    pub fn anomaly_rules_set(
+        &self,
+        rules: Box<[Rule]>,
+    ) -> Result<(), Error> {
+        self.manager_runner.manager().anomaly_rules_set(rules)
+    }
+    pub fn anomalies_subscribe(&self) -> mpsc::UnboundedReceiver<Anomaly> {
+        self.manager_runner.manager().anomalies_subscribe()
+    }
+
+    pub fn reports_set(
+        &self,
+        reports: HashMap<String, Report>,
+    ) -> Result<(), Error> {
+        self.manager_runner.manager().reports_set(reports)
+    }
+
     pub async fn finalize(self) {
         self.runner_sinks_runner.into_inner().finalize().await;
         self.manager_runner.finalize().await;
     }
 }
 
+impl<'f: 'r, 'r> uri_cursor::Handler for Runner<'f, 'r> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        self.manager_runner.manager().handle(request, uri_cursor)
+    }
+}
+
 #[self_referencing]
 #[derive(Debug)]
 struct RunnerOwnedInner<'f> {
@@ -461,6 +494,25 @@ impl<'f> RunnerOwned<'f> {
         runner.sinks_lock()
     }
 
+    pub fn anomaly_rules_set(
+        &self,
+        rules: Box<[Rule]>,
+    ) -> Result<(), Error> {
+        self.inner
+            .with_runner(|runner| runner.anomaly_rules_set(rules))
+    }
+    pub fn anomalies_subscribe(&self) -> mpsc::UnboundedReceiver<Anomaly> {
+        self.inner
+            .with_runner(|runner| runner.anomalies_subscribe())
+    }
+
+    pub fn reports_set(
+        &self,
+        reports: HashMap<String, Report>,
+    ) -> Result<(), Error> {
+        self.inner.with_runner(|runner| runner.reports_set(reports))
+    }
+
     pub async fn finalize(self) {
         let runner_runtime_scope = self
             .inner
@@ -497,3 +549,13 @@ impl<'f> RunnerOwned<'f> {
         drop(inner_heads);
     }
 }
+impl<'f> uri_cursor::Handler for RunnerOwned<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        self.inner
+            .with_runner(|runner| runner.handle(request, uri_cursor))
+    }
+}
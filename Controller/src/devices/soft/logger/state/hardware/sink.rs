@@ -2,7 +2,7 @@ use super::types::{Class, TimeValue, Type};
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
 use chrono::{DateTime, Utc};
 use futures::channel::mpsc;
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Instant};
 
 // typed sink
 #[derive(Debug)]
@@ -24,7 +24,11 @@ impl<'a, T: Type> SinkTypedRef<'a, T> {
         value: Option<T>,
     ) {
         let value = T::into_value(value);
-        let time_value = TimeValue { time, value };
+        let time_value = TimeValue {
+            time,
+            instant: Instant::now(),
+            value,
+        };
         self.base.items_sender.unbounded_send(time_value).unwrap();
     }
 }
@@ -1,12 +1,16 @@
-use crate::datatypes::{ratio::Ratio, real::Real, temperature::Temperature, voltage::Voltage};
+use crate::datatypes::{
+    quality::Quality, ratio::Ratio, real::Real, temperature::Temperature, voltage::Voltage,
+};
 use chrono::{DateTime, Utc};
-use std::fmt;
+use serde::Serialize;
+use std::{fmt, time::Instant};
 
 // TODO: Class & Value private
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
 pub enum Class {
     Boolean,
+    Quality,
     Ratio,
     Real,
     Temperature,
@@ -16,6 +20,7 @@ impl Class {
     pub fn from_string(input: &str) -> Option<Self> {
         match input {
             "Boolean" => Some(Class::Boolean),
+            "Quality" => Some(Class::Quality),
             "Ratio" => Some(Class::Ratio),
             "Real" => Some(Class::Real),
             "Temperature" => Some(Class::Temperature),
@@ -26,6 +31,7 @@ impl Class {
     pub fn to_string(&self) -> &'static str {
         match self {
             Class::Boolean => "Boolean",
+            Class::Quality => "Quality",
             Class::Ratio => "Ratio",
             Class::Real => "Real",
             Class::Temperature => "Temperature",
@@ -37,6 +43,7 @@ impl Class {
 #[derive(Debug)]
 pub enum Value {
     Boolean(Option<bool>),
+    Quality(Option<Quality>),
     Ratio(Option<Ratio>),
     Real(Option<Real>),
     Temperature(Option<Temperature>),
@@ -57,6 +64,15 @@ impl Type for bool {
     }
 }
 
+impl Type for Quality {
+    fn class() -> Class {
+        Class::Quality
+    }
+    fn into_value(value: Option<Self>) -> Value {
+        Value::Quality(value)
+    }
+}
+
 impl Type for Ratio {
     fn class() -> Class {
         Class::Ratio
@@ -96,5 +112,6 @@ impl Type for Voltage {
 #[derive(Debug)]
 pub struct TimeValue {
     pub time: DateTime<Utc>,
+    pub instant: Instant, // taken together with time, used to detect wall clock jumps
     pub value: Value,
 }
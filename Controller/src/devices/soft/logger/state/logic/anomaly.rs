@@ -0,0 +1,162 @@
+use super::super::hardware::manager::anomaly::Anomaly;
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use atomic_refcell::AtomicRefCell;
+use futures::{channel::mpsc, stream::StreamExt};
+use std::{borrow::Cow, iter};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // names of logger anomaly rules exposed as signals
+    pub rules: Box<[String]>,
+}
+
+// exposes anomalies detected by logger manager as signals
+// receiver comes from RunnerOwned::anomalies_subscribe()
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    anomalies_receiver: AtomicRefCell<mpsc::UnboundedReceiver<Anomaly>>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_actives: Box<[signal::state_source::Signal<bool>]>,
+    signal_alarms: Box<[signal::event_source::Signal<()>]>,
+}
+impl Device {
+    pub fn new(
+        configuration: Configuration,
+        anomalies_receiver: mpsc::UnboundedReceiver<Anomaly>,
+    ) -> Self {
+        let signal_actives = (0..configuration.rules.len())
+            .map(|_| signal::state_source::Signal::<bool>::new(Some(false)))
+            .collect::<Box<[_]>>();
+        let signal_alarms = (0..configuration.rules.len())
+            .map(|_| signal::event_source::Signal::<()>::new())
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+
+            anomalies_receiver: AtomicRefCell::new(anomalies_receiver),
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_actives,
+            signal_alarms,
+        }
+    }
+
+    fn anomaly_handle(
+        &self,
+        anomaly: Anomaly,
+    ) {
+        let index = match self
+            .configuration
+            .rules
+            .iter()
+            .position(|rule| *rule == anomaly.rule)
+        {
+            Some(index) => index,
+            None => return,
+        };
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_actives[index].set_one(Some(anomaly.active));
+        if anomaly.active {
+            signals_sources_changed |= self.signal_alarms[index].push_one(());
+        }
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut anomalies_receiver = self.anomalies_receiver.borrow_mut();
+        anomalies_receiver
+            .by_ref()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |anomaly| {
+                self.anomaly_handle(anomaly);
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/logger/state/anomaly")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Active(usize),
+    Alarm(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_actives
+                    .iter()
+                    .enumerate()
+                    .map(|(index, signal_active)| {
+                        (
+                            SignalIdentifier::Active(index),
+                            signal_active as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain(
+                self.signal_alarms
+                    .iter()
+                    .enumerate()
+                    .map(|(index, signal_alarm)| {
+                        (
+                            SignalIdentifier::Alarm(index),
+                            signal_alarm as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect()
+    }
+}
@@ -1,6 +1,7 @@
 use super::super::hardware::{sink::SinkTypedRef, types::Type};
 use crate::{
     devices,
+    modules::clock::Clock,
     signals::{self, signal, types::state::Value},
     util::{
         async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
@@ -9,7 +10,6 @@ use crate::{
     },
 };
 use async_trait::async_trait;
-use chrono::Utc;
 use futures::stream::StreamExt;
 use maplit::hashmap;
 use std::{any::type_name, borrow::Cow};
@@ -20,6 +20,7 @@ where
     V: Value + Type + Clone,
 {
     sink: SinkTypedRef<'a, V>,
+    clock: &'a Clock<'a>,
 
     signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
     signal_input: signal::state_target_queued::Signal<V>,
@@ -28,9 +29,13 @@ impl<'a, V> Device<'a, V>
 where
     V: Value + Type + Clone,
 {
-    pub fn new(sink: SinkTypedRef<'a, V>) -> Self {
+    pub fn new(
+        sink: SinkTypedRef<'a, V>,
+        clock: &'a Clock<'a>,
+    ) -> Self {
         Self {
             sink,
+            clock,
 
             signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
             signal_input: signal::state_target_queued::Signal::<V>::new(),
@@ -38,7 +43,7 @@ where
     }
 
     fn signals_targets_changed(&self) {
-        let now = Utc::now();
+        let now = self.clock.now();
 
         self.signal_input
             .take_pending()
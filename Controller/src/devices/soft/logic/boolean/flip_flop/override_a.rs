@@ -15,16 +15,17 @@ use futures::{
 };
 use maplit::hashmap;
 use parking_lot::RwLock;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum Mode {
     PassThrough,
     Override(bool),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub initial_mode: Mode,
 }
@@ -14,10 +14,11 @@ use futures::{
     stream::StreamExt,
 };
 use maplit::hashmap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub initial_value: bool,
 }
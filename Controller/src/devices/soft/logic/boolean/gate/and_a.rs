@@ -9,9 +9,11 @@ use crate::{
 };
 use async_trait::async_trait;
 use futures::stream::StreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, iter};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub inputs_count: usize,
 }
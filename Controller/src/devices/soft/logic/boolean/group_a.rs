@@ -0,0 +1,165 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{future::FutureExt, pin_mut, select, stream::StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    iter,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigurationOutput {
+    pub inverted: bool,
+    // since input change, eg. to avoid inrush when switching many contactors
+    pub delay: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub outputs: Box<[ConfigurationOutput]>,
+}
+
+// mirrors single input to all outputs
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+    // output indexes ordered by delay
+    outputs_order: Box<[usize]>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<bool>,
+    signal_outputs: Box<[signal::state_source::Signal<bool>]>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let mut outputs_order = (0..configuration.outputs.len()).collect::<Box<[_]>>();
+        outputs_order.sort_by_key(|output_index| configuration.outputs[*output_index].delay);
+
+        let signal_outputs = (0..configuration.outputs.len())
+            .map(|_output_index| signal::state_source::Signal::<bool>::new(None))
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+            outputs_order,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<bool>::new(),
+            signal_outputs,
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let signal_input_changed_stream = self
+            .signals_targets_changed_waker
+            .stream()
+            .filter(|_| async { self.signal_input.take_pending().is_some() });
+        pin_mut!(signal_input_changed_stream);
+
+        'outer: loop {
+            let state = self.signal_input.peek_last();
+            let changed = Instant::now();
+
+            // outputs not switched yet keep previous value if input changes
+            // in the meantime
+            for output_index in self.outputs_order.iter().copied() {
+                let configuration_output = &self.configuration.outputs[output_index];
+
+                let deadline = changed + configuration_output.delay;
+                if deadline > Instant::now() {
+                    select! {
+                        () = signal_input_changed_stream.select_next_some() => continue 'outer,
+                        () = timer_wheel::sleep_until(deadline).fuse() => {},
+                        () = exit_flag => break 'outer,
+                    }
+                }
+
+                let value = state.map(|state| state ^ configuration_output.inverted);
+                if self.signal_outputs[output_index].set_one(value) {
+                    self.signals_sources_changed_waker.wake();
+                }
+            }
+
+            select! {
+                () = signal_input_changed_stream.select_next_some() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/logic/boolean/group_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Output(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain([(
+                SignalIdentifier::Input,
+                &self.signal_input as &dyn signal::Base,
+            )])
+            .chain(
+                self.signal_outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(output_index, signal_output)| {
+                        (
+                            SignalIdentifier::Output(output_index),
+                            signal_output as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
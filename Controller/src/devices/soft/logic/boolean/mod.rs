@@ -1,3 +1,4 @@
 pub mod flip_flop;
 pub mod gate;
+pub mod group_a;
 pub mod value;
@@ -0,0 +1,357 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // press longer than this is a hold
+    pub click_duration_max: Duration,
+    // release longer than this ends clicks sequence
+    pub clicks_gap_max: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Gesture {
+    Single,
+    Double,
+    Triple,
+    Hold,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+    Idle,
+    Pressed { since: Instant, clicks: usize },
+    Released { since: Instant, clicks: usize },
+    Holding,
+}
+
+#[derive(Debug)]
+struct Decoder {
+    click_duration_max: Duration,
+    clicks_gap_max: Duration,
+
+    state: State,
+}
+impl Decoder {
+    const CLICKS_MAX: usize = 3;
+
+    pub fn new(
+        click_duration_max: Duration,
+        clicks_gap_max: Duration,
+    ) -> Self {
+        Self {
+            click_duration_max,
+            clicks_gap_max,
+
+            state: State::Idle,
+        }
+    }
+
+    fn gesture_clicks(clicks: usize) -> Gesture {
+        match clicks {
+            1 => Gesture::Single,
+            2 => Gesture::Double,
+            3 => Gesture::Triple,
+            _ => panic!("unsupported clicks count: {}", clicks),
+        }
+    }
+
+    pub fn holding(&self) -> bool {
+        self.state == State::Holding
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.state {
+            State::Idle | State::Holding => None,
+            State::Pressed { since, .. } => Some(since + self.click_duration_max),
+            State::Released { since, .. } => Some(since + self.clicks_gap_max),
+        }
+    }
+
+    pub fn input(
+        &mut self,
+        pressed: bool,
+        now: Instant,
+    ) -> Option<Gesture> {
+        // deadline may have passed before input was processed
+        let gesture = self.timeout(now);
+
+        match (self.state, pressed) {
+            (State::Idle, true) => {
+                self.state = State::Pressed {
+                    since: now,
+                    clicks: 0,
+                };
+            }
+            (State::Released { clicks, .. }, true) => {
+                self.state = State::Pressed { since: now, clicks };
+            }
+            (State::Pressed { clicks, .. }, false) => {
+                let clicks = clicks + 1;
+                if clicks >= Self::CLICKS_MAX {
+                    // no longer sequence possible, don't wait for the gap
+                    self.state = State::Idle;
+                    return Some(Self::gesture_clicks(clicks));
+                }
+                self.state = State::Released { since: now, clicks };
+            }
+            (State::Holding, false) => {
+                self.state = State::Idle;
+            }
+            // repeated value
+            _ => {}
+        }
+
+        gesture
+    }
+
+    pub fn timeout(
+        &mut self,
+        now: Instant,
+    ) -> Option<Gesture> {
+        if !self.deadline().is_some_and(|deadline| deadline <= now) {
+            return None;
+        }
+
+        match self.state {
+            State::Pressed { .. } => {
+                // clicks before hold are dropped
+                self.state = State::Holding;
+                Some(Gesture::Hold)
+            }
+            State::Released { clicks, .. } => {
+                self.state = State::Idle;
+                Some(Self::gesture_clicks(clicks))
+            }
+            State::Idle | State::Holding => None,
+        }
+    }
+}
+
+// decodes raw button press signal into gestures, so single wall button can
+// drive multiple actions
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_queued::Signal<bool>,
+    signal_single: signal::event_source::Signal<()>,
+    signal_double: signal::event_source::Signal<()>,
+    signal_triple: signal::event_source::Signal<()>,
+    signal_hold: signal::event_source::Signal<()>,
+    signal_holding: signal::state_source::Signal<bool>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        Self {
+            configuration,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_queued::Signal::<bool>::new(),
+            signal_single: signal::event_source::Signal::<()>::new(),
+            signal_double: signal::event_source::Signal::<()>::new(),
+            signal_triple: signal::event_source::Signal::<()>::new(),
+            signal_hold: signal::event_source::Signal::<()>::new(),
+            signal_holding: signal::state_source::Signal::<bool>::new(Some(false)),
+        }
+    }
+
+    fn gesture_push(
+        &self,
+        gesture: Gesture,
+    ) -> bool {
+        let signal = match gesture {
+            Gesture::Single => &self.signal_single,
+            Gesture::Double => &self.signal_double,
+            Gesture::Triple => &self.signal_triple,
+            Gesture::Hold => &self.signal_hold,
+        };
+        signal.push_one(())
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut decoder = Decoder::new(
+            self.configuration.click_duration_max,
+            self.configuration.clicks_gap_max,
+        );
+
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let wake = match decoder.deadline() {
+                Some(deadline) => timer_wheel::sleep_until(deadline).left_future(),
+                None => future::pending().right_future(),
+            };
+
+            let mut gestures = Vec::<Gesture>::new();
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {
+                    let now = Instant::now();
+                    gestures.extend(
+                        self.signal_input
+                            .take_pending()
+                            .iter()
+                            // unknown value is treated as released
+                            .filter_map(|value| decoder.input(value.unwrap_or(false), now)),
+                    );
+                },
+                () = wake.fuse() => {
+                    gestures.extend(decoder.timeout(Instant::now()));
+                },
+                () = exit_flag => break,
+            }
+
+            let mut signals_sources_changed = false;
+            for gesture in gestures {
+                signals_sources_changed |= self.gesture_push(gesture);
+            }
+            signals_sources_changed |= self.signal_holding.set_one(Some(decoder.holding()));
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/logic/button_decoder_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Single,
+    Double,
+    Triple,
+    Hold,
+    Holding,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Single => &self.signal_single as &dyn signal::Base,
+            SignalIdentifier::Double => &self.signal_double as &dyn signal::Base,
+            SignalIdentifier::Triple => &self.signal_triple as &dyn signal::Base,
+            SignalIdentifier::Hold => &self.signal_hold as &dyn signal::Base,
+            SignalIdentifier::Holding => &self.signal_holding as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Gesture};
+    use std::time::{Duration, Instant};
+
+    fn decoder_new() -> Decoder {
+        Decoder::new(Duration::from_millis(400), Duration::from_millis(300))
+    }
+
+    #[test]
+    fn single_double_triple() {
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+
+        let mut decoder = decoder_new();
+        assert_eq!(decoder.input(true, ms(0)), None);
+        assert_eq!(decoder.input(false, ms(100)), None);
+        assert_eq!(decoder.deadline(), Some(ms(400)));
+        assert_eq!(decoder.timeout(ms(400)), Some(Gesture::Single));
+        assert_eq!(decoder.deadline(), None);
+
+        let mut decoder = decoder_new();
+        assert_eq!(decoder.input(true, ms(0)), None);
+        assert_eq!(decoder.input(false, ms(100)), None);
+        assert_eq!(decoder.input(true, ms(200)), None);
+        assert_eq!(decoder.input(false, ms(300)), None);
+        assert_eq!(decoder.timeout(ms(600)), Some(Gesture::Double));
+
+        // third click does not wait for the gap
+        let mut decoder = decoder_new();
+        for step in 0..2 {
+            assert_eq!(decoder.input(true, ms(step * 200)), None);
+            assert_eq!(decoder.input(false, ms(step * 200 + 100)), None);
+        }
+        assert_eq!(decoder.input(true, ms(400)), None);
+        assert_eq!(decoder.input(false, ms(500)), Some(Gesture::Triple));
+        assert_eq!(decoder.deadline(), None);
+    }
+
+    #[test]
+    fn hold() {
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+
+        let mut decoder = decoder_new();
+        assert_eq!(decoder.input(true, ms(0)), None);
+        assert_eq!(decoder.timeout(ms(399)), None);
+        assert_eq!(decoder.timeout(ms(400)), Some(Gesture::Hold));
+        assert!(decoder.holding());
+        assert_eq!(decoder.input(false, ms(2000)), None);
+        assert!(!decoder.holding());
+
+        // late processed press completes previous sequence
+        let mut decoder = decoder_new();
+        assert_eq!(decoder.input(true, ms(0)), None);
+        assert_eq!(decoder.input(false, ms(100)), None);
+        assert_eq!(decoder.input(true, ms(1000)), Some(Gesture::Single));
+        assert_eq!(decoder.timeout(ms(1400)), Some(Gesture::Hold));
+    }
+}
@@ -10,9 +10,11 @@ use crate::{
 };
 use async_trait::async_trait;
 use futures::stream::StreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, iter};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub inputs_count: usize,
 }
@@ -1 +1,2 @@
 pub mod boolean_to_ratio_a;
+pub mod rotary_a;
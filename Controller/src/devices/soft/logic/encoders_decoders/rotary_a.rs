@@ -0,0 +1,337 @@
+use crate::{
+    datatypes::ratio::Ratio,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Mode {
+    // two phase shifted signals, as in most mechanical encoders
+    Quadrature { transitions_per_step: u8 },
+    // pulse on first input is step up, on second - step down
+    UpDown,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigurationAcceleration {
+    // steps closer than this are accelerated
+    pub interval: Duration,
+    // multiplier increase for each accelerated step
+    pub multiplier_step: f64,
+    pub multiplier_max: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub mode: Mode,
+    // output change per single step
+    pub step: Ratio,
+    pub minimum: Ratio,
+    pub maximum: Ratio,
+    pub acceleration: Option<ConfigurationAcceleration>,
+}
+
+#[derive(Debug)]
+struct Decoder {
+    mode: Mode,
+
+    state_last: Option<(bool, bool)>,
+    transitions: i32,
+}
+impl Decoder {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+
+            state_last: None,
+            transitions: 0,
+        }
+    }
+
+    // gray code order for single step forward
+    fn quadrature_position(state: (bool, bool)) -> i32 {
+        match state {
+            (false, false) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+            (false, true) => 3,
+        }
+    }
+
+    // returns number of steps, positive is up
+    pub fn input(
+        &mut self,
+        state: (bool, bool),
+    ) -> i32 {
+        let state_last = match self.state_last.replace(state) {
+            Some(state_last) => state_last,
+            None => return 0,
+        };
+
+        match self.mode {
+            Mode::Quadrature {
+                transitions_per_step,
+            } => {
+                let delta = (Self::quadrature_position(state)
+                    - Self::quadrature_position(state_last))
+                .rem_euclid(4);
+                match delta {
+                    1 => self.transitions += 1,
+                    3 => self.transitions -= 1,
+                    // both inputs changed at once, direction is unknown
+                    2 => log::trace!("invalid quadrature transition"),
+                    _ => {}
+                }
+
+                let transitions_per_step = transitions_per_step.max(1) as i32;
+                let steps = self.transitions / transitions_per_step;
+                self.transitions %= transitions_per_step;
+                steps
+            }
+            Mode::UpDown => {
+                let mut steps = 0;
+                if state.0 && !state_last.0 {
+                    steps += 1;
+                }
+                if state.1 && !state_last.1 {
+                    steps -= 1;
+                }
+                steps
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    decoder: Decoder,
+    multiplier: f64,
+    step_last: Option<Instant>,
+}
+
+// decodes rotary knob connected to two inputs into ratio, eg. for dimmers
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+    state: Mutex<State>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_a: signal::state_target_last::Signal<bool>,
+    signal_b: signal::state_target_last::Signal<bool>,
+    signal_set: signal::state_target_last::Signal<Ratio>,
+    signal_output: signal::state_source::Signal<Ratio>,
+    signal_up: signal::event_source::Signal<()>,
+    signal_down: signal::event_source::Signal<()>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let decoder = Decoder::new(configuration.mode);
+
+        Self {
+            configuration,
+            state: Mutex::new(State {
+                decoder,
+                multiplier: 1.0,
+                step_last: None,
+            }),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_a: signal::state_target_last::Signal::<bool>::new(),
+            signal_b: signal::state_target_last::Signal::<bool>::new(),
+            signal_set: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_output: signal::state_source::Signal::<Ratio>::new(None),
+            signal_up: signal::event_source::Signal::<()>::new(),
+            signal_down: signal::event_source::Signal::<()>::new(),
+        }
+    }
+
+    fn multiplier_update(
+        &self,
+        state: &mut State,
+        now: Instant,
+    ) -> f64 {
+        let accelerated = match &self.configuration.acceleration {
+            Some(acceleration) => match state.step_last {
+                Some(step_last) if now.duration_since(step_last) < acceleration.interval => {
+                    Some(acceleration)
+                }
+                _ => None,
+            },
+            None => None,
+        };
+        state.multiplier = match accelerated {
+            Some(acceleration) => {
+                (state.multiplier + acceleration.multiplier_step).min(acceleration.multiplier_max)
+            }
+            None => 1.0,
+        };
+        state.step_last = Some(now);
+
+        state.multiplier
+    }
+
+    fn signals_targets_changed(&self) {
+        let mut signals_sources_changed = false;
+
+        // eg. synchronize with value set from other place
+        if let Some(Some(set)) = self.signal_set.take_pending() {
+            signals_sources_changed |= self.signal_output.set_one(Some(set));
+        }
+
+        let a = self.signal_a.take_last();
+        let b = self.signal_b.take_last();
+        if let (true, Some(a), Some(b)) = (a.pending || b.pending, a.value, b.value) {
+            let mut state = self.state.lock();
+            let steps = state.decoder.input((a, b));
+            if steps != 0 {
+                let multiplier = self.multiplier_update(&mut state, Instant::now());
+                drop(state);
+
+                let value = self
+                    .signal_output
+                    .peek_last()
+                    .unwrap_or(self.configuration.minimum)
+                    .to_f64();
+                let value = (value + steps as f64 * self.configuration.step.to_f64() * multiplier)
+                    .clamp(
+                        self.configuration.minimum.to_f64(),
+                        self.configuration.maximum.to_f64(),
+                    );
+                let value = Ratio::from_f64(value).unwrap();
+                signals_sources_changed |= self.signal_output.set_one(Some(value));
+
+                let events = vec![(); steps.unsigned_abs() as usize].into_boxed_slice();
+                signals_sources_changed |= if steps > 0 {
+                    self.signal_up.push_many(events)
+                } else {
+                    self.signal_down.push_many(events)
+                };
+            }
+        }
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/logic/encoders_decoders/rotary_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    A,
+    B,
+    Set,
+    Output,
+    Up,
+    Down,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::A => &self.signal_a as &dyn signal::Base,
+            SignalIdentifier::B => &self.signal_b as &dyn signal::Base,
+            SignalIdentifier::Set => &self.signal_set as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+            SignalIdentifier::Up => &self.signal_up as &dyn signal::Base,
+            SignalIdentifier::Down => &self.signal_down as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Mode};
+
+    #[test]
+    fn quadrature() {
+        let mut decoder = Decoder::new(Mode::Quadrature {
+            transitions_per_step: 2,
+        });
+
+        assert_eq!(decoder.input((false, false)), 0);
+        // forward
+        assert_eq!(decoder.input((true, false)), 0);
+        assert_eq!(decoder.input((true, true)), 1);
+        assert_eq!(decoder.input((false, true)), 0);
+        assert_eq!(decoder.input((false, false)), 1);
+        // backward
+        assert_eq!(decoder.input((false, true)), 0);
+        assert_eq!(decoder.input((true, true)), -1);
+        // invalid, ignored
+        assert_eq!(decoder.input((false, false)), 0);
+    }
+
+    #[test]
+    fn up_down() {
+        let mut decoder = Decoder::new(Mode::UpDown);
+
+        assert_eq!(decoder.input((false, false)), 0);
+        assert_eq!(decoder.input((true, false)), 1);
+        assert_eq!(decoder.input((false, false)), 0);
+        assert_eq!(decoder.input((false, true)), -1);
+        assert_eq!(decoder.input((true, false)), 1);
+    }
+}
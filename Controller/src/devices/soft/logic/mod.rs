@@ -1,3 +1,4 @@
 pub mod boolean;
+pub mod button_decoder_a;
 pub mod compare;
 pub mod encoders_decoders;
@@ -0,0 +1 @@
+pub mod pulse_meter_a;
@@ -0,0 +1,586 @@
+use crate::{
+    datatypes::real::Real,
+    devices,
+    modules::{fs::Fs, sqlite::SQLite},
+    signals::{self, signal},
+    util::{
+        async_flag, async_waker,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::{ensure, Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use indoc::indoc;
+use maplit::hashmap;
+use parking_lot::RwLock;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::{borrow::Cow, pin::Pin, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across meters
+    pub name: String,
+    // meter constant, eg. 1000 for 1000 imp/kWh electricity meter or 100 for
+    // 0.01 m3/imp gas meter
+    pub pulses_per_unit: f64,
+    // rate drops to zero if no pulse arrives within this time
+    pub rate_timeout: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    // cumulative, in units (kWh, m3)
+    total: f64,
+    // in units per hour (kW, m3/h)
+    rate: f64,
+    pulse_last: Option<Instant>,
+}
+
+// converts pulses of utility meter (water, gas, electricity) into cumulative
+// total and flow / power
+// total is persisted and can be corrected to match meter reading
+#[derive(Debug)]
+pub struct Device<'f> {
+    configuration: Configuration,
+
+    sqlite: SQLite<'f>,
+
+    state: RwLock<State>,
+    state_changed_waker: async_waker::mpsc::Signal,
+    initialized: async_flag::LocalSender,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::event_target_queued::Signal<()>,
+    signal_total: signal::state_source::Signal<Real>,
+    signal_rate: signal::state_source::Signal<Real>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl<'f> Device<'f> {
+    // while flowing, rate is decayed and total is persisted at this pace
+    const INTERVAL: Duration = Duration::from_secs(10);
+
+    pub fn new(
+        configuration: Configuration,
+        fs: &'f Fs,
+    ) -> Self {
+        assert!(configuration.pulses_per_unit > 0.0);
+
+        let sqlite = SQLite::new(format!("pulse_meter.{}", configuration.name), fs);
+
+        Self {
+            configuration,
+
+            sqlite,
+
+            state: RwLock::new(State {
+                total: 0.0,
+                rate: 0.0,
+                pulse_last: None,
+            }),
+            state_changed_waker: async_waker::mpsc::Signal::new(),
+            initialized: async_flag::LocalSender::new(),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::event_target_queued::Signal::<()>::new(),
+            signal_total: signal::state_source::Signal::<Real>::new(None),
+            signal_rate: signal::state_source::Signal::<Real>::new(None),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // amount measured by pulses spread over duration, per hour
+    fn rate_calculate(
+        units: f64,
+        duration: Duration,
+    ) -> f64 {
+        units / duration.as_secs_f64() * 3600.0
+    }
+
+    fn pulses(
+        configuration: &Configuration,
+        state: &mut State,
+        pulses: usize,
+        now: Instant,
+    ) {
+        let units = pulses as f64 / configuration.pulses_per_unit;
+        state.total += units;
+
+        // first pulse after start or timeout has no reference
+        // pulses arriving at the same instant as previous ones give no duration
+        // to calculate rate from, so the rate is kept
+        match state.pulse_last {
+            Some(pulse_last) if now.duration_since(pulse_last).is_zero() => return,
+            Some(pulse_last) if now.duration_since(pulse_last) < configuration.rate_timeout => {
+                state.rate = Self::rate_calculate(units, now.duration_since(pulse_last));
+            }
+            _ => {}
+        }
+        state.pulse_last = Some(now);
+    }
+    fn decay(
+        configuration: &Configuration,
+        state: &mut State,
+        now: Instant,
+    ) {
+        let pulse_last = match state.pulse_last {
+            Some(pulse_last) => pulse_last,
+            None => return,
+        };
+
+        let elapsed = now.duration_since(pulse_last);
+        if elapsed >= configuration.rate_timeout {
+            state.rate = 0.0;
+            state.pulse_last = None;
+            return;
+        }
+
+        // next pulse has not arrived yet, so the rate is not higher than if it
+        // arrived now
+        let rate_max = Self::rate_calculate(1.0 / configuration.pulses_per_unit, elapsed);
+        state.rate = state.rate.min(rate_max);
+    }
+
+    // sets total to match meter reading
+    fn reading_set(
+        &self,
+        reading: f64,
+    ) -> Result<(), Error> {
+        ensure!(
+            reading.is_finite() && reading >= 0.0,
+            "reading must be finite and not negative"
+        );
+
+        let mut state = self.state.write();
+        let total_previous = state.total;
+        state.total = reading;
+        drop(state);
+
+        log::info!(
+            "{}: total corrected from {} to {}",
+            self.configuration.name,
+            total_previous,
+            reading
+        );
+        self.state_changed_waker.wake();
+
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<(), Error> {
+        let total = self
+            .sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                transaction
+                    .execute_batch(indoc!(
+                        "
+                        CREATE TABLE IF NOT EXISTS `meter` (
+                            `id` INTEGER PRIMARY KEY NOT NULL,
+                            `total` REAL NOT NULL
+                        ) STRICT;
+                    "
+                    ))
+                    .context("initialize")?;
+
+                let total = transaction
+                    .query_row("SELECT `total` FROM `meter` WHERE `id` = 0", [], |row| {
+                        row.get::<_, f64>(0)
+                    })
+                    .optional()
+                    .context("query_row")?;
+
+                Ok(total)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        if let Some(total) = total {
+            self.state.write().total = total;
+        }
+
+        Ok(())
+    }
+    async fn persist(
+        &self,
+        total: f64,
+    ) -> Result<(), Error> {
+        self.sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                transaction
+                    .execute(
+                        indoc!(
+                            "
+                            INSERT OR REPLACE INTO
+                                `meter` (`id`, `total`)
+                            VALUES
+                                (0, ?)
+                        "
+                        ),
+                        (total,),
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(())
+    }
+    async fn persist_state(&self) {
+        let total = self.state.read().total;
+        if let Err(error) = self.persist(total).await.context("persist") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+    }
+
+    // returns whether any pulse was counted
+    fn update(&self) -> bool {
+        let pulses = self.signal_input.take_pending().len();
+
+        let now = Instant::now();
+        let mut state = self.state.write();
+        if pulses > 0 {
+            Self::pulses(&self.configuration, &mut state, pulses, now);
+        } else {
+            Self::decay(&self.configuration, &mut state, now);
+        }
+        let (total, rate) = (state.total, state.rate);
+        drop(state);
+
+        let mut signals_sources_changed = false;
+        // non finite values are not exposed
+        signals_sources_changed |= self.signal_total.set_one(Real::from_f64(total).ok());
+        signals_sources_changed |= self.signal_rate.set_one(Real::from_f64(rate).ok());
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+            self.gui_summary_waker.wake();
+        }
+
+        pulses > 0
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        if let Err(error) = self.load().await.context("load") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+        self.initialized.signal();
+
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+        let mut state_changed_receiver = self.state_changed_waker.receiver();
+
+        // pulses are persisted on next tick, not to write on every pulse
+        // tick is kept across iterations, so continuous pulses don't postpone
+        // it
+        let mut dirty = false;
+        let mut tick = None::<Pin<Box<timer_wheel::Delay>>>;
+        loop {
+            dirty |= self.update();
+
+            let flowing = self.state.read().pulse_last.is_some();
+            if !(flowing || dirty) {
+                tick = None;
+            } else if tick.is_none() {
+                tick = Some(Box::pin(timer_wheel::sleep(Self::INTERVAL)));
+            }
+            let tick_runner = match tick.as_mut() {
+                Some(tick) => tick.left_future(),
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = state_changed_receiver.select_next_some() => {
+                    self.persist_state().await;
+                },
+                () = tick_runner.fuse() => {
+                    tick = None;
+                    if dirty {
+                        self.persist_state().await;
+                        dirty = false;
+                    }
+                },
+                () = exit_flag => break,
+            }
+        }
+
+        if dirty {
+            self.persist_state().await;
+        }
+
+        Exited
+    }
+}
+
+impl<'f> devices::Device for Device<'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/metering/pulse_meter_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+    fn initialized(&self) -> Option<&async_flag::LocalSender> {
+        Some(&self.initialized)
+    }
+}
+
+#[async_trait]
+impl<'f> Runnable for Device<'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Total,
+    Rate,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f> signals::Device for Device<'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Total => &self.signal_total as &dyn signal::Base,
+            SignalIdentifier::Rate => &self.signal_rate as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    total: f64,
+    rate: f64,
+}
+impl<'f> devices::gui_summary::Device for Device<'f> {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        Self::Value {
+            total: state.total,
+            rate: state.rate,
+        }
+    }
+}
+
+impl<'f> uri_cursor::Handler for Device<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("reading", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::PUT => {
+                        let reading = match request.body_parse_json::<f64>() {
+                            Ok(reading) => reading,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+                        let result = self.reading_set(reading);
+                        async move {
+                            match result {
+                                Ok(()) => web::Response::ok_empty(),
+                                Err(error) => web::Response::error_400_from_error(error),
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier, State};
+    use crate::{
+        modules::{fs::Fs, sqlite::sqlite_file},
+        simulation::{mock, settle, Simulation},
+    };
+    use approx::assert_relative_eq;
+    use futures::executor;
+    use rusqlite::OptionalExtension;
+    use std::{fs, time::Duration};
+    use tokio::time::Instant;
+
+    #[test]
+    fn rate_calculate() {
+        // 1 Wh within 3.6 s is 1 kW
+        assert_relative_eq!(
+            Device::rate_calculate(0.001, Duration::from_secs_f64(3.6)),
+            1.0
+        );
+        // 10 l within 1 minute is 0.6 m3/h
+        assert_relative_eq!(Device::rate_calculate(0.01, Duration::from_secs(60)), 0.6);
+    }
+
+    #[test]
+    fn pulses_and_decay() {
+        let configuration = Configuration {
+            name: "test".to_owned(),
+            pulses_per_unit: 1000.0,
+            rate_timeout: Duration::from_secs(600),
+        };
+        let mut state = State {
+            total: 10.0,
+            rate: 0.0,
+            pulse_last: None,
+        };
+        let start = Instant::now();
+
+        // first pulse has no reference for rate
+        Device::pulses(&configuration, &mut state, 1, start);
+        assert_relative_eq!(state.total, 10.001);
+        assert_eq!(state.rate, 0.0);
+
+        // 1 Wh within 3.6 s is 1 kW
+        Device::pulses(
+            &configuration,
+            &mut state,
+            1,
+            start + Duration::from_secs_f64(3.6),
+        );
+        assert_relative_eq!(state.total, 10.002);
+        assert_relative_eq!(state.rate, 1.0);
+
+        // second batch in the same instant is counted, but keeps the rate
+        Device::pulses(
+            &configuration,
+            &mut state,
+            1,
+            start + Duration::from_secs_f64(3.6),
+        );
+        assert_relative_eq!(state.total, 10.003);
+        assert_relative_eq!(state.rate, 1.0);
+
+        // no pulse for 36 s, so rate is at most 0.1 kW
+        Device::decay(
+            &configuration,
+            &mut state,
+            start + Duration::from_secs_f64(39.6),
+        );
+        assert_relative_eq!(state.rate, 0.1);
+
+        // no pulse within timeout
+        Device::decay(&configuration, &mut state, start + Duration::from_secs(610));
+        assert_eq!(state.rate, 0.0);
+        assert!(state.pulse_last.is_none());
+        assert_relative_eq!(state.total, 10.003);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn continuous_pulses_persisted() {
+        let fs = Fs::new(Default::default());
+        // total from previous run would be loaded on start
+        let name = "test_continuous_pulses".to_owned();
+        let path = sqlite_file(&fs, &format!("pulse_meter.{}", name));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(path.with_extension(format!("sqlite{}", suffix)));
+        }
+
+        let input = mock::EventSource::<()>::new();
+        let pulse_meter = Device::new(
+            Configuration {
+                name,
+                pulses_per_unit: 1.0,
+                rate_timeout: Duration::from_secs(60),
+            },
+            &fs,
+        );
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let pulse_meter_handle = simulation.device_add(&pulse_meter);
+        simulation.signals().d2d(
+            input_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            pulse_meter_handle,
+            SignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                // sqlite operations are serialized, so this waits for load
+                pulse_meter.sqlite.query(|_| ()).await;
+                settle().await;
+
+                // pulse every second, so the meter never goes idle, total
+                // should be saved within interval anyway
+                let mut total = None;
+                for _ in 0..(3 * Device::INTERVAL.as_secs()) {
+                    input.push(());
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    settle().await;
+
+                    // blocking the runtime keeps paused clock from advancing,
+                    // while sqlite operations are serialized, so persist
+                    // submitted on tick is already done
+                    total = executor::block_on(pulse_meter.sqlite.query(|connection| {
+                        connection
+                            .query_row("SELECT `total` FROM `meter` WHERE `id` = 0", [], |row| {
+                                row.get::<_, f64>(0)
+                            })
+                            .optional()
+                            .unwrap()
+                    }));
+                    if total.is_some() {
+                        break;
+                    }
+                }
+                assert!(total.unwrap() > 0.0);
+            })
+            .await
+            .unwrap();
+    }
+}
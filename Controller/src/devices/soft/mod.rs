@@ -1,10 +1,20 @@
 pub mod building;
 pub mod calendar;
+pub mod climate;
 pub mod converter;
 pub mod debug;
+pub mod diagnostics;
+pub mod energy;
+pub mod federation;
 pub mod logger;
 pub mod logic;
+pub mod metering;
+pub mod mode;
+pub mod net;
+pub mod notification;
+pub mod simulation;
 pub mod surveillance;
+pub mod system;
 pub mod time;
 pub mod value;
 pub mod web;
@@ -0,0 +1,292 @@
+use crate::{
+    datatypes::building::house_mode::HouseMode,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use async_trait::async_trait;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub initial: HouseMode,
+}
+
+// global house mode, selected from gui or by automations
+// last change wins, regardless if it came from signal or web
+#[derive(Debug)]
+pub struct Device {
+    value: RwLock<HouseMode>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<HouseMode>,
+    signal_output: signal::state_source::Signal<HouseMode>,
+    signal_home: signal::state_source::Signal<bool>,
+    signal_away: signal::state_source::Signal<bool>,
+    signal_night: signal::state_source::Signal<bool>,
+    signal_vacation: signal::state_source::Signal<bool>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let value = configuration.initial;
+
+        Self {
+            value: RwLock::new(value),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<HouseMode>::new(),
+            signal_output: signal::state_source::Signal::<HouseMode>::new(Some(value)),
+            signal_home: signal::state_source::Signal::<bool>::new(Some(value == HouseMode::Home)),
+            signal_away: signal::state_source::Signal::<bool>::new(Some(value == HouseMode::Away)),
+            signal_night: signal::state_source::Signal::<bool>::new(Some(
+                value == HouseMode::Night,
+            )),
+            signal_vacation: signal::state_source::Signal::<bool>::new(Some(
+                value == HouseMode::Vacation,
+            )),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn value_set(
+        &self,
+        value: HouseMode,
+    ) {
+        let mut value_lock = self.value.write();
+        if *value_lock == value {
+            return;
+        }
+        *value_lock = value;
+        drop(value_lock);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_output.set_one(Some(value));
+        signals_sources_changed |= self.signal_home.set_one(Some(value == HouseMode::Home));
+        signals_sources_changed |= self.signal_away.set_one(Some(value == HouseMode::Away));
+        signals_sources_changed |= self.signal_night.set_one(Some(value == HouseMode::Night));
+        signals_sources_changed |= self
+            .signal_vacation
+            .set_one(Some(value == HouseMode::Vacation));
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    fn signals_targets_changed(&self) {
+        // disconnected input keeps current mode
+        if let Some(Some(value)) = self.signal_input.take_pending() {
+            self.value_set(value);
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/mode/house_mode_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Output,
+    Home,
+    Away,
+    Night,
+    Vacation,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+            SignalIdentifier::Home => &self.signal_home as &dyn signal::Base,
+            SignalIdentifier::Away => &self.signal_away as &dyn signal::Base,
+            SignalIdentifier::Night => &self.signal_night as &dyn signal::Base,
+            SignalIdentifier::Vacation => &self.signal_vacation as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct GuiSummary {
+    value: HouseMode,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let value = *self.value.read();
+        Self::Value { value }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::POST => {
+                    let value = match request.body_parse_json::<HouseMode>() {
+                        Ok(value) => value,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed()
+                        }
+                    };
+
+                    self.value_set(value);
+
+                    async { web::Response::ok_empty() }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::building::house_mode::HouseMode,
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn decomposed() {
+        let input = mock::StateSource::<HouseMode>::new(None);
+        let house_mode = Device::new(Configuration {
+            initial: HouseMode::Home,
+        });
+        let night = mock::StateRecorder::<bool>::new();
+        let output = mock::StateRecorder::<HouseMode>::new();
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let house_mode_handle = simulation.device_add(&house_mode);
+        let night_handle = simulation.device_add(&night);
+        let output_handle = simulation.device_add(&output);
+        simulation.signals().d2d(
+            input_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            house_mode_handle,
+            SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            house_mode_handle,
+            SignalIdentifier::Night,
+            night_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            house_mode_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input.set(Some(HouseMode::Night));
+
+                // disconnecting input keeps last mode
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input.set(None);
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input.set(Some(HouseMode::Away));
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        night.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(1), Some(true)),
+            (Duration::from_secs(3), Some(false)),
+        ]);
+        output.trace().assert_entries(&[
+            (Duration::ZERO, Some(HouseMode::Home)),
+            (Duration::from_secs(1), Some(HouseMode::Night)),
+            (Duration::from_secs(3), Some(HouseMode::Away)),
+        ]);
+    }
+}
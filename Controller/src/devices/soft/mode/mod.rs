@@ -0,0 +1,2 @@
+pub mod house_mode_a;
+pub mod selector_a;
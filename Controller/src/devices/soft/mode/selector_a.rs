@@ -0,0 +1,400 @@
+use crate::{
+    datatypes::mode::Mode,
+    devices,
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, iter};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub modes: Box<[String]>,
+    // must be one of modes
+    pub initial: Option<String>,
+}
+
+// one of configured modes, selected from gui or by automations
+// last change wins, regardless if it came from signal or web
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+    // index in configuration modes
+    value: RwLock<Option<usize>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<Mode>,
+    signal_next: signal::event_target_last::Signal<()>,
+    signal_output: signal::state_source::Signal<Mode>,
+    signal_actives: Box<[signal::state_source::Signal<bool>]>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let value = configuration.initial.as_ref().and_then(|initial| {
+            let value = Self::mode_index(&configuration.modes, initial);
+            if value.is_none() {
+                log::warn!("initial mode {} is not one of modes", initial);
+            }
+            value
+        });
+
+        let signal_output = signal::state_source::Signal::<Mode>::new(
+            value.map(|value| Mode::new(configuration.modes[value].clone())),
+        );
+        let signal_actives = (0..configuration.modes.len())
+            .map(|mode_index| {
+                signal::state_source::Signal::<bool>::new(value.map(|value| value == mode_index))
+            })
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+            value: RwLock::new(value),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<Mode>::new(),
+            signal_next: signal::event_target_last::Signal::<()>::new(),
+            signal_output,
+            signal_actives,
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    fn mode_index(
+        modes: &[String],
+        mode: &str,
+    ) -> Option<usize> {
+        modes.iter().position(|candidate| candidate == mode)
+    }
+
+    fn value_set(
+        &self,
+        value: usize,
+    ) {
+        let mut value_lock = self.value.write();
+        if *value_lock == Some(value) {
+            return;
+        }
+        *value_lock = Some(value);
+        drop(value_lock);
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self
+            .signal_output
+            .set_one(Some(Mode::new(self.configuration.modes[value].clone())));
+        for (mode_index, signal_active) in self.signal_actives.iter().enumerate() {
+            signals_sources_changed |= signal_active.set_one(Some(mode_index == value));
+        }
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    // returns false if mode is not one of configured modes
+    fn mode_set(
+        &self,
+        mode: &str,
+    ) -> bool {
+        match Self::mode_index(&self.configuration.modes, mode) {
+            Some(value) => {
+                self.value_set(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn next(&self) {
+        if self.configuration.modes.is_empty() {
+            return;
+        }
+
+        let value = match *self.value.read() {
+            Some(value) => (value + 1) % self.configuration.modes.len(),
+            None => 0,
+        };
+        self.value_set(value);
+    }
+
+    fn signals_targets_changed(&self) {
+        // disconnected input keeps current mode
+        if let Some(Some(value)) = self.signal_input.take_pending() {
+            if !self.mode_set(value.as_str()) {
+                log::warn!(
+                    "{}: unknown mode {}",
+                    self.configuration.modes.join("/"),
+                    value
+                );
+            }
+        }
+        if self.signal_next.take_pending().is_some() {
+            self.next();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/mode/selector_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Next,
+    Output,
+    Active(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain([
+                (
+                    SignalIdentifier::Input,
+                    &self.signal_input as &dyn signal::Base,
+                ),
+                (
+                    SignalIdentifier::Next,
+                    &self.signal_next as &dyn signal::Base,
+                ),
+                (
+                    SignalIdentifier::Output,
+                    &self.signal_output as &dyn signal::Base,
+                ),
+            ])
+            .chain(
+                self.signal_actives
+                    .iter()
+                    .enumerate()
+                    .map(|(mode_index, signal_active)| {
+                        (
+                            SignalIdentifier::Active(mode_index),
+                            signal_active as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        let variants = self
+            .configuration
+            .modes
+            .iter()
+            .map(|mode| Cow::from(mode.clone()))
+            .collect::<Box<[_]>>();
+
+        hashmap! {
+            SignalIdentifier::Input => Metadata {
+                variants: Some(variants.clone()),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Output => Metadata {
+                variants: Some(variants),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    modes: Box<[String]>,
+    value: Option<String>,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let value = *self.value.read();
+        Self::Value {
+            modes: self.configuration.modes.clone(),
+            value: value.map(|value| self.configuration.modes[value].clone()),
+        }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::POST => {
+                    let value = match request.body_parse_json::<String>() {
+                        Ok(value) => value,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed()
+                        }
+                    };
+
+                    if !self.mode_set(&value) {
+                        let error = anyhow!("unknown mode {}", value);
+                        return async { web::Response::error_400_from_error(error) }.boxed();
+                    }
+
+                    async { web::Response::ok_empty() }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::mode::Mode,
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn select_and_cycle() {
+        let input = mock::StateSource::<Mode>::new(None);
+        let next = mock::EventSource::<()>::new();
+        let selector = Device::new(Configuration {
+            modes: vec!["off".to_owned(), "eco".to_owned(), "comfort".to_owned()]
+                .into_boxed_slice(),
+            initial: Some("off".to_owned()),
+        });
+        let comfort = mock::StateRecorder::<bool>::new();
+        let output = mock::StateRecorder::<Mode>::new();
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let next_handle = simulation.device_add(&next);
+        let selector_handle = simulation.device_add(&selector);
+        let comfort_handle = simulation.device_add(&comfort);
+        let output_handle = simulation.device_add(&output);
+        simulation.signals().d2d(
+            input_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            selector_handle,
+            SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            next_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            selector_handle,
+            SignalIdentifier::Next,
+        );
+        simulation.signals().d2d(
+            selector_handle,
+            SignalIdentifier::Active(2),
+            comfort_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            selector_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input.set(Some(Mode::new("comfort")));
+
+                // unknown mode is ignored
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input.set(Some(Mode::new("boost")));
+
+                // wraps around
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                next.push(());
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        comfort.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(1), Some(true)),
+            (Duration::from_secs(3), Some(false)),
+        ]);
+        output.trace().assert_entries(&[
+            (Duration::ZERO, Some(Mode::new("off"))),
+            (Duration::from_secs(1), Some(Mode::new("comfort"))),
+            (Duration::from_secs(3), Some(Mode::new("off"))),
+        ]);
+    }
+}
@@ -0,0 +1,432 @@
+use crate::{
+    datatypes::ratio::Ratio,
+    devices,
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use anyhow::{anyhow, ensure, Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{join_all, FutureExt},
+    select,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::VecDeque, time::Duration};
+use tokio::{net::TcpStream, process::Command, time::Instant};
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Probe {
+    // uses system ping, as raw sockets require elevated privileges
+    Icmp { host: String },
+    // host:port, succeeds when connection is established
+    Tcp { address: String },
+    // succeeds when name resolves through system resolver
+    Dns { name: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub probes: Box<[Probe]>,
+    pub interval: Duration,
+    pub timeout: Duration,
+
+    // internet is considered down after this many consecutive rounds with all
+    // probes failed, to avoid flapping
+    pub down_rounds: usize,
+    // number of rounds used to calculate loss
+    pub loss_rounds: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    // latest round, latency of each probe, None if failed
+    probes_latency: Box<[Option<Duration>]>,
+    // number of failed and total probes in each of recent rounds
+    rounds: VecDeque<(usize, usize)>,
+    rounds_down: usize,
+}
+impl State {
+    fn new(
+        probes_count: usize,
+        down_rounds: usize,
+    ) -> Self {
+        Self {
+            probes_latency: vec![None; probes_count].into_boxed_slice(),
+            rounds: VecDeque::new(),
+            // assume down until first probe succeeds
+            rounds_down: down_rounds,
+        }
+    }
+
+    fn round_push(
+        &mut self,
+        probes_latency: Box<[Option<Duration>]>,
+        loss_rounds: usize,
+    ) {
+        let failed = probes_latency
+            .iter()
+            .filter(|latency| latency.is_none())
+            .count();
+        let total = probes_latency.len();
+
+        if failed < total {
+            self.rounds_down = 0;
+        } else {
+            self.rounds_down += 1;
+        }
+
+        self.rounds.push_back((failed, total));
+        while self.rounds.len() > loss_rounds {
+            self.rounds.pop_front();
+        }
+
+        self.probes_latency = probes_latency;
+    }
+
+    fn up(
+        &self,
+        down_rounds: usize,
+    ) -> bool {
+        self.rounds_down < down_rounds
+    }
+    // lowest latency among successful probes of latest round
+    fn latency(&self) -> Option<Duration> {
+        self.probes_latency.iter().flatten().min().copied()
+    }
+    fn loss(&self) -> Option<Ratio> {
+        let (failed, total) =
+            self.rounds
+                .iter()
+                .fold((0, 0), |(failed, total), (round_failed, round_total)| {
+                    (failed + round_failed, total + round_total)
+                });
+        if total == 0 {
+            return None;
+        }
+        Some(Ratio::from_f64(failed as f64 / total as f64).unwrap())
+    }
+}
+
+// checks internet uplink, so notifications can fall back to other channels
+// (ex. sms) when it's down
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    state: RwLock<State>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_up: signal::state_source::Signal<bool>,
+    signal_latency: signal::state_source::Signal<Duration>,
+    signal_loss: signal::state_source::Signal<Ratio>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(!configuration.probes.is_empty());
+        assert!(configuration.down_rounds > 0);
+        assert!(configuration.loss_rounds > 0);
+
+        let state = State::new(configuration.probes.len(), configuration.down_rounds);
+
+        Self {
+            configuration,
+
+            state: RwLock::new(state),
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_up: signal::state_source::Signal::<bool>::new(None),
+            signal_latency: signal::state_source::Signal::<Duration>::new(None),
+            signal_loss: signal::state_source::Signal::<Ratio>::new(None),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // parses round trip time from ping output, ex.
+    // 64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=12.3 ms
+    fn ping_time_parse(output: &str) -> Result<Duration, Error> {
+        let time = output
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix("time="))
+            .ok_or_else(|| anyhow!("time missing"))?
+            .parse::<f64>()
+            .context("parse")?;
+        Ok(Duration::from_secs_f64(time / 1000.0))
+    }
+
+    async fn probe_once(
+        &self,
+        probe: &Probe,
+    ) -> Result<Duration, Error> {
+        let start = Instant::now();
+
+        match probe {
+            Probe::Icmp { host } => {
+                let output = Command::new("ping")
+                    .arg("-n")
+                    .arg("-c")
+                    .arg("1")
+                    .arg("-W")
+                    .arg(self.configuration.timeout.as_secs().max(1).to_string())
+                    .arg(host)
+                    .kill_on_drop(true)
+                    .output()
+                    .await
+                    .context("output")?;
+                ensure!(output.status.success(), "ping failed: {}", output.status);
+
+                let latency = Self::ping_time_parse(&String::from_utf8_lossy(&output.stdout))
+                    .context("ping_time_parse")?;
+                Ok(latency)
+            }
+            Probe::Tcp { address } => {
+                TcpStream::connect(address.as_str())
+                    .await
+                    .context("connect")?;
+                Ok(start.elapsed())
+            }
+            Probe::Dns { name } => {
+                let addresses = tokio::net::lookup_host((name.as_str(), 0))
+                    .await
+                    .context("lookup_host")?
+                    .count();
+                ensure!(addresses > 0, "no addresses");
+                Ok(start.elapsed())
+            }
+        }
+    }
+    async fn probe(
+        &self,
+        probe: &Probe,
+    ) -> Option<Duration> {
+        let result = tokio::time::timeout(self.configuration.timeout, self.probe_once(probe))
+            .await
+            .context("timeout")
+            .and_then(|result| result);
+
+        match result {
+            Ok(latency) => Some(latency),
+            Err(error) => {
+                log::debug!("connectivity: {:?}: {:?}", probe, error);
+                None
+            }
+        }
+    }
+
+    async fn round(&self) {
+        let probes_latency = join_all(
+            self.configuration
+                .probes
+                .iter()
+                .map(|probe| self.probe(probe)),
+        )
+        .await
+        .into_boxed_slice();
+
+        let mut state = self.state.write();
+        let up_last = state.up(self.configuration.down_rounds);
+        state.round_push(probes_latency, self.configuration.loss_rounds);
+        let up = state.up(self.configuration.down_rounds);
+        let latency = state.latency();
+        let loss = state.loss();
+        drop(state);
+
+        if up != up_last {
+            if up {
+                log::info!("connectivity: internet up");
+            } else {
+                log::warn!("connectivity: internet down");
+            }
+        }
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_up.set_one(Some(up));
+        signals_sources_changed |= self.signal_latency.set_one(latency);
+        signals_sources_changed |= self.signal_loss.set_one(loss);
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+        self.gui_summary_waker.wake();
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        loop {
+            select! {
+                () = self.round().fuse() => {},
+                () = exit_flag => break,
+            }
+
+            select! {
+                () = tokio::time::sleep(self.configuration.interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/net/connectivity_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Up,
+    Latency,
+    Loss,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Up => &self.signal_up as &dyn signal::Base,
+            SignalIdentifier::Latency => &self.signal_latency as &dyn signal::Base,
+            SignalIdentifier::Loss => &self.signal_loss as &dyn signal::Base,
+        }
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Up => Metadata {
+                name: Some(Cow::from("Internet connection")),
+                icon: Some(Cow::from("network")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Latency => Metadata {
+                name: Some(Cow::from("Latency")),
+                ..Metadata::default()
+            },
+            SignalIdentifier::Loss => Metadata {
+                name: Some(Cow::from("Packet loss")),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummaryProbe {
+    probe: Probe,
+    latency: Option<Duration>,
+}
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    up: bool,
+    loss: Option<Ratio>,
+    probes: Box<[GuiSummaryProbe]>,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let state = self.state.read();
+
+        let probes = self
+            .configuration
+            .probes
+            .iter()
+            .zip(state.probes_latency.iter())
+            .map(|(probe, latency)| GuiSummaryProbe {
+                probe: probe.clone(),
+                latency: *latency,
+            })
+            .collect::<Box<[_]>>();
+
+        Self::Value {
+            up: state.up(self.configuration.down_rounds),
+            loss: state.loss(),
+            probes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, State};
+    use std::time::Duration;
+
+    #[test]
+    fn ping_time_parse() {
+        let output = "PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.\n\
+            64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=12.3 ms\n";
+        assert_eq!(
+            Device::ping_time_parse(output).unwrap(),
+            Duration::from_micros(12300)
+        );
+        assert!(Device::ping_time_parse("100% packet loss").is_err());
+    }
+
+    #[test]
+    fn up_down_loss() {
+        let ms = |ms: u64| Some(Duration::from_millis(ms));
+
+        let mut state = State::new(2, 2);
+        assert!(!state.up(2));
+        assert_eq!(state.loss(), None);
+
+        state.round_push([ms(20), None].into(), 4);
+        assert!(state.up(2));
+        assert_eq!(state.latency(), ms(20));
+
+        // single failed round is tolerated
+        state.round_push([None, None].into(), 4);
+        assert!(state.up(2));
+        assert_eq!(state.latency(), None);
+        state.round_push([None, None].into(), 4);
+        assert!(!state.up(2));
+
+        state.round_push([ms(30), ms(10)].into(), 4);
+        assert!(state.up(2));
+        assert_eq!(state.latency(), ms(10));
+        assert_eq!(state.loss().unwrap().to_f64(), 5.0 / 8.0);
+
+        // oldest round dropped
+        state.round_push([ms(30), ms(10)].into(), 4);
+        assert_eq!(state.loss().unwrap().to_f64(), 4.0 / 8.0);
+    }
+}
@@ -0,0 +1,722 @@
+use crate::{
+    devices::{self, helpers::http as http_helpers},
+    modules::{
+        events,
+        fs::Fs,
+        sqlite::SQLite,
+        sqlite_migrations::{
+            self,
+            cli::Database,
+            graph::{Graph, GraphResolver},
+        },
+    },
+    signals,
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+    web::{self, uri_cursor},
+};
+use aes_gcm::{aead::Aead, Aes128Gcm, KeyInit};
+use anyhow::{anyhow, bail, ensure, Context, Error};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{BoxFuture, Future, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use hkdf::Hkdf;
+use maplit::hashmap;
+use p256::{
+    ecdh::EphemeralSecret,
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+    PublicKey, SecretKey,
+};
+use phf::phf_map;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{borrow::Cow, fs, io::Write, path::Path, time::Duration};
+use zeroize::Zeroizing;
+
+static MIGRATIONS: Graph = phf_map! {
+    1u32 => phf_map! {
+        0u32 => Some("
+            CREATE TABLE `subscriptions` (
+                `endpoint` TEXT PRIMARY KEY NOT NULL,
+                `p256dh` BLOB NOT NULL,
+                `auth` BLOB NOT NULL,
+                `created` INTEGER NOT NULL -- unix seconds
+            ) STRICT;
+        "),
+    },
+};
+static MIGRATIONS_RESOLVER: GraphResolver<'static> = GraphResolver(&MIGRATIONS);
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across web push devices
+    pub name: String,
+    // contact of the server operator, eg. "mailto:admin@example.com"
+    pub subject: String,
+    // events below this severity are not pushed
+    pub severity_min: events::Severity,
+}
+
+// push subscription of the browser, as returned by PushManager.subscribe()
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Subscription {
+    pub endpoint: String,
+    // user agent public key, uncompressed point
+    pub p256dh: Box<[u8]>,
+    pub auth: Box<[u8]>,
+}
+impl Subscription {
+    pub fn from_json(subscription_json: SubscriptionJson) -> Result<Self, Error> {
+        ensure!(
+            subscription_json.endpoint.starts_with("https://"),
+            "endpoint must be https url"
+        );
+
+        let p256dh = URL_SAFE_NO_PAD
+            .decode(subscription_json.keys.p256dh.trim_end_matches('='))
+            .context("p256dh")?
+            .into_boxed_slice();
+        PublicKey::from_sec1_bytes(&p256dh).context("from_sec1_bytes")?;
+
+        let auth = URL_SAFE_NO_PAD
+            .decode(subscription_json.keys.auth.trim_end_matches('='))
+            .context("auth")?
+            .into_boxed_slice();
+        ensure!(auth.len() == 16, "invalid auth length");
+
+        Ok(Self {
+            endpoint: subscription_json.endpoint,
+            p256dh,
+            auth,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionJsonKeys {
+    p256dh: String,
+    auth: String,
+}
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionJson {
+    endpoint: String,
+    keys: SubscriptionJsonKeys,
+}
+
+// message encryption, see rfc8291
+fn encrypt(
+    subscription: &Subscription,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    const RECORD_SIZE: u32 = 4096;
+
+    let ua_public = PublicKey::from_sec1_bytes(&subscription.p256dh).context("from_sec1_bytes")?;
+    let ua_public_bytes = ua_public.to_encoded_point(false);
+
+    let as_secret = EphemeralSecret::random(&mut OsRng);
+    let as_public_bytes = as_secret.public_key().to_encoded_point(false);
+
+    let ecdh_secret = as_secret.diffie_hellman(&ua_public);
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public_bytes.as_bytes());
+    key_info.extend_from_slice(as_public_bytes.as_bytes());
+    let mut ikm = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(
+        Some(&subscription.auth),
+        ecdh_secret.raw_secret_bytes().as_slice(),
+    )
+    .expand(&key_info, &mut *ikm)
+    .map_err(|_| anyhow!("ikm expand failed"))?;
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), &*ikm);
+    let mut cek = Zeroizing::new([0u8; 16]);
+    hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut *cek)
+        .map_err(|_| anyhow!("cek expand failed"))?;
+    let mut nonce = [0u8; 12];
+    hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce)
+        .map_err(|_| anyhow!("nonce expand failed"))?;
+
+    // single record, delimited as the last one
+    let mut record = plaintext.to_vec();
+    record.push(0x02);
+    ensure!(
+        record.len() + 16 <= RECORD_SIZE as usize,
+        "message too large"
+    );
+    let ciphertext = Aes128Gcm::new_from_slice(&*cek)
+        .context("new_from_slice")?
+        .encrypt(&nonce.into(), record.as_slice())
+        .map_err(|_| anyhow!("encrypt failed"))?;
+
+    let mut body = Vec::<u8>::with_capacity(16 + 4 + 1 + 65 + ciphertext.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public_bytes.as_bytes().len() as u8);
+    body.extend_from_slice(as_public_bytes.as_bytes());
+    body.extend_from_slice(&ciphertext);
+
+    Ok(body)
+}
+
+// application server identification, see rfc8292
+fn vapid_authorization(
+    signing_key: &SigningKey,
+    endpoint: &str,
+    subject: &str,
+    expires: DateTime<Utc>,
+) -> Result<String, Error> {
+    let endpoint = endpoint.parse::<http::Uri>().context("endpoint")?;
+    let audience = format!(
+        "{}://{}",
+        endpoint.scheme_str().context("scheme")?,
+        endpoint.authority().context("authority")?
+    );
+
+    let header = URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(
+        serde_json::to_vec(&serde_json::json!({
+            "aud": audience,
+            "exp": expires.timestamp(),
+            "sub": subject,
+        }))
+        .context("to_vec")?,
+    );
+    let signing_input = format!("{}.{}", header, claims);
+    let signature: Signature = signing_key.sign(signing_input.as_bytes());
+    let token = format!(
+        "{}.{}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    );
+
+    let public_key = URL_SAFE_NO_PAD.encode(
+        signing_key
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes(),
+    );
+
+    Ok(format!("vapid t={}, k={}", token, public_key))
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'e> {
+    id: u64,
+    timestamp: DateTime<Utc>,
+    severity: events::Severity,
+    source: &'e str,
+    message: &'e str,
+    // relative to gui root
+    attachment: Option<String>,
+}
+impl<'e> Message<'e> {
+    fn from_event(event: &'e events::Event) -> Self {
+        Self {
+            id: event.id,
+            timestamp: event.timestamp,
+            severity: event.severity,
+            source: &event.source,
+            message: &event.message,
+            attachment: event
+                .attachment
+                .as_ref()
+                .map(|_| format!("/events/events/{}/attachment", event.id)),
+        }
+    }
+}
+
+// forwards reported events to subscribed browsers, so gui installed as pwa
+// gets notified when closed
+// vapid key is kept in separate file, the same way as secrets key
+#[derive(Debug)]
+pub struct Device<'f> {
+    configuration: Configuration,
+    signing_key: SigningKey,
+
+    sqlite: SQLite<'f>,
+}
+impl<'f> Device<'f> {
+    const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+    const VAPID_EXPIRES: Duration = Duration::from_secs(12 * 60 * 60);
+
+    pub fn new(
+        configuration: Configuration,
+        fs: &'f Fs,
+    ) -> Result<Self, Error> {
+        let signing_key = Self::signing_key_load_or_create(
+            &fs.persistent_data_directory()
+                .join(format!("{}.key", Self::sqlite_name(&configuration.name))),
+        )
+        .context("signing_key_load_or_create")?;
+
+        let sqlite = SQLite::new(Self::sqlite_name(&configuration.name), fs);
+
+        Ok(Self {
+            configuration,
+            signing_key,
+
+            sqlite,
+        })
+    }
+
+    fn sqlite_name(name: &str) -> String {
+        format!("web_push.{}", name)
+    }
+    // for running migrations before startup, see sqlite_migrations::cli
+    pub fn migrations_database(name: &str) -> Database<'static> {
+        Database {
+            name: Self::sqlite_name(name),
+            resolver: &MIGRATIONS_RESOLVER,
+        }
+    }
+
+    fn signing_key_load_or_create(path: &Path) -> Result<SigningKey, Error> {
+        match fs::read(path) {
+            Ok(key) => {
+                let key = Zeroizing::new(key);
+                let secret_key = SecretKey::from_slice(&key).context("from_slice")?;
+                return Ok(SigningKey::from(secret_key));
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error).context("read"),
+        }
+
+        let secret_key = SecretKey::random(&mut OsRng);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path).context("open")?;
+        file.write_all(&secret_key.to_bytes())
+            .context("write_all")?;
+        file.sync_all().context("sync_all")?;
+
+        log::info!("web push: new vapid key created");
+
+        Ok(SigningKey::from(secret_key))
+    }
+
+    // to be passed as applicationServerKey to PushManager.subscribe()
+    pub fn public_key(&self) -> String {
+        URL_SAFE_NO_PAD.encode(
+            self.signing_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes(),
+        )
+    }
+
+    pub fn subscriptions(
+        &self
+    ) -> impl Future<Output = Result<Box<[Subscription]>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let subscriptions = transaction
+                    .prepare("SELECT `endpoint`, `p256dh`, `auth` FROM `subscriptions`")
+                    .context("prepare")?
+                    .query_map([], |row| {
+                        Ok(Subscription {
+                            endpoint: row.get(0)?,
+                            p256dh: row.get::<_, Vec<u8>>(1)?.into_boxed_slice(),
+                            auth: row.get::<_, Vec<u8>>(2)?.into_boxed_slice(),
+                        })
+                    })
+                    .context("query_map")?
+                    .collect::<rusqlite::Result<Box<[_]>>>()
+                    .context("collect")?;
+                Ok(subscriptions)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    pub fn subscription_add(
+        &self,
+        subscription: Subscription,
+    ) -> impl Future<Output = Result<(), Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO `subscriptions` (`endpoint`, `p256dh`, `auth`, `created`) VALUES (?, ?, ?, ?)",
+                        rusqlite::params![
+                            subscription.endpoint,
+                            subscription.p256dh,
+                            subscription.auth,
+                            Utc::now().timestamp()
+                        ],
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // resolves to false if subscription did not exist
+    pub fn subscription_remove(
+        &self,
+        endpoint: String,
+    ) -> impl Future<Output = Result<bool, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let removed = transaction
+                    .execute(
+                        "DELETE FROM `subscriptions` WHERE `endpoint` = ?",
+                        [&endpoint],
+                    )
+                    .context("execute")?;
+                Ok(removed > 0)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+        // creates or migrates the tables
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, transaction).context("execute")?;
+
+        Ok(())
+    }
+
+    // resolves to false if subscription is gone and should be removed
+    async fn send(
+        &self,
+        subscription: &Subscription,
+        payload: &[u8],
+    ) -> Result<bool, Error> {
+        let body = encrypt(subscription, payload).context("encrypt")?;
+        let authorization = vapid_authorization(
+            &self.signing_key,
+            &subscription.endpoint,
+            &self.configuration.subject,
+            Utc::now() + Self::VAPID_EXPIRES,
+        )
+        .context("vapid_authorization")?;
+
+        let response = http_helpers::client()
+            .post(&subscription.endpoint)
+            .timeout(http_helpers::request_timeout())
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .header(reqwest::header::CONTENT_ENCODING, "aes128gcm")
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header("TTL", Self::TTL.as_secs())
+            .body(body)
+            .send()
+            .await
+            .context("send")?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => Ok(false),
+            status => bail!("push service responded with {}", status),
+        }
+    }
+
+    async fn event_push(
+        &self,
+        event: &events::Event,
+    ) -> Result<(), Error> {
+        let payload = serde_json::to_vec(&Message::from_event(event)).context("to_vec")?;
+
+        let subscriptions = self.subscriptions().await.context("subscriptions")?;
+        for subscription in subscriptions.iter() {
+            match self.send(subscription, &payload).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::info!(
+                        "{}: subscription {} expired",
+                        self.configuration.name,
+                        subscription.endpoint
+                    );
+                    self.subscription_remove(subscription.endpoint.clone())
+                        .await
+                        .context("subscription_remove")?;
+                }
+                Err(error) => {
+                    log::warn!(
+                        "{}: push to {} failed: {:?}",
+                        self.configuration.name,
+                        subscription.endpoint,
+                        error
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let reporter = events::reporter();
+
+        // only events reported after start are pushed
+        let mut receiver = reporter.receiver();
+        let mut id_since = reporter.id_next();
+
+        loop {
+            select! {
+                () = receiver.select_next_some() => {},
+                () = exit_flag => break,
+            }
+
+            let events = reporter.events(id_since);
+            if let Some(event) = events.last() {
+                id_since = event.id + 1;
+            }
+
+            for event in events.iter() {
+                if event.severity < self.configuration.severity_min {
+                    continue;
+                }
+                if let Err(error) = self.event_push(event).await.context("event_push") {
+                    log::error!("{}: {:?}", self.configuration.name, error);
+                }
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'f> devices::Device for Device<'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/notification/web_push_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl<'f> Runnable for Device<'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f> signals::Device for Device<'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {}
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionRemoveRequest {
+    endpoint: String,
+}
+
+impl<'f> uri_cursor::Handler for Device<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("public-key", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let public_key = self.public_key();
+                        async { web::Response::ok_json(public_key) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("subscriptions", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        let subscription = match request
+                            .body_parse_json::<SubscriptionJson>()
+                            .and_then(Subscription::from_json)
+                        {
+                            Ok(subscription) => subscription,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+
+                        let name = self.configuration.name.clone();
+                        let added = self.subscription_add(subscription);
+                        async move {
+                            match added.await {
+                                Ok(()) => web::Response::ok_empty(),
+                                Err(error) => {
+                                    log::error!("{}: subscription_add: {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    http::Method::DELETE => {
+                        let endpoint = match request.body_parse_json::<SubscriptionRemoveRequest>()
+                        {
+                            Ok(subscription_remove_request) => subscription_remove_request.endpoint,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+
+                        let name = self.configuration.name.clone();
+                        let removed = self.subscription_remove(endpoint);
+                        async move {
+                            match removed.await {
+                                Ok(true) => web::Response::ok_empty(),
+                                Ok(false) => web::Response::error_404(),
+                                Err(error) => {
+                                    log::error!("{}: subscription_remove: {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encrypt, vapid_authorization, Subscription};
+    use aes_gcm::{aead::Aead, Aes128Gcm, KeyInit};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use chrono::{TimeZone, Utc};
+    use hkdf::Hkdf;
+    use p256::{
+        ecdsa::{signature::Verifier, Signature, SigningKey},
+        elliptic_curve::sec1::ToEncodedPoint,
+        PublicKey, SecretKey,
+    };
+    use rand::rngs::OsRng;
+    use sha2::Sha256;
+
+    // decrypts the message the way user agent does
+    #[test]
+    fn encrypt_roundtrip() {
+        let ua_secret = SecretKey::random(&mut OsRng);
+        let ua_public = ua_secret.public_key().to_encoded_point(false);
+        let auth = [3u8; 16];
+        let subscription = Subscription {
+            endpoint: "https://push.example.com/abc".to_owned(),
+            p256dh: ua_public.as_bytes().into(),
+            auth: auth.into(),
+        };
+
+        let body = encrypt(&subscription, b"doorbell").unwrap();
+
+        let (salt, body) = body.split_at(16);
+        let (record_size, body) = body.split_at(4);
+        assert_eq!(record_size, 4096u32.to_be_bytes());
+        let (key_id_length, body) = body.split_at(1);
+        let (as_public, ciphertext) = body.split_at(key_id_length[0] as usize);
+
+        let as_public = PublicKey::from_sec1_bytes(as_public).unwrap();
+        let ecdh_secret =
+            p256::ecdh::diffie_hellman(ua_secret.to_nonzero_scalar(), as_public.as_affine());
+
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(ua_public.as_bytes());
+        key_info.extend_from_slice(as_public.to_encoded_point(false).as_bytes());
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&auth), ecdh_secret.raw_secret_bytes().as_slice())
+            .expand(&key_info, &mut ikm)
+            .unwrap();
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let mut cek = [0u8; 16];
+        hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+            .unwrap();
+        let mut nonce = [0u8; 12];
+        hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce)
+            .unwrap();
+
+        let record = Aes128Gcm::new_from_slice(&cek)
+            .unwrap()
+            .decrypt(&nonce.into(), ciphertext)
+            .unwrap();
+        assert_eq!(record, b"doorbell\x02");
+    }
+
+    #[test]
+    fn vapid() {
+        let signing_key = SigningKey::from(SecretKey::from_slice(&[5u8; 32]).unwrap());
+
+        let authorization = vapid_authorization(
+            &signing_key,
+            "https://push.example.com/abc?x=1",
+            "mailto:admin@example.com",
+            Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        )
+        .unwrap();
+
+        let (token, public_key) = authorization
+            .strip_prefix("vapid t=")
+            .unwrap()
+            .split_once(", k=")
+            .unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(public_key).unwrap(),
+            signing_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+        );
+
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+        signing_key
+            .verifying_key()
+            .verify(signing_input.as_bytes(), &signature)
+            .unwrap();
+
+        let claims = signing_input.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap();
+        assert_eq!(claims["aud"], "https://push.example.com");
+        assert_eq!(claims["exp"], 1_700_000_000);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{
+    datatypes::ratio::Ratio,
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub position_initial: Ratio,
+    // time to move between fully open and fully closed
+    pub travel_time: Duration,
+
+    // how often position output is updated while moving
+    pub step: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    Stop,
+    Up,
+    Down,
+}
+impl Direction {
+    // both relays energized is not a valid state for real motor, motor stops
+    fn from_relays(
+        up: bool,
+        down: bool,
+    ) -> Self {
+        match (up, down) {
+            (true, false) => Self::Up,
+            (false, true) => Self::Down,
+            _ => Self::Stop,
+        }
+    }
+}
+
+// roller blind driven by two relays (up / down), moving with constant speed
+// and stopping at end positions
+// position is 0.0 for fully open (up) and 1.0 for fully closed (down)
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    position: RwLock<f64>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_up: signal::state_target_last::Signal<bool>,
+    signal_down: signal::state_target_last::Signal<bool>,
+    signal_position: signal::state_source::Signal<Ratio>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.travel_time > Duration::ZERO);
+        assert!(configuration.step > Duration::ZERO);
+
+        let position = configuration.position_initial.to_f64();
+
+        Self {
+            configuration,
+
+            position: RwLock::new(position),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_up: signal::state_target_last::Signal::<bool>::new(),
+            signal_down: signal::state_target_last::Signal::<bool>::new(),
+            signal_position: signal::state_source::Signal::<Ratio>::new(None),
+        }
+    }
+
+    fn position_next(
+        configuration: &Configuration,
+        position: f64,
+        direction: Direction,
+        elapsed: Duration,
+    ) -> f64 {
+        let distance = elapsed.as_secs_f64() / configuration.travel_time.as_secs_f64();
+        let position = match direction {
+            Direction::Stop => position,
+            Direction::Up => position - distance,
+            Direction::Down => position + distance,
+        };
+        position.clamp(0.0, 1.0)
+    }
+
+    fn update(
+        &self,
+        direction: Direction,
+        elapsed: Duration,
+    ) {
+        let mut position_lock = self.position.write();
+        let position = Self::position_next(&self.configuration, *position_lock, direction, elapsed);
+        *position_lock = position;
+        drop(position_lock);
+
+        if self
+            .signal_position
+            .set_one(Some(Ratio::from_f64(position).unwrap()))
+        {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut direction = Direction::Stop;
+        let mut updated_last = Instant::now();
+
+        loop {
+            let now = Instant::now();
+            self.update(direction, now.duration_since(updated_last));
+            updated_last = now;
+
+            direction = Direction::from_relays(
+                self.signal_up.take_last().value.unwrap_or(false),
+                self.signal_down.take_last().value.unwrap_or(false),
+            );
+
+            // end position reached, nothing to update until relays change
+            let moving = match direction {
+                Direction::Stop => false,
+                Direction::Up => *self.position.read() > 0.0,
+                Direction::Down => *self.position.read() < 1.0,
+            };
+            let step = if moving {
+                timer_wheel::sleep(self.configuration.step).left_future()
+            } else {
+                future::pending().right_future()
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = step.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/simulation/blind_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Up,
+    Down,
+    Position,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Up => &self.signal_up as &dyn signal::Base,
+            SignalIdentifier::Down => &self.signal_down as &dyn signal::Base,
+            SignalIdentifier::Position => &self.signal_position as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::ratio::Ratio,
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn travel() {
+        let up = mock::StateSource::<bool>::new(None);
+        let down = mock::StateSource::<bool>::new(None);
+        let blind = Device::new(Configuration {
+            position_initial: Ratio::zero(),
+            travel_time: Duration::from_secs(20),
+            step: Duration::from_secs(5),
+        });
+        let position = mock::StateRecorder::<Ratio>::new();
+
+        let mut simulation = Simulation::new();
+        let up_handle = simulation.device_add(&up);
+        let down_handle = simulation.device_add(&down);
+        let blind_handle = simulation.device_add(&blind);
+        let position_handle = simulation.device_add(&position);
+        simulation.signals().d2d(
+            up_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            blind_handle,
+            SignalIdentifier::Up,
+        );
+        simulation.signals().d2d(
+            down_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            blind_handle,
+            SignalIdentifier::Down,
+        );
+        simulation.signals().d2d(
+            blind_handle,
+            SignalIdentifier::Position,
+            position_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                // half way down
+                down.set(Some(true));
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                down.set(Some(false));
+                tokio::time::sleep(Duration::from_secs(10)).await;
+
+                // fully up, relay kept on after end position is reached
+                up.set(Some(true));
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            })
+            .await
+            .unwrap();
+
+        let ratio = |value: f64| Some(Ratio::from_f64(value).unwrap());
+        position.trace().assert_entries(&[
+            (Duration::ZERO, ratio(0.0)),
+            (Duration::from_secs(5), ratio(0.25)),
+            (Duration::from_secs(10), ratio(0.5)),
+            (Duration::from_secs(25), ratio(0.25)),
+            (Duration::from_secs(30), ratio(0.0)),
+        ]);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    datatypes::{angle::AngleNormalizedHalfZeroCentered, ratio::Ratio, real::Real},
+    devices,
+    signals::{self, metadata::Metadata, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // indoor illuminance from daylight with sun in zenith and blind open, in lx
+    pub daylight_max: f64,
+    // part of daylight passing through fully closed blind
+    pub blind_closed_transmittance: f64,
+    // illuminance from artificial light at full brightness, in lx
+    pub light_max: f64,
+}
+
+// indoor illuminance from daylight (given by sun elevation, eg. from
+// soft/calendar/solar_position_a), shaded by blind, plus artificial light
+// closes the loop for lighting and shading controllers without hardware
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_sun_elevation: signal::state_target_last::Signal<AngleNormalizedHalfZeroCentered>,
+    signal_blind_position: signal::state_target_last::Signal<Ratio>,
+    signal_light: signal::state_target_last::Signal<Ratio>,
+    signal_illuminance: signal::state_source::Signal<Real>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.daylight_max >= 0.0);
+        assert!((0.0..=1.0).contains(&configuration.blind_closed_transmittance));
+        assert!(configuration.light_max >= 0.0);
+
+        Self {
+            configuration,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_sun_elevation: signal::state_target_last::Signal::<
+                AngleNormalizedHalfZeroCentered,
+            >::new(),
+            signal_blind_position: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_light: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_illuminance: signal::state_source::Signal::<Real>::new(None),
+        }
+    }
+
+    // missing sun elevation is treated as night, missing blind position as
+    // open, missing light as off
+    fn illuminance(
+        configuration: &Configuration,
+        sun_elevation: Option<AngleNormalizedHalfZeroCentered>,
+        blind_position: Option<Ratio>,
+        light: Option<Ratio>,
+    ) -> f64 {
+        let daylight = sun_elevation.map_or(0.0, |sun_elevation| {
+            configuration.daylight_max * sun_elevation.to_radians().sin().max(0.0)
+        });
+
+        let blind_position = blind_position.map_or(0.0, |blind_position| blind_position.to_f64());
+        let transmittance = 1.0 - blind_position * (1.0 - configuration.blind_closed_transmittance);
+
+        let light = light.map_or(0.0, |light| light.to_f64()) * configuration.light_max;
+
+        daylight * transmittance + light
+    }
+
+    fn signals_targets_changed(&self) {
+        let illuminance = Self::illuminance(
+            &self.configuration,
+            self.signal_sun_elevation.take_last().value,
+            self.signal_blind_position.take_last().value,
+            self.signal_light.take_last().value,
+        );
+
+        if self
+            .signal_illuminance
+            .set_one(Some(Real::from_f64(illuminance.round()).unwrap()))
+        {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed();
+
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/simulation/light_level_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    SunElevation,
+    BlindPosition,
+    Light,
+    Illuminance,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::SunElevation => &self.signal_sun_elevation as &dyn signal::Base,
+            SignalIdentifier::BlindPosition => &self.signal_blind_position as &dyn signal::Base,
+            SignalIdentifier::Light => &self.signal_light as &dyn signal::Base,
+            SignalIdentifier::Illuminance => &self.signal_illuminance as &dyn signal::Base,
+        }
+    }
+
+    fn metadata(&self) -> signals::MetadataByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Illuminance => Metadata {
+                name: Some(Cow::from("Illuminance")),
+                unit: Some(Cow::from("lx")),
+                min: Some(0.0),
+                precision: Some(0),
+                ..Metadata::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device};
+    use crate::datatypes::{angle::AngleNormalizedHalfZeroCentered, ratio::Ratio};
+
+    #[test]
+    fn illuminance() {
+        let configuration = Configuration {
+            daylight_max: 1000.0,
+            blind_closed_transmittance: 0.1,
+            light_max: 300.0,
+        };
+        let elevation =
+            |degrees: f64| Some(AngleNormalizedHalfZeroCentered::from_degrees(degrees).unwrap());
+        let ratio = |value: f64| Some(Ratio::from_f64(value).unwrap());
+
+        // night, nothing connected
+        assert_eq!(Device::illuminance(&configuration, None, None, None), 0.0);
+        assert_eq!(
+            Device::illuminance(&configuration, elevation(-10.0), None, ratio(0.5)),
+            150.0
+        );
+
+        // sun 30 degrees above horizon
+        let illuminance = Device::illuminance(&configuration, elevation(30.0), None, None);
+        assert!((illuminance - 500.0).abs() < 1e-9);
+        let illuminance = Device::illuminance(&configuration, elevation(30.0), ratio(1.0), None);
+        assert!((illuminance - 50.0).abs() < 1e-9);
+        let illuminance =
+            Device::illuminance(&configuration, elevation(30.0), ratio(0.5), ratio(1.0));
+        assert!((illuminance - 575.0).abs() < 1e-9);
+    }
+}
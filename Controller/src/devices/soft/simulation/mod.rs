@@ -0,0 +1,3 @@
+pub mod blind_a;
+pub mod light_level_a;
+pub mod thermal_room_a;
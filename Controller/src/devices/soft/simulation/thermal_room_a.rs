@@ -0,0 +1,297 @@
+use crate::{
+    datatypes::{ratio::Ratio, temperature::Temperature},
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select, stream::StreamExt};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub temperature_initial: Temperature,
+    // used while outdoor temperature is not connected or unknown
+    pub temperature_outdoor_default: Temperature,
+
+    // heat lost through walls, windows and ventilation per kelvin of indoor -
+    // outdoor difference, in W/K
+    pub heat_loss: f64,
+    // heat needed to warm up the room (air, walls, furniture) by one kelvin, in
+    // J/K
+    pub heat_capacity: f64,
+    // heater output at full demand, in W
+    pub heater_power: f64,
+
+    // how often temperature output is updated
+    pub step: Duration,
+}
+
+// single zone room heated by heater with given power, losing heat to the
+// outside proportionally to temperature difference
+// closes the loop for heating controllers without hardware, eg. in demo mode
+// or when testing control strategies
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    temperature: RwLock<f64>, // kelvins
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_heating: signal::state_target_last::Signal<Ratio>,
+    signal_temperature_outdoor: signal::state_target_last::Signal<Temperature>,
+    signal_temperature: signal::state_source::Signal<Temperature>,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        assert!(configuration.heat_loss > 0.0);
+        assert!(configuration.heat_capacity > 0.0);
+        assert!(configuration.heater_power >= 0.0);
+        assert!(configuration.step > Duration::ZERO);
+
+        let temperature = configuration.temperature_initial.to_kelvins();
+
+        Self {
+            configuration,
+
+            temperature: RwLock::new(temperature),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_heating: signal::state_target_last::Signal::<Ratio>::new(),
+            signal_temperature_outdoor: signal::state_target_last::Signal::<Temperature>::new(),
+            signal_temperature: signal::state_source::Signal::<Temperature>::new(None),
+        }
+    }
+
+    // exact solution of first order model for constant inputs, so result does
+    // not depend on step length
+    fn temperature_next(
+        configuration: &Configuration,
+        temperature: f64,
+        temperature_outdoor: f64,
+        heating: f64,
+        elapsed: Duration,
+    ) -> f64 {
+        let temperature_steady =
+            temperature_outdoor + configuration.heater_power * heating / configuration.heat_loss;
+        let time_constant = configuration.heat_capacity / configuration.heat_loss;
+
+        temperature_steady
+            + (temperature - temperature_steady) * (-elapsed.as_secs_f64() / time_constant).exp()
+    }
+
+    // inputs are treated as constant since last update
+    fn update(
+        &self,
+        heating: Option<Ratio>,
+        temperature_outdoor: Option<Temperature>,
+        elapsed: Duration,
+    ) {
+        let heating = heating.map_or(0.0, |heating| heating.to_f64());
+        let temperature_outdoor = temperature_outdoor
+            .unwrap_or(self.configuration.temperature_outdoor_default)
+            .to_kelvins();
+
+        let mut temperature_lock = self.temperature.write();
+        let temperature = Self::temperature_next(
+            &self.configuration,
+            *temperature_lock,
+            temperature_outdoor,
+            heating,
+            elapsed,
+        );
+        *temperature_lock = temperature;
+        drop(temperature_lock);
+
+        // rounded like real sensor would do, not to flood targets with changes
+        // in the last decimal places
+        let temperature = Temperature::from_kelvins((temperature * 100.0).round() / 100.0).unwrap();
+        if self.signal_temperature.set_one(Some(temperature)) {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut heating = None::<Ratio>;
+        let mut temperature_outdoor = None::<Temperature>;
+        let mut updated_last = Instant::now();
+
+        loop {
+            let now = Instant::now();
+            self.update(
+                heating,
+                temperature_outdoor,
+                now.duration_since(updated_last),
+            );
+            updated_last = now;
+
+            heating = self.signal_heating.take_last().value;
+            temperature_outdoor = self.signal_temperature_outdoor.take_last().value;
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = timer_wheel::sleep(self.configuration.step).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/simulation/thermal_room_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    // heater power, boolean outputs can be connected through
+    // logic/encoders_decoders/boolean_to_ratio_a
+    Heating,
+    TemperatureOutdoor,
+    Temperature,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Heating => &self.signal_heating as &dyn signal::Base,
+            SignalIdentifier::TemperatureOutdoor => &self.signal_temperature_outdoor as &dyn signal::Base,
+            SignalIdentifier::Temperature => &self.signal_temperature as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::{
+        datatypes::{
+            ratio::Ratio,
+            temperature::{Temperature, Unit},
+        },
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+
+    fn configuration() -> Configuration {
+        Configuration {
+            temperature_initial: Temperature::from_unit(Unit::Celsius, 20.0).unwrap(),
+            temperature_outdoor_default: Temperature::from_unit(Unit::Celsius, 0.0).unwrap(),
+            heat_loss: 100.0,
+            heat_capacity: 360_000.0, // 1 hour time constant
+            heater_power: 3000.0,
+            step: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn temperature_next() {
+        let configuration = configuration();
+        let hour = Duration::from_secs(3600);
+
+        // steady state is 30K above outdoor temperature
+        let temperature = Device::temperature_next(&configuration, 293.15, 273.15, 1.0, hour);
+        let expected = 303.15 - 10.0 * (-1.0f64).exp();
+        assert!((temperature - expected).abs() < 1e-9);
+
+        // two half steps give the same result
+        let half = hour / 2;
+        let temperature_half = Device::temperature_next(&configuration, 293.15, 273.15, 1.0, half);
+        let temperature_half =
+            Device::temperature_next(&configuration, temperature_half, 273.15, 1.0, half);
+        assert!((temperature - temperature_half).abs() < 1e-9);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heating_closes_loop() {
+        let heating = mock::StateSource::<Ratio>::new(None);
+        let thermal_room = Device::new(configuration());
+        let temperature = mock::StateRecorder::<Temperature>::new();
+
+        let mut simulation = Simulation::new();
+        let heating_handle = simulation.device_add(&heating);
+        let thermal_room_handle = simulation.device_add(&thermal_room);
+        let temperature_handle = simulation.device_add(&temperature);
+        simulation.signals().d2d(
+            heating_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            thermal_room_handle,
+            SignalIdentifier::Heating,
+        );
+        simulation.signals().d2d(
+            thermal_room_handle,
+            SignalIdentifier::Temperature,
+            temperature_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        let celsius = |trace_time: u64| {
+            temperature
+                .trace()
+                .value_at(Duration::from_secs(trace_time))
+                .cloned()
+                .flatten()
+                .unwrap()
+                .to_unit(Unit::Celsius)
+        };
+
+        simulation
+            .run(async {
+                // cooling down without heating
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                heating.set(Some(Ratio::full()));
+                tokio::time::sleep(Duration::from_secs(3600 * 10)).await;
+            })
+            .await
+            .unwrap();
+
+        assert!((celsius(0) - 20.0).abs() < 0.01);
+        assert!((celsius(3600) - 20.0 * (-1.0f64).exp()).abs() < 0.01);
+        // settled at steady state
+        assert!((celsius(3600 * 11) - 30.0).abs() < 0.01);
+    }
+}
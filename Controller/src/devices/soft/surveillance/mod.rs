@@ -1,2 +1,4 @@
+pub mod motion_zones_a;
 pub mod rtsp_recorder;
 pub mod snapshot;
+pub mod timelapse_a;
@@ -0,0 +1,353 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, iter, time::Duration};
+use tokio::time::Instant;
+
+// logical zone (driveway, terrace) built from raw camera regions
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Zone {
+    pub name: String,
+    // indexes of inputs (camera motion / ivs region signals) covering the zone
+    pub inputs: Box<[usize]>,
+    // motion must last this long before zone becomes active
+    pub debounce: Duration,
+    // zone stays active this long after motion is gone
+    pub hold: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    pub inputs_count: usize,
+    pub zones: Box<[Zone]>,
+}
+
+#[derive(Debug)]
+struct ZoneDebouncer {
+    debounce: Duration,
+    hold: Duration,
+
+    value: Option<bool>,
+    // input value different from current one, with time it was first seen
+    pending: Option<(bool, Instant)>,
+}
+impl ZoneDebouncer {
+    fn new(
+        debounce: Duration,
+        hold: Duration,
+    ) -> Self {
+        Self {
+            debounce,
+            hold,
+
+            value: None,
+            pending: None,
+        }
+    }
+
+    // returns time the next update is required at, if any
+    fn update(
+        &mut self,
+        input: Option<bool>,
+        now: Instant,
+    ) -> Option<Instant> {
+        let input = match (self.value, input) {
+            // first known value (eg. after camera reconnects) is taken as is
+            (None, input) | (_, input @ None) => {
+                self.value = input;
+                self.pending = None;
+                return None;
+            }
+            (Some(value), Some(input)) if value == input => {
+                self.pending = None;
+                return None;
+            }
+            (Some(_), Some(input)) => input,
+        };
+
+        let since = match self.pending {
+            Some((pending, since)) if pending == input => since,
+            _ => now,
+        };
+        let delay = if input { self.debounce } else { self.hold };
+
+        let at = since + delay;
+        if at <= now {
+            self.value = Some(input);
+            self.pending = None;
+            None
+        } else {
+            self.pending = Some((input, since));
+            Some(at)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_inputs: Box<[signal::state_target_last::Signal<bool>]>,
+    signal_zones: Box<[signal::state_source::Signal<bool>]>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        for zone in configuration.zones.iter() {
+            assert!(
+                zone.inputs
+                    .iter()
+                    .all(|input_index| *input_index < configuration.inputs_count),
+                "zone {} refers to input out of range",
+                zone.name
+            );
+        }
+
+        let signal_inputs = (0..configuration.inputs_count)
+            .map(|_input_index| signal::state_target_last::Signal::<bool>::new())
+            .collect::<Box<[_]>>();
+        let signal_zones = (0..configuration.zones.len())
+            .map(|_zone_index| signal::state_source::Signal::<bool>::new(None))
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_inputs,
+            signal_zones,
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // any input active makes zone active, unknown if all inputs are unknown
+    fn zone_input(
+        zone: &Zone,
+        inputs: &[Option<bool>],
+    ) -> Option<bool> {
+        zone.inputs
+            .iter()
+            .filter_map(|input_index| inputs[*input_index])
+            .reduce(|a, b| a || b)
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut zone_debouncers = self
+            .configuration
+            .zones
+            .iter()
+            .map(|zone| ZoneDebouncer::new(zone.debounce, zone.hold))
+            .collect::<Box<[_]>>();
+        let mut inputs = vec![None::<bool>; self.configuration.inputs_count].into_boxed_slice();
+
+        loop {
+            let now = Instant::now();
+
+            for (input, signal_input) in inputs.iter_mut().zip(self.signal_inputs.iter()) {
+                *input = signal_input.take_last().value;
+            }
+
+            let mut wake_at = None::<Instant>;
+            let mut signals_sources_changed = false;
+            for ((zone, zone_debouncer), signal_zone) in self
+                .configuration
+                .zones
+                .iter()
+                .zip(zone_debouncers.iter_mut())
+                .zip(self.signal_zones.iter())
+            {
+                let zone_wake_at = zone_debouncer.update(Self::zone_input(zone, &inputs), now);
+                wake_at = match (wake_at, zone_wake_at) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+
+                signals_sources_changed |= signal_zone.set_one(zone_debouncer.value);
+            }
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+                self.gui_summary_waker.wake();
+            }
+
+            let wake = match wake_at {
+                Some(wake_at) => {
+                    timer_wheel::sleep(wake_at.saturating_duration_since(now)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/surveillance/motion_zones_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+    Zone(usize), // index in configuration.zones
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input_index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(input_index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain(
+                self.signal_zones
+                    .iter()
+                    .enumerate()
+                    .map(|(zone_index, signal_zone)| {
+                        (
+                            SignalIdentifier::Zone(zone_index),
+                            signal_zone as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummaryZone {
+    name: String,
+    value: Option<bool>,
+}
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    zones: Box<[GuiSummaryZone]>,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let zones = self
+            .configuration
+            .zones
+            .iter()
+            .zip(self.signal_zones.iter())
+            .map(|(zone, signal_zone)| GuiSummaryZone {
+                name: zone.name.clone(),
+                value: signal_zone.peek_last(),
+            })
+            .collect::<Box<[_]>>();
+
+        Self::Value { zones }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZoneDebouncer;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn debounce_hold() {
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        let mut zone_debouncer =
+            ZoneDebouncer::new(Duration::from_secs(2), Duration::from_secs(10));
+
+        assert_eq!(zone_debouncer.update(Some(false), at(0)), None);
+        assert_eq!(zone_debouncer.value, Some(false));
+
+        // short motion is ignored
+        assert_eq!(zone_debouncer.update(Some(true), at(1)), Some(at(3)));
+        assert_eq!(zone_debouncer.update(Some(false), at(2)), None);
+        assert_eq!(zone_debouncer.update(Some(false), at(3)), None);
+        assert_eq!(zone_debouncer.value, Some(false));
+
+        // motion lasting for debounce activates zone
+        assert_eq!(zone_debouncer.update(Some(true), at(4)), Some(at(6)));
+        assert_eq!(zone_debouncer.update(Some(true), at(6)), None);
+        assert_eq!(zone_debouncer.value, Some(true));
+
+        // gaps shorter than hold keep zone active
+        assert_eq!(zone_debouncer.update(Some(false), at(7)), Some(at(17)));
+        assert_eq!(zone_debouncer.update(Some(true), at(12)), None);
+        assert_eq!(zone_debouncer.update(Some(false), at(13)), Some(at(23)));
+        assert_eq!(zone_debouncer.update(Some(false), at(23)), None);
+        assert_eq!(zone_debouncer.value, Some(false));
+
+        // unknown inputs make zone unknown immediately
+        assert_eq!(zone_debouncer.update(None, at(24)), None);
+        assert_eq!(zone_debouncer.value, None);
+    }
+}
@@ -1,4 +1,7 @@
-use super::channel::ChannelSegment;
+use super::{
+    channel::ChannelSegment,
+    retention::{self, Quotas, Recording, Usage},
+};
 use crate::{
     datatypes::ratio::Ratio,
     modules,
@@ -9,23 +12,31 @@ use crate::{
         fs::{move_file, remove_all_dir_empty},
         runnable::{Exited, Runnable},
     },
+    web::{self, uri_cursor},
 };
 use anyhow::{Context, Error};
 use async_trait::async_trait;
 use atomic_refcell::AtomicRefCell;
-use chrono::{Datelike, Timelike};
+use chrono::{Datelike, Timelike, Utc};
 use futures::{
     channel::mpsc,
-    future::FutureExt,
+    future::{BoxFuture, Future, FutureExt},
     join, select,
     stream::{StreamExt, TryStreamExt},
 };
 use indoc::indoc;
-use modules::{fs::Fs, sqlite::SQLite};
-use std::{collections::HashMap, fmt, path::PathBuf, rc::Rc, time::Duration};
+use modules::{
+    fs::Fs,
+    sqlite::{delete_in, SQLite},
+};
+use parking_lot::Mutex;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::{collections::HashMap, fmt, path::PathBuf, time::Duration};
 use tokio::fs;
 
 pub type ChannelId = usize;
+pub type RecordingId = usize;
 
 #[derive(Debug)]
 pub struct ChannelData {
@@ -39,6 +50,12 @@ pub struct ChannelIdSegment {
     pub segment: ChannelSegment,
 }
 
+#[derive(Debug, Serialize)]
+pub struct StorageUsage {
+    pub global: Usage,
+    pub channels: HashMap<ChannelId, Usage>,
+}
+
 #[derive(Debug)]
 pub struct Manager<'f> {
     name: String,
@@ -50,6 +67,8 @@ pub struct Manager<'f> {
 
     channel_segment_sender: mpsc::UnboundedSender<ChannelIdSegment>,
     channel_segment_receiver: AtomicRefCell<mpsc::UnboundedReceiver<ChannelIdSegment>>,
+
+    quotas: Mutex<Quotas>,
 }
 impl<'f> Manager<'f> {
     pub fn new(
@@ -64,6 +83,8 @@ impl<'f> Manager<'f> {
             mpsc::unbounded::<ChannelIdSegment>();
         let channel_segment_receiver = AtomicRefCell::new(channel_segment_receiver);
 
+        let quotas = Mutex::new(Quotas::default());
+
         Self {
             name,
             fs,
@@ -74,6 +95,8 @@ impl<'f> Manager<'f> {
 
             channel_segment_sender,
             channel_segment_receiver,
+
+            quotas,
         }
     }
 
@@ -283,9 +306,34 @@ impl<'f> Manager<'f> {
         Exited
     }
 
+    // recordings
+    fn recording_path_storage_get(
+        &self,
+        recording_id: RecordingId,
+    ) -> impl Future<Output = Result<Option<PathBuf>, Error>> + 'static {
+        let storage_directory_root_path = self.storage_directory_root_path_build();
+
+        self.sqlite
+            .query(move |connection| -> Result<Option<PathBuf>, Error> {
+                let path_storage_relative = connection
+                    .query_row(
+                        "SELECT path_storage_relative FROM recordings WHERE recording_id = ?",
+                        [recording_id],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()
+                    .context("query_row")?;
+
+                let path_storage = path_storage_relative.map(|path_storage_relative| {
+                    storage_directory_root_path.join(path_storage_relative)
+                });
+                Ok(path_storage)
+            })
+    }
+
     // cleanup
     async fn cleanup(&self) -> Result<(), Error> {
-        // find recordings to remove
+        // storage groups, recordings with low detection level are removed first
         let recordings_to_remove = self
             .sqlite
             .query(|connection| -> Result<Box<[(usize, PathBuf)]>, Error> {
@@ -337,6 +385,53 @@ impl<'f> Manager<'f> {
             .await
             .context("recordings_to_remove query")?;
 
+        self.recordings_remove(recordings_to_remove)
+            .await
+            .context("recordings_remove")?;
+
+        // quotas
+        let quotas = self.quotas.lock().clone();
+        let recordings_to_remove = self
+            .sqlite
+            .query(
+                move |connection| -> Result<Box<[(usize, PathBuf)]>, Error> {
+                    let recordings =
+                        Self::sql_recordings_get(connection).context("sql_recordings_get")?;
+                    let recording_ids_to_remove = retention::recordings_to_remove(
+                        &quotas,
+                        &recordings
+                            .iter()
+                            .map(|(recording, _)| *recording)
+                            .collect::<Box<[_]>>(),
+                        Utc::now(),
+                    );
+
+                    let recordings_to_remove = recordings
+                        .into_vec()
+                        .into_iter()
+                        .filter(|(recording, _)| {
+                            recording_ids_to_remove.contains(&recording.recording_id)
+                        })
+                        .map(|(recording, path_storage_relative)| {
+                            (recording.recording_id, path_storage_relative)
+                        })
+                        .collect::<Box<[_]>>();
+                    Ok(recordings_to_remove)
+                },
+            )
+            .await
+            .context("quotas query")?;
+
+        self.recordings_remove(recordings_to_remove)
+            .await
+            .context("recordings_remove")?;
+
+        Ok(())
+    }
+    async fn recordings_remove(
+        &self,
+        recordings_to_remove: Box<[(RecordingId, PathBuf)]>,
+    ) -> Result<(), Error> {
         // return early, don't remove or clear db
         if recordings_to_remove.is_empty() {
             return Ok(());
@@ -373,23 +468,12 @@ impl<'f> Manager<'f> {
         // store information about removed
         self.sqlite
             .query(move |connection| -> Result<(), Error> {
-                connection
-                    .prepare(indoc!(
-                        "
-                            DELETE FROM
-                                recordings
-                            WHERE
-                                recording_id IN rarray(?)
-                        "
-                    ))?
-                    .execute(rusqlite::params![Rc::new(
-                        recordings_to_remove
-                            .iter()
-                            .map(|(recording_id, _)| rusqlite::types::Value::from(
-                                *recording_id as i64
-                            ))
-                            .collect::<Vec<_>>()
-                    )])?;
+                let recording_ids = recordings_to_remove
+                    .iter()
+                    .map(|(recording_id, _)| rusqlite::types::Value::from(*recording_id as i64))
+                    .collect::<Box<[_]>>();
+                delete_in(connection, "recordings", "recording_id", &recording_ids)
+                    .context("delete_in")?;
 
                 Ok(())
             })
@@ -398,6 +482,87 @@ impl<'f> Manager<'f> {
 
         Ok(())
     }
+    // ordered by timestamp_end, oldest first
+    fn sql_recordings_get(
+        connection: &rusqlite::Connection
+    ) -> Result<Box<[(Recording, PathBuf)]>, Error> {
+        let recordings = connection
+            .prepare(indoc!(
+                "
+                    SELECT
+                        recording_id,
+                        channel_id,
+                        timestamp_start,
+                        timestamp_end,
+                        size_bytes,
+                        path_storage_relative
+                    FROM
+                        recordings
+                    ORDER BY
+                        timestamp_end
+                "
+            ))?
+            .query_map([], |row| {
+                let recording = Recording {
+                    recording_id: row.get_ref_unwrap(0).as_i64()? as usize,
+                    channel_id: row.get_ref_unwrap(1).as_i64()? as usize,
+                    timestamp_start: row.get_ref_unwrap(2).as_i64()?,
+                    timestamp_end: row.get_ref_unwrap(3).as_i64()?,
+                    size_bytes: row.get_ref_unwrap(4).as_i64()? as u64,
+                };
+                let path_storage_relative = PathBuf::from(row.get_ref_unwrap(5).as_str()?);
+                Ok((recording, path_storage_relative))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(recordings)
+    }
+
+    // quotas
+    pub fn quotas_set(
+        &self,
+        quotas: Quotas,
+    ) {
+        *self.quotas.lock() = quotas;
+    }
+    fn storage_usage_get(&self) -> impl Future<Output = Result<StorageUsage, Error>> + 'static {
+        let quotas = self.quotas.lock().clone();
+
+        self.sqlite
+            .query(move |connection| -> Result<StorageUsage, Error> {
+                let recordings =
+                    Self::sql_recordings_get(connection).context("sql_recordings_get")?;
+                let now = Utc::now();
+
+                let global = retention::usage(
+                    &quotas.global,
+                    recordings.iter().map(|(recording, _)| recording),
+                    now,
+                );
+
+                let mut recordings_channels = HashMap::<ChannelId, Vec<&Recording>>::new();
+                for (recording, _) in recordings.iter() {
+                    recordings_channels
+                        .entry(recording.channel_id)
+                        .or_default()
+                        .push(recording);
+                }
+                let channels = recordings_channels
+                    .into_iter()
+                    .map(|(channel_id, recordings)| {
+                        let quota = quotas
+                            .channels
+                            .get(&channel_id)
+                            .copied()
+                            .unwrap_or_default();
+                        let usage = retention::usage(&quota, recordings, now);
+                        (channel_id, usage)
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                Ok(StorageUsage { global, channels })
+            })
+    }
 
     const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 5);
     async fn cleanup_loop_run_once(
@@ -517,6 +682,78 @@ impl<'f> Runnable for Manager<'f> {
         self.run(exit_flag).await
     }
 }
+impl<'f> uri_cursor::Handler for Manager<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("storage", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let storage_usage = self.storage_usage_get();
+                        let name = self.name.clone();
+                        async move {
+                            match storage_usage.await {
+                                Ok(storage_usage) => web::Response::ok_json(storage_usage),
+                                Err(error) => {
+                                    log::error!("Manager({}): {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("recordings", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Next(recording_id, uri_cursor) => {
+                    let recording_id: RecordingId =
+                        match recording_id.parse().context("recording_id") {
+                            Ok(recording_id) => recording_id,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+                    match uri_cursor.as_ref() {
+                        uri_cursor::UriCursor::Terminal => match *request.method() {
+                            http::Method::GET => {
+                                let path_storage = self.recording_path_storage_get(recording_id);
+                                let name = self.name.clone();
+                                async move {
+                                    let path_storage = match path_storage.await {
+                                        Ok(Some(path_storage)) => path_storage,
+                                        Ok(None) => return web::Response::error_404(),
+                                        Err(error) => {
+                                            log::error!("Manager({}): {:?}", name, error);
+                                            return web::Response::error_500();
+                                        }
+                                    };
+                                    let file = match fs::File::open(&path_storage).await {
+                                        Ok(file) => file,
+                                        Err(error) => {
+                                            log::error!("Manager({}): {:?}", name, error);
+                                            return web::Response::error_500();
+                                        }
+                                    };
+                                    web::Response::ok_content_type_file("video/x-matroska", file)
+                                }
+                                .boxed()
+                            }
+                            _ => async { web::Response::error_405() }.boxed(),
+                        },
+                        _ => async { web::Response::error_404() }.boxed(),
+                    }
+                }
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
 impl<'f> fmt::Display for Manager<'f> {
     fn fmt(
         &self,
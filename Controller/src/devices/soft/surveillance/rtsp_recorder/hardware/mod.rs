@@ -1,4 +1,5 @@
 pub mod channel;
 pub mod manager;
 pub mod recorder;
+pub mod retention;
 pub mod runner;
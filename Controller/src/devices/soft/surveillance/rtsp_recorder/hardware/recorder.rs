@@ -2,6 +2,7 @@ use crate::{
     datatypes::ipc_rtsp_url::IpcRtspUrl,
     util::{
         anyhow_multiple_error::AnyhowMultipleError,
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
         async_flag,
         runnable::{Exited, Runnable},
     },
@@ -344,7 +345,7 @@ impl Recorder {
     }
     async fn inotify_run_once(
         &self,
-        mut exit_flag: async_flag::Receiver,
+        exit_flag: async_flag::Receiver,
     ) -> Result<Exited, Error> {
         let mut inotify_instance = Inotify::init().context("inotify_instance")?;
         inotify_instance
@@ -355,9 +356,12 @@ impl Recorder {
         let mut buffer = BytesMut::with_capacity(INOTIFY_BUFFER_SIZE);
         unsafe { buffer.set_len(INOTIFY_BUFFER_SIZE) };
 
+        // inotify is stopped after ffmpeg exits, events already queued (eg. last
+        // segment closed by ffmpeg while finalizing) are still handled
         let error_stream = inotify_instance
             .event_stream(buffer)
             .context("event_stream")?
+            .stream_take_until_exhausted(exit_flag)
             .filter_map(async |event| {
                 match self
                     .inotify_handle_event(event)
@@ -370,12 +374,9 @@ impl Recorder {
             });
         pin_mut!(error_stream);
 
-        select! {
-            error = error_stream.next().fuse() => match error {
-                Some(error) => Err(error),
-                None => Err(anyhow!("error_stream closed")),
-            },
-            () = exit_flag => Ok(Exited),
+        match error_stream.next().await {
+            Some(error) => Err(error),
+            None => Ok(Exited),
         }
     }
     async fn inotify_run(
@@ -0,0 +1,217 @@
+use super::manager::{ChannelId, RecordingId};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// limits of stored recordings, oldest recordings are removed first
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Quota {
+    pub size_bytes_max: Option<u64>,
+    pub age_max: Option<TimeDelta>,
+}
+
+// channel quotas are applied first, global quota applies to all channels
+// together
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Quotas {
+    pub global: Quota,
+    pub channels: HashMap<ChannelId, Quota>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Recording {
+    pub recording_id: RecordingId,
+    pub channel_id: ChannelId,
+    pub timestamp_start: i64,
+    pub timestamp_end: i64,
+    pub size_bytes: u64,
+}
+
+// recordings must be ordered by timestamp_end, oldest first
+pub fn recordings_to_remove(
+    quotas: &Quotas,
+    recordings: &[Recording],
+    now: DateTime<Utc>,
+) -> HashSet<RecordingId> {
+    let mut removed = HashSet::<RecordingId>::new();
+
+    let quota_channel = |channel_id: ChannelId| -> Quota {
+        quotas
+            .channels
+            .get(&channel_id)
+            .copied()
+            .unwrap_or_default()
+    };
+
+    // age
+    for recording in recordings {
+        let expired = [quota_channel(recording.channel_id), quotas.global]
+            .into_iter()
+            .filter_map(|quota| quota.age_max)
+            .any(|age_max| recording.timestamp_end < (now - age_max).timestamp());
+        if expired {
+            removed.insert(recording.recording_id);
+        }
+    }
+
+    // size, newest recordings are kept
+    let mut size_bytes_channels = HashMap::<ChannelId, u64>::new();
+    for recording in recordings.iter().rev() {
+        if removed.contains(&recording.recording_id) {
+            continue;
+        }
+
+        let size_bytes = size_bytes_channels.entry(recording.channel_id).or_default();
+        *size_bytes += recording.size_bytes;
+        if let Some(size_bytes_max) = quota_channel(recording.channel_id).size_bytes_max {
+            if *size_bytes > size_bytes_max {
+                removed.insert(recording.recording_id);
+            }
+        }
+    }
+    if let Some(size_bytes_max) = quotas.global.size_bytes_max {
+        let mut size_bytes = 0;
+        for recording in recordings.iter().rev() {
+            if removed.contains(&recording.recording_id) {
+                continue;
+            }
+
+            size_bytes += recording.size_bytes;
+            if size_bytes > size_bytes_max {
+                removed.insert(recording.recording_id);
+            }
+        }
+    }
+
+    removed
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Usage {
+    pub recordings: usize,
+    pub size_bytes: u64,
+    pub timestamp_oldest: Option<i64>,
+
+    pub size_bytes_max: Option<u64>,
+    pub age_max_seconds: Option<i64>,
+
+    // average over stored recordings
+    pub bytes_per_day: Option<f64>,
+    // estimated number of days the quota is able to keep, at current rate
+    pub retention_days: Option<f64>,
+}
+
+pub fn usage<'r>(
+    quota: &Quota,
+    recordings: impl IntoIterator<Item = &'r Recording>,
+    now: DateTime<Utc>,
+) -> Usage {
+    const DAY_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+    // shorter history gives unreliable rate
+    const RATE_PERIOD_MIN: TimeDelta = TimeDelta::hours(1);
+
+    let mut count = 0;
+    let mut size_bytes = 0;
+    let mut timestamp_oldest = None::<i64>;
+    for recording in recordings {
+        count += 1;
+        size_bytes += recording.size_bytes;
+        timestamp_oldest = Some(match timestamp_oldest {
+            Some(timestamp_oldest) => timestamp_oldest.min(recording.timestamp_start),
+            None => recording.timestamp_start,
+        });
+    }
+
+    let bytes_per_day = timestamp_oldest.and_then(|timestamp_oldest| {
+        let period = now.timestamp() - timestamp_oldest;
+        if period < RATE_PERIOD_MIN.num_seconds() {
+            return None;
+        }
+        Some(size_bytes as f64 / (period as f64 / DAY_SECONDS))
+    });
+
+    let retention_days_size = match (quota.size_bytes_max, bytes_per_day) {
+        (Some(size_bytes_max), Some(bytes_per_day)) if bytes_per_day > 0.0 => {
+            Some(size_bytes_max as f64 / bytes_per_day)
+        }
+        _ => None,
+    };
+    let retention_days_age = quota
+        .age_max
+        .map(|age_max| age_max.num_seconds() as f64 / DAY_SECONDS);
+    let retention_days = match (retention_days_size, retention_days_age) {
+        (Some(size), Some(age)) => Some(size.min(age)),
+        (size, age) => size.or(age),
+    };
+
+    Usage {
+        recordings: count,
+        size_bytes,
+        timestamp_oldest,
+
+        size_bytes_max: quota.size_bytes_max,
+        age_max_seconds: quota.age_max.map(|age_max| age_max.num_seconds()),
+
+        bytes_per_day,
+        retention_days,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recordings_to_remove, usage, Quota, Quotas, Recording};
+    use chrono::{TimeDelta, TimeZone, Utc};
+    use maplit::{hashmap, hashset};
+
+    #[test]
+    fn quotas() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap();
+        let day = TimeDelta::days(1).num_seconds();
+
+        // one recording per day for both channels, oldest first
+        let recordings = (0..8)
+            .flat_map(|index| {
+                let timestamp_start = now.timestamp() - (8 - index) * day;
+                [1, 2].map(|channel_id| Recording {
+                    recording_id: (index * 10 + channel_id) as usize,
+                    channel_id: channel_id as usize,
+                    timestamp_start,
+                    timestamp_end: timestamp_start + 60,
+                    size_bytes: 100,
+                })
+            })
+            .collect::<Box<[_]>>();
+
+        let quotas = Quotas {
+            global: Quota {
+                size_bytes_max: Some(900),
+                age_max: None,
+            },
+            channels: hashmap! {
+                1 => Quota {
+                    size_bytes_max: None,
+                    age_max: Some(TimeDelta::days(3)),
+                },
+            },
+        };
+        // channel 1 keeps 3 days (300 bytes), channel 2 takes the rest of
+        // global quota (600 bytes)
+        assert_eq!(
+            recordings_to_remove(&quotas, &recordings, now),
+            hashset! {1, 11, 21, 31, 41, 2, 12}
+        );
+
+        let usage_ = usage(&quotas.channels[&1], recordings.iter(), now);
+        assert_eq!(usage_.recordings, 16);
+        assert_eq!(usage_.size_bytes, 1600);
+        assert_eq!(usage_.bytes_per_day, Some(200.0));
+        assert_eq!(usage_.retention_days, Some(3.0));
+
+        let usage_ = usage(&quotas.global, recordings.iter(), now);
+        assert_eq!(usage_.retention_days, Some(4.5));
+
+        let usage_ = usage(&Quota::default(), [].iter(), now);
+        assert_eq!(usage_.bytes_per_day, None);
+        assert_eq!(usage_.retention_days, None);
+    }
+}
@@ -1,6 +1,7 @@
 use super::{
     channel::Channel,
     manager::{ChannelId, ChannelIdSegment, Manager},
+    retention::Quotas,
 };
 use crate::{
     modules::{fs::Fs, module_path::ModulePath},
@@ -11,12 +12,13 @@ use crate::{
         runnable::{Exited, Runnable},
         runtime::{Runtime, RuntimeScope, RuntimeScopeRunnable},
     },
+    web::{self, uri_cursor},
 };
 use anyhow::{Context, Error};
 use async_trait::async_trait;
 use futures::{
     channel::mpsc::UnboundedSender,
-    future::{FutureExt, JoinAll},
+    future::{BoxFuture, FutureExt, JoinAll},
     join,
     stream::StreamExt,
 };
@@ -361,6 +363,13 @@ impl<'r, 'f> Runner<'r, 'f> {
         Some(runner_channels_lock)
     }
 
+    pub fn quotas_set(
+        &self,
+        quotas: Quotas,
+    ) {
+        self.manager_runner.manager().quotas_set(quotas)
+    }
+
     pub async fn finalize(self) {
         self.runner_channel_runners
             .into_inner()
@@ -374,6 +383,16 @@ impl<'r, 'f> Runner<'r, 'f> {
     }
 }
 
+impl<'r, 'f> uri_cursor::Handler for Runner<'r, 'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        self.manager_runner.manager().handle(request, uri_cursor)
+    }
+}
+
 #[self_referencing]
 #[derive(Debug)]
 struct RunnerOwnedInner<'f> {
@@ -450,6 +469,13 @@ impl<'f> RunnerOwned<'f> {
         runner.channels_lock()
     }
 
+    pub fn quotas_set(
+        &self,
+        quotas: Quotas,
+    ) {
+        self.inner.with_runner(|runner| runner.quotas_set(quotas))
+    }
+
     pub async fn finalize(self) {
         let runner_runtime_scope = self
             .inner
@@ -486,3 +512,13 @@ impl<'f> RunnerOwned<'f> {
         drop(inner_heads);
     }
 }
+impl<'f> uri_cursor::Handler for RunnerOwned<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        self.inner
+            .with_runner(|runner| runner.handle(request, uri_cursor))
+    }
+}
@@ -0,0 +1,124 @@
+use crate::modules::events::{Attachment, Reporter};
+use anyhow::{bail, Context, Error};
+use bytes::Bytes;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, Rgb, RgbImage};
+
+// rectangle blacked out before snapshot leaves the camera device, eg. zones
+// with detection disabled (neighbour's window, street)
+// coordinates are relative to image size, in 0.0 - 1.0 range
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Redaction {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    pub width_max: u32,
+    // must not exceed Reporter::ATTACHMENT_SIZE_MAX
+    pub size_bytes_max: usize,
+    pub redactions: Box<[Redaction]>,
+}
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            width_max: 1280,
+            size_bytes_max: Reporter::ATTACHMENT_SIZE_MAX,
+            redactions: Box::new([]),
+        }
+    }
+}
+
+fn redact(
+    image: &mut RgbImage,
+    redactions: &[Redaction],
+) {
+    let (width, height) = image.dimensions();
+    let scale = |value: f64, size: u32| -> u32 { (value.clamp(0.0, 1.0) * size as f64) as u32 };
+
+    for redaction in redactions {
+        let left = scale(redaction.left, width);
+        let top = scale(redaction.top, height);
+        let right = scale(redaction.left + redaction.width, width);
+        let bottom = scale(redaction.top + redaction.height, height);
+
+        for y in top..bottom {
+            for x in left..right {
+                image.put_pixel(x, y, Rgb([0, 0, 0]));
+            }
+        }
+    }
+}
+
+// quality is lowered until image fits in size limit
+const JPEG_QUALITIES: [u8; 4] = [85, 70, 55, 40];
+
+pub fn build(
+    image: &DynamicImage,
+    configuration: &Configuration,
+) -> Result<Attachment, Error> {
+    let image = if image.width() > configuration.width_max {
+        image.resize(configuration.width_max, u32::MAX, FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+
+    let mut image = image.to_rgb8();
+    redact(&mut image, &configuration.redactions);
+
+    for jpeg_quality in JPEG_QUALITIES {
+        let mut jpeg_bytes = Vec::<u8>::new();
+        image
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg_bytes, jpeg_quality))
+            .context("write_with_encoder")?;
+
+        if jpeg_bytes.len() <= configuration.size_bytes_max {
+            return Ok(Attachment {
+                content_type: "image/jpeg",
+                body: Bytes::from(jpeg_bytes),
+            });
+        }
+    }
+
+    bail!(
+        "snapshot does not fit in {} bytes",
+        configuration.size_bytes_max
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build, Configuration, Redaction};
+    use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+    #[test]
+    fn redaction_and_size() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 200, Rgb([255, 255, 255])));
+        let configuration = Configuration {
+            width_max: 200,
+            size_bytes_max: 64 * 1024,
+            redactions: Box::new([Redaction {
+                left: 0.5,
+                top: 0.0,
+                width: 0.5,
+                height: 1.0,
+            }]),
+        };
+
+        let attachment = build(&image, &configuration).unwrap();
+        assert_eq!(attachment.content_type, "image/jpeg");
+
+        let image = image::load_from_memory(&attachment.body).unwrap();
+        assert_eq!(image.dimensions(), (200, 100));
+        assert!(image.get_pixel(20, 50).0[0] > 200);
+        assert!(image.get_pixel(180, 50).0[0] < 50);
+
+        let configuration = Configuration {
+            size_bytes_max: 16,
+            ..configuration
+        };
+        assert!(build(&image, &configuration).is_err());
+    }
+}
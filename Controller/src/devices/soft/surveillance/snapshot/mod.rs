@@ -1 +1,2 @@
+pub mod attachment;
 pub mod logic_device_inner;
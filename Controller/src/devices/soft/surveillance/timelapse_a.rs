@@ -0,0 +1,391 @@
+use crate::{
+    datatypes::ipc_rtsp_url::IpcRtspUrl,
+    devices,
+    modules::fs::{Fs, StorageArea},
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use anyhow::{anyhow, ensure, Context, Error};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    select,
+};
+use maplit::hashmap;
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tokio::{fs, process::Command};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage directory name, must be unique across timelapses
+    pub name: String,
+    pub capture_interval: Duration,
+    // day boundaries for daily videos
+    pub timezone: Tz,
+    // frames per second of the assembled video
+    pub framerate: u32,
+}
+
+// frames are kept in day directories until the day is over, then assembled
+// into single video and removed
+//
+// <storage>/timelapse/<name>/frames/<date>/<timestamp>.jpg
+// <storage>/timelapse/<name>/videos/<date>.mp4
+#[derive(Debug)]
+pub struct Device {
+    configuration: Configuration,
+
+    frames_directory: PathBuf,
+    videos_directory: PathBuf,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_rtsp_url: signal::state_target_last::Signal<IpcRtspUrl>,
+}
+impl Device {
+    const FFMPEG_CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+    const FFMPEG_ASSEMBLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+    pub fn new(
+        configuration: Configuration,
+        fs: &Fs,
+    ) -> Self {
+        assert!(configuration.capture_interval > Duration::ZERO);
+        assert!(configuration.framerate > 0);
+
+        let directory = fs
+            .storage_area_directory(StorageArea::Surveillance)
+            .join("timelapse")
+            .join(&configuration.name);
+        let frames_directory = directory.join("frames");
+        let videos_directory = directory.join("videos");
+
+        Self {
+            configuration,
+
+            frames_directory,
+            videos_directory,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_rtsp_url: signal::state_target_last::Signal::<IpcRtspUrl>::new(),
+        }
+    }
+
+    fn today(&self) -> NaiveDate {
+        Utc::now()
+            .with_timezone(&self.configuration.timezone)
+            .date_naive()
+    }
+
+    async fn ffmpeg_run(
+        mut command: Command,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        command
+            .env_clear()
+            .kill_on_drop(true)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .args(["-loglevel", "error"])
+            .arg("-hide_banner")
+            .arg("-nostats")
+            .arg("-nostdin");
+
+        let output = tokio::time::timeout(timeout, command.output())
+            .await
+            .context("timeout")?
+            .context("output")?;
+        ensure!(
+            output.status.success(),
+            "ffmpeg exited with status code: {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        Ok(())
+    }
+
+    async fn capture(
+        &self,
+        rtsp_url: &IpcRtspUrl,
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let date = now.with_timezone(&self.configuration.timezone).date_naive();
+
+        let directory = self.frames_directory.join(date.to_string());
+        fs::create_dir_all(&directory)
+            .await
+            .context("create_dir_all")?;
+
+        let mut command = Command::new("/usr/bin/ffmpeg");
+        command
+            // input options
+            .args(["-f", "rtsp"])
+            .args(["-rtsp_transport", "tcp"])
+            .args(["-timeout", "10000000"])
+            .args(["-i", rtsp_url.to_string().as_str()])
+            // output options
+            .args(["-frames:v", "1"])
+            .args(["-q:v", "3"])
+            .arg(directory.join(format!("{}.jpg", now.timestamp())).as_os_str());
+        Self::ffmpeg_run(command, Self::FFMPEG_CAPTURE_TIMEOUT)
+            .await
+            .context("ffmpeg_run")?;
+
+        Ok(())
+    }
+
+    async fn assemble(
+        &self,
+        date: NaiveDate,
+    ) -> Result<(), Error> {
+        let frames_directory = self.frames_directory.join(date.to_string());
+
+        fs::create_dir_all(&self.videos_directory)
+            .await
+            .context("create_dir_all")?;
+        let video_path = self.videos_directory.join(format!("{}.mp4", date));
+
+        // frame names are timestamps, so glob order is capture order
+        let mut command = Command::new("/usr/bin/ffmpeg");
+        command
+            .current_dir(&frames_directory)
+            .arg("-y")
+            // input options
+            .args([
+                "-framerate",
+                self.configuration.framerate.to_string().as_str(),
+            ])
+            .args(["-pattern_type", "glob"])
+            .args(["-i", "*.jpg"])
+            // output options
+            .args(["-codec:v", "libx264"])
+            .args(["-pix_fmt", "yuv420p"])
+            .args(["-movflags", "+faststart"])
+            .arg(video_path.as_os_str());
+        Self::ffmpeg_run(command, Self::FFMPEG_ASSEMBLE_TIMEOUT)
+            .await
+            .context("ffmpeg_run")?;
+
+        fs::remove_dir_all(&frames_directory)
+            .await
+            .context("remove_dir_all")?;
+
+        Ok(())
+    }
+
+    async fn dates_list(directory: &Path) -> Result<Box<[NaiveDate]>, Error> {
+        let mut dates = Vec::<NaiveDate>::new();
+
+        let mut read_dir = match fs::read_dir(directory).await {
+            Ok(read_dir) => read_dir,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Box::new([]));
+            }
+            Err(error) => return Err(error).context("read_dir"),
+        };
+        while let Some(entry) = read_dir.next_entry().await.context("next_entry")? {
+            if let Some(date) = date_from_file_name(&entry.file_name().to_string_lossy()) {
+                dates.push(date);
+            }
+        }
+
+        dates.sort();
+        Ok(dates.into_boxed_slice())
+    }
+
+    // assembles all days that are over, including ones left from before restart
+    async fn assemble_finished(&self) -> Result<(), Error> {
+        let today = self.today();
+
+        let dates = Self::dates_list(&self.frames_directory)
+            .await
+            .context("dates_list")?;
+        for date in dates.iter().filter(|date| **date < today) {
+            self.assemble(*date)
+                .await
+                .with_context(|| format!("assemble {}", date))?;
+            log::info!("{}: timelapse {} created", self.configuration.name, date);
+        }
+
+        Ok(())
+    }
+
+    async fn tick(&self) {
+        // url is only needed at capture time, no need to react on changes
+        if let Some(rtsp_url) = self.signal_rtsp_url.take_last().value {
+            if let Err(error) = self.capture(&rtsp_url).await.context("capture") {
+                log::warn!("{}: {:?}", self.configuration.name, error);
+            }
+        }
+
+        if let Err(error) = self.assemble_finished().await.context("assemble_finished") {
+            log::error!("{}: {:?}", self.configuration.name, error);
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        loop {
+            select! {
+                () = self.tick().fuse() => {},
+                () = exit_flag => break,
+            }
+            select! {
+                () = timer_wheel::sleep(self.configuration.capture_interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+fn date_from_file_name(file_name: &str) -> Option<NaiveDate> {
+    let date = file_name.strip_suffix(".mp4").unwrap_or(file_name);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/surveillance/timelapse_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    RtspUrl,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::RtspUrl => &self.signal_rtsp_url as &dyn signal::Base,
+        }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("videos", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let videos_directory = self.videos_directory.clone();
+                        let name = self.configuration.name.clone();
+                        async move {
+                            match Self::dates_list(&videos_directory).await {
+                                Ok(dates) => web::Response::ok_json(dates),
+                                Err(error) => {
+                                    log::error!("{}: {:?}", name, error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                // date in yyyy-mm-dd format, also protects from path traversal
+                uri_cursor::UriCursor::Next(date, uri_cursor) => match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::GET => {
+                            let date = match date_from_file_name(date)
+                                .ok_or_else(|| anyhow!("invalid date"))
+                            {
+                                Ok(date) => date,
+                                Err(error) => {
+                                    return async { web::Response::error_400_from_error(error) }
+                                        .boxed()
+                                }
+                            };
+                            let video_path = self.videos_directory.join(format!("{}.mp4", date));
+                            let name = self.configuration.name.clone();
+                            async move {
+                                match fs::read(&video_path).await {
+                                    Ok(video) => web::Response::ok_content_type_body(
+                                        "video/mp4",
+                                        Bytes::from(video),
+                                    ),
+                                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                                        web::Response::error_404()
+                                    }
+                                    Err(error) => {
+                                        log::error!("{}: {:?}", name, error);
+                                        web::Response::error_500()
+                                    }
+                                }
+                            }
+                            .boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                },
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::date_from_file_name;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn date_file_names() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(date_from_file_name("2024-06-01"), Some(date));
+        assert_eq!(date_from_file_name("2024-06-01.mp4"), Some(date));
+        assert_eq!(date_from_file_name("../2024-06-01"), None);
+        assert_eq!(date_from_file_name("1717200000.jpg"), None);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    devices,
+    modules::{backup::Backup, clock::Clock},
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select, stream::StreamExt};
+use maplit::hashmap;
+use std::{borrow::Cow, time::Duration};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // backup is considered healthy if last success is not older than this
+    pub age_max: Duration,
+}
+
+#[derive(Debug)]
+pub struct Device<'b, 'f, 'c> {
+    configuration: Configuration,
+    backup: &'b Backup<'f>,
+    clock: &'c Clock<'c>,
+
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_output: signal::state_source::Signal<bool>,
+}
+impl<'b, 'f, 'c> Device<'b, 'f, 'c> {
+    const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        configuration: Configuration,
+        backup: &'b Backup<'f>,
+        clock: &'c Clock<'c>,
+    ) -> Self {
+        Self {
+            configuration,
+            backup,
+            clock,
+
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_output: signal::state_source::Signal::<bool>::new(None),
+        }
+    }
+
+    fn calculate(&self) -> bool {
+        let last_success = match self.backup.last_success().get() {
+            Some(last_success) => last_success,
+            None => return false,
+        };
+
+        let age = match (self.clock.now() - last_success).to_std() {
+            Ok(age) => age,
+            Err(_) => return true, // last success in future, clock went back
+        };
+
+        age <= self.configuration.age_max
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut last_success_changed_stream = self.backup.last_success().changed_stream(false);
+
+        loop {
+            if self.signal_output.set_one(Some(self.calculate())) {
+                self.signals_sources_changed_waker.wake();
+            }
+
+            select! {
+                () = last_success_changed_stream.select_next_some() => {},
+                () = tokio::time::sleep(Self::CHECK_INTERVAL).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'b, 'f, 'c> devices::Device for Device<'b, 'f, 'c> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/system/backup_status_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'b, 'f, 'c> Runnable for Device<'b, 'f, 'c> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Output,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'b, 'f, 'c> signals::Device for Device<'b, 'f, 'c> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    devices,
+    modules::emergency_stop::emergency_stop,
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::{join, stream::StreamExt};
+use maplit::hashmap;
+use std::borrow::Cow;
+
+// binds physical e-stop input to controller-wide emergency stop
+// only one such device is expected in configuration
+// latched output reflects the stop, regardless where it was reset (including
+// web)
+#[derive(Debug)]
+pub struct Device {
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<bool>,
+    signal_reset: signal::event_target_last::Signal<()>,
+    signal_latched: signal::state_source::Signal<bool>,
+}
+impl Device {
+    pub fn new() -> Self {
+        Self {
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<bool>::new(),
+            signal_reset: signal::event_target_last::Signal::<()>::new(),
+            signal_latched: signal::state_source::Signal::<bool>::new(None),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        if let Some(input) = self.signal_input.take_pending() {
+            // missing input (ex. failed input device) is treated as active
+            emergency_stop().input_set(input.unwrap_or(true));
+        }
+
+        if self.signal_reset.take_pending().is_some() {
+            if let Err(error) = emergency_stop().reset() {
+                log::warn!("emergency stop reset rejected: {:?}", error);
+            }
+        }
+    }
+
+    async fn signals_targets_changed_run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+    }
+
+    fn latched_propagate(&self) {
+        if self
+            .signal_latched
+            .set_one(Some(emergency_stop().latched()))
+        {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn latched_run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) {
+        self.latched_propagate();
+
+        emergency_stop()
+            .receiver()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.latched_propagate();
+            })
+            .await;
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        join!(
+            self.signals_targets_changed_run(exit_flag.clone()),
+            self.latched_run(exit_flag),
+        );
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/system/emergency_stop_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Reset,
+    Latched,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Reset => &self.signal_reset as &dyn signal::Base,
+            SignalIdentifier::Latched => &self.signal_latched as &dyn signal::Base,
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    devices::{self, maintenance::Maintenance},
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::{join, stream::StreamExt};
+use maplit::hashmap;
+use std::borrow::Cow;
+use tokio_stream::wrappers::WatchStream;
+
+// binds physical switch to maintenance mode
+// active output reflects mode requested by any source (including web)
+#[derive(Debug)]
+pub struct Device<'m> {
+    maintenance: &'m Maintenance,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<bool>,
+    signal_active: signal::state_source::Signal<bool>,
+}
+impl<'m> Device<'m> {
+    pub fn new(maintenance: &'m Maintenance) -> Self {
+        Self {
+            maintenance,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<bool>::new(),
+            signal_active: signal::state_source::Signal::<bool>::new(None),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        // missing input (ex. disconnected switch or bus failure) keeps current
+        // request, only explicit false releases it
+        if let Some(Some(input)) = self.signal_input.take_pending() {
+            if input {
+                log::warn!("maintenance mode requested by signal");
+            }
+            self.maintenance.signal_set(input);
+        }
+    }
+
+    async fn signals_targets_changed_run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+    }
+
+    async fn active_run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) {
+        WatchStream::new(self.maintenance.active_receiver())
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |active| {
+                if self.signal_active.set_one(Some(active)) {
+                    self.signals_sources_changed_waker.wake();
+                }
+            })
+            .await;
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        join!(
+            self.signals_targets_changed_run(exit_flag.clone()),
+            self.active_run(exit_flag),
+        );
+
+        Exited
+    }
+}
+
+impl<'m> devices::Device for Device<'m> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/system/maintenance_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'m> Runnable for Device<'m> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Active,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'m> signals::Device for Device<'m> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Active => &self.signal_active as &dyn signal::Base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, SignalIdentifier};
+    use crate::{
+        devices::maintenance::Maintenance,
+        simulation::{mock, Simulation},
+    };
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[tokio::test(start_paused = true)]
+    async fn input_missing_keeps_request() {
+        let maintenance = Maintenance::new();
+
+        let input = mock::StateSource::<bool>::new(None);
+        let device = Device::new(&maintenance);
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let device_handle = simulation.device_add(&device);
+        simulation.signals().d2d(
+            input_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            device_handle,
+            SignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                sleep(Duration::from_secs(1)).await;
+                assert!(!maintenance.active());
+
+                input.set(Some(true));
+                sleep(Duration::from_secs(1)).await;
+                assert!(maintenance.active());
+
+                input.set(None);
+                sleep(Duration::from_secs(1)).await;
+                assert!(maintenance.active());
+
+                input.set(Some(false));
+                sleep(Duration::from_secs(1)).await;
+                assert!(!maintenance.active());
+            })
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod backup_status_a;
+pub mod emergency_stop_a;
+pub mod maintenance_a;
+pub mod power_sequencer_a;
+pub mod scene_restore_a;
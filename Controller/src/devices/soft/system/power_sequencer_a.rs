@@ -0,0 +1,437 @@
+use crate::{
+    devices,
+    modules::{fs::Fs, sqlite::SQLite},
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use indoc::indoc;
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    iter,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct ConfigurationOutput {
+    // higher goes first, outputs with equal priority go in index order
+    pub priority: u32,
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    // used as storage name, must be unique across sequencers
+    pub name: String,
+    // minimal time between switching on two consecutive outputs
+    pub step_delay: Duration,
+    pub outputs: Box<[ConfigurationOutput]>,
+}
+
+// position in outputs order, outputs before it follow their inputs
+#[derive(Debug)]
+struct Sequence {
+    position: usize,
+    step_last: Option<Instant>,
+}
+impl Sequence {
+    pub fn new() -> Self {
+        Self {
+            position: 0,
+            step_last: None,
+        }
+    }
+
+    pub fn finished(
+        &self,
+        outputs_order: &[usize],
+    ) -> bool {
+        self.position >= outputs_order.len()
+    }
+
+    // moves sequence forward as far as possible, switching outputs
+    // returns deadline for next step, if sequence is waiting
+    pub fn advance(
+        &mut self,
+        outputs_order: &[usize],
+        step_delay: Duration,
+        values: &[Option<bool>],
+        outputs: &mut [Option<bool>],
+        now: Instant,
+    ) -> Option<Instant> {
+        let mut deadline = None;
+
+        for (position, output_index) in outputs_order.iter().copied().enumerate() {
+            let value = values[output_index];
+
+            // switching off causes no inrush
+            if value != Some(true) {
+                outputs[output_index] = value;
+                if position == self.position {
+                    self.position += 1;
+                }
+                continue;
+            }
+            // own step already passed
+            if position < self.position {
+                outputs[output_index] = value;
+                continue;
+            }
+            // waiting for own step
+            if position > self.position {
+                continue;
+            }
+
+            if outputs[output_index] != Some(true) {
+                if let Some(step_last) = self.step_last {
+                    let step_next = step_last + step_delay;
+                    if step_next > now {
+                        deadline = Some(step_next);
+                        continue;
+                    }
+                }
+
+                outputs[output_index] = Some(true);
+                self.step_last = Some(now);
+            }
+            self.position += 1;
+        }
+
+        deadline
+    }
+}
+
+// mirrors inputs to outputs, but after controller startup or power
+// restoration outputs are switched on one by one, in priority order, with
+// step delay between them, so inrush currents do not add up and trip the
+// mains breaker
+// inputs are persisted, so the last values are restored after restart even
+// before sources become available
+#[derive(Debug)]
+pub struct Device<'f> {
+    configuration: Configuration,
+    outputs_order: Box<[usize]>,
+
+    sqlite: SQLite<'f>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_power: signal::state_target_last::Signal<bool>,
+    signal_inputs: Box<[signal::state_target_last::Signal<bool>]>,
+    signal_outputs: Box<[signal::state_source::Signal<bool>]>,
+}
+impl<'f> Device<'f> {
+    pub fn new(
+        configuration: Configuration,
+        fs: &'f Fs,
+    ) -> Self {
+        let mut outputs_order = (0..configuration.outputs.len()).collect::<Box<[_]>>();
+        outputs_order
+            .sort_by_key(|output_index| Reverse(configuration.outputs[*output_index].priority));
+
+        let sqlite = SQLite::new(format!("power_sequencer.{}", configuration.name), fs);
+
+        let signal_inputs = (0..configuration.outputs.len())
+            .map(|_output_index| signal::state_target_last::Signal::<bool>::new())
+            .collect::<Box<[_]>>();
+        let signal_outputs = (0..configuration.outputs.len())
+            .map(|_output_index| signal::state_source::Signal::<bool>::new(None))
+            .collect::<Box<[_]>>();
+
+        Self {
+            configuration,
+            outputs_order,
+
+            sqlite,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_power: signal::state_target_last::Signal::<bool>::new(),
+            signal_inputs,
+            signal_outputs,
+        }
+    }
+
+    async fn load(&self) -> Result<Box<[Option<bool>]>, Error> {
+        let outputs_count = self.configuration.outputs.len();
+
+        let values = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                transaction
+                    .execute_batch(indoc!(
+                        "
+                        CREATE TABLE IF NOT EXISTS `values` (
+                            `output_index` INTEGER PRIMARY KEY NOT NULL,
+                            `value` INTEGER NOT NULL
+                        ) STRICT;
+                    "
+                    ))
+                    .context("initialize")?;
+
+                let mut values = vec![None; outputs_count].into_boxed_slice();
+
+                let mut statement = transaction
+                    .prepare("SELECT `output_index`, `value` FROM `values`")
+                    .context("prepare")?;
+                let mut rows = statement.query([]).context("query")?;
+                while let Some(row) = rows.next().context("next")? {
+                    let output_index: usize = row.get(0).context("output_index")?;
+                    let value: bool = row.get(1).context("value")?;
+
+                    // output count may have changed since
+                    if let Some(slot) = values.get_mut(output_index) {
+                        *slot = Some(value);
+                    }
+                }
+
+                Ok(values)
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(values)
+    }
+    async fn persist(
+        &self,
+        values: Box<[Option<bool>]>,
+    ) -> Result<(), Error> {
+        self.sqlite
+            .transaction(move |transaction| -> Result<(), Error> {
+                let mut statement = transaction
+                    .prepare(indoc!(
+                        "
+                        INSERT OR REPLACE INTO
+                            `values` (`output_index`, `value`)
+                        VALUES
+                            (?, ?)
+                    "
+                    ))
+                    .context("prepare")?;
+
+                for (output_index, value) in values.iter().enumerate() {
+                    if let Some(value) = value {
+                        statement
+                            .execute((output_index, value))
+                            .context("execute")?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+            .context("transaction")?
+            .context("transaction")?;
+
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut values = match self.load().await.context("load") {
+            Ok(values) => values,
+            Err(error) => {
+                log::error!("{}: {:?}", self.configuration.name, error);
+                vec![None; self.configuration.outputs.len()].into_boxed_slice()
+            }
+        };
+        let mut outputs = vec![None; self.configuration.outputs.len()].into_boxed_slice();
+
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        let mut sequence = Sequence::new();
+        loop {
+            // inputs not known yet keep values from before restart
+            let mut persist = false;
+            for (value, signal_input) in values.iter_mut().zip(self.signal_inputs.iter()) {
+                let input = signal_input.peek_last();
+                if input.is_some() && input != *value {
+                    *value = input;
+                    persist = true;
+                }
+            }
+            if persist {
+                if let Err(error) = self.persist(values.clone()).await.context("persist") {
+                    log::error!("{}: {:?}", self.configuration.name, error);
+                }
+            }
+
+            // power not connected is considered present
+            let power = self.signal_power.peek_last().unwrap_or(true);
+
+            let deadline = if power {
+                let sequence_finished = sequence.finished(&self.outputs_order);
+
+                let deadline = sequence.advance(
+                    &self.outputs_order,
+                    self.configuration.step_delay,
+                    &values,
+                    &mut outputs,
+                    Instant::now(),
+                );
+
+                if !sequence_finished && sequence.finished(&self.outputs_order) {
+                    log::info!("{}: sequence finished", self.configuration.name);
+                }
+
+                deadline
+            } else {
+                // loads would be switched on all at once when power returns
+                outputs.fill(Some(false));
+                sequence = Sequence::new();
+
+                None
+            };
+
+            let mut signals_sources_changed = false;
+            for (output, signal_output) in outputs.iter().zip(self.signal_outputs.iter()) {
+                signals_sources_changed |= signal_output.set_one(*output);
+            }
+            if signals_sources_changed {
+                self.signals_sources_changed_waker.wake();
+            }
+
+            let wake = match deadline {
+                Some(deadline) => timer_wheel::sleep_until(deadline).left_future(),
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'f> devices::Device for Device<'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/system/power_sequencer_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'f> Runnable for Device<'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Power,
+    Input(usize),
+    Output(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'f> signals::Device for Device<'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain([(
+                SignalIdentifier::Power,
+                &self.signal_power as &dyn signal::Base,
+            )])
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(output_index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(output_index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain(
+                self.signal_outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(output_index, signal_output)| {
+                        (
+                            SignalIdentifier::Output(output_index),
+                            signal_output as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sequence;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn sequence_steps() {
+        let outputs_order = [2, 0, 1, 3];
+        let step_delay = Duration::from_secs(5);
+        let values = [Some(true), Some(false), Some(true), Some(true)];
+        let mut outputs = [None; 4];
+
+        let start = Instant::now();
+        let mut sequence = Sequence::new();
+
+        // first on immediately, off ones switched without waiting
+        let deadline = sequence.advance(&outputs_order, step_delay, &values, &mut outputs, start);
+        assert_eq!(deadline, Some(start + step_delay));
+        assert_eq!(outputs, [None, Some(false), Some(true), None]);
+
+        // too early
+        let now = start + Duration::from_secs(1);
+        let deadline = sequence.advance(&outputs_order, step_delay, &values, &mut outputs, now);
+        assert_eq!(deadline, Some(start + step_delay));
+        assert_eq!(outputs, [None, Some(false), Some(true), None]);
+
+        let now = start + step_delay;
+        let deadline = sequence.advance(&outputs_order, step_delay, &values, &mut outputs, now);
+        assert_eq!(deadline, Some(now + step_delay));
+        assert_eq!(outputs, [Some(true), Some(false), Some(true), None]);
+
+        let now = now + step_delay;
+        let deadline = sequence.advance(&outputs_order, step_delay, &values, &mut outputs, now);
+        assert_eq!(deadline, None);
+        assert_eq!(outputs, [Some(true), Some(false), Some(true), Some(true)]);
+        assert!(sequence.finished(&outputs_order));
+
+        // after sequence outputs follow inputs
+        let values = [Some(false), Some(true), Some(true), Some(true)];
+        let deadline = sequence.advance(&outputs_order, step_delay, &values, &mut outputs, now);
+        assert_eq!(deadline, None);
+        assert_eq!(outputs, [Some(false), Some(true), Some(true), Some(true)]);
+    }
+}
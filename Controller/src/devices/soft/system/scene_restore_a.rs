@@ -0,0 +1,107 @@
+use crate::{
+    devices::{self, scenes::Scenes},
+    signals::{self, signal},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use std::borrow::Cow;
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub scene: String,
+}
+
+#[derive(Debug)]
+pub struct Device<'s, 'f> {
+    configuration: Configuration,
+    scenes: &'s Scenes<'f>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_input: signal::event_target_last::Signal<()>,
+}
+impl<'s, 'f> Device<'s, 'f> {
+    pub fn new(
+        configuration: Configuration,
+        scenes: &'s Scenes<'f>,
+    ) -> Self {
+        Self {
+            configuration,
+            scenes,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_input: signal::event_target_last::Signal::<()>::new(),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        if let Some(()) = self.signal_input.take_pending() {
+            self.scenes
+                .restore_request(self.configuration.scene.clone());
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<'s, 'f> devices::Device for Device<'s, 'f> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/system/scene_restore_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'s, 'f> Runnable for Device<'s, 'f> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Restore,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'s, 'f> signals::Device for Device<'s, 'f> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Restore => &self.signal_input as &dyn signal::Base,
+        }
+    }
+}
@@ -4,14 +4,17 @@ use crate::{
     util::{
         async_flag,
         runnable::{Exited, Runnable},
+        timer_wheel,
     },
 };
 use async_trait::async_trait;
 use futures::{future::MaybeDone, pin_mut, select, stream::StreamExt};
 use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, time::Duration};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub delay_raising: Duration,
     pub delay_falling: Duration,
@@ -58,7 +61,7 @@ impl Device {
             };
 
             let delay_runner = if delay >= Duration::ZERO {
-                let future = tokio::time::sleep(delay);
+                let future = timer_wheel::sleep(delay);
                 MaybeDone::Future(future)
             } else {
                 MaybeDone::Done(())
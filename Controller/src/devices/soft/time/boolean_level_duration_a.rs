@@ -4,18 +4,21 @@ use crate::{
     util::{
         async_flag,
         runnable::{Exited, Runnable},
+        timer_wheel,
     },
 };
 use async_trait::async_trait;
 use futures::{future::FutureExt, pin_mut, select, stream::StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, iter, time::Duration};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Breakpoint {
     pub expires: Duration, // after previous breakpoint
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub breakpoints: Box<[Breakpoint]>,
 }
@@ -88,7 +91,7 @@ impl Device {
 
             for (index, breakpoint) in self.configuration.breakpoints.iter().enumerate() {
                 // create timer to wait for breakpoint time
-                let breakpoint_timer = tokio::time::sleep(breakpoint.expires);
+                let breakpoint_timer = timer_wheel::sleep(breakpoint.expires);
                 pin_mut!(breakpoint_timer);
                 let mut breakpoint_timer = breakpoint_timer.fuse();
 
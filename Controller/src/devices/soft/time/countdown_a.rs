@@ -0,0 +1,393 @@
+use crate::{
+    devices,
+    signals::{self, signal},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+    web::{self, uri_cursor},
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, time::Duration};
+use tokio::{sync::watch, time::Instant};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Configuration {
+    // initial duration, may be changed from gui
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+pub struct Device {
+    duration: RwLock<Duration>,
+
+    deadline_sender: watch::Sender<Option<Instant>>,
+    deadline_receiver: watch::Receiver<Option<Instant>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_start: signal::event_target_last::Signal<()>,
+    signal_cancel: signal::event_target_last::Signal<()>,
+    signal_active: signal::state_source::Signal<bool>,
+    signal_remaining: signal::state_source::Signal<Duration>,
+    signal_expired: signal::event_source::Signal<()>,
+
+    gui_summary_waker: devices::gui_summary::Waker,
+}
+impl Device {
+    pub fn new(configuration: Configuration) -> Self {
+        let (deadline_sender, deadline_receiver) = watch::channel(None);
+
+        Self {
+            duration: RwLock::new(configuration.duration),
+
+            deadline_sender,
+            deadline_receiver,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_start: signal::event_target_last::Signal::<()>::new(),
+            signal_cancel: signal::event_target_last::Signal::<()>::new(),
+            signal_active: signal::state_source::Signal::<bool>::new(Some(false)),
+            signal_remaining: signal::state_source::Signal::<Duration>::new(Some(Duration::ZERO)),
+            signal_expired: signal::event_source::Signal::<()>::new(),
+
+            gui_summary_waker: devices::gui_summary::Waker::new(),
+        }
+    }
+
+    // restarts countdown if already running
+    fn start(&self) {
+        let deadline = Instant::now() + *self.duration.read();
+        self.deadline_sender.send_replace(Some(deadline));
+    }
+    fn cancel(&self) {
+        self.deadline_sender.send_replace(None);
+    }
+
+    fn duration_set(
+        &self,
+        duration: Duration,
+    ) {
+        *self.duration.write() = duration;
+        self.gui_summary_waker.wake();
+    }
+
+    fn signals_targets_changed(&self) {
+        // cancel goes first, so start and cancel in single batch starts
+        if self.signal_cancel.take_pending().is_some() {
+            self.cancel();
+        }
+        if self.signal_start.take_pending().is_some() {
+            self.start();
+        }
+    }
+
+    // remaining time, rounded up to whole seconds
+    fn remaining_seconds(remaining: Duration) -> Duration {
+        let mut seconds = remaining.as_secs();
+        if remaining.subsec_nanos() > 0 {
+            seconds += 1;
+        }
+        Duration::from_secs(seconds)
+    }
+
+    fn outputs_set(
+        &self,
+        remaining: Option<Duration>,
+    ) {
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_active.set_one(Some(remaining.is_some()));
+        signals_sources_changed |= self
+            .signal_remaining
+            .set_one(Some(remaining.unwrap_or(Duration::ZERO)));
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+            self.gui_summary_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut deadline_receiver = self.deadline_receiver.clone();
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let deadline = *deadline_receiver.borrow_and_update();
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+            // expired
+            if remaining == Some(Duration::ZERO) {
+                self.deadline_sender.send_replace(None);
+                self.outputs_set(None);
+                if self.signal_expired.push_one(()) {
+                    self.signals_sources_changed_waker.wake();
+                }
+                continue;
+            }
+
+            let remaining_seconds = remaining.map(Self::remaining_seconds);
+            self.outputs_set(remaining_seconds);
+
+            // wake up when displayed value changes
+            let tick = match (remaining, remaining_seconds) {
+                (Some(remaining), Some(remaining_seconds)) => {
+                    let tick = remaining + Duration::from_secs(1) - remaining_seconds;
+                    timer_wheel::sleep(tick).left_future()
+                }
+                _ => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {
+                    self.signals_targets_changed();
+                },
+                result = deadline_receiver.changed().fuse() => result.unwrap(),
+                () = tick.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl devices::Device for Device {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from("soft/time/countdown_a")
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+    fn as_gui_summary_device_base(&self) -> Option<&dyn devices::gui_summary::DeviceBase> {
+        Some(self)
+    }
+    fn as_web_handler(&self) -> Option<&dyn uri_cursor::Handler> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl Runnable for Device {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Start,
+    Cancel,
+    Active,
+    Remaining,
+    Expired,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl signals::Device for Device {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Start => &self.signal_start as &dyn signal::Base,
+            SignalIdentifier::Cancel => &self.signal_cancel as &dyn signal::Base,
+            SignalIdentifier::Active => &self.signal_active as &dyn signal::Base,
+            SignalIdentifier::Remaining => &self.signal_remaining as &dyn signal::Base,
+            SignalIdentifier::Expired => &self.signal_expired as &dyn signal::Base,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuiSummary {
+    duration: Duration,
+    remaining: Option<Duration>,
+}
+impl devices::gui_summary::Device for Device {
+    fn waker(&self) -> &devices::gui_summary::Waker {
+        &self.gui_summary_waker
+    }
+
+    type Value = GuiSummary;
+    fn value(&self) -> Self::Value {
+        let duration = *self.duration.read();
+        let remaining = match self.signal_active.peek_last() {
+            Some(true) => self.signal_remaining.peek_last(),
+            _ => None,
+        };
+
+        Self::Value {
+            duration,
+            remaining,
+        }
+    }
+}
+
+impl uri_cursor::Handler for Device {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("start", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        self.start();
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("cancel", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        self.cancel();
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("duration", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        let duration = match request.body_parse_json::<Duration>() {
+                            Ok(duration) => duration,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+                        self.duration_set(duration);
+                        async { web::Response::ok_empty() }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, Device, SignalIdentifier};
+    use crate::simulation::{mock, Simulation};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn expires_and_cancels() {
+        let start = mock::EventSource::<()>::new();
+        let cancel = mock::EventSource::<()>::new();
+        let countdown = Device::new(Configuration {
+            duration: Duration::from_secs(3),
+        });
+        let active = mock::StateRecorder::<bool>::new();
+        let remaining = mock::StateRecorder::<Duration>::new();
+        let expired = mock::EventRecorder::<()>::new();
+
+        let mut simulation = Simulation::new();
+        let start_handle = simulation.device_add(&start);
+        let cancel_handle = simulation.device_add(&cancel);
+        let countdown_handle = simulation.device_add(&countdown);
+        let active_handle = simulation.device_add(&active);
+        let remaining_handle = simulation.device_add(&remaining);
+        let expired_handle = simulation.device_add(&expired);
+        simulation.signals().d2d(
+            start_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            countdown_handle,
+            SignalIdentifier::Start,
+        );
+        simulation.signals().d2d(
+            cancel_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            countdown_handle,
+            SignalIdentifier::Cancel,
+        );
+        simulation.signals().d2d(
+            countdown_handle,
+            SignalIdentifier::Active,
+            active_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            countdown_handle,
+            SignalIdentifier::Remaining,
+            remaining_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            countdown_handle,
+            SignalIdentifier::Expired,
+            expired_handle,
+            mock::EventRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                // runs to the end
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                start.push(());
+
+                // cancelled
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                start.push(());
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                cancel.push(());
+
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            })
+            .await
+            .unwrap();
+
+        active.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(1), Some(true)),
+            (Duration::from_secs(4), Some(false)),
+            (Duration::from_secs(6), Some(true)),
+            (Duration::from_millis(6500), Some(false)),
+        ]);
+        remaining.trace().assert_entries(&[
+            (Duration::ZERO, Some(Duration::ZERO)),
+            (Duration::from_secs(1), Some(Duration::from_secs(3))),
+            (Duration::from_secs(2), Some(Duration::from_secs(2))),
+            (Duration::from_secs(3), Some(Duration::from_secs(1))),
+            (Duration::from_secs(4), Some(Duration::ZERO)),
+            (Duration::from_secs(6), Some(Duration::from_secs(3))),
+            (Duration::from_millis(6500), Some(Duration::ZERO)),
+        ]);
+        expired
+            .trace()
+            .assert_entries(&[(Duration::from_secs(4), ())]);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod boolean_change_delay_a;
 pub mod boolean_level_duration_a;
+pub mod countdown_a;
 pub mod pulse_a;
 pub mod pwm_slow_a;
 pub mod sequence_parallel_a;
@@ -4,14 +4,17 @@ use crate::{
     util::{
         async_flag,
         runnable::{Exited, Runnable},
+        timer_wheel,
     },
 };
 use async_trait::async_trait;
 use futures::{pin_mut, select, stream::StreamExt, FutureExt};
 use maplit::hashmap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, time::Duration};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub duration: Duration,
 }
@@ -69,7 +72,7 @@ impl Device {
                 // wait until timeout expires, restart if new event is detected
                 select! {
                     () = signal_input_changed_stream.select_next_some() => continue,
-                    () = tokio::time::sleep(self.configuration.duration).fuse() => break,
+                    () = timer_wheel::sleep(self.configuration.duration).fuse() => break,
                     () = exit_flag => break 'outer,
                 }
             }
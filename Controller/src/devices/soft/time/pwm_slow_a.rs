@@ -5,19 +5,22 @@ use crate::{
     util::{
         async_flag,
         runnable::{Exited, Runnable},
+        timer_wheel,
     },
 };
 use async_trait::async_trait;
 use futures::{future::FutureExt, pin_mut, select, stream::StreamExt};
 use maplit::hashmap;
 use rand::{thread_rng, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     ops::Rem,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     /// full (on + off) cycle duration
     pub cycle_duration: Duration,
@@ -133,7 +136,7 @@ impl Device {
                     // wait for input change / state change, exit signal
                     select! {
                         () = signal_input_changed_stream.select_next_some() => {},
-                        () = tokio::time::sleep(cycle_output_remaining).fuse() => {},
+                        () = timer_wheel::sleep(cycle_output_remaining).fuse() => {},
                         () = exit_flag => break,
                     }
                 }
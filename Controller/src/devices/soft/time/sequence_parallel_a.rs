@@ -19,10 +19,11 @@ use futures::{
 };
 use itertools::{izip, zip_eq, Itertools};
 use parking_lot::RwLock;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, cmp::min, collections::HashMap, iter, time::Duration};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ConfigurationChannel {
     pub name: String,
 
@@ -33,7 +34,7 @@ pub struct ConfigurationChannel {
     pub round_max: Duration,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Configuration {
     pub power_max: Multiplier,
     pub channels: Box<[ConfigurationChannel]>,
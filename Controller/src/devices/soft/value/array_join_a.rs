@@ -0,0 +1,225 @@
+use crate::{
+    devices,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use std::{any::type_name, borrow::Cow, iter};
+
+// combines separate signals into single array signal (eg. all channels of a
+// board), output is known only if all inputs are known
+#[derive(Debug)]
+pub struct Device<V, const N: usize>
+where
+    V: Value + Clone,
+{
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_inputs: [signal::state_target_last::Signal<V>; N],
+    signal_output: signal::state_source::Signal<[V; N]>,
+}
+impl<V, const N: usize> Device<V, N>
+where
+    V: Value + Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_inputs: std::array::from_fn(|_index| {
+                signal::state_target_last::Signal::<V>::new()
+            }),
+            signal_output: signal::state_source::Signal::<[V; N]>::new(None),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        let inputs = self
+            .signal_inputs
+            .each_ref()
+            .map(|signal_input| signal_input.take_last());
+
+        // if no signal is pending, don't recalculate
+        if !inputs.iter().any(|input| input.pending) {
+            return;
+        }
+
+        let value = inputs
+            .into_iter()
+            .map(|input| input.value)
+            .collect::<Option<Vec<_>>>()
+            .map(|values| <[V; N]>::try_from(values).unwrap());
+
+        if self.signal_output.set_one(value) {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<V, const N: usize> devices::Device for Device<V, N>
+where
+    V: Value + Clone,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "soft/value/array_join_a<{}, {}>",
+            type_name::<V>(),
+            N
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<V, const N: usize> Runnable for Device<V, N>
+where
+    V: Value + Clone,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input(usize),
+    Output,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<V, const N: usize> signals::Device for Device<V, N>
+where
+    V: Value + Clone,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain(
+                self.signal_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, signal_input)| {
+                        (
+                            SignalIdentifier::Input(index),
+                            signal_input as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .chain([(
+                SignalIdentifier::Output,
+                &self.signal_output as &dyn signal::Base,
+            )])
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::array_split_a, Device, SignalIdentifier};
+    use crate::simulation::{mock, Simulation};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn join_split() {
+        let input_0 = mock::StateSource::<bool>::new(Some(false));
+        let input_1 = mock::StateSource::<bool>::new(None);
+        let join = Device::<bool, 2>::new();
+        let split = array_split_a::Device::<bool, 2>::new();
+        let output = mock::StateRecorder::<[bool; 2]>::new();
+        let output_1 = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let input_0_handle = simulation.device_add(&input_0);
+        let input_1_handle = simulation.device_add(&input_1);
+        let join_handle = simulation.device_add(&join);
+        let split_handle = simulation.device_add(&split);
+        let output_handle = simulation.device_add(&output);
+        let output_1_handle = simulation.device_add(&output_1);
+        simulation.signals().d2d(
+            input_0_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            join_handle,
+            SignalIdentifier::Input(0),
+        );
+        simulation.signals().d2d(
+            input_1_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            join_handle,
+            SignalIdentifier::Input(1),
+        );
+        simulation.signals().d2d(
+            join_handle,
+            SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            join_handle,
+            SignalIdentifier::Output,
+            split_handle,
+            array_split_a::SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            split_handle,
+            array_split_a::SignalIdentifier::Output(1),
+            output_1_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input_1.set(Some(true));
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                input_0.set(Some(true));
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        output.trace().assert_entries(&[
+            (Duration::from_secs(1), Some([false, true])),
+            (Duration::from_secs(2), Some([true, true])),
+        ]);
+        output_1
+            .trace()
+            .assert_entries(&[(Duration::from_secs(1), Some(true))]);
+    }
+}
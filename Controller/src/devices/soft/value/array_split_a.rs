@@ -0,0 +1,144 @@
+use crate::{
+    devices,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use std::{any::type_name, borrow::Cow, iter};
+
+// exposes each element of array signal (eg. all channels of a board) as
+// separate signal
+#[derive(Debug)]
+pub struct Device<V, const N: usize>
+where
+    V: Value + Clone,
+{
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<[V; N]>,
+    signal_outputs: [signal::state_source::Signal<V>; N],
+}
+impl<V, const N: usize> Device<V, N>
+where
+    V: Value + Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<[V; N]>::new(),
+            signal_outputs: std::array::from_fn(|_index| {
+                signal::state_source::Signal::<V>::new(None)
+            }),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        let value = match self.signal_input.take_pending() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let mut signals_sources_changed = false;
+        for (index, signal_output) in self.signal_outputs.iter().enumerate() {
+            let value = value.as_ref().map(|value| value[index].clone());
+            signals_sources_changed |= signal_output.set_one(value);
+        }
+
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<V, const N: usize> devices::Device for Device<V, N>
+where
+    V: Value + Clone,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "soft/value/array_split_a<{}, {}>",
+            type_name::<V>(),
+            N
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<V, const N: usize> Runnable for Device<V, N>
+where
+    V: Value + Clone,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Output(usize),
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<V, const N: usize> signals::Device for Device<V, N>
+where
+    V: Value + Clone,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        iter::empty()
+            .chain([(
+                SignalIdentifier::Input,
+                &self.signal_input as &dyn signal::Base,
+            )])
+            .chain(
+                self.signal_outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, signal_output)| {
+                        (
+                            SignalIdentifier::Output(index),
+                            signal_output as &dyn signal::Base,
+                        )
+                    }),
+            )
+            .collect::<signals::ByIdentifier<_>>()
+    }
+}
@@ -1,7 +1,11 @@
+pub mod array_join_a;
+pub mod array_split_a;
 pub mod broadcast_event_a;
 pub mod broadcast_state_a;
 pub mod coalesce_a;
 pub mod constant_a;
 pub mod latch_a;
 pub mod sample_a;
+pub mod staleness_a;
+pub mod timestamp_a;
 pub mod trigger_a;
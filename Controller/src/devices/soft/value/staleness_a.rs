@@ -0,0 +1,172 @@
+use crate::{
+    datatypes::timestamped::Timestamped,
+    devices,
+    modules::clock::Clock,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_flag,
+        runnable::{Exited, Runnable},
+        timer_wheel,
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
+use maplit::hashmap;
+use std::{any::type_name, borrow::Cow, time::Duration};
+
+#[derive(Debug)]
+pub struct Configuration {
+    // values older than this are considered stale
+    pub age_max: Duration,
+}
+
+// unwraps timestamped value, replacing it with unknown once it gets too old
+// eg. before logger sink, so old values are stored as gaps, not as readings
+#[derive(Debug)]
+pub struct Device<'c, V>
+where
+    V: Value + Clone,
+{
+    configuration: Configuration,
+    clock: &'c Clock<'c>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_last::Signal<Timestamped<V>>,
+    signal_output: signal::state_source::Signal<V>,
+    signal_stale: signal::state_source::Signal<bool>,
+}
+impl<'c, V> Device<'c, V>
+where
+    V: Value + Clone,
+{
+    pub fn new(
+        configuration: Configuration,
+        clock: &'c Clock<'c>,
+    ) -> Self {
+        Self {
+            configuration,
+            clock,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_last::Signal::<Timestamped<V>>::new(),
+            signal_output: signal::state_source::Signal::<V>::new(None),
+            signal_stale: signal::state_source::Signal::<bool>::new(None),
+        }
+    }
+
+    // returns time left until value gets stale
+    fn update(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        let input = self.signal_input.peek_last();
+
+        let (value, stale, fresh_for) = match input {
+            Some(input) => {
+                let age = input.age(now);
+                if age > self.configuration.age_max {
+                    (None, Some(true), None)
+                } else {
+                    let fresh_for = self.configuration.age_max - age;
+                    (Some(input.value), Some(false), Some(fresh_for))
+                }
+            }
+            None => (None, None, None),
+        };
+
+        let mut signals_sources_changed = false;
+        signals_sources_changed |= self.signal_output.set_one(value);
+        signals_sources_changed |= self.signal_stale.set_one(stale);
+        if signals_sources_changed {
+            self.signals_sources_changed_waker.wake();
+        }
+
+        fresh_for
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let mut signals_targets_changed_stream = self.signals_targets_changed_waker.stream();
+
+        loop {
+            let wake = match self.update() {
+                // value equal to age max is still fresh
+                Some(fresh_for) => {
+                    timer_wheel::sleep(fresh_for + Duration::from_millis(1)).left_future()
+                }
+                None => future::pending().right_future(),
+            };
+
+            select! {
+                () = signals_targets_changed_stream.select_next_some() => {},
+                () = wake.fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+
+impl<'c, V> devices::Device for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!("soft/value/staleness_a<{}>", type_name::<V>()))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'c, V> Runnable for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Output,
+    Stale,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c, V> signals::Device for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+            SignalIdentifier::Stale => &self.signal_stale as &dyn signal::Base,
+        }
+    }
+}
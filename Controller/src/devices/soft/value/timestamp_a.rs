@@ -0,0 +1,130 @@
+use crate::{
+    datatypes::timestamped::Timestamped,
+    devices,
+    modules::clock::Clock,
+    signals::{self, signal, types::state::Value},
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use std::{any::type_name, borrow::Cow};
+
+// attaches time of arrival to values of sources not providing own timestamp
+#[derive(Debug)]
+pub struct Device<'c, V>
+where
+    V: Value + Clone,
+{
+    clock: &'c Clock<'c>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_input: signal::state_target_queued::Signal<V>,
+    signal_output: signal::state_source::Signal<Timestamped<V>>,
+}
+impl<'c, V> Device<'c, V>
+where
+    V: Value + Clone,
+{
+    pub fn new(clock: &'c Clock<'c>) -> Self {
+        Self {
+            clock,
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_input: signal::state_target_queued::Signal::<V>::new(),
+            signal_output: signal::state_source::Signal::<Timestamped<V>>::new(None),
+        }
+    }
+
+    fn signals_targets_changed(&self) {
+        let now = self.clock.now();
+
+        let values = self
+            .signal_input
+            .take_pending()
+            .into_vec()
+            .into_iter()
+            .map(|value| value.map(|value| Timestamped::new(value, now)))
+            .collect::<Box<[_]>>();
+
+        if self.signal_output.set_many(values) {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                self.signals_targets_changed();
+            })
+            .await;
+
+        Exited
+    }
+}
+
+impl<'c, V> devices::Device for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!("soft/value/timestamp_a<{}>", type_name::<V>()))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+
+#[async_trait]
+impl<'c, V> Runnable for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalIdentifier {
+    Input,
+    Output,
+}
+impl signals::Identifier for SignalIdentifier {}
+impl<'c, V> signals::Device for Device<'c, V>
+where
+    V: Value + Clone,
+{
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = SignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            SignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+            SignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     util::{
         async_flag,
         runnable::{Exited, Runnable},
+        timer_wheel,
     },
     web::{self, uri_cursor},
 };
@@ -75,7 +76,7 @@ impl Device {
                     break 'inner_wait_for_down;
                 }
 
-                let timeout = tokio::time::sleep(Self::VALUE_TIMEOUT);
+                let timeout = timer_wheel::sleep(Self::VALUE_TIMEOUT);
                 pin_mut!(timeout);
                 let mut timeout = timeout.fuse();
 
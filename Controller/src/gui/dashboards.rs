@@ -7,7 +7,8 @@ use futures::future::{BoxFuture, FutureExt};
 use itertools::Itertools;
 use serde::Serialize;
 
-#[derive(Debug)]
+// serialized as whole tree for definition export, gui uses the handler
+#[derive(Debug, Serialize)]
 pub struct Dashboard {
     name: String,
     icon: Icon,
@@ -247,41 +248,43 @@ impl uri_cursor::Handler for Dashboard {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
 pub enum Content {
     SectionContent(ContentSectionContent),
     Sections(ContentSections),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ContentSectionContent {
     section_content: SectionContent,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ContentSections {
     sections: Box<[Section]>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Section {
     name: Option<String>,
 
     content: SectionContent,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
 pub enum SectionContent {
     Dashboards(SectionContentDashboards),
     Devices(SectionContentDevices),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct SectionContentDashboards {
     dashboards: Box<[Dashboard]>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct SectionContentDevices {
     device_ids: Box<[DeviceId]>,
 }
@@ -17,6 +17,7 @@ pub struct Bus {
 impl Bus {
     const FTDI_DEVICE_CONFIGURATION: ftdi::DeviceConfiguration = ftdi::DeviceConfiguration {
         latency_timer_ms: 10,
+        rs485_direction_rts: None,
     };
     const FTDI_RETRY_COUNT: usize = 3;
     const FTDI_RETRY_INTERVAL: Duration = Duration::from_secs(1);
@@ -29,6 +30,7 @@ impl Bus {
         descriptor: ftdi::Descriptor,
         baud_rate: usize,
         parity: serial::Parity,
+        rs485_direction_rts: Option<serial::Rs485DirectionRts>,
     ) -> Self {
         let serial_configuration = serial::Configuration {
             baud_rate,
@@ -44,7 +46,10 @@ impl Bus {
         let ftdi_device = ftdi::DeviceFailSafe::new(
             descriptor,
             serial_configuration,
-            Self::FTDI_DEVICE_CONFIGURATION,
+            ftdi::DeviceConfiguration {
+                rs485_direction_rts,
+                ..Self::FTDI_DEVICE_CONFIGURATION
+            },
             Self::FTDI_RETRY_COUNT,
             Self::FTDI_RETRY_INTERVAL,
         );
@@ -310,6 +315,7 @@ pub struct AsyncBus {
     descriptor: ftdi::Descriptor,
     baud_rate: usize,
     parity: serial::Parity,
+    rs485_direction_rts: Option<serial::Rs485DirectionRts>,
 
     transaction_sender: ManuallyDrop<channel::Sender<AsyncBusTransaction>>,
     worker_thread: ManuallyDrop<thread::JoinHandle<()>>,
@@ -325,6 +331,7 @@ impl AsyncBus {
         descriptor: ftdi::Descriptor,
         baud_rate: usize,
         parity: serial::Parity,
+        rs485_direction_rts: Option<serial::Rs485DirectionRts>,
     ) -> Self {
         let (transaction_sender, transaction_receiver) =
             channel::unbounded::<AsyncBusTransaction>();
@@ -338,7 +345,13 @@ impl AsyncBus {
         let worker_thread = thread::Builder::new()
             .name(module_path_name.thread_name())
             .spawn(move || {
-                Self::thread_main(worker_descriptor, baud_rate, parity, transaction_receiver);
+                Self::thread_main(
+                    worker_descriptor,
+                    baud_rate,
+                    parity,
+                    rs485_direction_rts,
+                    transaction_receiver,
+                );
             })
             .unwrap();
 
@@ -346,6 +359,7 @@ impl AsyncBus {
             descriptor,
             baud_rate,
             parity,
+            rs485_direction_rts,
 
             transaction_sender: ManuallyDrop::new(transaction_sender),
             worker_thread: ManuallyDrop::new(worker_thread),
@@ -380,10 +394,11 @@ impl AsyncBus {
         descriptor: ftdi::Descriptor,
         baud_rate: usize,
         parity: serial::Parity,
+        rs485_direction_rts: Option<serial::Rs485DirectionRts>,
 
         transaction_receiver: channel::Receiver<AsyncBusTransaction>,
     ) {
-        let mut bus = Bus::new(descriptor, baud_rate, parity);
+        let mut bus = Bus::new(descriptor, baud_rate, parity, rs485_direction_rts);
 
         for transaction in transaction_receiver.iter() {
             let AsyncBusTransaction {
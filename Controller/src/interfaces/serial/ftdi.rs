@@ -4,7 +4,7 @@ pub use super::ftdi_linux::*;
 #[cfg(not(target_os = "linux"))]
 pub use super::ftdi_stub::*;
 
-use super::Configuration;
+use super::{Configuration, Rs485DirectionRts};
 use crate::util::anyhow_multiple_error::AnyhowMultipleError;
 use anyhow::{anyhow, bail, Context, Error};
 use itertools::Itertools;
@@ -71,6 +71,8 @@ impl Descriptors {
 #[derive(Debug)]
 pub struct DeviceConfiguration {
     pub latency_timer_ms: u8,
+    // None for adapters with automatic direction control (eg. TXDEN)
+    pub rs485_direction_rts: Option<Rs485DirectionRts>,
 }
 
 #[derive(Debug)]
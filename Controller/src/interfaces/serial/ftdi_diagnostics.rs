@@ -0,0 +1,256 @@
+// explains failures of opening ftdi device, as libftdi reports only error
+// codes, like "usb device not found" or "unable to open device"
+// usb devices are inspected through sysfs, so this is linux specific
+use super::ftdi::Descriptor;
+use anyhow::{Context, Error};
+use itertools::Itertools;
+use std::{
+    ffi, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub struct UsbDevice {
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+    pub bus_number: u8,
+    pub device_number: u8,
+    // kernel drivers bound to device interfaces
+    pub drivers: Box<[String]>,
+}
+impl UsbDevice {
+    pub fn node_path(&self) -> PathBuf {
+        PathBuf::from(format!(
+            "/dev/bus/usb/{:03}/{:03}",
+            self.bus_number, self.device_number
+        ))
+    }
+}
+
+const SYSFS_USB_DEVICES: &str = "/sys/bus/usb/devices";
+
+fn usb_device_read(
+    path: &Path,
+    name: &str,
+) -> Result<UsbDevice, Error> {
+    let attribute = |attribute: &str| -> Result<String, Error> {
+        let value = fs::read_to_string(path.join(attribute)).context(attribute.to_owned())?;
+        Ok(value.trim().to_owned())
+    };
+
+    let vid = u16::from_str_radix(&attribute("idVendor")?, 16).context("vid")?;
+    let pid = u16::from_str_radix(&attribute("idProduct")?, 16).context("pid")?;
+    let serial_number = attribute("serial").ok();
+    let bus_number = attribute("busnum")?.parse().context("bus_number")?;
+    let device_number = attribute("devnum")?.parse().context("device_number")?;
+
+    // interfaces are subdirectories named <device>:<configuration>.<interface>
+    let interface_prefix = format!("{name}:");
+    let drivers = fs::read_dir(path)
+        .context("read_dir")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&interface_prefix)
+        })
+        .filter_map(|entry| fs::read_link(entry.path().join("driver")).ok())
+        .filter_map(|driver| Some(driver.file_name()?.to_string_lossy().into_owned()))
+        .sorted()
+        .dedup()
+        .collect::<Box<[_]>>();
+
+    Ok(UsbDevice {
+        vid,
+        pid,
+        serial_number,
+        bus_number,
+        device_number,
+        drivers,
+    })
+}
+pub fn usb_devices_read() -> Result<Box<[UsbDevice]>, Error> {
+    let usb_devices = fs::read_dir(SYSFS_USB_DEVICES)
+        .context("read_dir")?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            // interfaces and root hubs without attributes are skipped
+            if name.contains(':') {
+                return None;
+            }
+            usb_device_read(&entry.path(), &name).ok()
+        })
+        .collect::<Box<[_]>>();
+
+    Ok(usb_devices)
+}
+
+fn node_accessible(path: &Path) -> bool {
+    let path = match ffi::CString::new(path.as_os_str().as_encoded_bytes()) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+}
+
+// actionable causes why descriptor could not be opened
+pub fn open_failure_causes(
+    descriptor: &Descriptor,
+    usb_devices: &[UsbDevice],
+    node_accessible: impl Fn(&Path) -> bool,
+) -> Box<[String]> {
+    let serial_number = descriptor.serial_number.to_string_lossy();
+
+    let usb_device = match usb_devices
+        .iter()
+        .find(|usb_device| usb_device.serial_number.as_deref() == Some(&*serial_number))
+    {
+        Some(usb_device) => usb_device,
+        None => {
+            let serial_numbers = usb_devices
+                .iter()
+                .filter(|usb_device| {
+                    usb_device.vid == descriptor.vid && usb_device.pid == descriptor.pid
+                })
+                .map(|usb_device| usb_device.serial_number.as_deref().unwrap_or("<none>"))
+                .join(", ");
+
+            let cause = if serial_numbers.is_empty() {
+                format!(
+                    "no usb device with serial number {} is connected",
+                    serial_number
+                )
+            } else {
+                format!(
+                    "no usb device with serial number {} is connected, devices {:04X}:{:04X} \
+                    with following serial numbers are present: {}",
+                    serial_number, descriptor.vid, descriptor.pid, serial_numbers
+                )
+            };
+            return Box::new([cause]);
+        }
+    };
+
+    let mut causes = Vec::<String>::new();
+
+    if usb_device.vid != descriptor.vid || usb_device.pid != descriptor.pid {
+        causes.push(format!(
+            "usb device with serial number {} has id {:04X}:{:04X}, while {:04X}:{:04X} is \
+            expected, check configuration or device eeprom",
+            serial_number, usb_device.vid, usb_device.pid, descriptor.vid, descriptor.pid
+        ));
+    }
+
+    let node_path = usb_device.node_path();
+    if !node_accessible(&node_path) {
+        causes.push(format!(
+            "no read/write permission for {}, add udev rule like: SUBSYSTEM==\"usb\", \
+            ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0666\"",
+            node_path.display(),
+            usb_device.vid,
+            usb_device.pid
+        ));
+    }
+
+    // libftdi detaches kernel driver on open, this fails without permissions
+    // or if serial port is in use
+    if usb_device.drivers.iter().any(|driver| driver == "ftdi_sio") {
+        causes.push(
+            "kernel driver ftdi_sio is bound to the device and could not be detached, unbind \
+            it or blacklist ftdi_sio module"
+                .to_owned(),
+        );
+    }
+
+    if causes.is_empty() {
+        causes.push(
+            "usb device is present and accessible, it may be opened by another process".to_owned(),
+        );
+    }
+
+    causes.into_boxed_slice()
+}
+
+pub fn open_failure_diagnose(descriptor: &Descriptor) -> String {
+    match usb_devices_read() {
+        Ok(usb_devices) => {
+            open_failure_causes(descriptor, &usb_devices, node_accessible).join("; ")
+        }
+        Err(error) => format!("usb devices could not be inspected: {:#}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ftdi::Descriptor, open_failure_causes, UsbDevice};
+    use std::ffi;
+
+    fn descriptor() -> Descriptor {
+        Descriptor {
+            vid: 0x0403,
+            pid: 0x6001,
+            serial_number: ffi::CString::new("A12345").unwrap(),
+        }
+    }
+    fn usb_device(
+        pid: u16,
+        serial_number: &str,
+        drivers: &[&str],
+    ) -> UsbDevice {
+        UsbDevice {
+            vid: 0x0403,
+            pid,
+            serial_number: Some(serial_number.to_owned()),
+            bus_number: 1,
+            device_number: 7,
+            drivers: drivers.iter().map(|driver| (*driver).to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn not_connected() {
+        let causes =
+            open_failure_causes(&descriptor(), &[usb_device(0x6001, "B00000", &[])], |_| {
+                true
+            });
+        assert_eq!(causes.len(), 1);
+        assert!(causes[0].contains("B00000"));
+    }
+
+    #[test]
+    fn wrong_id() {
+        let causes =
+            open_failure_causes(&descriptor(), &[usb_device(0x6015, "A12345", &[])], |_| {
+                true
+            });
+        assert_eq!(causes.len(), 1);
+        assert!(causes[0].contains("0403:6015"));
+    }
+
+    #[test]
+    fn permissions_and_driver() {
+        let causes = open_failure_causes(
+            &descriptor(),
+            &[usb_device(0x6001, "A12345", &["ftdi_sio"])],
+            |path| path.to_str() != Some("/dev/bus/usb/001/007"),
+        );
+        assert_eq!(causes.len(), 2);
+        assert!(causes[0].contains("/dev/bus/usb/001/007"));
+        assert!(causes[1].contains("ftdi_sio"));
+    }
+
+    #[test]
+    fn accessible() {
+        let causes = open_failure_causes(
+            &descriptor(),
+            &[usb_device(0x6001, "A12345", &["usbfs"])],
+            |_| true,
+        );
+        assert_eq!(causes.len(), 1);
+        assert!(causes[0].contains("another process"));
+    }
+}
@@ -2,12 +2,13 @@
 
 use super::{
     ftdi::{Descriptor, Descriptors, DeviceConfiguration},
-    Bits, Configuration, Parity, StopBits,
+    ftdi_diagnostics::open_failure_diagnose,
+    Bits, Configuration, Parity, Rs485DirectionRts, StopBits,
 };
 use anyhow::{bail, ensure, Context, Error};
 use libftdi1_sys::*;
 use scopeguard::defer;
-use std::{cell::RefCell, ffi, mem::MaybeUninit, ptr};
+use std::{cell::RefCell, ffi, mem::MaybeUninit, ptr, thread};
 
 #[derive(Debug)]
 pub struct Global {
@@ -175,6 +176,9 @@ impl Drop for Global {
 #[derive(Debug)]
 pub struct Device {
     context: *mut ftdi_context,
+
+    configuration: Configuration,
+    rs485_direction_rts: Option<Rs485DirectionRts>,
 }
 impl Device {
     pub fn new(
@@ -207,8 +211,9 @@ impl Device {
         };
         ensure!(
             ftdi_usb_open_desc_result == 0,
-            "ftdi_usb_open_desc() failed with code {}",
+            "ftdi_usb_open_desc() failed with code {}, possible causes: {}",
             ftdi_usb_open_desc_result,
+            open_failure_diagnose(descriptor),
         );
 
         let ftdi_set_baudrate_result =
@@ -253,11 +258,33 @@ impl Device {
             ftdi_set_latency_timer_result
         );
 
+        // start in receive mode
+        if let Some(rs485_direction_rts) = &device_configuration.rs485_direction_rts {
+            Self::rts_set(context, !rs485_direction_rts.transmit_high).context("rts_set")?;
+        }
+
         Ok(Self {
             context: context_rc.replace(ptr::null_mut()),
+
+            configuration: *configuration,
+            rs485_direction_rts: device_configuration.rs485_direction_rts,
         })
     }
 
+    fn rts_set(
+        context: *mut ftdi_context,
+        high: bool,
+    ) -> Result<(), Error> {
+        // RTS# pin is active low
+        let ftdi_setrts_result = unsafe { ftdi_setrts(context, if high { 0 } else { 1 }) };
+        ensure!(
+            ftdi_setrts_result == 0,
+            "ftdi_setrts() failed with code {}",
+            ftdi_setrts_result,
+        );
+        Ok(())
+    }
+
     pub fn purge(&mut self) -> Result<(), Error> {
         let ftdi_usb_purge_buffers_result = unsafe { ftdi_usb_purge_buffers(self.context) };
         ensure!(
@@ -271,6 +298,11 @@ impl Device {
         &mut self,
         data: &[u8],
     ) -> Result<(), Error> {
+        if let Some(rs485_direction_rts) = &self.rs485_direction_rts {
+            Self::rts_set(self.context, rs485_direction_rts.transmit_high).context("rts_set")?;
+            thread::sleep(rs485_direction_rts.guard_before);
+        }
+
         let ftdi_write_data_submit_result = unsafe {
             ftdi_write_data_submit(
                 self.context,
@@ -292,6 +324,16 @@ impl Device {
             ftdi_transfer_data_done_result,
         );
 
+        if let Some(rs485_direction_rts) = &self.rs485_direction_rts {
+            // transfer is done when data reaches chip buffer, not when it is
+            // shifted out, so wait for the whole frame
+            thread::sleep(
+                self.configuration.transmission_duration(data.len())
+                    + rs485_direction_rts.guard_after,
+            );
+            Self::rts_set(self.context, !rs485_direction_rts.transmit_high).context("rts_set")?;
+        }
+
         Ok(())
     }
     pub fn read(&mut self) -> Result<Box<[u8]>, Error> {
@@ -5,6 +5,10 @@ pub mod ftdi_linux;
 pub mod ftdi_stub;
 
 pub mod ftdi;
+#[cfg(target_os = "linux")]
+pub mod ftdi_diagnostics;
+
+use std::time::Duration;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Bits {
@@ -33,3 +37,74 @@ pub struct Configuration {
     pub stop_bits: StopBits,
     pub parity: Parity,
 }
+impl Configuration {
+    // time needed to shift out given number of characters, including start,
+    // parity and stop bits
+    pub fn transmission_duration(
+        &self,
+        characters: usize,
+    ) -> Duration {
+        let bits_data = match self.bits {
+            Bits::Bits7 => 7.0,
+            Bits::Bits8 => 8.0,
+        };
+        let bits_parity = match self.parity {
+            Parity::None => 0.0,
+            Parity::Odd | Parity::Even | Parity::Mark | Parity::Space => 1.0,
+        };
+        let bits_stop = match self.stop_bits {
+            StopBits::StopBits1 => 1.0,
+            StopBits::StopBits15 => 1.5,
+            StopBits::StopBits2 => 2.0,
+        };
+        let bits_character = 1.0 + bits_data + bits_parity + bits_stop;
+
+        Duration::from_secs_f64(characters as f64 * bits_character / self.baud_rate as f64)
+    }
+}
+
+// driver enable for rs-485 transceivers without automatic direction control
+// (eg. bare MAX485 modules), with DE/RE connected to RTS
+// driver is enabled for the time of write only, so the bus is released for
+// the response
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rs485DirectionRts {
+    // level of RTS pin while transmitting
+    pub transmit_high: bool,
+    // delay between enabling driver and first bit, for transceiver to settle
+    pub guard_before: Duration,
+    // delay between last bit and disabling driver
+    pub guard_after: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bits, Configuration, Parity, StopBits};
+    use std::time::Duration;
+
+    #[test]
+    fn transmission_duration() {
+        let configuration = Configuration {
+            baud_rate: 9600,
+            bits: Bits::Bits8,
+            stop_bits: StopBits::StopBits2,
+            parity: Parity::None,
+        };
+        assert_eq!(
+            configuration.transmission_duration(96),
+            Duration::from_millis(110)
+        );
+
+        let configuration = Configuration {
+            baud_rate: 100_000,
+            bits: Bits::Bits7,
+            stop_bits: StopBits::StopBits1,
+            parity: Parity::Even,
+        };
+        assert_eq!(
+            configuration.transmission_duration(10),
+            Duration::from_millis(1)
+        );
+        assert_eq!(configuration.transmission_duration(0), Duration::ZERO);
+    }
+}
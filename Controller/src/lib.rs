@@ -33,6 +33,8 @@ pub mod gui;
 pub mod interfaces;
 pub mod modules;
 pub mod signals;
+#[cfg(any(test, feature = "simulation"))]
+pub mod simulation;
 pub mod stubs;
 pub mod util;
 pub mod web;
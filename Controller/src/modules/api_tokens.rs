@@ -0,0 +1,538 @@
+use super::{
+    fs::Fs,
+    secrets::Secret,
+    sqlite::SQLite,
+    sqlite_migrations::{
+        self,
+        cli::Database,
+        graph::{Graph, GraphResolver},
+    },
+};
+use crate::{
+    devices,
+    modules::restart::Restart,
+    web::{self, root_service::Authorizer, uri_cursor},
+};
+use anyhow::{ensure, Context, Error};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, Future, FutureExt};
+use http::{header, Method, StatusCode};
+use parking_lot::RwLock;
+use phf::phf_map;
+use rand::{rngs::OsRng, RngCore};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, sync::Arc};
+
+static MIGRATIONS: Graph = phf_map! {
+    1u32 => phf_map! {
+        0u32 => Some("
+            CREATE TABLE `tokens` (
+                `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+                `name` TEXT NOT NULL,
+                `scope` TEXT NOT NULL, -- json
+                `hash` BLOB NOT NULL UNIQUE,
+                `created` INTEGER NOT NULL -- unix seconds
+            ) STRICT;
+        "),
+    },
+};
+static MIGRATIONS_RESOLVER: GraphResolver<'static> = GraphResolver(&MIGRATIONS);
+
+// routes managing credentials or whole system, they always require admin
+// token, even if anonymous access is allowed
+fn admin_required(api_path: &str) -> bool {
+    let mut segments = api_path.split('/');
+    match (segments.next(), segments.next()) {
+        (Some(route), _) if route == ApiTokens::ROUTE => true,
+        (Some(route), _) if route == Restart::ROUTE => true,
+        (Some("secrets"), _) => true,
+        (Some("emergency-stop"), Some("reset")) => true,
+        _ => false,
+    }
+}
+
+// what token holder is allowed to do
+// everything readable is visible to all scopes, token management, secrets,
+// restart and emergency stop reset require admin
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Scope {
+    ReadOnly,
+    // read only, plus any request to listed devices (eg. wall tablet)
+    Actuate { device_ids: Box<[devices::Id]> },
+    Admin,
+}
+impl Scope {
+    // api_path is relative to api root, eg. "devices-runner/devices/12/device"
+    pub fn allows(
+        &self,
+        method: &Method,
+        api_path: &str,
+    ) -> bool {
+        let device_ids = match self {
+            Scope::Admin => return true,
+            Scope::ReadOnly => &[][..],
+            Scope::Actuate { device_ids } => &device_ids[..],
+        };
+
+        if admin_required(api_path) {
+            return false;
+        }
+        if *method == Method::GET || *method == Method::HEAD {
+            return true;
+        }
+
+        let mut segments = api_path.split('/');
+        match (segments.next(), segments.next(), segments.next()) {
+            (Some("devices-runner"), Some("devices"), Some(device_id)) => {
+                match device_id.parse::<devices::Id>() {
+                    Ok(device_id) => device_ids.contains(&device_id),
+                    Err(_) => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Token {
+    pub id: i64,
+    pub name: String,
+    pub scope: Scope,
+    pub created: DateTime<Utc>,
+}
+
+type Hash = [u8; 32];
+fn hash(token: &str) -> Hash {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+// initial token is registered only if there is no admin token, so it
+// bootstraps token management, or restores access if all admin tokens were
+// removed
+// returns true if token was inserted and should be persisted
+fn admin_token_initial_register(
+    scopes_by_hash: &mut HashMap<Hash, Scope>,
+    admin_token_initial_hash: Hash,
+) -> bool {
+    if scopes_by_hash.values().any(|scope| *scope == Scope::Admin) {
+        return false;
+    }
+    scopes_by_hash.insert(admin_token_initial_hash, Scope::Admin);
+    true
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    // if false, requests without token are rejected
+    pub anonymous_allowed: bool,
+    // registered as admin token on load if there is no admin token yet, as
+    // token management requires admin scope itself
+    // stays valid until removed through the api
+    pub admin_token_initial: Option<Secret>,
+}
+
+// long lived credentials for integrations (grafana, scripts, tablets), sent as
+// "Authorization: Bearer <token>"
+// only hashes are stored, plaintext token is returned once on creation
+#[derive(Debug)]
+pub struct ApiTokens<'f> {
+    anonymous_allowed: bool,
+    admin_token_initial_hash: Option<Hash>,
+
+    sqlite: SQLite<'f>,
+    // authorization is done synchronously, before request is routed
+    scopes_by_hash: Arc<RwLock<HashMap<Hash, Scope>>>,
+}
+impl<'f> ApiTokens<'f> {
+    const SQLITE_NAME: &'static str = "api_tokens";
+    pub const ROUTE: &'static str = "api-tokens";
+
+    const ADMIN_TOKEN_INITIAL_NAME: &'static str = "initial admin";
+    const ADMIN_TOKEN_INITIAL_LENGTH_MIN: usize = 16;
+
+    pub fn new(
+        fs: &'f Fs,
+        configuration: Configuration,
+    ) -> Self {
+        let admin_token_initial_hash =
+            configuration
+                .admin_token_initial
+                .map(|admin_token_initial| {
+                    assert!(
+                        admin_token_initial.expose().len() >= Self::ADMIN_TOKEN_INITIAL_LENGTH_MIN,
+                        "initial admin token must be at least {} characters long",
+                        Self::ADMIN_TOKEN_INITIAL_LENGTH_MIN
+                    );
+                    hash(admin_token_initial.expose())
+                });
+
+        let sqlite = SQLite::new(Self::SQLITE_NAME.to_owned(), fs);
+
+        Self {
+            anonymous_allowed: configuration.anonymous_allowed,
+            admin_token_initial_hash,
+
+            sqlite,
+            scopes_by_hash: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    // for running migrations before startup, see sqlite_migrations::cli
+    pub fn migrations_database() -> Database<'static> {
+        Database {
+            name: Self::SQLITE_NAME.to_owned(),
+            resolver: &MIGRATIONS_RESOLVER,
+        }
+    }
+
+    // must be called before serving requests
+    pub async fn load(&self) -> Result<(), Error> {
+        let admin_token_initial_hash = self.admin_token_initial_hash;
+        let scopes_by_hash = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let mut scopes_by_hash = transaction
+                    .prepare("SELECT `hash`, `scope` FROM `tokens`")
+                    .context("prepare")?
+                    .query_map([], |row| {
+                        Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, String>(1)?))
+                    })
+                    .context("query_map")?
+                    .map(|row| -> Result<(Hash, Scope), Error> {
+                        let (hash, scope) = row.context("row")?;
+                        let hash = Hash::try_from(hash.as_slice()).context("hash")?;
+                        let scope = serde_json::from_str(&scope).context("scope")?;
+                        Ok((hash, scope))
+                    })
+                    .collect::<Result<HashMap<_, _>, _>>()
+                    .context("collect")?;
+
+                if let Some(admin_token_initial_hash) = admin_token_initial_hash {
+                    if admin_token_initial_register(&mut scopes_by_hash, admin_token_initial_hash) {
+                        transaction
+                            .execute(
+                                "INSERT INTO `tokens` (`name`, `scope`, `hash`, `created`) VALUES (?, ?, ?, ?)",
+                                rusqlite::params![
+                                    Self::ADMIN_TOKEN_INITIAL_NAME,
+                                    serde_json::to_string(&Scope::Admin).context("to_string")?,
+                                    admin_token_initial_hash,
+                                    Utc::now().timestamp()
+                                ],
+                            )
+                            .context("execute")?;
+                        log::info!("api tokens: initial admin token registered");
+                    }
+                }
+
+                Ok(scopes_by_hash)
+            })
+            .await
+            .context("transaction")??;
+
+        *self.scopes_by_hash.write() = scopes_by_hash;
+
+        Ok(())
+    }
+
+    pub fn list(&self) -> impl Future<Output = Result<Box<[Token]>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let tokens = transaction
+                    .prepare("SELECT `id`, `name`, `scope`, `created` FROM `tokens` ORDER BY `id`")
+                    .context("prepare")?
+                    .query_map([], |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, i64>(3)?,
+                        ))
+                    })
+                    .context("query_map")?
+                    .map(|row| -> Result<Token, Error> {
+                        let (id, name, scope, created) = row.context("row")?;
+                        let scope = serde_json::from_str(&scope).context("scope")?;
+                        let created =
+                            DateTime::<Utc>::from_timestamp(created, 0).context("created")?;
+                        Ok(Token {
+                            id,
+                            name,
+                            scope,
+                            created,
+                        })
+                    })
+                    .collect::<Result<Box<[_]>, _>>()
+                    .context("collect")?;
+                Ok(tokens)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // resolves to plaintext token
+    pub fn create(
+        &self,
+        name: String,
+        scope: Scope,
+    ) -> Result<impl Future<Output = Result<String, Error>> + Send + 'static, Error> {
+        ensure!(!name.is_empty(), "name must not be empty");
+
+        let mut token = [0u8; 32];
+        OsRng.fill_bytes(&mut token);
+        let token = hex::encode(token);
+        let token_hash = hash(&token);
+
+        let scope_json = serde_json::to_string(&scope).context("to_string")?;
+        let scopes_by_hash = self.scopes_by_hash.clone();
+
+        let persisted = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                transaction
+                    .execute(
+                        "INSERT INTO `tokens` (`name`, `scope`, `hash`, `created`) VALUES (?, ?, ?, ?)",
+                        rusqlite::params![name, scope_json, token_hash, Utc::now().timestamp()],
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .map(move |result| {
+                result.context("transaction").and_then(|result| result)?;
+                scopes_by_hash.write().insert(token_hash, scope);
+                Ok(token)
+            });
+        Ok(persisted)
+    }
+
+    // resolves to false if token did not exist
+    pub fn remove(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<bool, Error>> + Send + 'static {
+        let scopes_by_hash = self.scopes_by_hash.clone();
+
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let hash = transaction
+                    .query_row(
+                        "DELETE FROM `tokens` WHERE `id` = ? RETURNING `hash`",
+                        [id],
+                        |row| row.get::<_, Vec<u8>>(0),
+                    )
+                    .optional()
+                    .context("query_row")?;
+                Ok(hash)
+            })
+            .map(move |result| {
+                let hash = result.context("transaction").and_then(|result| result)?;
+                let hash = match hash {
+                    Some(hash) => hash,
+                    None => return Ok(false),
+                };
+                let hash = Hash::try_from(hash.as_slice()).context("hash")?;
+                scopes_by_hash.write().remove(&hash);
+                Ok(true)
+            })
+    }
+
+    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+        // creates or migrates the tables
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, transaction).context("execute")?;
+
+        Ok(())
+    }
+}
+impl<'f> Authorizer for ApiTokens<'f> {
+    fn authorize(
+        &self,
+        request: &web::Request,
+        api_path: &str,
+    ) -> Result<(), StatusCode> {
+        let authorization = match request.headers().get(header::AUTHORIZATION) {
+            Some(authorization) => authorization,
+            None => {
+                return if self.anonymous_allowed && !admin_required(api_path) {
+                    Ok(())
+                } else {
+                    Err(StatusCode::UNAUTHORIZED)
+                };
+            }
+        };
+
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        let scopes_by_hash = self.scopes_by_hash.read();
+        let scope = scopes_by_hash
+            .get(&hash(token))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        if !scope.allows(request.method(), api_path) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenCreateRequest {
+    name: String,
+    scope: Scope,
+}
+#[derive(Serialize)]
+struct TokenCreateResponse {
+    token: String,
+}
+
+impl<'f> uri_cursor::Handler for ApiTokens<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let tokens = self.list();
+                    async {
+                        match tokens.await {
+                            Ok(tokens) => web::Response::ok_json(tokens),
+                            Err(error) => {
+                                log::error!("api tokens: list: {:?}", error);
+                                web::Response::error_500()
+                            }
+                        }
+                    }
+                    .boxed()
+                }
+                http::Method::POST => {
+                    let token_create_request = match request.body_parse_json::<TokenCreateRequest>()
+                    {
+                        Ok(token_create_request) => token_create_request,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed();
+                        }
+                    };
+
+                    match self.create(token_create_request.name, token_create_request.scope) {
+                        Ok(created) => async {
+                            match created.await {
+                                Ok(token) => web::Response::ok_json(TokenCreateResponse { token }),
+                                Err(error) => {
+                                    log::error!("api tokens: create: {:?}", error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed(),
+                        Err(error) => async { web::Response::error_400_from_error(error) }.boxed(),
+                    }
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next(id, uri_cursor) => {
+                let id: i64 = match id.parse().context("id") {
+                    Ok(id) => id,
+                    Err(error) => {
+                        return async { web::Response::error_400_from_error(error) }.boxed()
+                    }
+                };
+                match uri_cursor.as_ref() {
+                    uri_cursor::UriCursor::Terminal => match *request.method() {
+                        http::Method::DELETE => {
+                            let removed = self.remove(id);
+                            async {
+                                match removed.await {
+                                    Ok(true) => web::Response::ok_empty(),
+                                    Ok(false) => web::Response::error_404(),
+                                    Err(error) => {
+                                        log::error!("api tokens: remove: {:?}", error);
+                                        web::Response::error_500()
+                                    }
+                                }
+                            }
+                            .boxed()
+                        }
+                        _ => async { web::Response::error_405() }.boxed(),
+                    },
+                    _ => async { web::Response::error_404() }.boxed(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{admin_required, admin_token_initial_register, hash, Scope};
+    use http::Method;
+    use std::collections::HashMap;
+
+    #[test]
+    fn admin_required_routes() {
+        assert!(admin_required("api-tokens"));
+        assert!(admin_required("api-tokens/1"));
+        assert!(admin_required("secrets"));
+        assert!(admin_required("secrets/camera.gate"));
+        assert!(admin_required("restart"));
+        assert!(admin_required("emergency-stop/reset"));
+        assert!(!admin_required("emergency-stop"));
+        assert!(!admin_required("devices-runner/devices/3/device"));
+        assert!(!admin_required("events/events"));
+    }
+
+    #[test]
+    fn scope_allows() {
+        let read_only = Scope::ReadOnly;
+        assert!(read_only.allows(&Method::GET, "devices-runner/devices/list"));
+        assert!(!read_only.allows(&Method::POST, "devices-runner/devices/3/device"));
+        assert!(!read_only.allows(&Method::GET, "api-tokens"));
+        assert!(!read_only.allows(&Method::GET, "secrets"));
+
+        let actuate = Scope::Actuate {
+            device_ids: Box::new([3]),
+        };
+        assert!(actuate.allows(&Method::GET, "events/events"));
+        assert!(actuate.allows(&Method::POST, "devices-runner/devices/3/device"));
+        assert!(!actuate.allows(&Method::POST, "devices-runner/devices/4/device"));
+        assert!(!actuate.allows(&Method::POST, "devices-runner/devices/03x/device"));
+        assert!(!actuate.allows(&Method::POST, "devices-runner/maintenance"));
+        assert!(!actuate.allows(&Method::DELETE, "api-tokens/1"));
+        assert!(!actuate.allows(&Method::POST, "restart"));
+        assert!(!actuate.allows(&Method::POST, "emergency-stop/reset"));
+
+        let admin = Scope::Admin;
+        assert!(admin.allows(&Method::DELETE, "api-tokens/1"));
+        assert!(admin.allows(&Method::PUT, "secrets/camera.gate"));
+        assert!(admin.allows(&Method::POST, "emergency-stop/reset"));
+    }
+
+    #[test]
+    fn admin_token_initial() {
+        let mut scopes_by_hash = HashMap::from([(hash("tablet"), Scope::ReadOnly)]);
+        assert!(admin_token_initial_register(
+            &mut scopes_by_hash,
+            hash("initial")
+        ));
+        assert_eq!(scopes_by_hash.get(&hash("initial")), Some(&Scope::Admin));
+
+        // admin exists, so initial token is not registered
+        let mut scopes_by_hash = HashMap::from([(hash("admin"), Scope::Admin)]);
+        assert!(!admin_token_initial_register(
+            &mut scopes_by_hash,
+            hash("initial")
+        ));
+        assert_eq!(scopes_by_hash.get(&hash("initial")), None);
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use parking_lot::Mutex;
+use std::fmt;
+
+pub trait Source: Send + Sync + fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// wall clock of the operating system
+#[derive(Debug)]
+pub struct SourceSystem;
+impl Source for SourceSystem {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// manually driven clock, for tests and simulations
+#[derive(Debug)]
+pub struct SourceSimulated {
+    now: Mutex<DateTime<Utc>>,
+}
+impl SourceSimulated {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        let now = Mutex::new(now);
+
+        Self { now }
+    }
+
+    pub fn set(
+        &self,
+        now: DateTime<Utc>,
+    ) {
+        *self.now.lock() = now;
+    }
+    pub fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        *self.now.lock() += duration;
+    }
+}
+impl Source for SourceSimulated {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock()
+    }
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    // IANA timezone of the installation, used for all calendar calculations
+    pub timezone: Tz,
+}
+impl Default for Configuration {
+    fn default() -> Self {
+        Self { timezone: Tz::UTC }
+    }
+}
+
+#[derive(Debug)]
+pub struct Clock<'s> {
+    configuration: Configuration,
+    source: &'s dyn Source,
+}
+impl<'s> Clock<'s> {
+    pub fn new(
+        configuration: Configuration,
+        source: &'s dyn Source,
+    ) -> Self {
+        Self {
+            configuration,
+            source,
+        }
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.configuration.timezone
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.source.now()
+    }
+    pub fn now_local(&self) -> DateTime<Tz> {
+        self.now().with_timezone(&self.configuration.timezone)
+    }
+
+    // returns instant of given local date and time
+    // when time does not exist (DST gap) it returns the first existing instant
+    // after it when time is ambiguous (DST overlap) it returns the earlier one
+    pub fn local(
+        &self,
+        date: NaiveDate,
+        time: NaiveTime,
+    ) -> DateTime<Tz> {
+        let timezone = self.configuration.timezone;
+
+        let mut datetime = date.and_time(time);
+        loop {
+            if let Some(datetime) = timezone.from_local_datetime(&datetime).earliest() {
+                return datetime;
+            }
+            // gaps are at most few hours long, moving forward in small steps finds the end
+            datetime += Duration::minutes(15);
+        }
+    }
+    // returns first instant of given local date
+    pub fn day_start(
+        &self,
+        date: NaiveDate,
+    ) -> DateTime<Tz> {
+        self.local(date, NaiveTime::MIN)
+    }
+    // length of given local date, 23 or 25 hours on DST transition days
+    pub fn day_duration(
+        &self,
+        date: NaiveDate,
+    ) -> Duration {
+        self.day_start(date.succ_opt().unwrap()) - self.day_start(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Configuration, SourceSimulated};
+    use chrono::{Duration, NaiveDate, TimeZone, Timelike, Utc};
+    use chrono_tz::Europe::Warsaw;
+
+    #[test]
+    fn simulated_advance() {
+        let source = SourceSimulated::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let clock = Clock::new(Configuration { timezone: Warsaw }, &source);
+
+        assert_eq!(clock.now_local().hour(), 13);
+
+        source.advance(Duration::hours(2));
+        assert_eq!(
+            clock.now(),
+            Utc.with_ymd_and_hms(2024, 1, 1, 14, 0, 0).unwrap()
+        );
+        assert_eq!(clock.now_local().hour(), 15);
+    }
+
+    #[test]
+    fn dst_transitions() {
+        let source = SourceSimulated::new(Utc.with_ymd_and_hms(2024, 3, 31, 0, 30, 0).unwrap());
+        let clock = Clock::new(Configuration { timezone: Warsaw }, &source);
+
+        assert_eq!(clock.now_local().hour(), 1);
+        source.advance(Duration::hours(1));
+        assert_eq!(clock.now_local().hour(), 3); // 02:00 - 03:00 does not exist
+
+        let spring = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let autumn = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        let regular = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(clock.day_duration(spring), Duration::hours(23));
+        assert_eq!(clock.day_duration(autumn), Duration::hours(25));
+        assert_eq!(clock.day_duration(regular), Duration::hours(24));
+    }
+}
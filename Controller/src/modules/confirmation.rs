@@ -0,0 +1,244 @@
+// two-step confirmation of dangerous actuations (opening gate, disabling
+// alarm), so stray tap in gui does nothing on its own
+// first request is answered with 428 and one-time token, client asks user and
+// repeats exactly the same request with token in CONFIRMATION_TOKEN_HEADER
+use crate::web;
+use http::StatusCode;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+pub const CONFIRMATION_TOKEN_HEADER: &str = "x-confirmation-token";
+
+// time for user to confirm
+const TOKEN_VALIDITY: Duration = Duration::from_secs(30);
+// client asking for more confirmations than any user could answer loses its
+// oldest ones, when all clients together reach the global limit new ones are
+// refused
+const PENDINGS_PER_CLIENT_MAX: usize = 16;
+const PENDINGS_MAX: usize = 256;
+
+type Fingerprint = [u8; 32];
+
+// token is bound to the request it was issued for, so it can't be used to
+// confirm different action
+fn request_fingerprint(request: &web::Request) -> Fingerprint {
+    let mut hasher = Sha256::new();
+    hasher.update(request.method().as_str());
+    hasher.update([0]);
+    hasher.update(request.uri().to_string());
+    hasher.update([0]);
+    hasher.update(request.body_payload());
+    hasher.finalize().into()
+}
+
+#[derive(Debug)]
+struct Pending {
+    client: IpAddr,
+    fingerprint: Fingerprint,
+    expires: Instant,
+}
+
+#[derive(Debug, Serialize)]
+struct ConfirmationRequired<'a> {
+    confirmation_token: &'a str,
+    description: &'a str,
+}
+
+#[derive(Debug)]
+pub struct Confirmations {
+    pendings: Mutex<HashMap<String, Pending>>,
+}
+impl Confirmations {
+    pub fn new() -> Self {
+        Self {
+            pendings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // returns None if limit of pending confirmations is reached
+    fn issue(
+        &self,
+        client: IpAddr,
+        fingerprint: Fingerprint,
+        now: Instant,
+    ) -> Option<String> {
+        let mut pendings = self.pendings.lock();
+        pendings.retain(|_, pending| pending.expires > now);
+
+        let client_pendings = pendings
+            .iter()
+            .filter(|(_, pending)| pending.client == client)
+            .collect::<Vec<_>>();
+        if client_pendings.len() >= PENDINGS_PER_CLIENT_MAX {
+            // all have the same validity, so the oldest expires first
+            let token_oldest = client_pendings
+                .into_iter()
+                .min_by_key(|(_, pending)| pending.expires)
+                .map(|(token, _)| token.clone())
+                .unwrap();
+            pendings.remove(&token_oldest);
+        }
+        if pendings.len() >= PENDINGS_MAX {
+            return None;
+        }
+
+        let mut token = [0u8; 16];
+        OsRng.fill_bytes(&mut token);
+        let token = hex::encode(token);
+
+        pendings.insert(
+            token.clone(),
+            Pending {
+                client,
+                fingerprint,
+                expires: now + TOKEN_VALIDITY,
+            },
+        );
+
+        Some(token)
+    }
+
+    // tokens are single use, also when not matching
+    fn consume(
+        &self,
+        token: &str,
+        fingerprint: &Fingerprint,
+        now: Instant,
+    ) -> bool {
+        match self.pendings.lock().remove(token) {
+            Some(pending) => pending.expires > now && pending.fingerprint == *fingerprint,
+            None => false,
+        }
+    }
+
+    // returns None if request carries valid token, otherwise response to be
+    // sent instead, with new token and description of the action shown to the
+    // user
+    pub fn check(
+        &self,
+        request: &web::Request,
+        description: &str,
+    ) -> Option<web::Response> {
+        let now = Instant::now();
+        let fingerprint = request_fingerprint(request);
+
+        let token = request
+            .headers()
+            .get(CONFIRMATION_TOKEN_HEADER)
+            .and_then(|token| token.to_str().ok());
+        if let Some(token) = token {
+            if self.consume(token, &fingerprint, now) {
+                log::info!("confirmed: {}", description);
+                return None;
+            }
+        }
+
+        let confirmation_token = match self.issue(request.remote_address().ip(), fingerprint, now) {
+            Some(confirmation_token) => confirmation_token,
+            None => {
+                log::warn!("too many pending confirmations, refusing: {}", description);
+                return Some(web::Response::error(StatusCode::TOO_MANY_REQUESTS));
+            }
+        };
+        Some(web::Response::error_json(
+            StatusCode::PRECONDITION_REQUIRED,
+            ConfirmationRequired {
+                confirmation_token: &confirmation_token,
+                description,
+            },
+        ))
+    }
+}
+
+pub fn confirmations() -> &'static Confirmations {
+    static CONFIRMATIONS: Lazy<Confirmations> = Lazy::new(Confirmations::new);
+    &CONFIRMATIONS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Confirmations, PENDINGS_MAX, PENDINGS_PER_CLIENT_MAX, TOKEN_VALIDITY};
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    fn client(id: u32) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::from(id))
+    }
+
+    #[test]
+    fn issue_and_consume() {
+        let confirmations = Confirmations::new();
+        let now = Instant::now();
+
+        let token = confirmations.issue(client(1), [1; 32], now).unwrap();
+        // single use
+        assert!(confirmations.consume(&token, &[1; 32], now));
+        assert!(!confirmations.consume(&token, &[1; 32], now));
+
+        // different request
+        let token = confirmations.issue(client(1), [1; 32], now).unwrap();
+        assert!(!confirmations.consume(&token, &[2; 32], now));
+
+        // expired
+        let token = confirmations.issue(client(1), [1; 32], now).unwrap();
+        assert!(!confirmations.consume(
+            &token,
+            &[1; 32],
+            now + TOKEN_VALIDITY + Duration::from_secs(1)
+        ));
+
+        assert!(!confirmations.consume("unknown", &[1; 32], now));
+    }
+
+    #[test]
+    fn pendings_limited() {
+        let confirmations = Confirmations::new();
+        let now = Instant::now();
+
+        // client flooding with distinct requests keeps only latest ones
+        let tokens = (0..PENDINGS_PER_CLIENT_MAX as u32 * 4)
+            .map(|index| {
+                let now = now + Duration::from_millis(index.into());
+                confirmations.issue(client(1), [1; 32], now).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(confirmations.pendings.lock().len(), PENDINGS_PER_CLIENT_MAX);
+        let (tokens_evicted, tokens_kept) = tokens.split_at(tokens.len() - PENDINGS_PER_CLIENT_MAX);
+        assert!(!confirmations.consume(&tokens_evicted[0], &[1; 32], now));
+        assert!(confirmations.consume(&tokens_kept[0], &[1; 32], now));
+
+        // other clients are not affected, until global limit
+        let token_other = confirmations.issue(client(2), [1; 32], now).unwrap();
+        for index in 0..PENDINGS_MAX as u32 {
+            if confirmations.pendings.lock().len() >= PENDINGS_MAX {
+                break;
+            }
+            confirmations
+                .issue(client(100 + index), [1; 32], now)
+                .unwrap();
+        }
+        assert_eq!(confirmations.pendings.lock().len(), PENDINGS_MAX);
+        assert!(confirmations.issue(client(3), [1; 32], now).is_none());
+        assert!(confirmations.consume(&token_other, &[1; 32], now));
+
+        // expired are released
+        assert!(confirmations
+            .issue(
+                client(3),
+                [1; 32],
+                now + TOKEN_VALIDITY + Duration::from_secs(1)
+            )
+            .is_some());
+        assert_eq!(confirmations.pendings.lock().len(), 1);
+    }
+}
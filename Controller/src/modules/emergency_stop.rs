@@ -0,0 +1,175 @@
+// controller-wide emergency stop
+// once input becomes active, stop is latched until explicitly reset, even if
+// input goes back to inactive. hardware runners force their configured
+// outputs to safe states while latched, regardless of what logic requests
+use super::events;
+use crate::{
+    util::async_waker::mpmc_static,
+    web::{self, uri_cursor},
+};
+use anyhow::{ensure, Error};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Status {
+    pub input_active: bool,
+    pub latched: bool,
+    pub latched_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct EmergencyStop {
+    status: Mutex<Status>,
+    sender: mpmc_static::Sender,
+}
+impl EmergencyStop {
+    const EVENT_SOURCE: &'static str = "emergency_stop";
+
+    fn new() -> Self {
+        let status = Status {
+            input_active: false,
+            latched: false,
+            latched_since: None,
+        };
+        let status = Mutex::new(status);
+
+        let sender = mpmc_static::Sender::new();
+
+        Self { status, sender }
+    }
+
+    pub fn input_set(
+        &self,
+        active: bool,
+    ) {
+        let mut status = self.status.lock();
+        if status.input_active == active {
+            return;
+        }
+        status.input_active = active;
+
+        let latched = active && !status.latched;
+        if latched {
+            status.latched = true;
+            status.latched_since = Some(Utc::now());
+        }
+        drop(status);
+
+        if latched {
+            log::error!("emergency stop latched");
+            events::reporter().report(
+                events::Severity::Error,
+                Self::EVENT_SOURCE.to_owned(),
+                "emergency stop latched".to_owned(),
+            );
+        }
+
+        self.sender.wake();
+    }
+
+    // reset is possible only after input is released
+    pub fn reset(&self) -> Result<(), Error> {
+        let mut status = self.status.lock();
+        ensure!(!status.input_active, "input is still active");
+        if !status.latched {
+            return Ok(());
+        }
+        status.latched = false;
+        status.latched_since = None;
+        drop(status);
+
+        log::warn!("emergency stop reset");
+        events::reporter().report(
+            events::Severity::Warning,
+            Self::EVENT_SOURCE.to_owned(),
+            "emergency stop reset".to_owned(),
+        );
+
+        self.sender.wake();
+
+        Ok(())
+    }
+
+    pub fn latched(&self) -> bool {
+        self.status.lock().latched
+    }
+    pub fn status(&self) -> Status {
+        *self.status.lock()
+    }
+
+    // woken after input or latch changes
+    pub fn receiver(&self) -> mpmc_static::Receiver {
+        self.sender.receiver()
+    }
+}
+impl uri_cursor::Handler for EmergencyStop {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let status = self.status();
+                    async move { web::Response::ok_json(status) }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("reset", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::POST => {
+                        let result = self.reset();
+                        async move {
+                            match result {
+                                Ok(()) => web::Response::ok_empty(),
+                                Err(error) => web::Response::error_400_from_error(error),
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+pub fn emergency_stop() -> &'static EmergencyStop {
+    static EMERGENCY_STOP: Lazy<EmergencyStop> = Lazy::new(EmergencyStop::new);
+    &EMERGENCY_STOP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmergencyStop;
+
+    #[test]
+    fn latch_and_reset() {
+        let emergency_stop = EmergencyStop::new();
+        assert!(!emergency_stop.latched());
+        assert!(emergency_stop.reset().is_ok());
+
+        emergency_stop.input_set(true);
+        assert!(emergency_stop.latched());
+        assert!(emergency_stop.status().latched_since.is_some());
+
+        // input still active
+        assert!(emergency_stop.reset().is_err());
+        assert!(emergency_stop.latched());
+
+        // latched after input is released
+        emergency_stop.input_set(false);
+        assert!(emergency_stop.latched());
+
+        emergency_stop.reset().unwrap();
+        assert!(!emergency_stop.latched());
+        assert!(emergency_stop.status().latched_since.is_none());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{
+    util::async_waker::mpmc_static,
+    web::{self, sse, uri_cursor},
+};
+use anyhow::Context;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::StreamExt,
+};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{borrow::Cow, collections::VecDeque};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+// eg. camera snapshot, body is served separately from event list
+#[derive(Clone, Debug, Serialize)]
+pub struct Attachment {
+    pub content_type: &'static str,
+    #[serde(skip)]
+    pub body: Bytes,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Event {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub severity: Severity,
+    pub source: String, // eg. "houseblocks/avr_v1/0001-123456", "sqlite/logger"
+    pub message: String,
+    pub attachment: Option<Attachment>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    id_next: u64,
+    events: VecDeque<Event>,
+}
+
+#[derive(Debug)]
+pub struct Reporter {
+    inner: Mutex<Inner>,
+    sender: mpmc_static::Sender,
+}
+impl Reporter {
+    const EVENTS_RETAINED: usize = 256;
+    // all retained events are kept in memory
+    pub const ATTACHMENT_SIZE_MAX: usize = 256 * 1024;
+
+    fn new() -> Self {
+        let inner = Inner {
+            id_next: 0,
+            events: VecDeque::with_capacity(Self::EVENTS_RETAINED),
+        };
+        let inner = Mutex::new(inner);
+
+        let sender = mpmc_static::Sender::new();
+
+        Self { inner, sender }
+    }
+
+    pub fn report(
+        &self,
+        severity: Severity,
+        source: String,
+        message: String,
+    ) {
+        self.report_with_attachment(severity, source, message, None);
+    }
+    // attachments over ATTACHMENT_SIZE_MAX are dropped, event is still reported
+    pub fn report_with_attachment(
+        &self,
+        severity: Severity,
+        source: String,
+        message: String,
+        attachment: Option<Attachment>,
+    ) {
+        let attachment = match attachment {
+            Some(attachment) if attachment.body.len() > Self::ATTACHMENT_SIZE_MAX => {
+                log::warn!(
+                    "attachment of event from {} dropped, {} bytes is over limit",
+                    source,
+                    attachment.body.len()
+                );
+                None
+            }
+            attachment => attachment,
+        };
+
+        {
+            let mut inner = self.inner.lock();
+
+            let id = inner.id_next;
+            inner.id_next += 1;
+
+            if inner.events.len() >= Self::EVENTS_RETAINED {
+                inner.events.pop_front();
+            }
+            inner.events.push_back(Event {
+                id,
+                timestamp: Utc::now(),
+                severity,
+                source,
+                message,
+                attachment,
+            });
+        }
+
+        self.sender.wake();
+    }
+
+    // id the next reported event will get
+    pub fn id_next(&self) -> u64 {
+        self.inner.lock().id_next
+    }
+
+    // woken after new events are reported
+    pub fn receiver(&self) -> mpmc_static::Receiver {
+        self.sender.receiver()
+    }
+
+    // events with id greater or equal to id_since, oldest first
+    pub fn events(
+        &self,
+        id_since: u64,
+    ) -> Box<[Event]> {
+        self.inner
+            .lock()
+            .events
+            .iter()
+            .filter(|event| event.id >= id_since)
+            .cloned()
+            .collect()
+    }
+
+    pub fn attachment(
+        &self,
+        id: u64,
+    ) -> Option<Attachment> {
+        self.inner
+            .lock()
+            .events
+            .iter()
+            .find(|event| event.id == id)
+            .and_then(|event| event.attachment.clone())
+    }
+}
+impl uri_cursor::Handler for Reporter {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("events", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let id_since = match form_urlencoded::parse(
+                            request.uri().query().unwrap_or("").as_bytes(),
+                        )
+                        .find_map(|(key, value)| (key == "since").then_some(value))
+                        .map(|value| value.parse::<u64>())
+                        .transpose()
+                        {
+                            Ok(id_since) => id_since.unwrap_or(0),
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+
+                        let events = self.events(id_since);
+                        async { web::Response::ok_json(events) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                uri_cursor::UriCursor::Next(id, uri_cursor) => {
+                    let id: u64 = match id.parse().context("id") {
+                        Ok(id) => id,
+                        Err(error) => {
+                            return async { web::Response::error_400_from_error(error) }.boxed()
+                        }
+                    };
+                    match uri_cursor.as_ref() {
+                        uri_cursor::UriCursor::Next("attachment", uri_cursor) => {
+                            match uri_cursor.as_ref() {
+                                uri_cursor::UriCursor::Terminal => match *request.method() {
+                                    http::Method::GET => {
+                                        let attachment = self.attachment(id);
+                                        async {
+                                            match attachment {
+                                                Some(attachment) => {
+                                                    web::Response::ok_content_type_body(
+                                                        attachment.content_type,
+                                                        attachment.body,
+                                                    )
+                                                }
+                                                None => web::Response::error_404(),
+                                            }
+                                        }
+                                        .boxed()
+                                    }
+                                    _ => async { web::Response::error_405() }.boxed(),
+                                },
+                                _ => async { web::Response::error_404() }.boxed(),
+                            }
+                        }
+                        _ => async { web::Response::error_404() }.boxed(),
+                    }
+                }
+            },
+            // notifies about new events, client is expected to fetch them from /events
+            uri_cursor::UriCursor::Next("sse", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let sse_stream = self.sender.receiver().map(|()| sse::Event {
+                            id: None,
+                            data: Cow::from(""),
+                        });
+                        async { web::Response::ok_sse_stream(sse_stream) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+pub fn reporter() -> &'static Reporter {
+    static REPORTER: Lazy<Reporter> = Lazy::new(Reporter::new);
+    &REPORTER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attachment, Reporter, Severity};
+    use bytes::Bytes;
+
+    #[test]
+    fn retention() {
+        let reporter = Reporter::new();
+        for index in 0..Reporter::EVENTS_RETAINED + 10 {
+            reporter.report(Severity::Error, "test".to_owned(), index.to_string());
+        }
+
+        let events = reporter.events(0);
+        assert_eq!(events.len(), Reporter::EVENTS_RETAINED);
+        assert_eq!(events[0].id, 10);
+
+        let events = reporter.events(Reporter::EVENTS_RETAINED as u64 + 8);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].message,
+            (Reporter::EVENTS_RETAINED + 9).to_string()
+        );
+    }
+
+    #[test]
+    fn attachment_size() {
+        let reporter = Reporter::new();
+        for size in [16, Reporter::ATTACHMENT_SIZE_MAX + 1] {
+            reporter.report_with_attachment(
+                Severity::Warning,
+                "test".to_owned(),
+                size.to_string(),
+                Some(Attachment {
+                    content_type: "image/jpeg",
+                    body: Bytes::from(vec![0; size]),
+                }),
+            );
+        }
+
+        assert_eq!(reporter.attachment(0).unwrap().body.len(), 16);
+        assert!(reporter.attachment(1).is_none());
+        assert!(reporter.events(1)[0].attachment.is_none());
+    }
+}
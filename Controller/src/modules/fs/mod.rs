@@ -1,17 +1,47 @@
+pub mod monitor;
+
+use serde::Serialize;
 use std::{
+    collections::HashMap,
     env::{self, current_dir},
     fs::create_dir_all,
     path::{Path, PathBuf},
 };
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize)]
+pub enum StorageArea {
+    Logger,
+    Surveillance,
+    Backups,
+}
+impl StorageArea {
+    pub const ALL: [StorageArea; 3] = [Self::Logger, Self::Surveillance, Self::Backups];
+
+    pub fn directory_name(&self) -> &'static str {
+        match self {
+            Self::Logger => "logger",
+            Self::Surveillance => "surveillance",
+            Self::Backups => "backups",
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Configuration {
+    // maximum number of bytes the area is allowed to take, missing = unlimited
+    pub storage_area_quotas: HashMap<StorageArea, u64>,
+}
+
 #[derive(Debug)]
 pub struct Fs {
+    configuration: Configuration,
+
     persistent_data_directory: PathBuf,
     persistent_storage_directory: PathBuf,
     temporary_storage_directory: PathBuf,
 }
 impl Fs {
-    pub fn new() -> Self {
+    pub fn new(configuration: Configuration) -> Self {
         // TODO: Make this instance dependant
         let persistent_root = current_dir().unwrap().join("data");
         create_dir_all(&persistent_root).unwrap();
@@ -39,7 +69,14 @@ impl Fs {
         let temporary_storage_directory = temporary_root.join("storage");
         create_dir_all(&temporary_storage_directory).unwrap();
 
+        for storage_area in StorageArea::ALL {
+            create_dir_all(persistent_storage_directory.join(storage_area.directory_name()))
+                .unwrap();
+        }
+
         Self {
+            configuration,
+
             persistent_data_directory,
             persistent_storage_directory,
             temporary_storage_directory,
@@ -54,4 +91,21 @@ impl Fs {
     pub fn temporary_storage_directory(&self) -> &Path {
         &self.temporary_storage_directory
     }
+
+    pub fn storage_area_directory(
+        &self,
+        storage_area: StorageArea,
+    ) -> PathBuf {
+        self.persistent_storage_directory
+            .join(storage_area.directory_name())
+    }
+    pub fn storage_area_quota(
+        &self,
+        storage_area: StorageArea,
+    ) -> Option<u64> {
+        self.configuration
+            .storage_area_quotas
+            .get(&storage_area)
+            .copied()
+    }
 }
@@ -0,0 +1,241 @@
+use super::{super::metrics, Fs, StorageArea};
+use crate::util::{
+    async_flag,
+    fs::{directory_size, disk_space, DiskSpace},
+    observable,
+    runnable::{Exited, Runnable},
+};
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use futures::{future::FutureExt, select};
+use maplit::btreemap;
+use serde::Serialize;
+use std::{collections::HashMap, fmt, time::Duration};
+
+#[async_trait]
+pub trait CleanupHook: Send + Sync + fmt::Debug {
+    fn storage_area(&self) -> StorageArea;
+
+    // called when storage area went over its quota or disk space is low
+    async fn cleanup(&self) -> Result<(), Error>;
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub interval: Duration,
+    pub disk_space_available_min: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct StorageAreaStatus {
+    pub usage: u64,
+    pub quota: Option<u64>,
+}
+impl StorageAreaStatus {
+    pub fn quota_exceeded(&self) -> bool {
+        match self.quota {
+            Some(quota) => self.usage > quota,
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Status {
+    pub disk_space: DiskSpace,
+    pub disk_space_low: bool,
+    pub storage_areas: HashMap<StorageArea, StorageAreaStatus>,
+}
+
+#[derive(Debug)]
+pub struct Monitor<'f, 'h> {
+    fs: &'f Fs,
+    configuration: Configuration,
+    cleanup_hooks: Box<[&'h dyn CleanupHook]>,
+
+    status: observable::Value<Option<Status>>,
+}
+impl<'f, 'h> Monitor<'f, 'h> {
+    pub fn new(
+        fs: &'f Fs,
+        configuration: Configuration,
+        cleanup_hooks: Box<[&'h dyn CleanupHook]>,
+    ) -> Self {
+        let status = observable::Value::new(None);
+
+        Self {
+            fs,
+            configuration,
+            cleanup_hooks,
+
+            status,
+        }
+    }
+
+    pub fn status(&self) -> observable::Getter<'_, Option<Status>> {
+        self.status.getter()
+    }
+
+    async fn status_build(&self) -> Result<Status, Error> {
+        let disk_space =
+            disk_space(self.fs.persistent_storage_directory()).context("disk_space")?;
+        let disk_space_low = disk_space.available < self.configuration.disk_space_available_min;
+
+        let mut storage_areas = HashMap::<StorageArea, StorageAreaStatus>::new();
+        for storage_area in StorageArea::ALL {
+            let usage = directory_size(&self.fs.storage_area_directory(storage_area))
+                .await
+                .context("directory_size")?;
+            let quota = self.fs.storage_area_quota(storage_area);
+
+            storage_areas.insert(storage_area, StorageAreaStatus { usage, quota });
+        }
+
+        let status = Status {
+            disk_space,
+            disk_space_low,
+            storage_areas,
+        };
+
+        Ok(status)
+    }
+
+    fn metrics_update(status: &Status) {
+        let registry = metrics::registry();
+
+        registry
+            .gauge(
+                "fs_disk_space_total_bytes",
+                "Size of the filesystem holding persistent storage",
+                btreemap! {},
+            )
+            .set(status.disk_space.total as f64);
+        registry
+            .gauge(
+                "fs_disk_space_available_bytes",
+                "Space available on the filesystem holding persistent storage",
+                btreemap! {},
+            )
+            .set(status.disk_space.available as f64);
+        registry
+            .gauge(
+                "fs_disk_space_low",
+                "1 if available disk space is below configured minimum",
+                btreemap! {},
+            )
+            .set(if status.disk_space_low { 1.0 } else { 0.0 });
+
+        for (storage_area, storage_area_status) in status.storage_areas.iter() {
+            registry
+                .gauge(
+                    "fs_storage_area_usage_bytes",
+                    "Space used by storage area",
+                    btreemap! { "area".to_owned() => storage_area.directory_name().to_owned() },
+                )
+                .set(storage_area_status.usage as f64);
+        }
+    }
+
+    async fn cleanup(
+        &self,
+        status: &Status,
+    ) {
+        for cleanup_hook in self.cleanup_hooks.iter() {
+            let storage_area = cleanup_hook.storage_area();
+
+            let quota_exceeded = status
+                .storage_areas
+                .get(&storage_area)
+                .map(|storage_area_status| storage_area_status.quota_exceeded())
+                .unwrap_or(false);
+            if !quota_exceeded && !status.disk_space_low {
+                continue;
+            }
+
+            log::warn!(
+                "{}: running cleanup for {:?} (quota exceeded: {}, disk space low: {})",
+                self,
+                storage_area,
+                quota_exceeded,
+                status.disk_space_low
+            );
+
+            match cleanup_hook.cleanup().await.context("cleanup") {
+                Ok(()) => {}
+                Err(error) => {
+                    log::error!("{}: {:?}: {:?}", self, cleanup_hook, error);
+                }
+            }
+        }
+    }
+
+    async fn run_once(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Result<Exited, Error> {
+        loop {
+            let status = self.status_build().await.context("status_build")?;
+
+            if status.disk_space_low {
+                log::warn!(
+                    "{}: disk space low ({} bytes available)",
+                    self,
+                    status.disk_space.available
+                );
+            }
+
+            Self::metrics_update(&status);
+            self.cleanup(&status).await;
+
+            self.status.set(Some(status));
+
+            select! {
+                () = tokio::time::sleep(self.configuration.interval).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Ok(Exited)
+    }
+
+    async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        const ERROR_DELAY: Duration = Duration::from_secs(60);
+
+        loop {
+            let error = match self.run_once(exit_flag.clone()).await.context("run_once") {
+                Ok(Exited) => break,
+                Err(error) => error,
+            };
+            log::error!("{}: {:?}", self, error);
+
+            self.status.set(None);
+
+            select! {
+                () = tokio::time::sleep(ERROR_DELAY).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        Exited
+    }
+}
+#[async_trait]
+impl<'f, 'h> Runnable for Monitor<'f, 'h> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+impl<'f, 'h> fmt::Display for Monitor<'f, 'h> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "Monitor")
+    }
+}
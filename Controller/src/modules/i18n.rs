@@ -0,0 +1,249 @@
+// catalog of translated display names
+// devices and other components register label keys with translations, gui
+// receives labels resolved for user locale, internal identifiers (device
+// names, signal identifiers) stay untouched
+use crate::web::{self, uri_cursor};
+use futures::future::{BoxFuture, FutureExt};
+use http::header;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+
+// used when none of requested locales has translation
+pub const LOCALE_DEFAULT: &str = "en";
+
+pub type Labels = BTreeMap<String, String>;
+
+// locales are matched case insensitive, so they are stored in lowercase
+fn locale_normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+// exact locale first, then its language, eg. "pl-pl", "pl"
+fn locale_candidates(locale: &str) -> impl Iterator<Item = &str> {
+    let language = locale.split_once('-').map(|(language, _)| language);
+    [Some(locale), language].into_iter().flatten()
+}
+
+// parses header like "pl-PL,pl;q=0.9,en;q=0.8", most preferred first
+fn accept_language_parse(accept_language: &str) -> Box<[String]> {
+    let mut locales = accept_language
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let locale = locale_normalize(parts.next()?);
+            if locale.is_empty() || locale == "*" {
+                return None;
+            }
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f64>().ok())?;
+            Some((locale, quality))
+        })
+        .collect::<Vec<_>>();
+    // sort is stable, so header order is kept for equal qualities
+    locales.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    locales
+        .into_iter()
+        .map(|(locale, _)| locale)
+        .collect::<Box<[_]>>()
+}
+
+// ?locale=<locale> (may be repeated) takes precedence over accept-language
+pub fn request_locales(request: &web::Request) -> Box<[String]> {
+    let locales = form_urlencoded::parse(request.uri().query().unwrap_or("").as_bytes())
+        .filter(|(key, _)| key == "locale")
+        .map(|(_, value)| locale_normalize(&value))
+        .collect::<Box<[_]>>();
+    if !locales.is_empty() {
+        return locales;
+    }
+
+    request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|accept_language| accept_language.to_str().ok())
+        .map(accept_language_parse)
+        .unwrap_or_default()
+}
+
+#[derive(Debug)]
+pub struct Catalog {
+    // key -> locale -> label
+    translations: RwLock<BTreeMap<String, BTreeMap<String, String>>>,
+}
+impl Catalog {
+    pub fn new() -> Self {
+        Self {
+            translations: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    // translations for already registered key are merged, replacing existing
+    // locales
+    pub fn register<'a>(
+        &self,
+        key: &str,
+        translations: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) {
+        assert!(!key.is_empty(), "key must not be empty");
+
+        let mut catalog = self.translations.write();
+        let key_translations = catalog.entry(key.to_owned()).or_default();
+        for (locale, label) in translations {
+            key_translations.insert(locale_normalize(locale), label.to_owned());
+        }
+    }
+
+    fn translate_locked(
+        key_translations: &BTreeMap<String, String>,
+        locales: &[String],
+    ) -> Option<String> {
+        locales
+            .iter()
+            .map(String::as_str)
+            .chain([LOCALE_DEFAULT])
+            .flat_map(locale_candidates)
+            .find_map(|locale| key_translations.get(locale))
+            .cloned()
+    }
+
+    // returns None for unknown key or if no matching translation exists, so
+    // caller can fall back to internal name
+    pub fn translate(
+        &self,
+        key: &str,
+        locales: &[String],
+    ) -> Option<String> {
+        let catalog = self.translations.read();
+        Self::translate_locked(catalog.get(key)?, locales)
+    }
+
+    // all keys having translation for given locales
+    pub fn labels(
+        &self,
+        locales: &[String],
+    ) -> Labels {
+        self.translations
+            .read()
+            .iter()
+            .filter_map(|(key, key_translations)| {
+                let label = Self::translate_locked(key_translations, locales)?;
+                Some((key.clone(), label))
+            })
+            .collect::<Labels>()
+    }
+
+    pub fn locales(&self) -> Box<[String]> {
+        let mut locales = self
+            .translations
+            .read()
+            .values()
+            .flat_map(|key_translations| key_translations.keys().cloned())
+            .collect::<Vec<_>>();
+        locales.sort();
+        locales.dedup();
+        locales.into_boxed_slice()
+    }
+}
+impl uri_cursor::Handler for Catalog {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let labels = self.labels(&request_locales(&request));
+                    let response = web::Response::ok_json_etag(&request, labels);
+                    async { response }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("locales", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let locales = self.locales();
+                        async { web::Response::ok_json(locales) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+// process-wide catalog
+pub fn catalog() -> &'static Catalog {
+    static CATALOG: Lazy<Catalog> = Lazy::new(Catalog::new);
+    &CATALOG
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accept_language_parse, Catalog};
+
+    #[test]
+    fn accept_language_parse_1() {
+        assert_eq!(
+            &*accept_language_parse("en;q=0.5, pl-PL,de_DE;q=0.8, *;q=0.1, fr;q=x"),
+            &["pl-pl".to_owned(), "de-de".to_owned(), "en".to_owned()]
+        );
+    }
+
+    #[test]
+    fn translate() {
+        let catalog = Catalog::new();
+        catalog.register(
+            "device.living_room.light",
+            [("en", "Living room light"), ("pl", "Światło w salonie")],
+        );
+        catalog.register("device.garage.door", [("de-DE", "Garagentor")]);
+
+        let locales = |locales: &[&str]| {
+            locales
+                .iter()
+                .map(|locale| (*locale).to_owned())
+                .collect::<Box<[_]>>()
+        };
+
+        // language fallback
+        assert_eq!(
+            catalog
+                .translate("device.living_room.light", &locales(&["pl-pl"]))
+                .as_deref(),
+            Some("Światło w salonie")
+        );
+        // default locale fallback
+        assert_eq!(
+            catalog
+                .translate("device.living_room.light", &locales(&["fr"]))
+                .as_deref(),
+            Some("Living room light")
+        );
+        // no matching translation
+        assert_eq!(
+            catalog.translate("device.garage.door", &locales(&["de"])),
+            None
+        );
+        assert_eq!(
+            catalog
+                .translate("device.garage.door", &locales(&["fr", "de-de"]))
+                .as_deref(),
+            Some("Garagentor")
+        );
+        assert_eq!(catalog.translate("device.unknown", &locales(&[])), None);
+
+        let labels = catalog.labels(&locales(&["fr"]));
+        assert_eq!(labels.len(), 1);
+
+        assert_eq!(
+            &*catalog.locales(),
+            &["de-de".to_owned(), "en".to_owned(), "pl".to_owned()]
+        );
+    }
+}
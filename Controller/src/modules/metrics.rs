@@ -0,0 +1,512 @@
+use crate::web::{self, uri_cursor};
+use futures::future::{BoxFuture, FutureExt};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+fn name_validate(name: &str) {
+    assert!(!name.is_empty(), "name must not be empty");
+    assert!(
+        name.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'),
+        "name must be alphanumeric, underscore or colon"
+    );
+    assert!(
+        !name.chars().next().unwrap().is_ascii_digit(),
+        "name must not start with digit"
+    );
+}
+
+pub type Labels = BTreeMap<String, String>;
+
+// Counter
+#[derive(Debug)]
+pub struct Counter {
+    value: AtomicU64,
+}
+impl Counter {
+    fn new() -> Self {
+        Self {
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn increment(&self) {
+        self.add(1);
+    }
+    pub fn add(
+        &self,
+        value: u64,
+    ) {
+        self.value.fetch_add(value, Ordering::Relaxed);
+    }
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+// Gauge
+#[derive(Debug)]
+pub struct Gauge {
+    value: AtomicU64, // f64 bits
+}
+impl Gauge {
+    fn new() -> Self {
+        Self {
+            value: AtomicU64::new(0.0f64.to_bits()),
+        }
+    }
+
+    pub fn set(
+        &self,
+        value: f64,
+    ) {
+        self.value.store(value.to_bits(), Ordering::Relaxed);
+    }
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.value.load(Ordering::Relaxed))
+    }
+}
+
+// Histogram
+#[derive(Debug)]
+struct HistogramInner {
+    buckets_counts: Box<[u64]>, // not cumulative
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HistogramSnapshot {
+    pub buckets: Box<[(f64, u64)]>, // (upper bound, cumulative count)
+    pub count: u64,
+    pub sum: f64,
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Box<[f64]>,
+    inner: RwLock<HistogramInner>,
+}
+impl Histogram {
+    fn new(buckets: Box<[f64]>) -> Self {
+        assert!(
+            buckets.array_windows().all(|[a, b]| a < b),
+            "buckets must be sorted and unique"
+        );
+
+        let inner = HistogramInner {
+            buckets_counts: vec![0; buckets.len()].into_boxed_slice(),
+            count: 0,
+            sum: 0.0,
+        };
+        let inner = RwLock::new(inner);
+
+        Self { buckets, inner }
+    }
+
+    pub fn observe(
+        &self,
+        value: f64,
+    ) {
+        let mut inner = self.inner.write();
+
+        if let Some(bucket_index) = self.buckets.iter().position(|bucket| value <= *bucket) {
+            inner.buckets_counts[bucket_index] += 1;
+        }
+        inner.count += 1;
+        inner.sum += value;
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let inner = self.inner.read();
+
+        let buckets = self
+            .buckets
+            .iter()
+            .zip(inner.buckets_counts.iter())
+            .scan(0, |cumulative, (bucket, count)| {
+                *cumulative += count;
+                Some((*bucket, *cumulative))
+            })
+            .collect::<Box<[_]>>();
+
+        HistogramSnapshot {
+            buckets,
+            count: inner.count,
+            sum: inner.sum,
+        }
+    }
+}
+
+// Registry
+#[derive(Clone, Debug)]
+enum Metric {
+    Counter(Arc<Counter>),
+    Gauge(Arc<Gauge>),
+    Histogram(Arc<Histogram>),
+}
+impl Metric {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Counter(_) => "counter",
+            Self::Gauge(_) => "gauge",
+            Self::Histogram(_) => "histogram",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Family {
+    help: String,
+    metrics: BTreeMap<Labels, Metric>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum SampleValue {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(HistogramSnapshot),
+}
+#[derive(Debug, Serialize)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Labels,
+    pub value: SampleValue,
+}
+
+#[derive(Debug)]
+pub struct Registry {
+    families: RwLock<BTreeMap<String, Family>>,
+}
+impl Registry {
+    pub fn new() -> Self {
+        Self {
+            families: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    // returns existing metric if already registered with same name and labels
+    fn register(
+        &self,
+        name: &str,
+        help: &str,
+        labels: Labels,
+        metric_new: impl FnOnce() -> Metric,
+    ) -> Metric {
+        name_validate(name);
+        labels.keys().for_each(|label| name_validate(label));
+
+        let mut families = self.families.write();
+
+        let family = families.entry(name.to_owned()).or_insert_with(|| Family {
+            help: help.to_owned(),
+            metrics: BTreeMap::new(),
+        });
+        let metric = family
+            .metrics
+            .entry(labels)
+            .or_insert_with(metric_new)
+            .clone();
+        assert!(
+            family
+                .metrics
+                .values()
+                .all(|family_metric| family_metric.type_name() == metric.type_name()),
+            "metric {} registered with different types",
+            name
+        );
+
+        metric
+    }
+
+    pub fn counter(
+        &self,
+        name: &str,
+        help: &str,
+        labels: Labels,
+    ) -> Arc<Counter> {
+        match self.register(name, help, labels, || {
+            Metric::Counter(Arc::new(Counter::new()))
+        }) {
+            Metric::Counter(counter) => counter,
+            _ => panic!("metric {} is not a counter", name),
+        }
+    }
+    pub fn gauge(
+        &self,
+        name: &str,
+        help: &str,
+        labels: Labels,
+    ) -> Arc<Gauge> {
+        match self.register(name, help, labels, || Metric::Gauge(Arc::new(Gauge::new()))) {
+            Metric::Gauge(gauge) => gauge,
+            _ => panic!("metric {} is not a gauge", name),
+        }
+    }
+    pub fn histogram(
+        &self,
+        name: &str,
+        help: &str,
+        labels: Labels,
+        buckets: Box<[f64]>,
+    ) -> Arc<Histogram> {
+        match self.register(name, help, labels, || {
+            Metric::Histogram(Arc::new(Histogram::new(buckets)))
+        }) {
+            Metric::Histogram(histogram) => histogram,
+            _ => panic!("metric {} is not a histogram", name),
+        }
+    }
+
+    pub fn samples(&self) -> Box<[Sample]> {
+        self.families
+            .read()
+            .iter()
+            .flat_map(|(name, family)| {
+                family.metrics.iter().map(|(labels, metric)| {
+                    let value = match metric {
+                        Metric::Counter(counter) => SampleValue::Counter(counter.get()),
+                        Metric::Gauge(gauge) => SampleValue::Gauge(gauge.get()),
+                        Metric::Histogram(histogram) => {
+                            SampleValue::Histogram(histogram.snapshot())
+                        }
+                    };
+                    Sample {
+                        name: name.clone(),
+                        labels: labels.clone(),
+                        value,
+                    }
+                })
+            })
+            .collect::<Box<[_]>>()
+    }
+
+    // https://prometheus.io/docs/instrumenting/exposition_formats/
+    pub fn prometheus_render(&self) -> String {
+        fn labels_render(
+            labels: &Labels,
+            extra: Option<(&str, &str)>,
+        ) -> String {
+            let labels = labels
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .chain(extra)
+                .map(|(key, value)| {
+                    let value = value
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace('\n', "\\n");
+                    format!("{}=\"{}\"", key, value)
+                })
+                .collect::<Vec<_>>();
+
+            if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels.join(","))
+            }
+        }
+
+        let mut output = String::new();
+
+        for (name, family) in self.families.read().iter() {
+            let type_name = match family.metrics.values().next() {
+                Some(metric) => metric.type_name(),
+                None => continue,
+            };
+            writeln!(output, "# HELP {} {}", name, family.help.replace('\n', " ")).unwrap();
+            writeln!(output, "# TYPE {} {}", name, type_name).unwrap();
+
+            for (labels, metric) in family.metrics.iter() {
+                match metric {
+                    Metric::Counter(counter) => {
+                        writeln!(
+                            output,
+                            "{}{} {}",
+                            name,
+                            labels_render(labels, None),
+                            counter.get()
+                        )
+                        .unwrap();
+                    }
+                    Metric::Gauge(gauge) => {
+                        writeln!(
+                            output,
+                            "{}{} {}",
+                            name,
+                            labels_render(labels, None),
+                            gauge.get()
+                        )
+                        .unwrap();
+                    }
+                    Metric::Histogram(histogram) => {
+                        let snapshot = histogram.snapshot();
+                        for (bucket, count) in snapshot.buckets.iter() {
+                            writeln!(
+                                output,
+                                "{}_bucket{} {}",
+                                name,
+                                labels_render(labels, Some(("le", &bucket.to_string()))),
+                                count
+                            )
+                            .unwrap();
+                        }
+                        writeln!(
+                            output,
+                            "{}_bucket{} {}",
+                            name,
+                            labels_render(labels, Some(("le", "+Inf"))),
+                            snapshot.count
+                        )
+                        .unwrap();
+                        writeln!(
+                            output,
+                            "{}_sum{} {}",
+                            name,
+                            labels_render(labels, None),
+                            snapshot.sum
+                        )
+                        .unwrap();
+                        writeln!(
+                            output,
+                            "{}_count{} {}",
+                            name,
+                            labels_render(labels, None),
+                            snapshot.count
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+
+        output
+    }
+}
+impl uri_cursor::Handler for Registry {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("prometheus", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let payload = self.prometheus_render();
+                        async {
+                            web::Response::ok_content_type_body(
+                                "text/plain; version=0.0.4",
+                                payload.into(),
+                            )
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next("samples", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        let samples = self.samples();
+                        async { web::Response::ok_json(samples) }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+// process-wide registry
+pub fn registry() -> &'static Registry {
+    static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+    &REGISTRY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Registry;
+    use indoc::indoc;
+    use maplit::btreemap;
+
+    #[test]
+    fn same_metric_is_returned() {
+        let registry = Registry::new();
+
+        let counter_1 = registry.counter("requests", "", btreemap! {});
+        let counter_2 = registry.counter("requests", "", btreemap! {});
+        counter_1.increment();
+        counter_2.add(2);
+
+        assert_eq!(counter_1.get(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn different_type_panics() {
+        let registry = Registry::new();
+
+        registry.counter("value", "", btreemap! {});
+        registry.gauge("value", "", btreemap! {});
+    }
+
+    #[test]
+    fn prometheus_render_1() {
+        let registry = Registry::new();
+
+        registry
+            .counter(
+                "requests_total",
+                "Requests count",
+                btreemap! {"method".to_owned() => "GET".to_owned()},
+            )
+            .add(5);
+        registry
+            .gauge("temperature", "Temperature", btreemap! {})
+            .set(21.5);
+        let histogram = registry.histogram(
+            "duration_seconds",
+            "Duration",
+            btreemap! {},
+            vec![0.1, 1.0].into_boxed_slice(),
+        );
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(2.0);
+
+        assert_eq!(
+            registry.prometheus_render(),
+            indoc!(
+                r#"
+                # HELP duration_seconds Duration
+                # TYPE duration_seconds histogram
+                duration_seconds_bucket{le="0.1"} 1
+                duration_seconds_bucket{le="1"} 2
+                duration_seconds_bucket{le="+Inf"} 3
+                duration_seconds_sum 2.55
+                duration_seconds_count 3
+                # HELP requests_total Requests count
+                # TYPE requests_total counter
+                requests_total{method="GET"} 5
+                # HELP temperature Temperature
+                # TYPE temperature gauge
+                temperature 21.5
+            "#
+            )
+        );
+    }
+}
@@ -1,4 +1,14 @@
+pub mod api_tokens;
+pub mod backup;
+pub mod clock;
+pub mod confirmation;
+pub mod emergency_stop;
+pub mod events;
 pub mod fs;
+pub mod i18n;
+pub mod metrics;
 pub mod module_path;
+pub mod restart;
+pub mod secrets;
 pub mod sqlite;
 pub mod sqlite_migrations;
@@ -0,0 +1,97 @@
+use crate::{
+    util::async_flag,
+    web::{self, uri_cursor},
+};
+use anyhow::Error;
+use futures::future::{BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+// in-place restart, used eg. after binary upgrade
+// app finalizes all devices (flushing loggers, counters, etc.) as during
+// regular shutdown, then replaces the process with the current executable,
+// keeping the pid, so service manager does not notice
+#[derive(Debug)]
+pub struct Restart {
+    sender: Mutex<Option<async_flag::Sender>>,
+    receiver: async_flag::Receiver,
+}
+impl Restart {
+    pub const ROUTE: &'static str = "restart";
+
+    pub fn new() -> Self {
+        let (sender, receiver) = async_flag::pair();
+        let sender = Mutex::new(Some(sender));
+
+        Self { sender, receiver }
+    }
+
+    // returns false if restart was already requested
+    pub fn request(&self) -> bool {
+        match self.sender.lock().take() {
+            Some(sender) => {
+                sender.signal();
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn requested(&self) -> bool {
+        self.sender.lock().is_none()
+    }
+
+    pub fn receiver(&self) -> async_flag::Receiver {
+        self.receiver.clone()
+    }
+}
+
+// returns only on failure
+#[cfg(unix)]
+pub fn exec() -> Error {
+    use anyhow::Context;
+    use std::{env, os::unix::process::CommandExt, process::Command};
+
+    let executable = match env::current_exe().context("current_exe") {
+        Ok(executable) => executable,
+        Err(error) => return error,
+    };
+
+    let error = Command::new(executable).args(env::args_os().skip(1)).exec();
+    Error::new(error).context("exec")
+}
+#[cfg(not(unix))]
+pub fn exec() -> Error {
+    anyhow::anyhow!("restart is supported only on unix")
+}
+
+impl uri_cursor::Handler for Restart {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    #[derive(Debug, Serialize)]
+                    struct Status {
+                        requested: bool,
+                    }
+
+                    let status = Status {
+                        requested: self.requested(),
+                    };
+                    async { web::Response::ok_json(status) }.boxed()
+                }
+                http::Method::POST => {
+                    if self.request() {
+                        log::info!("restart: requested");
+                    }
+                    async { web::Response::ok_empty() }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use super::{
+    fs::Fs,
+    sqlite::SQLite,
+    sqlite_migrations::{
+        self,
+        cli::Database,
+        graph::{Graph, GraphResolver},
+    },
+};
+use crate::web::{self, uri_cursor};
+use anyhow::{anyhow, ensure, Context, Error};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use futures::future::{BoxFuture, Future, FutureExt};
+use phf::phf_map;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, io::Write, path::Path, str};
+use zeroize::Zeroizing;
+
+static MIGRATIONS: Graph = phf_map! {
+    1u32 => phf_map! {
+        0u32 => Some("
+            CREATE TABLE `secrets` (
+                `name` TEXT PRIMARY KEY NOT NULL,
+                `nonce` BLOB NOT NULL,
+                `ciphertext` BLOB NOT NULL
+            ) STRICT;
+        "),
+    },
+};
+static MIGRATIONS_RESOLVER: GraphResolver<'static> = GraphResolver(&MIGRATIONS);
+
+// name of the secret in the store, to be put in configuration instead of the
+// secret itself
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretReference(pub String);
+impl fmt::Display for SecretReference {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// plaintext secret, wiped from memory when dropped
+// should be fetched right before use and dropped as soon as possible
+pub struct Secret(Zeroizing<String>);
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Debug for Secret {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+fn name_validate(name: &str) -> Result<(), Error> {
+    ensure!(!name.is_empty(), "name must not be empty");
+    ensure!(
+        name.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c)),
+        "name must contain only lower text, digits, dot, underscore or dash"
+    );
+    Ok(())
+}
+
+// credentials store, encrypted at rest
+// key is kept in separate file next to the database, it is not a sqlite file,
+// so it's not included in backups together with the encrypted values
+pub struct Secrets<'f> {
+    sqlite: SQLite<'f>,
+    cipher: ChaCha20Poly1305,
+}
+impl<'f> fmt::Debug for Secrets<'f> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_struct("Secrets")
+            .field("sqlite", &self.sqlite)
+            .finish_non_exhaustive()
+    }
+}
+impl<'f> Secrets<'f> {
+    const SQLITE_NAME: &'static str = "secrets";
+    const KEY_FILE_NAME: &'static str = "secrets.key";
+
+    pub fn new(fs: &'f Fs) -> Result<Self, Error> {
+        let key =
+            Self::key_load_or_create(&fs.persistent_data_directory().join(Self::KEY_FILE_NAME))
+                .context("key_load_or_create")?;
+        let cipher = ChaCha20Poly1305::new(&key);
+
+        let sqlite = SQLite::new(Self::SQLITE_NAME.to_owned(), fs);
+
+        Ok(Self { sqlite, cipher })
+    }
+
+    // for running migrations before startup, see sqlite_migrations::cli
+    pub fn migrations_database() -> Database<'static> {
+        Database {
+            name: Self::SQLITE_NAME.to_owned(),
+            resolver: &MIGRATIONS_RESOLVER,
+        }
+    }
+
+    fn key_load_or_create(path: &Path) -> Result<Key, Error> {
+        match fs::read(path) {
+            Ok(key) => {
+                let key = Zeroizing::new(key);
+                ensure!(key.len() == 32, "invalid key length");
+                return Ok(*Key::from_slice(&key));
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error).context("read"),
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path).context("open")?;
+        file.write_all(&key).context("write_all")?;
+        file.sync_all().context("sync_all")?;
+
+        log::info!("secrets: new key created");
+
+        Ok(key)
+    }
+
+    fn encrypt(
+        cipher: &ChaCha20Poly1305,
+        name: &str,
+        value: &Secret,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        // name is authenticated, so values can't be swapped between names
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.expose().as_bytes(),
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("encrypt failed"))?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+    fn decrypt(
+        cipher: &ChaCha20Poly1305,
+        name: &str,
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Secret, Error> {
+        ensure!(nonce.len() == 12, "invalid nonce length");
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("decrypt failed, key mismatch or data corrupted"))?;
+        let plaintext = Zeroizing::new(plaintext);
+        let value = str::from_utf8(&plaintext).context("from_utf8")?;
+        Ok(Secret::new(value.to_owned()))
+    }
+
+    pub fn names(&self) -> impl Future<Output = Result<Box<[String]>, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(|transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let names = transaction
+                    .prepare("SELECT `name` FROM `secrets` ORDER BY `name`")
+                    .context("prepare")?
+                    .query_map([], |row| row.get::<_, String>(0))
+                    .context("query_map")?
+                    .collect::<rusqlite::Result<Box<[_]>>>()
+                    .context("collect")?;
+                Ok(names)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    // error if secret is missing
+    pub fn get(
+        &self,
+        reference: &SecretReference,
+    ) -> impl Future<Output = Result<Secret, Error>> + Send + 'static {
+        let cipher = self.cipher.clone();
+        let name = reference.0.clone();
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let (nonce, ciphertext) = transaction
+                    .query_row(
+                        "SELECT `nonce`, `ciphertext` FROM `secrets` WHERE `name` = ?",
+                        [&name],
+                        |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?)),
+                    )
+                    .optional()
+                    .context("query_row")?
+                    .ok_or_else(|| anyhow!("secret {} not found", name))?;
+                let secret =
+                    Self::decrypt(&cipher, &name, &nonce, &ciphertext).context("decrypt")?;
+                Ok(secret)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    pub fn set(
+        &self,
+        name: String,
+        value: Secret,
+    ) -> Result<impl Future<Output = Result<(), Error>> + Send + 'static, Error> {
+        name_validate(&name).context("name_validate")?;
+        let (nonce, ciphertext) = Self::encrypt(&self.cipher, &name, &value).context("encrypt")?;
+        drop(value);
+
+        let persisted = self
+            .sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO `secrets` (`name`, `nonce`, `ciphertext`) VALUES (?, ?, ?)",
+                        rusqlite::params![name, nonce, ciphertext],
+                    )
+                    .context("execute")?;
+                Ok(())
+            })
+            .map(|result| result.context("transaction").and_then(|result| result));
+        Ok(persisted)
+    }
+
+    // resolves to false if secret did not exist
+    pub fn remove(
+        &self,
+        name: String,
+    ) -> impl Future<Output = Result<bool, Error>> + Send + 'static {
+        self.sqlite
+            .transaction(move |transaction| -> Result<_, Error> {
+                Self::sql_initialize(transaction).context("sql_initialize")?;
+                let removed = transaction
+                    .execute("DELETE FROM `secrets` WHERE `name` = ?", [&name])
+                    .context("execute")?;
+                Ok(removed > 0)
+            })
+            .map(|result| result.context("transaction").and_then(|result| result))
+    }
+
+    fn sql_initialize(transaction: &rusqlite::Transaction) -> Result<(), Error> {
+        // creates or migrates the tables
+        sqlite_migrations::execute(&MIGRATIONS_RESOLVER, transaction).context("execute")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct SecretSetRequest {
+    value: String,
+}
+
+// values are write only, they are never returned over web
+impl<'f> uri_cursor::Handler for Secrets<'f> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let names = self.names();
+                    async {
+                        match names.await {
+                            Ok(names) => web::Response::ok_json(names),
+                            Err(error) => {
+                                log::error!("secrets: names: {:?}", error);
+                                web::Response::error_500()
+                            }
+                        }
+                    }
+                    .boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            uri_cursor::UriCursor::Next(name, uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::PUT => {
+                        let value = match request.body_parse_json::<SecretSetRequest>() {
+                            Ok(secret_set_request) => Secret::new(secret_set_request.value),
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }
+                                    .boxed();
+                            }
+                        };
+
+                        match self.set((*name).to_owned(), value) {
+                            Ok(persisted) => async {
+                                match persisted.await {
+                                    Ok(()) => web::Response::ok_empty(),
+                                    Err(error) => {
+                                        log::error!("secrets: set: {:?}", error);
+                                        web::Response::error_500()
+                                    }
+                                }
+                            }
+                            .boxed(),
+                            Err(error) => {
+                                async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        }
+                    }
+                    http::Method::DELETE => {
+                        let removed = self.remove((*name).to_owned());
+                        async {
+                            match removed.await {
+                                Ok(true) => web::Response::ok_empty(),
+                                Ok(false) => web::Response::error_404(),
+                                Err(error) => {
+                                    log::error!("secrets: remove: {:?}", error);
+                                    web::Response::error_500()
+                                }
+                            }
+                        }
+                        .boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{name_validate, Secret, Secrets};
+    use chacha20poly1305::{aead::KeyInit, ChaCha20Poly1305, Key};
+
+    #[test]
+    fn encrypt_decrypt() {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&[7; 32]));
+
+        let (nonce, ciphertext) =
+            Secrets::encrypt(&cipher, "camera.garden", &Secret::new("hunter2".to_owned())).unwrap();
+        assert!(!ciphertext.windows(7).any(|window| window == b"hunter2"));
+
+        let secret = Secrets::decrypt(&cipher, "camera.garden", &nonce, &ciphertext).unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(format!("{:?}", secret), "Secret(..)");
+
+        // bound to name and key
+        assert!(Secrets::decrypt(&cipher, "camera.gate", &nonce, &ciphertext).is_err());
+        let cipher_other = ChaCha20Poly1305::new(Key::from_slice(&[8; 32]));
+        assert!(Secrets::decrypt(&cipher_other, "camera.garden", &nonce, &ciphertext).is_err());
+
+        assert!(name_validate("camera.garden").is_ok());
+        assert!(name_validate("../camera").is_err());
+    }
+}
@@ -1,15 +1,230 @@
-use super::fs::Fs;
-use anyhow::{Context, Error};
+use super::{events, fs::Fs, metrics};
+use anyhow::{ensure, Context, Error};
 use crossbeam::channel;
 use futures::{
     channel::oneshot,
     future::{Future, FutureExt},
 };
-use rusqlite::{vtab, Connection, Transaction};
-use std::{any::type_name, fmt, mem::ManuallyDrop, path::PathBuf, thread};
+use maplit::btreemap;
+use rusqlite::{params_from_iter, types::Value, vtab, Connection, Transaction};
+use std::{
+    any::type_name,
+    fmt, iter,
+    mem::ManuallyDrop,
+    path::PathBuf,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
 
 type Operation = Box<dyn FnOnce(&mut Connection) + Send + 'static>;
 
+// runs optimizer, releases free pages and verifies database integrity
+fn maintenance_execute(connection: &Connection) -> Result<(), Error> {
+    connection
+        .execute_batch("PRAGMA optimize;")
+        .context("optimize")?;
+    connection
+        .execute_batch("PRAGMA incremental_vacuum;")
+        .context("incremental_vacuum")?;
+
+    let integrity_check = connection
+        .prepare("PRAGMA integrity_check")
+        .context("prepare")?
+        .query_map([], |row| row.get::<_, String>(0))
+        .context("query_map")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("collect")?;
+    ensure!(
+        integrity_check == ["ok"],
+        "integrity check failed: {:?}",
+        integrity_check
+    );
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct InsertBatchLimits {
+    // maximum number of rows in single INSERT statement
+    pub rows: usize,
+    // statement is executed as soon as bound values exceed this size
+    pub bytes: usize,
+}
+impl Default for InsertBatchLimits {
+    fn default() -> Self {
+        Self {
+            rows: 256,
+            bytes: 64 * 1024,
+        }
+    }
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::Null => 1,
+        Value::Integer(_) | Value::Real(_) => 8,
+        Value::Text(value) => value.len(),
+        Value::Blob(value) => value.len(),
+    }
+}
+
+// splits batch of given number of rows into statements, full batches are
+// inserted as they are, smaller ones (flushed by bytes limit or last) are split
+// into powers of two, so number of distinct statements stays within
+// log2(rows_max) + 2 and they all fit in statement cache
+fn chunk_sizes(
+    mut rows: usize,
+    rows_max: usize,
+) -> impl Iterator<Item = usize> {
+    iter::from_fn(move || {
+        if rows == 0 {
+            return None;
+        }
+        let chunk_size = if rows >= rows_max {
+            rows_max
+        } else {
+            1 << rows.ilog2()
+        };
+        rows -= chunk_size;
+        Some(chunk_size)
+    })
+}
+
+// inserts rows using multi-row INSERT statements, C is number of columns
+// `insert` is the statement prefix, eg. "INSERT INTO `table` (`a`, `b`)"
+// statements are cached, see chunk_sizes for their shapes
+pub fn insert_batched<const C: usize>(
+    connection: &Connection,
+    insert: &str,
+    rows: impl IntoIterator<Item = [Value; C]>,
+    limits: InsertBatchLimits,
+) -> Result<usize, Error> {
+    // SQLITE_MAX_VARIABLE_NUMBER default since 3.32.0
+    const VARIABLES_MAX: usize = 32766;
+
+    let rows_max = limits.rows.min(VARIABLES_MAX / C).max(1);
+
+    let execute_chunk = |batch: &[[Value; C]]| -> Result<(), Error> {
+        let row = format!(
+            "({})",
+            iter::repeat_n("?", C).collect::<Vec<_>>().join(", ")
+        );
+        let sql = format!(
+            "{} VALUES {}",
+            insert,
+            iter::repeat_n(row.as_str(), batch.len())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        connection
+            .prepare_cached(&sql)
+            .context("prepare_cached")?
+            .execute(params_from_iter(batch.iter().flatten()))
+            .context("execute")?;
+
+        Ok(())
+    };
+    let execute = |batch: &[[Value; C]]| -> Result<(), Error> {
+        let mut offset = 0;
+        for chunk_size in chunk_sizes(batch.len(), rows_max) {
+            execute_chunk(&batch[offset..offset + chunk_size]).context("execute_chunk")?;
+            offset += chunk_size;
+        }
+        Ok(())
+    };
+
+    let mut count = 0;
+    let mut batch = Vec::<[Value; C]>::with_capacity(rows_max);
+    let mut batch_bytes = 0;
+    for row in rows {
+        batch_bytes += row.iter().map(value_size).sum::<usize>();
+        batch.push(row);
+
+        if batch.len() >= rows_max || batch_bytes >= limits.bytes {
+            execute(&batch).context("execute")?;
+            count += batch.len();
+            batch.clear();
+            batch_bytes = 0;
+        }
+    }
+    if !batch.is_empty() {
+        execute(&batch).context("execute")?;
+        count += batch.len();
+    }
+
+    Ok(count)
+}
+
+// location of database with given name
+pub fn sqlite_file(
+    fs: &Fs,
+    name: &str,
+) -> PathBuf {
+    assert!(
+        name.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_'),
+        "database name must be valid for fs path (lower text, digits, dot, underscore)"
+    );
+    fs.persistent_data_directory()
+        .join([name, ".sqlite"].concat())
+}
+
+// checks whether rarray() table valued function can be used on the connection
+pub fn rarray_available(connection: &Connection) -> bool {
+    connection
+        .prepare_cached("SELECT `value` FROM rarray(?)")
+        .is_ok()
+}
+
+// deletes rows of `table` with `column` equal to any of values
+// uses rarray() if available, chunked `IN (?, ?, ...)` otherwise
+pub fn delete_in(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+    values: &[Value],
+) -> Result<usize, Error> {
+    const CHUNK_SIZE: usize = 256;
+
+    if values.is_empty() {
+        return Ok(0);
+    }
+
+    if rarray_available(connection) {
+        let values = Rc::new(values.to_vec());
+        let count = connection
+            .prepare_cached(&format!(
+                "DELETE FROM `{}` WHERE `{}` IN rarray(?)",
+                table, column
+            ))
+            .context("prepare_cached")?
+            .execute([values])
+            .context("execute")?;
+        return Ok(count);
+    }
+
+    let mut count = 0;
+    for chunk in values.chunks(CHUNK_SIZE) {
+        let sql = format!(
+            "DELETE FROM `{}` WHERE `{}` IN ({})",
+            table,
+            column,
+            iter::repeat_n("?", chunk.len())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        count += connection
+            .prepare_cached(&sql)
+            .context("prepare_cached")?
+            .execute(params_from_iter(chunk.iter()))
+            .context("execute")?;
+    }
+
+    Ok(count)
+}
+
 #[derive(Debug)]
 pub struct SQLite<'f> {
     name: String,
@@ -23,14 +238,7 @@ impl<'f> SQLite<'f> {
         name: String,
         fs: &'f Fs,
     ) -> Self {
-        assert!(
-            name.chars()
-                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_'),
-            "database name must be valid for fs path (lower text, digits, dot, underscore)"
-        );
-        let sqlite_file = fs
-            .persistent_data_directory()
-            .join([name.as_str(), ".sqlite"].concat());
+        let sqlite_file = sqlite_file(fs, &name);
 
         let thread_name = format!("{}.sqlite", name);
 
@@ -39,7 +247,10 @@ impl<'f> SQLite<'f> {
 
         let sqlite_thread = thread::Builder::new()
             .name(thread_name)
-            .spawn(|| Self::thread_main(sqlite_file, operation_receiver))
+            .spawn({
+                let name = name.clone();
+                || Self::thread_main(name, sqlite_file, operation_receiver)
+            })
             .unwrap();
         let sqlite_thread = ManuallyDrop::new(sqlite_thread);
 
@@ -51,12 +262,20 @@ impl<'f> SQLite<'f> {
         }
     }
 
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+    const PREPARED_STATEMENT_CACHE_CAPACITY: usize = 64;
+    const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
     fn thread_main(
+        name: String,
         sqlite_file: PathBuf,
         operation_receiver: channel::Receiver<Operation>,
     ) -> Result<(), Error> {
         // initialization
         let mut connection = Connection::open(sqlite_file).context("open")?;
+        connection
+            .busy_timeout(Self::BUSY_TIMEOUT)
+            .context("busy_timeout")?;
         connection
             .pragma_update(None, "auto_vacuum", "INCREMENTAL")
             .context("auto_vacuum")?;
@@ -74,18 +293,47 @@ impl<'f> SQLite<'f> {
             .context("synchronous")?;
         // TODO: set locking_mode to EXCLUSIVE, as we are using single connection?
         // this won't allow to view the database while it's opened though
-        // TODO: auto_vacuum = INCREMENTAL does not actually vacuum anything
-        // expose .vacuum() method and add it on system start/stop or with some periodic
-        // stuff
-        // TODO: use pragma optimize before opening/closing the connection
-        vtab::array::load_module(&connection).context("vtab load_module")?;
-
-        // main loop
-        while let Ok(operation) = operation_receiver.recv() {
-            operation(&mut connection);
+        // users are expected to check rarray_available() and fall back if needed
+        if let Err(error) = vtab::array::load_module(&connection) {
+            log::warn!("{}: rarray not available: {:?}", name, error);
+        }
+        connection.set_prepared_statement_cache_capacity(Self::PREPARED_STATEMENT_CACHE_CAPACITY);
+
+        let maintenance_failures = metrics::registry().counter(
+            "sqlite_maintenance_failures_total",
+            "Failed periodic maintenance runs (optimize, vacuum, integrity check)",
+            btreemap! { "database".to_owned() => name.clone() },
+        );
+
+        // main loop, with periodic maintenance
+        let mut maintenance_next = Instant::now() + Self::MAINTENANCE_INTERVAL;
+        loop {
+            let timeout = maintenance_next.saturating_duration_since(Instant::now());
+            match operation_receiver.recv_timeout(timeout) {
+                Ok(operation) => operation(&mut connection),
+                Err(channel::RecvTimeoutError::Timeout) => {
+                    match maintenance_execute(&connection).context("maintenance_execute") {
+                        Ok(()) => {}
+                        Err(error) => {
+                            log::error!("{}: {:?}", name, error);
+                            maintenance_failures.increment();
+                            events::reporter().report(
+                                events::Severity::Error,
+                                format!("sqlite/{}", name),
+                                format!("maintenance failed: {:#}", error),
+                            );
+                        }
+                    }
+                    maintenance_next = Instant::now() + Self::MAINTENANCE_INTERVAL;
+                }
+                Err(channel::RecvTimeoutError::Disconnected) => break,
+            }
         }
 
         // finalization
+        connection
+            .execute_batch("PRAGMA optimize;")
+            .context("optimize")?;
         connection
             .close()
             .map_err(|(_, error)| error)
@@ -94,6 +342,27 @@ impl<'f> SQLite<'f> {
         Ok(())
     }
 
+    pub fn maintenance(&self) -> impl Future<Output = Result<(), Error>> {
+        self.query(maintenance_execute)
+    }
+
+    // creates consistent snapshot of the database, while it is still being used
+    pub fn backup(
+        &self,
+        target: PathBuf,
+    ) -> impl Future<Output = Result<(), Error>> {
+        self.query(move |connection| -> Result<(), Error> {
+            ensure!(!target.exists(), "backup target already exists");
+            let target = target.to_str().context("target")?.to_owned();
+
+            connection
+                .execute("VACUUM INTO ?", [target])
+                .context("execute")?;
+
+            Ok(())
+        })
+    }
+
     pub fn query<E, R>(
         &self,
         e: E,
@@ -150,3 +419,84 @@ impl<'f> Drop for SQLite<'f> {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_sizes, delete_in, insert_batched, rarray_available, InsertBatchLimits};
+    use rusqlite::{types::Value, Connection};
+    use std::collections::HashSet;
+
+    #[test]
+    fn chunk_sizes_1() {
+        assert_eq!(chunk_sizes(0, 256).count(), 0);
+        assert_eq!(chunk_sizes(256, 256).collect::<Vec<_>>(), [256]);
+        assert_eq!(chunk_sizes(200, 256).collect::<Vec<_>>(), [128, 64, 8]);
+        assert_eq!(chunk_sizes(7, 6).collect::<Vec<_>>(), [6, 1]);
+
+        let shapes = (1..=256)
+            .flat_map(|rows| chunk_sizes(rows, 256))
+            .collect::<HashSet<_>>();
+        assert_eq!(shapes.len(), 9);
+    }
+
+    #[test]
+    fn insert_batched_splits() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE `items` (`a` INTEGER, `b` TEXT);")
+            .unwrap();
+
+        let rows = (0..10).map(|index| [Value::Integer(index), Value::Text("x".repeat(10))]);
+        let limits = InsertBatchLimits {
+            rows: 4,
+            bytes: 1024,
+        };
+        let count =
+            insert_batched(&connection, "INSERT INTO `items` (`a`, `b`)", rows, limits).unwrap();
+        assert_eq!(count, 10);
+
+        // bytes limit hit every 2 rows
+        let rows = (10..15).map(|index| [Value::Integer(index), Value::Text("x".repeat(10))]);
+        let limits = InsertBatchLimits {
+            rows: 100,
+            bytes: 30,
+        };
+        let count =
+            insert_batched(&connection, "INSERT INTO `items` (`a`, `b`)", rows, limits).unwrap();
+        assert_eq!(count, 5);
+
+        let (count, sum) = connection
+            .query_row("SELECT COUNT(*), SUM(`a`) FROM `items`", [], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })
+            .unwrap();
+        assert_eq!(count, 15);
+        assert_eq!(sum, (0..15).sum::<i64>());
+    }
+
+    #[test]
+    fn delete_in_fallback() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE `items` (`a` INTEGER); INSERT INTO `items` VALUES (1), (2), (3);",
+            )
+            .unwrap();
+
+        // array module is not loaded
+        assert!(!rarray_available(&connection));
+        let count = delete_in(
+            &connection,
+            "items",
+            "a",
+            &[Value::Integer(1), Value::Integer(3), Value::Integer(4)],
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+
+        rusqlite::vtab::array::load_module(&connection).unwrap();
+        assert!(rarray_available(&connection));
+        let count = delete_in(&connection, "items", "a", &[Value::Integer(2)]).unwrap();
+        assert_eq!(count, 1);
+    }
+}
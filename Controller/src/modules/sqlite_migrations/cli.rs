@@ -0,0 +1,86 @@
+use super::{apply, plan, Resolver, Version};
+use crate::modules::{fs::Fs, sqlite::sqlite_file};
+use anyhow::{ensure, Context, Error};
+
+// to be flattened into application command line arguments, so migrations can
+// be run explicitly before startup, instead of implicitly by database users
+#[derive(clap::Args, Clone, Debug)]
+pub struct Arguments {
+    /// Apply migrations of registered databases and exit
+    #[arg(long)]
+    pub migrate: bool,
+    /// Print migrations path and SQL without applying it
+    #[arg(long, requires = "migrate")]
+    pub migrate_dry_run: bool,
+    /// Limit migrations to single database
+    #[arg(long, requires = "migrate")]
+    pub migrate_database: Option<String>,
+    /// Target version instead of the latest one, allows downgrades
+    #[arg(long, requires = "migrate_database")]
+    pub migrate_target: Option<Version>,
+}
+
+pub struct Database<'r> {
+    // same as passed to SQLite::new()
+    pub name: String,
+    pub resolver: &'r dyn Resolver,
+}
+
+// returns true if application should exit after handling arguments
+pub fn run(
+    arguments: &Arguments,
+    fs: &Fs,
+    databases: &[Database],
+) -> Result<bool, Error> {
+    if !arguments.migrate {
+        return Ok(false);
+    }
+
+    let databases = databases
+        .iter()
+        .filter(|database| match &arguments.migrate_database {
+            Some(name) => database.name == *name,
+            None => true,
+        })
+        .collect::<Box<[_]>>();
+    ensure!(!databases.is_empty(), "no matching databases");
+
+    for database in databases.iter() {
+        database_run(arguments, fs, database)
+            .with_context(|| format!("database {}", database.name))?;
+    }
+
+    Ok(true)
+}
+fn database_run(
+    arguments: &Arguments,
+    fs: &Fs,
+    database: &Database,
+) -> Result<(), Error> {
+    let sqlite_file = sqlite_file(fs, &database.name);
+    if !sqlite_file.exists() {
+        println!("-- {}: not created yet", database.name);
+        return Ok(());
+    }
+
+    let mut connection = rusqlite::Connection::open(sqlite_file).context("open")?;
+    connection
+        .pragma_update(None, "foreign_keys", true)
+        .context("foreign_keys")?;
+
+    let transaction = connection.transaction().context("transaction")?;
+
+    let plan = plan(database.resolver, &transaction, arguments.migrate_target).context("plan")?;
+    println!("-- {}", database.name);
+    print!("{}", plan);
+
+    if arguments.migrate_dry_run {
+        // dropping transaction rolls it back
+        return Ok(());
+    }
+
+    apply(&plan, &transaction).context("apply")?;
+    transaction.commit().context("commit")?;
+
+    Ok(())
+}
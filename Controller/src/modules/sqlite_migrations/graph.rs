@@ -1,8 +1,12 @@
-use super::{Migration, Resolver, Version};
+use super::{Migration, Resolver, Step, Version};
 use itertools::Itertools;
 use phf::Map;
+use std::collections::{HashMap, VecDeque};
 
-pub type Graph = Map<Version, Map<Version, Option<Migration>>>; // {target => {source => migration}}
+// {target => {source => migration}}
+// edges with target < source are downgrades, used only when explicitly
+// requested
+pub type Graph = Map<Version, Map<Version, Option<Migration>>>;
 
 pub struct GraphResolver<'g>(pub &'g Graph);
 impl<'g> Resolver for GraphResolver<'g> {
@@ -12,31 +16,40 @@ impl<'g> Resolver for GraphResolver<'g> {
     ) -> (Version, Option<Box<[Migration]>>) {
         resolve(self.0, current)
     }
+
+    fn latest(&self) -> Version {
+        latest(self.0)
+    }
+    fn resolve_path(
+        &self,
+        source: Version,
+        target: Version,
+    ) -> Option<Box<[Step]>> {
+        resolve_path(self.0, source, target)
+    }
 }
 
-pub fn resolve(
-    graph: &Graph,
-    source: Version,
-) -> (Version, Option<Box<[Migration]>>) {
-    // validate graph. errors in graph are treated as programming error and cannot
-    // be handled
+fn validate(graph: &Graph) {
+    // errors in graph are treated as programming error and cannot be handled
     graph.into_iter().for_each(|(target, sources)| {
         sources.into_iter().for_each(|(source, _)| {
-            // only forward migrations are supported for now
-            // this also implies that target > 0 and that graph contains no cycles and is
-            // monotonic
-            assert!(
-                target > source,
-                "unsupported negative migration found in graph"
-            );
+            assert!(target != source, "self migration found in graph");
         });
     });
+}
+
+pub fn latest(graph: &Graph) -> Version {
+    graph.keys().max().copied().unwrap_or(0)
+}
+
+pub fn resolve(
+    graph: &Graph,
+    source: Version,
+) -> (Version, Option<Box<[Migration]>>) {
+    validate(graph);
 
     // calculate target version as maximum key
-    let target = match graph.keys().max() {
-        Some(target_version) => *target_version,
-        None => 0,
-    };
+    let target = latest(graph);
 
     // only forward migrations are supported
     if target < source {
@@ -82,13 +95,72 @@ fn resolve_step(
         .keys()
         .copied() // all sources for current node
         .sorted() // since the graph is monotonic and we are looking in descending order (search >= current), we order possible nodes lowest to highest to make as low number of steps as possible
+        .filter(|source| *source < current) // downgrade edges are not used here
         .filter(|source| *source >= search) // we filter out impossible candidates (non-monotonic)
         .find_map(|source| resolve_step(graph, search, source, path.clone()))
 }
 
+// shortest path from source to target, using only upgrade edges when
+// target > source and only downgrade edges when target < source
+pub fn resolve_path(
+    graph: &Graph,
+    source: Version,
+    target: Version,
+) -> Option<Box<[Step]>> {
+    validate(graph);
+
+    let upgrade = target >= source;
+    let between = |version: Version| -> bool {
+        if upgrade {
+            version <= target
+        } else {
+            version >= target
+        }
+    };
+
+    // breadth first search, {version => previous version on the path}
+    let mut previous = HashMap::<Version, Version>::new();
+    let mut queue = VecDeque::<Version>::from([source]);
+    while let Some(current) = queue.pop_front() {
+        if current == target {
+            break;
+        }
+
+        for next in graph.keys().copied().sorted() {
+            if (next > current) != upgrade || !between(next) {
+                continue;
+            }
+            if next == source || previous.contains_key(&next) {
+                continue;
+            }
+            if graph.get(&next).unwrap().contains_key(&current) {
+                previous.insert(next, current);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    // recreate path, target -> source
+    let mut steps = Vec::<Step>::new();
+    let mut current = target;
+    while current != source {
+        let step_source = *previous.get(&current)?;
+        let migration = *graph.get(&current).unwrap().get(&step_source).unwrap();
+        steps.push(Step {
+            source: step_source,
+            target: current,
+            migration,
+        });
+        current = step_source;
+    }
+    steps.reverse();
+
+    Some(steps.into_boxed_slice())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{super::Step, *};
     use phf::phf_map;
 
     #[test]
@@ -194,6 +266,68 @@ mod tests {
         assert_eq!(migrations, Some(vec!["0to1", "2to4"].into_boxed_slice()));
     }
     #[test]
+    fn resolve_path_downgrade() {
+        let graph: Graph = phf_map! {
+            0u32 => phf_map!{
+                1u32 => Some("1to0"),
+            },
+            1u32 => phf_map!{
+                0u32 => Some("0to1"),
+                2u32 => None,
+            },
+            2u32 => phf_map!{
+                1u32 => Some("1to2"),
+                3u32 => Some("3to2"),
+            },
+            3u32 => phf_map!{
+                2u32 => Some("2to3"),
+            },
+        };
+
+        // downgrade edges are ignored when upgrading
+        let (target, migrations) = resolve(&graph, 0);
+        assert_eq!(target, 3);
+        assert_eq!(
+            migrations,
+            Some(vec!["0to1", "1to2", "2to3"].into_boxed_slice())
+        );
+
+        let steps = resolve_path(&graph, 3, 0).unwrap();
+        assert_eq!(
+            &*steps,
+            [
+                Step {
+                    source: 3,
+                    target: 2,
+                    migration: Some("3to2"),
+                },
+                Step {
+                    source: 2,
+                    target: 1,
+                    migration: None,
+                },
+                Step {
+                    source: 1,
+                    target: 0,
+                    migration: Some("1to0"),
+                },
+            ]
+        );
+
+        assert_eq!(
+            resolve_path(&graph, 1, 1),
+            Some(Vec::new().into_boxed_slice())
+        );
+
+        // no downgrade edge from 4
+        let graph: Graph = phf_map! {
+            4u32 => phf_map!{
+                3u32 => Some("3to4"),
+            },
+        };
+        assert_eq!(resolve_path(&graph, 4, 3), None);
+    }
+    #[test]
     fn resolve_backwards() {
         let graph: Graph = phf_map! {
             100u32 => phf_map! {},
@@ -1,6 +1,8 @@
+pub mod cli;
 pub mod graph;
 
-use anyhow::{bail, Context, Error};
+use anyhow::{bail, ensure, Context, Error};
+use std::fmt;
 
 pub type Version = u32;
 pub type Migration = &'static str;
@@ -10,39 +12,127 @@ pub trait Resolver {
         &self,
         current: Version,
     ) -> (Version, Option<Box<[Migration]>>); // (target, migrations)
+
+    fn latest(&self) -> Version;
+    // steps leading from source to target, downgrade if target < source
+    fn resolve_path(
+        &self,
+        source: Version,
+        target: Version,
+    ) -> Option<Box<[Step]>>;
 }
 
-pub fn execute(
-    resolver: &impl Resolver,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Step {
+    pub source: Version,
+    pub target: Version,
+    pub migration: Option<Migration>, // None for no-op steps
+}
+
+// migrations that would bring the database to target version
+// created without modifying the database, so it can be used as a dry run
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Plan {
+    pub current: Version,
+    pub target: Version,
+    pub steps: Box<[Step]>,
+}
+impl fmt::Display for Plan {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        writeln!(f, "-- version {} -> {}", self.current, self.target)?;
+        for step in self.steps.iter() {
+            match step.migration {
+                Some(migration) => {
+                    writeln!(f, "-- step {} -> {}", step.source, step.target)?;
+                    writeln!(f, "{}", migration.trim_end())?;
+                }
+                None => {
+                    writeln!(f, "-- step {} -> {} (no-op)", step.source, step.target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// when target is not given, latest version is used
+// downgrades are performed only when target is given explicitly
+pub fn plan(
+    resolver: &dyn Resolver,
     transaction: &rusqlite::Transaction,
-) -> Result<(), Error> {
+    target: Option<Version>,
+) -> Result<Plan, Error> {
     // determine current version
     let current = sqlite_version_get(transaction).context("sqlite_version_get")?;
 
-    // obtain list of migrations
-    let (target, migrations) = resolver.resolve(current);
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let latest = resolver.latest();
+            ensure!(
+                latest >= current,
+                "database version {current} is newer than latest known version {latest}"
+            );
+            latest
+        }
+    };
 
     // prepare migrations path
-    let migrations = match migrations {
-        Some(migrations) => migrations,
+    let steps = match resolver.resolve_path(current, target) {
+        Some(steps) => steps,
         None => bail!("unable to find migrations path from version {current} to {target}"),
     };
 
+    Ok(Plan {
+        current,
+        target,
+        steps,
+    })
+}
+pub fn apply(
+    plan: &Plan,
+    transaction: &rusqlite::Transaction,
+) -> Result<(), Error> {
+    let current = sqlite_version_get(transaction).context("sqlite_version_get")?;
+    ensure!(
+        current == plan.current,
+        "plan was created for version {}, database is at {}",
+        plan.current,
+        current
+    );
+
     // apply migrations
-    for migration in migrations.into_vec().into_iter() {
+    for step in plan.steps.iter() {
+        let migration = match step.migration {
+            Some(migration) => migration,
+            None => continue,
+        };
         transaction
             .execute_batch(migration)
-            .context("execute_batch")?;
+            .with_context(|| format!("execute_batch {} -> {}", step.source, step.target))?;
     }
 
     // set version on database
-    if current != target {
-        sqlite_version_set(target, transaction).context("sqlite_version_set")?;
+    if plan.current != plan.target {
+        sqlite_version_set(plan.target, transaction).context("sqlite_version_set")?;
     }
 
     Ok(())
 }
 
+pub fn execute(
+    resolver: &impl Resolver,
+    transaction: &rusqlite::Transaction,
+) -> Result<(), Error> {
+    let plan = plan(resolver, transaction, None).context("plan")?;
+    apply(&plan, transaction).context("apply")?;
+
+    Ok(())
+}
+
 const PRAGMA_VERSION: &str = "user_version";
 
 fn sqlite_version_get(transaction: &rusqlite::Transaction) -> Result<Version, Error> {
@@ -129,4 +219,46 @@ mod tests {
             maplit::hashset! {"t1".to_owned(), "t2".to_owned()}
         );
     }
+    #[test]
+    fn plan_downgrade() {
+        let graph: Graph = phf_map! {
+            0u32 => phf_map! {
+                1u32 => Some("DROP TABLE t1;"),
+            },
+            1u32 => phf_map! {
+                0u32 => Some("CREATE TABLE t1 (a INTEGER);"),
+            },
+        };
+
+        let mut connection = rusqlite::Connection::open_in_memory().unwrap();
+        let transaction = connection.transaction().unwrap();
+        execute(&GraphResolver(&graph), &transaction).unwrap();
+        transaction.commit().unwrap();
+
+        // dry run
+        let transaction = connection.transaction().unwrap();
+        let plan_ = plan(&GraphResolver(&graph), &transaction, Some(0)).unwrap();
+        assert_eq!(
+            plan_.to_string(),
+            "-- version 1 -> 0\n-- step 1 -> 0\nDROP TABLE t1;\n"
+        );
+        drop(transaction);
+        assert_eq!(
+            connection
+                .query_row("SELECT COUNT(*) FROM t1", (), |row| row.get::<_, u32>(0))
+                .unwrap(),
+            0
+        );
+
+        let transaction = connection.transaction().unwrap();
+        apply(&plan_, &transaction).unwrap();
+        transaction.commit().unwrap();
+        assert!(connection
+            .query_row("SELECT COUNT(*) FROM t1", (), |_| Ok(()))
+            .is_err());
+
+        // plan made for different version
+        let transaction = connection.transaction().unwrap();
+        assert!(apply(&plan_, &transaction).is_err());
+    }
 }
@@ -1,14 +1,16 @@
 #![allow(clippy::type_complexity)]
 use super::{
+    latency::{Hop, Origin, Recorder, RouteLatency},
     signal::{
         Base, EventSourceRemoteBase, EventTargetRemoteBase, RemoteBase, RemoteBaseVariant,
         StateSourceRemoteBase, StateTargetRemoteBase,
     },
+    types::AnyValue,
     waker::{SourcesChangedWakerRemote, TargetsChangedWakerRemote},
     DeviceBaseRef, IdentifierBaseWrapper,
 };
 use crate::{
-    devices::Id as DeviceId,
+    devices::{maintenance::Maintenance, Id as DeviceId},
     util::{
         async_ext::{
             ready_chunks_dynamic::ReadyChunksDynamicExt,
@@ -22,9 +24,19 @@ use crate::{
 use anyhow::{anyhow, bail, ensure, Context, Error};
 use async_trait::async_trait;
 use by_address::ByAddress;
-use futures::stream::StreamExt;
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{self, FutureExt},
+    select,
+    stream::StreamExt,
+};
 use ouroboros::self_referencing;
-use std::collections::{HashMap, HashSet};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct DeviceIdSignalIdentifierBaseWrapper {
@@ -42,6 +54,23 @@ impl DeviceIdSignalIdentifierBaseWrapper {
             signal_identifier_base_wrapper,
         }
     }
+
+    pub fn device_id(&self) -> DeviceId {
+        self.device_id
+    }
+    pub fn signal_identifier_base_wrapper(&self) -> &IdentifierBaseWrapper {
+        &self.signal_identifier_base_wrapper
+    }
+
+    pub fn with_device_id(
+        &self,
+        device_id: DeviceId,
+    ) -> Self {
+        Self {
+            device_id,
+            signal_identifier_base_wrapper: self.signal_identifier_base_wrapper.clone(),
+        }
+    }
 }
 
 pub type ConnectionRequested = (
@@ -49,6 +78,86 @@ pub type ConnectionRequested = (
     DeviceIdSignalIdentifierBaseWrapper,
 );
 
+// device target signal, given by identifier name (see
+// IdentifierBaseWrapper::name())
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct TargetAddress {
+    pub device_id: DeviceId,
+    pub signal: String,
+}
+
+// value written directly to device target signal, bypassing its source
+// for state targets null value is written as None
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetWrite {
+    #[serde(flatten)]
+    pub target_address: TargetAddress,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug)]
+struct ShadowRecord {
+    value: serde_json::Value,
+    count: usize,
+    updated: DateTime<Utc>,
+}
+
+// connection from device running in shadow mode
+// values are recorded for comparison instead of being forwarded to target
+#[derive(Debug)]
+struct ShadowConnection<'d> {
+    connection_requested: ConnectionRequested,
+    state_target_remote_base: Option<&'d dyn StateTargetRemoteBase>,
+    record: Mutex<Option<ShadowRecord>>,
+}
+impl<'d> ShadowConnection<'d> {
+    fn new(
+        connection_requested: ConnectionRequested,
+        state_target_remote_base: Option<&'d dyn StateTargetRemoteBase>,
+    ) -> Self {
+        Self {
+            connection_requested,
+            state_target_remote_base,
+            record: Mutex::new(None),
+        }
+    }
+
+    fn record(
+        &self,
+        value: serde_json::Value,
+        count: usize,
+    ) {
+        let mut record = self.record.lock();
+        let count = record.as_ref().map_or(0, |record| record.count) + count;
+        *record = Some(ShadowRecord {
+            value,
+            count,
+            updated: Utc::now(),
+        });
+    }
+}
+
+// shadow connection as seen by user
+// for state connections live value of the target is included, so it can be
+// compared with value that would be set by shadow device
+#[derive(Debug, Serialize)]
+pub struct ShadowComparison {
+    pub source_device_id: DeviceId,
+    pub source_signal: String,
+    pub target_device_id: DeviceId,
+    pub target_signal: String,
+
+    // last recorded value (last event for events), None if nothing was recorded
+    pub shadow: Option<serde_json::Value>,
+    // number of recorded values (or events)
+    pub count: usize,
+    pub updated: Option<DateTime<Utc>>,
+
+    // state connections only
+    pub live: Option<serde_json::Value>,
+    pub matches: Option<bool>,
+}
+
 #[self_referencing]
 #[derive(Debug)]
 struct ExchangerInner<'d> {
@@ -101,22 +210,415 @@ struct ExchangerInnerChild<'p, 'd> {
         ByAddress<&'d dyn StateTargetRemoteBase>,     // signal
         ByAddress<&'p TargetsChangedWakerRemote<'d>>, // waker
     >,
+    shadow_connections_state:
+        HashMap<ByAddress<&'d dyn StateSourceRemoteBase>, Vec<ShadowConnection<'d>>>,
+    shadow_connections_event:
+        HashMap<ByAddress<&'d dyn EventSourceRemoteBase>, Vec<ShadowConnection<'d>>>,
+    state_targets_maintenance: HashSet<ByAddress<&'d dyn StateTargetRemoteBase>>,
+    sources_changed_waker_remotes_device_ids:
+        HashMap<ByAddress<&'p SourcesChangedWakerRemote<'d>>, DeviceId>,
+    targets_changed_waker_remotes_device_ids:
+        HashMap<ByAddress<&'p TargetsChangedWakerRemote<'d>>, DeviceId>,
 }
 
 #[derive(Debug)]
 pub struct Exchanger<'d> {
     inner: ExchangerInner<'d>,
+    maintenance: Option<&'d Maintenance>,
+    // (origin device, target device), see latency module
+    latency_recorders: Mutex<HashMap<(DeviceId, DeviceId), Recorder>>,
 }
 impl<'d> Exchanger<'d> {
+    // sources of devices from shadow_device_ids are not forwarded to targets,
+    // see shadow_compare()
+    // state targets of devices given with maintenance are not updated while it
+    // is active, they receive current values after it is released
     pub fn new(
         devices: &HashMap<DeviceId, DeviceBaseRef<'d>>,
         connections_requested: &[ConnectionRequested],
+        shadow_device_ids: &HashSet<DeviceId>,
+        maintenance: Option<(&'d Maintenance, &HashSet<DeviceId>)>,
     ) -> Result<Self, Error> {
-        let inner = new_inner(devices, connections_requested).context("new_inner")?;
-        Ok(Self { inner })
+        let maintenance_device_ids = maintenance
+            .map(|(_, maintenance_device_ids)| maintenance_device_ids.clone())
+            .unwrap_or_default();
+        let inner = new_inner(
+            devices,
+            connections_requested,
+            shadow_device_ids,
+            &maintenance_device_ids,
+        )
+        .context("new_inner")?;
+        let maintenance = maintenance.map(|(maintenance, _)| maintenance);
+
+        Ok(Self {
+            inner,
+            maintenance,
+            latency_recorders: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn maintenance_active(&self) -> bool {
+        self.maintenance
+            .is_some_and(|maintenance| maintenance.active())
+    }
+    fn state_target_frozen(
+        &self,
+        maintenance_active: bool,
+        state_target_remote_base: &ByAddress<&'d dyn StateTargetRemoteBase>,
+    ) -> bool {
+        maintenance_active
+            && self
+                .inner
+                .borrow_child()
+                .state_targets_maintenance
+                .contains(state_target_remote_base)
+    }
+
+    fn target_remote_base(
+        &self,
+        target_address: &TargetAddress,
+    ) -> Result<(&'d dyn RemoteBase, Option<&TargetsChangedWakerRemote<'d>>), Error> {
+        let (device, targets_changed_waker_remote, _, remote_bases_by_identifier) = self
+            .inner
+            .borrow_parent()
+            .device_contexts
+            .get(&target_address.device_id)
+            .ok_or_else(|| anyhow!("device #{} not found", target_address.device_id))?;
+
+        let remote_base = remote_bases_by_identifier
+            .iter()
+            .find(|(signal_identifier, _)| signal_identifier.name() == target_address.signal)
+            .map(|(_, remote_base)| *remote_base)
+            .ok_or_else(|| {
+                anyhow!(
+                    "signal {} not found on device #{} ({})",
+                    target_address.signal,
+                    target_address.device_id,
+                    device.type_name(),
+                )
+            })?;
+
+        Ok((remote_base, targets_changed_waker_remote.as_ref()))
+    }
+
+    pub fn target_exists(
+        &self,
+        target_address: &TargetAddress,
+    ) -> bool {
+        self.target_remote_base(target_address).is_ok()
+    }
+
+    // current values of state targets, in form accepted by targets_write()
+    pub fn targets_read(
+        &self,
+        target_addresses: &[TargetAddress],
+    ) -> Result<Box<[TargetWrite]>, Error> {
+        let target_writes = target_addresses
+            .iter()
+            .map(|target_address| -> Result<_, Error> {
+                let (remote_base, _) = self
+                    .target_remote_base(target_address)
+                    .context("target_remote_base")?;
+
+                let value = match remote_base.as_remote_base_variant() {
+                    RemoteBaseVariant::StateTarget(state_target_remote_base) => {
+                        match state_target_remote_base.peek_last() {
+                            Some(value) => value
+                                .to_json()
+                                .with_context(|| format!("value of {}", target_address.signal))?,
+                            None => serde_json::Value::Null,
+                        }
+                    }
+                    RemoteBaseVariant::StateSource(_)
+                    | RemoteBaseVariant::EventSource(_)
+                    | RemoteBaseVariant::EventTarget(_) => {
+                        bail!("signal {} is not a state target", target_address.signal)
+                    }
+                };
+
+                Ok(TargetWrite {
+                    target_address: target_address.clone(),
+                    value,
+                })
+            })
+            .collect::<Result<Box<[_]>, Error>>()?;
+
+        Ok(target_writes)
+    }
+
+    // all writes are validated before any of them is applied and targets are
+    // woken after all values are set, so devices receive them as single batch
+    // written state targets keep the value until their source changes
+    pub fn targets_write(
+        &self,
+        target_writes: Vec<TargetWrite>,
+    ) -> Result<(), Error> {
+        enum Write<'d> {
+            State(&'d dyn StateTargetRemoteBase, Option<AnyValue>),
+            Event(&'d dyn EventTargetRemoteBase, AnyValue),
+        }
+
+        let writes = target_writes
+            .into_iter()
+            .map(|target_write| -> Result<_, Error> {
+                let TargetWrite {
+                    target_address,
+                    value,
+                } = target_write;
+
+                let (remote_base, targets_changed_waker_remote) = self
+                    .target_remote_base(&target_address)
+                    .context("target_remote_base")?;
+
+                let write = match remote_base.as_remote_base_variant() {
+                    RemoteBaseVariant::StateTarget(state_target_remote_base) => {
+                        ensure!(
+                            !self.state_target_frozen(
+                                self.maintenance_active(),
+                                &ByAddress(state_target_remote_base)
+                            ),
+                            "device #{} is frozen by maintenance mode",
+                            target_address.device_id
+                        );
+
+                        let value = if value.is_null() {
+                            None
+                        } else {
+                            Some(
+                                AnyValue::from_json(remote_base.type_id(), value).with_context(
+                                    || format!("value of {}", target_address.signal),
+                                )?,
+                            )
+                        };
+                        Write::State(state_target_remote_base, value)
+                    }
+                    RemoteBaseVariant::EventTarget(event_target_remote_base) => {
+                        let value = AnyValue::from_json(remote_base.type_id(), value)
+                            .with_context(|| format!("value of {}", target_address.signal))?;
+                        Write::Event(event_target_remote_base, value)
+                    }
+                    RemoteBaseVariant::StateSource(_) | RemoteBaseVariant::EventSource(_) => {
+                        bail!("signal {} is not a target", target_address.signal)
+                    }
+                };
+
+                // device with targets has waker, this is checked in new_inner_child
+                let targets_changed_waker_remote = targets_changed_waker_remote.unwrap();
+
+                Ok((write, targets_changed_waker_remote))
+            })
+            .collect::<Result<Box<[_]>, Error>>()?;
+
+        let mut targets_changed_waker_remotes =
+            HashSet::<ByAddress<&TargetsChangedWakerRemote<'d>>>::new();
+
+        for (write, targets_changed_waker_remote) in writes.into_vec() {
+            let changed = match write {
+                Write::State(state_target_remote_base, value) => {
+                    state_target_remote_base.set(&[value])
+                }
+                Write::Event(event_target_remote_base, value) => {
+                    event_target_remote_base.push(&[value])
+                }
+            };
+            if changed {
+                targets_changed_waker_remotes.insert(ByAddress(targets_changed_waker_remote));
+            }
+        }
+
+        for targets_changed_waker_remote in targets_changed_waker_remotes {
+            targets_changed_waker_remote.wake();
+        }
+
+        Ok(())
+    }
+
+    fn shadow_state_record(
+        &self,
+        state_source_remote_base: &ByAddress<&'d dyn StateSourceRemoteBase>,
+        values: &[Option<AnyValue>],
+    ) {
+        let shadow_connections = match self
+            .inner
+            .borrow_child()
+            .shadow_connections_state
+            .get(state_source_remote_base)
+        {
+            Some(shadow_connections) => shadow_connections,
+            None => return,
+        };
+
+        let value = match values.last().unwrap() {
+            Some(value) => match value.to_json() {
+                Ok(value) => value,
+                Err(error) => {
+                    log::warn!("shadow value: {:?}", error);
+                    return;
+                }
+            },
+            None => serde_json::Value::Null,
+        };
+        for shadow_connection in shadow_connections {
+            shadow_connection.record(value.clone(), values.len());
+        }
+    }
+    fn shadow_event_record(
+        &self,
+        event_source_remote_base: &ByAddress<&'d dyn EventSourceRemoteBase>,
+        values: &[AnyValue],
+    ) {
+        let shadow_connections = match self
+            .inner
+            .borrow_child()
+            .shadow_connections_event
+            .get(event_source_remote_base)
+        {
+            Some(shadow_connections) => shadow_connections,
+            None => return,
+        };
+
+        let value = match values.last().unwrap().to_json() {
+            Ok(value) => value,
+            Err(error) => {
+                log::warn!("shadow value: {:?}", error);
+                return;
+            }
+        };
+        for shadow_connection in shadow_connections {
+            shadow_connection.record(value.clone(), values.len());
+        }
+    }
+
+    pub fn shadow_compare(&self) -> Box<[ShadowComparison]> {
+        let inner_child = self.inner.borrow_child();
+
+        let mut shadow_comparisons =
+            inner_child
+                .shadow_connections_state
+                .values()
+                .chain(inner_child.shadow_connections_event.values())
+                .flatten()
+                .map(|shadow_connection| {
+                    let (source, target) = &shadow_connection.connection_requested;
+
+                    let (shadow, count, updated) = match &*shadow_connection.record.lock() {
+                        Some(record) => (
+                            Some(record.value.clone()),
+                            record.count,
+                            Some(record.updated),
+                        ),
+                        None => (None, 0, None),
+                    };
+
+                    let live = shadow_connection.state_target_remote_base.map(
+                        |state_target_remote_base| match state_target_remote_base.peek_last() {
+                            Some(value) => value.to_json().unwrap_or(serde_json::Value::Null),
+                            None => serde_json::Value::Null,
+                        },
+                    );
+                    let matches = live.as_ref().map(|live| shadow.as_ref() == Some(live));
+
+                    ShadowComparison {
+                        source_device_id: source.device_id,
+                        source_signal: source.signal_identifier_base_wrapper.name(),
+                        target_device_id: target.device_id,
+                        target_signal: target.signal_identifier_base_wrapper.name(),
+                        shadow,
+                        count,
+                        updated,
+                        live,
+                        matches,
+                    }
+                })
+                .collect::<Box<[_]>>();
+        shadow_comparisons.sort_by(|a, b| {
+            (
+                a.source_device_id,
+                &a.source_signal,
+                a.target_device_id,
+                &a.target_signal,
+            )
+                .cmp(&(
+                    b.source_device_id,
+                    &b.source_signal,
+                    b.target_device_id,
+                    &b.target_signal,
+                ))
+        });
+        shadow_comparisons
+    }
+
+    // passes origin to traced targets and records exchanger hop
+    fn latency_record(
+        &self,
+        origins: HashMap<ByAddress<&TargetsChangedWakerRemote<'d>>, Origin>,
+    ) {
+        let inner_child = self.inner.borrow_child();
+        let mut latency_recorders = self.latency_recorders.lock();
+
+        for (targets_changed_waker_remote, origin) in origins {
+            targets_changed_waker_remote.origin_merge(origin);
+
+            let origin_device_id = match origin.device_id {
+                Some(origin_device_id) => origin_device_id,
+                None => continue,
+            };
+            let target_device_id =
+                inner_child.targets_changed_waker_remotes_device_ids[&targets_changed_waker_remote];
+            latency_recorders
+                .entry((origin_device_id, target_device_id))
+                .or_insert_with(Recorder::new)
+                .record(origin.instant.elapsed());
+        }
+    }
+
+    pub fn latency(&self) -> Box<[RouteLatency]> {
+        let inner_child = self.inner.borrow_child();
+
+        let mut route_latencies = self
+            .latency_recorders
+            .lock()
+            .iter()
+            .filter_map(|((origin_device_id, target_device_id), recorder)| {
+                recorder.percentiles().map(|percentiles| RouteLatency {
+                    origin_device_id: *origin_device_id,
+                    target_device_id: *target_device_id,
+                    hop: Hop::Exchanger,
+                    percentiles,
+                })
+            })
+            .chain(
+                inner_child
+                    .targets_changed_waker_remotes_device_ids
+                    .iter()
+                    .flat_map(|(targets_changed_waker_remote, target_device_id)| {
+                        targets_changed_waker_remote
+                            .latency_percentiles()
+                            .into_vec()
+                            .into_iter()
+                            .map(|(origin_device_id, percentiles)| RouteLatency {
+                                origin_device_id,
+                                target_device_id: *target_device_id,
+                                hop: Hop::Device,
+                                percentiles,
+                            })
+                    }),
+            )
+            .collect::<Box<[_]>>();
+        route_latencies.sort_by_key(|route_latency| {
+            (
+                route_latency.origin_device_id,
+                route_latency.target_device_id,
+                route_latency.hop == Hop::Device,
+            )
+        });
+        route_latencies
     }
 
     async fn sources_to_targets_all_run(&self) {
+        let maintenance_active = self.maintenance_active();
+
         let mut targets_changed_waker_remotes =
             HashSet::<ByAddress<&TargetsChangedWakerRemote>>::new();
 
@@ -125,7 +627,9 @@ impl<'d> Exchanger<'d> {
         for (state_target_remote_base, targets_changed_waker_remote) in
             self.inner.borrow_child().state_targets_disconnected.iter()
         {
-            if state_target_remote_base.set(&values_state_disconnected) {
+            if !self.state_target_frozen(maintenance_active, state_target_remote_base)
+                && state_target_remote_base.set(&values_state_disconnected)
+            {
                 targets_changed_waker_remotes.insert(*targets_changed_waker_remote);
             }
         }
@@ -141,11 +645,14 @@ impl<'d> Exchanger<'d> {
                 if values.is_empty() {
                     values = vec![state_source_remote_base.peek_last()].into_boxed_slice();
                 }
+                self.shadow_state_record(state_source_remote_base, &values);
 
                 for (state_target_remote_base, targets_changed_waker_remote) in
                     connection_targets.iter()
                 {
-                    if state_target_remote_base.set(&values) {
+                    if !self.state_target_frozen(maintenance_active, state_target_remote_base)
+                        && state_target_remote_base.set(&values)
+                    {
                         targets_changed_waker_remotes.insert(*targets_changed_waker_remote);
                     }
                 }
@@ -157,6 +664,7 @@ impl<'d> Exchanger<'d> {
                 if values.is_empty() {
                     continue;
                 }
+                self.shadow_event_record(event_source_remote_base, &values);
 
                 for (event_target_remote_base, targets_changed_waker_remote) in
                     connection_targets.iter()
@@ -214,8 +722,13 @@ impl<'d> Exchanger<'d> {
                     .collect::<HashSet<_>>()
             })
             .for_each(async |sources_changed_waker_remotes| {
+                let maintenance_active = self.maintenance_active();
+
                 let mut targets_changed_waker_remotes =
                     HashSet::<ByAddress<&TargetsChangedWakerRemote<'d>>>::new();
+                // only when latency tracing is enabled
+                let mut origins =
+                    HashMap::<ByAddress<&TargetsChangedWakerRemote<'d>>, Origin>::new();
 
                 for sources_changed_waker_remote in sources_changed_waker_remotes {
                     let (connections_state, connections_event) = self
@@ -225,18 +738,46 @@ impl<'d> Exchanger<'d> {
                         .get(sources_changed_waker_remote)
                         .unwrap();
 
+                    let origin = sources_changed_waker_remote
+                        .origin_take()
+                        .map(|origin| Origin {
+                            device_id: origin.device_id.or_else(|| {
+                                self.inner
+                                    .borrow_child()
+                                    .sources_changed_waker_remotes_device_ids
+                                    .get(sources_changed_waker_remote)
+                                    .copied()
+                            }),
+                            ..origin
+                        });
+                    let mut targets_traced = |targets_changed_waker_remote| {
+                        if let Some(origin) = origin {
+                            let origin_target = origins
+                                .entry(targets_changed_waker_remote)
+                                .or_insert(origin);
+                            if origin.instant < origin_target.instant {
+                                *origin_target = origin;
+                            }
+                        }
+                    };
+
                     // state signals
                     for (state_source_remote_base, connection_targets) in connections_state.iter() {
                         let values = state_source_remote_base.take_pending();
                         if values.is_empty() {
                             continue;
                         }
+                        self.shadow_state_record(state_source_remote_base, &values);
 
                         for (state_target_remote_base, targets_changed_waker_remote) in
                             connection_targets.iter()
                         {
-                            if state_target_remote_base.set(&values) {
+                            if !self
+                                .state_target_frozen(maintenance_active, state_target_remote_base)
+                                && state_target_remote_base.set(&values)
+                            {
                                 targets_changed_waker_remotes.insert(*targets_changed_waker_remote);
+                                targets_traced(*targets_changed_waker_remote);
                             }
                         }
                     }
@@ -247,17 +788,23 @@ impl<'d> Exchanger<'d> {
                         if values.is_empty() {
                             continue;
                         }
+                        self.shadow_event_record(event_source_remote_base, &values);
 
                         for (event_target_remote_base, targets_changed_waker_remote) in
                             connection_targets.iter()
                         {
                             if event_target_remote_base.push(&values) {
                                 targets_changed_waker_remotes.insert(*targets_changed_waker_remote);
+                                targets_traced(*targets_changed_waker_remote);
                             }
                         }
                     }
                 }
 
+                if !origins.is_empty() {
+                    self.latency_record(origins);
+                }
+
                 for targets_changed_waker_remote in targets_changed_waker_remotes {
                     targets_changed_waker_remote.wake();
                 }
@@ -267,12 +814,35 @@ impl<'d> Exchanger<'d> {
         Exited
     }
 
+    // after maintenance is released, frozen targets are brought up to date
+    async fn maintenance_run(&self) -> ! {
+        let maintenance = match self.maintenance {
+            Some(maintenance) => maintenance,
+            None => future::pending().await,
+        };
+
+        let mut active_receiver = maintenance.active_receiver();
+        loop {
+            active_receiver.changed().await.unwrap();
+            if *active_receiver.borrow_and_update() {
+                log::warn!("maintenance mode active, hardware targets frozen");
+            } else {
+                log::info!("maintenance mode released");
+                self.sources_to_targets_all_run().await;
+            }
+        }
+    }
+
     async fn run(
         &self,
         exit_flag: async_flag::Receiver,
     ) -> Exited {
         self.sources_to_targets_all_run().await;
-        let _: Exited = self.sources_to_targets_wakers_run(exit_flag).await;
+
+        select! {
+            _ = self.sources_to_targets_wakers_run(exit_flag).fuse() => {},
+            _ = self.maintenance_run().fuse() => panic!("maintenance_run yielded"),
+        }
 
         Exited
     }
@@ -290,12 +860,19 @@ impl<'d> Runnable for Exchanger<'d> {
 fn new_inner<'d>(
     devices: &HashMap<DeviceId, DeviceBaseRef<'d>>,
     connections_requested: &[ConnectionRequested],
+    shadow_device_ids: &HashSet<DeviceId>,
+    maintenance_device_ids: &HashSet<DeviceId>,
 ) -> Result<ExchangerInner<'d>, Error> {
     let inner = ExchangerInner::try_new(
         new_inner_parent(devices).context("new_inner_parent")?,
         |parent| -> Result<_, Error> {
-            let child =
-                new_inner_child(parent, connections_requested).context("new_inner_child")?;
+            let child = new_inner_child(
+                parent,
+                connections_requested,
+                shadow_device_ids,
+                maintenance_device_ids,
+            )
+            .context("new_inner_child")?;
             Ok(child)
         },
     )
@@ -364,7 +941,12 @@ fn new_inner_parent<'d>(
 fn new_inner_child<'p, 'd>(
     parent: &'p ExchangerInnerParent<'d>,
     connections_requested: &[ConnectionRequested],
+    shadow_device_ids: &HashSet<DeviceId>,
+    maintenance_device_ids: &HashSet<DeviceId>,
 ) -> Result<ExchangerInnerChild<'p, 'd>, Error> {
+    // state targets frozen while maintenance mode is active
+    let mut state_targets_maintenance = HashSet::<ByAddress<&dyn StateTargetRemoteBase>>::new();
+
     // list of disconnected targets (with no source)
     // used to be set to None during initialization
     let mut state_targets_disconnected = HashMap::<
@@ -452,6 +1034,10 @@ fn new_inner_child<'p, 'd>(
 
             // prepare list of unused targets
             if let RemoteBaseVariant::StateTarget(state_target_remote_base) = remote_base_variant {
+                if maintenance_device_ids.contains(device_id) {
+                    state_targets_maintenance.insert(ByAddress(state_target_remote_base));
+                }
+
                 state_targets_disconnected.insert(
                     ByAddress(state_target_remote_base),
                     ByAddress(targets_changed_waker_remote.as_ref().unwrap()), // this is checked above
@@ -472,10 +1058,27 @@ fn new_inner_child<'p, 'd>(
         ByAddress<&dyn EventTargetRemoteBase>,
     )>::new();
 
+    // connections from shadow devices
+    // they don't count as target sources, so target can have its live source
+    // and any number of shadow ones
+    let mut shadow_connections_state =
+        HashMap::<ByAddress<&dyn StateSourceRemoteBase>, Vec<ShadowConnection<'d>>>::new();
+    let mut shadow_connections_event =
+        HashMap::<ByAddress<&dyn EventSourceRemoteBase>, Vec<ShadowConnection<'d>>>::new();
+
+    // signals metadata, for enumeration variants checks
+    let metadata_by_device_id = parent
+        .device_contexts
+        .iter()
+        .map(|(device_id, (device, _, _, _))| (*device_id, device.metadata()))
+        .collect::<HashMap<_, _>>();
+
     // connections processing loop
     for (source_device_id_signal_identifier_base, target_device_id_signal_identifier_base) in
         connections_requested
     {
+        let shadow = shadow_device_ids.contains(&source_device_id_signal_identifier_base.device_id);
+
         // source device and signal
         let (source_device, _, source_sources_changed_waker_remote, source_signals_by_identifier) =
             parent
@@ -540,6 +1143,36 @@ fn new_inner_child<'p, 'd>(
             target_remote_base_remote_base.type_name(),
         );
 
+        // enumerations, both sides must declare variants to be checked
+        let source_variants = metadata_by_device_id
+            .get(&source_device_id_signal_identifier_base.device_id)
+            .and_then(|metadata| {
+                metadata
+                    .get(&source_device_id_signal_identifier_base.signal_identifier_base_wrapper)
+            })
+            .and_then(|metadata| metadata.variants.as_deref());
+        let target_variants = metadata_by_device_id
+            .get(&target_device_id_signal_identifier_base.device_id)
+            .and_then(|metadata| {
+                metadata
+                    .get(&target_device_id_signal_identifier_base.signal_identifier_base_wrapper)
+            })
+            .and_then(|metadata| metadata.variants.as_deref());
+        if let (Some(source_variants), Some(target_variants)) = (source_variants, target_variants) {
+            let variants_unhandled = variants_unhandled(source_variants, target_variants);
+            ensure!(
+                variants_unhandled.is_empty(),
+                "source #{} ({}) :: {:?} -> target #{} ({}) :: {:?} variants not handled by target: {:?}",
+                &source_device_id_signal_identifier_base.device_id,
+                source_device.type_name(),
+                &source_device_id_signal_identifier_base.signal_identifier_base_wrapper,
+                &target_device_id_signal_identifier_base.device_id,
+                target_device.type_name(),
+                &target_device_id_signal_identifier_base.signal_identifier_base_wrapper,
+                variants_unhandled,
+            );
+        }
+
         match (
             source_signal_remote_base.as_remote_base_variant(),
             target_remote_base_remote_base.as_remote_base_variant(),
@@ -548,6 +1181,20 @@ fn new_inner_child<'p, 'd>(
                 RemoteBaseVariant::StateSource(state_source_remote_base),
                 RemoteBaseVariant::StateTarget(state_target_remote_base),
             ) => {
+                if shadow {
+                    shadow_connections_state
+                        .entry(ByAddress(state_source_remote_base))
+                        .or_default()
+                        .push(ShadowConnection::new(
+                            (
+                                source_device_id_signal_identifier_base.clone(),
+                                target_device_id_signal_identifier_base.clone(),
+                            ),
+                            Some(state_target_remote_base),
+                        ));
+                    continue;
+                }
+
                 // this is checked during signals iteration
                 let source_sources_changed_waker_remote =
                     source_sources_changed_waker_remote.as_ref().unwrap();
@@ -583,6 +1230,20 @@ fn new_inner_child<'p, 'd>(
                 RemoteBaseVariant::EventSource(event_source_remote_base),
                 RemoteBaseVariant::EventTarget(event_target_remote_base),
             ) => {
+                if shadow {
+                    shadow_connections_event
+                        .entry(ByAddress(event_source_remote_base))
+                        .or_default()
+                        .push(ShadowConnection::new(
+                            (
+                                source_device_id_signal_identifier_base.clone(),
+                                target_device_id_signal_identifier_base.clone(),
+                            ),
+                            None,
+                        ));
+                    continue;
+                }
+
                 // this is checked during signals iteration
                 let source_sources_changed_waker_remote =
                     source_sources_changed_waker_remote.as_ref().unwrap();
@@ -644,8 +1305,108 @@ fn new_inner_child<'p, 'd>(
         }
     }
 
+    // used to resolve devices of traced values
+    let sources_changed_waker_remotes_device_ids = parent
+        .device_contexts
+        .iter()
+        .filter_map(|(device_id, (_, _, sources_changed_waker_remote, _))| {
+            sources_changed_waker_remote
+                .as_ref()
+                .map(|sources_changed_waker_remote| {
+                    (ByAddress(sources_changed_waker_remote), *device_id)
+                })
+        })
+        .collect::<HashMap<_, _>>();
+    let targets_changed_waker_remotes_device_ids = parent
+        .device_contexts
+        .iter()
+        .filter_map(|(device_id, (_, targets_changed_waker_remote, _, _))| {
+            targets_changed_waker_remote
+                .as_ref()
+                .map(|targets_changed_waker_remote| {
+                    (ByAddress(targets_changed_waker_remote), *device_id)
+                })
+        })
+        .collect::<HashMap<_, _>>();
+
     Ok(ExchangerInnerChild {
         connections,
         state_targets_disconnected,
+        shadow_connections_state,
+        shadow_connections_event,
+        state_targets_maintenance,
+        sources_changed_waker_remotes_device_ids,
+        targets_changed_waker_remotes_device_ids,
     })
 }
+
+// returns source variants missing in target
+fn variants_unhandled<'s>(
+    source_variants: &'s [Cow<'static, str>],
+    target_variants: &[Cow<'static, str>],
+) -> Box<[&'s str]> {
+    source_variants
+        .iter()
+        .filter(|source_variant| !target_variants.contains(source_variant))
+        .map(|source_variant| source_variant.as_ref())
+        .collect::<Box<[_]>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::variants_unhandled;
+    use crate::simulation::{mock, Simulation};
+    use std::{borrow::Cow, time::Duration};
+
+    #[test]
+    fn variants() {
+        let source = [Cow::from("eco"), Cow::from("comfort")];
+        let target = [Cow::from("off"), Cow::from("eco"), Cow::from("comfort")];
+
+        assert!(variants_unhandled(&source, &target).is_empty());
+        assert_eq!(&*variants_unhandled(&target, &source), &["off"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shadow_not_forwarded() {
+        let live = mock::StateSource::<bool>::new(Some(false));
+        let shadow = mock::StateSource::<bool>::new(Some(true));
+        let target = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let live_handle = simulation.device_add(&live);
+        let shadow_handle = simulation.device_add(&shadow);
+        let target_handle = simulation.device_add(&target);
+        simulation.device_shadow_set(shadow_handle);
+
+        // shadow source does not count as second source of the target
+        simulation.signals().d2d(
+            live_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            target_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            shadow_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            target_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                shadow.set(Some(false));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                live.set(Some(true));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            })
+            .await
+            .unwrap();
+
+        target.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(2), Some(true)),
+        ]);
+    }
+}
@@ -0,0 +1,215 @@
+// optional tracing of time needed for value to travel from its origin (eg.
+// hardware input) through exchanger and devices, eg. to verify that button to
+// relay latency is within budget
+//
+// change of a source is tagged with origin (time and device) when source waker
+// is woken. devices woken by traced targets pass the origin to sources changed
+// in the same poll, so the origin is kept along the whole route.
+// hops are recorded when exchanger forwards value and when target device
+// receives it, hardware output transactions are not included
+use crate::devices::Id as DeviceId;
+use futures::future::Future;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+pub fn enabled_set(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Origin {
+    pub instant: Instant,
+    // None until source device is resolved by exchanger
+    pub device_id: Option<DeviceId>,
+}
+impl Origin {
+    // earlier origin wins, so latency is measured from first change
+    fn merge(
+        origin: &mut Option<Self>,
+        other: Self,
+    ) {
+        if !origin.is_some_and(|origin| origin.instant <= other.instant) {
+            *origin = Some(other);
+        }
+    }
+}
+
+thread_local! {
+    // origin of targets received by currently polled device
+    static ORIGIN_CURRENT: Cell<Option<Origin>> = const { Cell::new(None) };
+}
+pub(super) fn origin_current() -> Option<Origin> {
+    ORIGIN_CURRENT.with(|origin_current| origin_current.get())
+}
+pub(super) fn origin_current_set(origin: Option<Origin>) {
+    ORIGIN_CURRENT.with(|origin_current| origin_current.set(origin));
+}
+
+// keeps earliest origin of changes not yet consumed
+#[derive(Debug)]
+pub(super) struct OriginPending {
+    inner: Mutex<Option<Origin>>,
+}
+impl OriginPending {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(None),
+        }
+    }
+
+    pub fn merge(
+        &self,
+        origin: Origin,
+    ) {
+        Origin::merge(&mut self.inner.lock(), origin);
+    }
+    pub fn take(&self) -> Option<Origin> {
+        self.inner.lock().take()
+    }
+}
+
+// limits origin inherited by sources to single poll of device future, so it
+// does not leak to unrelated changes (eg. timers) processed later
+#[derive(Debug)]
+pub struct Scoped<F>
+where
+    F: Future,
+{
+    future: F,
+}
+impl<F> Scoped<F>
+where
+    F: Future,
+{
+    pub fn new(future: F) -> Self {
+        Self { future }
+    }
+}
+impl<F> Future for Scoped<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let future = unsafe { self.map_unchecked_mut(|self_| &mut self_.future) };
+
+        origin_current_set(None);
+        let result = future.poll(cx);
+        origin_current_set(None);
+
+        result
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Percentiles {
+    pub count: usize,
+    pub p50_seconds: f64,
+    pub p90_seconds: f64,
+    pub p99_seconds: f64,
+    pub max_seconds: f64,
+}
+
+// keeps most recent samples only
+#[derive(Debug)]
+pub struct Recorder {
+    samples: Mutex<VecDeque<Duration>>,
+}
+impl Recorder {
+    const SAMPLES_MAX: usize = 1024;
+
+    pub fn new() -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(
+        &self,
+        sample: Duration,
+    ) {
+        let mut samples = self.samples.lock();
+        if samples.len() >= Self::SAMPLES_MAX {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        let mut samples = self.samples.lock().iter().copied().collect::<Box<[_]>>();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+
+        // nearest rank
+        let percentile = |percentile: usize| {
+            let rank = (samples.len() * percentile).div_ceil(100).max(1);
+            samples[rank - 1].as_secs_f64()
+        };
+
+        Some(Percentiles {
+            count: samples.len(),
+            p50_seconds: percentile(50),
+            p90_seconds: percentile(90),
+            p99_seconds: percentile(99),
+            max_seconds: samples[samples.len() - 1].as_secs_f64(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize)]
+pub enum Hop {
+    // value forwarded by exchanger to target signal
+    Exchanger,
+    // target device woken with the value
+    Device,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteLatency {
+    pub origin_device_id: DeviceId,
+    pub target_device_id: DeviceId,
+    pub hop: Hop,
+    pub percentiles: Percentiles,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recorder;
+    use std::time::Duration;
+
+    #[test]
+    fn percentiles() {
+        let recorder = Recorder::new();
+        assert!(recorder.percentiles().is_none());
+
+        for sample in (1..=100).rev() {
+            recorder.record(Duration::from_millis(sample));
+        }
+
+        let percentiles = recorder.percentiles().unwrap();
+        assert_eq!(percentiles.count, 100);
+        assert_eq!(percentiles.p50_seconds, 0.050);
+        assert_eq!(percentiles.p90_seconds, 0.090);
+        assert_eq!(percentiles.p99_seconds, 0.099);
+        assert_eq!(percentiles.max_seconds, 0.100);
+    }
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+use std::borrow::Cow;
+
+// optional display hints of signal, declared by device, so gui and logger can
+// label values without duplicating this knowledge
+// all fields describe value as serialized, for typed values (ex. Temperature,
+// Ratio) unit is implied by type and should be left empty
+#[derive(Clone, Default, Debug, Serialize)]
+pub struct Metadata {
+    pub name: Option<Cow<'static, str>>,
+    pub unit: Option<Cow<'static, str>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    // number of decimal places
+    pub precision: Option<u8>,
+    // icon name from gui icon set
+    pub icon: Option<Cow<'static, str>>,
+    // allowed values of enumeration (ex. Mode) signals
+    // source with variants not handled by target is rejected by exchanger
+    pub variants: Option<Box<[Cow<'static, str>]>>,
+}
@@ -1,4 +1,6 @@
 pub mod exchanger;
+pub mod latency;
+pub mod metadata;
 pub mod signal;
 pub mod types;
 pub mod utils;
@@ -84,6 +86,11 @@ impl IdentifierBaseWrapper {
         let inner = Box::new(identifier);
         Self { inner }
     }
+
+    // identifier as written in code, eg. "Output" or "Input(1)"
+    pub fn name(&self) -> String {
+        format!("{:?}", self.inner.as_debug())
+    }
 }
 impl Clone for IdentifierBaseWrapper {
     fn clone(&self) -> Self {
@@ -124,6 +131,9 @@ impl fmt::Debug for IdentifierBaseWrapper {
 #[allow(type_alias_bounds)] // FIXME: one day maybe this will be fixed
 pub type ByIdentifier<'s, I: Identifier> = HashMap<I, &'s dyn signal::Base>;
 pub type ByIdentifierBaseWrapper<'s> = HashMap<IdentifierBaseWrapper, &'s dyn signal::Base>;
+#[allow(type_alias_bounds)] // FIXME: one day maybe this will be fixed
+pub type MetadataByIdentifier<I: Identifier> = HashMap<I, metadata::Metadata>;
+pub type MetadataByIdentifierBaseWrapper = HashMap<IdentifierBaseWrapper, metadata::Metadata>;
 
 // Device
 pub trait Device: fmt::Debug + Send + Sync {
@@ -132,12 +142,18 @@ pub trait Device: fmt::Debug + Send + Sync {
 
     type Identifier: Identifier;
     fn by_identifier(&self) -> ByIdentifier<Self::Identifier>;
+
+    // signals without metadata can be omitted
+    fn metadata(&self) -> MetadataByIdentifier<Self::Identifier> {
+        MetadataByIdentifier::<Self::Identifier>::new()
+    }
 }
 
 pub trait DeviceBase: Send + Sync + fmt::Debug {
     fn targets_changed_waker(&self) -> Option<&waker::TargetsChangedWaker>;
     fn sources_changed_waker(&self) -> Option<&waker::SourcesChangedWaker>;
     fn by_identifier(&self) -> ByIdentifierBaseWrapper;
+    fn metadata(&self) -> MetadataByIdentifierBaseWrapper;
 
     fn type_name(&self) -> &str; // for debugging
 
@@ -159,6 +175,15 @@ impl<D: Device> DeviceBase for D {
             })
             .collect::<ByIdentifierBaseWrapper>()
     }
+    fn metadata(&self) -> MetadataByIdentifierBaseWrapper {
+        self.metadata()
+            .into_iter()
+            .map(|(identifier, metadata)| {
+                let identifier = IdentifierBaseWrapper::new(identifier);
+                (identifier, metadata)
+            })
+            .collect::<MetadataByIdentifierBaseWrapper>()
+    }
 
     fn type_name(&self) -> &str {
         type_name::<D>()
@@ -191,6 +216,9 @@ impl<'d> DeviceBaseRef<'d> {
     pub fn by_identifier(&self) -> ByIdentifierBaseWrapper<'d> {
         self.inner.by_identifier()
     }
+    pub fn metadata(&self) -> MetadataByIdentifierBaseWrapper {
+        self.inner.metadata()
+    }
 
     pub fn type_name(&self) -> &str {
         self.inner.type_name()
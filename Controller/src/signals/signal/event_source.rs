@@ -1,5 +1,5 @@
 use super::{
-    super::types::{event::Value, Base as ValueBase},
+    super::types::{event::Value, AnyValue},
     Base, EventSourceRemoteBase, RemoteBase, RemoteBaseVariant,
 };
 use parking_lot::RwLock;
@@ -63,14 +63,11 @@ impl<V: Value + Clone> Base for Signal<V> {
     }
 }
 impl<V: Value + Clone> EventSourceRemoteBase for Signal<V> {
-    fn take_pending(&self) -> Box<[Box<dyn ValueBase>]> {
+    fn take_pending(&self) -> Box<[AnyValue]> {
         let mut lock = self.inner.write();
 
         let pending = replace(&mut lock.pending, Vec::<V>::new());
-        let pending = pending
-            .into_iter()
-            .map(|value| Box::new(value) as Box<dyn ValueBase>)
-            .collect::<Box<[_]>>();
+        let pending = pending.into_iter().map(AnyValue::new).collect::<Box<[_]>>();
 
         drop(lock);
 
@@ -1,5 +1,5 @@
 use super::{
-    super::types::{event::Value, Base as ValueBase},
+    super::types::{event::Value, AnyValue},
     Base, EventTargetRemoteBase, RemoteBase, RemoteBaseVariant,
 };
 use parking_lot::RwLock;
@@ -28,7 +28,7 @@ impl<V: Value + Clone> Base for Signal<V> {
 impl<V: Value + Clone> EventTargetRemoteBase for Signal<V> {
     fn push(
         &self,
-        values: &[Box<dyn ValueBase>],
+        values: &[AnyValue],
     ) -> bool {
         let value = match values.iter().last() {
             Some(value) => value,
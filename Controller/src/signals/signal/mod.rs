@@ -5,7 +5,7 @@ pub mod state_source;
 pub mod state_target_last;
 pub mod state_target_queued;
 
-use super::types::Base as ValueBase;
+use super::types::AnyValue;
 use std::{any::TypeId, fmt};
 
 // Signals
@@ -14,25 +14,26 @@ pub trait Base: Send + Sync {
 }
 
 pub trait StateSourceRemoteBase: RemoteBase {
-    fn take_pending(&self) -> Box<[Option<Box<dyn ValueBase>>]>;
-    fn peek_last(&self) -> Option<Box<dyn ValueBase>>;
+    fn take_pending(&self) -> Box<[Option<AnyValue>]>;
+    fn peek_last(&self) -> Option<AnyValue>;
 }
 pub trait StateTargetRemoteBase: RemoteBase {
     #[must_use = "use this value to wake signals change notifier"]
     fn set(
         &self,
-        values: &[Option<Box<dyn ValueBase>>],
+        values: &[Option<AnyValue>],
     ) -> bool;
+    fn peek_last(&self) -> Option<AnyValue>;
 }
 
 pub trait EventSourceRemoteBase: RemoteBase {
-    fn take_pending(&self) -> Box<[Box<dyn ValueBase>]>;
+    fn take_pending(&self) -> Box<[AnyValue]>;
 }
 pub trait EventTargetRemoteBase: RemoteBase {
     #[must_use = "use this value to wake signals change notifier"]
     fn push(
         &self,
-        values: &[Box<dyn ValueBase>],
+        values: &[AnyValue],
     ) -> bool;
 }
 
@@ -1,5 +1,5 @@
 use super::{
-    super::types::{state::Value, Base as ValueBase},
+    super::types::{state::Value, AnyValue},
     Base, RemoteBase, RemoteBaseVariant, StateSourceRemoteBase,
 };
 use parking_lot::RwLock;
@@ -86,13 +86,13 @@ impl<V: Value + Clone> Base for Signal<V> {
     }
 }
 impl<V: Value + Clone> StateSourceRemoteBase for Signal<V> {
-    fn take_pending(&self) -> Box<[Option<Box<dyn ValueBase>>]> {
+    fn take_pending(&self) -> Box<[Option<AnyValue>]> {
         let mut lock = self.inner.write();
 
         let pending = replace(&mut lock.pending, Vec::<Option<V>>::new());
         let pending = pending
             .into_iter()
-            .map(|value| value.map(|value| Box::new(value) as Box<dyn ValueBase>))
+            .map(|value| value.map(AnyValue::new))
             .collect::<Box<[_]>>();
 
         drop(lock);
@@ -100,12 +100,8 @@ impl<V: Value + Clone> StateSourceRemoteBase for Signal<V> {
         pending
     }
 
-    fn peek_last(&self) -> Option<Box<dyn ValueBase>> {
-        self.inner
-            .read()
-            .last
-            .clone()
-            .map(|value| Box::new(value) as Box<dyn ValueBase>)
+    fn peek_last(&self) -> Option<AnyValue> {
+        self.inner.read().last.clone().map(AnyValue::new)
     }
 }
 impl<V: Value + Clone> RemoteBase for Signal<V> {
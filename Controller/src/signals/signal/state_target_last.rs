@@ -1,5 +1,5 @@
 use super::{
-    super::types::{state::Value, Base as ValueBase},
+    super::types::{state::Value, AnyValue},
     Base, RemoteBase, RemoteBaseVariant, StateTargetRemoteBase,
 };
 use parking_lot::RwLock;
@@ -76,7 +76,7 @@ impl<V: Value + Clone> StateTargetRemoteBase for Signal<V> {
     #[must_use = "use this value to wake signals change notifier"]
     fn set(
         &self,
-        values: &[Option<Box<dyn ValueBase>>],
+        values: &[Option<AnyValue>],
     ) -> bool {
         let value = match values.iter().last() {
             Some(value) => value,
@@ -101,6 +101,9 @@ impl<V: Value + Clone> StateTargetRemoteBase for Signal<V> {
 
         true
     }
+    fn peek_last(&self) -> Option<AnyValue> {
+        Signal::peek_last(self).map(AnyValue::new)
+    }
 }
 impl<V: Value + Clone> RemoteBase for Signal<V> {
     fn type_id(&self) -> TypeId {
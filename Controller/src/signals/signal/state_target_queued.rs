@@ -1,5 +1,5 @@
 use super::{
-    super::types::{state::Value, Base as ValueBase},
+    super::types::{state::Value, AnyValue},
     Base, RemoteBase, RemoteBaseVariant, StateTargetRemoteBase,
 };
 use parking_lot::RwLock;
@@ -83,7 +83,7 @@ impl<V: Value + Clone> StateTargetRemoteBase for Signal<V> {
     #[must_use = "use this value to wake signals change notifier"]
     fn set(
         &self,
-        values: &[Option<Box<dyn ValueBase>>],
+        values: &[Option<AnyValue>],
     ) -> bool {
         let mut lock = self.inner.write();
 
@@ -108,6 +108,9 @@ impl<V: Value + Clone> StateTargetRemoteBase for Signal<V> {
 
         changes
     }
+    fn peek_last(&self) -> Option<AnyValue> {
+        Signal::peek_last(self).map(AnyValue::new)
+    }
 }
 impl<V: Value + Clone> RemoteBase for Signal<V> {
     fn type_id(&self) -> TypeId {
@@ -1,6 +1,202 @@
 pub mod event;
 pub mod state;
 
-use std::any::Any;
+use crate::datatypes::{
+    building::{
+        house_mode::HouseMode,
+        window::{WindowOpenStateOpenClosed, WindowOpenStateOpenTiltedClosed},
+    },
+    energy::spot_prices::SpotPrices,
+    mode::Mode,
+    multiplier::Multiplier,
+    quality::Quality,
+    ratio::Ratio,
+    real::Real,
+    resistance::Resistance,
+    temperature::Temperature,
+    voltage::Voltage,
+};
+use anyhow::{bail, Context, Error};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    any::{Any, TypeId},
+    time::Duration,
+};
 
 pub trait Base = Any + Send + Sync + 'static;
+
+// Type erased signal value, passed between sources and targets by the
+// exchanger. Most of the traffic consists of small primitives, which are stored
+// inline to avoid allocating for every value. Everything else is boxed.
+#[derive(Debug)]
+pub enum AnyValue {
+    Unit,
+    Bool(bool),
+    Duration(Duration),
+    Multiplier(Multiplier),
+    Ratio(Ratio),
+    Real(Real),
+    Temperature(Temperature),
+    Boxed(Box<dyn Base>),
+}
+impl AnyValue {
+    pub fn new<V: Base>(value: V) -> Self {
+        // moves value into matching inline variant, if there is one
+        fn inline<V: Base, T: Base>(value: &mut Option<V>) -> Option<T> {
+            (value as &mut dyn Any)
+                .downcast_mut::<Option<T>>()
+                .map(|value| value.take().unwrap())
+        }
+
+        let mut value = Some(value);
+        if let Some(()) = inline::<V, ()>(&mut value) {
+            return Self::Unit;
+        }
+        if let Some(value) = inline::<V, bool>(&mut value) {
+            return Self::Bool(value);
+        }
+        if let Some(value) = inline::<V, Duration>(&mut value) {
+            return Self::Duration(value);
+        }
+        if let Some(value) = inline::<V, Multiplier>(&mut value) {
+            return Self::Multiplier(value);
+        }
+        if let Some(value) = inline::<V, Ratio>(&mut value) {
+            return Self::Ratio(value);
+        }
+        if let Some(value) = inline::<V, Real>(&mut value) {
+            return Self::Real(value);
+        }
+        if let Some(value) = inline::<V, Temperature>(&mut value) {
+            return Self::Temperature(value);
+        }
+        Self::Boxed(Box::new(value.unwrap()))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        match self {
+            Self::Unit => &(),
+            Self::Bool(value) => value,
+            Self::Duration(value) => value,
+            Self::Multiplier(value) => value,
+            Self::Ratio(value) => value,
+            Self::Real(value) => value,
+            Self::Temperature(value) => value,
+            Self::Boxed(value) => value.as_ref(),
+        }
+    }
+    pub fn downcast_ref<V: Base>(&self) -> Option<&V> {
+        self.as_any().downcast_ref::<V>()
+    }
+
+    // decodes value of type identified by type_id, used to write signals from
+    // outside (eg. web)
+    pub fn from_json(
+        type_id: TypeId,
+        value: serde_json::Value,
+    ) -> Result<Self, Error> {
+        let json_codec = json_codec(type_id).context("json_codec")?;
+        (json_codec.decode)(value)
+    }
+    pub fn to_json(&self) -> Result<serde_json::Value, Error> {
+        let json_codec = json_codec(Any::type_id(self.as_any())).context("json_codec")?;
+        (json_codec.encode)(self)
+    }
+}
+
+struct JsonCodec {
+    type_id: TypeId,
+    decode: fn(serde_json::Value) -> Result<AnyValue, Error>,
+    encode: fn(&AnyValue) -> Result<serde_json::Value, Error>,
+}
+impl JsonCodec {
+    fn new<V: Base + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            type_id: TypeId::of::<V>(),
+            decode: |value| {
+                let value = serde_json::from_value::<V>(value).context("from_value")?;
+                Ok(AnyValue::new(value))
+            },
+            encode: |value| {
+                let value = value.downcast_ref::<V>().unwrap();
+                let value = serde_json::to_value(value).context("to_value")?;
+                Ok(value)
+            },
+        }
+    }
+}
+fn json_codec(type_id: TypeId) -> Result<JsonCodec, Error> {
+    let json_codecs = [
+        JsonCodec::new::<()>(),
+        JsonCodec::new::<bool>(),
+        JsonCodec::new::<Duration>(),
+        JsonCodec::new::<HouseMode>(),
+        JsonCodec::new::<Mode>(),
+        JsonCodec::new::<Multiplier>(),
+        JsonCodec::new::<Quality>(),
+        JsonCodec::new::<Ratio>(),
+        JsonCodec::new::<Real>(),
+        JsonCodec::new::<Resistance>(),
+        JsonCodec::new::<SpotPrices>(),
+        JsonCodec::new::<Temperature>(),
+        JsonCodec::new::<Voltage>(),
+        JsonCodec::new::<WindowOpenStateOpenClosed>(),
+        JsonCodec::new::<WindowOpenStateOpenTiltedClosed>(),
+    ];
+
+    let json_codec = match json_codecs
+        .into_iter()
+        .find(|json_codec| json_codec.type_id == type_id)
+    {
+        Some(json_codec) => json_codec,
+        None => bail!("type has no json representation"),
+    };
+
+    Ok(json_codec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnyValue;
+    use crate::datatypes::{mode::Mode, real::Real};
+    use serde_json::json;
+    use std::any::TypeId;
+
+    #[test]
+    fn inline_and_boxed() {
+        let value = AnyValue::new(true);
+        assert!(matches!(value, AnyValue::Bool(true)));
+        assert_eq!(value.downcast_ref::<bool>(), Some(&true));
+        assert_eq!(value.downcast_ref::<Real>(), None);
+
+        let value = AnyValue::new(());
+        assert!(matches!(value, AnyValue::Unit));
+        assert_eq!(value.downcast_ref::<()>(), Some(&()));
+
+        let value = AnyValue::new("text".to_owned());
+        assert!(matches!(value, AnyValue::Boxed(_)));
+        assert_eq!(value.downcast_ref::<String>().unwrap(), "text");
+        assert_eq!(value.downcast_ref::<bool>(), None);
+    }
+
+    #[test]
+    fn json() {
+        let value = AnyValue::from_json(TypeId::of::<bool>(), json!(true)).unwrap();
+        assert_eq!(value.downcast_ref::<bool>(), Some(&true));
+
+        let value = AnyValue::from_json(TypeId::of::<Real>(), json!(1.5)).unwrap();
+        assert_eq!(
+            value.downcast_ref::<Real>(),
+            Some(&Real::from_f64(1.5).unwrap())
+        );
+
+        assert_eq!(value.to_json().unwrap(), json!(1.5));
+
+        let value = AnyValue::from_json(TypeId::of::<Mode>(), json!("eco")).unwrap();
+        assert_eq!(value.downcast_ref::<Mode>(), Some(&Mode::new("eco")));
+        assert_eq!(value.to_json().unwrap(), json!("eco"));
+
+        assert!(AnyValue::from_json(TypeId::of::<bool>(), json!(1.5)).is_err());
+        assert!(AnyValue::from_json(TypeId::of::<String>(), json!("text")).is_err());
+    }
+}
@@ -4,23 +4,31 @@ use crate::datatypes::{
         AngleNormalized, AngleNormalizedHalf, AngleNormalizedHalfZeroCentered,
         AngleNormalizedZeroCentered,
     },
-    building::window::{WindowOpenStateOpenClosed, WindowOpenStateOpenTiltedClosed},
+    building::{
+        house_mode::HouseMode,
+        window::{WindowOpenStateOpenClosed, WindowOpenStateOpenTiltedClosed},
+    },
     color_rgb_boolean::ColorRgbBoolean,
+    energy::spot_prices::SpotPrices,
     ipc_rtsp_url::IpcRtspUrl,
+    mode::Mode,
     multiplier::Multiplier,
+    quality::Quality,
     range::Range,
     ratio::Ratio,
     real::Real,
     resistance::Resistance,
     temperature::Temperature,
+    timestamped::Timestamped,
     voltage::Voltage,
 };
-use std::fmt;
+use std::{fmt, time::Duration};
 
 pub trait Value: Base + Eq + fmt::Debug + 'static {}
 
 //
 impl Value for bool {}
+impl Value for Duration {}
 
 // datatypes
 impl Value for AngleNormalized {}
@@ -29,7 +37,9 @@ impl Value for AngleNormalizedHalfZeroCentered {}
 impl Value for AngleNormalizedZeroCentered {}
 impl Value for ColorRgbBoolean {}
 impl Value for IpcRtspUrl {}
+impl Value for Mode {}
 impl Value for Multiplier {}
+impl Value for Quality {}
 impl Value for Ratio {}
 impl Value for Real {}
 impl Value for Resistance {}
@@ -38,7 +48,16 @@ impl Value for Voltage {}
 
 // datatypes parent
 impl<T> Value for Range<T> where T: Value {}
+impl<T> Value for Timestamped<T> where T: Value {}
+
+// arrays, eg. all channels of a board as single signal
+// see soft::value::array_split_a / array_join_a for per-element connections
+impl<T, const N: usize> Value for [T; N] where T: Value {}
 
 // datatypes::building
+impl Value for HouseMode {}
 impl Value for WindowOpenStateOpenClosed {}
 impl Value for WindowOpenStateOpenTiltedClosed {}
+
+// datatypes::energy
+impl Value for SpotPrices {}
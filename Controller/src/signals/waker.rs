@@ -1,18 +1,30 @@
-use crate::util::async_waker::mpsc;
+use super::latency::{self, Origin, OriginPending, Percentiles, Recorder};
+use crate::{devices::Id as DeviceId, util::async_waker::mpsc};
 use futures::stream::{FusedStream, Stream};
+use parking_lot::Mutex;
 use std::{
+    collections::HashMap,
     pin::Pin,
     task::{Context, Poll},
+    time::Instant,
 };
 
 #[derive(Debug)]
 pub struct TargetsChangedWaker {
     inner: mpsc::Signal,
+
+    origin_pending: OriginPending,
+    // by origin device
+    latency_recorders: Mutex<HashMap<DeviceId, Recorder>>,
 }
 impl TargetsChangedWaker {
     pub fn new() -> Self {
         let inner = mpsc::Signal::new();
-        Self { inner }
+        Self {
+            inner,
+            origin_pending: OriginPending::new(),
+            latency_recorders: Mutex::new(HashMap::new()),
+        }
     }
 
     pub fn stream(&self) -> TargetsChangedWakerStream {
@@ -25,12 +37,13 @@ impl TargetsChangedWaker {
 }
 #[derive(Debug)]
 pub struct TargetsChangedWakerStream<'a> {
+    parent: &'a TargetsChangedWaker,
     inner: mpsc::Receiver<'a>,
 }
 impl<'a> TargetsChangedWakerStream<'a> {
     fn new(parent: &'a TargetsChangedWaker) -> Self {
         let inner = parent.inner.receiver();
-        Self { inner }
+        Self { parent, inner }
     }
 }
 impl<'a> Stream for TargetsChangedWakerStream<'a> {
@@ -40,7 +53,29 @@ impl<'a> Stream for TargetsChangedWakerStream<'a> {
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        unsafe { Pin::new_unchecked(&mut self.get_unchecked_mut().inner) }.poll_next(cx)
+        let self_ = unsafe { self.get_unchecked_mut() };
+        let result = unsafe { Pin::new_unchecked(&mut self_.inner) }.poll_next(cx);
+
+        // sources changed while handling this wake up inherit the origin
+        if let Poll::Ready(Some(())) = result {
+            let origin = self_.parent.origin_pending.take();
+            if let Some(Origin {
+                instant,
+                device_id: Some(device_id),
+            }) = origin
+            {
+                self_
+                    .parent
+                    .latency_recorders
+                    .lock()
+                    .entry(device_id)
+                    .or_insert_with(Recorder::new)
+                    .record(instant.elapsed());
+            }
+            latency::origin_current_set(origin);
+        }
+
+        result
     }
 }
 impl<'a> FusedStream for TargetsChangedWakerStream<'a> {
@@ -50,29 +85,64 @@ impl<'a> FusedStream for TargetsChangedWakerStream<'a> {
 }
 #[derive(Debug)]
 pub struct TargetsChangedWakerRemote<'a> {
+    parent: &'a TargetsChangedWaker,
     inner: mpsc::Sender<'a>,
 }
 impl<'a> TargetsChangedWakerRemote<'a> {
     fn new(parent: &'a TargetsChangedWaker) -> Self {
         let inner = parent.inner.sender();
-        Self { inner }
+        Self { parent, inner }
     }
     pub fn wake(&self) {
         self.inner.wake();
     }
+
+    // must be called before wake()
+    pub fn origin_merge(
+        &self,
+        origin: Origin,
+    ) {
+        self.parent.origin_pending.merge(origin);
+    }
+    // by origin device
+    pub fn latency_percentiles(&self) -> Box<[(DeviceId, Percentiles)]> {
+        self.parent
+            .latency_recorders
+            .lock()
+            .iter()
+            .filter_map(|(device_id, recorder)| {
+                recorder
+                    .percentiles()
+                    .map(|percentiles| (*device_id, percentiles))
+            })
+            .collect::<Box<[_]>>()
+    }
 }
 
 #[derive(Debug)]
 pub struct SourcesChangedWaker {
     inner: mpsc::Signal,
+
+    origin_pending: OriginPending,
 }
 impl SourcesChangedWaker {
     pub fn new() -> Self {
         let inner = mpsc::Signal::new();
-        Self { inner }
+        Self {
+            inner,
+            origin_pending: OriginPending::new(),
+        }
     }
 
     pub fn wake(&self) {
+        if latency::enabled() {
+            let origin = latency::origin_current().unwrap_or(Origin {
+                instant: Instant::now(),
+                device_id: None,
+            });
+            self.origin_pending.merge(origin);
+        }
+
         self.inner.wake();
     }
 
@@ -91,6 +161,10 @@ impl<'a> SourcesChangedWakerRemote<'a> {
     pub fn stream(&self) -> SourcesChangedWakerRemoteStream {
         SourcesChangedWakerRemoteStream::new(self)
     }
+
+    pub fn origin_take(&self) -> Option<Origin> {
+        self.parent.origin_pending.take()
+    }
 }
 #[derive(Debug)]
 pub struct SourcesChangedWakerRemoteStream<'a> {
@@ -0,0 +1,302 @@
+// golden trace tests: device is fed scripted timeline of input values on
+// virtual time and its output trace is compared against file stored in the
+// repository, so behaviour of time / logic devices is locked during refactors
+// running tests with GOLDEN_UPDATE_ENV set rewrites the files instead of
+// comparing, changes in behaviour show up as diffs in review
+use super::{mock, trace::Trace};
+use crate::{
+    devices::soft::logger::state::hardware::manager::import,
+    signals::types::{event::Value as EventValue, state::Value as StateValue},
+};
+use anyhow::{anyhow, ensure, Context, Error};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{env, fmt::Write, fs, path::PathBuf, time::Duration};
+use tokio::time::{sleep_until, Instant};
+
+pub const GOLDEN_UPDATE_ENV: &str = "GOLDEN_UPDATE";
+
+// file format is one entry per line: "<seconds> <json value>", eg. "1.500
+// true", with millisecond resolution. empty lines and lines starting with #
+// are ignored
+fn duration_render(duration: Duration) -> String {
+    format!("{}.{:03}", duration.as_secs(), duration.subsec_millis())
+}
+fn duration_parse(duration: &str) -> Result<Duration, Error> {
+    let (seconds, milliseconds) = duration.split_once('.').unwrap_or((duration, "0"));
+    ensure!(
+        !milliseconds.is_empty() && milliseconds.len() <= 3,
+        "expected up to 3 fractional digits"
+    );
+    let seconds = seconds.parse::<u64>().context("seconds")?;
+    let milliseconds = milliseconds.parse::<u64>().context("milliseconds")?
+        * 10u64.pow(3 - milliseconds.len() as u32);
+
+    Ok(Duration::from_secs(seconds) + Duration::from_millis(milliseconds))
+}
+
+pub fn render<T: Serialize>(trace: &Trace<T>) -> String {
+    let mut output = String::new();
+    for (time, value) in trace.entries() {
+        writeln!(
+            output,
+            "{} {}",
+            duration_render(*time),
+            serde_json::to_string(value).unwrap()
+        )
+        .unwrap();
+    }
+    output
+}
+pub fn parse<T: DeserializeOwned>(text: &str) -> Result<Trace<T>, Error> {
+    let entries = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| -> Result<_, Error> {
+            let (time, value) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("expected time and value"))
+                .with_context(|| format!("line {}", index + 1))?;
+            let time = duration_parse(time).with_context(|| format!("line {}", index + 1))?;
+            let value = serde_json::from_str::<T>(value.trim())
+                .with_context(|| format!("line {}", index + 1))?;
+            Ok((time, value))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    ensure!(
+        entries.is_sorted_by_key(|(time, _)| *time),
+        "entries must be ordered by time"
+    );
+
+    Ok(Trace::new(entries))
+}
+
+// path is relative to crate root, eg. "src/simulation/golden/device.trace"
+#[track_caller]
+pub fn assert_golden<T: Serialize>(
+    trace: &Trace<T>,
+    path: &str,
+) {
+    let path_full = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path);
+    let actual = render(trace);
+
+    if env::var_os(GOLDEN_UPDATE_ENV).is_some() {
+        fs::write(&path_full, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path_full).unwrap_or_else(|error| {
+        panic!(
+            "golden trace {} could not be read ({}), run with {}=1 to create it",
+            path, error, GOLDEN_UPDATE_ENV
+        )
+    });
+    // comments and formatting in golden file are not significant
+    let expected = render(&parse::<serde_json::Value>(&expected).unwrap());
+    let actual = render(&parse::<serde_json::Value>(&actual).unwrap());
+    assert_eq!(
+        expected, actual,
+        "golden trace {} differs, run with {}=1 to accept",
+        path, GOLDEN_UPDATE_ENV
+    );
+}
+
+// sets values at times given by timeline, relative to the moment of call, so
+// the script should be started together with simulation
+pub async fn play_state<V: StateValue + Clone>(
+    source: &mock::StateSource<V>,
+    timeline: &Trace<Option<V>>,
+) {
+    let start = Instant::now();
+    for (time, value) in timeline.entries() {
+        sleep_until(start + *time).await;
+        source.set(value.clone());
+    }
+}
+pub async fn play_event<V: EventValue + Clone>(
+    source: &mock::EventSource<V>,
+    timeline: &Trace<V>,
+) {
+    let start = Instant::now();
+    for (time, value) in timeline.entries() {
+        sleep_until(start + *time).await;
+        source.push(value.clone());
+    }
+}
+
+// converts live capture (samples exported from state logger sink or other
+// system, see logger import) to timeline, times are relative to the first
+// sample
+pub fn timeline_from_samples<V>(
+    samples: &[import::Sample],
+    value_from: impl Fn(f64) -> Result<V, Error>,
+) -> Result<Trace<Option<V>>, Error> {
+    let start = match samples.first() {
+        Some((start, _)) => *start,
+        None => return Ok(Trace::new(Vec::new())),
+    };
+
+    let entries = samples
+        .iter()
+        .map(|(timestamp, value)| -> Result<_, Error> {
+            let time = (*timestamp - start).to_std().context("to_std")?;
+            let value = value
+                .map(&value_from)
+                .transpose()
+                .with_context(|| format!("value at {}", timestamp))?;
+            Ok((time, value))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Trace::new(entries))
+}
+pub fn timeline_from_capture<V>(
+    format: import::Format,
+    text: &str,
+    value_from: impl Fn(f64) -> Result<V, Error>,
+) -> Result<Trace<Option<V>>, Error> {
+    let samples = import::parse(format, text).context("parse")?;
+    timeline_from_samples(&samples, value_from).context("timeline_from_samples")
+}
+
+// value converters for timeline_from_samples
+pub fn boolean_from_f64(value: f64) -> Result<bool, Error> {
+    Ok(value != 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{mock, trace::Trace, Simulation},
+        assert_golden, boolean_from_f64, parse, play_state, render, timeline_from_capture,
+    };
+    use crate::devices::soft::{
+        logger::state::hardware::manager::import::Format, time::boolean_change_delay_a,
+    };
+    use indoc::indoc;
+    use std::time::Duration;
+
+    #[test]
+    fn render_parse() {
+        let trace = Trace::new(vec![
+            (Duration::ZERO, None),
+            (Duration::from_millis(1500), Some(true)),
+            (Duration::from_secs(60), Some(false)),
+        ]);
+
+        let text = render(&trace);
+        assert_eq!(
+            text,
+            indoc!(
+                "
+                0.000 null
+                1.500 true
+                60.000 false
+            "
+            )
+        );
+        assert_eq!(parse::<Option<bool>>(&text).unwrap(), trace);
+
+        let trace_commented = parse::<Option<bool>>(indoc!(
+            "
+            # initial value
+            0 null
+
+            1.5 true
+            60.000 false
+        "
+        ))
+        .unwrap();
+        assert_eq!(trace_commented, trace);
+
+        assert!(parse::<Option<bool>>("1.0001 true").is_err());
+        assert!(parse::<Option<bool>>("2 true\n1 false").is_err());
+        assert!(parse::<Option<bool>>("1 maybe").is_err());
+    }
+
+    #[test]
+    fn timeline_from_capture_1() {
+        let timeline = timeline_from_capture(
+            Format::Csv,
+            indoc!(
+                "
+                timestamp,value
+                2024-01-01T00:00:10Z,1
+                2024-01-01T00:00:00Z,0
+                2024-01-01T00:01:00Z,
+            "
+            ),
+            boolean_from_f64,
+        )
+        .unwrap();
+
+        assert_eq!(
+            timeline.entries(),
+            [
+                (Duration::ZERO, Some(false)),
+                (Duration::from_secs(10), Some(true)),
+                (Duration::from_secs(60), None),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn boolean_change_delay_a_golden() {
+        // door contact captured from state logger
+        let timeline = timeline_from_capture(
+            Format::Csv,
+            indoc!(
+                "
+                2024-01-01T00:00:00Z,0
+                2024-01-01T00:00:05Z,1
+                2024-01-01T00:00:06Z,0
+                2024-01-01T00:00:07Z,1
+                2024-01-01T00:00:20Z,0
+                2024-01-01T00:00:30Z,
+            "
+            ),
+            boolean_from_f64,
+        )
+        .unwrap();
+
+        let input = mock::StateSource::<bool>::new(None);
+        let boolean_change_delay =
+            boolean_change_delay_a::Device::new(boolean_change_delay_a::Configuration {
+                delay_raising: Duration::from_secs(2),
+                delay_falling: Duration::from_secs(5),
+            });
+        let output = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let input_handle = simulation.device_add(&input);
+        let boolean_change_delay_handle = simulation.device_add(&boolean_change_delay);
+        let output_handle = simulation.device_add(&output);
+        simulation.signals().d2d(
+            input_handle,
+            mock::StateSourceSignalIdentifier::Output,
+            boolean_change_delay_handle,
+            boolean_change_delay_a::SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            boolean_change_delay_handle,
+            boolean_change_delay_a::SignalIdentifier::Output,
+            output_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                play_state(&input, &timeline).await;
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            })
+            .await
+            .unwrap();
+
+        assert_golden(
+            &output.trace(),
+            "src/simulation/golden/boolean_change_delay_a.trace",
+        );
+    }
+}
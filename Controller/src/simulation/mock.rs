@@ -0,0 +1,356 @@
+use super::trace::Trace;
+use crate::{
+    devices,
+    signals::{
+        self, signal,
+        types::{event::Value as EventValue, state::Value as StateValue},
+    },
+    util::{
+        async_ext::stream_take_until_exhausted::StreamTakeUntilExhaustedExt,
+        async_flag,
+        runnable::{Exited, Runnable},
+    },
+};
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use maplit::hashmap;
+use parking_lot::Mutex;
+use std::{any::type_name, borrow::Cow, time::Duration};
+use tokio::time::Instant;
+
+// all devices in simulation are started at the same moment
+#[derive(Debug)]
+struct TraceStart {
+    start: Mutex<Option<Instant>>,
+}
+impl TraceStart {
+    fn new() -> Self {
+        Self {
+            start: Mutex::new(None),
+        }
+    }
+    fn reset(&self) {
+        *self.start.lock() = Some(Instant::now());
+    }
+    fn elapsed(&self) -> Duration {
+        self.start.lock().unwrap().elapsed()
+    }
+}
+
+// state source controlled by the script
+#[derive(Debug)]
+pub struct StateSource<V: StateValue + Clone> {
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_output: signal::state_source::Signal<V>,
+}
+impl<V: StateValue + Clone> StateSource<V> {
+    pub fn new(initial: Option<V>) -> Self {
+        Self {
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_output: signal::state_source::Signal::<V>::new(initial),
+        }
+    }
+
+    pub fn set(
+        &self,
+        value: Option<V>,
+    ) {
+        if self.signal_output.set_one(value) {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+}
+impl<V: StateValue + Clone> devices::Device for StateSource<V> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "simulation/mock/state_source<{}>",
+            type_name::<V>()
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+#[async_trait]
+impl<V: StateValue + Clone> Runnable for StateSource<V> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        exit_flag.await;
+        Exited
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StateSourceSignalIdentifier {
+    Output,
+}
+impl signals::Identifier for StateSourceSignalIdentifier {}
+impl<V: StateValue + Clone> signals::Device for StateSource<V> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = StateSourceSignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            StateSourceSignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
+
+// event source controlled by the script
+#[derive(Debug)]
+pub struct EventSource<V: EventValue + Clone> {
+    signals_sources_changed_waker: signals::waker::SourcesChangedWaker,
+    signal_output: signal::event_source::Signal<V>,
+}
+impl<V: EventValue + Clone> EventSource<V> {
+    pub fn new() -> Self {
+        Self {
+            signals_sources_changed_waker: signals::waker::SourcesChangedWaker::new(),
+            signal_output: signal::event_source::Signal::<V>::new(),
+        }
+    }
+
+    pub fn push(
+        &self,
+        value: V,
+    ) {
+        if self.signal_output.push_one(value) {
+            self.signals_sources_changed_waker.wake();
+        }
+    }
+}
+impl<V: EventValue + Clone> devices::Device for EventSource<V> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "simulation/mock/event_source<{}>",
+            type_name::<V>()
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+#[async_trait]
+impl<V: EventValue + Clone> Runnable for EventSource<V> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        exit_flag.await;
+        Exited
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventSourceSignalIdentifier {
+    Output,
+}
+impl signals::Identifier for EventSourceSignalIdentifier {}
+impl<V: EventValue + Clone> signals::Device for EventSource<V> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        None
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        Some(&self.signals_sources_changed_waker)
+    }
+
+    type Identifier = EventSourceSignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            EventSourceSignalIdentifier::Output => &self.signal_output as &dyn signal::Base,
+        }
+    }
+}
+
+// records every state value delivered to its input
+#[derive(Debug)]
+pub struct StateRecorder<V: StateValue + Clone> {
+    trace_start: TraceStart,
+    entries: Mutex<Vec<(Duration, Option<V>)>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_input: signal::state_target_queued::Signal<V>,
+}
+impl<V: StateValue + Clone> StateRecorder<V> {
+    pub fn new() -> Self {
+        Self {
+            trace_start: TraceStart::new(),
+            entries: Mutex::new(Vec::new()),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_input: signal::state_target_queued::Signal::<V>::new(),
+        }
+    }
+
+    pub fn trace(&self) -> Trace<Option<V>> {
+        Trace::new(self.entries.lock().clone())
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.trace_start.reset();
+
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                let time = self.trace_start.elapsed();
+                let values = self.signal_input.take_pending();
+                self.entries
+                    .lock()
+                    .extend(values.into_vec().into_iter().map(|value| (time, value)));
+            })
+            .await;
+
+        Exited
+    }
+}
+impl<V: StateValue + Clone> devices::Device for StateRecorder<V> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "simulation/mock/state_recorder<{}>",
+            type_name::<V>()
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+#[async_trait]
+impl<V: StateValue + Clone> Runnable for StateRecorder<V> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StateRecorderSignalIdentifier {
+    Input,
+}
+impl signals::Identifier for StateRecorderSignalIdentifier {}
+impl<V: StateValue + Clone> signals::Device for StateRecorder<V> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = StateRecorderSignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            StateRecorderSignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+        }
+    }
+}
+
+// records every event delivered to its input
+#[derive(Debug)]
+pub struct EventRecorder<V: EventValue + Clone> {
+    trace_start: TraceStart,
+    entries: Mutex<Vec<(Duration, V)>>,
+
+    signals_targets_changed_waker: signals::waker::TargetsChangedWaker,
+    signal_input: signal::event_target_queued::Signal<V>,
+}
+impl<V: EventValue + Clone> EventRecorder<V> {
+    pub fn new() -> Self {
+        Self {
+            trace_start: TraceStart::new(),
+            entries: Mutex::new(Vec::new()),
+
+            signals_targets_changed_waker: signals::waker::TargetsChangedWaker::new(),
+            signal_input: signal::event_target_queued::Signal::<V>::new(),
+        }
+    }
+
+    pub fn trace(&self) -> Trace<V> {
+        Trace::new(self.entries.lock().clone())
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.trace_start.reset();
+
+        self.signals_targets_changed_waker
+            .stream()
+            .stream_take_until_exhausted(exit_flag)
+            .for_each(async |()| {
+                let time = self.trace_start.elapsed();
+                let values = self.signal_input.take_pending();
+                self.entries
+                    .lock()
+                    .extend(values.into_vec().into_iter().map(|value| (time, value)));
+            })
+            .await;
+
+        Exited
+    }
+}
+impl<V: EventValue + Clone> devices::Device for EventRecorder<V> {
+    fn class(&self) -> Cow<'static, str> {
+        Cow::from(format!(
+            "simulation/mock/event_recorder<{}>",
+            type_name::<V>()
+        ))
+    }
+
+    fn as_runnable(&self) -> &dyn Runnable {
+        self
+    }
+    fn as_signals_device_base(&self) -> &dyn signals::DeviceBase {
+        self
+    }
+}
+#[async_trait]
+impl<V: EventValue + Clone> Runnable for EventRecorder<V> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventRecorderSignalIdentifier {
+    Input,
+}
+impl signals::Identifier for EventRecorderSignalIdentifier {}
+impl<V: EventValue + Clone> signals::Device for EventRecorder<V> {
+    fn targets_changed_waker(&self) -> Option<&signals::waker::TargetsChangedWaker> {
+        Some(&self.signals_targets_changed_waker)
+    }
+    fn sources_changed_waker(&self) -> Option<&signals::waker::SourcesChangedWaker> {
+        None
+    }
+
+    type Identifier = EventRecorderSignalIdentifier;
+    fn by_identifier(&self) -> signals::ByIdentifier<Self::Identifier> {
+        hashmap! {
+            EventRecorderSignalIdentifier::Input => &self.signal_input as &dyn signal::Base,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+pub mod golden;
+pub mod mock;
+pub mod trace;
+
+use crate::{
+    devices::{
+        helpers::{DeviceHandle, DeviceHandleErased, Signals},
+        maintenance::Maintenance,
+        Device, Id as DeviceId,
+    },
+    signals::{
+        exchanger::Exchanger, Device as SignalsDevice, DeviceBaseRef as SignalsDeviceBaseRef,
+    },
+    util::{async_flag, runnable::Runnable, timer_wheel::TokioTimeGuard},
+};
+use anyhow::{Context, Error};
+use futures::{
+    future::{join_all, Future},
+    join,
+};
+use std::collections::{HashMap, HashSet};
+
+// runs set of devices connected through regular exchanger, on tokio clock
+// must be used from current thread runtime with paused time, eg.
+// #[tokio::test(start_paused = true)], so the result does not depend on real
+// time or scheduling
+#[derive(Debug)]
+pub struct Simulation<'d> {
+    devices: Vec<&'d dyn Device>,
+    shadow_device_ids: HashSet<DeviceId>,
+    maintenance: Option<(&'d Maintenance, HashSet<DeviceId>)>,
+    signals: Signals,
+}
+impl<'d> Simulation<'d> {
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::<&'d dyn Device>::new(),
+            shadow_device_ids: HashSet::<DeviceId>::new(),
+            maintenance: None,
+            signals: Signals::new(),
+        }
+    }
+
+    pub fn device_add<D: Device + SignalsDevice + 'd>(
+        &mut self,
+        device: &'d D,
+    ) -> DeviceHandle<'d, D> {
+        let device_id = (self.devices.len() + 1) as DeviceId; // starts from 1
+        self.devices.push(device);
+
+        DeviceHandle::<D>::new(device_id)
+    }
+
+    pub fn device_shadow_set<D: Device + SignalsDevice>(
+        &mut self,
+        device: DeviceHandle<'d, D>,
+    ) {
+        self.shadow_device_ids
+            .insert(device.into_erased().device_id());
+    }
+
+    // state targets of given devices are frozen while maintenance is active
+    pub fn maintenance_set(
+        &mut self,
+        maintenance: &'d Maintenance,
+        devices: &[DeviceHandleErased<'d>],
+    ) {
+        let device_ids = devices
+            .iter()
+            .map(|device| device.device_id())
+            .collect::<HashSet<_>>();
+        self.maintenance = Some((maintenance, device_ids));
+    }
+
+    pub fn signals(&mut self) -> &mut Signals {
+        &mut self.signals
+    }
+
+    // runs all devices until script completes
+    pub async fn run<S: Future<Output = ()>>(
+        self,
+        script: S,
+    ) -> Result<(), Error> {
+        let _tokio_time_guard = TokioTimeGuard::new();
+
+        let exchanger_devices = self
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(index, device)| {
+                let device_id = (index + 1) as DeviceId;
+                let signals_device_base =
+                    SignalsDeviceBaseRef::from_device_base(device.as_signals_device_base());
+                (device_id, signals_device_base)
+            })
+            .collect::<HashMap<_, _>>();
+        let exchanger = Exchanger::new(
+            &exchanger_devices,
+            self.signals.as_connections_requested(),
+            &self.shadow_device_ids,
+            self.maintenance
+                .as_ref()
+                .map(|(maintenance, device_ids)| (*maintenance, device_ids)),
+        )
+        .context("new")?;
+
+        let exit_flag_sender = async_flag::Sender::new();
+
+        let devices_runnable = join_all(
+            self.devices
+                .iter()
+                .map(|device| device.as_runnable().run(exit_flag_sender.receiver())),
+        );
+        let exchanger_runnable = Runnable::run(&exchanger, exit_flag_sender.receiver());
+        let script_runnable = async move {
+            script.await;
+            exit_flag_sender.signal();
+        };
+
+        join!(devices_runnable, exchanger_runnable, script_runnable);
+
+        Ok(())
+    }
+}
+
+// lets all woken devices and exchanger react, without advancing the clock
+pub async fn settle() {
+    const ROUNDS: usize = 16;
+
+    for _ in 0..ROUNDS {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock, Simulation};
+    use crate::devices::soft::time::pulse_a;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn pulse_a() {
+        let event_source = mock::EventSource::<()>::new();
+        let pulse = pulse_a::Device::new(pulse_a::Configuration {
+            duration: Duration::from_secs(2),
+        });
+        let state_recorder = mock::StateRecorder::<bool>::new();
+
+        let mut simulation = Simulation::new();
+        let event_source_handle = simulation.device_add(&event_source);
+        let pulse_handle = simulation.device_add(&pulse);
+        let state_recorder_handle = simulation.device_add(&state_recorder);
+        simulation.signals().d2d(
+            event_source_handle,
+            mock::EventSourceSignalIdentifier::Output,
+            pulse_handle,
+            pulse_a::SignalIdentifier::Input,
+        );
+        simulation.signals().d2d(
+            pulse_handle,
+            pulse_a::SignalIdentifier::Output,
+            state_recorder_handle,
+            mock::StateRecorderSignalIdentifier::Input,
+        );
+
+        simulation
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                event_source.push(());
+
+                // retriggered while on
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                event_source.push(());
+
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            })
+            .await
+            .unwrap();
+
+        state_recorder.trace().assert_entries(&[
+            (Duration::ZERO, Some(false)),
+            (Duration::from_secs(1), Some(true)),
+            (Duration::from_secs(4), Some(false)),
+        ]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{fmt, time::Duration};
+
+// values observed by recorder, with simulation time they were seen at
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trace<T> {
+    entries: Vec<(Duration, T)>,
+}
+impl<T> Trace<T> {
+    pub fn new(entries: Vec<(Duration, T)>) -> Self {
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[(Duration, T)] {
+        &self.entries
+    }
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    // last value seen at or before given time
+    pub fn value_at(
+        &self,
+        time: Duration,
+    ) -> Option<&T> {
+        self.entries
+            .iter()
+            .take_while(|(entry_time, _)| *entry_time <= time)
+            .last()
+            .map(|(_, value)| value)
+    }
+}
+impl<T> Trace<T>
+where
+    T: PartialEq + fmt::Debug,
+{
+    #[track_caller]
+    pub fn assert_values(
+        &self,
+        expected: &[T],
+    ) {
+        let values = self.values().collect::<Vec<_>>();
+        let expected = expected.iter().collect::<Vec<_>>();
+        assert_eq!(values, expected, "trace: {:?}", self.entries);
+    }
+
+    #[track_caller]
+    pub fn assert_entries(
+        &self,
+        expected: &[(Duration, T)],
+    ) {
+        assert_eq!(self.entries, expected);
+    }
+}
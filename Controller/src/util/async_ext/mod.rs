@@ -1,4 +1,5 @@
 pub mod optional;
+pub mod poll_budget;
 pub mod ready_chunks_dynamic;
 pub mod select_all_or_pending;
 pub mod stream_take_until_exhausted;
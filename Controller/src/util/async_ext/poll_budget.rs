@@ -0,0 +1,132 @@
+use crate::modules::metrics::Counter;
+use futures::future::Future;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+// accumulates number of polls and time spent inside poll() of a future
+#[derive(Debug)]
+pub struct PollBudget {
+    polls: Arc<Counter>,
+    poll_time_microseconds: Arc<Counter>,
+}
+impl PollBudget {
+    pub fn new(
+        polls: Arc<Counter>,
+        poll_time_microseconds: Arc<Counter>,
+    ) -> Self {
+        Self {
+            polls,
+            poll_time_microseconds,
+        }
+    }
+
+    pub fn polls(&self) -> u64 {
+        self.polls.get()
+    }
+    pub fn poll_time(&self) -> Duration {
+        Duration::from_micros(self.poll_time_microseconds.get())
+    }
+}
+
+#[derive(Debug)]
+pub struct PollBudgeted<'b, F>
+where
+    F: Future,
+{
+    future: F,
+    poll_budget: &'b PollBudget,
+}
+impl<'b, F> PollBudgeted<'b, F>
+where
+    F: Future,
+{
+    pub fn new(
+        future: F,
+        poll_budget: &'b PollBudget,
+    ) -> Self {
+        Self {
+            future,
+            poll_budget,
+        }
+    }
+}
+impl<'b, F> Future for PollBudgeted<'b, F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let self_ = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut self_.future) };
+
+        let start = Instant::now();
+        let result = future.poll(cx);
+        let poll_time = start.elapsed();
+
+        self_.poll_budget.polls.increment();
+        self_
+            .poll_budget
+            .poll_time_microseconds
+            .add(poll_time.as_micros() as u64);
+
+        result
+    }
+}
+
+pub trait PollBudgetedExt: Future {
+    fn poll_budgeted(
+        self,
+        poll_budget: &PollBudget,
+    ) -> PollBudgeted<'_, Self>
+    where
+        Self: Sized;
+}
+impl<F: Sized> PollBudgetedExt for F
+where
+    F: Future,
+{
+    fn poll_budgeted(
+        self,
+        poll_budget: &PollBudget,
+    ) -> PollBudgeted<'_, Self>
+    where
+        Self: Sized,
+    {
+        PollBudgeted::new(self, poll_budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PollBudget, PollBudgetedExt};
+    use crate::modules::metrics::Registry;
+    use maplit::btreemap;
+    use std::{thread, time::Duration};
+
+    #[tokio::test]
+    async fn counts_polls_and_time() {
+        let registry = Registry::new();
+        let poll_budget = PollBudget::new(
+            registry.counter("polls", "", btreemap! {}),
+            registry.counter("poll_time", "", btreemap! {}),
+        );
+
+        async {
+            tokio::task::yield_now().await;
+            thread::sleep(Duration::from_millis(5));
+        }
+        .poll_budgeted(&poll_budget)
+        .await;
+
+        assert_eq!(poll_budget.polls(), 2);
+        assert!(poll_budget.poll_time() >= Duration::from_millis(5));
+    }
+}
@@ -8,7 +8,7 @@ use std::{
     pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Weak,
     },
     task::{Context, Poll},
 };
@@ -17,9 +17,12 @@ use std::{
 struct Inner {
     signaled: AtomicBool,
     receivers: Mutex<HashSet<*const ReceiverInner>>,
+    children: Mutex<Vec<Weak<Inner>>>,
+    // keeps intermediate flags alive, so signal reaches all descendants
+    parent: Option<Arc<Inner>>,
 }
 impl Inner {
-    pub fn new() -> Self {
+    pub fn new(parent: Option<Arc<Inner>>) -> Self {
         let signaled = false;
         let signaled = AtomicBool::new(signaled);
 
@@ -27,17 +30,39 @@ impl Inner {
         let receivers = HashSet::<*const ReceiverInner>::new();
         let receivers = Mutex::new(receivers);
 
+        let children = Vec::<Weak<Inner>>::new();
+        let children = Mutex::new(children);
+
         Self {
             signaled,
             receivers,
+            children,
+            parent,
+        }
+    }
+
+    pub fn child(self: &Arc<Self>) -> Arc<Inner> {
+        let child = Inner::new(Some(self.clone()));
+        let child = Arc::new(child);
+
+        // lock before checking the flag, so signal() in progress does not miss the
+        // child
+        let mut children = self.children.lock();
+        if self.signaled.load(Ordering::Relaxed) {
+            child.signal();
+        } else {
+            children.retain(|child| child.strong_count() > 0);
+            children.push(Arc::downgrade(&child));
         }
+
+        child
     }
 
+    // child flags may be signaled both by their parent and by their own sender
     pub fn signal(&self) {
-        assert!(
-            !self.signaled.swap(true, Ordering::Relaxed),
-            "flag already signaled",
-        );
+        if self.signaled.swap(true, Ordering::Relaxed) {
+            return;
+        }
 
         self.receivers
             .lock()
@@ -47,6 +72,12 @@ impl Inner {
                 let receiver_inner = unsafe { &*receiver_inner_ptr };
                 receiver_inner.waker.wake();
             });
+
+        self.children
+            .lock()
+            .drain(..)
+            .filter_map(|child| child.upgrade())
+            .for_each(|child| child.signal());
     }
 }
 unsafe impl Send for Inner {}
@@ -58,7 +89,7 @@ pub struct Sender {
 }
 impl Sender {
     pub fn new() -> Self {
-        let inner = Inner::new();
+        let inner = Inner::new(None);
         let inner = Arc::new(inner);
 
         Self { inner }
@@ -66,6 +97,13 @@ impl Sender {
     pub fn receiver(&self) -> Receiver {
         Receiver::new(self.inner.clone())
     }
+    // creates flag signaled together with this one, but able to be signaled earlier
+    // on its own
+    pub fn child(&self) -> Sender {
+        let inner = self.inner.child();
+
+        Sender { inner }
+    }
 
     pub fn signal(self) {
         self.inner.signal();
@@ -100,6 +138,14 @@ impl Receiver {
             receiver_inner,
         }
     }
+
+    // creates flag signaled together with this one, but able to be signaled earlier
+    // on its own
+    pub fn child(&self) -> Sender {
+        let inner = self.receiver_inner.inner.child();
+
+        Sender { inner }
+    }
 }
 impl Clone for Receiver {
     fn clone(&self) -> Self {
@@ -253,3 +299,42 @@ impl<'s> Drop for LocalReceiver<'s> {
             .remove(&(&*self.receiver_inner as *const LocalReceiverInner)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::pair;
+    use futures::future::FutureExt;
+
+    #[test]
+    fn child_signaled_by_parent() {
+        let (sender, receiver) = pair();
+        let child_sender = sender.child();
+        let mut child_receiver = child_sender.receiver();
+        let mut grandchild_receiver = receiver.child().child().receiver();
+
+        assert_eq!((&mut child_receiver).now_or_never(), None);
+
+        sender.signal();
+        assert_eq!((&mut child_receiver).now_or_never(), Some(()));
+        assert_eq!((&mut grandchild_receiver).now_or_never(), Some(()));
+
+        // signaling already signaled child is allowed
+        child_sender.signal();
+    }
+
+    #[test]
+    fn child_signaled_independently() {
+        let (sender, mut receiver) = pair();
+        let child_sender = sender.child();
+        let mut child_receiver = child_sender.receiver();
+
+        child_sender.signal();
+        assert_eq!((&mut child_receiver).now_or_never(), Some(()));
+        assert_eq!((&mut receiver).now_or_never(), None);
+
+        // child created after parent was signaled is signaled immediately
+        sender.signal();
+        let mut late_child_receiver = receiver.child().receiver();
+        assert_eq!((&mut late_child_receiver).now_or_never(), Some(()));
+    }
+}
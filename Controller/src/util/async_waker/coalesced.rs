@@ -0,0 +1,128 @@
+use futures::{
+    future::Future,
+    stream::{FusedStream, Stream},
+};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{sleep, Sleep};
+
+// wraps waker receiver, merging bursts of wakes into single item
+// first wake is delivered immediately, following ones not earlier than
+// interval_min after it
+#[derive(Debug)]
+pub struct ReceiverCoalesced<R>
+where
+    R: Stream<Item = ()> + Unpin,
+{
+    receiver: R,
+    interval_min: Duration,
+
+    pending: bool,
+    delay: Option<Pin<Box<Sleep>>>,
+    terminated: bool,
+}
+impl<R> ReceiverCoalesced<R>
+where
+    R: Stream<Item = ()> + Unpin,
+{
+    pub fn new(
+        receiver: R,
+        interval_min: Duration,
+    ) -> Self {
+        Self {
+            receiver,
+            interval_min,
+
+            pending: false,
+            delay: None,
+            terminated: false,
+        }
+    }
+}
+impl<R> Stream for ReceiverCoalesced<R>
+where
+    R: Stream<Item = ()> + Unpin,
+{
+    type Item = ();
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let self_ = self.get_mut();
+
+        if self_.terminated {
+            return Poll::Ready(None);
+        }
+
+        // drain everything that arrived so far
+        let mut receiver_finished = false;
+        while let Poll::Ready(item) = Pin::new(&mut self_.receiver).poll_next(cx) {
+            match item {
+                Some(()) => self_.pending = true,
+                None => {
+                    receiver_finished = true;
+                    break;
+                }
+            }
+        }
+
+        if self_.pending {
+            // wait until minimum interval from last delivery passes
+            if let Some(delay) = &mut self_.delay {
+                if delay.as_mut().poll(cx).is_pending() && !receiver_finished {
+                    return Poll::Pending;
+                }
+            }
+
+            self_.pending = false;
+            self_.delay = Some(Box::pin(sleep(self_.interval_min)));
+            return Poll::Ready(Some(()));
+        }
+
+        if receiver_finished {
+            self_.terminated = true;
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
+impl<R> FusedStream for ReceiverCoalesced<R>
+where
+    R: Stream<Item = ()> + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::mpsc, ReceiverCoalesced};
+    use futures::{future::FutureExt, stream::StreamExt};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn burst() {
+        let signal = mpsc::Signal::new();
+        let mut receiver = ReceiverCoalesced::new(signal.receiver(), Duration::from_millis(50));
+
+        // first wake goes through immediately
+        signal.wake();
+        assert_eq!(receiver.next().now_or_never(), Some(Some(())));
+
+        // burst is held back until interval passes
+        for _ in 0..100 {
+            signal.wake();
+        }
+        assert_eq!(receiver.next().now_or_never(), None);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(receiver.next().now_or_never(), Some(Some(())));
+        assert_eq!(receiver.next().now_or_never(), None);
+    }
+}
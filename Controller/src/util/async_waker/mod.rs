@@ -1,3 +1,4 @@
+pub mod coalesced;
 pub mod mpmc;
 pub mod mpmc_static;
 pub mod mpsc;
@@ -1,3 +1,4 @@
+use super::coalesced::ReceiverCoalesced;
 use futures::{stream::FusedStream, task::AtomicWaker, Stream};
 use parking_lot::RwLock;
 use std::{
@@ -5,6 +6,7 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicBool, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
 #[derive(Debug)]
@@ -38,6 +40,12 @@ impl Signal {
     pub fn receiver(&self) -> Receiver {
         Receiver::new(self)
     }
+    pub fn receiver_coalesced(
+        &self,
+        interval_min: Duration,
+    ) -> ReceiverCoalesced<Receiver> {
+        ReceiverCoalesced::new(self.receiver(), interval_min)
+    }
 }
 impl Drop for Signal {
     fn drop(&mut self) {
@@ -1,3 +1,4 @@
+use super::coalesced::ReceiverCoalesced;
 use futures::{
     stream::{FusedStream, Stream},
     task::AtomicWaker,
@@ -7,6 +8,7 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicBool, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
 #[derive(Debug)]
@@ -45,6 +47,12 @@ impl Signal {
     pub fn receiver(&self) -> Receiver {
         Receiver::new(self)
     }
+    pub fn receiver_coalesced(
+        &self,
+        interval_min: Duration,
+    ) -> ReceiverCoalesced<Receiver> {
+        ReceiverCoalesced::new(self.receiver(), interval_min)
+    }
 }
 
 #[derive(Debug)]
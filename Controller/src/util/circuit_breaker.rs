@@ -0,0 +1,214 @@
+use crate::modules::metrics::{self, Counter, Gauge};
+use maplit::btreemap;
+use parking_lot::Mutex;
+use std::{cmp::min, sync::Arc, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    // consecutive failures after which circuit is opened
+    pub failures_threshold: usize,
+    // first open period, doubled after each failed probe
+    pub open_duration: Duration,
+    pub open_duration_max: Duration,
+}
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            failures_threshold: 3,
+            open_duration: Duration::from_secs(30),
+            open_duration_max: Duration::from_secs(60 * 10),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Closed { failures: usize },
+    Open { until: Instant },
+    HalfOpen,
+}
+impl State {
+    fn metric_value(&self) -> f64 {
+        match self {
+            State::Closed { .. } => 0.0,
+            State::HalfOpen => 1.0,
+            State::Open { .. } => 2.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: State,
+    open_duration: Duration,
+}
+
+// guards retry loops talking to remote peers
+// after configured number of consecutive failures peer is considered dead and
+// attempts are suspended for open period, then single probe is let through
+// only transitions are meant to be logged, so dead peer doesn't flood the logs
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    configuration: Configuration,
+
+    inner: Mutex<Inner>,
+
+    metric_state: Arc<Gauge>,
+    metric_failures: Arc<Counter>,
+    metric_opened: Arc<Counter>,
+}
+impl CircuitBreaker {
+    pub fn new(
+        name: String,
+        configuration: Configuration,
+    ) -> Self {
+        assert!(configuration.failures_threshold > 0);
+        assert!(configuration.open_duration <= configuration.open_duration_max);
+
+        let inner = Inner {
+            state: State::Closed { failures: 0 },
+            open_duration: configuration.open_duration,
+        };
+        let inner = Mutex::new(inner);
+
+        let registry = metrics::registry();
+        let labels = btreemap! { "name".to_owned() => name };
+        let metric_state = registry.gauge(
+            "circuit_breaker_state",
+            "0 if closed, 1 if half-open (probing), 2 if open",
+            labels.clone(),
+        );
+        let metric_failures = registry.counter(
+            "circuit_breaker_failures_total",
+            "Failures reported to circuit breaker",
+            labels.clone(),
+        );
+        let metric_opened = registry.counter(
+            "circuit_breaker_opened_total",
+            "Number of times circuit breaker was opened",
+            labels,
+        );
+
+        metric_state.set(State::Closed { failures: 0 }.metric_value());
+
+        Self {
+            configuration,
+
+            inner,
+
+            metric_state,
+            metric_failures,
+            metric_opened,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.inner.lock().state
+    }
+
+    fn state_set(
+        &self,
+        inner: &mut Inner,
+        state: State,
+    ) {
+        inner.state = state;
+        self.metric_state.set(state.metric_value());
+    }
+
+    // waits until next attempt is allowed
+    pub async fn ready(&self) {
+        let until = match self.inner.lock().state {
+            State::Open { until } => until,
+            State::Closed { .. } | State::HalfOpen => return,
+        };
+
+        tokio::time::sleep_until(until).await;
+
+        let mut inner = self.inner.lock();
+        if let State::Open { .. } = inner.state {
+            self.state_set(&mut inner, State::HalfOpen);
+        }
+    }
+
+    // returns true if circuit was closed by this call
+    pub fn success(&self) -> bool {
+        let mut inner = self.inner.lock();
+
+        let closed = inner.state == State::HalfOpen;
+        inner.open_duration = self.configuration.open_duration;
+        self.state_set(&mut inner, State::Closed { failures: 0 });
+
+        closed
+    }
+
+    // returns true if failure should be reported, that is if circuit was still
+    // closed or has just been opened
+    // failed probes are not reported
+    pub fn failure(&self) -> bool {
+        self.metric_failures.increment();
+
+        let mut inner = self.inner.lock();
+        match inner.state {
+            State::Closed { failures } => {
+                let failures = failures + 1;
+                if failures >= self.configuration.failures_threshold {
+                    let until = Instant::now() + inner.open_duration;
+                    self.state_set(&mut inner, State::Open { until });
+                    self.metric_opened.increment();
+                } else {
+                    self.state_set(&mut inner, State::Closed { failures });
+                }
+                true
+            }
+            State::HalfOpen => {
+                inner.open_duration = min(
+                    inner.open_duration * 2,
+                    self.configuration.open_duration_max,
+                );
+                let until = Instant::now() + inner.open_duration;
+                self.state_set(&mut inner, State::Open { until });
+                false
+            }
+            State::Open { .. } => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitBreaker, Configuration, State};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn opens_and_probes() {
+        let circuit_breaker = CircuitBreaker::new(
+            "tests::opens_and_probes".to_owned(),
+            Configuration {
+                failures_threshold: 2,
+                open_duration: Duration::from_secs(10),
+                open_duration_max: Duration::from_secs(15),
+            },
+        );
+
+        assert!(circuit_breaker.failure());
+        assert!(circuit_breaker.failure());
+        assert!(matches!(circuit_breaker.state(), State::Open { .. }));
+
+        // suspended for open duration, then probe is allowed
+        let start = Instant::now();
+        circuit_breaker.ready().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+        assert_eq!(circuit_breaker.state(), State::HalfOpen);
+
+        // failed probe is not reported, open duration grows up to max
+        assert!(!circuit_breaker.failure());
+        let start = Instant::now();
+        circuit_breaker.ready().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(15));
+
+        assert!(circuit_breaker.success());
+        assert_eq!(circuit_breaker.state(), State::Closed { failures: 0 });
+    }
+}
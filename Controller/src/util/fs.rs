@@ -1,4 +1,5 @@
 use anyhow::{ensure, Context, Error};
+use serde::Serialize;
 use std::path::Path;
 use tokio::fs;
 
@@ -50,3 +51,58 @@ pub async fn remove_all_dir_empty(
 
     Ok(())
 }
+
+// total size of all files in directory (recursive)
+pub async fn directory_size(root: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let mut read_dir = fs::read_dir(&directory).await.context("read_dir")?;
+        while let Some(entry) = read_dir.next_entry().await.context("next_entry")? {
+            let metadata = entry.metadata().await.context("metadata")?;
+            if metadata.is_dir() {
+                directories.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+
+    Ok(size)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct DiskSpace {
+    pub total: u64,
+    pub available: u64,
+}
+
+#[cfg(target_os = "linux")]
+#[allow(clippy::unnecessary_cast)] // statvfs field types are platform dependent
+pub fn disk_space(path: &Path) -> Result<DiskSpace, Error> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).context("path")?;
+
+    let mut statvfs = MaybeUninit::<libc::statvfs>::uninit();
+    let result = unsafe { libc::statvfs(path.as_ptr(), statvfs.as_mut_ptr()) };
+    ensure!(
+        result == 0,
+        "statvfs failed: {}",
+        std::io::Error::last_os_error()
+    );
+    let statvfs = unsafe { statvfs.assume_init() };
+
+    let fragment_size = statvfs.f_frsize as u64;
+    let disk_space = DiskSpace {
+        total: statvfs.f_blocks as u64 * fragment_size,
+        available: statvfs.f_bavail as u64 * fragment_size,
+    };
+
+    Ok(disk_space)
+}
+#[cfg(not(target_os = "linux"))]
+pub fn disk_space(_path: &Path) -> Result<DiskSpace, Error> {
+    anyhow::bail!("disk_space is not supported on this platform");
+}
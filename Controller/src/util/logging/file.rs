@@ -0,0 +1,166 @@
+use super::{record_fields, Output};
+use anyhow::{Context, Error};
+use chrono::Utc;
+use parking_lot::Mutex;
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub path: PathBuf,
+    // rotate when file grows over this size
+    pub rotation_size: Option<u64>,
+    // rotate when file is older than this
+    pub rotation_interval: Option<Duration>,
+    // number of rotated files kept (path.1, path.2, ...)
+    pub retained: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    writer: BufWriter<fs::File>,
+    size: u64,
+    opened: Instant,
+}
+
+#[derive(Debug)]
+pub struct File {
+    configuration: Configuration,
+
+    state: Mutex<State>,
+}
+impl File {
+    pub fn new(configuration: Configuration) -> Result<Self, Error> {
+        if let Some(parent) = configuration.path.parent() {
+            fs::create_dir_all(parent).context("create_dir_all")?;
+        }
+
+        let state = Self::open(&configuration.path).context("open")?;
+        let state = Mutex::new(state);
+
+        Ok(Self {
+            configuration,
+
+            state,
+        })
+    }
+
+    fn open(path: &Path) -> Result<State, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("open")?;
+        let size = file.metadata().context("metadata")?.len();
+
+        let writer = BufWriter::new(file);
+        let opened = Instant::now();
+
+        Ok(State {
+            writer,
+            size,
+            opened,
+        })
+    }
+
+    fn rotated_path(
+        &self,
+        index: usize,
+    ) -> PathBuf {
+        let mut path = self.configuration.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+    fn rotation_required(
+        &self,
+        state: &State,
+    ) -> bool {
+        let size_exceeded = self
+            .configuration
+            .rotation_size
+            .is_some_and(|rotation_size| state.size >= rotation_size);
+        let interval_exceeded = self
+            .configuration
+            .rotation_interval
+            .is_some_and(|rotation_interval| state.opened.elapsed() >= rotation_interval);
+
+        size_exceeded || interval_exceeded
+    }
+    fn rotate(
+        &self,
+        state: &mut State,
+    ) -> Result<(), Error> {
+        state.writer.flush().context("flush")?;
+
+        if self.configuration.retained == 0 {
+            fs::remove_file(&self.configuration.path).context("remove_file")?;
+        } else {
+            // path.(n-1) -> path.n, ..., path -> path.1, oldest one is overwritten
+            for index in (1..self.configuration.retained).rev() {
+                let source = self.rotated_path(index);
+                if source.exists() {
+                    fs::rename(&source, self.rotated_path(index + 1)).context("rename")?;
+                }
+            }
+            fs::rename(&self.configuration.path, self.rotated_path(1)).context("rename")?;
+        }
+
+        *state = Self::open(&self.configuration.path).context("open")?;
+
+        Ok(())
+    }
+
+    fn write_record(
+        &self,
+        record: &log::Record<'_>,
+    ) -> Result<(), Error> {
+        let mut line = format!(
+            "{} {:<5} {}: {}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        for (key, value) in record_fields(record) {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        line.push('\n');
+
+        let mut state = self.state.lock();
+
+        if self.rotation_required(&state) {
+            self.rotate(&mut state).context("rotate")?;
+        }
+
+        state
+            .writer
+            .write_all(line.as_bytes())
+            .context("write_all")?;
+        state.size += line.len() as u64;
+
+        // warnings and errors are important enough to hit the disk immediately
+        if record.level() <= log::Level::Warn {
+            state.writer.flush().context("flush")?;
+        }
+
+        Ok(())
+    }
+}
+impl Output for File {
+    fn write(
+        &self,
+        record: &log::Record<'_>,
+    ) {
+        if let Err(error) = self.write_record(record) {
+            eprintln!("logging: file: {:?}", error);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self.state.lock().writer.flush();
+    }
+}
@@ -0,0 +1,88 @@
+use super::{record_fields, Output};
+use anyhow::{Context, Error};
+use std::os::unix::net::UnixDatagram;
+
+// https://systemd.io/JOURNAL_NATIVE_PROTOCOL/
+#[derive(Debug)]
+pub struct Journald {
+    socket: UnixDatagram,
+}
+impl Journald {
+    const SOCKET_PATH: &'static str = "/run/systemd/journal/socket";
+
+    pub fn new() -> Result<Self, Error> {
+        let socket = UnixDatagram::unbound().context("unbound")?;
+        socket.connect(Self::SOCKET_PATH).context("connect")?;
+
+        Ok(Self { socket })
+    }
+
+    fn priority(level: log::Level) -> u8 {
+        match level {
+            log::Level::Error => 3,
+            log::Level::Warn => 4,
+            log::Level::Info => 6,
+            log::Level::Debug | log::Level::Trace => 7,
+        }
+    }
+    // journald field names are uppercase letters, digits and underscores
+    fn field_name(key: &str) -> String {
+        key.chars()
+            .map(|character| {
+                if character.is_ascii_alphanumeric() {
+                    character.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+    fn field_append(
+        buffer: &mut Vec<u8>,
+        name: &str,
+        value: &str,
+    ) {
+        buffer.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            // multiline values use length prefixed binary form
+            buffer.push(b'\n');
+            buffer.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            buffer.push(b'=');
+        }
+        buffer.extend_from_slice(value.as_bytes());
+        buffer.push(b'\n');
+    }
+}
+impl Output for Journald {
+    fn write(
+        &self,
+        record: &log::Record<'_>,
+    ) {
+        let mut buffer = Vec::<u8>::new();
+
+        Self::field_append(
+            &mut buffer,
+            "PRIORITY",
+            &Self::priority(record.level()).to_string(),
+        );
+        Self::field_append(&mut buffer, "SYSLOG_IDENTIFIER", "logicblocks");
+        Self::field_append(&mut buffer, "MESSAGE", &record.args().to_string());
+        Self::field_append(&mut buffer, "MODULE", record.target());
+        if let Some(file) = record.file() {
+            Self::field_append(&mut buffer, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            Self::field_append(&mut buffer, "CODE_LINE", &line.to_string());
+        }
+        for (key, value) in record_fields(record) {
+            Self::field_append(&mut buffer, &Self::field_name(&key), &value);
+        }
+
+        if let Err(error) = self.socket.send(&buffer) {
+            eprintln!("logging: journald: {:?}", error);
+        }
+    }
+
+    fn flush(&self) {}
+}
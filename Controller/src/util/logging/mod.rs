@@ -0,0 +1,303 @@
+pub mod file;
+#[cfg(unix)]
+pub mod journald;
+#[cfg(unix)]
+pub mod syslog;
+
+use crate::web::{self, uri_cursor};
+use anyhow::{anyhow, ensure, Context, Error};
+use futures::future::{BoxFuture, FutureExt};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{env, fmt};
+
+// additional destination for log records, besides stderr
+pub trait Output: Send + Sync + fmt::Debug {
+    fn write(
+        &self,
+        record: &log::Record<'_>,
+    );
+    fn flush(&self);
+}
+
+#[derive(Default, Debug)]
+pub struct Configuration {
+    pub file: Option<file::Configuration>,
+    pub journald: bool,
+    pub syslog: bool,
+}
+
+#[derive(Debug)]
+struct Stderr {
+    filters: String,
+    logger: env_logger::Logger,
+}
+impl Stderr {
+    // filters use RUST_LOG syntax, eg.
+    // "info,logicblocks_controller::devices::houseblocks=trace"
+    fn new(filters: String) -> Self {
+        let logger = env_logger::Builder::new().parse_filters(&filters).build();
+
+        Self { filters, logger }
+    }
+}
+
+#[derive(Debug)]
+struct Logger {
+    // replaced as a whole when filters are changed at runtime
+    stderr: RwLock<Stderr>,
+    outputs: Box<[Box<dyn Output>]>,
+}
+impl log::Log for Logger {
+    fn enabled(
+        &self,
+        metadata: &log::Metadata<'_>,
+    ) -> bool {
+        self.stderr.read().logger.enabled(metadata)
+    }
+
+    fn log(
+        &self,
+        record: &log::Record<'_>,
+    ) {
+        let stderr = self.stderr.read();
+        if !stderr.logger.matches(record) {
+            return;
+        }
+
+        stderr.logger.log(record);
+        drop(stderr);
+
+        for output in self.outputs.iter() {
+            output.write(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.read().logger.flush();
+        for output in self.outputs.iter() {
+            output.flush();
+        }
+    }
+}
+
+static LOGGER: OnceCell<&'static Logger> = OnceCell::new();
+
+// structured fields attached to records, eg. log::error!(device_id = 5; "...")
+fn record_fields(record: &log::Record<'_>) -> Vec<(String, String)> {
+    struct Visitor(Vec<(String, String)>);
+    impl<'k> log::kv::VisitSource<'k> for Visitor {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'k>,
+            value: log::kv::Value<'k>,
+        ) -> Result<(), log::kv::Error> {
+            self.0.push((key.to_string(), value.to_string()));
+            Ok(())
+        }
+    }
+
+    let mut visitor = Visitor(Vec::new());
+    let _ = record.key_values().visit(&mut visitor);
+    visitor.0
+}
+
+pub fn configure(
+    root_module: &str,
+    tracing: bool,
+) {
+    configure_with_outputs(root_module, tracing, Configuration::default());
+}
+pub fn configure_with_outputs(
+    root_module: &str,
+    tracing: bool,
+    configuration: Configuration,
+) {
+    let level = if tracing {
+        log::LevelFilter::Trace
+    } else {
+        log::LevelFilter::Debug
+    };
+
+    // RUST_LOG goes last, so it can override defaults
+    let mut filters = format!(
+        "{},logicblocks_controller={},{}={}",
+        log::LevelFilter::Info,
+        level,
+        root_module,
+        level
+    );
+    if let Ok(filters_env) = env::var("RUST_LOG") {
+        filters.push(',');
+        filters.push_str(&filters_env);
+    }
+    let stderr = Stderr::new(filters);
+
+    // failing outputs are reported and skipped, stderr is always there
+    let mut outputs = Vec::<Box<dyn Output>>::new();
+    if let Some(file_configuration) = configuration.file {
+        match file::File::new(file_configuration) {
+            Ok(file) => outputs.push(Box::new(file)),
+            Err(error) => eprintln!("logging: file: {:?}", error),
+        }
+    }
+    #[cfg(unix)]
+    if configuration.journald {
+        match journald::Journald::new() {
+            Ok(journald) => outputs.push(Box::new(journald)),
+            Err(error) => eprintln!("logging: journald: {:?}", error),
+        }
+    }
+    #[cfg(unix)]
+    if configuration.syslog {
+        match syslog::Syslog::new() {
+            Ok(syslog) => outputs.push(Box::new(syslog)),
+            Err(error) => eprintln!("logging: syslog: {:?}", error),
+        }
+    }
+    #[cfg(not(unix))]
+    if configuration.journald || configuration.syslog {
+        eprintln!("logging: journald and syslog are supported only on unix");
+    }
+    let outputs = outputs.into_boxed_slice();
+
+    let max_level = stderr.logger.filter();
+    let stderr = RwLock::new(stderr);
+    let logger = Logger { stderr, outputs };
+    let logger: &'static Logger = Box::leak(Box::new(logger));
+
+    log::set_logger(logger).unwrap();
+    log::set_max_level(max_level);
+    LOGGER.set(logger).unwrap();
+}
+
+// env_logger ignores invalid directives (only printing warning to stderr), so
+// they are checked here, to reject typos instead of silently changing nothing
+// syntax is "directive,directive,.../regex", where directive is one of "level",
+// "module::path" or "module::path=level"
+fn filters_validate(filters: &str) -> Result<(), Error> {
+    let (directives, regex) = match filters.split_once('/') {
+        Some((directives, regex)) => (directives, Some(regex)),
+        None => (filters, None),
+    };
+
+    for directive in directives
+        .split(',')
+        .map(|directive| directive.trim())
+        .filter(|directive| !directive.is_empty())
+    {
+        let (module, level) = match directive.split_once('=') {
+            Some((module, level)) => (Some(module.trim()), Some(level.trim())),
+            None => match directive.parse::<log::LevelFilter>() {
+                Ok(_) => (None, None),
+                Err(_) => (Some(directive), None),
+            },
+        };
+
+        if let Some(module) = module {
+            ensure!(
+                module.split("::").all(|item| !item.is_empty()
+                    && item
+                        .chars()
+                        .all(|character| character.is_ascii_alphanumeric() || character == '_')),
+                "invalid module path {:?} in directive {:?}",
+                module,
+                directive
+            );
+        }
+        if let Some(level) = level {
+            level
+                .parse::<log::LevelFilter>()
+                .map_err(|_| anyhow!("invalid level {:?} in directive {:?}", level, directive))?;
+        }
+    }
+
+    if let Some(regex) = regex {
+        regex::Regex::new(regex).context("regex")?;
+    }
+
+    Ok(())
+}
+
+// returns None if logging was not configured by this module
+pub fn filters() -> Option<String> {
+    let logger = LOGGER.get()?;
+    let filters = logger.stderr.read().filters.clone();
+    Some(filters)
+}
+pub fn filters_set(filters: String) -> Result<(), Error> {
+    filters_validate(&filters).context("filters_validate")?;
+
+    let logger = LOGGER.get().context("logging not configured")?;
+
+    log::info!("changing log filters to {:?}", filters);
+
+    let stderr = Stderr::new(filters);
+    let max_level = stderr.logger.filter();
+    *logger.stderr.write() = stderr;
+    log::set_max_level(max_level);
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Handler;
+impl uri_cursor::Handler for Handler {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Next("filters", uri_cursor) => match uri_cursor.as_ref() {
+                uri_cursor::UriCursor::Terminal => match *request.method() {
+                    http::Method::GET => {
+                        #[derive(Serialize)]
+                        struct Response {
+                            filters: Option<String>,
+                        }
+                        let response = Response { filters: filters() };
+                        async { web::Response::ok_json(response) }.boxed()
+                    }
+                    http::Method::POST => {
+                        let filters = match request.body_parse_json::<String>() {
+                            Ok(filters) => filters,
+                            Err(error) => {
+                                return async { web::Response::error_400_from_error(error) }.boxed()
+                            }
+                        };
+                        let response = match filters_set(filters) {
+                            Ok(()) => web::Response::ok_empty(),
+                            Err(error) => web::Response::error_400_from_error(error),
+                        };
+                        async { response }.boxed()
+                    }
+                    _ => async { web::Response::error_405() }.boxed(),
+                },
+                _ => async { web::Response::error_404() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filters_validate;
+
+    #[test]
+    fn filters_validate_1() {
+        filters_validate("").unwrap();
+        filters_validate("info").unwrap();
+        filters_validate("INFO,logicblocks_controller::devices::houseblocks=trace").unwrap();
+        filters_validate("warn,hyper, tokio_util = off").unwrap();
+        filters_validate("debug/frame \\d+").unwrap();
+
+        assert!(filters_validate("logicblocks_controller=verbose").is_err());
+        assert!(filters_validate("logicblocks controller=info").is_err());
+        assert!(filters_validate("logicblocks_controller:::devices=info").is_err());
+        assert!(filters_validate("=info").is_err());
+        assert!(filters_validate("info/(").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use super::{record_fields, Output};
+use anyhow::{Context, Error};
+use std::{os::unix::net::UnixDatagram, process};
+
+// RFC 3164 messages sent to local syslog daemon
+#[derive(Debug)]
+pub struct Syslog {
+    socket: UnixDatagram,
+}
+impl Syslog {
+    const SOCKET_PATH: &'static str = "/dev/log";
+    const FACILITY_DAEMON: u8 = 3;
+
+    pub fn new() -> Result<Self, Error> {
+        let socket = UnixDatagram::unbound().context("unbound")?;
+        socket.connect(Self::SOCKET_PATH).context("connect")?;
+
+        Ok(Self { socket })
+    }
+
+    fn severity(level: log::Level) -> u8 {
+        match level {
+            log::Level::Error => 3,
+            log::Level::Warn => 4,
+            log::Level::Info => 6,
+            log::Level::Debug | log::Level::Trace => 7,
+        }
+    }
+}
+impl Output for Syslog {
+    fn write(
+        &self,
+        record: &log::Record<'_>,
+    ) {
+        let mut message = format!(
+            "<{}>logicblocks[{}]: {}: {}",
+            Self::FACILITY_DAEMON * 8 + Self::severity(record.level()),
+            process::id(),
+            record.target(),
+            record.args()
+        );
+        for (key, value) in record_fields(record) {
+            message.push_str(&format!(" {}={}", key, value));
+        }
+
+        if let Err(error) = self.socket.send(message.as_bytes()) {
+            eprintln!("logging: syslog: {:?}", error);
+        }
+    }
+
+    fn flush(&self) {}
+}
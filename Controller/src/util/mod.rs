@@ -3,9 +3,13 @@ pub mod async_barrier;
 pub mod async_ext;
 pub mod async_flag;
 pub mod async_waker;
+pub mod circuit_breaker;
 pub mod drop_guard;
 pub mod fs;
 pub mod logging;
 pub mod observable;
 pub mod runnable;
 pub mod runtime;
+#[cfg(unix)]
+pub mod systemd;
+pub mod timer_wheel;
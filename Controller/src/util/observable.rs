@@ -1,6 +1,6 @@
 use futures::{
-    future::{FusedFuture, Future},
-    stream::{FusedStream, Stream},
+    future::{self, FusedFuture, Future},
+    stream::{self, BoxStream, FusedStream, Stream, StreamExt},
     task::AtomicWaker,
 };
 use parking_lot::RwLock;
@@ -8,7 +8,9 @@ use std::{
     collections::HashSet,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
+use tokio::time::{sleep, Sleep};
 
 #[derive(Debug)]
 struct Inner<T>
@@ -643,3 +645,324 @@ where
         assert!(removed);
     }
 }
+
+// Combinators
+/// Common interface of observable values, both stored ([`Value`],
+/// [`Getter`]) and derived ([`Map`], [`Combine`]).
+///
+/// Derived observables don't store anything and don't need any background
+/// task - value is calculated on [`Observable::get`] and change notifications
+/// are forwarded from underlying observables.
+pub trait Observable: Send + Sync {
+    type Value: Clone + Eq + Send + Sync;
+
+    /// Returns current value
+    fn get(&self) -> Self::Value;
+    /// Returns stream yielding () when value may have changed
+    fn changed_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, ()>;
+
+    /// Returns stream yielding value when it is changed
+    fn value_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, Self::Value> {
+        let mut last_seen_value = if !initially_pending {
+            Some(self.get())
+        } else {
+            None
+        };
+
+        self.changed_stream_boxed(initially_pending)
+            .filter_map(move |()| {
+                let value = self.get();
+                let value = if last_seen_value.as_ref() != Some(&value) {
+                    last_seen_value.replace(value.clone());
+                    Some(value)
+                } else {
+                    None
+                };
+                future::ready(value)
+            })
+            .boxed()
+    }
+
+    /// Returns observable with value transformed by `mapper`
+    fn map<U, F>(
+        self,
+        mapper: F,
+    ) -> Map<Self, F>
+    where
+        Self: Sized,
+        U: Clone + Eq + Send + Sync,
+        F: Fn(Self::Value) -> U + Send + Sync,
+    {
+        Map::new(self, mapper)
+    }
+    /// Returns observable with tuple of latest values of `self` and `other`
+    fn combine<O>(
+        self,
+        other: O,
+    ) -> Combine<Self, O>
+    where
+        Self: Sized,
+        O: Observable,
+    {
+        Combine::new(self, other)
+    }
+}
+impl<T> Observable for Value<T>
+where
+    T: Clone + Eq + Send + Sync,
+{
+    type Value = T;
+
+    fn get(&self) -> Self::Value {
+        self.get()
+    }
+    fn changed_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, ()> {
+        self.changed_stream(initially_pending).boxed()
+    }
+}
+impl<'v, T> Observable for Getter<'v, T>
+where
+    T: Clone + Eq + Send + Sync,
+{
+    type Value = T;
+
+    fn get(&self) -> Self::Value {
+        self.get()
+    }
+    fn changed_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, ()> {
+        self.changed_stream(initially_pending).boxed()
+    }
+}
+
+/// Observable transforming value of another one. See [`Observable::map`].
+#[derive(Debug)]
+pub struct Map<O, F> {
+    observable: O,
+    mapper: F,
+}
+impl<O, F> Map<O, F> {
+    fn new(
+        observable: O,
+        mapper: F,
+    ) -> Self {
+        Self { observable, mapper }
+    }
+}
+impl<O, U, F> Observable for Map<O, F>
+where
+    O: Observable,
+    U: Clone + Eq + Send + Sync,
+    F: Fn(O::Value) -> U + Send + Sync,
+{
+    type Value = U;
+
+    fn get(&self) -> Self::Value {
+        (self.mapper)(self.observable.get())
+    }
+    fn changed_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, ()> {
+        self.observable.changed_stream_boxed(initially_pending)
+    }
+}
+
+/// Observable with latest values of two others. See [`Observable::combine`].
+#[derive(Debug)]
+pub struct Combine<A, B> {
+    a: A,
+    b: B,
+}
+impl<A, B> Combine<A, B> {
+    fn new(
+        a: A,
+        b: B,
+    ) -> Self {
+        Self { a, b }
+    }
+}
+impl<A, B> Observable for Combine<A, B>
+where
+    A: Observable,
+    B: Observable,
+{
+    type Value = (A::Value, B::Value);
+
+    fn get(&self) -> Self::Value {
+        (self.a.get(), self.b.get())
+    }
+    fn changed_stream_boxed(
+        &self,
+        initially_pending: bool,
+    ) -> BoxStream<'_, ()> {
+        stream::select(
+            self.a.changed_stream_boxed(initially_pending),
+            self.b.changed_stream_boxed(initially_pending),
+        )
+        .boxed()
+    }
+}
+
+// Debounce
+/// Stream yielding the most recent item of underlying stream, only after no new
+/// items arrived for given duration. Useful with
+/// [`Observable::value_stream_boxed`] to skip intermediate values of quickly
+/// changing observables.
+#[derive(Debug)]
+pub struct Debounce<S>
+where
+    S: Stream + Unpin,
+{
+    stream: S,
+    duration: Duration,
+
+    pending: Option<S::Item>,
+    delay: Option<Pin<Box<Sleep>>>,
+    terminated: bool,
+}
+impl<S> Debounce<S>
+where
+    S: Stream + Unpin,
+{
+    pub fn new(
+        stream: S,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            stream,
+            duration,
+
+            pending: None,
+            delay: None,
+            terminated: false,
+        }
+    }
+}
+impl<S> Stream for Debounce<S>
+where
+    S: Stream + Unpin,
+    S::Item: Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let self_ = self.get_mut();
+
+        if self_.terminated {
+            return Poll::Ready(None);
+        }
+
+        // every new item restarts the quiet period
+        let mut stream_finished = false;
+        while let Poll::Ready(item) = Pin::new(&mut self_.stream).poll_next(cx) {
+            match item {
+                Some(item) => {
+                    self_.pending.replace(item);
+                    self_.delay = Some(Box::pin(sleep(self_.duration)));
+                }
+                None => {
+                    stream_finished = true;
+                    break;
+                }
+            }
+        }
+
+        if stream_finished {
+            // flush whatever is pending, then terminate
+            self_.delay = None;
+            return match self_.pending.take() {
+                Some(item) => Poll::Ready(Some(item)),
+                None => {
+                    self_.terminated = true;
+                    Poll::Ready(None)
+                }
+            };
+        }
+
+        if let Some(delay) = &mut self_.delay {
+            if delay.as_mut().poll(cx).is_ready() {
+                self_.delay = None;
+                if let Some(item) = self_.pending.take() {
+                    return Poll::Ready(Some(item));
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+impl<S> FusedStream for Debounce<S>
+where
+    S: Stream + Unpin,
+    S::Item: Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+pub trait DebounceExt: Stream + Unpin + Sized {
+    fn debounce(
+        self,
+        duration: Duration,
+    ) -> Debounce<Self> {
+        Debounce::new(self, duration)
+    }
+}
+impl<S> DebounceExt for S where S: Stream + Unpin {}
+
+#[cfg(test)]
+mod tests_combinators {
+    use super::{DebounceExt, Observable, Value};
+    use futures::{future::FutureExt, stream::StreamExt};
+    use std::time::Duration;
+
+    #[test]
+    fn map_combine() {
+        let a = Value::new(1);
+        let b = Value::new(false);
+
+        let combined = a.getter().map(|a| a * 2).combine(b.getter());
+        assert_eq!(combined.get(), (2, false));
+
+        let mut value_stream = combined.value_stream_boxed(false);
+        assert_eq!(value_stream.next().now_or_never(), None);
+
+        a.set(2);
+        assert_eq!(value_stream.next().now_or_never(), Some(Some((4, false))));
+        b.set(true);
+        assert_eq!(value_stream.next().now_or_never(), Some(Some((4, true))));
+        assert_eq!(value_stream.next().now_or_never(), None);
+    }
+
+    #[tokio::test]
+    async fn debounce() {
+        let value = Value::new(0);
+        let mut value_stream = value
+            .value_stream_boxed(false)
+            .debounce(Duration::from_millis(50));
+
+        for index in 1..=10 {
+            value.set(index);
+            assert_eq!(value_stream.next().now_or_never(), None);
+        }
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(value_stream.next().now_or_never(), Some(Some(10)));
+    }
+}
@@ -1,5 +1,18 @@
 use super::async_flag;
+use crate::web::{self, uri_cursor};
 use async_trait::async_trait;
+use futures::{
+    future::{join_all, BoxFuture, FutureExt},
+    select,
+};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    any::Any,
+    fmt,
+    panic::AssertUnwindSafe,
+    time::{Duration, Instant},
+};
 
 #[async_trait]
 pub trait Runnable: Send + Sync {
@@ -11,3 +24,286 @@ pub trait Runnable: Send + Sync {
 
 #[derive(Debug)]
 pub struct Exited;
+
+#[derive(Debug)]
+pub struct SupervisorConfiguration {
+    pub restart_delay_min: Duration,
+    pub restart_delay_max: Duration,
+    // runnable working longer than this is considered healthy and backoff is reset
+    pub stable_duration: Duration,
+}
+impl Default for SupervisorConfiguration {
+    fn default() -> Self {
+        Self {
+            restart_delay_min: Duration::from_secs(1),
+            restart_delay_max: Duration::from_secs(60 * 5),
+            stable_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum SupervisorRunnableState {
+    Running,
+    Backoff,
+    Exited,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SupervisorRunnableStatus {
+    pub name: String,
+    pub state: SupervisorRunnableState,
+    pub restarts: usize,
+    pub failure_last: Option<String>,
+}
+
+pub struct Supervisor<'r> {
+    configuration: SupervisorConfiguration,
+    runnables: Box<[(String, &'r dyn Runnable)]>,
+
+    statuses: Mutex<Box<[SupervisorRunnableStatus]>>,
+}
+impl<'r> Supervisor<'r> {
+    pub fn new(
+        configuration: SupervisorConfiguration,
+        runnables: Box<[(String, &'r dyn Runnable)]>,
+    ) -> Self {
+        assert!(configuration.restart_delay_min <= configuration.restart_delay_max);
+
+        let statuses = runnables
+            .iter()
+            .map(|(name, _)| SupervisorRunnableStatus {
+                name: name.clone(),
+                state: SupervisorRunnableState::Exited,
+                restarts: 0,
+                failure_last: None,
+            })
+            .collect::<Box<[_]>>();
+        let statuses = Mutex::new(statuses);
+
+        Self {
+            configuration,
+            runnables,
+
+            statuses,
+        }
+    }
+
+    pub fn statuses(&self) -> Box<[SupervisorRunnableStatus]> {
+        self.statuses.lock().clone()
+    }
+
+    fn restart_delay(
+        configuration: &SupervisorConfiguration,
+        failures: u32,
+    ) -> Duration {
+        configuration
+            .restart_delay_min
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(configuration.restart_delay_max)
+    }
+    fn panic_message(payload: &(dyn Any + Send)) -> String {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            (*message).to_owned()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_owned()
+        }
+    }
+
+    async fn run_runnable(
+        &self,
+        index: usize,
+        mut exit_flag: async_flag::Receiver,
+    ) {
+        let (name, runnable) = &self.runnables[index];
+
+        let mut failures = 0u32;
+        loop {
+            self.statuses.lock()[index].state = SupervisorRunnableState::Running;
+
+            let started = Instant::now();
+            let result = AssertUnwindSafe(runnable.run(exit_flag.clone()))
+                .catch_unwind()
+                .await;
+
+            // runnables are expected to return only after exit flag was signaled
+            let failure = match result {
+                Ok(Exited) if (&mut exit_flag).now_or_never().is_some() => break,
+                Ok(Exited) => "exited before exit flag was signaled".to_owned(),
+                Err(payload) => format!("panicked: {}", Self::panic_message(&*payload)),
+            };
+            log::error!("{}: {}: {}", self, name, failure);
+
+            if started.elapsed() >= self.configuration.stable_duration {
+                failures = 0;
+            }
+            failures = failures.saturating_add(1);
+
+            {
+                let mut statuses = self.statuses.lock();
+                let status = &mut statuses[index];
+                status.state = SupervisorRunnableState::Backoff;
+                status.restarts += 1;
+                status.failure_last = Some(failure);
+            }
+
+            let delay = Self::restart_delay(&self.configuration, failures);
+            select! {
+                () = tokio::time::sleep(delay).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+
+        self.statuses.lock()[index].state = SupervisorRunnableState::Exited;
+    }
+
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        join_all(
+            (0..self.runnables.len()).map(|index| self.run_runnable(index, exit_flag.clone())),
+        )
+        .await;
+
+        Exited
+    }
+}
+#[async_trait]
+impl<'r> Runnable for Supervisor<'r> {
+    async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        self.run(exit_flag).await
+    }
+}
+impl<'r> uri_cursor::Handler for Supervisor<'r> {
+    fn handle(
+        &self,
+        request: web::Request,
+        uri_cursor: &uri_cursor::UriCursor,
+    ) -> BoxFuture<'static, web::Response> {
+        match uri_cursor {
+            uri_cursor::UriCursor::Terminal => match *request.method() {
+                http::Method::GET => {
+                    let statuses = self.statuses();
+                    async { web::Response::ok_json(statuses) }.boxed()
+                }
+                _ => async { web::Response::error_405() }.boxed(),
+            },
+            _ => async { web::Response::error_404() }.boxed(),
+        }
+    }
+}
+impl<'r> fmt::Debug for Supervisor<'r> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("configuration", &self.configuration)
+            .field("statuses", &self.statuses)
+            .finish()
+    }
+}
+impl<'r> fmt::Display for Supervisor<'r> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "Supervisor")
+    }
+}
+
+#[cfg(test)]
+mod tests_supervisor {
+    use super::{
+        super::async_flag, Exited, Runnable, Supervisor, SupervisorConfiguration,
+        SupervisorRunnableState,
+    };
+    use async_trait::async_trait;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    #[derive(Debug)]
+    struct Panicking {
+        panics: usize,
+        runs: AtomicUsize,
+    }
+    #[async_trait]
+    impl Runnable for Panicking {
+        async fn run(
+            &self,
+            exit_flag: async_flag::Receiver,
+        ) -> Exited {
+            if self.runs.fetch_add(1, Ordering::Relaxed) < self.panics {
+                panic!("failure");
+            }
+            exit_flag.await;
+            Exited
+        }
+    }
+
+    #[test]
+    fn restart_delay() {
+        let configuration = SupervisorConfiguration {
+            restart_delay_min: Duration::from_secs(1),
+            restart_delay_max: Duration::from_secs(10),
+            stable_duration: Duration::from_secs(60),
+        };
+
+        assert_eq!(
+            Supervisor::restart_delay(&configuration, 1),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            Supervisor::restart_delay(&configuration, 3),
+            Duration::from_secs(4)
+        );
+        assert_eq!(
+            Supervisor::restart_delay(&configuration, 100),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn panic_restart() {
+        let panicking = Panicking {
+            panics: 2,
+            runs: AtomicUsize::new(0),
+        };
+        let supervisor = Supervisor::new(
+            SupervisorConfiguration {
+                restart_delay_min: Duration::from_millis(1),
+                restart_delay_max: Duration::from_millis(1),
+                stable_duration: Duration::from_secs(60),
+            },
+            Box::new([("panicking".to_owned(), &panicking as &dyn Runnable)]),
+        );
+
+        let (exit_flag_sender, exit_flag_receiver) = async_flag::pair();
+        let supervisor_runner = supervisor.run(exit_flag_receiver);
+        let test_runner = async {
+            while panicking.runs.load(Ordering::Relaxed) < 3 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            let status = supervisor.statuses()[0].clone();
+            assert_eq!(status.state, SupervisorRunnableState::Running);
+            assert_eq!(status.restarts, 2);
+            assert_eq!(status.failure_last.as_deref(), Some("panicked: failure"));
+
+            exit_flag_sender.signal();
+        };
+        futures::join!(supervisor_runner, test_runner);
+
+        assert_eq!(
+            supervisor.statuses()[0].state,
+            SupervisorRunnableState::Exited
+        );
+    }
+}
@@ -49,6 +49,12 @@ impl Runtime {
     {
         self.inner.as_ref().unwrap().spawn(future)
     }
+
+    // completes when runtime is able to schedule and run a task, used for
+    // health checking of runtime with all workers stuck
+    pub async fn probe(&self) {
+        self.spawn(async {}).await.unwrap();
+    }
 }
 impl Drop for Runtime {
     fn drop(&mut self) {
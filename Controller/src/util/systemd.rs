@@ -0,0 +1,121 @@
+// minimal implementation of sd_notify protocol, see sd_notify(3)
+// all functions are no-op when not running under systemd
+
+use super::async_flag;
+use anyhow::{Context, Error};
+use futures::{
+    future::{Future, FutureExt},
+    select,
+};
+use std::{
+    env,
+    os::unix::net::{SocketAddr, UnixDatagram},
+    process,
+    time::Duration,
+};
+
+fn notify_socket_address() -> Result<Option<SocketAddr>, Error> {
+    let notify_socket = match env::var_os("NOTIFY_SOCKET") {
+        Some(notify_socket) => notify_socket,
+        None => return Ok(None),
+    };
+    let notify_socket = notify_socket.into_encoded_bytes();
+
+    let address = match notify_socket.strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name).context("from_abstract_name")?
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => anyhow::bail!("abstract sockets are supported only on linux"),
+        None => SocketAddr::from_pathname(String::from_utf8_lossy(&notify_socket).as_ref())
+            .context("from_pathname")?,
+    };
+
+    Ok(Some(address))
+}
+
+pub fn notify(state: &str) -> Result<(), Error> {
+    let address = match notify_socket_address().context("notify_socket_address")? {
+        Some(address) => address,
+        None => return Ok(()),
+    };
+
+    let socket = UnixDatagram::unbound().context("unbound")?;
+    socket
+        .send_to_addr(state.as_bytes(), &address)
+        .context("send_to_addr")?;
+
+    Ok(())
+}
+
+pub fn ready() {
+    if let Err(error) = notify("READY=1").context("notify") {
+        log::warn!("systemd: {:?}", error);
+    }
+}
+// followed by READY=1 sent by the new process image, pid stays the same
+pub fn reloading() {
+    if let Err(error) = notify("RELOADING=1").context("notify") {
+        log::warn!("systemd: {:?}", error);
+    }
+}
+pub fn stopping() {
+    if let Err(error) = notify("STOPPING=1").context("notify") {
+        log::warn!("systemd: {:?}", error);
+    }
+}
+
+// returns watchdog timeout configured in unit file (WatchdogSec=), if enabled
+// for this process
+pub fn watchdog_timeout() -> Option<Duration> {
+    if let Some(watchdog_pid) = env::var_os("WATCHDOG_PID") {
+        if watchdog_pid.to_str()?.parse::<u32>().ok()? != process::id() {
+            return None;
+        }
+    }
+
+    let watchdog_usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    if watchdog_usec == 0 {
+        return None;
+    }
+
+    Some(Duration::from_micros(watchdog_usec))
+}
+
+// sends watchdog keep-alive at half of the configured timeout, only if
+// health_check (given time limit) passes, so heartbeats stop when checked
+// component (eg. devices runtime) gets stuck, not only executor running this
+// future
+pub async fn watchdog_run<H, F>(
+    health_check: H,
+    mut exit_flag: async_flag::Receiver,
+) where
+    H: Fn(Duration) -> F,
+    F: Future<Output = bool>,
+{
+    let watchdog_timeout = match watchdog_timeout() {
+        Some(watchdog_timeout) => watchdog_timeout,
+        None => {
+            exit_flag.await;
+            return;
+        }
+    };
+    let interval = watchdog_timeout / 2;
+
+    loop {
+        if health_check(interval / 2).await {
+            if let Err(error) = notify("WATCHDOG=1").context("notify") {
+                log::warn!("systemd: {:?}", error);
+            }
+        } else {
+            log::error!("systemd: health check failed, watchdog keep-alive not sent");
+        }
+
+        select! {
+            () = tokio::time::sleep(interval).fuse() => {},
+            () = exit_flag => break,
+        }
+    }
+}
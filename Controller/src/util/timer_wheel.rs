@@ -0,0 +1,370 @@
+use futures::future::Future;
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+#[cfg(any(test, feature = "simulation"))]
+use std::cell::Cell;
+use std::{
+    pin::Pin,
+    sync::Once,
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    tick: u64,
+    waker: Waker,
+}
+
+#[derive(Debug)]
+struct Inner {
+    tick_current: u64, // all ticks up to (including) this one were fired
+    id_next: u64,
+    slots: Box<[Vec<Entry>]>,
+    entries: usize,
+}
+
+// coarse timer shared by soft devices
+// deadlines are rounded up to ticks and fired in batches by single driver
+// thread, so many devices waiting for similar moments cause a single wakeup
+#[derive(Debug)]
+pub struct TimerWheel {
+    resolution: Duration,
+    start: Instant,
+
+    inner: Mutex<Inner>,
+    condvar: Condvar,
+}
+impl TimerWheel {
+    const SLOTS: usize = 1024;
+
+    pub fn new(resolution: Duration) -> Self {
+        assert!(!resolution.is_zero());
+
+        let start = Instant::now();
+
+        let inner = Inner {
+            tick_current: 0,
+            id_next: 0,
+            slots: (0..Self::SLOTS).map(|_| Vec::new()).collect(),
+            entries: 0,
+        };
+        let inner = Mutex::new(inner);
+
+        let condvar = Condvar::new();
+
+        Self {
+            resolution,
+            start,
+
+            inner,
+            condvar,
+        }
+    }
+
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    // rounded up, so timers never fire early
+    fn tick_from_instant(
+        &self,
+        instant: Instant,
+    ) -> u64 {
+        let elapsed = instant.saturating_duration_since(self.start);
+        elapsed.as_nanos().div_ceil(self.resolution.as_nanos()) as u64
+    }
+    fn tick_to_instant(
+        &self,
+        tick: u64,
+    ) -> Instant {
+        self.start + Duration::from_nanos((self.resolution.as_nanos() * tick as u128) as u64)
+    }
+    fn tick_now(&self) -> u64 {
+        let elapsed = self.start.elapsed();
+        (elapsed.as_nanos() / self.resolution.as_nanos()) as u64
+    }
+
+    fn slot_index(tick: u64) -> usize {
+        (tick % Self::SLOTS as u64) as usize
+    }
+
+    pub fn sleep_until(
+        &self,
+        deadline: Instant,
+    ) -> Sleep<'_> {
+        Sleep {
+            timer_wheel: self,
+            tick: self.tick_from_instant(deadline),
+            id: None,
+        }
+    }
+    pub fn sleep(
+        &self,
+        duration: Duration,
+    ) -> Sleep<'_> {
+        self.sleep_until(Instant::now() + duration)
+    }
+
+    fn entry_remove(
+        inner: &mut Inner,
+        tick: u64,
+        id: u64,
+    ) {
+        let slot = &mut inner.slots[Self::slot_index(tick)];
+        if let Some(position) = slot.iter().position(|entry| entry.id == id) {
+            slot.swap_remove(position);
+            inner.entries -= 1;
+        }
+    }
+
+    // returns first tick with pending entry, or one revolution ahead if there is
+    // none
+    fn tick_next(inner: &Inner) -> u64 {
+        (1..=Self::SLOTS as u64)
+            .map(|offset| inner.tick_current + offset)
+            .find(|tick| {
+                inner.slots[Self::slot_index(*tick)]
+                    .iter()
+                    .any(|entry| entry.tick <= *tick)
+            })
+            .unwrap_or(inner.tick_current + Self::SLOTS as u64)
+    }
+
+    fn fire(
+        &self,
+        inner: &mut MutexGuard<'_, Inner>,
+    ) {
+        let tick_now = self.tick_now();
+        if tick_now <= inner.tick_current {
+            return;
+        }
+
+        // after long stall every slot is visited once
+        let ticks = (tick_now - inner.tick_current).min(Self::SLOTS as u64);
+
+        let mut wakers = Vec::<Waker>::new();
+        for offset in 1..=ticks {
+            let slot_index = Self::slot_index(inner.tick_current + offset);
+            inner.slots[slot_index].retain(|entry| {
+                if entry.tick <= tick_now {
+                    wakers.push(entry.waker.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        inner.entries -= wakers.len();
+        inner.tick_current = tick_now;
+
+        if !wakers.is_empty() {
+            MutexGuard::unlocked(inner, || {
+                wakers.into_iter().for_each(Waker::wake);
+            });
+        }
+    }
+
+    fn driver_run(&self) -> ! {
+        let mut inner = self.inner.lock();
+        loop {
+            self.fire(&mut inner);
+
+            if inner.entries == 0 {
+                self.condvar.wait(&mut inner);
+                continue;
+            }
+
+            let tick_next = Self::tick_next(&inner);
+            self.condvar
+                .wait_until(&mut inner, self.tick_to_instant(tick_next));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Sleep<'w> {
+    timer_wheel: &'w TimerWheel,
+    tick: u64,
+    id: Option<u64>,
+}
+impl<'w> Future for Sleep<'w> {
+    type Output = ();
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let self_ = self.get_mut();
+        let mut inner = self_.timer_wheel.inner.lock();
+
+        if self_.tick <= inner.tick_current {
+            if let Some(id) = self_.id.take() {
+                TimerWheel::entry_remove(&mut inner, self_.tick, id);
+            }
+            return Poll::Ready(());
+        }
+
+        match self_.id {
+            Some(id) => {
+                // already registered, task may have changed
+                let slot = &mut inner.slots[TimerWheel::slot_index(self_.tick)];
+                if let Some(entry) = slot.iter_mut().find(|entry| entry.id == id) {
+                    entry.waker.clone_from(cx.waker());
+                }
+            }
+            None => {
+                let id = inner.id_next;
+                inner.id_next += 1;
+
+                inner.slots[TimerWheel::slot_index(self_.tick)].push(Entry {
+                    id,
+                    tick: self_.tick,
+                    waker: cx.waker().clone(),
+                });
+                inner.entries += 1;
+                self_.id = Some(id);
+
+                // driver may be sleeping until later deadline
+                self_.timer_wheel.condvar.notify_one();
+            }
+        }
+
+        Poll::Pending
+    }
+}
+impl<'w> Drop for Sleep<'w> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut inner = self.timer_wheel.inner.lock();
+            TimerWheel::entry_remove(&mut inner, self.tick, id);
+        }
+    }
+}
+
+const RESOLUTION: Duration = Duration::from_millis(10);
+
+pub fn timer_wheel() -> &'static TimerWheel {
+    static TIMER_WHEEL: Lazy<TimerWheel> = Lazy::new(|| TimerWheel::new(RESOLUTION));
+    static DRIVER: Once = Once::new();
+
+    DRIVER.call_once(|| {
+        thread::Builder::new()
+            .name("timer_wheel".to_owned())
+            .spawn(|| TIMER_WHEEL.driver_run())
+            .unwrap();
+    });
+
+    &TIMER_WHEEL
+}
+
+// simulation runs on tokio paused clock, so timers must go through tokio
+#[cfg(any(test, feature = "simulation"))]
+thread_local! {
+    static TOKIO_TIME: Cell<bool> = const { Cell::new(false) };
+}
+#[cfg(any(test, feature = "simulation"))]
+#[derive(Debug)]
+pub struct TokioTimeGuard {
+    tokio_time_previous: bool,
+}
+#[cfg(any(test, feature = "simulation"))]
+impl TokioTimeGuard {
+    // routes sleeps on current thread to tokio timer, until dropped
+    pub fn new() -> Self {
+        let tokio_time_previous = TOKIO_TIME.replace(true);
+        Self {
+            tokio_time_previous,
+        }
+    }
+}
+#[cfg(any(test, feature = "simulation"))]
+impl Drop for TokioTimeGuard {
+    fn drop(&mut self) {
+        TOKIO_TIME.set(self.tokio_time_previous);
+    }
+}
+
+#[derive(Debug)]
+pub enum Delay {
+    Wheel(Sleep<'static>),
+    #[cfg(any(test, feature = "simulation"))]
+    Tokio(Pin<Box<tokio::time::Sleep>>),
+}
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        match self.get_mut() {
+            Self::Wheel(sleep) => Pin::new(sleep).poll(cx),
+            #[cfg(any(test, feature = "simulation"))]
+            Self::Tokio(sleep) => sleep.as_mut().poll(cx),
+        }
+    }
+}
+
+// drop-in replacements for tokio::time::sleep, for use in soft devices
+pub fn sleep(duration: Duration) -> Delay {
+    #[cfg(any(test, feature = "simulation"))]
+    if TOKIO_TIME.get() {
+        return Delay::Tokio(Box::pin(tokio::time::sleep(duration)));
+    }
+
+    Delay::Wheel(timer_wheel().sleep(duration))
+}
+pub fn sleep_until(deadline: Instant) -> Delay {
+    #[cfg(any(test, feature = "simulation"))]
+    if TOKIO_TIME.get() {
+        let duration = deadline.saturating_duration_since(Instant::now());
+        return Delay::Tokio(Box::pin(tokio::time::sleep(duration)));
+    }
+
+    Delay::Wheel(timer_wheel().sleep_until(deadline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimerWheel;
+    use futures::{future::FutureExt, select};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn timer_wheel() -> &'static TimerWheel {
+        let timer_wheel: &'static TimerWheel =
+            Box::leak(Box::new(TimerWheel::new(Duration::from_millis(10))));
+        thread::spawn(|| timer_wheel.driver_run());
+        timer_wheel
+    }
+
+    #[tokio::test]
+    async fn fires_not_early() {
+        let timer_wheel = timer_wheel();
+
+        let start = Instant::now();
+        timer_wheel.sleep(Duration::from_millis(35)).await;
+        let elapsed = start.elapsed();
+
+        assert!(elapsed >= Duration::from_millis(35));
+        assert!(elapsed < Duration::from_secs(1));
+        assert_eq!(timer_wheel.inner.lock().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_is_removed() {
+        let timer_wheel = timer_wheel();
+
+        select! {
+            () = timer_wheel.sleep(Duration::from_secs(60)).fuse() => panic!(),
+            () = timer_wheel.sleep(Duration::from_millis(20)).fuse() => {},
+        }
+
+        assert_eq!(timer_wheel.inner.lock().entries, 0);
+    }
+}
@@ -0,0 +1,486 @@
+use super::server::Bind;
+use crate::util::async_flag;
+use anyhow::{bail, ensure, Context, Error};
+use futures::{future::FutureExt, select};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+use tokio::net::UdpSocket;
+
+// minimal mDNS / DNS-SD responder (RFC 6762, RFC 6763), advertising api
+// endpoint as _logicblocks._tcp service
+// only ipv4 multicast group is used, ipv6 addresses are still announced as
+// AAAA records
+
+const MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MULTICAST_PORT: u16 = 5353;
+
+const SERVICE_TYPE: &str = "_logicblocks._tcp.local";
+const SERVICES_META: &str = "_services._dns-sd._udp.local";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+
+const CLASS_IN: u16 = 1;
+// records unique to this host should replace cached ones
+const CLASS_CACHE_FLUSH: u16 = 0x8000;
+
+const TTL_SHARED: u32 = 4500;
+const TTL_UNIQUE: u32 = 120;
+
+#[derive(Clone, Debug)]
+pub struct Advertisement {
+    // service instance and host name, eg. "logicblocks"
+    pub instance: String,
+    pub port: u16,
+    // server listen addresses, wildcard ones are advertised as addresses of
+    // all (or the bound) interfaces of the same family
+    pub binds: Box<[Bind]>,
+}
+
+#[derive(Debug)]
+pub struct Advertiser {
+    advertisement: Advertisement,
+}
+impl Advertiser {
+    pub fn new(advertisement: Advertisement) -> Self {
+        Self { advertisement }
+    }
+
+    fn instance_name(&self) -> String {
+        format!("{}.{}", self.advertisement.instance, SERVICE_TYPE)
+    }
+    fn host_name(&self) -> String {
+        format!("{}.local", self.advertisement.instance)
+    }
+
+    fn addresses(&self) -> Result<Box<[IpAddr]>, Error> {
+        let interfaces = if_addrs::get_if_addrs()
+            .context("get_if_addrs")?
+            .into_iter()
+            .map(|interface| (interface.name, interface.addr.ip()))
+            .collect::<Box<[_]>>();
+
+        let addresses = addresses_from_binds(&self.advertisement.binds, &interfaces);
+        ensure!(!addresses.is_empty(), "no addresses to advertise");
+
+        Ok(addresses)
+    }
+
+    fn response_build(
+        &self,
+        addresses: &[IpAddr],
+        ttl_multiplier: u32,
+    ) -> Box<[u8]> {
+        let instance_name = self.instance_name();
+        let host_name = self.host_name();
+
+        let mut records = Vec::<Record>::new();
+        records.push(Record {
+            name: SERVICES_META.to_owned(),
+            type_: TYPE_PTR,
+            cache_flush: false,
+            ttl: TTL_SHARED * ttl_multiplier,
+            data: name_encode(SERVICE_TYPE),
+        });
+        records.push(Record {
+            name: SERVICE_TYPE.to_owned(),
+            type_: TYPE_PTR,
+            cache_flush: false,
+            ttl: TTL_SHARED * ttl_multiplier,
+            data: name_encode(&instance_name),
+        });
+
+        let mut srv = Vec::<u8>::new();
+        srv.extend_from_slice(&0u16.to_be_bytes()); // priority
+        srv.extend_from_slice(&0u16.to_be_bytes()); // weight
+        srv.extend_from_slice(&self.advertisement.port.to_be_bytes());
+        srv.extend_from_slice(&name_encode(&host_name));
+        records.push(Record {
+            name: instance_name.clone(),
+            type_: TYPE_SRV,
+            cache_flush: true,
+            ttl: TTL_UNIQUE * ttl_multiplier,
+            data: srv,
+        });
+
+        let txt = "path=/api";
+        let mut txt_data = vec![txt.len() as u8];
+        txt_data.extend_from_slice(txt.as_bytes());
+        records.push(Record {
+            name: instance_name,
+            type_: TYPE_TXT,
+            cache_flush: true,
+            ttl: TTL_SHARED * ttl_multiplier,
+            data: txt_data,
+        });
+
+        for address in addresses {
+            let (type_, data) = match address {
+                IpAddr::V4(address) => (TYPE_A, address.octets().to_vec()),
+                IpAddr::V6(address) => (TYPE_AAAA, address.octets().to_vec()),
+            };
+            records.push(Record {
+                name: host_name.clone(),
+                type_,
+                cache_flush: true,
+                ttl: TTL_UNIQUE * ttl_multiplier,
+                data,
+            });
+        }
+
+        response_encode(&records)
+    }
+
+    fn query_matches(
+        &self,
+        packet: &[u8],
+    ) -> Result<bool, Error> {
+        let instance_name = self.instance_name();
+        let host_name = self.host_name();
+
+        let questions = query_parse(packet).context("query_parse")?;
+        let matches = questions.iter().any(|(name, type_)| {
+            let name_matches = |expected: &str| name.eq_ignore_ascii_case(expected);
+            let type_matches = |expected: &[u16]| *type_ == TYPE_ANY || expected.contains(type_);
+
+            (name_matches(SERVICES_META) && type_matches(&[TYPE_PTR]))
+                || (name_matches(SERVICE_TYPE) && type_matches(&[TYPE_PTR]))
+                || (name_matches(&instance_name) && type_matches(&[TYPE_SRV, TYPE_TXT]))
+                || (name_matches(&host_name) && type_matches(&[TYPE_A, TYPE_AAAA]))
+        });
+        Ok(matches)
+    }
+
+    fn socket_create() -> Result<UdpSocket, Error> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).context("new")?;
+        // other responders (avahi, bonjour) usually own this port too
+        socket
+            .set_reuse_address(true)
+            .context("set_reuse_address")?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true).context("set_reuse_port")?;
+        socket
+            .bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MULTICAST_PORT)).into())
+            .context("bind")?;
+        socket
+            .join_multicast_v4(&MULTICAST_ADDRESS, &Ipv4Addr::UNSPECIFIED)
+            .context("join_multicast_v4")?;
+        socket
+            .set_multicast_ttl_v4(255)
+            .context("set_multicast_ttl_v4")?;
+        socket.set_nonblocking(true).context("set_nonblocking")?;
+
+        let socket = UdpSocket::from_std(socket.into()).context("from_std")?;
+        Ok(socket)
+    }
+
+    async fn run_once(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) -> Result<(), Error> {
+        const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+        const ANNOUNCE_COUNT: usize = 2;
+
+        let socket = Self::socket_create().context("socket_create")?;
+        let target = SocketAddr::V4(SocketAddrV4::new(MULTICAST_ADDRESS, MULTICAST_PORT));
+
+        let addresses = self.addresses().context("addresses")?;
+        let response = self.response_build(&addresses, 1);
+
+        // unsolicited announcements, so listeners learn about us without asking
+        for _ in 0..ANNOUNCE_COUNT {
+            socket.send_to(&response, target).await.context("send_to")?;
+            select! {
+                () = tokio::time::sleep(ANNOUNCE_INTERVAL).fuse() => {},
+                () = exit_flag => return Ok(()),
+            }
+        }
+
+        let mut buffer = [0u8; 9000];
+        loop {
+            let (length, _) = select! {
+                result = socket.recv_from(&mut buffer).fuse() => result.context("recv_from")?,
+                () = exit_flag => break,
+            };
+            match self.query_matches(&buffer[..length]) {
+                Ok(true) => {
+                    socket.send_to(&response, target).await.context("send_to")?;
+                }
+                Ok(false) => {}
+                Err(error) => {
+                    log::trace!("{self}: invalid packet: {error:?}");
+                }
+            }
+        }
+
+        // goodbye, ttl = 0 removes records from caches
+        let goodbye = self.response_build(&addresses, 0);
+        socket.send_to(&goodbye, target).await.context("send_to")?;
+
+        Ok(())
+    }
+
+    pub async fn run(
+        &self,
+        mut exit_flag: async_flag::Receiver,
+    ) {
+        const ERROR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+        loop {
+            let error = match self.run_once(exit_flag.clone()).await.context("run_once") {
+                Ok(()) => break,
+                Err(error) => error,
+            };
+            log::error!("{self}: {error:?}");
+
+            select! {
+                () = tokio::time::sleep(ERROR_RESTART_DELAY).fuse() => {},
+                () = exit_flag => break,
+            }
+        }
+    }
+}
+impl fmt::Display for Advertiser {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "Advertiser ({})", self.advertisement.instance)
+    }
+}
+
+#[derive(Debug)]
+struct Record {
+    name: String,
+    type_: u16,
+    cache_flush: bool,
+    ttl: u32,
+    data: Vec<u8>,
+}
+
+fn name_encode(name: &str) -> Vec<u8> {
+    let mut buffer = Vec::<u8>::new();
+    for label in name.split('.') {
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label.as_bytes());
+    }
+    buffer.push(0);
+    buffer
+}
+
+fn response_encode(records: &[Record]) -> Box<[u8]> {
+    let mut buffer = Vec::<u8>::new();
+    buffer.extend_from_slice(&0u16.to_be_bytes()); // id
+    buffer.extend_from_slice(&0x8400u16.to_be_bytes()); // response, authoritative
+    buffer.extend_from_slice(&0u16.to_be_bytes()); // questions
+    buffer.extend_from_slice(&(records.len() as u16).to_be_bytes()); // answers
+    buffer.extend_from_slice(&0u16.to_be_bytes()); // authority
+    buffer.extend_from_slice(&0u16.to_be_bytes()); // additional
+
+    for record in records {
+        let class = CLASS_IN
+            | if record.cache_flush {
+                CLASS_CACHE_FLUSH
+            } else {
+                0
+            };
+        buffer.extend_from_slice(&name_encode(&record.name));
+        buffer.extend_from_slice(&record.type_.to_be_bytes());
+        buffer.extend_from_slice(&class.to_be_bytes());
+        buffer.extend_from_slice(&record.ttl.to_be_bytes());
+        buffer.extend_from_slice(&(record.data.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(&record.data);
+    }
+
+    buffer.into_boxed_slice()
+}
+
+// returns name and offset right after it (pointers are followed, but don't
+// move the returned offset)
+fn name_parse(
+    packet: &[u8],
+    mut offset: usize,
+) -> Result<(String, usize), Error> {
+    const JUMPS_MAX: usize = 16;
+
+    let mut labels = Vec::<String>::new();
+    let mut offset_end = None::<usize>;
+    let mut jumps = 0;
+    loop {
+        let length = *packet.get(offset).context("length")? as usize;
+        match length {
+            0 => {
+                offset += 1;
+                break;
+            }
+            length if length & 0xc0 == 0xc0 => {
+                let pointer_low = *packet.get(offset + 1).context("pointer")? as usize;
+                if offset_end.is_none() {
+                    offset_end = Some(offset + 2);
+                }
+                jumps += 1;
+                ensure!(jumps <= JUMPS_MAX, "too many jumps");
+                offset = ((length & 0x3f) << 8) | pointer_low;
+            }
+            length if length & 0xc0 == 0 => {
+                let label = packet
+                    .get(offset + 1..offset + 1 + length)
+                    .context("label")?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + length;
+            }
+            length => bail!("unsupported label type: {length:#x}"),
+        }
+    }
+
+    Ok((labels.join("."), offset_end.unwrap_or(offset)))
+}
+
+// returns (name, type) of each question, empty for responses
+fn query_parse(packet: &[u8]) -> Result<Box<[(String, u16)]>, Error> {
+    ensure!(packet.len() >= 12, "packet too short");
+
+    let flags = u16::from_be_bytes([packet[2], packet[3]]);
+    if flags & 0x8000 != 0 {
+        return Ok(Box::new([]));
+    }
+    let questions_count = u16::from_be_bytes([packet[4], packet[5]]);
+
+    let mut offset = 12;
+    let mut questions = Vec::<(String, u16)>::new();
+    for _ in 0..questions_count {
+        let (name, offset_next) = name_parse(packet, offset).context("name_parse")?;
+        let type_ = packet.get(offset_next..offset_next + 2).context("type")?;
+        let type_ = u16::from_be_bytes([type_[0], type_[1]]);
+        offset = offset_next + 4; // type + class
+        questions.push((name, type_));
+    }
+
+    Ok(questions.into_boxed_slice())
+}
+
+// interfaces are (name, address) pairs
+fn addresses_from_binds(
+    binds: &[Bind],
+    interfaces: &[(String, IpAddr)],
+) -> Box<[IpAddr]> {
+    let mut addresses = Vec::<IpAddr>::new();
+    for bind in binds {
+        let bind_address = bind.address.ip();
+        let bind_addresses = if bind_address.is_unspecified() {
+            interfaces
+                .iter()
+                .filter(|(name, _)| {
+                    bind.interface
+                        .as_ref()
+                        .is_none_or(|interface| interface == name)
+                })
+                .map(|(_, address)| *address)
+                .filter(|address| {
+                    !address.is_loopback() && address.is_ipv4() == bind_address.is_ipv4()
+                })
+                .collect::<Vec<_>>()
+        } else {
+            vec![bind_address]
+        };
+        for address in bind_addresses {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    addresses.into_boxed_slice()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::server::Bind, addresses_from_binds, name_encode, name_parse, query_parse, TYPE_PTR,
+        TYPE_SRV,
+    };
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    #[test]
+    fn addresses_from_binds_wildcard() {
+        let interfaces = [
+            ("lo".to_owned(), IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            (
+                "eth0".to_owned(),
+                IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+            ),
+            (
+                "eth0".to_owned(),
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 10)),
+            ),
+            ("wlan0".to_owned(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))),
+        ];
+
+        let binds = [Bind {
+            address: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 8080),
+            interface: None,
+        }];
+        assert_eq!(
+            &*addresses_from_binds(&binds, &interfaces),
+            &[
+                IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)),
+            ]
+        );
+
+        let binds = [
+            Bind {
+                address: SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 8080),
+                interface: Some("eth0".to_owned()),
+            },
+            Bind {
+                address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)), 8080),
+                interface: None,
+            },
+        ];
+        assert_eq!(
+            &*addresses_from_binds(&binds, &interfaces),
+            &[
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 10)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn query_parse_compressed() {
+        let mut packet = vec![
+            0x00, 0x00, 0x00, 0x00, // id, flags
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // counts
+        ];
+        packet.extend_from_slice(&name_encode("_logicblocks._tcp.local"));
+        packet.extend_from_slice(&[0x00, 0x0c, 0x00, 0x01]);
+        // "instance" + pointer to first name
+        packet.extend_from_slice(&[8]);
+        packet.extend_from_slice(b"instance");
+        packet.extend_from_slice(&[0xc0, 12]);
+        packet.extend_from_slice(&[0x00, 0x21, 0x00, 0x01]);
+
+        let questions = query_parse(&packet).unwrap();
+        assert_eq!(
+            &*questions,
+            &[
+                ("_logicblocks._tcp.local".to_owned(), TYPE_PTR),
+                ("instance._logicblocks._tcp.local".to_owned(), TYPE_SRV),
+            ]
+        );
+    }
+
+    #[test]
+    fn name_parse_loop() {
+        let packet = [0xc0, 0x00];
+        assert!(name_parse(&packet, 0).is_err());
+    }
+}
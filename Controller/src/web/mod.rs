@@ -1,3 +1,4 @@
+pub mod mdns;
 pub mod root_service;
 pub mod server;
 pub mod sse;
@@ -7,14 +8,16 @@ pub mod uri_cursor;
 use anyhow::{ensure, Context, Error};
 use bytes::Bytes;
 use futures::{
-    future::BoxFuture,
+    future::{ready, BoxFuture},
     stream::{once, Stream, StreamExt},
 };
 use http::{header, request::Parts, HeaderMap, Method, Response as HttpResponse, StatusCode, Uri};
 use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full, StreamBody};
 use hyper::body::Frame;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{convert::Infallible, net::SocketAddr};
+use tokio_util::io::ReaderStream;
 
 #[derive(Debug)]
 pub struct Request {
@@ -35,6 +38,9 @@ impl Request {
         }
     }
 
+    pub fn remote_address(&self) -> &SocketAddr {
+        &self.remote_address
+    }
     pub fn method(&self) -> &Method {
         &self.http_parts.method
     }
@@ -44,6 +50,9 @@ impl Request {
     pub fn headers(&self) -> &HeaderMap {
         &self.http_parts.headers
     }
+    pub fn body_payload(&self) -> &Bytes {
+        &self.body_payload
+    }
 
     pub fn body_parse_json<'s, T: Deserialize<'s>>(&'s self) -> Result<T, Error> {
         let content_type = self
@@ -106,6 +115,70 @@ impl Response {
 
         Self { http_response }
     }
+    // for frequently polled endpoints, etag is derived from the payload, so
+    // unchanged values are answered with 304 and no body. no-cache makes
+    // browsers revalidate on each fetch instead of using heuristic freshness
+    pub fn ok_json_etag<T: Serialize>(
+        request: &Request,
+        value: T,
+    ) -> Self {
+        let body_payload = Bytes::from(serde_json::to_vec(&value).unwrap());
+        let etag = etag_from_payload(&body_payload);
+
+        let not_modified = request
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|header| header.to_str().ok())
+            .is_some_and(|if_none_match| etag_matches(if_none_match, &etag));
+
+        let http_response = if not_modified {
+            HttpResponse::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, etag)
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(Empty::new().boxed())
+                .unwrap()
+        } else {
+            HttpResponse::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::ETAG, etag)
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(Full::new(body_payload).boxed())
+                .unwrap()
+        };
+
+        Self { http_response }
+    }
+    // body is sent in chunks as they are produced, without buffering whole payload
+    pub fn ok_content_type_stream<S: Stream<Item = Bytes> + Send + Sync + 'static>(
+        content_type: &str,
+        body_stream: S,
+    ) -> Self {
+        let http_response = HttpResponse::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(BodyExt::boxed(StreamBody::new(
+                body_stream.map(|chunk| Ok(Frame::data(chunk))),
+            )))
+            .unwrap();
+
+        Self { http_response }
+    }
+    pub fn ok_content_type_file(
+        content_type: &str,
+        file: tokio::fs::File,
+    ) -> Self {
+        // read errors can't be reported after headers were sent, body is just cut short
+        let body_stream = ReaderStream::new(file).scan((), |(), chunk| {
+            ready(match chunk {
+                Ok(chunk) => Some(chunk),
+                Err(error) => {
+                    log::warn!("error while streaming file: {:?}", error);
+                    None
+                }
+            })
+        });
+        Self::ok_content_type_stream(content_type, body_stream)
+    }
     pub fn ok_sse_stream<S: Stream<Item = sse::Event> + Send + Sync + 'static>(
         sse_stream: S
     ) -> Self {
@@ -143,6 +216,20 @@ impl Response {
 
         Self { http_response }
     }
+    pub fn error_json<T: Serialize>(
+        status_code: StatusCode,
+        value: T,
+    ) -> Self {
+        let body_payload = Bytes::from(serde_json::to_vec(&value).unwrap());
+
+        let http_response = HttpResponse::builder()
+            .status(status_code)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(body_payload).boxed())
+            .unwrap();
+
+        Self { http_response }
+    }
     pub fn error_400_from_error<T: Into<Error>>(error: T) -> Self {
         let body_payload = Bytes::from(error.into().to_string());
         let http_response = HttpResponse::builder()
@@ -162,9 +249,40 @@ impl Response {
     }
 }
 
+fn etag_from_payload(body_payload: &[u8]) -> String {
+    let digest = Sha256::digest(body_payload);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+// if-none-match may contain list of (possibly weak) etags or a wildcard
+fn etag_matches(
+    if_none_match: &str,
+    etag: &str,
+) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
 pub trait Handler {
     fn handle(
         &self,
         request: Request,
     ) -> BoxFuture<'static, Response>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{etag_from_payload, etag_matches};
+
+    #[test]
+    fn etag_matches_list() {
+        let etag = etag_from_payload(b"[1,2,3]");
+        assert_eq!(etag.len(), 34);
+        assert_ne!(etag, etag_from_payload(b"[1,2]"));
+
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("\"other\", W/{}", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"other\"", &etag));
+    }
+}
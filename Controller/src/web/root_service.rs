@@ -3,18 +3,34 @@ use super::{
     Handler, Request, Response,
 };
 use futures::future::{BoxFuture, FutureExt};
+use http::StatusCode;
+
+// checks api requests before they are routed, gui is always served
+pub trait Authorizer {
+    // api_path is relative to api root
+    fn authorize(
+        &self,
+        request: &Request,
+        api_path: &str,
+    ) -> Result<(), StatusCode>;
+}
 
 // #[derive(Debug)] // Debug not possible
 pub struct RootService<'a> {
     api_handler: &'a (dyn UriCursorHandler + Sync),
+    api_authorizer: &'a (dyn Authorizer + Sync),
     gui_responder: gui_responder::GuiResponder,
 }
 impl<'a> RootService<'a> {
-    pub fn new(api_handler: &'a (dyn UriCursorHandler + Sync)) -> Self {
+    pub fn new(
+        api_handler: &'a (dyn UriCursorHandler + Sync),
+        api_authorizer: &'a (dyn Authorizer + Sync),
+    ) -> Self {
         let gui_responder = gui_responder::GuiResponder::new();
 
         Self {
             api_handler,
+            api_authorizer,
             gui_responder,
         }
     }
@@ -29,6 +45,10 @@ impl<'a> Handler for RootService<'a> {
 
         // Serve API if url starts with /api
         if let Some(api_path) = path.strip_prefix("/api/") {
+            if let Err(status_code) = self.api_authorizer.authorize(&request, api_path) {
+                return async move { Response::error(status_code) }.boxed();
+            }
+
             let uri_cursor = UriCursor::new(api_path);
             return self.api_handler.handle(request, &uri_cursor);
         }
@@ -1,4 +1,4 @@
-use super::{Handler, Request, Response};
+use super::{mdns, Handler, Request, Response};
 use crate::{
     modules::module_path::ModulePath,
     util::{
@@ -8,22 +8,25 @@ use crate::{
         runtime::{Runtime, RuntimeScopeRunnable},
     },
 };
-use anyhow::{Context, Error};
+use anyhow::{ensure, Context, Error};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::{
-    future::{select, Either, FutureExt},
+    future::{join, select, select_all, Either, FutureExt},
     pin_mut, select,
 };
-use http::{request::Request as HttpRequest, response::Response as HttpResponse};
-use http_body_util::{combinators::BoxBody, BodyExt};
+use http::{
+    header, request::Request as HttpRequest, response::Response as HttpResponse, StatusCode,
+};
+use http_body_util::{combinators::BoxBody, BodyExt, LengthLimitError, Limited};
 use hyper::{body::Incoming, service::service_fn};
 use hyper_util::{
-    rt::{TokioExecutor, TokioIo},
+    rt::{TokioExecutor, TokioIo, TokioTimer},
     server::{conn::auto::Builder, graceful::GracefulShutdown},
 };
 use once_cell::sync::Lazy;
 use ouroboros::self_referencing;
+use socket2::{Domain, Protocol, Socket, Type};
 use std::{
     convert::Infallible,
     fmt,
@@ -33,36 +36,157 @@ use std::{
 };
 use tokio::net::TcpListener;
 
+// protects the process from clients sending huge or never ending requests
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub body_size_max: usize,
+    // time to receive complete request headers
+    pub header_timeout: Duration,
+    // time to receive the body and produce response head, streamed response
+    // bodies (eg. sse) are not limited
+    pub request_timeout: Duration,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            body_size_max: 4 * 1024 * 1024,
+            header_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bind {
+    // use [::] for ipv6, link local addresses need scope id set
+    pub address: SocketAddr,
+    // restricts the listener to given network interface (eg. "eth0"), linux
+    // only
+    pub interface: Option<String>,
+}
+impl Bind {
+    fn listener_create(&self) -> Result<TcpListener, Error> {
+        let socket = Socket::new(
+            Domain::for_address(self.address),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )
+        .context("new")?;
+        socket
+            .set_reuse_address(true)
+            .context("set_reuse_address")?;
+        // so [::] and 0.0.0.0 can be bound at the same time
+        if self.address.is_ipv6() {
+            socket.set_only_v6(true).context("set_only_v6")?;
+        }
+        if let Some(interface) = &self.interface {
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            socket
+                .bind_device(Some(interface.as_bytes()))
+                .context("bind_device")?;
+            #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+            anyhow::bail!("binding to interface {interface} is not supported on this platform");
+        }
+        socket.bind(&self.address.into()).context("bind")?;
+        socket.listen(1024).context("listen")?;
+        socket.set_nonblocking(true).context("set_nonblocking")?;
+
+        let listener = TcpListener::from_std(socket.into()).context("from_std")?;
+        Ok(listener)
+    }
+}
+impl fmt::Display for Bind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match &self.interface {
+            Some(interface) => write!(f, "{}%{}", self.address, interface),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub binds: Box<[Bind]>,
+    pub limits: Limits,
+    pub advertisement: Option<mdns::Advertisement>,
+}
+
 // #[derive(Debug)] // Debug not possible
 pub struct Server<'h> {
-    bind: SocketAddr,
+    binds: Box<[Bind]>,
+    limits: Limits,
+    advertiser: Option<mdns::Advertiser>,
     handler: &'h (dyn Handler + Sync),
 }
 impl<'h> Server<'h> {
     pub fn new(
-        bind: SocketAddr,
+        configuration: Configuration,
         handler: &'h (dyn Handler + Sync),
     ) -> Self {
-        Self { bind, handler }
+        let advertiser = configuration.advertisement.map(mdns::Advertiser::new);
+
+        Self {
+            binds: configuration.binds,
+            limits: configuration.limits,
+            advertiser,
+            handler,
+        }
     }
 
-    async fn respond(
+    async fn respond_limited(
         &self,
         remote_address: SocketAddr,
         http_request: HttpRequest<Incoming>,
-    ) -> HttpResponse<BoxBody<Bytes, Infallible>> {
+    ) -> Response {
         let (parts, body) = http_request.into_parts();
-        // TODO: we probably want to limit incoming body size here?
-        let body_payload = match body.collect().await.context("collect") {
+
+        // reject early if client announced too large body
+        let content_length = parts
+            .headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.parse::<usize>().ok());
+        if content_length.is_some_and(|content_length| content_length > self.limits.body_size_max) {
+            return Response::error(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        let body_payload = match Limited::new(body, self.limits.body_size_max)
+            .collect()
+            .await
+        {
             Ok(body_payload) => body_payload.to_bytes(),
-            Err(error) => return Response::error_400_from_error(error).into_http_response(),
+            Err(error) => {
+                if error.is::<LengthLimitError>() {
+                    return Response::error(StatusCode::PAYLOAD_TOO_LARGE);
+                }
+                return Response::error_400_from_error(anyhow::anyhow!(error).context("collect"));
+            }
         };
 
         let request = Request::from_http_request(remote_address, parts, body_payload);
-        let log_method = request.method().clone();
-        let log_uri = request.uri().clone();
+        self.handler.handle(request).await
+    }
 
-        let response = self.handler.handle(request).await;
+    async fn respond(
+        &self,
+        remote_address: SocketAddr,
+        http_request: HttpRequest<Incoming>,
+    ) -> HttpResponse<BoxBody<Bytes, Infallible>> {
+        let log_method = http_request.method().clone();
+        let log_uri = http_request.uri().clone();
+
+        let response = match tokio::time::timeout(
+            self.limits.request_timeout,
+            self.respond_limited(remote_address, http_request),
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(_) => Response::error(StatusCode::REQUEST_TIMEOUT),
+        };
         let log_status_code = response.status_code();
 
         log::debug!(
@@ -81,10 +205,22 @@ impl<'h> Server<'h> {
         &self,
         mut exit_flag: async_flag::Receiver,
     ) -> Result<Exited, Error> {
-        let listener = TcpListener::bind(self.bind).await.context("bind")?;
+        ensure!(!self.binds.is_empty(), "no binds configured");
+        let listeners = self
+            .binds
+            .iter()
+            .map(|bind| {
+                bind.listener_create()
+                    .with_context(|| format!("listener_create {bind}"))
+            })
+            .collect::<Result<Box<[_]>, _>>()?;
         log::trace!("{self}: server listening");
 
-        let server = Builder::new(TokioExecutor::new());
+        let mut server = Builder::new(TokioExecutor::new());
+        server
+            .http1()
+            .timer(TokioTimer::new())
+            .header_read_timeout(self.limits.header_timeout);
         let graceful = GracefulShutdown::new();
 
         // SAFETY: we guarantee that all connections are closed before leaving
@@ -92,11 +228,12 @@ impl<'h> Server<'h> {
         let self_static = unsafe { transmute::<&'_ Server<'_>, &'static Server<'static>>(self) };
 
         loop {
-            let listener_accept = listener.accept();
-            pin_mut!(listener_accept);
+            let listeners_accept =
+                select_all(listeners.iter().map(|listener| listener.accept().boxed()));
+            pin_mut!(listeners_accept);
 
-            match select(listener_accept, &mut exit_flag).await {
-                Either::Left((connection, _)) => {
+            match select(listeners_accept, &mut exit_flag).await {
+                Either::Left(((connection, _, _), _)) => {
                     let (stream, remote_address) = match connection.context("connection") {
                         Ok(connection) => connection,
                         Err(error) => {
@@ -134,7 +271,7 @@ impl<'h> Server<'h> {
         }
 
         // stop accepting new connections
-        drop(listener);
+        drop(listeners);
 
         // shutdown all connections
         log::trace!("{self}: waiting for all remaining connections to shutdown");
@@ -145,6 +282,21 @@ impl<'h> Server<'h> {
     }
 
     async fn run(
+        &self,
+        exit_flag: async_flag::Receiver,
+    ) -> Exited {
+        let advertiser_exit_flag = exit_flag.clone();
+        let advertiser_runner = async move {
+            if let Some(advertiser) = &self.advertiser {
+                advertiser.run(advertiser_exit_flag).await;
+            }
+        };
+        let ((), Exited) = join(advertiser_runner, self.run_server(exit_flag)).await;
+
+        Exited
+    }
+
+    async fn run_server(
         &self,
         mut exit_flag: async_flag::Receiver,
     ) -> Exited {
@@ -180,7 +332,14 @@ impl<'h> fmt::Display for Server<'h> {
         &self,
         f: &mut fmt::Formatter<'_>,
     ) -> fmt::Result {
-        write!(f, "Server ({:?})", self.bind)
+        write!(f, "Server (")?;
+        for (index, bind) in self.binds.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{bind}")?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -202,10 +361,10 @@ pub struct Runner<'r, 'h> {
 impl<'r, 'h> Runner<'r, 'h> {
     pub fn new(
         runtime: &'r Runtime,
-        bind: SocketAddr,
+        configuration: Configuration,
         handler: &'h (dyn Handler + Sync),
     ) -> Self {
-        let server = Server::new(bind, handler);
+        let server = Server::new(configuration, handler);
 
         let inner = RunnerInnerBuilder {
             server,
@@ -257,7 +416,7 @@ impl<'h> RunnerOwned<'h> {
     }
 
     pub fn new(
-        bind: SocketAddr,
+        configuration: Configuration,
         handler: &'h (dyn Handler + Sync),
     ) -> Self {
         let runtime = Runtime::new(Self::module_path(), 2, 2);
@@ -266,7 +425,7 @@ impl<'h> RunnerOwned<'h> {
             runtime,
 
             runner_builder: |runtime| {
-                let runner = Runner::new(runtime, bind, handler);
+                let runner = Runner::new(runtime, configuration, handler);
                 let runner = ManuallyDrop::new(runner);
                 runner
             },
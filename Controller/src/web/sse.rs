@@ -34,3 +34,113 @@ impl Event {
         buffer
     }
 }
+
+// parses event stream produced by Event::to_payload()
+// chunks may split lines (and utf-8 sequences) at any position, so incomplete
+// line is kept until next chunk arrives
+#[derive(Debug)]
+pub struct Parser {
+    buffer: Vec<u8>,
+    id: Option<String>,
+    data: Option<String>,
+}
+impl Parser {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::<u8>::new(),
+            id: None,
+            data: None,
+        }
+    }
+
+    // returns events completed by this chunk
+    pub fn push(
+        &mut self,
+        chunk: &[u8],
+    ) -> Vec<Event> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::<Event>::new();
+        while let Some(position) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=position).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            // empty line ends the event
+            if line.is_empty() {
+                if let Some(data) = self.data.take() {
+                    events.push(Event {
+                        id: self.id.take().map(Cow::from),
+                        data: Cow::from(data),
+                    });
+                }
+                continue;
+            }
+
+            // comment, used for pings
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "id" => {
+                    self.id = Some(value.to_owned());
+                }
+                "data" => match &mut self.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => {
+                        self.data = Some(value.to_owned());
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Parser};
+    use std::borrow::Cow;
+
+    #[test]
+    fn parser_round_trip() {
+        let events = [
+            Event {
+                id: Some(Cow::from("1")),
+                data: Cow::from("{\"a\":1}"),
+            },
+            Event {
+                id: None,
+                data: Cow::from("multi\nline ż"),
+            },
+        ];
+
+        let payload = ":\r\n".to_owned()
+            + &events
+                .iter()
+                .map(|event| event.to_payload())
+                .collect::<String>();
+        let payload = payload.as_bytes();
+
+        // split at every position, including inside of multi byte character
+        for split in 0..payload.len() {
+            let mut parser = Parser::new();
+            let mut parsed = parser.push(&payload[..split]);
+            parsed.extend(parser.push(&payload[split..]));
+
+            assert_eq!(parsed.len(), events.len());
+            for (parsed, event) in parsed.iter().zip(events.iter()) {
+                assert_eq!(parsed.id, event.id);
+                assert_eq!(parsed.data, event.data);
+            }
+        }
+    }
+}
@@ -16,6 +16,7 @@ use futures::{
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
+    time::Duration,
 };
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -144,6 +145,10 @@ pub struct Responder<'a> {
     topic_paths: HashMap<TopicPath, ResponderTopicPathValue<'a>>,
 }
 impl<'a> Responder<'a> {
+    // sources waking more often than this are coalesced, so clients are not flooded
+    // with events
+    const WAKE_INTERVAL_MIN: Duration = Duration::from_millis(100);
+
     pub fn new(root: &'a Node<'a>) -> Self {
         let mut topic_paths = HashMap::<TopicPath, ResponderTopicPathValue<'a>>::new();
         Self::traverse_node(&mut topic_paths, Vec::new(), root);
@@ -207,7 +212,7 @@ impl<'a> Responder<'a> {
             .topic_paths
             .values()
             .map(|value| {
-                let receiver = value.waker.receiver();
+                let receiver = value.waker.receiver_coalesced(Self::WAKE_INTERVAL_MIN);
                 let sender = &value.sender;
 
                 receiver.for_each(async move |_| sender.wake()).boxed()
@@ -3,6 +3,7 @@ import { DeviceId, deviceEndpointBuild } from "./Device";
 
 export interface DeviceData {
   name: string;
+  label: string | null; // translated for browser locale, if registered
   class: string;
 }
 
@@ -1,6 +1,14 @@
 import { getJson } from "@/lib/Api";
-import { deviceEndpointBuild, DeviceId } from "./Device";
+import { deviceEndpointBuild, DeviceId, endpointBuild } from "./Device";
 
 export async function fetchDeviceSummary<T>(deviceId: DeviceId): Promise<T> {
   return await getJson<T>(deviceEndpointBuild(deviceId, "/gui-summary"));
 }
+
+// devices without gui summary are not included
+export async function fetchDeviceSummaries<T>(classes?: string[]): Promise<Partial<Record<DeviceId, T>>> {
+  const query = new URLSearchParams((classes ?? []).map((class_) => ["class", class_])).toString();
+  return await getJson<Partial<Record<DeviceId, T>>>(
+    endpointBuild(`/devices/gui-summaries${query !== "" ? `?${query}` : ""}`),
+  );
+}
@@ -15,7 +15,7 @@ const DeviceSummaryManagedWrapper: React.FC<{
   return (
     <Wrapper>
       <Details>
-        <DetailsName>{deviceData.name}</DetailsName>
+        <DetailsName>{deviceData.label ?? deviceData.name}</DetailsName>
         <DetailsDetails>
           #{deviceId} {deviceData.class}
         </DetailsDetails>
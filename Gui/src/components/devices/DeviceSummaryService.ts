@@ -2,7 +2,7 @@ import * as SSETopic from "@/lib/SSETopic";
 import assert from "assert-ts";
 import { useEffect, useState } from "react";
 import { DeviceId, endpointBuild } from "./Device";
-import { fetchDeviceSummary } from "./DeviceSummary";
+import { fetchDeviceSummaries, fetchDeviceSummary } from "./DeviceSummary";
 
 const aggregatorEventsClientUrl = endpointBuild("/devices/gui-summary-sse");
 
@@ -66,6 +66,13 @@ export class AggregatorSubscriptionDevice {
 
     this.deviceSummaryReloadPending = false;
   }
+  public deviceSummarySet(deviceSummary: unknown) {
+    assert(!this.deviceSummaryReloadRunning);
+
+    this.deviceSummary = deviceSummary;
+
+    this.deviceSummaryReloadPending = false;
+  }
   public deviceSummarySetPending() {
     this.deviceSummaryReloadPending = true;
   }
@@ -200,11 +207,19 @@ export class Aggregator {
       if (subscriptionDevicesReloadRequired.length <= 0) break;
 
       // first load all to execute callbacks sequentially
-      await Promise.all(
-        subscriptionDevicesReloadRequired.map((subscriptionDeviceReloadRequired) =>
-          subscriptionDeviceReloadRequired.deviceSummaryReload(),
-        ),
-      );
+      // multiple devices are loaded with single request (eg. on page load)
+      if (subscriptionDevicesReloadRequired.length > 1) {
+        const deviceSummaries = await fetchDeviceSummaries();
+        subscriptionDevicesReloadRequired.forEach((subscriptionDevice) => {
+          subscriptionDevice.deviceSummarySet(deviceSummaries[subscriptionDevice.deviceId]);
+        });
+      } else {
+        await Promise.all(
+          subscriptionDevicesReloadRequired.map((subscriptionDeviceReloadRequired) =>
+            subscriptionDeviceReloadRequired.deviceSummaryReload(),
+          ),
+        );
+      }
 
       // execute callbacks sequentially
       subscriptionDevicesReloadRequired.forEach((subscriptionDeviceReloadRequired) => {
@@ -23,6 +23,11 @@ export const Basic: React.FC = () => (
           audio_mutation: false,
           smart_motion_human: true,
           smart_motion_vehicle: false,
+
+          video_motion_regions: [true, false],
+          smart_motion_human_regions: [true, false],
+          smart_motion_vehicle_regions: [false, false],
+          ivs_rules: [{ human: true, vehicle: false, other: false }],
         },
       }}
       snapshotEndpoint={undefined}
@@ -42,6 +42,16 @@ export interface DataEvents {
   audio_mutation: boolean;
   smart_motion_human: boolean;
   smart_motion_vehicle: boolean;
+
+  video_motion_regions: boolean[];
+  smart_motion_human_regions: boolean[];
+  smart_motion_vehicle_regions: boolean[];
+  ivs_rules: DataEventsIvsRule[];
+}
+export interface DataEventsIvsRule {
+  human: boolean;
+  vehicle: boolean;
+  other: boolean;
 }
 
 const Component: React.FC<{
@@ -91,6 +101,32 @@ const Component: React.FC<{
               <Chip type={ChipType.INFO} enabled={data.events.smart_motion_vehicle}>
                 Vehicle Motion
               </Chip>
+              {data.events.video_motion_regions.map((value, index) => (
+                <Chip key={`video_motion_${index}`} type={ChipType.INFO} enabled={value}>
+                  Motion #{index}
+                </Chip>
+              ))}
+              {data.events.smart_motion_human_regions.map((value, index) => (
+                <Chip key={`smart_motion_human_${index}`} type={ChipType.INFO} enabled={value}>
+                  Human #{index}
+                </Chip>
+              ))}
+              {data.events.smart_motion_vehicle_regions.map((value, index) => (
+                <Chip key={`smart_motion_vehicle_${index}`} type={ChipType.INFO} enabled={value}>
+                  Vehicle #{index}
+                </Chip>
+              ))}
+              {data.events.ivs_rules.map((ivsRule, index) => (
+                <Chip
+                  key={`ivs_rule_${index}`}
+                  type={ChipType.WARNING}
+                  enabled={ivsRule.human || ivsRule.vehicle || ivsRule.other}
+                >
+                  Rule #{index}
+                  {ivsRule.human ? " (Human)" : null}
+                  {ivsRule.vehicle ? " (Vehicle)" : null}
+                </Chip>
+              ))}
             </ChipsGroup>
           ) : null}
         </Events>
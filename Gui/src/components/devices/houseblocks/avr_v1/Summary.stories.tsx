@@ -10,7 +10,14 @@ export const Basic: React.FC = () => (
   <>
     <DeviceComponentAvrV1
       data={{
-        hardware_runner: { device_state: "Running" },
+        hardware_runner: {
+          device_state: "Running",
+          transaction_last: "2024-01-01T12:00:00Z",
+          errors_consecutive: 0,
+          retry_delay: null,
+          running_since: "2024-01-01T08:00:00Z",
+          reset_cause: { wdt: false, bod: true, ext_reset: false, pon: false },
+        },
         device: { a: 7, b: "aaa" },
       }}
     />
@@ -3,8 +3,25 @@ import styled from "styled-components";
 
 export type DeviceState = "Error" | "Initializing" | "Running";
 
+export interface Duration {
+  secs: number;
+  nanos: number;
+}
+
+export interface PowerFlags {
+  wdt: boolean;
+  bod: boolean;
+  ext_reset: boolean;
+  pon: boolean;
+}
+
 export interface Data {
   device_state: DeviceState;
+  transaction_last: string | null;
+  errors_consecutive: number;
+  retry_delay: Duration | null;
+  running_since: string | null;
+  reset_cause: PowerFlags | null;
 }
 
 const Component: React.FC<{
@@ -17,6 +34,19 @@ const Component: React.FC<{
       <Chip type={deviceStateToChipState(state?.device_state)} enabled={true}>
         {state !== undefined ? state.device_state : "Unknown"}
       </Chip>
+      {state !== undefined ? (
+        <Diagnostics>
+          {state.running_since !== null ? (
+            <div>Running since: {new Date(state.running_since).toLocaleString()}</div>
+          ) : null}
+          {state.transaction_last !== null ? (
+            <div>Last transaction: {new Date(state.transaction_last).toLocaleString()}</div>
+          ) : null}
+          {state.errors_consecutive > 0 ? <div>Consecutive errors: {state.errors_consecutive}</div> : null}
+          {state.retry_delay !== null ? <div>Retry in: {state.retry_delay.secs}s</div> : null}
+          {state.reset_cause !== null ? <div>Reset cause: {powerFlagsToString(state.reset_cause)}</div> : null}
+        </Diagnostics>
+      ) : null}
     </Wrapper>
   );
 };
@@ -24,7 +54,20 @@ export default Component;
 
 const Wrapper = styled.div`
   display: flex;
+  flex-direction: column;
 `;
+const Diagnostics = styled.div`
+  font-size: x-small;
+`;
+
+function powerFlagsToString(powerFlags: PowerFlags): string {
+  const causes = [];
+  if (powerFlags.wdt) causes.push("watchdog");
+  if (powerFlags.bod) causes.push("brown-out");
+  if (powerFlags.ext_reset) causes.push("external");
+  if (powerFlags.pon) causes.push("power on");
+  return causes.length > 0 ? causes.join(", ") : "none";
+}
 
 function deviceStateToChipState(deviceState: DeviceState | undefined): ChipType {
   switch (deviceState) {
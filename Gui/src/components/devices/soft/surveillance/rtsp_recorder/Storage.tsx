@@ -0,0 +1,113 @@
+import Colors from "@/components/common/Colors";
+import GaugeLinear from "@/components/common/GaugeLinear";
+import Loader from "@/components/common/Loader";
+import { getJson } from "@/lib/Api";
+import { formatSI } from "@/util/Number";
+import { useEffect, useState } from "react";
+import styled from "styled-components";
+
+interface Usage {
+  recordings: number;
+  size_bytes: number;
+  timestamp_oldest: number | null;
+
+  size_bytes_max: number | null;
+  age_max_seconds: number | null;
+
+  bytes_per_day: number | null;
+  retention_days: number | null;
+}
+interface StorageUsage {
+  global: Usage;
+  channels: Record<string, Usage>;
+}
+
+// endpoint of rtsp recorder manager
+const Storage: React.FC<{
+  endpoint: string;
+}> = (props) => {
+  const { endpoint } = props;
+
+  const storageUsage = useStorageUsage(endpoint);
+
+  if (storageUsage === undefined) {
+    return <Loader sizeRem={4} />;
+  }
+
+  return (
+    <Table>
+      <tbody>
+        <StorageRow name="All channels" usage={storageUsage.global} />
+        {Object.entries(storageUsage.channels).map(([channelId, usage]) => (
+          <StorageRow key={channelId} name={`Channel #${channelId}`} usage={usage} />
+        ))}
+      </tbody>
+    </Table>
+  );
+};
+export default Storage;
+
+const StorageRow: React.FC<{
+  name: string;
+  usage: Usage;
+}> = (props) => {
+  const { name, usage } = props;
+
+  return (
+    <Row>
+      <Cell>{name}</Cell>
+      <Cell>
+        {usage.size_bytes_max !== null ? (
+          <GaugeLinear
+            value={usage.size_bytes}
+            valueMin={0}
+            valueMax={usage.size_bytes_max}
+            valueSerializer={(value) => `${formatSI(value, 1, "B")} / ${formatSI(usage.size_bytes_max ?? 0, 1, "B")}`}
+          />
+        ) : (
+          formatSI(usage.size_bytes, 1, "B")
+        )}
+      </Cell>
+      <Cell>{usage.recordings} recordings</Cell>
+      <Cell>{usage.bytes_per_day !== null ? `${formatSI(usage.bytes_per_day, 1, "B")} / day` : "-"}</Cell>
+      <Cell>{usage.retention_days !== null ? `${usage.retention_days.toFixed(1)} days` : "-"}</Cell>
+    </Row>
+  );
+};
+
+function useStorageUsage(endpoint: string): StorageUsage | undefined {
+  const [storageUsage, setStorageUsage] = useState<StorageUsage>();
+
+  useEffect(() => {
+    let active = true;
+
+    const refresh = async () => {
+      const storageUsage = await getJson<StorageUsage>(`${endpoint}/storage`);
+      if (!active) return;
+      setStorageUsage(storageUsage);
+    };
+
+    // usage changes slowly, cleanup runs every few minutes
+    const interval = setInterval(() => {
+      void refresh();
+    }, 60 * 1000);
+    void refresh();
+
+    return () => {
+      active = false;
+      clearInterval(interval);
+    };
+  }, [endpoint]);
+
+  return storageUsage;
+}
+
+const Table = styled.table`
+  width: 100%;
+  border-collapse: collapse;
+`;
+const Row = styled.tr``;
+const Cell = styled.td`
+  padding: 0.25rem 0.5rem;
+  border-bottom: solid 1px ${Colors.GREY_LIGHTEST};
+`;
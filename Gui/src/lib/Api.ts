@@ -21,8 +21,34 @@ export async function getJson<R>(endpoint: string): Promise<R> {
   return json;
 }
 
+// dangerous actuations are answered with 428 and one-time token
+// request is repeated with the token only after user confirms
+interface ConfirmationRequired {
+  confirmation_token: string;
+  description: string;
+}
+async function fetchConfirmed(endpoint: string, init: RequestInit): Promise<Response> {
+  const response = await fetch(urlBuild(endpoint), init);
+  if (response.status !== 428) {
+    return response;
+  }
+
+  const confirmationRequired = (await response.json()) as ConfirmationRequired;
+  if (!window.confirm(`Confirm: ${confirmationRequired.description}`)) {
+    return response;
+  }
+
+  return await fetch(urlBuild(endpoint), {
+    ...init,
+    headers: {
+      ...(init.headers as Record<string, string> | undefined),
+      "X-Confirmation-Token": confirmationRequired.confirmation_token,
+    },
+  });
+}
+
 export async function postEmpty(endpoint: string): Promise<void> {
-  const response = await fetch(urlBuild(endpoint), {
+  const response = await fetchConfirmed(endpoint, {
     method: "POST",
   });
   if (!response.ok) {
@@ -37,7 +63,7 @@ export async function postEmpty(endpoint: string): Promise<void> {
 // eslint-disable-next-line @typescript-eslint/no-unnecessary-type-parameters
 export async function postJsonEmpty<D>(endpoint: string, data: D): Promise<void> {
   const request = JSON.stringify(data);
-  const response = await fetch(urlBuild(endpoint), {
+  const response = await fetchConfirmed(endpoint, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
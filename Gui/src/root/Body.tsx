@@ -4,6 +4,7 @@ import styled from "styled-components";
 import Dashboards from "./Dashboards";
 import DevicesSummary from "./DevicesSummary";
 import Error404 from "./Error404";
+import Events from "./Events";
 
 const Body: React.FC = () => {
   return (
@@ -12,12 +13,14 @@ const Body: React.FC = () => {
         <Menu>
           <MenuItem to="/dashboards">Dashboards</MenuItem>
           <MenuItem to="/devices_summary">Devices</MenuItem>
+          <MenuItem to="/events">Events</MenuItem>
         </Menu>
       </TopBar>
       <Content>
         <Routes>
           <Route path="dashboards/*" element={<Dashboards />} />
           <Route path="devices_summary/*" element={<DevicesSummary />} />
+          <Route path="events" element={<Events />} />
           <Route path="" element={<Navigate to="/dashboards" />} />
           <Route path="*" element={<Error404 />} />
         </Routes>
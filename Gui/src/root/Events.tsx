@@ -0,0 +1,100 @@
+import Colors from "@/components/common/Colors";
+import Loader from "@/components/common/Loader";
+import { getJson, urlBuild } from "@/lib/Api";
+import { useEffect, useState } from "react";
+import styled from "styled-components";
+
+type Severity = "Info" | "Warning" | "Error";
+interface Attachment {
+  content_type: string;
+}
+interface Event {
+  id: number;
+  timestamp: string;
+  severity: Severity;
+  source: string;
+  message: string;
+  attachment: Attachment | null;
+}
+
+const Events: React.FC = () => {
+  const events = useEvents();
+
+  if (events === undefined) {
+    return <Loader sizeRem={4} />;
+  }
+
+  return (
+    <Table>
+      <tbody>
+        {events.map((event) => (
+          <Row key={event.id} $severity={event.severity}>
+            <Cell>{new Date(event.timestamp).toLocaleString()}</Cell>
+            <Cell>{event.severity}</Cell>
+            <Cell>{event.source}</Cell>
+            <Cell>{event.message}</Cell>
+            <Cell>
+              {event.attachment !== null ? (
+                <a href={urlBuild(`/events/events/${event.id}/attachment`)} target="_blank" rel="noreferrer">
+                  {event.attachment.content_type.startsWith("image/") ? (
+                    <AttachmentImage src={urlBuild(`/events/events/${event.id}/attachment`)} />
+                  ) : (
+                    "Attachment"
+                  )}
+                </a>
+              ) : null}
+            </Cell>
+          </Row>
+        ))}
+      </tbody>
+    </Table>
+  );
+};
+export default Events;
+
+function useEvents(): Event[] | undefined {
+  const [events, setEvents] = useState<Event[]>();
+
+  useEffect(() => {
+    let active = true;
+
+    const refresh = async () => {
+      const events = await getJson<Event[]>("/events/events");
+      if (!active) return;
+      setEvents(events.reverse());
+    };
+
+    // sse only notifies about new events, list is fetched separately
+    const eventSource = new EventSource(urlBuild("/events/sse"));
+    eventSource.onmessage = () => {
+      void refresh();
+    };
+    void refresh();
+
+    return () => {
+      active = false;
+      eventSource.close();
+    };
+  }, []);
+
+  return events;
+}
+
+const Table = styled.table`
+  width: 100%;
+  border-collapse: collapse;
+`;
+const Row = styled.tr<{
+  $severity: Severity;
+}>`
+  background-color: ${(props) =>
+    props.$severity === "Error" ? Colors.RED : props.$severity === "Warning" ? Colors.YELLOW : "unset"};
+`;
+const Cell = styled.td`
+  padding: 0.25rem 0.5rem;
+  border-bottom: solid 1px ${Colors.GREY_LIGHTEST};
+`;
+const AttachmentImage = styled.img`
+  display: block;
+  max-height: 4rem;
+`;